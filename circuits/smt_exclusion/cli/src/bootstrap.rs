@@ -0,0 +1,98 @@
+//! A throwaway environment for trying the example, in one command
+//!
+//! `smt-cli bootstrap` does what a newcomer would otherwise do by hand: make
+//! an admin keypair, fund it from the cluster's faucet, create its state
+//! account, blacklist a sample list and push its root, then prove and send a
+//! transfer against it. Everything it writes goes in one directory, and a
+//! rerun picks up from what is already there, so a faucet that rate-limits
+//! the airdrop only costs a retry.
+
+use std::{path::PathBuf, str::FromStr};
+
+use anyhow::bail;
+use solana_keypair::Keypair;
+use solana_pubkey::Pubkey;
+use solana_signer::Signer;
+
+/// A cluster with a faucet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cluster {
+    Devnet,
+    /// `solana-test-validator`, with the programs deployed to it
+    Localnet,
+}
+
+impl Cluster {
+    pub fn rpc_url(self) -> &'static str {
+        match self {
+            Self::Devnet => "https://api.devnet.solana.com",
+            Self::Localnet => "http://127.0.0.1:8899",
+        }
+    }
+}
+
+impl FromStr for Cluster {
+    type Err = anyhow::Error;
+
+    fn from_str(cluster: &str) -> anyhow::Result<Self> {
+        Ok(match cluster {
+            "devnet" => Self::Devnet,
+            "localnet" | "localhost" => Self::Localnet,
+            "mainnet" | "mainnet-beta" | "testnet" => {
+                bail!("{cluster} has no faucet for a throwaway keypair (devnet or localnet)")
+            }
+            cluster => bail!("unknown cluster {cluster} (devnet or localnet)"),
+        })
+    }
+}
+
+/// The files a bootstrap writes, all in `dir`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Workspace {
+    pub dir: PathBuf,
+}
+
+impl Workspace {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The throwaway admin, who is also the demo transfer's sender
+    pub fn keypair(&self) -> PathBuf {
+        self.dir.join("admin.json")
+    }
+
+    pub fn journal(&self) -> PathBuf {
+        self.dir.join("blacklist.journal")
+    }
+
+    /// The sample blacklist, as `smt-cli import` reads it
+    pub fn sample(&self) -> PathBuf {
+        self.dir.join("sample.csv")
+    }
+
+    /// The `smt-cli` flags that carry on from the workspace
+    pub fn flags(&self, rpc_url: &str, program_id: &Pubkey) -> String {
+        format!(
+            "--rpc-url {rpc_url} --program-id {program_id} --keypair {} --journal {}",
+            self.keypair().display(),
+            self.journal().display()
+        )
+    }
+}
+
+/// `count` pubkeys no one holds the keys to, standing in for sanctioned
+/// wallets
+pub fn sample_blacklist(count: usize) -> Vec<Pubkey> {
+    (0..count).map(|_| Keypair::new().pubkey()).collect()
+}
+
+/// `pubkeys` as a CSV list with a header, which `import::parse` skips
+pub fn sample_csv(pubkeys: &[Pubkey]) -> String {
+    let mut csv = String::from("pubkey\n");
+    for pubkey in pubkeys {
+        csv.push_str(&pubkey.to_string());
+        csv.push('\n');
+    }
+    csv
+}
@@ -0,0 +1,180 @@
+//! Blacklists read from CSV or JSON files, as `smt-cli import` loads them
+//!
+//! A CSV list has a pubkey in the first column of each line; blank lines and
+//! lines starting with `#` are skipped, and a first line whose first cell is
+//! too short to be a pubkey is taken for a header. A JSON list is an array of
+//! base58 strings, as `recover.ts` writes. Every entry is checked before the
+//! tree changes, so a list with a bad entry isn't half-imported.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{bail, Context};
+use smt::Op;
+use solana_pubkey::Pubkey;
+
+use crate::tree::LocalTree;
+
+/// Shortest base58 encoding of a 32-byte pubkey
+const MIN_PUBKEY_LEN: usize = 32;
+
+/// Invalid entries listed in an error before the rest are counted
+const MAX_LISTED: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    /// The format `path`'s extension names; CSV unless it's `.json`
+    pub fn of(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => Self::Json,
+            _ => Self::Csv,
+        }
+    }
+}
+
+/// A list's distinct pubkeys, in the order they first appear
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct List {
+    pub pubkeys: Vec<Pubkey>,
+    /// Entries naming a pubkey an earlier entry did
+    pub duplicates: usize,
+}
+
+impl List {
+    fn push(&mut self, seen: &mut BTreeSet<Pubkey>, pubkey: Pubkey) {
+        if seen.insert(pubkey) {
+            self.pubkeys.push(pubkey);
+        } else {
+            self.duplicates += 1;
+        }
+    }
+}
+
+impl FromIterator<Pubkey> for List {
+    fn from_iter<I: IntoIterator<Item = Pubkey>>(pubkeys: I) -> Self {
+        let mut list = Self::default();
+        let mut seen = BTreeSet::new();
+        for pubkey in pubkeys {
+            list.push(&mut seen, pubkey);
+        }
+        list
+    }
+}
+
+/// Parse `text` as a `format` list, failing with the entries that aren't
+/// pubkeys
+pub fn parse(text: &str, format: Format) -> anyhow::Result<List> {
+    // (where, entry) for each entry that isn't a pubkey
+    let mut invalid = Vec::new();
+    let mut list = List::default();
+    let mut seen = BTreeSet::new();
+    match format {
+        Format::Csv => {
+            let lines = text
+                .lines()
+                .enumerate()
+                .map(|(i, line)| (i, line.trim()))
+                .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+            for (n, (i, line)) in lines.enumerate() {
+                let cell = line.split(',').next().unwrap_or_default().trim();
+                let cell = cell.trim_matches('"').trim();
+                match Pubkey::from_str(cell) {
+                    Ok(pubkey) => list.push(&mut seen, pubkey),
+                    Err(_) if n == 0 && cell.len() < MIN_PUBKEY_LEN => {}
+                    Err(_) => invalid.push((format!("line {}", i + 1), cell.to_string())),
+                }
+            }
+        }
+        Format::Json => {
+            let entries: Vec<serde_json::Value> =
+                serde_json::from_str(text).context("expected a JSON array of pubkeys")?;
+            for (i, entry) in entries.iter().enumerate() {
+                match entry.as_str().map(Pubkey::from_str) {
+                    Some(Ok(pubkey)) => list.push(&mut seen, pubkey),
+                    _ => invalid.push((format!("entry {i}"), entry.to_string())),
+                }
+            }
+        }
+    }
+    if !invalid.is_empty() {
+        let mut message = format!("{} entries aren't pubkeys:", invalid.len());
+        for (at, entry) in invalid.iter().take(MAX_LISTED) {
+            message.push_str(&format!("\n  {at}: {entry:?}"));
+        }
+        if invalid.len() > MAX_LISTED {
+            message.push_str(&format!("\n  and {} more", invalid.len() - MAX_LISTED));
+        }
+        bail!(message);
+    }
+    Ok(list)
+}
+
+/// The changes importing a list makes to the tree
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diff {
+    /// Inserts, then (when replacing) deletes
+    pub ops: Vec<Op>,
+    pub added: usize,
+    /// Listed pubkeys the tree already had
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+/// What importing `list` changes in `tree`; with `replace`, pubkeys the list
+/// doesn't name come off the blacklist
+pub fn diff(tree: &LocalTree, list: &List, replace: bool) -> Diff {
+    let members = tree.members();
+    let mut diff = Diff::default();
+    for pubkey in &list.pubkeys {
+        if members.contains(&pubkey.to_bytes()) {
+            diff.unchanged += 1;
+        } else {
+            diff.ops.push(Op::Insert(pubkey.to_bytes()));
+            diff.added += 1;
+        }
+    }
+    if replace {
+        let listed: BTreeSet<[u8; 32]> = list
+            .pubkeys
+            .iter()
+            .map(|pubkey| pubkey.to_bytes())
+            .collect();
+        for pubkey in members.difference(&listed) {
+            diff.ops.push(Op::Delete(*pubkey));
+            diff.removed += 1;
+        }
+    }
+    diff
+}
+
+/// Apply `diff` to `tree` in journal records of up to `batch_size` ops,
+/// returning the record that added or removed each pubkey
+pub fn apply(
+    tree: &mut LocalTree,
+    diff: &Diff,
+    batch_size: usize,
+) -> anyhow::Result<BTreeMap<Pubkey, u64>> {
+    anyhow::ensure!(batch_size > 0, "the batch size must be at least 1");
+    let mut changed = BTreeMap::new();
+    for batch in diff.ops.chunks(batch_size) {
+        tree.apply(batch)?;
+        let sequence = tree
+            .entries()
+            .last()
+            .expect("a record was appended")
+            .sequence;
+        for op in batch {
+            let (Op::Insert(pubkey) | Op::Delete(pubkey)) = op;
+            changed.insert(Pubkey::new_from_array(*pubkey), sequence);
+        }
+    }
+    Ok(changed)
+}
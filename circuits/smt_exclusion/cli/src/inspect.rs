@@ -0,0 +1,245 @@
+//! The program's accounts, decoded for `smt-cli inspect`
+//!
+//! Every account the program owns starts with an 8-byte discriminator, so
+//! the kind is read from the data alone and the fields come from the
+//! `exclusion-client` decoders. The program has no nullifier accounts: a
+//! proof is bound to its sender's pubkey and a recent slot instead.
+
+use anyhow::bail;
+use exclusion_client::accounts::{
+    decode_attestation, decode_config, decode_escrow_account, decode_exclusion_queue,
+    decode_proof_buffer, decode_rate_limit_account, decode_relay_account, decode_state,
+    decode_verifier_registry,
+};
+use exclusion_common::state::{
+    hash_scheme, state_version, tree_kind, ATTESTATION_DISCRIMINATOR, BOND_ACCOUNT_DISCRIMINATOR,
+    CONFIG_DISCRIMINATOR, ESCROW_ACCOUNT_DISCRIMINATOR, EXCLUSION_QUEUE_DISCRIMINATOR,
+    PROOF_BUFFER_DISCRIMINATOR, RATE_LIMIT_ACCOUNT_DISCRIMINATOR, RELAY_ACCOUNT_DISCRIMINATOR,
+    STATE_DISCRIMINATOR, STATE_VERSION, TREASURY_ACCOUNT_DISCRIMINATOR,
+    VERIFIER_REGISTRY_DISCRIMINATOR,
+};
+use serde_json::{json, Value};
+use smt::inputs::field_hex;
+use solana_pubkey::Pubkey;
+
+/// An account's kind and fields, in layout order
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Inspection {
+    pub kind: &'static str,
+    pub fields: Vec<(&'static str, String)>,
+}
+
+impl std::fmt::Display for Inspection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.kind)?;
+        let width = self.fields.iter().map(|(name, _)| name.len()).max();
+        for (name, value) in &self.fields {
+            writeln!(f, "  {name:width$}  {value}", width = width.unwrap_or(0))?;
+        }
+        Ok(())
+    }
+}
+
+impl Inspection {
+    /// `kind`, and `fields` as `{name, value}` objects in layout order, since
+    /// some names repeat
+    pub fn to_json(&self) -> Value {
+        json!({
+            "kind": self.kind,
+            "fields": self
+                .fields
+                .iter()
+                .map(|(name, value)| json!({ "name": name, "value": value }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+fn expiry(unix_time: i64) -> String {
+    match unix_time {
+        0 => "never".to_string(),
+        time => format!("unix time {time}"),
+    }
+}
+
+/// Decode an account the program owns, by its discriminator
+pub fn inspect(data: &[u8]) -> anyhow::Result<Inspection> {
+    let Some(discriminator) = data
+        .get(..8)
+        .and_then(|bytes| <[u8; 8]>::try_from(bytes).ok())
+    else {
+        bail!(
+            "{} bytes: too short for one of the program's accounts",
+            data.len()
+        );
+    };
+    let mut fields = vec![(
+        "discriminator",
+        format!(
+            "{} ({:?})",
+            discriminator
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>(),
+            String::from_utf8_lossy(&discriminator)
+        ),
+    )];
+    let kind = match discriminator {
+        STATE_DISCRIMINATOR if state_version(data) < STATE_VERSION => {
+            fields.push((
+                "layout",
+                format!("v{}; run MIGRATE_STATE to decode it", state_version(data)),
+            ));
+            "State account"
+        }
+        STATE_DISCRIMINATOR => {
+            let state = decode_state(data)?;
+            let scheme = match state.hash_scheme {
+                hash_scheme::POSEIDON => "Poseidon".to_string(),
+                hash_scheme::POSEIDON2 => "Poseidon2".to_string(),
+                other => format!("unknown ({other})"),
+            };
+            let tree = match state.tree_kind {
+                tree_kind::SPARSE => format!("sparse, depth {}", state.tree_depth),
+                tree_kind::INDEXED => "indexed".to_string(),
+                other => format!("unknown ({other})"),
+            };
+            fields.extend([
+                ("layout", format!("v{STATE_VERSION}")),
+                ("admin", Pubkey::new_from_array(state.admin).to_string()),
+                ("smt_root", field_hex(&state.smt_root)),
+                ("root_seq", state.root_seq.to_string()),
+                ("root_valid_until", expiry(state.root_valid_until)),
+                ("hash_scheme", scheme),
+                ("tree", tree),
+                ("challenged", state.challenged.to_string()),
+                ("transfer_flags", format!("{:#04x}", state.transfer_flags)),
+                ("verifier_flags", format!("{:#04x}", state.verifier_flags)),
+                ("daily_limit", state.daily_limit.to_string()),
+                ("fee_bps", state.fee_bps.to_string()),
+                ("bond_amount", state.bond_amount.to_string()),
+                ("config", Pubkey::new_from_array(state.config).to_string()),
+                (
+                    "approved_callers",
+                    state.approved_callers[..usize::from(state.approved_caller_count)]
+                        .iter()
+                        .map(|caller| Pubkey::new_from_array(*caller).to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ),
+                (
+                    "metadata",
+                    state
+                        .metadata
+                        .iter()
+                        .map(|byte| format!("{byte:02x}"))
+                        .collect(),
+                ),
+            ]);
+            "State account"
+        }
+        ATTESTATION_DISCRIMINATOR => {
+            let attestation = decode_attestation(data)?;
+            fields.extend([
+                ("subject", attestation.subject.to_string()),
+                ("state", attestation.state.to_string()),
+                ("smt_root", field_hex(&attestation.smt_root)),
+                ("slot", attestation.slot.to_string()),
+            ]);
+            "Attestation"
+        }
+        PROOF_BUFFER_DISCRIMINATOR => {
+            let buffer = decode_proof_buffer(data)?;
+            fields.extend([
+                ("owner", buffer.owner.to_string()),
+                ("smt_root", field_hex(&buffer.witness.smt_root())),
+                ("pubkey_hash", field_hex(&buffer.witness.pubkey_hash())),
+                (
+                    "recent_slot",
+                    buffer
+                        .witness
+                        .recent_slot()
+                        .map_or("not a u64".to_string(), |slot| slot.to_string()),
+                ),
+            ]);
+            "Proof buffer"
+        }
+        RELAY_ACCOUNT_DISCRIMINATOR => {
+            let relay = decode_relay_account(data)?;
+            fields.extend([
+                ("owner", relay.owner.to_string()),
+                ("nonce", relay.nonce.to_string()),
+            ]);
+            "Relay account"
+        }
+        ESCROW_ACCOUNT_DISCRIMINATOR => {
+            let escrow = decode_escrow_account(data)?;
+            fields.extend([
+                ("sender", escrow.sender.to_string()),
+                ("recipient", escrow.recipient.to_string()),
+                ("state", escrow.state.to_string()),
+                ("escrow_id", escrow.escrow_id.to_string()),
+                ("amount", escrow.amount.to_string()),
+                ("release_at", format!("unix time {}", escrow.release_at)),
+                ("escrow_flags", format!("{:#04x}", escrow.escrow_flags)),
+            ]);
+            "Escrow"
+        }
+        CONFIG_DISCRIMINATOR => {
+            let config = decode_config(data)?;
+            fields.extend([
+                ("paused", config.paused.to_string()),
+                ("zk_verifier", config.zk_verifier.to_string()),
+                (
+                    "transition_verifier",
+                    config.transition_verifier.to_string(),
+                ),
+                (
+                    "max_proof_age_slots",
+                    config.max_proof_age_slots.to_string(),
+                ),
+                ("max_fee_bps", config.max_fee_bps.to_string()),
+            ]);
+            "Program config"
+        }
+        VERIFIER_REGISTRY_DISCRIMINATOR => {
+            let registry = decode_verifier_registry(data)?;
+            fields.push(("authority", registry.authority.to_string()));
+            fields.extend(registry.verifiers.iter().map(|verifier| {
+                let tree = match verifier.tree_kind {
+                    tree_kind::SPARSE => "sparse",
+                    tree_kind::INDEXED => "indexed",
+                    _ => "unknown",
+                };
+                (
+                    "verifier",
+                    format!(
+                        "{} (circuit {}, {tree} depth {})",
+                        verifier.program_id,
+                        field_hex(&verifier.circuit_hash),
+                        verifier.tree_depth
+                    ),
+                )
+            }));
+            "Verifier registry"
+        }
+        RATE_LIMIT_ACCOUNT_DISCRIMINATOR => {
+            let limit = decode_rate_limit_account(data)?;
+            fields.extend([
+                ("last_hour", limit.last_hour.to_string()),
+                ("sent_24h", limit.hourly.iter().sum::<u64>().to_string()),
+            ]);
+            "Rate limit account"
+        }
+        EXCLUSION_QUEUE_DISCRIMINATOR => {
+            let queue = decode_exclusion_queue(data)?;
+            fields.push(("queued", queue.len().to_string()));
+            fields.extend(queue.iter().map(|pubkey| ("pubkey", pubkey.to_string())));
+            "Exclusion queue"
+        }
+        TREASURY_ACCOUNT_DISCRIMINATOR => "Treasury (fees are its lamports)",
+        BOND_ACCOUNT_DISCRIMINATOR => "Bond (the bond is its lamports)",
+        _ => bail!("not one of the program's accounts: unknown discriminator"),
+    };
+    Ok(Inspection { kind, fields })
+}
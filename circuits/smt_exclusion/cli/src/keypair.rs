@@ -0,0 +1,56 @@
+//! Keypair files, as the Solana CLI writes them, and Ledgers, as it names them
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use exclusion_client::ledger::{DerivationPath, LedgerSigner};
+use solana_keypair::{read_keypair_file, write_keypair_file, Keypair};
+use solana_signer::Signer;
+
+/// `path` with a leading `~/` taken from `HOME`
+pub fn expand_home(path: &str) -> anyhow::Result<PathBuf> {
+    Ok(match path.strip_prefix("~/") {
+        Some(rest) => PathBuf::from(std::env::var("HOME").context("HOME is not set")?).join(rest),
+        None => PathBuf::from(path),
+    })
+}
+
+/// Read the keypair at `path`, which may start with `~/`
+pub fn read_keypair(path: &str) -> anyhow::Result<Keypair> {
+    let path = expand_home(path)?;
+    read_keypair_file(&path).map_err(|e| anyhow::anyhow!("reading {}: {e}", path.display()))
+}
+
+/// The key `usb://ledger?key=ACCOUNT/CHANGE` names, as the Solana CLI takes
+/// it; `None` for anything else
+pub fn ledger_path(location: &str) -> anyhow::Result<Option<DerivationPath>> {
+    let Some(rest) = location.strip_prefix("usb://ledger") else {
+        return Ok(None);
+    };
+    // A device's base58 ID may follow, naming one of several; the first found
+    // is used
+    let query = rest.split_once('?').map_or("", |(_, query)| query);
+    let key = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("key="))
+        .unwrap_or("");
+    key.parse()
+        .map(Some)
+        .with_context(|| format!("parsing {location}"))
+}
+
+/// The signer `location` names: `usb://ledger[?key=ACCOUNT/CHANGE]` for a
+/// Ledger, or a keypair file
+pub fn read_signer(location: &str) -> anyhow::Result<Box<dyn Signer + Send + Sync>> {
+    Ok(match ledger_path(location)? {
+        Some(path) => Box::new(LedgerSigner::usb(path)?),
+        None => Box::new(read_keypair(location)?),
+    })
+}
+
+/// Write `keypair` to `path` as the Solana CLI would
+pub fn write_keypair(keypair: &Keypair, path: &Path) -> anyhow::Result<()> {
+    write_keypair_file(keypair, path)
+        .map(drop)
+        .map_err(|e| anyhow::anyhow!("writing {}: {e}", path.display()))
+}
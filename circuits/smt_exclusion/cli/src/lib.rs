@@ -0,0 +1,28 @@
+//! `smt-cli`: the exclusion example from the command line
+//!
+//! The admin keeps the blacklist as a journal on disk (`tree`), adds and
+//! removes pubkeys or pulls them from lists (`import`, `ofac`, `source`,
+//! `merge`) while logging where each came from (`provenance`), pushes its root to
+//! their state account with `SET_SMT_ROOT`, and checks the account's root
+//! against the journal (`sync`) as it changes (`watch`). Anyone not on the
+//! list proves their exclusion against that root and sends gated transfers,
+//! through `exclusion-client`, and can check a proof offline (`verify`).
+//! `inspect` decodes any of the program's accounts, and `bootstrap` sets up a
+//! throwaway devnet environment to try all this in. `profile` tracks the
+//! compute units each instruction consumes. Every command prints text or,
+//! for scripts, JSON (`output`).
+
+pub mod bootstrap;
+pub mod import;
+pub mod inspect;
+pub mod keypair;
+pub mod merge;
+pub mod ofac;
+pub mod output;
+pub mod profile;
+pub mod provenance;
+pub mod source;
+pub mod sync;
+pub mod tree;
+pub mod verify;
+pub mod watch;
@@ -0,0 +1,1597 @@
+//! Manage a blacklist, push its root, and prove exclusion and transfer against it
+//!
+//! Run with: cargo run -- --help
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use anyhow::{bail, ensure, Context};
+use clap::{Parser, Subcommand};
+use exclusion_client::{
+    compute_budget::{
+        build_nonced_transaction, build_sized_transaction, size_message, size_nonced_message,
+        ComputeBudgetOptions,
+    },
+    groth16::VerifyingKey,
+    instructions::{
+        create_proof_buffer_ix, derive_state_pda, initialize_ix, set_smt_root_ix,
+        transfer_from_buffer_ix, transfer_sol_ix, write_proof_chunk_ix, TransferAccounts,
+    },
+    nonce::{create_nonce_account, fetch_nonce_account},
+    offline::OfflineTransaction,
+    prover::{generate_exclusion_proof, GeneratedProof, Prover},
+    pubsub::{websocket_url, AccountSubscription},
+    send::{send_and_confirm, send_checked, SendOptions},
+    squads::{derive_vault_pda, propose, transaction_index, vault_message, SQUADS_PROGRAM_ID},
+    state::{get_state_account, ExclusionState},
+};
+use exclusion_common::{instruction::METADATA_SIZE, state::hash_scheme};
+use exclusion_program_example::ZK_VERIFIER_PROGRAM_ID;
+use serde_json::json;
+use smt::{inputs::field_hex, Op, TREE_DEPTH};
+use smt_cli::{
+    bootstrap::{sample_blacklist, sample_csv, Cluster, Workspace},
+    import::{self, Diff, Format, List},
+    inspect::inspect,
+    keypair::{read_keypair, read_signer, write_keypair},
+    merge, ofac,
+    output::{Output, OutputFormat},
+    profile::{self, Profile},
+    provenance,
+    source::{self, ListFormat},
+    sync::{self, Drift},
+    tree::{unix_time, LocalTree},
+    verify,
+    watch::root_change,
+};
+use solana_commitment_config::CommitmentConfig;
+use solana_instruction::Instruction;
+use solana_keypair::Keypair;
+use solana_message::VersionedMessage;
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_signer::Signer;
+
+/// Pubkeys per journal record when importing
+const IMPORT_BATCH_SIZE: usize = 10_000;
+
+/// Wait before resubscribing after the websocket drops
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// What `bootstrap` asks the faucet for, when the admin has less than
+/// `BOOTSTRAP_MIN_BALANCE`
+const BOOTSTRAP_AIRDROP: u64 = 1_000_000_000;
+const BOOTSTRAP_MIN_BALANCE: u64 = 100_000_000;
+
+/// The demo transfer: enough for the fresh recipient to be rent exempt
+const DEMO_TRANSFER: u64 = 10_000_000;
+
+/// Pubkeys in the tree `profile` proves against
+const PROFILE_TREE_SIZE: usize = 16;
+
+#[derive(Parser)]
+#[command(name = "smt-cli", about)]
+struct Args {
+    /// The blacklist's journal
+    #[arg(long, env = "SMT_JOURNAL", default_value = "blacklist.journal")]
+    journal: PathBuf,
+    #[arg(long, env = "RPC_URL", default_value = "https://api.devnet.solana.com")]
+    rpc_url: String,
+    /// Signer: the admin for `init` and `root --push`, the sender for `prove`
+    /// and `transfer`; a keypair file, or a Ledger as usb://ledger?key=0/0
+    #[arg(long, default_value = "~/.config/solana/id.json")]
+    keypair: String,
+    /// Exclusion program
+    #[arg(long, env = "EXCLUSION_PROGRAM_ID")]
+    program_id: Option<Pubkey>,
+    /// ZK verifier program
+    #[arg(long, env = "ZK_VERIFIER_PROGRAM_ID", default_value_t = ZK_VERIFIER_PROGRAM_ID)]
+    verifier_id: Pubkey,
+    /// State account to prove against; the keypair's own by default
+    #[arg(long)]
+    state: Option<Pubkey>,
+    /// Circuit directory, with its compiled circuit and keys in target/
+    #[arg(long, default_value = "..")]
+    circuit_dir: PathBuf,
+    /// text, or json: one object per command, for scripts
+    #[arg(long, global = true, default_value = "text")]
+    output: OutputFormat,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start an empty blacklist
+    Init {
+        #[arg(long, default_value_t = TREE_DEPTH)]
+        depth: usize,
+        /// Also create the keypair's state account (`INITIALIZE`)
+        #[arg(long)]
+        on_chain: bool,
+    },
+    /// Blacklist pubkeys
+    Add {
+        #[arg(required = true)]
+        pubkeys: Vec<Pubkey>,
+    },
+    /// Take pubkeys off the blacklist
+    Remove {
+        #[arg(required = true)]
+        pubkeys: Vec<Pubkey>,
+    },
+    /// Blacklist every pubkey in a CSV (first column) or JSON (array) file
+    Import {
+        file: PathBuf,
+        /// Take pubkeys the file doesn't list off the blacklist
+        #[arg(long)]
+        replace: bool,
+        /// Pubkeys per journal record
+        #[arg(long, default_value_t = IMPORT_BATCH_SIZE)]
+        batch_size: usize,
+    },
+    /// Blacklist the Solana addresses on OFAC's SDN list (sdn.xml or
+    /// sdn.csv), logging the entry each came from
+    ImportSdn { file: PathBuf },
+    /// Blacklist the pubkeys a list names, from a file or an http(s) URL,
+    /// logging the source of each
+    Pull {
+        location: String,
+        /// The source's name in the provenance log; its location by default
+        #[arg(long)]
+        name: Option<String>,
+        /// csv, json, sdn-xml or sdn-csv; by the location's extension by
+        /// default (.json, .xml for OFAC's sdn.xml, CSV otherwise)
+        #[arg(long)]
+        format: Option<ListFormat>,
+    },
+    /// Blacklist every pubkey any of several sources names, logging which
+    /// sources flagged each
+    Merge {
+        /// NAME=LOCATION or NAME:FORMAT=LOCATION, a file or an http(s) URL
+        #[arg(long = "source", required = true)]
+        sources: Vec<String>,
+        /// Take pubkeys no source names off the blacklist
+        #[arg(long)]
+        replace: bool,
+    },
+    /// Say which sources flagged a pubkey, and when it was added or removed
+    Why { pubkey: Pubkey },
+    /// Print the blacklist's root, and the state account's
+    #[command(visible_alias = "set-root")]
+    Root {
+        /// Set the keypair's state account to the blacklist's root (`SET_SMT_ROOT`)
+        #[arg(long)]
+        push: bool,
+        /// Propose the `SET_SMT_ROOT` to this Squads multisig, whose vault is
+        /// the admin, as the keypair (a member) instead
+        #[arg(long, value_name = "MULTISIG", conflicts_with = "push")]
+        via_squads: Option<Pubkey>,
+        /// The multisig's vault that is the admin
+        #[arg(long, default_value_t = 0, requires = "via_squads")]
+        vault_index: u8,
+        /// Squads v4 program
+        #[arg(long, env = "SQUADS_PROGRAM_ID", default_value_t = SQUADS_PROGRAM_ID)]
+        squads_program_id: Pubkey,
+        /// Unix time the new root expires; never by default
+        #[arg(long, default_value_t = 0)]
+        valid_until: i64,
+    },
+    /// Compare the blacklist with the state account's root
+    Sync {
+        /// When the chain is behind, append a record undoing the changes it
+        /// doesn't have
+        #[arg(long)]
+        rewind: bool,
+    },
+    /// Stream the state account's root changes over the RPC node's websocket
+    Watch {
+        /// Websocket URL; derived from --rpc-url by default
+        #[arg(long, env = "WS_URL")]
+        ws_url: Option<String>,
+        /// Compare each new root with the blacklist, as `sync` does
+        #[arg(long)]
+        resync: bool,
+        /// POST each change to this URL as JSON
+        #[arg(long)]
+        webhook: Option<String>,
+    },
+    /// Prove a pubkey, the keypair's by default, is not blacklisted
+    Prove {
+        pubkey: Option<Pubkey>,
+        /// Directory to write proof.bin and witness.bin to
+        #[arg(long, default_value = ".")]
+        out: PathBuf,
+    },
+    /// Decode one of the program's accounts: a state account, attestation,
+    /// proof buffer, escrow, config and so on
+    Inspect { address: Pubkey },
+    /// Check a proof offline, and which root and pubkey it is for
+    VerifyProof {
+        #[arg(long, default_value = "proof.bin")]
+        proof: PathBuf,
+        #[arg(long, default_value = "witness.bin")]
+        witness: PathBuf,
+        /// Verifying key; the circuit directory's target/smt_exclusion.vk by
+        /// default
+        #[arg(long)]
+        vk: Option<PathBuf>,
+        /// Check the proof is for this pubkey
+        #[arg(long)]
+        pubkey: Option<Pubkey>,
+    },
+    /// Prove the keypair is not blacklisted and send a gated transfer
+    Transfer {
+        recipient: Pubkey,
+        lamports: u64,
+        /// Write the transaction here unsigned, for `sign` on another
+        /// machine, instead of sending it
+        #[arg(long, value_name = "FILE")]
+        unsigned_out: Option<PathBuf>,
+        /// The sender, when the keypair isn't on this machine
+        #[arg(long, requires = "unsigned_out")]
+        sender: Option<Pubkey>,
+        /// Carry this nonce account's blockhash (see `create-nonce`), so the
+        /// transaction doesn't expire before it's signed and sent
+        #[arg(long, value_name = "ADDRESS")]
+        nonce: Option<Pubkey>,
+    },
+    /// Create a durable nonce account, for `transfer --nonce`
+    CreateNonce {
+        /// The key that advances the nonce, and signs every transaction
+        /// using it; the keypair's by default
+        #[arg(long)]
+        authority: Option<Pubkey>,
+    },
+    /// Show what a transaction `transfer --unsigned-out` wrote does, and sign
+    /// it with the keypair; needs no network
+    Sign {
+        file: PathBuf,
+        /// Sign without asking
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Send a transaction `sign` signed
+    Submit { file: PathBuf },
+    /// Measure each instruction's compute units against a local validator,
+    /// and compare them with a baseline
+    Profile {
+        /// Counts to compare with; written by --update
+        #[arg(long, default_value = "cu-baseline.json")]
+        baseline: PathBuf,
+        /// Make these counts the new baseline
+        #[arg(long)]
+        update: bool,
+        /// Percent more units than the baseline that counts as a regression
+        #[arg(long, default_value_t = 2.0)]
+        tolerance: f64,
+        /// Skip the instructions that need a proof, which runs nargo and
+        /// sunspot
+        #[arg(long)]
+        no_proof: bool,
+    },
+    /// Set up a throwaway admin, state account and sample blacklist, and send
+    /// a transfer against them; rerun to carry on after a failure
+    Bootstrap {
+        /// devnet or localnet; sets the RPC URL
+        #[arg(long, default_value = "devnet")]
+        cluster: Cluster,
+        /// Directory for the keypair, journal and sample list
+        #[arg(long, default_value = "bootstrap")]
+        dir: PathBuf,
+        /// Pubkeys in the sample blacklist
+        #[arg(long, default_value_t = 100)]
+        sample: usize,
+    },
+}
+
+/// What commands that go on chain share
+struct Chain {
+    rpc: RpcClient,
+    program_id: Pubkey,
+    signer: Box<dyn Signer + Send + Sync>,
+}
+
+impl Args {
+    fn rpc(&self) -> RpcClient {
+        RpcClient::new_with_commitment(self.rpc_url.clone(), CommitmentConfig::confirmed())
+    }
+
+    fn program_id(&self) -> anyhow::Result<Pubkey> {
+        self.program_id
+            .context("pass --program-id or set EXCLUSION_PROGRAM_ID")
+    }
+
+    fn chain(&self) -> anyhow::Result<Chain> {
+        Ok(Chain {
+            rpc: self.rpc(),
+            program_id: self.program_id()?,
+            signer: read_signer(&self.keypair)?,
+        })
+    }
+
+    /// `--state`, or the keypair's state account
+    fn state_address(&self) -> anyhow::Result<Pubkey> {
+        match self.state {
+            Some(state) => Ok(state),
+            None => {
+                Ok(derive_state_pda(&self.program_id()?, &read_signer(&self.keypair)?.pubkey()).0)
+            }
+        }
+    }
+}
+
+impl Chain {
+    /// The state account `state` names, or the signer's
+    async fn state(&self, state: Option<Pubkey>) -> anyhow::Result<ExclusionState> {
+        let address =
+            state.unwrap_or_else(|| derive_state_pda(&self.program_id, &self.signer.pubkey()).0);
+        get_state_account(&self.rpc, &self.program_id, &address).await
+    }
+
+    async fn send(&self, out: &mut Output, instructions: &[Instruction]) -> anyhow::Result<()> {
+        let signature = send_and_confirm(
+            &self.rpc,
+            &self.program_id,
+            || async {
+                let sized = build_sized_transaction(
+                    &self.rpc,
+                    &self.signer,
+                    instructions,
+                    &[],
+                    &ComputeBudgetOptions::default(),
+                )
+                .await?;
+                Ok(sized.transaction)
+            },
+            &SendOptions::default(),
+        )
+        .await?;
+        out.push("signatures", "Signature", signature.to_string());
+        Ok(())
+    }
+}
+
+/// Prove `pubkey`'s exclusion from `tree`, once its root is `state`'s
+async fn prove(
+    args: &Args,
+    rpc: &RpcClient,
+    state: &ExclusionState,
+    tree: &LocalTree,
+    pubkey: &Pubkey,
+) -> anyhow::Result<GeneratedProof> {
+    ensure!(
+        state.state.hash_scheme == hash_scheme::POSEIDON,
+        "{} uses a hash scheme this tree doesn't",
+        state.address
+    );
+    ensure!(
+        state.smt_root == tree.tree().root(),
+        "{} holds root {}, not the blacklist's {} (run `smt-cli root --push`)",
+        state.address,
+        field_hex(&state.smt_root),
+        field_hex(&tree.tree().root())
+    );
+    let recent_slot = rpc
+        .get_slot_with_commitment(CommitmentConfig::finalized())
+        .await
+        .context("fetching a recent slot")?;
+    let prover = Prover::new(&args.circuit_dir);
+    generate_exclusion_proof(&prover, tree.tree(), pubkey, recent_slot).await
+}
+
+/// Print `state`'s root changes as they happen, reconnecting when the
+/// websocket drops
+async fn watch(
+    args: &Args,
+    out: &mut Output,
+    ws_url: Option<&str>,
+    resync: bool,
+    webhook: Option<&str>,
+) -> anyhow::Result<()> {
+    let rpc = args.rpc();
+    let program_id = args.program_id()?;
+    let address = args.state_address()?;
+    let ws_url = match ws_url {
+        Some(url) => url.to_string(),
+        None => websocket_url(&args.rpc_url)?,
+    };
+    let http = reqwest::Client::new();
+    let mut last: Option<ExclusionState> = None;
+    loop {
+        let mut subscription =
+            match AccountSubscription::subscribe(&ws_url, &address, CommitmentConfig::confirmed())
+                .await
+            {
+                Ok(subscription) => subscription,
+                Err(error) => {
+                    eprintln!("{error:#}; retrying");
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                    continue;
+                }
+            };
+        out.note(format!("Watching {address} on {ws_url}"));
+        // Catch up on changes made while disconnected
+        let mut state = Some(get_state_account(&rpc, &program_id, &address).await?);
+        loop {
+            let current = match state.take() {
+                Some(state) => state,
+                None => match subscription.next().await {
+                    Ok(update) if update.data.is_empty() => bail!("{address} was closed"),
+                    Ok(update) => {
+                        ExclusionState::from_account_data(address, update.slot, &update.data)?
+                    }
+                    Err(error) => {
+                        eprintln!("{error:#}; reconnecting");
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                        break;
+                    }
+                },
+            };
+            if let Some(change) = root_change(last.as_ref(), &current) {
+                let mut text = change.to_string();
+                let mut json = change.to_json();
+                if resync {
+                    let tree = LocalTree::open(&args.journal)?;
+                    let drift = sync::compare(&tree, &change.smt_root);
+                    text.push_str(match drift {
+                        Drift::InSync => "\n  in sync with the blacklist",
+                        Drift::Behind { .. } => "\n  behind the blacklist",
+                        Drift::Unknown => "\n  not a root the blacklist ever had",
+                    });
+                    if let Drift::Behind { behind, .. } = drift {
+                        text.push_str(&format!(" by {behind} records"));
+                    }
+                    for (key, value) in drift.fields() {
+                        json[key] = value;
+                    }
+                }
+                out.event(text, &json);
+                if let Some(url) = webhook {
+                    let posted = http
+                        .post(url)
+                        .json(&change.to_json())
+                        .send()
+                        .await
+                        .and_then(reqwest::Response::error_for_status);
+                    if let Err(error) = posted {
+                        eprintln!("  posting to {url}: {error}");
+                    }
+                }
+            }
+            last = Some(current);
+        }
+    }
+}
+
+/// A Squads multisig vault acting as the admin
+struct Squads {
+    program_id: Pubkey,
+    multisig: Pubkey,
+    vault_index: u8,
+}
+
+/// Propose setting the vault's state account to `root`, as the keypair, and
+/// approve the proposal
+async fn propose_root(
+    args: &Args,
+    out: &mut Output,
+    squads: &Squads,
+    root: &[u8; 32],
+    valid_until: i64,
+) -> anyhow::Result<()> {
+    let chain = args.chain()?;
+    let member = chain.signer.pubkey();
+    let (vault, _) = derive_vault_pda(&squads.program_id, &squads.multisig, squads.vault_index);
+    out.value("vault", "Vault", vault.to_string());
+    // The state keeps the address of the admin that initialized it
+    let address = args
+        .state
+        .unwrap_or_else(|| derive_state_pda(&chain.program_id, &vault).0);
+    let state = get_state_account(&chain.rpc, &chain.program_id, &address).await?;
+    ensure!(
+        state.admin == vault,
+        "{address}'s admin is {}, not the vault (hand it over first, or pass --state)",
+        state.admin
+    );
+    out.value("state", "State", address.to_string());
+
+    let multisig = chain
+        .rpc
+        .get_account(&squads.multisig)
+        .await
+        .with_context(|| format!("fetching {}", squads.multisig))?;
+    ensure!(
+        multisig.owner == squads.program_id,
+        "{} isn't a multisig of {}",
+        squads.multisig,
+        squads.program_id
+    );
+    let index = transaction_index(&multisig.data)? + 1;
+    let mut set_root = set_smt_root_ix(
+        &chain.program_id,
+        &vault,
+        root,
+        valid_until,
+        &[0; METADATA_SIZE],
+    );
+    set_root.accounts[1].pubkey = address;
+    let message = vault_message(&vault, &[set_root])?;
+    let proposal = propose(
+        &squads.program_id,
+        &squads.multisig,
+        squads.vault_index,
+        index,
+        &member,
+        &message,
+    );
+    chain.send(out, &proposal.instructions).await?;
+    out.line(
+        format!("Proposed and approved vault transaction {index}"),
+        [
+            ("transactionIndex", json!(index)),
+            ("transaction", json!(proposal.transaction.to_string())),
+            ("proposal", json!(proposal.proposal.to_string())),
+        ],
+    );
+    out.note(format!(
+        "Once the other members approve it, execute it in the Squads app or with `just squads-execute-smt {} {index}`",
+        squads.multisig
+    ));
+    Ok(())
+}
+
+/// Prove the sender isn't blacklisted and send a transfer from it, or write
+/// the transfer to `unsigned_out` for the sender to sign elsewhere
+async fn transfer(
+    args: &Args,
+    out: &mut Output,
+    recipient: &Pubkey,
+    lamports: u64,
+    unsigned_out: Option<&Path>,
+    sender: Option<Pubkey>,
+    nonce: Option<Pubkey>,
+) -> anyhow::Result<()> {
+    let rpc = args.rpc();
+    let program_id = args.program_id()?;
+    let sender = match sender {
+        Some(sender) => sender,
+        None => read_signer(&args.keypair)?.pubkey(),
+    };
+    let tree = LocalTree::open(&args.journal)?;
+    let address = args
+        .state
+        .unwrap_or_else(|| derive_state_pda(&program_id, &sender).0);
+    let state = get_state_account(&rpc, &program_id, &address).await?;
+    let proof = prove(args, &rpc, &state, &tree, &sender).await?;
+    let accounts = TransferAccounts {
+        program_id,
+        sender,
+        recipient: *recipient,
+        state: state.address,
+        verifier: args.verifier_id,
+    };
+    let instruction = transfer_sol_ix(&accounts, lamports, &proof.proof, &proof.witness);
+    let nonce = match nonce {
+        Some(nonce) => Some(fetch_nonce_account(&rpc, &nonce).await?),
+        None => None,
+    };
+    let options = ComputeBudgetOptions::default();
+    match (unsigned_out, &nonce) {
+        (None, None) => args.chain()?.send(out, &[instruction]).await?,
+        (None, Some(nonce)) => {
+            let chain = args.chain()?;
+            ensure!(
+                chain.signer.pubkey() == nonce.authority,
+                "nonce account {}'s authority is {}, not the keypair (use --unsigned-out for \
+                 it to sign)",
+                nonce.address,
+                nonce.authority
+            );
+            let instructions = [instruction];
+            let signature = send_and_confirm(
+                &rpc,
+                &program_id,
+                || async {
+                    // Built against the nonce as it is now, in case it moved
+                    let nonce = fetch_nonce_account(&rpc, &nonce.address).await?;
+                    let sized = build_nonced_transaction(
+                        &rpc,
+                        &chain.signer,
+                        &chain.signer,
+                        &nonce,
+                        &instructions,
+                        &[],
+                        &options,
+                    )
+                    .await?;
+                    Ok(sized.transaction)
+                },
+                &SendOptions::default(),
+            )
+            .await?;
+            out.push("signatures", "Signature", signature.to_string());
+        }
+        (Some(path), _) => {
+            let sized = match &nonce {
+                Some(nonce) => {
+                    size_nonced_message(&rpc, &sender, nonce, &[instruction], &[], &options).await?
+                }
+                None => size_message(&rpc, &sender, &[instruction], &[], &options).await?,
+            };
+            let offline = OfflineTransaction::new(VersionedMessage::V0(sized.message));
+            write_offline(path, &offline)?;
+            let signers: Vec<String> = offline.signers().iter().map(Pubkey::to_string).collect();
+            out.line(
+                format!(
+                    "Wrote {}, for {} to sign",
+                    path.display(),
+                    signers.join(" and ")
+                ),
+                [
+                    ("unsigned", json!(path.display().to_string())),
+                    ("sender", json!(sender.to_string())),
+                    ("signers", json!(signers)),
+                ],
+            );
+            match &nonce {
+                Some(nonce) => out.note(format!(
+                    "It's valid until nonce account {} is advanced, but its proof only for the \
+                     program's max_proof_age_slots after slot {}",
+                    nonce.address, proof.inputs.recent_slot
+                )),
+                None => out.note(
+                    "Its blockhash expires in about a minute: sign and submit it before then",
+                ),
+            }
+        }
+    }
+    if let Some(nonce) = &nonce {
+        out.set("nonce", nonce.address.to_string());
+    }
+    out.set("recentSlot", proof.inputs.recent_slot);
+    Ok(())
+}
+
+fn read_offline(path: &Path) -> anyhow::Result<OfflineTransaction> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    OfflineTransaction::from_json(&text).with_context(|| format!("reading {}", path.display()))
+}
+
+fn write_offline(path: &Path, offline: &OfflineTransaction) -> anyhow::Result<()> {
+    std::fs::write(path, offline.to_json()?).with_context(|| format!("writing {}", path.display()))
+}
+
+/// Ask `question` on stderr and read y or n from stdin
+fn confirm(question: &str) -> anyhow::Result<bool> {
+    eprint!("{question} [y/N] ");
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .context("reading the answer")?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Send `instructions` from a funded throwaway signer, recording the units
+/// the exclusion program's instruction consumed as `name`
+async fn profile_step(
+    chain: &Chain,
+    out: &mut Output,
+    profiled: &mut Profile,
+    name: &str,
+    instructions: &[Instruction],
+) -> anyhow::Result<()> {
+    let sized = build_sized_transaction(
+        &chain.rpc,
+        &chain.signer,
+        instructions,
+        &[],
+        &ComputeBudgetOptions {
+            fee_percentile: 0,
+            ..ComputeBudgetOptions::default()
+        },
+    )
+    .await
+    .with_context(|| format!("simulating {name}"))?;
+    let simulation = chain
+        .rpc
+        .simulate_transaction(&sized.transaction)
+        .await
+        .with_context(|| format!("simulating {name}"))?
+        .value;
+    let units = profile::units(&simulation.logs.unwrap_or_default(), &chain.program_id)
+        .with_context(|| format!("{name}'s logs don't say what it consumed"))?;
+    send_checked(&chain.rpc, &chain.program_id, &sized.transaction)
+        .await
+        .with_context(|| format!("sending {name}"))?;
+    out.note(format!("{name}: {} units", units.total));
+    profiled.insert(name.to_string(), units);
+    Ok(())
+}
+
+/// Run each instruction from a throwaway admin, then compare what they
+/// consumed with the baseline at `baseline`, or replace it
+async fn profile(
+    args: &Args,
+    out: &mut Output,
+    baseline: &Path,
+    update: bool,
+    tolerance: f64,
+    no_proof: bool,
+) -> anyhow::Result<()> {
+    let chain = Chain {
+        rpc: args.rpc(),
+        program_id: args.program_id.unwrap_or(exclusion_program_example::ID),
+        signer: Box::new(Keypair::new()),
+    };
+    let admin = chain.signer.pubkey();
+    let signature = chain
+        .rpc
+        .request_airdrop(&admin, BOOTSTRAP_AIRDROP)
+        .await
+        .with_context(|| format!("airdropping to {admin}; profile needs a local validator"))?;
+    chain
+        .rpc
+        .poll_for_signature(&signature)
+        .await
+        .context("confirming the airdrop")?;
+    out.note(format!("Profiling on {} as {admin}", args.rpc_url));
+
+    let journal = std::env::temp_dir().join(format!("smt-cli-profile-{admin}.journal"));
+    let mut tree = LocalTree::create(&journal, TREE_DEPTH)?;
+    let ops: Vec<Op> = sample_blacklist(PROFILE_TREE_SIZE)
+        .iter()
+        .map(|pubkey| Op::Insert(pubkey.to_bytes()))
+        .collect();
+    let applied = tree.apply(&ops);
+    // The tree only stands in for a blacklist for this run
+    std::fs::remove_file(&journal).ok();
+    applied?;
+
+    let mut profiled = Profile::new();
+    let (state, _) = derive_state_pda(&chain.program_id, &admin);
+    let root = tree.tree().root();
+    let steps = [
+        ("INITIALIZE", initialize_ix(&chain.program_id, &admin, 0)),
+        (
+            "SET_SMT_ROOT",
+            set_smt_root_ix(&chain.program_id, &admin, &root, 0, &[0; METADATA_SIZE]),
+        ),
+    ];
+    for (name, instruction) in steps {
+        profile_step(&chain, out, &mut profiled, name, &[instruction]).await?;
+    }
+    if !no_proof {
+        let proof = prove(
+            args,
+            &chain.rpc,
+            &chain.state(Some(state)).await?,
+            &tree,
+            &admin,
+        )
+        .await
+        .context("proving for TRANSFER_SOL (pass --no-proof to skip it)")?;
+        let accounts = TransferAccounts {
+            program_id: chain.program_id,
+            sender: admin,
+            recipient: Keypair::new().pubkey(),
+            state,
+            verifier: args.verifier_id,
+        };
+        let payload = [&proof.proof.as_bytes()[..], proof.witness.as_bytes()].concat();
+        let steps = [
+            (
+                "TRANSFER_SOL",
+                transfer_sol_ix(&accounts, DEMO_TRANSFER, &proof.proof, &proof.witness),
+            ),
+            (
+                "CREATE_PROOF_BUFFER",
+                create_proof_buffer_ix(&chain.program_id, &admin),
+            ),
+            (
+                "WRITE_PROOF_CHUNK",
+                write_proof_chunk_ix(&chain.program_id, &admin, 0, &payload),
+            ),
+            (
+                "TRANSFER_FROM_BUFFER",
+                transfer_from_buffer_ix(&accounts, DEMO_TRANSFER),
+            ),
+        ];
+        for (name, instruction) in steps {
+            profile_step(&chain, out, &mut profiled, name, &[instruction]).await?;
+        }
+    }
+
+    let before = profile::read_baseline(baseline)?;
+    let rows = profile::rows(&profiled, before.as_ref());
+    for row in &rows {
+        let change = match (row.baseline, row.change_percent()) {
+            (Some(baseline), Some(change)) => format!(" ({change:+.1}% on {baseline})"),
+            _ => " (not in the baseline)".to_string(),
+        };
+        out.item(
+            format!("{:<60} {:>9}{change}", row.name, row.units),
+            "instructions",
+            json!({
+                "name": row.name,
+                "units": row.units,
+                "baseline": row.baseline,
+                "regressed": row.regressed(tolerance),
+            }),
+        );
+    }
+    let regressed = rows.iter().filter(|row| row.regressed(tolerance)).count();
+    out.set("regressions", regressed);
+    if update {
+        profile::write_baseline(baseline, &profiled)?;
+        out.value("baseline", "Wrote", baseline.display().to_string());
+    } else if before.is_none() {
+        out.note(format!(
+            "No baseline at {}; write one with --update",
+            baseline.display()
+        ));
+    } else {
+        ensure!(
+            regressed == 0,
+            "{regressed} counts grew more than {tolerance}% on {}",
+            baseline.display()
+        );
+    }
+    Ok(())
+}
+
+/// Set up a throwaway admin on `cluster`, with a state account holding a
+/// sample blacklist's root, and send a transfer against it. Each step is
+/// skipped when `dir` or the chain shows it was done.
+async fn bootstrap(
+    args: &Args,
+    out: &mut Output,
+    cluster: Cluster,
+    dir: &Path,
+    sample: usize,
+) -> anyhow::Result<()> {
+    let workspace = Workspace::new(dir);
+    tokio::fs::create_dir_all(dir)
+        .await
+        .with_context(|| format!("creating {}", dir.display()))?;
+    let keypair = workspace.keypair();
+    let signer = if keypair.exists() {
+        read_keypair(&keypair.to_string_lossy())?
+    } else {
+        let signer = Keypair::new();
+        write_keypair(&signer, &keypair)?;
+        signer
+    };
+    let chain = Chain {
+        rpc: RpcClient::new_with_commitment(
+            cluster.rpc_url().to_string(),
+            CommitmentConfig::confirmed(),
+        ),
+        program_id: args.program_id.unwrap_or(exclusion_program_example::ID),
+        signer: Box::new(signer),
+    };
+    let admin = chain.signer.pubkey();
+    out.line(
+        format!("Admin: {admin} ({})", keypair.display()),
+        [
+            ("admin", json!(admin.to_string())),
+            ("keypair", json!(keypair.display().to_string())),
+        ],
+    );
+
+    let balance = chain
+        .rpc
+        .get_balance(&admin)
+        .await
+        .with_context(|| format!("fetching {admin}'s balance from {}", cluster.rpc_url()))?;
+    if balance < BOOTSTRAP_MIN_BALANCE {
+        let signature = chain
+            .rpc
+            .request_airdrop(&admin, BOOTSTRAP_AIRDROP)
+            .await
+            .with_context(|| {
+                format!(
+                    "airdropping to {admin}; if the faucet is rate-limited, fund it another way and rerun"
+                )
+            })?;
+        chain
+            .rpc
+            .poll_for_signature(&signature)
+            .await
+            .context("confirming the airdrop")?;
+        out.line(
+            format!("Airdropped {BOOTSTRAP_AIRDROP} lamports: {signature}"),
+            [("airdrop", json!(signature.to_string()))],
+        );
+    }
+
+    let (state, _) = derive_state_pda(&chain.program_id, &admin);
+    let created = chain
+        .rpc
+        .get_account_with_commitment(&state, CommitmentConfig::confirmed())
+        .await
+        .with_context(|| format!("fetching {state}"))?
+        .value
+        .is_some();
+    if !created {
+        chain
+            .send(out, &[initialize_ix(&chain.program_id, &admin, 0)])
+            .await?;
+    }
+    out.value("state", "State", state.to_string());
+
+    let journal = workspace.journal();
+    let mut tree = if journal.exists() {
+        LocalTree::open(&journal)?
+    } else {
+        LocalTree::create(&journal, TREE_DEPTH)?
+    };
+    if tree.tree().is_empty() {
+        let csv = sample_csv(&sample_blacklist(sample));
+        tokio::fs::write(workspace.sample(), &csv)
+            .await
+            .with_context(|| format!("writing {}", workspace.sample().display()))?;
+        let list = import::parse(&csv, Format::Csv)?;
+        let diff = import::diff(&tree, &list, false);
+        import::apply(&mut tree, &diff, IMPORT_BATCH_SIZE)?;
+    }
+    out.line(
+        format!(
+            "Blacklisted: {} ({})",
+            tree.tree().len(),
+            workspace.sample().display()
+        ),
+        [
+            ("blacklisted", json!(tree.tree().len())),
+            ("journal", json!(journal.display().to_string())),
+            ("sample", json!(workspace.sample().display().to_string())),
+        ],
+    );
+
+    let root = tree.tree().root();
+    if chain.state(Some(state)).await?.smt_root != root {
+        chain
+            .send(
+                out,
+                &[set_smt_root_ix(
+                    &chain.program_id,
+                    &admin,
+                    &root,
+                    0,
+                    &[0; METADATA_SIZE],
+                )],
+            )
+            .await?;
+    }
+    out.value("root", "Root", field_hex(&root));
+
+    let recipient = Keypair::new().pubkey();
+    let current = chain.state(Some(state)).await?;
+    let proof = prove(args, &chain.rpc, &current, &tree, &admin)
+        .await
+        .context(
+            "proving the admin isn't blacklisted (this runs nargo and sunspot in --circuit-dir)",
+        )?;
+    let accounts = TransferAccounts {
+        program_id: chain.program_id,
+        sender: admin,
+        recipient,
+        state,
+        verifier: args.verifier_id,
+    };
+    chain
+        .send(
+            out,
+            &[transfer_sol_ix(
+                &accounts,
+                DEMO_TRANSFER,
+                &proof.proof,
+                &proof.witness,
+            )],
+        )
+        .await?;
+    out.line(
+        format!("Sent {DEMO_TRANSFER} lamports to {recipient}"),
+        [
+            ("recipient", json!(recipient.to_string())),
+            ("lamports", json!(DEMO_TRANSFER)),
+        ],
+    );
+    let flags = workspace.flags(cluster.rpc_url(), &chain.program_id);
+    out.line(
+        format!("Carry on with: smt-cli {flags} <COMMAND>"),
+        [("flags", json!(flags))],
+    );
+    Ok(())
+}
+
+/// The blacklist's size and root, and the root before a change
+fn print_tree(out: &mut Output, tree: &LocalTree, previous: Option<&[u8; 32]>) {
+    out.value("blacklisted", "Blacklisted", tree.tree().len());
+    if let Some(previous) = previous {
+        out.value("previousRoot", "Previous root", field_hex(previous));
+    }
+    out.value("root", "Root", field_hex(&tree.tree().root()));
+}
+
+fn edit(args: &Args, out: &mut Output, ops: &[Op]) -> anyhow::Result<()> {
+    let mut tree = LocalTree::open(&args.journal)?;
+    let changed = tree.apply(ops)?;
+    out.line(
+        format!("Changed {changed} of {} pubkeys", ops.len()),
+        [("changed", json!(changed)), ("requested", json!(ops.len()))],
+    );
+    print_tree(out, &tree, None);
+    Ok(())
+}
+
+async fn run(args: &Args, out: &mut Output) -> anyhow::Result<()> {
+    match &args.command {
+        Command::Init { depth, on_chain } => {
+            let tree = LocalTree::create(&args.journal, *depth)?;
+            out.line(
+                format!("Created {}", tree.path().display()),
+                [("journal", json!(tree.path().display().to_string()))],
+            );
+            out.value("root", "Root", field_hex(&tree.tree().root()));
+            if *on_chain {
+                let chain = args.chain()?;
+                let admin = chain.signer.pubkey();
+                chain
+                    .send(out, &[initialize_ix(&chain.program_id, &admin, 0)])
+                    .await?;
+                let state = derive_state_pda(&chain.program_id, &admin).0;
+                out.value("state", "State", state.to_string());
+            }
+        }
+        Command::Add { pubkeys } => {
+            let ops: Vec<Op> = pubkeys.iter().map(|p| Op::Insert(p.to_bytes())).collect();
+            edit(args, out, &ops)?;
+        }
+        Command::Remove { pubkeys } => {
+            let ops: Vec<Op> = pubkeys.iter().map(|p| Op::Delete(p.to_bytes())).collect();
+            edit(args, out, &ops)?;
+        }
+        Command::Import {
+            file,
+            replace,
+            batch_size,
+        } => {
+            let text = tokio::fs::read_to_string(file)
+                .await
+                .with_context(|| format!("reading {}", file.display()))?;
+            let list = import::parse(&text, Format::of(file))
+                .with_context(|| format!("importing {}", file.display()))?;
+            let mut tree = LocalTree::open(&args.journal)?;
+            let previous = tree.tree().root();
+            let diff = import::diff(&tree, &list, *replace);
+            import::apply(&mut tree, &diff, *batch_size)?;
+            out.line(
+                format!(
+                    "Read {} pubkeys ({} duplicates)",
+                    list.pubkeys.len(),
+                    list.duplicates
+                ),
+                [
+                    ("read", json!(list.pubkeys.len())),
+                    ("duplicates", json!(list.duplicates)),
+                ],
+            );
+            print_diff(out, &diff);
+            print_tree(out, &tree, Some(&previous));
+        }
+        Command::ImportSdn { file } => {
+            let text = tokio::fs::read_to_string(file)
+                .await
+                .with_context(|| format!("reading {}", file.display()))?;
+            let sdn = ofac::parse_file(file, &text)?;
+            for (uid, address) in &sdn.invalid {
+                eprintln!("Skipping uid {uid}'s SOL address {address:?}: not a pubkey");
+            }
+            let list: List = sdn.addresses.iter().map(|address| address.pubkey).collect();
+            let mut tree = LocalTree::open(&args.journal)?;
+            let previous = tree.tree().root();
+            let diff = import::diff(&tree, &list, false);
+            let added = import::apply(&mut tree, &diff, IMPORT_BATCH_SIZE)?;
+            let log = provenance::log_path(&args.journal);
+            let records = source::provenance(&sdn.source(), &sdn.entries(), &added, unix_time());
+            provenance::append(&log, &records)?;
+            out.line(
+                format!(
+                    "Read {} SOL addresses from {}",
+                    sdn.addresses.len(),
+                    sdn.source()
+                ),
+                [
+                    ("read", json!(sdn.addresses.len())),
+                    ("invalid", json!(sdn.invalid.len())),
+                    ("source", json!(sdn.source())),
+                ],
+            );
+            print_diff(out, &diff);
+            print_tree(out, &tree, Some(&previous));
+            out.value("provenance", "Provenance", log.display().to_string());
+        }
+        Command::Pull {
+            location,
+            name,
+            format,
+        } => {
+            let name = name.as_deref().unwrap_or(location);
+            let format = format.unwrap_or_else(|| ListFormat::of(location));
+            let source = source::open(name, location, format);
+            let last_updated = source.last_updated().await?;
+            let entries = source.entries().await?;
+            let list: List = entries.iter().map(|entry| entry.pubkey).collect();
+            let mut tree = LocalTree::open(&args.journal)?;
+            let previous = tree.tree().root();
+            let diff = import::diff(&tree, &list, false);
+            let added = import::apply(&mut tree, &diff, IMPORT_BATCH_SIZE)?;
+            let log = provenance::log_path(&args.journal);
+            provenance::append(
+                &log,
+                &source::provenance(name, &entries, &added, unix_time()),
+            )?;
+            let text = match last_updated {
+                Some(time) => format!(
+                    "Read {} pubkeys from {name}, last updated at unix time {time}",
+                    list.pubkeys.len()
+                ),
+                None => format!("Read {} pubkeys from {name}", list.pubkeys.len()),
+            };
+            out.line(
+                text,
+                [
+                    ("read", json!(list.pubkeys.len())),
+                    ("source", json!(name)),
+                    ("lastUpdated", json!(last_updated)),
+                ],
+            );
+            print_diff(out, &diff);
+            print_tree(out, &tree, Some(&previous));
+            out.value("provenance", "Provenance", log.display().to_string());
+        }
+        Command::Merge { sources, replace } => {
+            let mut pulled = Vec::new();
+            for spec in sources {
+                let source = source::from_spec(spec)?;
+                let list = merge::pull(source.as_ref()).await?;
+                let text = match list.last_updated {
+                    Some(time) => format!(
+                        "{}: {} entries, last updated at unix time {time}",
+                        list.name,
+                        list.entries.len()
+                    ),
+                    None => format!("{}: {} entries", list.name, list.entries.len()),
+                };
+                out.item(
+                    text,
+                    "sources",
+                    json!({
+                        "name": list.name,
+                        "entries": list.entries.len(),
+                        "lastUpdated": list.last_updated,
+                    }),
+                );
+                pulled.push(list);
+            }
+            let log = provenance::log_path(&args.journal);
+            let mut tree = LocalTree::open(&args.journal)?;
+            let previous = tree.tree().root();
+            let merged = merge::merge(&tree, &provenance::read(&log)?, &pulled, *replace);
+            let changed = import::apply(&mut tree, &merged.diff, IMPORT_BATCH_SIZE)?;
+            provenance::append(
+                &log,
+                &merge::records(&merged, &pulled, &changed, unix_time()),
+            )?;
+            out.line(
+                format!(
+                    "Flagged {} pubkeys, {} by only some sources",
+                    merged.flagged_by.len(),
+                    merged.conflicts(pulled.len())
+                ),
+                [
+                    ("flagged", json!(merged.flagged_by.len())),
+                    ("conflicts", json!(merged.conflicts(pulled.len()))),
+                ],
+            );
+            out.line(
+                format!(
+                    "Dropped by a source since the last merge: {}",
+                    merged.dropped.len()
+                ),
+                [("dropped", json!(merged.dropped.len()))],
+            );
+            print_diff(out, &merged.diff);
+            print_tree(out, &tree, Some(&previous));
+            out.value("provenance", "Provenance", log.display().to_string());
+        }
+        Command::Why { pubkey } => {
+            let tree = LocalTree::open(&args.journal)?;
+            let log = provenance::read(&provenance::log_path(&args.journal))?;
+            let why = merge::why_blacklisted(&tree, &log, pubkey);
+            let status = if why.blacklisted { "is" } else { "is not" };
+            out.line(
+                format!("{pubkey} {status} blacklisted"),
+                [
+                    ("pubkey", json!(pubkey.to_string())),
+                    ("blacklisted", json!(why.blacklisted)),
+                    ("history", json!([])),
+                    ("flags", json!([])),
+                ],
+            );
+            for (sequence, timestamp, added) in &why.history {
+                let change = if *added { "added" } else { "removed" };
+                out.item(
+                    format!("  {change} in record {sequence}, at unix time {timestamp}"),
+                    "history",
+                    json!({ "sequence": sequence, "timestamp": timestamp, "added": added }),
+                );
+            }
+            for flag in &why.flags {
+                let status = if flag.listed { "flags" } else { "dropped" };
+                out.item(
+                    format!(
+                        "  {} {status} it: {} {} (first seen {}, last {})",
+                        flag.source, flag.reference, flag.detail, flag.first_seen, flag.last_seen
+                    ),
+                    "flags",
+                    json!({
+                        "source": flag.source,
+                        "listed": flag.listed,
+                        "reference": flag.reference,
+                        "detail": flag.detail,
+                        "firstSeen": flag.first_seen,
+                        "lastSeen": flag.last_seen,
+                    }),
+                );
+            }
+            if why.flags.is_empty() {
+                out.note("  no source has named it");
+            }
+        }
+        Command::Root {
+            push,
+            via_squads,
+            vault_index,
+            squads_program_id,
+            valid_until,
+        } => {
+            let tree = LocalTree::open(&args.journal)?;
+            let root = tree.tree().root();
+            out.value("root", "Root", field_hex(&root));
+            out.value("blacklisted", "Blacklisted", tree.tree().len());
+            if let Some(multisig) = via_squads {
+                let squads = Squads {
+                    program_id: *squads_program_id,
+                    multisig: *multisig,
+                    vault_index: *vault_index,
+                };
+                propose_root(args, out, &squads, &root, *valid_until).await?;
+            } else if *push {
+                let chain = args.chain()?;
+                let admin = chain.signer.pubkey();
+                chain
+                    .send(
+                        out,
+                        &[set_smt_root_ix(
+                            &chain.program_id,
+                            &admin,
+                            &root,
+                            *valid_until,
+                            &[0; METADATA_SIZE],
+                        )],
+                    )
+                    .await?;
+            } else if args.program_id.is_some() {
+                let state = args.chain()?.state(args.state).await?;
+                out.line(
+                    format!(
+                        "On chain: {} ({})",
+                        field_hex(&state.smt_root),
+                        state.address
+                    ),
+                    [
+                        ("onChainRoot", json!(field_hex(&state.smt_root))),
+                        ("state", json!(state.address.to_string())),
+                    ],
+                );
+            }
+        }
+        Command::Sync { rewind } => {
+            let chain = args.chain()?;
+            let mut tree = LocalTree::open(&args.journal)?;
+            let state = chain.state(args.state).await?;
+            let records = tree.entries().len();
+            out.line(
+                format!(
+                    "Journal: {records} records, root {}",
+                    field_hex(&tree.tree().root())
+                ),
+                [
+                    ("records", json!(records)),
+                    ("root", json!(field_hex(&tree.tree().root()))),
+                ],
+            );
+            out.line(
+                format!(
+                    "On chain: {} ({} at slot {})",
+                    field_hex(&state.smt_root),
+                    state.address,
+                    state.slot
+                ),
+                [
+                    ("onChainRoot", json!(field_hex(&state.smt_root))),
+                    ("state", json!(state.address.to_string())),
+                    ("slot", json!(state.slot)),
+                ],
+            );
+            let drift = sync::compare(&tree, &state.smt_root);
+            for (key, value) in drift.fields() {
+                out.set(key, value);
+            }
+            match drift {
+                Drift::InSync => out.note("In sync"),
+                Drift::Behind { sequence, behind } => {
+                    match sequence {
+                        Some(sequence) => out.note(format!(
+                            "The chain is at record {sequence}, {behind} records behind"
+                        )),
+                        None => out.note(format!(
+                            "The chain holds the empty tree, {behind} records behind"
+                        )),
+                    }
+                    if *rewind {
+                        let ops = sync::rewind_ops(&tree, behind);
+                        tree.apply(&ops)?;
+                        ensure!(
+                            tree.tree().root() == state.smt_root,
+                            "rewinding led to {}, not the chain's root",
+                            field_hex(&tree.tree().root())
+                        );
+                        out.line(
+                            format!("Rewound {} pubkeys to the chain's root", ops.len()),
+                            [("rewound", json!(ops.len()))],
+                        );
+                    } else {
+                        out.note("Push with `smt-cli root --push`, or rewind with `smt-cli sync --rewind`");
+                    }
+                }
+                Drift::Unknown => {
+                    bail!("the chain's root was never in the journal: it was set from another list")
+                }
+            }
+        }
+        Command::Watch {
+            ws_url,
+            resync,
+            webhook,
+        } => watch(args, out, ws_url.as_deref(), *resync, webhook.as_deref()).await?,
+        Command::Prove { pubkey, out: dir } => {
+            let chain = args.chain()?;
+            let tree = LocalTree::open(&args.journal)?;
+            let pubkey = pubkey.unwrap_or_else(|| chain.signer.pubkey());
+            let state = chain.state(args.state).await?;
+            let proof = prove(args, &chain.rpc, &state, &tree, &pubkey).await?;
+            for (key, name, bytes) in [
+                ("proof", "proof.bin", &proof.proof.as_bytes()[..]),
+                ("witness", "witness.bin", &proof.witness.as_bytes()[..]),
+            ] {
+                let path = dir.join(name);
+                tokio::fs::write(&path, bytes)
+                    .await
+                    .with_context(|| format!("writing {}", path.display()))?;
+                out.line(
+                    format!("Wrote {}", path.display()),
+                    [(key, json!(path.display().to_string()))],
+                );
+            }
+            out.value("recentSlot", "Recent slot", proof.inputs.recent_slot);
+        }
+        Command::Inspect { address } => {
+            let account = args
+                .rpc()
+                .get_account(address)
+                .await
+                .with_context(|| format!("fetching {address}"))?;
+            out.value("address", "Address", address.to_string());
+            out.value("owner", "Owner", account.owner.to_string());
+            out.value("lamports", "Lamports", account.lamports);
+            out.line(
+                format!("Size: {} bytes", account.data.len()),
+                [("size", json!(account.data.len()))],
+            );
+            if args
+                .program_id
+                .is_some_and(|program_id| program_id != account.owner)
+            {
+                eprintln!("Not owned by the exclusion program; decoding anyway");
+            }
+            let inspection = inspect(&account.data)?;
+            let json = inspection.to_json();
+            out.line(
+                inspection.to_string().trim_end(),
+                [
+                    ("kind", json["kind"].clone()),
+                    ("fields", json["fields"].clone()),
+                ],
+            );
+        }
+        Command::VerifyProof {
+            proof,
+            witness,
+            vk,
+            pubkey,
+        } => {
+            let vk_path = vk
+                .clone()
+                .unwrap_or_else(|| args.circuit_dir.join("target/smt_exclusion.vk"));
+            let read = |path: &PathBuf| {
+                std::fs::read(path).with_context(|| format!("reading {}", path.display()))
+            };
+            let key = VerifyingKey::from_gnark_bytes(&read(&vk_path)?)
+                .with_context(|| format!("parsing {}", vk_path.display()))?;
+            let report = verify::check(&key, &read(proof)?, &read(witness)?)?;
+            out.value(
+                "verifyingKey",
+                "Verifying key",
+                vk_path.display().to_string(),
+            );
+            let text = match &report.verified {
+                Ok(()) => "Proof: verifies".to_string(),
+                Err(error) => format!("Proof: doesn't verify ({error})"),
+            };
+            out.line(text, [("verified", json!(report.verified.is_ok()))]);
+            let inputs = &report.inputs;
+            out.value("smtRoot", "smt_root", field_hex(&inputs.smt_root));
+            match LocalTree::open(&args.journal) {
+                Ok(tree) => {
+                    let drift = sync::compare(&tree, &inputs.smt_root);
+                    for (key, value) in drift.fields() {
+                        out.set(key, value);
+                    }
+                    match drift {
+                        Drift::InSync => out.note("  the blacklist's root"),
+                        Drift::Behind {
+                            sequence: Some(sequence),
+                            behind,
+                        } => out.note(format!(
+                            "  the root after record {sequence}, {behind} records before the blacklist's: SmtRootMismatch unless the state account still holds it"
+                        )),
+                        Drift::Behind { sequence: None, .. } => {
+                            out.note("  the empty tree's root: SmtRootMismatch unless the state account still holds it")
+                        }
+                        Drift::Unknown => out.note(format!(
+                            "  not a root {} ever had: proven against another list",
+                            tree.path().display()
+                        )),
+                    }
+                }
+                Err(_) => out.note(format!(
+                    "  (no journal at {} to compare)",
+                    args.journal.display()
+                )),
+            }
+            out.value("pubkeyHash", "pubkey_hash", field_hex(&inputs.pubkey_hash));
+            if let Some(pubkey) = pubkey {
+                let text = if report.binds_pubkey(pubkey) {
+                    format!("  {pubkey}'s")
+                } else {
+                    format!("  not {pubkey}'s: PubkeyHashMismatch if {pubkey} sends it")
+                };
+                out.line(
+                    text,
+                    [("pubkeyMatches", json!(report.binds_pubkey(pubkey)))],
+                );
+            }
+            out.value("recentSlot", "recent_slot", inputs.recent_slot);
+            if report.verified.is_err() {
+                bail!("{} doesn't verify", proof.display());
+            }
+        }
+        Command::Transfer {
+            recipient,
+            lamports,
+            unsigned_out,
+            sender,
+            nonce,
+        } => {
+            transfer(
+                args,
+                out,
+                recipient,
+                *lamports,
+                unsigned_out.as_deref(),
+                *sender,
+                *nonce,
+            )
+            .await?
+        }
+        Command::CreateNonce { authority } => {
+            let chain = args.chain()?;
+            let authority = authority.unwrap_or_else(|| chain.signer.pubkey());
+            let nonce =
+                create_nonce_account(&chain.rpc, &*chain.signer, &Keypair::new(), &authority)
+                    .await?;
+            out.value("nonce", "Nonce account", nonce.address.to_string());
+            out.value("authority", "Authority", nonce.authority.to_string());
+            out.value("blockhash", "Blockhash", nonce.blockhash.to_string());
+        }
+        Command::Sign { file, yes } => {
+            let program_id = args.program_id()?;
+            let mut offline = read_offline(file)?;
+            for line in offline.describe(&program_id) {
+                out.item(&line, "description", json!(line));
+            }
+            let signer = read_signer(&args.keypair)?;
+            if !yes && !confirm(&format!("Sign as {}?", signer.pubkey()))? {
+                bail!("not signed");
+            }
+            offline.sign(&*signer)?;
+            write_offline(file, &offline)?;
+            out.value("signer", "Signed as", signer.pubkey().to_string());
+            let missing = offline.missing();
+            for signer in &missing {
+                out.push("missing", "Still to sign", signer.to_string());
+            }
+            if missing.is_empty() {
+                out.note(format!(
+                    "Submit it with `smt-cli submit {}`",
+                    file.display()
+                ));
+            }
+        }
+        Command::Submit { file } => {
+            let program_id = args.program_id()?;
+            let transaction = read_offline(file)?.into_signed()?;
+            let signature = send_checked(&args.rpc(), &program_id, &transaction).await?;
+            out.push("signatures", "Signature", signature.to_string());
+        }
+        Command::Profile {
+            baseline,
+            update,
+            tolerance,
+            no_proof,
+        } => profile(args, out, baseline, *update, *tolerance, *no_proof).await?,
+        Command::Bootstrap {
+            cluster,
+            dir,
+            sample,
+        } => bootstrap(args, out, *cluster, dir, *sample).await?,
+    }
+    Ok(())
+}
+
+/// What an import or merge changed
+fn print_diff(out: &mut Output, diff: &Diff) {
+    out.line(
+        format!(
+            "Added {}, already blacklisted {}, removed {}",
+            diff.added, diff.unchanged, diff.removed
+        ),
+        [
+            ("added", json!(diff.added)),
+            ("unchanged", json!(diff.unchanged)),
+            ("removed", json!(diff.removed)),
+        ],
+    );
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    let mut out = Output::new(args.output);
+    let result = run(&args, &mut out).await;
+    if let Err(error) = &result {
+        out.error(error);
+    }
+    out.finish();
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(_) => ExitCode::FAILURE,
+    }
+}
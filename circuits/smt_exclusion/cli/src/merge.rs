@@ -0,0 +1,222 @@
+//! One blacklist from several screening vendors
+//!
+//! A pubkey is blacklisted when any source names it. Sources often disagree:
+//! a merge logs each source's entries, and the pubkeys a source named last
+//! time but has dropped since, so the provenance log says which sources
+//! flagged each pubkey and when. `why_blacklisted` reads that back with the
+//! journal, for the CLI or an indexer answering a user who can't prove.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Context;
+use smt::Op;
+use solana_pubkey::Pubkey;
+
+use crate::{
+    import::{self, Diff, List},
+    provenance::Provenance,
+    source::{ListSource, SourceEntry},
+    tree::LocalTree,
+};
+
+/// A source's list, as of one pull
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pulled {
+    pub name: String,
+    /// Unix time the list last changed, if the source says
+    pub last_updated: Option<u64>,
+    pub entries: Vec<SourceEntry>,
+}
+
+/// Fetch and parse `source`'s list
+pub async fn pull(source: &dyn ListSource) -> anyhow::Result<Pulled> {
+    let last_updated = source
+        .last_updated()
+        .await
+        .with_context(|| format!("asking {} when it last changed", source.name()))?;
+    Ok(Pulled {
+        name: source.name().to_string(),
+        last_updated,
+        entries: source.entries().await?,
+    })
+}
+
+/// What merging the sources' lists changes
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Merge {
+    pub diff: Diff,
+    /// The sources naming each pubkey any does
+    pub flagged_by: BTreeMap<Pubkey, Vec<String>>,
+    /// `(source, pubkey)` for pubkeys a source has dropped since it was last
+    /// logged naming them
+    pub dropped: Vec<(String, Pubkey)>,
+}
+
+impl Merge {
+    /// Pubkeys some sources name and others don't
+    pub fn conflicts(&self, sources: usize) -> usize {
+        self.flagged_by
+            .values()
+            .filter(|names| names.len() < sources)
+            .count()
+    }
+}
+
+/// Whether each source last named each pubkey, by the log
+fn listed(log: &[Provenance]) -> BTreeMap<(&str, &str), bool> {
+    log.iter()
+        .map(|record| {
+            (
+                (record.source.as_str(), record.pubkey.as_str()),
+                record.listed,
+            )
+        })
+        .collect()
+}
+
+/// What merging `pulled` into `tree` changes; with `replace`, pubkeys no
+/// source names come off the blacklist
+pub fn merge(tree: &LocalTree, log: &[Provenance], pulled: &[Pulled], replace: bool) -> Merge {
+    let mut flagged_by: BTreeMap<Pubkey, Vec<String>> = BTreeMap::new();
+    for source in pulled {
+        let named: BTreeSet<Pubkey> = source.entries.iter().map(|entry| entry.pubkey).collect();
+        for pubkey in named {
+            flagged_by
+                .entry(pubkey)
+                .or_default()
+                .push(source.name.clone());
+        }
+    }
+    let names: BTreeSet<&str> = pulled.iter().map(|source| source.name.as_str()).collect();
+    let dropped = listed(log)
+        .into_iter()
+        .filter(|((source, _), listed)| *listed && names.contains(source))
+        .filter_map(|((source, pubkey), _)| {
+            let pubkey: Pubkey = pubkey.parse().ok()?;
+            let still = flagged_by
+                .get(&pubkey)
+                .is_some_and(|names| names.iter().any(|name| name == source));
+            (!still).then(|| (source.to_string(), pubkey))
+        })
+        .collect();
+    let list: List = flagged_by.keys().copied().collect();
+    Merge {
+        diff: import::diff(tree, &list, replace),
+        flagged_by,
+        dropped,
+    }
+}
+
+/// The provenance records a merge appends: each source's entries, then the
+/// pubkeys sources dropped. `changed` is the journal record that added or
+/// removed each pubkey the merge changed.
+pub fn records(
+    merge: &Merge,
+    pulled: &[Pulled],
+    changed: &BTreeMap<Pubkey, u64>,
+    recorded: u64,
+) -> Vec<Provenance> {
+    let mut records = Vec::new();
+    for source in pulled {
+        records.extend(crate::source::provenance(
+            &source.name,
+            &source.entries,
+            changed,
+            recorded,
+        ));
+    }
+    records.extend(merge.dropped.iter().map(|(source, pubkey)| Provenance {
+        pubkey: pubkey.to_string(),
+        source: source.clone(),
+        reference: String::new(),
+        detail: String::new(),
+        listed: false,
+        sequence: changed.get(pubkey).copied(),
+        recorded,
+    }));
+    records
+}
+
+/// One source's say on a pubkey
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Flag {
+    pub source: String,
+    /// Whether the source's latest list names the pubkey
+    pub listed: bool,
+    /// The source's latest entry for it
+    pub reference: String,
+    pub detail: String,
+    /// Unix time the source was first logged naming the pubkey
+    pub first_seen: u64,
+    /// Unix time of the source's latest record on the pubkey
+    pub last_seen: u64,
+}
+
+/// Why a pubkey is, or isn't, on the blacklist
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Why {
+    pub pubkey: Pubkey,
+    pub blacklisted: bool,
+    /// The journal records that added or removed it: `(sequence, unix time,
+    /// added)`
+    pub history: Vec<(u64, u64, bool)>,
+    /// Each source that has named it, in the order they first did
+    pub flags: Vec<Flag>,
+}
+
+/// Which sources flagged `pubkey` and when, and the journal records that
+/// changed it
+pub fn why_blacklisted(tree: &LocalTree, log: &[Provenance], pubkey: &Pubkey) -> Why {
+    let bytes = pubkey.to_bytes();
+    let history = tree
+        .replay()
+        .history(&bytes)
+        .map(|entry| {
+            let added = entry
+                .ops
+                .iter()
+                .rev()
+                .find_map(|op| match op {
+                    Op::Insert(other) if *other == bytes => Some(true),
+                    Op::Delete(other) if *other == bytes => Some(false),
+                    _ => None,
+                })
+                .expect("history only yields records with an op on the pubkey");
+            (entry.sequence, entry.timestamp, added)
+        })
+        .collect();
+
+    let key = pubkey.to_string();
+    let mut flags: Vec<Flag> = Vec::new();
+    for record in log.iter().filter(|record| record.pubkey == key) {
+        let position = flags.iter().position(|flag| flag.source == record.source);
+        let flag = match position {
+            Some(position) => &mut flags[position],
+            // A source that only ever dropped the pubkey never flagged it
+            None if !record.listed => continue,
+            None => {
+                flags.push(Flag {
+                    source: record.source.clone(),
+                    listed: true,
+                    reference: String::new(),
+                    detail: String::new(),
+                    first_seen: record.recorded,
+                    last_seen: record.recorded,
+                });
+                flags.last_mut().expect("just pushed")
+            }
+        };
+        flag.listed = record.listed;
+        flag.last_seen = record.recorded;
+        if record.listed {
+            flag.reference.clone_from(&record.reference);
+            flag.detail.clone_from(&record.detail);
+        }
+    }
+    Why {
+        pubkey: *pubkey,
+        blacklisted: tree.members().contains(&bytes),
+        history,
+        flags,
+    }
+}
@@ -0,0 +1,224 @@
+//! Solana addresses on OFAC's Specially Designated Nationals (SDN) list
+//!
+//! OFAC publishes the list as XML (`sdn.xml`) and CSV (`sdn.csv`). A
+//! sanctioned wallet is an identifier of type `Digital Currency Address - SOL`
+//! on an entry: in the XML an `<id>` with that `<idType>`, in the CSV a
+//! `Digital Currency Address - SOL <address>;` clause in the remarks column.
+//! Each address keeps the entry it came from (its `uid`, name and sanctions
+//! programs), so the blacklist can say why a pubkey is on it.
+
+use std::{path::Path, str::FromStr};
+
+use anyhow::{bail, ensure, Context};
+use solana_pubkey::Pubkey;
+
+use crate::source::SourceEntry;
+
+/// The identifier type OFAC gives Solana addresses
+pub const SOL_ID_TYPE: &str = "Digital Currency Address - SOL";
+
+/// Columns of `sdn.csv`, which has no header
+const CSV_COLUMNS: usize = 12;
+const CSV_UID: usize = 0;
+const CSV_NAME: usize = 1;
+const CSV_PROGRAMS: usize = 3;
+const CSV_REMARKS: usize = 11;
+
+/// A Solana address and the SDN entry that lists it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SdnAddress {
+    pub pubkey: Pubkey,
+    /// The entry's `uid` (`ent_num` in the CSV)
+    pub uid: u64,
+    pub name: String,
+    /// Sanctions programs, such as `CYBER2`
+    pub programs: Vec<String>,
+}
+
+/// The Solana addresses in one edition of the list
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SdnList {
+    /// The list's publish date, as OFAC writes it (`MM/DD/YYYY`); the CSV
+    /// doesn't carry one
+    pub published: Option<String>,
+    pub addresses: Vec<SdnAddress>,
+    /// `(uid, address)` for SOL identifiers that aren't pubkeys
+    pub invalid: Vec<(u64, String)>,
+}
+
+impl SdnList {
+    /// How provenance records name this edition of the list
+    pub fn source(&self) -> String {
+        match &self.published {
+            Some(published) => format!("OFAC SDN {published}"),
+            None => "OFAC SDN".to_string(),
+        }
+    }
+
+    /// The addresses as a `ListSource` gives them
+    pub fn entries(&self) -> Vec<SourceEntry> {
+        self.addresses
+            .iter()
+            .map(|address| SourceEntry {
+                pubkey: address.pubkey,
+                reference: format!("uid {}", address.uid),
+                detail: format!("{} [{}]", address.name, address.programs.join("] [")),
+            })
+            .collect()
+    }
+
+    fn push(&mut self, uid: u64, name: &str, programs: &[String], address: &str) {
+        match Pubkey::from_str(address) {
+            Ok(pubkey) => self.addresses.push(SdnAddress {
+                pubkey,
+                uid,
+                name: name.to_string(),
+                programs: programs.to_vec(),
+            }),
+            Err(_) => self.invalid.push((uid, address.to_string())),
+        }
+    }
+}
+
+/// Read `sdn.xml` or `sdn.csv`, by `path`'s extension
+pub fn parse_file(path: &Path, text: &str) -> anyhow::Result<SdnList> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) if extension.eq_ignore_ascii_case("xml") => parse_xml(text),
+        Some(extension) if extension.eq_ignore_ascii_case("csv") => parse_csv(text),
+        _ => bail!("{}: expected OFAC's sdn.xml or sdn.csv", path.display()),
+    }
+}
+
+/// The Solana addresses in `sdn.xml`
+pub fn parse_xml(text: &str) -> anyhow::Result<SdnList> {
+    ensure!(text.contains("<sdnList"), "not an SDN list: no <sdnList>");
+    let mut list = SdnList {
+        published: elements(text, "Publish_Date").next().map(unescape),
+        ..SdnList::default()
+    };
+    for entry in elements(text, "sdnEntry") {
+        let ids: Vec<&str> = elements(entry, "id").collect();
+        let sol: Vec<String> = ids
+            .iter()
+            .filter(|id| {
+                elements(id, "idType").next().map(unescape).as_deref() == Some(SOL_ID_TYPE)
+            })
+            .filter_map(|id| elements(id, "idNumber").next().map(unescape))
+            .collect();
+        if sol.is_empty() {
+            continue;
+        }
+        // The entry's own uid comes before its identifiers' and addresses'
+        let uid = elements(entry, "uid")
+            .next()
+            .and_then(|uid| uid.trim().parse().ok())
+            .context("an sdnEntry without a uid")?;
+        let name = [
+            elements(entry, "firstName").next(),
+            elements(entry, "lastName").next(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(unescape)
+        .collect::<Vec<_>>()
+        .join(" ");
+        let programs: Vec<String> = elements(entry, "program").map(unescape).collect();
+        for address in &sol {
+            list.push(uid, &name, &programs, address.trim());
+        }
+    }
+    Ok(list)
+}
+
+/// The Solana addresses in `sdn.csv`
+pub fn parse_csv(text: &str) -> anyhow::Result<SdnList> {
+    let mut list = SdnList::default();
+    for (i, line) in text.lines().enumerate() {
+        let fields = csv_fields(line);
+        // The file ends with an end-of-file character on a line of its own
+        let Ok(uid) = fields[CSV_UID].trim().parse::<u64>() else {
+            continue;
+        };
+        ensure!(
+            fields.len() == CSV_COLUMNS,
+            "line {}: expected {CSV_COLUMNS} columns, found {}",
+            i + 1,
+            fields.len()
+        );
+        let sol: Vec<&str> = fields[CSV_REMARKS]
+            .split(';')
+            .filter_map(|clause| {
+                let clause = clause.trim();
+                let clause = clause.strip_prefix("alt. ").unwrap_or(clause);
+                clause.strip_prefix(SOL_ID_TYPE)
+            })
+            // The last clause ends the remarks with a full stop
+            .map(|address| address.trim().trim_end_matches('.'))
+            .collect();
+        if sol.is_empty() {
+            continue;
+        }
+        let programs: Vec<String> = fields[CSV_PROGRAMS]
+            .trim_matches(|c| c == '[' || c == ']' || c == ' ')
+            .split("] [")
+            .map(str::to_string)
+            .collect();
+        for address in sol {
+            list.push(uid, fields[CSV_NAME].trim(), &programs, address);
+        }
+    }
+    Ok(list)
+}
+
+/// The contents of each `<tag>` element in `text`, outermost first
+fn elements<'a>(text: &'a str, tag: &'a str) -> impl Iterator<Item = &'a str> {
+    let open = format!("<{tag}");
+    let close = format!("</{tag}>");
+    let mut rest = text;
+    std::iter::from_fn(move || loop {
+        let start = rest.find(&open)?;
+        let after = &rest[start + open.len()..];
+        // `<id>` mustn't match `<idType>`
+        let Some(end_of_open) = after.find('>') else {
+            rest = "";
+            return None;
+        };
+        if !after.starts_with(['>', ' ', '\t', '\r', '\n']) {
+            rest = after;
+            continue;
+        }
+        let body = &after[end_of_open + 1..];
+        let end = body.find(&close)?;
+        rest = &body[end + close.len()..];
+        return Some(&body[..end]);
+    })
+}
+
+/// `text` with XML's predefined entities replaced
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// A CSV line's fields, unquoted; `""` in a quoted field is a quote
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().expect("there's always a field");
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(String::new()),
+            (c, _) => field.push(c),
+        }
+    }
+    fields
+}
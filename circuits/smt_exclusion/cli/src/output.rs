@@ -0,0 +1,157 @@
+//! What commands print: lines for people, or one JSON object for scripts
+//!
+//! With `--output json`, each command prints a single JSON object on stdout
+//! when it finishes, holding the values the text lines carry (roots,
+//! signatures, addresses, counts) under fixed camelCase keys. A failure adds
+//! `error`, and for a transaction the program rejected, `programError` and
+//! the logs, then exits non-zero. `watch` never finishes, so it prints one
+//! object per change instead. Warnings go to stderr either way.
+
+use std::str::FromStr;
+
+use anyhow::bail;
+use exclusion_client::send::SendError;
+use serde_json::{Map, Value};
+
+/// How commands print
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> anyhow::Result<Self> {
+        Ok(match format {
+            "text" => Self::Text,
+            "json" => Self::Json,
+            format => bail!("unknown output format {format} (text or json)"),
+        })
+    }
+}
+
+/// A command's output: printed line by line as text, or gathered into one
+/// JSON object
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Output {
+    /// `None` for text
+    json: Option<Map<String, Value>>,
+}
+
+/// A value as a text line shows it: strings unquoted
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+impl Output {
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            json: (format == OutputFormat::Json).then(Map::new),
+        }
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.json.is_some()
+    }
+
+    /// Print `text`, or set `fields` in the JSON object
+    pub fn line<K: Into<String>>(
+        &mut self,
+        text: impl std::fmt::Display,
+        fields: impl IntoIterator<Item = (K, Value)>,
+    ) {
+        match &mut self.json {
+            Some(object) => {
+                object.extend(fields.into_iter().map(|(key, value)| (key.into(), value)))
+            }
+            None => println!("{text}"),
+        }
+    }
+
+    /// `Label: value`, or `"key": value`
+    pub fn value(&mut self, key: &str, label: &str, value: impl Into<Value>) {
+        let value = value.into();
+        self.line(format!("{label}: {}", display(&value)), [(key, value)]);
+    }
+
+    /// Print `text`, or append `value` to the array at `key`
+    pub fn item(&mut self, text: impl std::fmt::Display, key: &str, value: Value) {
+        match &mut self.json {
+            Some(object) => match object
+                .entry(key)
+                .or_insert_with(|| Value::Array(Vec::new()))
+            {
+                Value::Array(values) => values.push(value),
+                other => *other = Value::Array(vec![other.take(), value]),
+            },
+            None => println!("{text}"),
+        }
+    }
+
+    /// `Label: value`, or `value` appended to the array at `key`
+    pub fn push(&mut self, key: &str, label: &str, value: impl Into<Value>) {
+        let value = value.into();
+        self.item(format!("{label}: {}", display(&value)), key, value);
+    }
+
+    /// Set `key` in the JSON object; text leaves it out
+    pub fn set(&mut self, key: &str, value: impl Into<Value>) {
+        if let Some(object) = &mut self.json {
+            object.insert(key.into(), value.into());
+        }
+    }
+
+    /// A line only people need, left out of the JSON
+    pub fn note(&mut self, text: impl std::fmt::Display) {
+        if self.json.is_none() {
+            println!("{text}");
+        }
+    }
+
+    /// Print `text`, or `json` on a line of its own right away, for commands
+    /// that stream
+    pub fn event(&mut self, text: impl std::fmt::Display, json: &Value) {
+        match self.json {
+            Some(_) => println!("{json}"),
+            None => println!("{text}"),
+        }
+    }
+
+    /// The JSON object so far; `None` for text
+    pub fn json(&self) -> Option<&Map<String, Value>> {
+        self.json.as_ref()
+    }
+
+    /// Report why the command failed: on stderr as text, or in the JSON
+    /// object as the error's chain and what the program or runtime said if a
+    /// transaction failed
+    pub fn error(&mut self, error: &anyhow::Error) {
+        let Some(object) = &mut self.json else {
+            eprintln!("Error: {error:?}");
+            return;
+        };
+        object.insert("error".into(), format!("{error:#}").into());
+        let send = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<SendError>());
+        if let Some(send) = send {
+            if let SendError::Program { error, .. } = send {
+                object.insert("programError".into(), format!("{error:?}").into());
+            }
+            object.insert("logs".into(), send.logs().into());
+        }
+    }
+
+    /// Print the JSON object, if there is one
+    pub fn finish(self) {
+        if let Some(object) = self.json {
+            println!("{}", Value::Object(object));
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Compute units each of the program's instructions consumes
+//!
+//! `smt-cli profile` sends each instruction in turn against a local validator,
+//! simulating it first. The simulation's logs say what every program consumed:
+//! `Program <id> consumed N of M compute units` as each invocation returns,
+//! nested ones (the ZK verifier a transfer invokes) before their caller, whose
+//! count includes them. A baseline file keeps the last accepted counts, so a
+//! change that makes an instruction costlier shows up as a regression.
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use solana_pubkey::Pubkey;
+
+/// Compute units one instruction consumed
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Units {
+    pub total: u64,
+    /// What each program the instruction invoked consumed, by program ID;
+    /// part of `total`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub cpi: BTreeMap<String, u64>,
+}
+
+/// Units by instruction name, as the baseline file holds them
+pub type Profile = BTreeMap<String, Units>;
+
+/// The units `program_id`'s top-level instruction consumed in `logs`, and
+/// what the programs it invoked did; `None` if it didn't run
+pub fn units(logs: &[String], program_id: &Pubkey) -> Option<Units> {
+    let program_id = program_id.to_string();
+    // The programs invoked so far and not yet returned, outermost first
+    let mut stack: Vec<&str> = Vec::new();
+    let mut found: Option<Units> = None;
+    let mut cpi: BTreeMap<String, u64> = BTreeMap::new();
+    for line in logs {
+        let Some(rest) = line.strip_prefix("Program ") else {
+            continue;
+        };
+        let mut words = rest.split(' ');
+        let (Some(program), Some(verb)) = (words.next(), words.next()) else {
+            continue;
+        };
+        match verb {
+            "invoke" => stack.push(program),
+            "consumed" => {
+                let Some(consumed) = words.next().and_then(|units| units.parse::<u64>().ok())
+                else {
+                    continue;
+                };
+                let inside = stack.first() == Some(&program_id.as_str());
+                match stack.len() {
+                    1 if program == program_id => {
+                        found = Some(Units {
+                            total: consumed,
+                            cpi: std::mem::take(&mut cpi),
+                        });
+                    }
+                    depth if depth > 1 && inside => {
+                        *cpi.entry(program.to_string()).or_default() += consumed;
+                    }
+                    _ => {}
+                }
+            }
+            "success" | "failed:" => {
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+/// The baseline at `path`; `None` if there isn't one yet
+pub fn read_baseline(path: &Path) -> anyhow::Result<Option<Profile>> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text)
+            .map(Some)
+            .with_context(|| format!("parsing {}", path.display())),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error).with_context(|| format!("reading {}", path.display())),
+    }
+}
+
+/// Write `profile` to `path` as the new baseline
+pub fn write_baseline(path: &Path, profile: &Profile) -> anyhow::Result<()> {
+    let mut text = serde_json::to_string_pretty(profile)?;
+    text.push('\n');
+    std::fs::write(path, text).with_context(|| format!("writing {}", path.display()))
+}
+
+/// One count, and the baseline's for it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Row {
+    /// The instruction, or `INSTRUCTION > program` for a program it invoked
+    pub name: String,
+    pub units: u64,
+    pub baseline: Option<u64>,
+}
+
+impl Row {
+    /// Percent more (or, negative, fewer) units than the baseline
+    pub fn change_percent(&self) -> Option<f64> {
+        let baseline = self.baseline.filter(|&baseline| baseline > 0)?;
+        Some((self.units as f64 - baseline as f64) * 100.0 / baseline as f64)
+    }
+
+    /// Whether the units grew more than `tolerance_percent` on the baseline
+    pub fn regressed(&self, tolerance_percent: f64) -> bool {
+        self.change_percent()
+            .is_some_and(|change| change > tolerance_percent)
+    }
+}
+
+/// Each instruction's count, then those of the programs it invoked, next to
+/// `baseline`'s
+pub fn rows(profile: &Profile, baseline: Option<&Profile>) -> Vec<Row> {
+    let mut rows = Vec::new();
+    for (name, units) in profile {
+        let before = baseline.and_then(|baseline| baseline.get(name));
+        rows.push(Row {
+            name: name.clone(),
+            units: units.total,
+            baseline: before.map(|before| before.total),
+        });
+        for (program, &consumed) in &units.cpi {
+            rows.push(Row {
+                name: format!("{name} > {program}"),
+                units: consumed,
+                baseline: before.and_then(|before| before.cpi.get(program).copied()),
+            });
+        }
+    }
+    rows
+}
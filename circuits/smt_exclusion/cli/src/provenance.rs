@@ -0,0 +1,83 @@
+//! Where each blacklisted pubkey came from
+//!
+//! The journal records what changed and when, but not why. The provenance
+//! log sits next to it (`blacklist.journal` → `blacklist.provenance`) and
+//! holds one JSON line per listed pubkey an import read: the source, the
+//! entry in it, and the journal record that added the pubkey, if any. A merge
+//! also logs the pubkeys a source has dropped since. Like the journal, it is
+//! only appended to.
+
+use std::{
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// A source naming a pubkey, or no longer naming it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    /// Base58
+    pub pubkey: String,
+    /// The list, such as `OFAC SDN 03/14/2025`
+    pub source: String,
+    /// The entry in the list, such as `uid 12345`
+    pub reference: String,
+    /// What the list says about the entry
+    pub detail: String,
+    /// False once the source stopped naming the pubkey
+    #[serde(default = "listed")]
+    pub listed: bool,
+    /// The journal record that added (or, unlisted, removed) the pubkey;
+    /// `None` when the tree didn't change
+    pub sequence: Option<u64>,
+    /// Unix time of the import
+    pub recorded: u64,
+}
+
+fn listed() -> bool {
+    true
+}
+
+/// The provenance log beside `journal`
+pub fn log_path(journal: &Path) -> PathBuf {
+    journal.with_extension("provenance")
+}
+
+/// Append `records` to the log at `path`, creating it if needed
+pub fn append(path: &Path, records: &[Provenance]) -> anyhow::Result<()> {
+    let mut lines = String::new();
+    for record in records {
+        lines.push_str(&serde_json::to_string(record)?);
+        lines.push('\n');
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(lines.as_bytes()))
+        .with_context(|| format!("appending to {}", path.display()))
+}
+
+/// The log at `path`, oldest first; empty if there's none
+pub fn read(path: &Path) -> anyhow::Result<Vec<Provenance>> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => {
+            return Err(error).with_context(|| format!("opening {}", path.display()));
+        }
+    };
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let line = line.with_context(|| format!("reading {}", path.display()))?;
+            serde_json::from_str(&line)
+                .with_context(|| format!("{} line {}", path.display(), i + 1))
+        })
+        .collect()
+}
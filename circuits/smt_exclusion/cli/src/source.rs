@@ -0,0 +1,283 @@
+//! Where blacklists come from
+//!
+//! A `ListSource` is one screening vendor's list: a file an operator drops in
+//! place, or an HTTP endpoint the vendor serves. Each fetches the list's raw
+//! contents, parses them into entries, and says when the list last changed,
+//! so a service can pull several vendors into one tree and log which one
+//! named each pubkey (see `provenance`).
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use reqwest::header::LAST_MODIFIED;
+use solana_pubkey::Pubkey;
+
+use crate::{import, ofac, provenance::Provenance};
+
+/// A pubkey a list names, and what the list says about it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceEntry {
+    pub pubkey: Pubkey,
+    /// The entry in the list, such as `uid 12345`; empty when the list
+    /// doesn't identify its entries
+    pub reference: String,
+    /// Why the list names the pubkey; empty when it doesn't say
+    pub detail: String,
+}
+
+impl SourceEntry {
+    fn bare(pubkey: Pubkey) -> Self {
+        Self {
+            pubkey,
+            reference: String::new(),
+            detail: String::new(),
+        }
+    }
+}
+
+/// How a list is written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListFormat {
+    /// Pubkeys in the first column (see `import`)
+    Csv,
+    /// An array of pubkeys
+    Json,
+    /// OFAC's `sdn.xml` (see `ofac`)
+    SdnXml,
+    /// OFAC's `sdn.csv`
+    SdnCsv,
+}
+
+impl ListFormat {
+    /// The format a file name or URL path suggests: `.json` and `.xml` (the
+    /// SDN list) by extension, CSV otherwise
+    pub fn of(location: &str) -> Self {
+        let path = location.split(['?', '#']).next().unwrap_or_default();
+        match Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => Self::Json,
+            Some(extension) if extension.eq_ignore_ascii_case("xml") => Self::SdnXml,
+            _ => Self::Csv,
+        }
+    }
+
+    /// The entries in `raw`
+    pub fn parse(self, raw: &[u8]) -> anyhow::Result<Vec<SourceEntry>> {
+        let text = std::str::from_utf8(raw).context("the list isn't UTF-8")?;
+        let entries = match self {
+            Self::Csv | Self::Json => {
+                let format = if self == Self::Csv {
+                    import::Format::Csv
+                } else {
+                    import::Format::Json
+                };
+                import::parse(text, format)?
+                    .pubkeys
+                    .into_iter()
+                    .map(SourceEntry::bare)
+                    .collect()
+            }
+            Self::SdnXml | Self::SdnCsv => {
+                let list = if self == Self::SdnXml {
+                    ofac::parse_xml(text)?
+                } else {
+                    ofac::parse_csv(text)?
+                };
+                list.entries()
+            }
+        };
+        Ok(entries)
+    }
+}
+
+impl FromStr for ListFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> anyhow::Result<Self> {
+        Ok(match format {
+            "csv" => Self::Csv,
+            "json" => Self::Json,
+            "sdn-xml" => Self::SdnXml,
+            "sdn-csv" => Self::SdnCsv,
+            format => bail!("unknown list format {format} (csv, json, sdn-xml or sdn-csv)"),
+        })
+    }
+}
+
+/// A blacklist to pull pubkeys from
+#[async_trait]
+pub trait ListSource: Send + Sync {
+    /// How provenance records name the source
+    fn name(&self) -> &str;
+
+    /// The list's raw contents
+    async fn fetch(&self) -> anyhow::Result<Vec<u8>>;
+
+    /// The entries in what `fetch` returned
+    fn parse(&self, raw: &[u8]) -> anyhow::Result<Vec<SourceEntry>>;
+
+    /// Unix time the list last changed, if the source says
+    async fn last_updated(&self) -> anyhow::Result<Option<u64>>;
+
+    /// Fetch and parse the list
+    async fn entries(&self) -> anyhow::Result<Vec<SourceEntry>> {
+        let raw = self.fetch().await?;
+        self.parse(&raw)
+            .with_context(|| format!("parsing {}", self.name()))
+    }
+}
+
+/// A list in a local file; it changed when the file was last written
+pub struct FileSource {
+    pub name: String,
+    pub path: PathBuf,
+    pub format: ListFormat,
+}
+
+#[async_trait]
+impl ListSource for FileSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self) -> anyhow::Result<Vec<u8>> {
+        tokio::fs::read(&self.path)
+            .await
+            .with_context(|| format!("reading {}", self.path.display()))
+    }
+
+    fn parse(&self, raw: &[u8]) -> anyhow::Result<Vec<SourceEntry>> {
+        self.format.parse(raw)
+    }
+
+    async fn last_updated(&self) -> anyhow::Result<Option<u64>> {
+        let metadata = tokio::fs::metadata(&self.path)
+            .await
+            .with_context(|| format!("reading {}", self.path.display()))?;
+        Ok(metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_secs()))
+    }
+}
+
+/// A list an HTTP endpoint serves; it changed when its `Last-Modified`
+/// header says
+pub struct HttpSource {
+    pub name: String,
+    pub url: String,
+    pub format: ListFormat,
+    pub client: reqwest::Client,
+}
+
+impl HttpSource {
+    pub fn new(name: impl Into<String>, url: impl Into<String>, format: ListFormat) -> Self {
+        Self {
+            name: name.into(),
+            url: url.into(),
+            format,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ListSource for HttpSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self) -> anyhow::Result<Vec<u8>> {
+        let response = self
+            .client
+            .get(&self.url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .with_context(|| format!("fetching {}", self.url))?;
+        let body = response
+            .bytes()
+            .await
+            .with_context(|| format!("reading {}", self.url))?;
+        Ok(body.to_vec())
+    }
+
+    fn parse(&self, raw: &[u8]) -> anyhow::Result<Vec<SourceEntry>> {
+        self.format.parse(raw)
+    }
+
+    async fn last_updated(&self) -> anyhow::Result<Option<u64>> {
+        let response = self
+            .client
+            .head(&self.url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .with_context(|| format!("fetching {}", self.url))?;
+        Ok(response
+            .headers()
+            .get(LAST_MODIFIED)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| httpdate::parse_http_date(value).ok())
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_secs()))
+    }
+}
+
+/// A source at `location`: an `http(s)://` URL or a file
+pub fn open(name: &str, location: &str, format: ListFormat) -> Box<dyn ListSource> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        Box::new(HttpSource::new(name, location, format))
+    } else {
+        Box::new(FileSource {
+            name: name.to_string(),
+            path: location.into(),
+            format,
+        })
+    }
+}
+
+/// A source from a `NAME=LOCATION` or `NAME:FORMAT=LOCATION` spec, the
+/// format by the location's extension when it isn't given
+pub fn from_spec(spec: &str) -> anyhow::Result<Box<dyn ListSource>> {
+    let (label, location) = spec
+        .split_once('=')
+        .with_context(|| format!("{spec}: expected NAME=LOCATION or NAME:FORMAT=LOCATION"))?;
+    let (name, format) = match label.split_once(':') {
+        Some((name, format)) => (name, format.parse()?),
+        None => (label, ListFormat::of(location)),
+    };
+    anyhow::ensure!(!name.is_empty(), "{spec}: the source has no name");
+    Ok(open(name, location, format))
+}
+
+/// A provenance record for each of `source`'s entries, given the journal
+/// record that added each pubkey an import added
+pub fn provenance(
+    source: &str,
+    entries: &[SourceEntry],
+    added: &BTreeMap<Pubkey, u64>,
+    recorded: u64,
+) -> Vec<Provenance> {
+    entries
+        .iter()
+        .map(|entry| Provenance {
+            pubkey: entry.pubkey.to_string(),
+            source: source.to_string(),
+            reference: entry.reference.clone(),
+            detail: entry.detail.clone(),
+            listed: true,
+            sequence: added.get(&entry.pubkey).copied(),
+            recorded,
+        })
+        .collect()
+}
@@ -0,0 +1,81 @@
+//! Where the state account's root is in the blacklist's journal
+//!
+//! A root on chain that the tree doesn't have is either one the journal had
+//! before its latest records, which haven't been pushed yet, or one the
+//! journal never had: someone else set it. Rewinding appends a record that
+//! undoes the later records, so the tree is back at the chain's root and the
+//! journal still shows what happened.
+
+use serde_json::{json, Value};
+use smt::{default_hash, Op};
+
+use crate::tree::{membership, LocalTree};
+
+/// How the chain's root relates to the journal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Drift {
+    /// The chain holds the tree's root
+    InSync,
+    /// The chain holds the root after record `sequence` (`None`: the empty
+    /// tree), `behind` records before the last
+    Behind {
+        sequence: Option<u64>,
+        behind: usize,
+    },
+    /// The chain holds a root the journal never had
+    Unknown,
+}
+
+impl Drift {
+    /// `drift`, and for a chain that's behind, where, as `--output json`
+    /// gives them
+    pub fn fields(&self) -> Vec<(&'static str, Value)> {
+        match self {
+            Self::InSync => vec![("drift", json!("inSync"))],
+            Self::Behind { sequence, behind } => vec![
+                ("drift", json!("behind")),
+                ("sequence", json!(sequence)),
+                ("behind", json!(behind)),
+            ],
+            Self::Unknown => vec![("drift", json!("unknown"))],
+        }
+    }
+}
+
+/// Where `chain_root` is in `tree`'s journal, taking the latest record with it
+pub fn compare(tree: &LocalTree, chain_root: &[u8; 32]) -> Drift {
+    let entries = tree.entries();
+    if tree.tree().root() == *chain_root {
+        return Drift::InSync;
+    }
+    if let Some(position) = entries.iter().rposition(|entry| entry.root == *chain_root) {
+        return Drift::Behind {
+            sequence: Some(entries[position].sequence),
+            behind: entries.len() - 1 - position,
+        };
+    }
+    if default_hash(tree.tree().depth()) == *chain_root {
+        return Drift::Behind {
+            sequence: None,
+            behind: entries.len(),
+        };
+    }
+    Drift::Unknown
+}
+
+/// The ops that take `tree` back to where it was `behind` records ago
+pub fn rewind_ops(tree: &LocalTree, behind: usize) -> Vec<Op> {
+    let entries = tree.entries();
+    let then = membership(&entries[..entries.len() - behind.min(entries.len())]);
+    membership(entries)
+        .into_iter()
+        .filter_map(|(pubkey, now)| {
+            let before = then.get(&pubkey).copied().unwrap_or(false);
+            match (before, now) {
+                (true, false) => Some(Op::Insert(pubkey)),
+                (false, true) => Some(Op::Delete(pubkey)),
+                _ => None,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,126 @@
+//! The blacklist tree, kept as a journal on disk
+//!
+//! The journal (see `smt::journal`) is the only file: opening it replays every
+//! batch and checks each root, and each change appends a record. So the tree
+//! on disk can't be half-written, and its history says when each pubkey was
+//! added or removed.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{File, OpenOptions},
+    io::BufReader,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use smt::{
+    journal::{self, Journal, JournalEntry, Replay},
+    Op, SparseMerkleTree,
+};
+
+/// A tree and the journal it was replayed from
+pub struct LocalTree {
+    path: PathBuf,
+    replay: Replay,
+}
+
+impl LocalTree {
+    /// Start an empty tree of `depth` levels at `path`; fails if a file is
+    /// already there
+    pub fn create(path: impl Into<PathBuf>, depth: usize) -> anyhow::Result<Self> {
+        let path = path.into();
+        anyhow::ensure!(
+            smt::SUPPORTED_DEPTHS.contains(&depth),
+            "unsupported tree depth {depth}"
+        );
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| format!("creating {}", path.display()))?;
+        Journal::create(file, depth).with_context(|| format!("writing {}", path.display()))?;
+        Self::open(path)
+    }
+
+    /// Replay the journal at `path`
+    pub fn open(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let file = File::open(&path).with_context(|| {
+            format!(
+                "opening {} (run `smt-cli init` to start a tree)",
+                path.display()
+            )
+        })?;
+        let replay = journal::replay(BufReader::new(file))
+            .with_context(|| format!("replaying {}", path.display()))?;
+        Ok(Self { path, replay })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn tree(&self) -> &SparseMerkleTree {
+        &self.replay.tree
+    }
+
+    /// The journal's records, oldest first
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.replay.entries
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// The blacklisted pubkeys, as the journal names them
+    pub fn members(&self) -> BTreeSet<[u8; 32]> {
+        membership(self.entries())
+            .into_iter()
+            .filter_map(|(pubkey, member)| member.then_some(pubkey))
+            .collect()
+    }
+
+    /// Apply `ops` as one journaled batch at unix time `timestamp`, returning
+    /// how many leaves changed
+    pub fn apply_at(&mut self, ops: &[Op], timestamp: u64) -> anyhow::Result<usize> {
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("opening {}", self.path.display()))?;
+        let mut journal = Journal::resume(file, &self.replay);
+        let mut tree = self.replay.tree.clone();
+        let changed = tree
+            .apply_journaled(&mut journal, timestamp, ops)
+            .with_context(|| format!("appending to {}", self.path.display()))?;
+        // The replay's chain hash is the journal's to advance
+        *self = Self::open(self.path.clone())?;
+        Ok(changed)
+    }
+
+    /// Apply `ops` as one journaled batch, now
+    pub fn apply(&mut self, ops: &[Op]) -> anyhow::Result<usize> {
+        self.apply_at(ops, unix_time())
+    }
+}
+
+/// Seconds since 1970, as the journal stamps records
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("the clock is after 1970")
+        .as_secs()
+}
+
+/// Whether each pubkey `entries` name is blacklisted after them
+pub(crate) fn membership(entries: &[JournalEntry]) -> BTreeMap<[u8; 32], bool> {
+    let mut members = BTreeMap::new();
+    for op in entries.iter().flat_map(|entry| &entry.ops) {
+        match op {
+            Op::Insert(pubkey) => members.insert(*pubkey, true),
+            Op::Delete(pubkey) => members.insert(*pubkey, false),
+        };
+    }
+    members
+}
@@ -0,0 +1,45 @@
+//! Checking a proof off chain, as `smt-cli verify-proof` does
+//!
+//! A transfer fails `SmtRootMismatch` when its proof is against a root the
+//! state account no longer holds, and `PubkeyHashMismatch` when it's for
+//! another pubkey. Both are public inputs of the proof, so reading them out
+//! of the witness, next to the journal's roots, says which it was without
+//! sending anything. The pairings are checked with the verifying key
+//! `sunspot deploy` builds the verifier program from.
+
+use anyhow::Context;
+use exclusion_client::{groth16::VerifyingKey, witness::ExclusionWitness};
+use exclusion_common::{pubkey_to_index, ExclusionError, Proof, PublicWitness};
+use solana_pubkey::Pubkey;
+
+/// What a proof and its witness say
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProofReport {
+    /// The public inputs the proof binds
+    pub inputs: ExclusionWitness,
+    /// `Err(ZkVerificationFailed)` when the pairings don't hold
+    pub verified: Result<(), ExclusionError>,
+}
+
+impl ProofReport {
+    /// Whether the proof is for `pubkey`, under the Poseidon hash scheme
+    pub fn binds_pubkey(&self, pubkey: &Pubkey) -> bool {
+        self.inputs.pubkey_hash == pubkey_to_index(&pubkey.to_bytes())
+    }
+}
+
+/// Read `proof` and `witness` (proof.bin and witness.bin) and check them
+/// against `vk`
+pub fn check(vk: &VerifyingKey, proof: &[u8], witness: &[u8]) -> anyhow::Result<ProofReport> {
+    let proof = Proof::try_from(proof)
+        .map_err(|error| anyhow::anyhow!("{error}"))
+        .context("not a sunspot proof")?;
+    let inputs = ExclusionWitness::from_gnark_bytes(witness)
+        .map_err(|error| anyhow::anyhow!("{error}"))
+        .context("not the exclusion circuit's public witness")?;
+    let witness = PublicWitness::try_from(witness).expect("parsed above");
+    Ok(ProofReport {
+        inputs,
+        verified: vk.verify(&proof, &witness),
+    })
+}
@@ -0,0 +1,64 @@
+//! Root changes, as `smt-cli watch` reports them
+//!
+//! Each `SET_SMT_ROOT` (or other root update) bumps the state account's
+//! `root_seq`, so a notification is a root change when the sequence number
+//! or the root differs from the last one seen. Other changes to the account,
+//! such as its flags, aren't reported.
+
+use exclusion_client::state::ExclusionState;
+use serde_json::{json, Value};
+use smt::inputs::field_hex;
+use solana_pubkey::Pubkey;
+
+/// A state account's new root
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RootChange {
+    pub state: Pubkey,
+    pub slot: u64,
+    pub root_seq: u64,
+    pub smt_root: [u8; 32],
+    /// Unix time the root expires at; 0 for never
+    pub root_valid_until: i64,
+}
+
+impl RootChange {
+    /// The body posted to a webhook
+    pub fn to_json(&self) -> Value {
+        json!({
+            "state": self.state.to_string(),
+            "slot": self.slot,
+            "rootSeq": self.root_seq,
+            "smtRoot": field_hex(&self.smt_root),
+            "rootValidUntil": self.root_valid_until,
+        })
+    }
+}
+
+impl std::fmt::Display for RootChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "slot {}: root {} is {}",
+            self.slot,
+            self.root_seq,
+            field_hex(&self.smt_root)
+        )
+    }
+}
+
+/// The root change from `previous` to `current`, if there was one
+pub fn root_change(
+    previous: Option<&ExclusionState>,
+    current: &ExclusionState,
+) -> Option<RootChange> {
+    let changed = previous.is_none_or(|previous| {
+        previous.smt_root != current.smt_root || previous.state.root_seq != current.state.root_seq
+    });
+    changed.then_some(RootChange {
+        state: current.address,
+        slot: current.slot,
+        root_seq: current.state.root_seq,
+        smt_root: current.smt_root,
+        root_valid_until: current.state.root_valid_until,
+    })
+}
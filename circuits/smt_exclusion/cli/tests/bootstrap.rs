@@ -0,0 +1,40 @@
+//! Checks the pieces of `bootstrap` that don't need a cluster.
+
+use smt_cli::{
+    bootstrap::{sample_blacklist, sample_csv, Cluster, Workspace},
+    import::{self, Format},
+    keypair::{read_keypair, write_keypair},
+};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+#[test]
+fn clusters_need_a_faucet() {
+    assert_eq!("devnet".parse::<Cluster>().unwrap(), Cluster::Devnet);
+    assert_eq!(
+        "localnet".parse::<Cluster>().unwrap().rpc_url(),
+        "http://127.0.0.1:8899"
+    );
+    let error = "mainnet-beta".parse::<Cluster>().unwrap_err();
+    assert!(error.to_string().contains("no faucet"), "{error}");
+}
+
+#[test]
+fn writes_a_workspace_the_other_commands_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let workspace = Workspace::new(dir.path());
+
+    let pubkeys = sample_blacklist(5);
+    let list = import::parse(&sample_csv(&pubkeys), Format::Csv).unwrap();
+    assert_eq!(list.pubkeys, pubkeys);
+    assert_eq!(list.duplicates, 0);
+
+    let admin = Keypair::new();
+    write_keypair(&admin, &workspace.keypair()).unwrap();
+    let read = read_keypair(&workspace.keypair().to_string_lossy()).unwrap();
+    assert_eq!(read.pubkey(), admin.pubkey());
+
+    let flags = workspace.flags("http://127.0.0.1:8899", &admin.pubkey());
+    assert!(flags.contains(&format!("--keypair {}", workspace.keypair().display())));
+    assert!(flags.contains(&format!("--journal {}", workspace.journal().display())));
+}
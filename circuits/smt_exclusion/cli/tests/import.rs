@@ -0,0 +1,70 @@
+//! Checks lists are parsed, validated and deduplicated, and what importing
+//! them changes.
+
+use smt::Op;
+use smt_cli::{
+    import::{diff, parse, Format},
+    tree::LocalTree,
+};
+use solana_pubkey::Pubkey;
+
+#[test]
+fn parses_csv_and_json_lists() {
+    let [a, b, c] = [(); 3].map(|_| Pubkey::new_unique());
+    let csv = format!(
+        "# exported 2026-10-01\naddress,reason\n{a},sanctioned\n\n\"{b}\",\n{a},again\n{c}\n"
+    );
+    let list = parse(&csv, Format::Csv).unwrap();
+    assert_eq!(list.pubkeys, [a, b, c]);
+    assert_eq!(list.duplicates, 1);
+
+    let json = format!(r#"["{c}", "{a}", "{c}"]"#);
+    let list = parse(&json, Format::Json).unwrap();
+    assert_eq!(list.pubkeys, [c, a]);
+    assert_eq!(list.duplicates, 1);
+    assert_eq!(Format::of("list.JSON".as_ref()), Format::Json);
+    assert_eq!(Format::of("list.txt".as_ref()), Format::Csv);
+}
+
+#[test]
+fn rejects_lists_with_invalid_entries() {
+    let a = Pubkey::new_unique();
+    let csv = format!("{a}\nnot-a-pubkey\n{a}0\n");
+    let error = format!("{:#}", parse(&csv, Format::Csv).unwrap_err());
+    assert!(error.starts_with("2 entries aren't pubkeys"), "{error}");
+    assert!(error.contains("line 2: \"not-a-pubkey\""), "{error}");
+    assert!(error.contains("line 3:"), "{error}");
+
+    // A first cell long enough to be a mistyped pubkey isn't a header
+    let csv = format!("0{}\n{a}\n", &a.to_string()[1..]);
+    assert!(parse(&csv, Format::Csv).is_err());
+
+    let error = format!("{:#}", parse(r#"["x", 7]"#, Format::Json).unwrap_err());
+    assert!(error.contains("entry 0: \"\\\"x\\\"\""), "{error}");
+    assert!(error.contains("entry 1: \"7\""), "{error}");
+    assert!(parse("{}", Format::Json).is_err());
+}
+
+#[test]
+fn diffs_against_the_tree() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut tree = LocalTree::create(dir.path().join("j"), 32).unwrap();
+    let [a, b, c] = [(); 3].map(|_| Pubkey::new_unique());
+    tree.apply_at(&[Op::Insert(a.to_bytes()), Op::Insert(b.to_bytes())], 1)
+        .unwrap();
+
+    let list = parse(&format!("{b}\n{c}\n"), Format::Csv).unwrap();
+    let merged = diff(&tree, &list, false);
+    assert_eq!(merged.ops, [Op::Insert(c.to_bytes())]);
+    assert_eq!((merged.added, merged.unchanged, merged.removed), (1, 1, 0));
+
+    let replaced = diff(&tree, &list, true);
+    assert_eq!(
+        replaced.ops,
+        [Op::Insert(c.to_bytes()), Op::Delete(a.to_bytes())]
+    );
+    assert_eq!(replaced.removed, 1);
+    tree.apply_at(&replaced.ops, 2).unwrap();
+    assert_eq!(tree.members(), [b.to_bytes(), c.to_bytes()].into());
+    assert!(diff(&tree, &list, true).ops.is_empty());
+}
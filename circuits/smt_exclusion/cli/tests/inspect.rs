@@ -0,0 +1,102 @@
+//! Checks accounts are told apart by discriminator and their fields decoded.
+
+use exclusion_common::{
+    instruction::METADATA_SIZE,
+    state::{
+        ATTESTATION_DISCRIMINATOR, MAX_APPROVED_CALLERS, STATE_DISCRIMINATOR, STATE_SIZE,
+        TREASURY_ACCOUNT_DISCRIMINATOR,
+    },
+    State,
+};
+use smt::inputs::field_hex;
+use smt_cli::inspect::{inspect, Inspection};
+use solana_pubkey::Pubkey;
+
+fn field<'a>(inspection: &'a Inspection, name: &str) -> &'a str {
+    &inspection
+        .fields
+        .iter()
+        .find(|(field, _)| *field == name)
+        .unwrap_or_else(|| panic!("no {name} field"))
+        .1
+}
+
+#[test]
+fn decodes_state_accounts() {
+    let admin = Pubkey::new_unique();
+    let caller = Pubkey::new_unique();
+    let mut approved_callers = [[0; 32]; MAX_APPROVED_CALLERS];
+    approved_callers[0] = caller.to_bytes();
+    let state = State {
+        admin: admin.to_bytes(),
+        smt_root: [1; 32],
+        root_valid_until: 0,
+        transfer_flags: 0,
+        daily_limit: 0,
+        fee_bps: 25,
+        root_seq: 3,
+        approved_callers,
+        approved_caller_count: 1,
+        bump: 255,
+        treasury_bump: 255,
+        hash_scheme: 0,
+        verifier_flags: 0,
+        metadata: [0; METADATA_SIZE],
+        challenged: false,
+        bond_bump: 255,
+        bond_amount: 0,
+        bond_withdrawal_amount: 0,
+        bond_withdrawal_at: 0,
+        config: [0; 32],
+        tree_kind: 0,
+        tree_depth: 254,
+    };
+    let mut data = vec![0; STATE_SIZE];
+    state.pack(&mut data);
+    let inspection = inspect(&data).unwrap();
+    assert_eq!(inspection.kind, "State account");
+    assert_eq!(
+        field(&inspection, "discriminator"),
+        "736d745f726f6f74 (\"smt_root\")"
+    );
+    assert_eq!(field(&inspection, "admin"), admin.to_string());
+    assert_eq!(field(&inspection, "smt_root"), field_hex(&[1; 32]));
+    assert_eq!(field(&inspection, "root_seq"), "3");
+    assert_eq!(field(&inspection, "root_valid_until"), "never");
+    assert_eq!(field(&inspection, "tree"), "sparse, depth 254");
+    assert_eq!(field(&inspection, "approved_callers"), caller.to_string());
+    assert!(inspection
+        .to_string()
+        .starts_with("State account\n  discriminator  "));
+
+    // From before state accounts carried a version
+    let mut old = STATE_DISCRIMINATOR.to_vec();
+    old.resize(200, 0);
+    let inspection = inspect(&old).unwrap();
+    assert_eq!(
+        field(&inspection, "layout"),
+        "v0; run MIGRATE_STATE to decode it"
+    );
+}
+
+#[test]
+fn decodes_other_accounts() {
+    let subject = Pubkey::new_unique();
+    let state = Pubkey::new_unique();
+    let mut data = ATTESTATION_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(subject.as_ref());
+    data.extend_from_slice(state.as_ref());
+    data.extend_from_slice(&[2; 32]);
+    data.extend_from_slice(&42u64.to_le_bytes());
+    let inspection = inspect(&data).unwrap();
+    assert_eq!(inspection.kind, "Attestation");
+    assert_eq!(field(&inspection, "subject"), subject.to_string());
+    assert_eq!(field(&inspection, "state"), state.to_string());
+    assert_eq!(field(&inspection, "slot"), "42");
+    assert!(inspect(&data[..50]).is_err(), "a truncated attestation");
+
+    let treasury = inspect(&TREASURY_ACCOUNT_DISCRIMINATOR).unwrap();
+    assert_eq!(treasury.kind, "Treasury (fees are its lamports)");
+    assert!(inspect(b"whatever").is_err());
+    assert!(inspect(&[1, 2]).is_err());
+}
@@ -0,0 +1,27 @@
+//! Checks `--keypair` tells Ledgers from keypair files as the Solana CLI does.
+
+use smt_cli::keypair::ledger_path;
+
+#[test]
+fn names_ledger_keys() {
+    assert_eq!(ledger_path("~/.config/solana/id.json").unwrap(), None);
+    assert_eq!(
+        ledger_path("usb://ledger").unwrap().unwrap().to_string(),
+        "m/44'/501'"
+    );
+    assert_eq!(
+        ledger_path("usb://ledger?key=1/0")
+            .unwrap()
+            .unwrap()
+            .to_string(),
+        "m/44'/501'/1'/0'"
+    );
+    assert_eq!(
+        ledger_path("usb://ledger/BsNsvfXqQTtJnagwFWdBS7FBXgnsK8VZ5CmuznN85swK?key=2")
+            .unwrap()
+            .unwrap()
+            .account,
+        Some(2)
+    );
+    assert!(ledger_path("usb://ledger?key=zero").is_err());
+}
@@ -0,0 +1,118 @@
+//! Checks merging several sources' lists, the pubkeys sources drop, and
+//! `why_blacklisted`'s account of a pubkey.
+
+use std::collections::BTreeMap;
+
+use smt_cli::{
+    import,
+    merge::{merge, records, why_blacklisted, Flag, Pulled},
+    source::{self, SourceEntry},
+    tree::LocalTree,
+};
+use solana_pubkey::Pubkey;
+
+fn pulled(name: &str, pubkeys: &[Pubkey]) -> Pulled {
+    Pulled {
+        name: name.to_string(),
+        last_updated: None,
+        entries: pubkeys
+            .iter()
+            .map(|pubkey| SourceEntry {
+                pubkey: *pubkey,
+                reference: format!("{name}-ref"),
+                detail: format!("{name} says so"),
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn merges_sources_and_logs_who_flagged_what() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut tree = LocalTree::create(dir.path().join("j"), 32).unwrap();
+    let [a, b, c] = [(); 3].map(|_| Pubkey::new_unique());
+    let mut log = Vec::new();
+
+    // Both vendors name a; only one names b or c
+    let first = [pulled("vendor-a", &[a, b]), pulled("vendor-b", &[a, c])];
+    let merged = merge(&tree, &log, &first, false);
+    assert_eq!(merged.flagged_by[&a], ["vendor-a", "vendor-b"]);
+    assert_eq!(merged.conflicts(2), 2);
+    assert!(merged.dropped.is_empty());
+    assert_eq!(merged.diff.added, 3);
+    let changed = import::apply(&mut tree, &merged.diff, 2).unwrap();
+    assert_eq!(changed.len(), 3);
+    let b_added = changed[&b];
+    log.extend(records(&merged, &first, &changed, 100));
+    assert_eq!(log.len(), 4);
+
+    // vendor-a drops b and a, which vendor-b still names; replacing takes b off
+    let second = [pulled("vendor-a", &[]), pulled("vendor-b", &[a, c])];
+    let merged = merge(&tree, &log, &second, true);
+    let mut dropped = merged.dropped.clone();
+    dropped.sort();
+    let mut expected = vec![("vendor-a".to_string(), a), ("vendor-a".to_string(), b)];
+    expected.sort();
+    assert_eq!(dropped, expected);
+    assert_eq!(
+        (
+            merged.diff.added,
+            merged.diff.unchanged,
+            merged.diff.removed
+        ),
+        (0, 2, 1)
+    );
+    let changed = import::apply(&mut tree, &merged.diff, 10).unwrap();
+    assert_eq!(changed, BTreeMap::from([(b, 2)]));
+    log.extend(records(&merged, &second, &changed, 200));
+
+    let why = why_blacklisted(&tree, &log, &a);
+    assert!(why.blacklisted);
+    assert_eq!(why.history.len(), 1);
+    assert!(why.history[0].2, "a was added");
+    assert_eq!(
+        why.flags,
+        [
+            Flag {
+                source: "vendor-a".to_string(),
+                listed: false,
+                reference: "vendor-a-ref".to_string(),
+                detail: "vendor-a says so".to_string(),
+                first_seen: 100,
+                last_seen: 200,
+            },
+            Flag {
+                source: "vendor-b".to_string(),
+                listed: true,
+                reference: "vendor-b-ref".to_string(),
+                detail: "vendor-b says so".to_string(),
+                first_seen: 100,
+                last_seen: 200,
+            },
+        ]
+    );
+
+    let why = why_blacklisted(&tree, &log, &b);
+    assert!(!why.blacklisted);
+    assert_eq!(
+        why.history
+            .iter()
+            .map(|(sequence, _, added)| (*sequence, *added))
+            .collect::<Vec<_>>(),
+        [(b_added, true), (2, false)]
+    );
+    assert!(!why.flags[0].listed);
+
+    let stranger = why_blacklisted(&tree, &log, &Pubkey::new_unique());
+    assert!(!stranger.blacklisted && stranger.history.is_empty() && stranger.flags.is_empty());
+}
+
+#[test]
+fn reads_source_specs() {
+    let source = source::from_spec("ofac:sdn-csv=https://example.com/sdn.csv").unwrap();
+    assert_eq!(source.name(), "ofac");
+    assert!(source::from_spec("vendor=list.json").is_ok());
+    assert!(source::from_spec("list.json").is_err());
+    assert!(source::from_spec("vendor:xls=list.json").is_err());
+    assert!(source::from_spec("=list.json").is_err());
+}
@@ -0,0 +1,150 @@
+//! Checks SOL addresses are read from both forms of the SDN list, and the
+//! provenance they're logged with.
+
+use std::collections::BTreeMap;
+
+use smt_cli::{
+    ofac::{parse_csv, parse_file, parse_xml, SdnAddress},
+    provenance::{self, Provenance},
+    source::provenance as source_provenance,
+};
+use solana_pubkey::Pubkey;
+
+fn sdn_xml(a: &Pubkey, b: &Pubkey) -> String {
+    format!(
+        r#"<?xml version="1.0" standalone="yes"?>
+<sdnList xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns="http://tempuri.org/sdnList.xsd">
+  <publshInformation>
+    <Publish_Date>03/14/2025</Publish_Date>
+    <Record_Count>3</Record_Count>
+  </publshInformation>
+  <sdnEntry>
+    <uid>100</uid>
+    <lastName>BANK OF NOWHERE</lastName>
+    <sdnType>Entity</sdnType>
+    <programList>
+      <program>SDGT</program>
+    </programList>
+  </sdnEntry>
+  <sdnEntry>
+    <uid>200</uid>
+    <firstName>Ivan</firstName>
+    <lastName>PETROV &amp; SONS</lastName>
+    <sdnType>Individual</sdnType>
+    <programList>
+      <program>CYBER2</program>
+      <program>RUSSIA-EO14024</program>
+    </programList>
+    <idList>
+      <id>
+        <uid>201</uid>
+        <idType>Digital Currency Address - ETH</idType>
+        <idNumber>0x098b716b8aaf21512996dc57eb0615e2383e2f96</idNumber>
+      </id>
+      <id>
+        <uid>202</uid>
+        <idType>Digital Currency Address - SOL</idType>
+        <idNumber>{a}</idNumber>
+      </id>
+      <id>
+        <uid>203</uid>
+        <idType>Digital Currency Address - SOL</idType>
+        <idNumber>{b}</idNumber>
+      </id>
+    </idList>
+  </sdnEntry>
+  <sdnEntry>
+    <uid>300</uid>
+    <lastName>TYPO LTD</lastName>
+    <sdnType>Entity</sdnType>
+    <programList>
+      <program>CYBER2</program>
+    </programList>
+    <idList>
+      <id>
+        <uid>301</uid>
+        <idType>Digital Currency Address - SOL</idType>
+        <idNumber>0OIl</idNumber>
+      </id>
+    </idList>
+  </sdnEntry>
+</sdnList>
+"#
+    )
+}
+
+#[test]
+fn reads_sol_addresses_from_sdn_xml() {
+    let [a, b] = [(); 2].map(|_| Pubkey::new_unique());
+    let list = parse_xml(&sdn_xml(&a, &b)).unwrap();
+    assert_eq!(list.published.as_deref(), Some("03/14/2025"));
+    assert_eq!(list.source(), "OFAC SDN 03/14/2025");
+    let entry = |pubkey| SdnAddress {
+        pubkey,
+        uid: 200,
+        name: "Ivan PETROV & SONS".to_string(),
+        programs: vec!["CYBER2".to_string(), "RUSSIA-EO14024".to_string()],
+    };
+    assert_eq!(list.addresses, [entry(a), entry(b)]);
+    assert_eq!(list.invalid, [(300, "0OIl".to_string())]);
+    assert!(parse_xml("<html></html>").is_err());
+}
+
+#[test]
+fn reads_sol_addresses_from_sdn_csv() {
+    let [a, b] = [(); 2].map(|_| Pubkey::new_unique());
+    let csv = format!(
+        "100,\"BANK OF NOWHERE\",-0- ,\"SDGT\",-0- ,-0- ,-0- ,-0- ,-0- ,-0- ,-0- ,\"Website www.example.com.\"\n\
+         200,\"PETROV, Ivan\",\"individual\",\"CYBER2] [RUSSIA-EO14024\",-0- ,-0- ,-0- ,-0- ,-0- ,-0- ,-0- ,\"DOB 1980; Digital Currency Address - XBT 1AjZPMsnmpdK2Rv9KQNfMurTXinscVro9V; Digital Currency Address - SOL {a}; alt. Digital Currency Address - SOL {b}.\"\n\
+         \u{1a}\n"
+    );
+    let list = parse_csv(&csv).unwrap();
+    assert_eq!(list.published, None);
+    let pubkeys: Vec<Pubkey> = list
+        .addresses
+        .iter()
+        .map(|address| address.pubkey)
+        .collect();
+    assert_eq!(pubkeys, [a, b]);
+    assert_eq!(list.addresses[0].uid, 200);
+    assert_eq!(list.addresses[0].name, "PETROV, Ivan");
+    assert_eq!(list.addresses[0].programs, ["CYBER2", "RUSSIA-EO14024"]);
+    assert!(list.invalid.is_empty());
+
+    assert!(parse_csv("7,\"TOO FEW\",-0- \n").is_err());
+    assert!(parse_file("sdn.txt".as_ref(), &csv).is_err());
+    assert_eq!(parse_file("SDN.CSV".as_ref(), &csv).unwrap(), list);
+}
+
+#[test]
+fn logs_provenance_beside_the_journal() {
+    let [a, b] = [(); 2].map(|_| Pubkey::new_unique());
+    let list = parse_xml(&sdn_xml(&a, &b)).unwrap();
+    let records = source_provenance(
+        &list.source(),
+        &list.entries(),
+        &BTreeMap::from([(a, 4)]),
+        1_700_000_000,
+    );
+    assert_eq!(
+        records[0],
+        Provenance {
+            pubkey: a.to_string(),
+            source: "OFAC SDN 03/14/2025".to_string(),
+            reference: "uid 200".to_string(),
+            detail: "Ivan PETROV & SONS [CYBER2] [RUSSIA-EO14024]".to_string(),
+            listed: true,
+            sequence: Some(4),
+            recorded: 1_700_000_000,
+        }
+    );
+    assert_eq!(records[1].sequence, None, "b was already blacklisted");
+
+    let dir = tempfile::tempdir().unwrap();
+    let log = provenance::log_path(&dir.path().join("blacklist.journal"));
+    assert_eq!(log, dir.path().join("blacklist.provenance"));
+    assert!(provenance::read(&log).unwrap().is_empty());
+    provenance::append(&log, &records[..1]).unwrap();
+    provenance::append(&log, &records[1..]).unwrap();
+    assert_eq!(provenance::read(&log).unwrap(), records);
+}
@@ -0,0 +1,53 @@
+//! Checks what `--output json` gathers, and how failures are reported.
+
+use exclusion_client::send::SendError;
+use exclusion_common::ExclusionError;
+use serde_json::json;
+use smt_cli::output::{Output, OutputFormat};
+
+#[test]
+fn gathers_values_into_one_object() {
+    let mut out = Output::new("json".parse().unwrap());
+    out.value("root", "Root", "0x01");
+    out.line("Added 2", [("added", json!(2)), ("removed", json!(0))]);
+    out.push("signatures", "Signature", "a");
+    out.push("signatures", "Signature", "b");
+    out.note("only for people");
+    out.set("drift", "inSync");
+    assert_eq!(
+        serde_json::Value::Object(out.json().unwrap().clone()),
+        json!({
+            "root": "0x01",
+            "added": 2,
+            "removed": 0,
+            "signatures": ["a", "b"],
+            "drift": "inSync",
+        })
+    );
+
+    let mut text = Output::new(OutputFormat::Text);
+    text.value("root", "Root", "0x01");
+    assert!(text.json().is_none());
+    assert!("yaml".parse::<OutputFormat>().is_err());
+}
+
+#[test]
+fn reports_what_the_program_rejected() {
+    let mut out = Output::new(OutputFormat::Json);
+    out.push("signatures", "Signature", "a");
+    let error = anyhow::Error::new(SendError::Program {
+        instruction: 1,
+        error: ExclusionError::SmtRootMismatch,
+        logs: vec!["Program log: root mismatch".to_string()],
+    })
+    .context("sending TRANSFER_SOL");
+    out.error(&error);
+    let object = out.json().unwrap();
+    assert!(object["error"]
+        .as_str()
+        .unwrap()
+        .starts_with("sending TRANSFER_SOL: instruction 1: "));
+    assert_eq!(object["programError"], "SmtRootMismatch");
+    assert_eq!(object["logs"], json!(["Program log: root mismatch"]));
+    assert_eq!(object["signatures"], json!(["a"]));
+}
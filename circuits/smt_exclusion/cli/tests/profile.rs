@@ -0,0 +1,86 @@
+//! Checks compute units are read from simulation logs and compared with a
+//! baseline.
+
+use smt_cli::profile::{self, Profile, Units};
+use solana_pubkey::Pubkey;
+
+#[test]
+fn reads_units_and_cpis_from_logs() {
+    let program = Pubkey::new_unique();
+    let verifier = Pubkey::new_unique();
+    let budget = "ComputeBudget111111111111111111111111111111";
+    let lines = [
+        format!("Program {budget} invoke [1]"),
+        format!("Program {budget} success"),
+        format!("Program {program} invoke [1]"),
+        "Program log: Instruction: TRANSFER_SOL".to_string(),
+        format!("Program {verifier} invoke [2]"),
+        format!("Program {verifier} consumed 180000 of 1390000 compute units"),
+        format!("Program {verifier} success"),
+        "Program 11111111111111111111111111111111 invoke [2]".to_string(),
+        "Program 11111111111111111111111111111111 success".to_string(),
+        format!("Program {program} consumed 195000 of 1400000 compute units"),
+        format!("Program {program} success"),
+    ];
+    let units = profile::units(&lines, &program).unwrap();
+    assert_eq!(units.total, 195_000);
+    assert_eq!(
+        units.cpi.into_iter().collect::<Vec<_>>(),
+        [(verifier.to_string(), 180_000)]
+    );
+    assert_eq!(profile::units(&lines, &Pubkey::new_unique()), None);
+}
+
+#[test]
+fn flags_regressions_against_the_baseline() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cu-baseline.json");
+    assert_eq!(profile::read_baseline(&path).unwrap(), None);
+
+    let units = |total, cpi: &[(&str, u64)]| Units {
+        total,
+        cpi: cpi.iter().map(|(p, u)| (p.to_string(), *u)).collect(),
+    };
+    let baseline: Profile = [
+        ("INITIALIZE".to_string(), units(5_000, &[])),
+        (
+            "TRANSFER_SOL".to_string(),
+            units(200_000, &[("verifier", 180_000)]),
+        ),
+    ]
+    .into();
+    profile::write_baseline(&path, &baseline).unwrap();
+    assert_eq!(
+        profile::read_baseline(&path).unwrap(),
+        Some(baseline.clone())
+    );
+
+    let now: Profile = [
+        ("INITIALIZE".to_string(), units(5_050, &[])),
+        ("SET_SMT_ROOT".to_string(), units(3_000, &[])),
+        (
+            "TRANSFER_SOL".to_string(),
+            units(210_000, &[("verifier", 190_000)]),
+        ),
+    ]
+    .into();
+    let rows = profile::rows(&now, Some(&baseline));
+    let names: Vec<&str> = rows.iter().map(|row| row.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "INITIALIZE",
+            "SET_SMT_ROOT",
+            "TRANSFER_SOL",
+            "TRANSFER_SOL > verifier"
+        ]
+    );
+    let regressed: Vec<&str> = rows
+        .iter()
+        .filter(|row| row.regressed(2.0))
+        .map(|row| row.name.as_str())
+        .collect();
+    assert_eq!(regressed, ["TRANSFER_SOL", "TRANSFER_SOL > verifier"]);
+    assert_eq!(rows[0].change_percent(), Some(1.0));
+    assert_eq!(rows[1].baseline, None);
+}
@@ -0,0 +1,89 @@
+//! Checks lists are pulled from files and HTTP endpoints, with when they
+//! last changed.
+
+use smt_cli::source::{self, FileSource, HttpSource, ListFormat, ListSource, SourceEntry};
+use solana_pubkey::Pubkey;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+#[test]
+fn picks_formats_by_extension() {
+    for (location, format) in [
+        ("list.csv", ListFormat::Csv),
+        (
+            "https://vendor.example.com/list.json?key=1",
+            ListFormat::Json,
+        ),
+        ("sdn.XML", ListFormat::SdnXml),
+        ("https://vendor.example.com/latest", ListFormat::Csv),
+    ] {
+        assert_eq!(ListFormat::of(location), format, "{location}");
+    }
+    assert_eq!("sdn-csv".parse::<ListFormat>().unwrap(), ListFormat::SdnCsv);
+    assert!("xls".parse::<ListFormat>().is_err());
+}
+
+#[tokio::test]
+async fn reads_a_file_source() {
+    let [a, b] = [(); 2].map(|_| Pubkey::new_unique());
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("vendor.json");
+    std::fs::write(&path, format!(r#"["{a}", "{b}", "{a}"]"#)).unwrap();
+    let source = FileSource {
+        name: "vendor".to_string(),
+        path,
+        format: ListFormat::Json,
+    };
+    let pubkeys: Vec<Pubkey> = source
+        .entries()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.pubkey)
+        .collect();
+    assert_eq!(pubkeys, [a, b]);
+    assert!(source.last_updated().await.unwrap().unwrap() > 1_600_000_000);
+
+    let missing = source::open("gone", "/nonexistent/list.csv", ListFormat::Csv);
+    assert_eq!(missing.name(), "gone");
+    assert!(missing.entries().await.is_err());
+}
+
+#[tokio::test]
+async fn reads_an_http_source() {
+    let a = Pubkey::new_unique();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/list.csv", listener.local_addr().unwrap());
+    let body = format!("address\n{a}\n");
+    let server = tokio::spawn(async move {
+        // A HEAD for Last-Modified, then the GET
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(stream.read_u8().await.unwrap());
+            }
+            let head = request.starts_with(b"HEAD");
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nLast-Modified: Tue, 14 Nov 2023 22:13:20 GMT\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                if head { "" } else { &body }
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+
+    let source = HttpSource::new("vendor", &url, ListFormat::of(&url));
+    assert_eq!(source.last_updated().await.unwrap(), Some(1_700_000_000));
+    assert_eq!(
+        source.entries().await.unwrap(),
+        [SourceEntry {
+            pubkey: a,
+            reference: String::new(),
+            detail: String::new(),
+        }]
+    );
+    server.await.unwrap();
+}
@@ -0,0 +1,53 @@
+//! Checks the chain's root is found in the journal, and rewinding to it.
+
+use smt::{default_hash, Op};
+use smt_cli::{
+    sync::{compare, rewind_ops, Drift},
+    tree::LocalTree,
+};
+
+#[test]
+fn finds_and_rewinds_to_the_chains_root() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut tree = LocalTree::create(dir.path().join("j"), 32).unwrap();
+    assert_eq!(
+        compare(&tree, &default_hash(32)),
+        Drift::InSync,
+        "an empty tree is at the empty root"
+    );
+
+    tree.apply_at(&[Op::Insert([1; 32]), Op::Insert([2; 32])], 1)
+        .unwrap();
+    let pushed = tree.tree().root();
+    tree.apply_at(&[Op::Delete([1; 32]), Op::Insert([3; 32])], 2)
+        .unwrap();
+    tree.apply_at(&[Op::Insert([4; 32])], 3).unwrap();
+
+    assert_eq!(compare(&tree, &tree.tree().root()), Drift::InSync);
+    assert_eq!(compare(&tree, &[9; 32]), Drift::Unknown);
+    assert_eq!(
+        compare(&tree, &default_hash(32)),
+        Drift::Behind {
+            sequence: None,
+            behind: 3
+        }
+    );
+    let Drift::Behind { sequence, behind } = compare(&tree, &pushed) else {
+        panic!("the pushed root is in the journal");
+    };
+    assert_eq!((sequence, behind), (Some(0), 2));
+
+    let ops = rewind_ops(&tree, behind);
+    assert_eq!(
+        ops,
+        [
+            Op::Insert([1; 32]),
+            Op::Delete([3; 32]),
+            Op::Delete([4; 32])
+        ]
+    );
+    tree.apply_at(&ops, 4).unwrap();
+    assert_eq!(tree.tree().root(), pushed);
+    assert_eq!(tree.entries().len(), 4);
+    assert_eq!(compare(&tree, &pushed), Drift::InSync);
+}
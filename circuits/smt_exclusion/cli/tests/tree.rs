@@ -0,0 +1,50 @@
+//! Checks the blacklist journal round-trips: changes survive reopening, and a
+//! damaged journal doesn't open.
+
+use smt::{Op, SparseMerkleTree};
+use smt_cli::tree::LocalTree;
+
+#[test]
+fn keeps_changes_across_opens() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("blacklist.journal");
+    let mut tree = LocalTree::create(&path, 32).unwrap();
+    assert!(tree.tree().is_empty());
+    assert!(LocalTree::create(&path, 32).is_err());
+
+    assert_eq!(
+        tree.apply_at(&[Op::Insert([1; 32]), Op::Insert([2; 32])], 100)
+            .unwrap(),
+        2
+    );
+    // Already blacklisted: journaled, but nothing changes
+    assert_eq!(tree.apply_at(&[Op::Insert([1; 32])], 200).unwrap(), 0);
+    assert_eq!(tree.apply_at(&[Op::Delete([2; 32])], 300).unwrap(), 1);
+
+    let reopened = LocalTree::open(&path).unwrap();
+    let mut expected = SparseMerkleTree::with_depth(32);
+    expected.insert(&[1; 32]).unwrap();
+    assert_eq!(reopened.tree().root(), expected.root());
+    assert_eq!(reopened.tree().len(), 1);
+    let timestamps: Vec<u64> = reopened.entries().iter().map(|e| e.timestamp).collect();
+    assert_eq!(timestamps, [100, 200, 300]);
+    assert!(!reopened.replay().blacklisted_at(&[2; 32], 300));
+    assert!(reopened.replay().blacklisted_at(&[2; 32], 250));
+}
+
+#[test]
+fn refuses_damaged_journals() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("blacklist.journal");
+    assert!(LocalTree::create(&path, 7).is_err());
+    assert!(!path.exists());
+
+    let mut tree = LocalTree::create(&path, 32).unwrap();
+    tree.apply_at(&[Op::Insert([1; 32])], 100).unwrap();
+    let mut bytes = std::fs::read(&path).unwrap();
+    let pubkey = bytes.len() - 64 - 32;
+    bytes[pubkey] ^= 1;
+    std::fs::write(&path, bytes).unwrap();
+    let error = format!("{:#}", LocalTree::open(&path).err().unwrap());
+    assert!(error.contains("replaying"), "{error}");
+}
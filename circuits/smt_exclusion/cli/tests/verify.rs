@@ -0,0 +1,65 @@
+//! Checks a proof's public inputs are read back and its pairings checked.
+//!
+//! No sunspot key is checked in, so the key and proof here are generator
+//! points in gnark's raw encoding: well formed, but not a valid proof.
+
+use ark_bn254::{Fq, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::{BigInteger, PrimeField};
+use exclusion_client::groth16::VerifyingKey;
+use exclusion_common::{pubkey_to_index, ExclusionError, PublicWitness};
+use smt_cli::verify::check;
+use solana_pubkey::Pubkey;
+
+fn be(element: Fq) -> Vec<u8> {
+    element.into_bigint().to_bytes_be()
+}
+
+fn g1() -> Vec<u8> {
+    let point = G1Affine::generator();
+    [be(point.x), be(point.y)].concat()
+}
+
+fn g2() -> Vec<u8> {
+    let point = G2Affine::generator();
+    [
+        be(point.x.c1),
+        be(point.x.c0),
+        be(point.y.c1),
+        be(point.y.c0),
+    ]
+    .concat()
+}
+
+fn verifying_key() -> VerifyingKey {
+    let mut vk = [g1(), g1(), g2(), g2(), g1(), g2()].concat();
+    vk.extend_from_slice(&5u32.to_be_bytes());
+    for _ in 0..5 {
+        vk.extend(g1());
+    }
+    // One commitment, to no public inputs
+    vk.extend_from_slice(&1u32.to_be_bytes());
+    vk.extend_from_slice(&0u32.to_be_bytes());
+    vk.extend_from_slice(&1u32.to_be_bytes());
+    vk.extend([g2(), g2()].concat());
+    VerifyingKey::from_gnark_bytes(&vk).unwrap()
+}
+
+#[test]
+fn reports_what_a_proof_binds() {
+    let vk = verifying_key();
+    let proof = [g1(), g2(), g1(), 1u32.to_be_bytes().to_vec(), g1(), g1()].concat();
+    let sender = Pubkey::new_unique();
+    let root = [7; 32];
+    let witness = PublicWitness::new(&root, &pubkey_to_index(&sender.to_bytes()), 300);
+
+    let report = check(&vk, &proof, witness.as_bytes()).unwrap();
+    assert_eq!(report.inputs.smt_root, root);
+    assert_eq!(report.inputs.recent_slot, 300);
+    assert!(report.binds_pubkey(&sender));
+    assert!(!report.binds_pubkey(&Pubkey::new_unique()));
+    assert_eq!(report.verified, Err(ExclusionError::ZkVerificationFailed));
+
+    assert!(check(&vk, &proof[1..], witness.as_bytes()).is_err());
+    assert!(check(&vk, &proof, &witness.as_bytes()[..40]).is_err());
+}
@@ -0,0 +1,69 @@
+//! Checks which state account updates are reported as root changes.
+
+use exclusion_client::state::ExclusionState;
+use exclusion_common::{instruction::METADATA_SIZE, state::MAX_APPROVED_CALLERS, State};
+use smt_cli::watch::root_change;
+use solana_pubkey::Pubkey;
+
+const ADDRESS: Pubkey = Pubkey::new_from_array([7; 32]);
+
+fn state(slot: u64, root: u8, root_seq: u64, daily_limit: u64) -> ExclusionState {
+    ExclusionState {
+        address: ADDRESS,
+        admin: Pubkey::new_from_array([8; 32]),
+        smt_root: [root; 32],
+        slot,
+        state: State {
+            admin: [8; 32],
+            smt_root: [root; 32],
+            root_valid_until: 0,
+            transfer_flags: 0,
+            daily_limit,
+            fee_bps: 0,
+            root_seq,
+            approved_callers: [[0; 32]; MAX_APPROVED_CALLERS],
+            approved_caller_count: 0,
+            bump: 255,
+            treasury_bump: 255,
+            hash_scheme: 0,
+            verifier_flags: 0,
+            metadata: [0; METADATA_SIZE],
+            challenged: false,
+            bond_bump: 255,
+            bond_amount: 0,
+            bond_withdrawal_amount: 0,
+            bond_withdrawal_at: 0,
+            config: [0; 32],
+            tree_kind: 0,
+            tree_depth: 254,
+        },
+    }
+}
+
+#[test]
+fn reports_new_roots_only() {
+    let first = state(10, 1, 1, 0);
+    let change = root_change(None, &first).unwrap();
+    assert_eq!((change.slot, change.root_seq), (10, 1));
+    assert_eq!(
+        change.to_string(),
+        format!("slot 10: root 1 is 0x{}", "01".repeat(32))
+    );
+    assert_eq!(change.to_json()["state"], ADDRESS.to_string());
+
+    // Another field changed
+    assert_eq!(root_change(Some(&first), &state(11, 1, 1, 5)), None);
+    // The same root pushed again still counts
+    assert_eq!(
+        root_change(Some(&first), &state(12, 1, 2, 0))
+            .unwrap()
+            .root_seq,
+        2
+    );
+    let change = root_change(Some(&first), &state(13, 2, 2, 0)).unwrap();
+    assert_eq!(change.smt_root, [2; 32]);
+    assert_eq!(
+        change.to_json()["smtRoot"],
+        format!("0x{}", "02".repeat(32))
+    );
+}
@@ -0,0 +1,552 @@
+// ============================================================================
+// Governance - Root Updates Through SPL Governance (Realms) Proposals
+// ============================================================================
+// Lets a DAO own a blacklist: the state account's admin is the governance's
+// native treasury PDA, which SPL Governance signs for when it executes an
+// approved proposal. The treasury is a system account, so it can also pay for
+// INITIALIZE. Any PDA that signs via CPI works as an admin the same way.
+//
+//   propose: create a proposal whose transactions set the state's root to the
+//            root of a blacklist file (initializing the state first if needed),
+//            and sign it off so voting starts
+//   execute: after the vote passes and the hold-up time elapses, run the
+//            proposal's transactions (anyone can execute)
+//
+// Members vote in the Realms UI or with any SPL Governance client. Root updates
+// then run as CPIs from the governance program; recover.ts replays those too.
+//
+// Run with:
+//   npm run governance -- propose --realm <R> --governance <G> --mint <M> --list blacklist.json
+//   npm run governance -- execute --governance <G> --proposal <P>
+// ============================================================================
+
+import {
+  address,
+  createKeyPairSignerFromBytes,
+  createSolanaRpc,
+  createSolanaRpcSubscriptions,
+  createTransactionMessage,
+  appendTransactionMessageInstructions,
+  setTransactionMessageFeePayerSigner,
+  setTransactionMessageLifetimeUsingBlockhash,
+  signTransactionMessageWithSigners,
+  assertIsSendableTransaction,
+  assertIsTransactionWithBlockhashLifetime,
+  pipe,
+  sendAndConfirmTransactionFactory,
+  getSignatureFromTransaction,
+  getAddressDecoder,
+  getAddressEncoder,
+  getProgramDerivedAddress,
+  type Address,
+  type Instruction,
+  type KeyPairSigner,
+} from "@solana/kit";
+import { SYSTEM_PROGRAM_ADDRESS } from "@solana-program/system";
+import fs from "fs";
+import path from "path";
+import {
+  SparseMerkleTree,
+  pubkeyToBytes,
+  fieldToHex,
+  initPoseidon,
+} from "./smt.js";
+import { getAddressFromKeypairFile } from "@solana-noir-examples/lib/keypair";
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+const RPC_URL = process.env.RPC_URL || "https://api.devnet.solana.com";
+
+const WS_URL =
+  process.env.WS_URL ||
+  RPC_URL.replace("https://", "wss://").replace("http://", "ws://");
+
+const EXCLUSION_PROGRAM_ID = address(
+  process.env.EXCLUSION_PROGRAM_ID ||
+    (await getAddressFromKeypairFile(
+      "../on_chain_program/target/deploy/exclusion_program_example-keypair.json"
+    ))
+);
+
+// SPL Governance v3 (the program Realms deploys)
+const GOVERNANCE_PROGRAM_ID = address(
+  process.env.GOVERNANCE_PROGRAM_ID ||
+    "GovER5Lthms3bLBqWub97yVrMmEogzX7xNjdXpPPCVZw"
+);
+
+// Events are CPI'd into the SPL noop program (program built with `noop-events`)
+const NOOP_PROGRAM_ID = address("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
+const SYSVAR_RENT_ADDRESS = address(
+  "SysvarRent111111111111111111111111111111111"
+);
+
+const proposerWalletPath = path.join(
+  process.cwd(),
+  "..",
+  "keypair",
+  "deployer.json"
+);
+
+const INSTRUCTION = {
+  INITIALIZE: 0,
+  SET_SMT_ROOT: 1,
+};
+
+// Instruction data header: [discriminator, version]
+const INSTRUCTION_VERSION = 1;
+
+// GovernanceInstruction variants (Borsh enum indices)
+const GOVERNANCE_INSTRUCTION = {
+  CREATE_PROPOSAL: 6,
+  INSERT_TRANSACTION: 9,
+  SIGN_OFF_PROPOSAL: 12,
+  EXECUTE_TRANSACTION: 16,
+};
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+const textEncoder = new TextEncoder();
+const addressEncoder = getAddressEncoder();
+const addressDecoder = getAddressDecoder();
+
+async function loadKeypair(filePath: string): Promise<KeyPairSigner> {
+  if (!fs.existsSync(filePath)) {
+    throw new Error(`Keypair not found: ${filePath}`);
+  }
+  const bytes = new Uint8Array(JSON.parse(fs.readFileSync(filePath, "utf-8")));
+  return createKeyPairSignerFromBytes(bytes);
+}
+
+async function pda(
+  programAddress: Address,
+  seeds: Uint8Array[]
+): Promise<Address> {
+  const [derived] = await getProgramDerivedAddress({ programAddress, seeds });
+  return derived;
+}
+
+const getStatePda = (admin: Address) =>
+  pda(EXCLUSION_PROGRAM_ID, [
+    textEncoder.encode("state"),
+    addressEncoder.encode(admin),
+  ]);
+
+/** The governance's SOL treasury; signs for the governance in proposals */
+const getNativeTreasuryPda = (governance: Address) =>
+  pda(GOVERNANCE_PROGRAM_ID, [
+    textEncoder.encode("native-treasury"),
+    addressEncoder.encode(governance),
+  ]);
+
+const getTokenOwnerRecordPda = (
+  realm: Address,
+  mint: Address,
+  owner: Address
+) =>
+  pda(GOVERNANCE_PROGRAM_ID, [
+    textEncoder.encode("governance"),
+    addressEncoder.encode(realm),
+    addressEncoder.encode(mint),
+    addressEncoder.encode(owner),
+  ]);
+
+const getProposalPda = (governance: Address, mint: Address, seed: Address) =>
+  pda(GOVERNANCE_PROGRAM_ID, [
+    textEncoder.encode("governance"),
+    addressEncoder.encode(governance),
+    addressEncoder.encode(mint),
+    addressEncoder.encode(seed),
+  ]);
+
+const getProposalTransactionPda = (proposal: Address, index: number) =>
+  pda(GOVERNANCE_PROGRAM_ID, [
+    textEncoder.encode("governance"),
+    addressEncoder.encode(proposal),
+    new Uint8Array([0]), // option index (u8)
+    new Uint8Array([index & 0xff, index >> 8]), // transaction index (u16 LE)
+  ]);
+
+const getRealmConfigPda = (realm: Address) =>
+  pda(GOVERNANCE_PROGRAM_ID, [
+    textEncoder.encode("realm-config"),
+    addressEncoder.encode(realm),
+  ]);
+
+const getProposalDepositPda = (proposal: Address, payer: Address) =>
+  pda(GOVERNANCE_PROGRAM_ID, [
+    textEncoder.encode("proposal-deposit"),
+    addressEncoder.encode(proposal),
+    addressEncoder.encode(payer),
+  ]);
+
+/** Minimal Borsh writer for SPL Governance instruction data */
+class BorshWriter {
+  private parts: Uint8Array[] = [];
+
+  u8(value: number): this {
+    this.parts.push(new Uint8Array([value]));
+    return this;
+  }
+
+  u16(value: number): this {
+    const bytes = new Uint8Array(2);
+    new DataView(bytes.buffer).setUint16(0, value, true);
+    this.parts.push(bytes);
+    return this;
+  }
+
+  u32(value: number): this {
+    const bytes = new Uint8Array(4);
+    new DataView(bytes.buffer).setUint32(0, value, true);
+    this.parts.push(bytes);
+    return this;
+  }
+
+  bytes(value: Uint8Array): this {
+    this.parts.push(value);
+    return this;
+  }
+
+  vec(value: Uint8Array): this {
+    return this.u32(value.length).bytes(value);
+  }
+
+  string(value: string): this {
+    return this.vec(textEncoder.encode(value));
+  }
+
+  address(value: Address): this {
+    return this.bytes(new Uint8Array(addressEncoder.encode(value)));
+  }
+
+  toBytes(): Uint8Array {
+    return Uint8Array.from(this.parts.flatMap((part) => [...part]));
+  }
+}
+
+/**
+ * An exclusion program instruction as the governance stores it. Accounts the
+ * governance signs for keep `isSigner`; no transaction signature supplies it.
+ */
+interface ProposedInstruction {
+  accounts: { address: Address; isSigner: boolean; isWritable: boolean }[];
+  data: Uint8Array;
+}
+
+async function proposedInstructions(
+  treasury: Address,
+  root: Uint8Array,
+  initialize: boolean
+): Promise<ProposedInstruction[]> {
+  const statePda = await getStatePda(treasury);
+  const setRootData = new Uint8Array(2 + 32);
+  setRootData[0] = INSTRUCTION.SET_SMT_ROOT;
+  setRootData[1] = INSTRUCTION_VERSION;
+  setRootData.set(root, 2);
+
+  const setRoot: ProposedInstruction = {
+    accounts: [
+      { address: treasury, isSigner: true, isWritable: false },
+      { address: statePda, isSigner: false, isWritable: true },
+      { address: NOOP_PROGRAM_ID, isSigner: false, isWritable: false },
+    ],
+    data: setRootData,
+  };
+  if (!initialize) return [setRoot];
+
+  // The treasury pays the state account's rent, so it must hold enough SOL
+  const init: ProposedInstruction = {
+    accounts: [
+      { address: treasury, isSigner: true, isWritable: true },
+      { address: statePda, isSigner: false, isWritable: true },
+      { address: SYSTEM_PROGRAM_ADDRESS, isSigner: false, isWritable: false },
+    ],
+    data: new Uint8Array([INSTRUCTION.INITIALIZE, INSTRUCTION_VERSION]),
+  };
+  return [init, setRoot];
+}
+
+function encodeInstructionData(ix: ProposedInstruction): Uint8Array {
+  const writer = new BorshWriter()
+    .address(EXCLUSION_PROGRAM_ID)
+    .u32(ix.accounts.length);
+  for (const meta of ix.accounts) {
+    writer
+      .address(meta.address)
+      .u8(meta.isSigner ? 1 : 0)
+      .u8(meta.isWritable ? 1 : 0);
+  }
+  return writer.vec(ix.data).toBytes();
+}
+
+async function send(
+  payer: KeyPairSigner,
+  instructions: Instruction[]
+): Promise<string> {
+  const rpc = createSolanaRpc(RPC_URL);
+  const sendAndConfirm = sendAndConfirmTransactionFactory({
+    rpc,
+    rpcSubscriptions: createSolanaRpcSubscriptions(WS_URL),
+  });
+  const { value: latestBlockhash } = await rpc.getLatestBlockhash().send();
+
+  const transactionMessage = pipe(
+    createTransactionMessage({ version: 0 }),
+    (tx) => setTransactionMessageFeePayerSigner(payer, tx),
+    (tx) => setTransactionMessageLifetimeUsingBlockhash(latestBlockhash, tx),
+    (tx) => appendTransactionMessageInstructions(instructions, tx)
+  );
+
+  const signedTx = await signTransactionMessageWithSigners(transactionMessage);
+  assertIsSendableTransaction(signedTx);
+  assertIsTransactionWithBlockhashLifetime(signedTx);
+  await sendAndConfirm(signedTx, { commitment: "confirmed" });
+  return getSignatureFromTransaction(signedTx);
+}
+
+async function accountExists(account: Address): Promise<boolean> {
+  const rpc = createSolanaRpc(RPC_URL);
+  const info = await rpc.getAccountInfo(account, { encoding: "base64" }).send();
+  return info.value !== null;
+}
+
+function printUsage() {
+  console.log(`
+Usage:
+  npm run governance -- propose --realm <pubkey> --governance <pubkey> --mint <pubkey> --list <file> [--name <title>] [--keypair <file>]
+  npm run governance -- execute --governance <pubkey> --proposal <pubkey> [--keypair <file>]
+
+Commands:
+  propose   Create and sign off a proposal setting the DAO's root to the list's root
+  execute   Run an approved proposal's transactions once the hold-up time has passed
+
+Options:
+  --realm <pubkey>        Realm the governance belongs to
+  --governance <pubkey>   Governance whose native treasury is the state's admin
+  --mint <pubkey>         Governing token mint the proposer holds deposited tokens of
+  --list <file>           New blacklist (JSON array of base58 pubkeys)
+  --proposal <pubkey>     Proposal printed by propose
+  --name <title>          Proposal title (default: "Update exclusion list root")
+  --keypair <file>        Proposer / payer (default: keypair/deployer.json)
+`);
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+async function propose(
+  realm: Address,
+  governance: Address,
+  mint: Address,
+  listPath: string,
+  name: string,
+  proposer: KeyPairSigner
+) {
+  const treasury = await getNativeTreasuryPda(governance);
+  const statePda = await getStatePda(treasury);
+
+  const blacklist: string[] = JSON.parse(fs.readFileSync(listPath, "utf-8"));
+  const smt = new SparseMerkleTree();
+  for (const pubkey of blacklist) {
+    smt.insert(pubkeyToBytes(pubkey), 1n);
+  }
+  const root = Uint8Array.from(
+    Buffer.from(fieldToHex(smt.getRoot()).slice(2), "hex")
+  );
+
+  const initialize = !(await accountExists(statePda));
+  const instructions = await proposedInstructions(treasury, root, initialize);
+
+  // Proposals are addressed by a random seed
+  const seed = addressDecoder.decode(crypto.getRandomValues(new Uint8Array(32)));
+  const proposal = await getProposalPda(governance, mint, seed);
+  const ownerRecord = await getTokenOwnerRecordPda(
+    realm,
+    mint,
+    proposer.address
+  );
+
+  console.log(`Admin (native treasury): ${treasury}`);
+  console.log(`State account: ${statePda}${initialize ? " (new)" : ""}`);
+  console.log(`Proposed root: ${fieldToHex(smt.getRoot())}`);
+  console.log(`Proposal: ${proposal}\n`);
+
+  const createProposal: Instruction = {
+    programAddress: GOVERNANCE_PROGRAM_ID,
+    accounts: [
+      { address: realm, role: 0 }, // readonly
+      { address: proposal, role: 1 }, // writable
+      { address: governance, role: 1 }, // writable
+      { address: ownerRecord, role: 1 }, // writable
+      { address: mint, role: 0 }, // readonly
+      { address: proposer.address, role: 2 }, // signer (governance authority)
+      { address: proposer.address, role: 3 }, // signer + writable (payer)
+      { address: SYSTEM_PROGRAM_ADDRESS, role: 0 }, // readonly
+      { address: await getRealmConfigPda(realm), role: 0 }, // readonly
+      {
+        address: await getProposalDepositPda(proposal, proposer.address),
+        role: 1, // writable
+      },
+    ],
+    data: new BorshWriter()
+      .u8(GOVERNANCE_INSTRUCTION.CREATE_PROPOSAL)
+      .string(name)
+      .string(`${listPath}: ${blacklist.length} keys`)
+      .u8(0) // VoteType::SingleChoice
+      .u32(1)
+      .string("Approve")
+      .u8(1) // use_deny_option
+      .address(seed)
+      .toBytes(),
+  };
+
+  const insertTransactions: Instruction[] = await Promise.all(
+    instructions.map(async (ix, index) => ({
+      programAddress: GOVERNANCE_PROGRAM_ID,
+      accounts: [
+        { address: governance, role: 0 }, // readonly
+        { address: proposal, role: 1 }, // writable
+        { address: ownerRecord, role: 0 }, // readonly
+        { address: proposer.address, role: 2 }, // signer (governance authority)
+        { address: await getProposalTransactionPda(proposal, index), role: 1 }, // writable
+        { address: proposer.address, role: 3 }, // signer + writable (payer)
+        { address: SYSTEM_PROGRAM_ADDRESS, role: 0 }, // readonly
+        { address: SYSVAR_RENT_ADDRESS, role: 0 }, // readonly
+      ],
+      data: new BorshWriter()
+        .u8(GOVERNANCE_INSTRUCTION.INSERT_TRANSACTION)
+        .u8(0) // option index
+        .u16(index)
+        .u32(0) // legacy hold-up time (the governance's minimum applies)
+        .u32(1) // one instruction per transaction
+        .bytes(encodeInstructionData(ix))
+        .toBytes(),
+    }))
+  );
+
+  const signOff: Instruction = {
+    programAddress: GOVERNANCE_PROGRAM_ID,
+    accounts: [
+      { address: realm, role: 0 }, // readonly
+      { address: governance, role: 0 }, // readonly
+      { address: proposal, role: 1 }, // writable
+      { address: proposer.address, role: 2 }, // signer
+      { address: ownerRecord, role: 0 }, // readonly (signed off by the owner)
+    ],
+    data: new Uint8Array([GOVERNANCE_INSTRUCTION.SIGN_OFF_PROPOSAL]),
+  };
+
+  // One transaction each keeps every message well under the size limit
+  const steps: [string, Instruction][] = [
+    ["create proposal", createProposal],
+    ...insertTransactions.map(
+      (ix, index): [string, Instruction] => [`insert transaction ${index}`, ix]
+    ),
+    ["sign off", signOff],
+  ];
+  for (const [step, ix] of steps) {
+    const sig = await send(proposer, [ix]);
+    console.log(`  ${sig.slice(0, 16)}... ${step}`);
+  }
+  console.log("\nProposal created and signed off.");
+  console.log("Voting is open; execute once it passes:");
+  console.log(
+    `  npm run governance -- execute --governance ${governance} --proposal ${proposal}`
+  );
+}
+
+async function execute(
+  governance: Address,
+  proposal: Address,
+  payer: KeyPairSigner
+) {
+  const treasury = await getNativeTreasuryPda(governance);
+  const statePda = await getStatePda(treasury);
+
+  // A second transaction means the proposal initializes the state first; skip
+  // that one if an earlier execute already ran it
+  const initializes = await accountExists(
+    await getProposalTransactionPda(proposal, 1)
+  );
+  const instructions = await proposedInstructions(
+    treasury,
+    new Uint8Array(32), // only the account metas are needed
+    initializes
+  );
+  const pending = initializes && (await accountExists(statePda)) ? 1 : 0;
+
+  console.log(`State account: ${statePda}`);
+  console.log(`Proposal: ${proposal}\n`);
+
+  for (const [index, ix] of instructions.entries()) {
+    if (index < pending) continue;
+    const sig = await send(payer, [
+      {
+        programAddress: GOVERNANCE_PROGRAM_ID,
+        accounts: [
+          { address: governance, role: 0 }, // readonly
+          { address: proposal, role: 1 }, // writable
+          { address: await getProposalTransactionPda(proposal, index), role: 1 }, // writable
+          { address: EXCLUSION_PROGRAM_ID, role: 0 }, // readonly
+          // The governance signs for the treasury by CPI
+          ...ix.accounts.map((meta) => ({
+            address: meta.address,
+            role: meta.isWritable ? 1 : 0,
+          })),
+        ],
+        data: new Uint8Array([GOVERNANCE_INSTRUCTION.EXECUTE_TRANSACTION]),
+      },
+    ]);
+    console.log(`  ${sig.slice(0, 16)}... executed transaction ${index}`);
+  }
+  console.log("\nRoot updated by the DAO.");
+}
+
+// ============================================================================
+// Main
+// ============================================================================
+
+async function main() {
+  const [command, ...args] = process.argv.slice(2);
+  const argValue = (flag: string) => {
+    const idx = args.indexOf(flag);
+    return idx !== -1 ? args[idx + 1] : undefined;
+  };
+
+  const realmArg = argValue("--realm");
+  const governanceArg = argValue("--governance");
+  const mintArg = argValue("--mint");
+  const listArg = argValue("--list");
+  const proposalArg = argValue("--proposal");
+  const keypair = await loadKeypair(
+    argValue("--keypair") ?? proposerWalletPath
+  );
+
+  if (command === "propose" && realmArg && governanceArg && mintArg && listArg) {
+    await initPoseidon();
+    await propose(
+      address(realmArg),
+      address(governanceArg),
+      address(mintArg),
+      listArg,
+      argValue("--name") ?? "Update exclusion list root",
+      keypair
+    );
+  } else if (command === "execute" && governanceArg && proposalArg) {
+    await execute(address(governanceArg), address(proposalArg), keypair);
+  } else {
+    printUsage();
+    process.exit(1);
+  }
+}
+
+main().catch((err) => {
+  console.error(err);
+  process.exit(1);
+});
@@ -17,6 +17,7 @@ export {
 export interface SmtExclusionInputs {
   smt_root: string;
   pubkey_hash: string;
+  recent_slot: string;
   pubkey: number[];
   siblings: string[];
   leaf_value: string;
@@ -52,6 +53,7 @@ export function writeSmtProverToml(
 # Public inputs
 smt_root = "${inputs.smt_root}"
 pubkey_hash = "${inputs.pubkey_hash}"
+recent_slot = "${inputs.recent_slot}"
 
 # Private inputs (witness)
 pubkey = ${formatByteArray(inputs.pubkey)}
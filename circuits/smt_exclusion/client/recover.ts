@@ -0,0 +1,295 @@
+// ============================================================================
+// Tree Recovery - Rebuild the Off-Chain Blacklist from On-Chain History
+// ============================================================================
+// Reconstructs the SMT behind a state account's root when the operator's local
+// tree is lost. Walks the state account's transaction history oldest-first:
+//
+//   - SUBMIT_ROOT_TRANSITION: the inserted pubkey is in the instruction data,
+//     so the insertion is replayed and checked against the proof's new_root
+//   - INSERT_LEAF / REMOVE_LEAF: the pubkey is in the instruction data and the
+//     program recomputed the root on-chain, so the update is replayed directly
+//   - SET_SMT_ROOT: the root is an opaque blob; the tree is reset from a
+//     published snapshot (--snapshot) whose root must match, otherwise the
+//     history cannot be replayed past this point
+//
+// Instructions are found at the top level and as CPIs (e.g. executed by a
+// governance proposal). Writes the recovered blacklist as a JSON array of
+// base58 pubkeys.
+//
+// Run with: npm run recover -- --admin <ADMIN_PUBKEY> [--snapshot list.json]
+// ============================================================================
+
+import {
+  address,
+  createSolanaRpc,
+  getAddressDecoder,
+  getAddressEncoder,
+  getBase58Encoder,
+  getProgramDerivedAddress,
+  type Address,
+  type Signature,
+} from "@solana/kit";
+import fs from "fs";
+import {
+  SparseMerkleTree,
+  pubkeyToBytes,
+  fieldToHex,
+  initPoseidon,
+} from "./smt.js";
+import { getAddressFromKeypairFile } from "@solana-noir-examples/lib/keypair";
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+const RPC_URL = process.env.RPC_URL || "https://api.devnet.solana.com";
+
+const EXCLUSION_PROGRAM_ID = address(
+  process.env.EXCLUSION_PROGRAM_ID ||
+    (await getAddressFromKeypairFile(
+      "../on_chain_program/target/deploy/exclusion_program_example-keypair.json"
+    ))
+);
+
+const INSTRUCTION = {
+  SET_SMT_ROOT: 1,
+  SUBMIT_ROOT_TRANSITION: 6,
+  INSERT_LEAF: 8,
+  REMOVE_LEAF: 9,
+};
+
+// Instruction data header: [discriminator, version]
+const HEADER_LEN = 2;
+const PROOF_SIZE = 388;
+// Transition witness: 12-byte header + old_root + new_root + key
+const TRANSITION_NEW_ROOT_OFFSET = HEADER_LEN + 32 + PROOF_SIZE + 12 + 32;
+// SET_SMT_ROOT version 3: root + root_valid_until + 64-byte metadata
+const SET_ROOT_METADATA_OFFSET = HEADER_LEN + 32 + 8;
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+const rpc = createSolanaRpc(RPC_URL);
+const base58 = getBase58Encoder();
+const addressDecoder = getAddressDecoder();
+
+function bytesToHex(bytes: Uint8Array): string {
+  return "0x" + Buffer.from(bytes).toString("hex");
+}
+
+// Metadata is usually a zero-padded CID or URL; fall back to hex otherwise
+function metadataToString(metadata: Uint8Array): string {
+  const end = metadata.indexOf(0);
+  const text = metadata.slice(0, end === -1 ? metadata.length : end);
+  return text.every((b) => b >= 0x20 && b < 0x7f)
+    ? Buffer.from(text).toString("ascii")
+    : bytesToHex(metadata);
+}
+
+function buildTree(pubkeys: string[]): SparseMerkleTree {
+  const smt = new SparseMerkleTree();
+  for (const pubkey of pubkeys) {
+    smt.insert(pubkeyToBytes(pubkey), 1n);
+  }
+  return smt;
+}
+
+/** All successful signatures touching `account`, oldest first */
+async function getHistory(account: Address): Promise<Signature[]> {
+  const signatures: Signature[] = [];
+  let before: Signature | undefined;
+
+  for (;;) {
+    const page = await rpc
+      .getSignaturesForAddress(account, { before, limit: 1000 })
+      .send();
+    if (page.length === 0) break;
+    for (const entry of page) {
+      if (entry.err === null) signatures.push(entry.signature);
+    }
+    before = page[page.length - 1].signature;
+  }
+
+  return signatures.reverse();
+}
+
+function printUsage() {
+  console.log(`
+Usage: npm run recover -- --admin <pubkey> [options]
+
+Options:
+  --admin <pubkey>      Admin whose state PDA should be recovered
+  --snapshot <file>     JSON array of base58 pubkeys for a SET_SMT_ROOT
+  --out <file>          Output file (default: recovered-blacklist.json)
+`);
+}
+
+// ============================================================================
+// Main
+// ============================================================================
+
+async function main() {
+  const args = process.argv.slice(2);
+  const argValue = (flag: string) => {
+    const idx = args.indexOf(flag);
+    return idx !== -1 ? args[idx + 1] : undefined;
+  };
+
+  const adminArg = argValue("--admin");
+  if (!adminArg || args.includes("--help") || args.includes("-h")) {
+    printUsage();
+    process.exit(adminArg ? 0 : 1);
+  }
+
+  await initPoseidon();
+
+  const admin = address(adminArg);
+  const [statePda] = await getProgramDerivedAddress({
+    programAddress: EXCLUSION_PROGRAM_ID,
+    seeds: [
+      new TextEncoder().encode("state"),
+      getAddressEncoder().encode(admin),
+    ],
+  });
+  const snapshotPath = argValue("--snapshot");
+  const snapshot: string[] = snapshotPath
+    ? JSON.parse(fs.readFileSync(snapshotPath, "utf-8"))
+    : [];
+  const snapshotRoot = fieldToHex(buildTree(snapshot).getRoot());
+  const outPath = argValue("--out") ?? "recovered-blacklist.json";
+
+  console.log(`RPC: ${RPC_URL}`);
+  console.log(`State account: ${statePda}\n`);
+
+  let blacklist: string[] = [];
+  let smt = new SparseMerkleTree();
+  let replayable = true;
+
+  const history = await getHistory(statePda);
+  console.log(`Replaying ${history.length} transactions...\n`);
+
+  for (const signature of history) {
+    const tx = await rpc
+      .getTransaction(signature, {
+        encoding: "json",
+        maxSupportedTransactionVersion: 0,
+      })
+      .send();
+    if (!tx) continue;
+
+    // Updates can also arrive by CPI, e.g. when a governance proposal executes
+    const { accountKeys, instructions } = tx.transaction.message;
+    const inner = tx.meta?.innerInstructions ?? [];
+    const ordered = instructions.flatMap((ix, i) => [
+      ix,
+      ...(inner.find((cpi) => cpi.index === i)?.instructions ?? []),
+    ]);
+    for (const ix of ordered) {
+      if (accountKeys[ix.programIdIndex] !== EXCLUSION_PROGRAM_ID) continue;
+
+      const data = new Uint8Array(base58.encode(ix.data));
+      const accounts = ix.accounts.map((i) => accountKeys[i]);
+
+      if (data[0] === INSTRUCTION.SET_SMT_ROOT && accounts[1] === statePda) {
+        const root = bytesToHex(data.slice(HEADER_LEN, HEADER_LEN + 32));
+        if (root === snapshotRoot) {
+          blacklist = [...snapshot];
+          smt = buildTree(blacklist);
+          replayable = true;
+          console.log(
+            `  ${signature.slice(0, 16)}... SET_SMT_ROOT (snapshot, ${blacklist.length} keys)`
+          );
+        } else {
+          replayable = false;
+          console.log(
+            `  ${signature.slice(0, 16)}... SET_SMT_ROOT ${root} (no matching snapshot)`
+          );
+          // Version 3 carries a pointer to the published list for this root
+          const metadata = data.slice(
+            SET_ROOT_METADATA_OFFSET,
+            SET_ROOT_METADATA_OFFSET + 64
+          );
+          if (data[1] >= 3 && metadata.some((b) => b !== 0)) {
+            console.log(`    list published at: ${metadataToString(metadata)}`);
+          }
+        }
+      } else if (
+        data[0] === INSTRUCTION.SUBMIT_ROOT_TRANSITION &&
+        accounts[0] === statePda
+      ) {
+        const pubkey = addressDecoder.decode(
+          data.slice(HEADER_LEN, HEADER_LEN + 32)
+        );
+        const newRoot = bytesToHex(
+          data.slice(
+            TRANSITION_NEW_ROOT_OFFSET,
+            TRANSITION_NEW_ROOT_OFFSET + 32
+          )
+        );
+        blacklist.push(pubkey);
+        smt.insert(pubkeyToBytes(pubkey), 1n);
+
+        const status = !replayable
+          ? "unverifiable (unknown base tree)"
+          : fieldToHex(smt.getRoot()) === newRoot
+            ? "ok"
+            : "ROOT MISMATCH";
+        console.log(
+          `  ${signature.slice(0, 16)}... insert ${pubkey} ${status}`
+        );
+      } else if (
+        (data[0] === INSTRUCTION.INSERT_LEAF ||
+          data[0] === INSTRUCTION.REMOVE_LEAF) &&
+        accounts[1] === statePda
+      ) {
+        const pubkey = addressDecoder.decode(
+          data.slice(HEADER_LEN, HEADER_LEN + 32)
+        );
+        if (data[0] === INSTRUCTION.INSERT_LEAF) {
+          blacklist.push(pubkey);
+          smt.insert(pubkeyToBytes(pubkey), 1n);
+        } else {
+          blacklist = blacklist.filter((key) => key !== pubkey);
+          smt.insert(pubkeyToBytes(pubkey), 0n);
+        }
+        const op = data[0] === INSTRUCTION.INSERT_LEAF ? "insert" : "remove";
+        console.log(`  ${signature.slice(0, 16)}... ${op} ${pubkey}`);
+      }
+    }
+  }
+
+  // Compare against the current on-chain root
+  const account = await rpc
+    .getAccountInfo(statePda, { encoding: "base64" })
+    .send();
+  if (!account.value) {
+    throw new Error(`State account not found: ${statePda}`);
+  }
+  const stateData = Buffer.from(account.value.data[0], "base64");
+  // Layout v1: discriminator (8) + version (1) + admin (32) + smt_root (32)
+  const onChainRoot = bytesToHex(stateData.subarray(41, 73));
+  const recoveredRoot = fieldToHex(smt.getRoot());
+
+  fs.writeFileSync(outPath, JSON.stringify(blacklist, null, 2) + "\n");
+
+  console.log(`\nRecovered ${blacklist.length} keys -> ${outPath}`);
+  console.log(`Recovered root: ${recoveredRoot}`);
+  console.log(`On-chain root:  ${onChainRoot}`);
+
+  if (recoveredRoot !== onChainRoot) {
+    console.error("\nRecovered tree does not match the on-chain root.");
+    if (!replayable) {
+      console.error(
+        "Provide the snapshot published for the last SET_SMT_ROOT via --snapshot."
+      );
+    }
+    process.exit(1);
+  }
+  console.log("\nRecovered tree matches the on-chain root.");
+}
+
+main().catch((err) => {
+  console.error(err);
+  process.exit(1);
+});
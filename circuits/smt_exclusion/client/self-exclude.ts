@@ -0,0 +1,347 @@
+// ============================================================================
+// Self-Exclusion - Queue a Self-Ban and Drain It into the Blacklist
+// ============================================================================
+// Demonstrates the opt-in self-exclusion workflow end to end:
+//
+//   request: a user signs SELF_EXCLUDE, adding their pubkey to the state's
+//            exclusion queue PDA
+//   drain:   the admin inserts every queued pubkey into the tree with
+//            INSERT_LEAF, which proves the insertion on-chain and removes the
+//            key from the queue
+//
+// Draining needs the admin's current blacklist (a JSON array of base58
+// pubkeys, e.g. the output of recover.ts), whose root must match on-chain. The
+// list is updated in place with the inserted keys.
+//
+// Run with:
+//   npm run self-exclude -- request --admin <ADMIN_PUBKEY> --keypair <file>
+//   npm run self-exclude -- drain --list blacklist.json
+// ============================================================================
+
+import {
+  address,
+  createKeyPairSignerFromBytes,
+  createSolanaRpc,
+  createSolanaRpcSubscriptions,
+  createTransactionMessage,
+  appendTransactionMessageInstructions,
+  setTransactionMessageFeePayerSigner,
+  setTransactionMessageLifetimeUsingBlockhash,
+  signTransactionMessageWithSigners,
+  assertIsSendableTransaction,
+  assertIsTransactionWithBlockhashLifetime,
+  pipe,
+  sendAndConfirmTransactionFactory,
+  getSignatureFromTransaction,
+  getAddressDecoder,
+  getAddressEncoder,
+  getProgramDerivedAddress,
+  type Address,
+  type Instruction,
+  type KeyPairSigner,
+} from "@solana/kit";
+import { getSetComputeUnitLimitInstruction } from "@solana-program/compute-budget";
+import { SYSTEM_PROGRAM_ADDRESS } from "@solana-program/system";
+import fs from "fs";
+import path from "path";
+import {
+  SparseMerkleTree,
+  pubkeyToBytes,
+  fieldToHex,
+  initPoseidon,
+  poseidonHash2,
+} from "./smt.js";
+import { getAddressFromKeypairFile } from "@solana-noir-examples/lib/keypair";
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+const RPC_URL = process.env.RPC_URL || "https://api.devnet.solana.com";
+
+const WS_URL =
+  process.env.WS_URL ||
+  RPC_URL.replace("https://", "wss://").replace("http://", "ws://");
+
+const EXCLUSION_PROGRAM_ID = address(
+  process.env.EXCLUSION_PROGRAM_ID ||
+    (await getAddressFromKeypairFile(
+      "../on_chain_program/target/deploy/exclusion_program_example-keypair.json"
+    ))
+);
+
+// Events are CPI'd into the SPL noop program (program built with `noop-events`)
+const NOOP_PROGRAM_ID = address("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
+const adminWalletPath = path.join(process.cwd(), "..", "keypair", "deployer.json");
+
+const INSTRUCTION = {
+  INSERT_LEAF: 8,
+  SELF_EXCLUDE: 30,
+};
+
+// Instruction data header: [discriminator, version]
+const INSTRUCTION_VERSION = 1;
+
+const TREE_DEPTH = 254;
+
+// Recomputing two roots over 254 levels with the Poseidon syscall
+const INSERT_LEAF_COMPUTE_UNITS = 1_400_000;
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+const textEncoder = new TextEncoder();
+const addressEncoder = getAddressEncoder();
+const addressDecoder = getAddressDecoder();
+
+async function loadKeypair(filePath: string): Promise<KeyPairSigner> {
+  if (!fs.existsSync(filePath)) {
+    throw new Error(`Keypair not found: ${filePath}`);
+  }
+  const bytes = new Uint8Array(JSON.parse(fs.readFileSync(filePath, "utf-8")));
+  return createKeyPairSignerFromBytes(bytes);
+}
+
+async function getStatePda(admin: Address): Promise<Address> {
+  const [pda] = await getProgramDerivedAddress({
+    programAddress: EXCLUSION_PROGRAM_ID,
+    seeds: [textEncoder.encode("state"), addressEncoder.encode(admin)],
+  });
+  return pda;
+}
+
+async function getExclusionQueuePda(statePda: Address): Promise<Address> {
+  const [pda] = await getProgramDerivedAddress({
+    programAddress: EXCLUSION_PROGRAM_ID,
+    seeds: [
+      textEncoder.encode("exclusion_queue"),
+      addressEncoder.encode(statePda),
+    ],
+  });
+  return pda;
+}
+
+function fieldToBytes(f: bigint): Uint8Array {
+  return Uint8Array.from(Buffer.from(fieldToHex(f).slice(2), "hex"));
+}
+
+function bytesToHex(bytes: Uint8Array): string {
+  return "0x" + Buffer.from(bytes).toString("hex");
+}
+
+// Empty-subtree hashes at each level, as in the program's DEFAULT_HASHES
+function defaultHashes(): bigint[] {
+  const hashes = [0n];
+  for (let level = 1; level < TREE_DEPTH; level++) {
+    hashes.push(poseidonHash2(hashes[level - 1], hashes[level - 1]));
+  }
+  return hashes;
+}
+
+/** INSERT_LEAF payload: pubkey + sibling bitmap + non-default siblings */
+function encodeLeafUpdate(
+  smt: SparseMerkleTree,
+  pubkey: string,
+  defaults: bigint[]
+): Uint8Array {
+  const { siblings } = smt.getMerkleProof(pubkeyToBytes(pubkey));
+  const bitmap = new Uint8Array(32);
+  const provided: Uint8Array[] = [];
+  siblings.forEach((sibling, level) => {
+    if (sibling !== defaults[level]) {
+      bitmap[level >> 3] |= 1 << (level & 7);
+      provided.push(fieldToBytes(sibling));
+    }
+  });
+
+  const data = new Uint8Array(2 + 32 + 32 + 32 * provided.length);
+  data[0] = INSTRUCTION.INSERT_LEAF;
+  data[1] = INSTRUCTION_VERSION;
+  data.set(addressEncoder.encode(address(pubkey)), 2);
+  data.set(bitmap, 34);
+  provided.forEach((sibling, i) => data.set(sibling, 66 + 32 * i));
+  return data;
+}
+
+async function send(
+  payer: KeyPairSigner,
+  instructions: Instruction[]
+): Promise<string> {
+  const rpc = createSolanaRpc(RPC_URL);
+  const sendAndConfirm = sendAndConfirmTransactionFactory({
+    rpc,
+    rpcSubscriptions: createSolanaRpcSubscriptions(WS_URL),
+  });
+  const { value: latestBlockhash } = await rpc.getLatestBlockhash().send();
+
+  const transactionMessage = pipe(
+    createTransactionMessage({ version: 0 }),
+    (tx) => setTransactionMessageFeePayerSigner(payer, tx),
+    (tx) => setTransactionMessageLifetimeUsingBlockhash(latestBlockhash, tx),
+    (tx) => appendTransactionMessageInstructions(instructions, tx)
+  );
+
+  const signedTx = await signTransactionMessageWithSigners(transactionMessage);
+  assertIsSendableTransaction(signedTx);
+  assertIsTransactionWithBlockhashLifetime(signedTx);
+  await sendAndConfirm(signedTx, { commitment: "confirmed" });
+  return getSignatureFromTransaction(signedTx);
+}
+
+/** Pubkeys waiting in the queue: discriminator (8) + count (1) + 32 each */
+async function readQueue(queuePda: Address): Promise<string[]> {
+  const rpc = createSolanaRpc(RPC_URL);
+  const account = await rpc
+    .getAccountInfo(queuePda, { encoding: "base64" })
+    .send();
+  if (!account.value) return [];
+
+  const data = Buffer.from(account.value.data[0], "base64");
+  const queued: string[] = [];
+  for (let i = 0; i < data[8]; i++) {
+    queued.push(addressDecoder.decode(data.subarray(9 + 32 * i, 41 + 32 * i)));
+  }
+  return queued;
+}
+
+function printUsage() {
+  console.log(`
+Usage:
+  npm run self-exclude -- request --admin <pubkey> --keypair <file>
+  npm run self-exclude -- drain --list <file>
+
+Commands:
+  request   Queue the keypair's pubkey for exclusion by <admin>'s blacklist
+  drain     Insert queued pubkeys into the admin's tree (admin: keypair/deployer.json)
+
+Options:
+  --admin <pubkey>   Admin whose blacklist to join
+  --keypair <file>   Keypair of the user excluding themselves
+  --list <file>      Current blacklist (JSON array of base58 pubkeys), updated in place
+`);
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+async function request(admin: Address, userKeypairPath: string) {
+  const user = await loadKeypair(userKeypairPath);
+  const statePda = await getStatePda(admin);
+  const queuePda = await getExclusionQueuePda(statePda);
+
+  console.log(`User: ${user.address}`);
+  console.log(`State account: ${statePda}`);
+  console.log(`Exclusion queue: ${queuePda}\n`);
+
+  const sig = await send(user, [
+    {
+      programAddress: EXCLUSION_PROGRAM_ID,
+      accounts: [
+        { address: user.address, role: 3 }, // signer + writable
+        { address: statePda, role: 0 }, // readonly
+        { address: queuePda, role: 1 }, // writable
+        { address: SYSTEM_PROGRAM_ADDRESS, role: 0 }, // readonly
+      ],
+      data: new Uint8Array([INSTRUCTION.SELF_EXCLUDE, INSTRUCTION_VERSION]),
+    },
+  ]);
+  console.log(`Queued for exclusion: ${sig.slice(0, 20)}...`);
+  console.log(
+    "Transfers stay possible until the admin drains the queue into the tree."
+  );
+}
+
+async function drain(listPath: string) {
+  const admin = await loadKeypair(adminWalletPath);
+  const statePda = await getStatePda(admin.address);
+  const queuePda = await getExclusionQueuePda(statePda);
+
+  const blacklist: string[] = JSON.parse(fs.readFileSync(listPath, "utf-8"));
+  const smt = new SparseMerkleTree();
+  for (const pubkey of blacklist) {
+    smt.insert(pubkeyToBytes(pubkey), 1n);
+  }
+
+  // The list must describe the tree behind the on-chain root
+  const rpc = createSolanaRpc(RPC_URL);
+  const state = await rpc
+    .getAccountInfo(statePda, { encoding: "base64" })
+    .send();
+  if (!state.value) {
+    throw new Error(`State account not found: ${statePda}`);
+  }
+  const stateData = Buffer.from(state.value.data[0], "base64");
+  const onChainRoot = bytesToHex(stateData.subarray(41, 73));
+  if (fieldToHex(smt.getRoot()) !== onChainRoot) {
+    throw new Error(
+      `${listPath} does not match the on-chain root; run recover.ts first`
+    );
+  }
+
+  const queued = await readQueue(queuePda);
+  console.log(`State account: ${statePda}`);
+  console.log(`Draining ${queued.length} queued keys...\n`);
+
+  const defaults = defaultHashes();
+  for (const pubkey of queued) {
+    const sig = await send(admin, [
+      getSetComputeUnitLimitInstruction({ units: INSERT_LEAF_COMPUTE_UNITS }),
+      {
+        programAddress: EXCLUSION_PROGRAM_ID,
+        accounts: [
+          { address: admin.address, role: 2 }, // signer
+          { address: statePda, role: 1 }, // writable
+          { address: queuePda, role: 1 }, // writable
+          { address: NOOP_PROGRAM_ID, role: 0 }, // readonly
+        ],
+        data: encodeLeafUpdate(smt, pubkey, defaults),
+      },
+    ]);
+
+    // Keys already in the tree are only dequeued
+    if (!smt.isBlacklisted(pubkeyToBytes(pubkey))) {
+      smt.insert(pubkeyToBytes(pubkey), 1n);
+      blacklist.push(pubkey);
+    }
+    console.log(`  ${sig.slice(0, 16)}... insert ${pubkey}`);
+  }
+
+  fs.writeFileSync(listPath, JSON.stringify(blacklist, null, 2) + "\n");
+  console.log(`\nBlacklist now holds ${blacklist.length} keys -> ${listPath}`);
+  console.log(`Root: ${fieldToHex(smt.getRoot())}`);
+}
+
+// ============================================================================
+// Main
+// ============================================================================
+
+async function main() {
+  const [command, ...args] = process.argv.slice(2);
+  const argValue = (flag: string) => {
+    const idx = args.indexOf(flag);
+    return idx !== -1 ? args[idx + 1] : undefined;
+  };
+
+  await initPoseidon();
+
+  const adminArg = argValue("--admin");
+  const keypairArg = argValue("--keypair");
+  const listArg = argValue("--list");
+
+  if (command === "request" && adminArg && keypairArg) {
+    await request(address(adminArg), keypairArg);
+  } else if (command === "drain" && listArg) {
+    await drain(listArg);
+  } else {
+    printUsage();
+    process.exit(1);
+  }
+}
+
+main().catch((err) => {
+  console.error(err);
+  process.exit(1);
+});
@@ -297,5 +297,6 @@ if (import.meta.url === `file://${process.argv[1]}`) {
   console.log("\n=== Circuit Inputs for Innocent Pubkey ===");
   console.log(`smt_root = "${fieldToHex(smt.getRoot())}"`);
   console.log(`pubkey_hash = "${fieldToHex(pubkeyToIndex(innocentPubkey))}"`);
+  console.log(`recent_slot = "<recent finalized slot>"`);
   console.log(`leaf_value = "${innocentProof.leafValue}"`);
 }
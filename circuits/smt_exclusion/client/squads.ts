@@ -0,0 +1,544 @@
+// ============================================================================
+// Squads - Multisig Admin Flows Through Squads v4 Vault Transactions
+// ============================================================================
+// Production admins rarely live in one wallet. With Squads the state account's
+// admin is a multisig vault PDA, which the Squads program signs for by CPI when
+// it executes an approved vault transaction:
+//
+//   hand-over:     the current single-key admin makes the vault the admin
+//                  (SET_ADMIN, signed directly)
+//   propose-root:  propose a SET_SMT_ROOT to the root of a blacklist file
+//   propose-admin: propose handing the state to another admin (SET_ADMIN)
+//   approve:       approve a proposal as another member
+//   execute:       run an approved vault transaction
+//
+// The proposer approves their own proposal; other members approve here or in
+// the Squads app. The state account keeps the address derived from the admin
+// that initialized it, so pass --admin when that wasn't the vault.
+//
+// Run with:
+//   npm run squads -- hand-over --multisig <M>
+//   npm run squads -- propose-root --multisig <M> --list blacklist.json
+//   npm run squads -- propose-admin --multisig <M> --new-admin <PUBKEY>
+//   npm run squads -- approve --multisig <M> --index <N>
+//   npm run squads -- execute --multisig <M> --index <N>
+// ============================================================================
+
+import {
+  address,
+  createKeyPairSignerFromBytes,
+  createSolanaRpc,
+  createSolanaRpcSubscriptions,
+  createTransactionMessage,
+  appendTransactionMessageInstructions,
+  setTransactionMessageFeePayerSigner,
+  setTransactionMessageLifetimeUsingBlockhash,
+  signTransactionMessageWithSigners,
+  assertIsSendableTransaction,
+  assertIsTransactionWithBlockhashLifetime,
+  pipe,
+  sendAndConfirmTransactionFactory,
+  getSignatureFromTransaction,
+  getAddressDecoder,
+  getAddressEncoder,
+  getProgramDerivedAddress,
+  type Address,
+  type Instruction,
+  type KeyPairSigner,
+} from "@solana/kit";
+import { SYSTEM_PROGRAM_ADDRESS } from "@solana-program/system";
+import fs from "fs";
+import path from "path";
+import {
+  SparseMerkleTree,
+  pubkeyToBytes,
+  fieldToHex,
+  initPoseidon,
+} from "./smt.js";
+import { getAddressFromKeypairFile } from "@solana-noir-examples/lib/keypair";
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+const RPC_URL = process.env.RPC_URL || "https://api.devnet.solana.com";
+
+const WS_URL =
+  process.env.WS_URL ||
+  RPC_URL.replace("https://", "wss://").replace("http://", "ws://");
+
+const EXCLUSION_PROGRAM_ID = address(
+  process.env.EXCLUSION_PROGRAM_ID ||
+    (await getAddressFromKeypairFile(
+      "../on_chain_program/target/deploy/exclusion_program_example-keypair.json"
+    ))
+);
+
+const SQUADS_PROGRAM_ID = address(
+  process.env.SQUADS_PROGRAM_ID || "SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf"
+);
+
+// Events are CPI'd into the SPL noop program (program built with `noop-events`)
+const NOOP_PROGRAM_ID = address("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
+const memberWalletPath = path.join(
+  process.cwd(),
+  "..",
+  "keypair",
+  "deployer.json"
+);
+
+const INSTRUCTION = {
+  SET_SMT_ROOT: 1,
+  SET_ADMIN: 40,
+};
+
+// Instruction data header: [discriminator, version]
+const INSTRUCTION_VERSION = 1;
+
+// Anchor discriminators: sha256("global:<name>")[..8]
+const SQUADS_INSTRUCTION = {
+  VAULT_TRANSACTION_CREATE: [48, 250, 78, 168, 208, 226, 218, 211],
+  PROPOSAL_CREATE: [220, 60, 73, 224, 30, 108, 79, 159],
+  PROPOSAL_APPROVE: [144, 37, 164, 136, 188, 216, 42, 248],
+  VAULT_TRANSACTION_EXECUTE: [194, 8, 161, 87, 153, 164, 25, 171],
+};
+
+// Multisig account: discriminator (8) + create_key (32) + config_authority (32)
+// + threshold (2) + time_lock (4), then transaction_index (u64 LE)
+const MULTISIG_TRANSACTION_INDEX_OFFSET = 78;
+
+// VaultTransaction account: discriminator (8) + multisig (32) + creator (32)
+// + index (8) + bump, vault_index, vault_bump (1 each), then the ephemeral
+// signer bumps (Vec<u8>) and the message
+const VAULT_TRANSACTION_EPHEMERAL_BUMPS_OFFSET = 83;
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+const textEncoder = new TextEncoder();
+const addressEncoder = getAddressEncoder();
+const addressDecoder = getAddressDecoder();
+
+async function loadKeypair(filePath: string): Promise<KeyPairSigner> {
+  if (!fs.existsSync(filePath)) {
+    throw new Error(`Keypair not found: ${filePath}`);
+  }
+  const bytes = new Uint8Array(JSON.parse(fs.readFileSync(filePath, "utf-8")));
+  return createKeyPairSignerFromBytes(bytes);
+}
+
+async function pda(
+  programAddress: Address,
+  seeds: Uint8Array[]
+): Promise<Address> {
+  const [derived] = await getProgramDerivedAddress({ programAddress, seeds });
+  return derived;
+}
+
+function u64Le(value: bigint): Uint8Array {
+  const bytes = new Uint8Array(8);
+  new DataView(bytes.buffer).setBigUint64(0, value, true);
+  return bytes;
+}
+
+const getStatePda = (admin: Address) =>
+  pda(EXCLUSION_PROGRAM_ID, [
+    textEncoder.encode("state"),
+    addressEncoder.encode(admin),
+  ]);
+
+const getVaultPda = (multisig: Address, vaultIndex: number) =>
+  pda(SQUADS_PROGRAM_ID, [
+    textEncoder.encode("multisig"),
+    addressEncoder.encode(multisig),
+    textEncoder.encode("vault"),
+    new Uint8Array([vaultIndex]),
+  ]);
+
+const getTransactionPda = (multisig: Address, index: bigint) =>
+  pda(SQUADS_PROGRAM_ID, [
+    textEncoder.encode("multisig"),
+    addressEncoder.encode(multisig),
+    textEncoder.encode("transaction"),
+    u64Le(index),
+  ]);
+
+const getProposalPda = (multisig: Address, index: bigint) =>
+  pda(SQUADS_PROGRAM_ID, [
+    textEncoder.encode("multisig"),
+    addressEncoder.encode(multisig),
+    textEncoder.encode("transaction"),
+    u64Le(index),
+    textEncoder.encode("proposal"),
+  ]);
+
+async function readAccount(account: Address): Promise<Buffer> {
+  const rpc = createSolanaRpc(RPC_URL);
+  const info = await rpc.getAccountInfo(account, { encoding: "base64" }).send();
+  if (!info.value) {
+    throw new Error(`Account not found: ${account}`);
+  }
+  return Buffer.from(info.value.data[0], "base64");
+}
+
+/**
+ * Serialize one exclusion program instruction, signed by the vault, as a
+ * Squads TransactionMessage: the vault (writable signer, as the SDK's fee
+ * payer) first, then writable and readonly non-signers, the program last.
+ */
+function encodeVaultMessage(
+  vault: Address,
+  writable: Address[],
+  readonly: Address[],
+  data: Uint8Array
+): Uint8Array {
+  const keys = [vault, ...writable, ...readonly, EXCLUSION_PROGRAM_ID];
+  // The instruction takes every key but the program, in order
+  const accountIndexes = keys.slice(0, -1).map((_, i) => i);
+
+  const out: number[] = [
+    1, // num_signers
+    1, // num_writable_signers
+    writable.length, // num_writable_non_signers
+    keys.length,
+    ...keys.flatMap((key) => [...addressEncoder.encode(key)]),
+    1, // one instruction
+    keys.length - 1, // program_id_index
+    accountIndexes.length,
+    ...accountIndexes,
+    data.length & 0xff,
+    data.length >> 8, // data length (u16 LE)
+    ...data,
+    0, // no address table lookups
+  ];
+  return Uint8Array.from(out);
+}
+
+async function send(
+  payer: KeyPairSigner,
+  instructions: Instruction[]
+): Promise<string> {
+  const rpc = createSolanaRpc(RPC_URL);
+  const sendAndConfirm = sendAndConfirmTransactionFactory({
+    rpc,
+    rpcSubscriptions: createSolanaRpcSubscriptions(WS_URL),
+  });
+  const { value: latestBlockhash } = await rpc.getLatestBlockhash().send();
+
+  const transactionMessage = pipe(
+    createTransactionMessage({ version: 0 }),
+    (tx) => setTransactionMessageFeePayerSigner(payer, tx),
+    (tx) => setTransactionMessageLifetimeUsingBlockhash(latestBlockhash, tx),
+    (tx) => appendTransactionMessageInstructions(instructions, tx)
+  );
+
+  const signedTx = await signTransactionMessageWithSigners(transactionMessage);
+  assertIsSendableTransaction(signedTx);
+  assertIsTransactionWithBlockhashLifetime(signedTx);
+  await sendAndConfirm(signedTx, { commitment: "confirmed" });
+  return getSignatureFromTransaction(signedTx);
+}
+
+function approveInstruction(
+  multisig: Address,
+  proposal: Address,
+  member: KeyPairSigner
+): Instruction {
+  return {
+    programAddress: SQUADS_PROGRAM_ID,
+    accounts: [
+      { address: multisig, role: 0 }, // readonly
+      { address: member.address, role: 3 }, // signer + writable
+      { address: proposal, role: 1 }, // writable
+    ],
+    data: Uint8Array.from([...SQUADS_INSTRUCTION.PROPOSAL_APPROVE, 0]), // no memo
+  };
+}
+
+/** Create a vault transaction and its proposal, and approve it as the creator */
+async function propose(
+  multisig: Address,
+  vaultIndex: number,
+  message: Uint8Array,
+  member: KeyPairSigner
+): Promise<bigint> {
+  const multisigData = await readAccount(multisig);
+  const index =
+    multisigData.readBigUInt64LE(MULTISIG_TRANSACTION_INDEX_OFFSET) + 1n;
+  const transaction = await getTransactionPda(multisig, index);
+  const proposal = await getProposalPda(multisig, index);
+
+  const messageLen = new Uint8Array(4);
+  new DataView(messageLen.buffer).setUint32(0, message.length, true);
+
+  const sig = await send(member, [
+    {
+      programAddress: SQUADS_PROGRAM_ID,
+      accounts: [
+        { address: multisig, role: 1 }, // writable
+        { address: transaction, role: 1 }, // writable
+        { address: member.address, role: 2 }, // signer (creator)
+        { address: member.address, role: 3 }, // signer + writable (rent payer)
+        { address: SYSTEM_PROGRAM_ADDRESS, role: 0 }, // readonly
+      ],
+      data: Uint8Array.from([
+        ...SQUADS_INSTRUCTION.VAULT_TRANSACTION_CREATE,
+        vaultIndex,
+        0, // ephemeral signers
+        ...messageLen,
+        ...message,
+        0, // no memo
+      ]),
+    },
+    {
+      programAddress: SQUADS_PROGRAM_ID,
+      accounts: [
+        { address: multisig, role: 0 }, // readonly
+        { address: proposal, role: 1 }, // writable
+        { address: member.address, role: 2 }, // signer (creator)
+        { address: member.address, role: 3 }, // signer + writable (rent payer)
+        { address: SYSTEM_PROGRAM_ADDRESS, role: 0 }, // readonly
+      ],
+      data: Uint8Array.from([
+        ...SQUADS_INSTRUCTION.PROPOSAL_CREATE,
+        ...u64Le(index),
+        0, // not a draft: open for voting
+      ]),
+    },
+    approveInstruction(multisig, proposal, member),
+  ]);
+
+  console.log(`Vault transaction ${index} proposed: ${sig.slice(0, 20)}...`);
+  console.log("Collect the remaining approvals, then execute:");
+  console.log(
+    `  npm run squads -- execute --multisig ${multisig} --index ${index}`
+  );
+  return index;
+}
+
+function printUsage() {
+  console.log(`
+Usage:
+  npm run squads -- hand-over --multisig <pubkey>
+  npm run squads -- propose-root --multisig <pubkey> --list <file>
+  npm run squads -- propose-admin --multisig <pubkey> --new-admin <pubkey>
+  npm run squads -- approve --multisig <pubkey> --index <n>
+  npm run squads -- execute --multisig <pubkey> --index <n>
+
+Commands:
+  hand-over       Make the vault the admin (signed by the current admin's --keypair)
+  propose-root    Propose setting the root to the list's root
+  propose-admin   Propose handing the state to <new-admin>
+  approve         Approve vault transaction <n>
+  execute         Execute approved vault transaction <n>
+
+Options:
+  --multisig <pubkey>    Squads multisig account
+  --vault-index <n>      Vault acting as admin (default: 0)
+  --admin <pubkey>       Admin the state was initialized with (default: the
+                         --keypair for hand-over, otherwise the vault)
+  --list <file>          New blacklist (JSON array of base58 pubkeys)
+  --new-admin <pubkey>   Admin to hand the state to
+  --index <n>            Vault transaction index printed by propose-*
+  --keypair <file>       Member / current admin (default: keypair/deployer.json)
+`);
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+async function handOver(statePda: Address, vault: Address, admin: KeyPairSigner) {
+  const data = new Uint8Array(2 + 32);
+  data[0] = INSTRUCTION.SET_ADMIN;
+  data[1] = INSTRUCTION_VERSION;
+  data.set(addressEncoder.encode(vault), 2);
+
+  const sig = await send(admin, [
+    {
+      programAddress: EXCLUSION_PROGRAM_ID,
+      accounts: [
+        { address: admin.address, role: 2 }, // signer
+        { address: statePda, role: 1 }, // writable
+      ],
+      data,
+    },
+  ]);
+  console.log(`Admin handed to vault ${vault}: ${sig.slice(0, 20)}...`);
+}
+
+async function proposeRoot(
+  multisig: Address,
+  vaultIndex: number,
+  statePda: Address,
+  listPath: string,
+  member: KeyPairSigner
+) {
+  const blacklist: string[] = JSON.parse(fs.readFileSync(listPath, "utf-8"));
+  const smt = new SparseMerkleTree();
+  for (const pubkey of blacklist) {
+    smt.insert(pubkeyToBytes(pubkey), 1n);
+  }
+  console.log(`Proposed root: ${fieldToHex(smt.getRoot())}`);
+
+  const data = new Uint8Array(2 + 32);
+  data[0] = INSTRUCTION.SET_SMT_ROOT;
+  data[1] = INSTRUCTION_VERSION;
+  data.set(Buffer.from(fieldToHex(smt.getRoot()).slice(2), "hex"), 2);
+
+  const vault = await getVaultPda(multisig, vaultIndex);
+  await propose(
+    multisig,
+    vaultIndex,
+    encodeVaultMessage(vault, [statePda], [NOOP_PROGRAM_ID], data),
+    member
+  );
+}
+
+async function proposeAdmin(
+  multisig: Address,
+  vaultIndex: number,
+  statePda: Address,
+  newAdmin: Address,
+  member: KeyPairSigner
+) {
+  const data = new Uint8Array(2 + 32);
+  data[0] = INSTRUCTION.SET_ADMIN;
+  data[1] = INSTRUCTION_VERSION;
+  data.set(addressEncoder.encode(newAdmin), 2);
+
+  const vault = await getVaultPda(multisig, vaultIndex);
+  console.log(`Proposed admin: ${newAdmin}`);
+  await propose(
+    multisig,
+    vaultIndex,
+    encodeVaultMessage(vault, [statePda], [], data),
+    member
+  );
+}
+
+async function approve(
+  multisig: Address,
+  index: bigint,
+  member: KeyPairSigner
+) {
+  const proposal = await getProposalPda(multisig, index);
+  const sig = await send(member, [
+    approveInstruction(multisig, proposal, member),
+  ]);
+  console.log(`Approved vault transaction ${index}: ${sig.slice(0, 20)}...`);
+}
+
+async function execute(
+  multisig: Address,
+  index: bigint,
+  member: KeyPairSigner
+) {
+  const transaction = await getTransactionPda(multisig, index);
+  const proposal = await getProposalPda(multisig, index);
+
+  // The message's account keys follow the ephemeral signer bumps
+  const data = await readAccount(transaction);
+  let offset = VAULT_TRANSACTION_EPHEMERAL_BUMPS_OFFSET;
+  offset += 4 + data.readUInt32LE(offset);
+  const [numSigners, numWritableSigners, numWritableNonSigners] = data.subarray(
+    offset,
+    offset + 3
+  );
+  offset += 3;
+  const keyCount = data.readUInt32LE(offset);
+  offset += 4;
+
+  // The vault is signed for by the Squads program, not this transaction
+  const messageAccounts = Array.from({ length: keyCount }, (_, i) => {
+    const key = addressDecoder.decode(
+      data.subarray(offset + 32 * i, offset + 32 * (i + 1))
+    );
+    const writable =
+      i < numSigners
+        ? i < numWritableSigners
+        : i - numSigners < numWritableNonSigners;
+    return { address: key, role: writable ? 1 : 0 } as const;
+  });
+
+  const sig = await send(member, [
+    {
+      programAddress: SQUADS_PROGRAM_ID,
+      accounts: [
+        { address: multisig, role: 0 }, // readonly
+        { address: proposal, role: 1 }, // writable
+        { address: transaction, role: 0 }, // readonly
+        { address: member.address, role: 2 }, // signer
+        ...messageAccounts,
+      ],
+      data: Uint8Array.from(SQUADS_INSTRUCTION.VAULT_TRANSACTION_EXECUTE),
+    },
+  ]);
+  console.log(`Executed vault transaction ${index}: ${sig.slice(0, 20)}...`);
+}
+
+// ============================================================================
+// Main
+// ============================================================================
+
+async function main() {
+  const [command, ...args] = process.argv.slice(2);
+  const argValue = (flag: string) => {
+    const idx = args.indexOf(flag);
+    return idx !== -1 ? args[idx + 1] : undefined;
+  };
+
+  const multisigArg = argValue("--multisig");
+  if (!multisigArg) {
+    printUsage();
+    process.exit(1);
+  }
+  const multisig = address(multisigArg);
+  const vaultIndex = Number(argValue("--vault-index") ?? 0);
+  const vault = await getVaultPda(multisig, vaultIndex);
+  const adminArg = argValue("--admin");
+  const listArg = argValue("--list");
+  const newAdminArg = argValue("--new-admin");
+  const indexArg = argValue("--index");
+  const member = await loadKeypair(argValue("--keypair") ?? memberWalletPath);
+
+  console.log(`Vault: ${vault}`);
+
+  // hand-over is signed by the current admin; proposals by the vault
+  const initialAdmin = adminArg
+    ? address(adminArg)
+    : command === "hand-over"
+      ? member.address
+      : vault;
+  const statePda = await getStatePda(initialAdmin);
+  console.log(`State account: ${statePda}\n`);
+
+  if (command === "hand-over") {
+    await handOver(statePda, vault, member);
+  } else if (command === "propose-root" && listArg) {
+    await initPoseidon();
+    await proposeRoot(multisig, vaultIndex, statePda, listArg, member);
+  } else if (command === "propose-admin" && newAdminArg) {
+    await proposeAdmin(
+      multisig,
+      vaultIndex,
+      statePda,
+      address(newAdminArg),
+      member
+    );
+  } else if (command === "approve" && indexArg) {
+    await approve(multisig, BigInt(indexArg), member);
+  } else if (command === "execute" && indexArg) {
+    await execute(multisig, BigInt(indexArg), member);
+  } else {
+    printUsage();
+    process.exit(1);
+  }
+}
+
+main().catch((err) => {
+  console.error(err);
+  process.exit(1);
+});
@@ -34,6 +34,7 @@ import {
   type Address,
   type KeyPairSigner,
   type ProgramDerivedAddressBump,
+  type Signature,
 } from "@solana/kit";
 import { getSetComputeUnitLimitInstruction } from "@solana-program/compute-budget";
 import {
@@ -77,6 +78,9 @@ const EXCLUSION_PROGRAM_ID = address(
     ))
 );
 
+// Events are CPI'd into the SPL noop program (program built with `noop-events`)
+const NOOP_PROGRAM_ID = address("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
 const circuitConfig: CircuitConfig = {
   circuitDir: path.join(process.cwd(), ".."),
   circuitName: "smt_exclusion",
@@ -89,8 +93,15 @@ const INSTRUCTION = {
   INITIALIZE: 0,
   SET_SMT_ROOT: 1,
   TRANSFER_SOL: 2,
+  MIGRATE_STATE: 22,
 };
 
+// Current state account size (layout v10); smaller accounts need MIGRATE_STATE
+const STATE_SIZE = 358;
+
+// Instruction data header: [discriminator, version]
+const INSTRUCTION_VERSION = 1;
+
 // ============================================================================
 // Helpers
 // ============================================================================
@@ -159,7 +170,7 @@ async function initializeState(
       { address: statePda, role: 1 }, // writable
       { address: SYSTEM_PROGRAM_ADDRESS, role: 0 }, // readonly
     ],
-    data: new Uint8Array([INSTRUCTION.INITIALIZE]),
+    data: new Uint8Array([INSTRUCTION.INITIALIZE, INSTRUCTION_VERSION]),
   };
 
   const transactionMessage = pipe(
@@ -176,6 +187,69 @@ async function initializeState(
   return getSignatureFromTransaction(signedTx);
 }
 
+// Upgrade a state account from an older program build to the current layout
+async function migrateStateIfNeeded(
+  ctx: RpcContext,
+  admin: KeyPairSigner
+): Promise<string | null> {
+  const [statePda] = await getStatePdaForAdmin(admin.address);
+  const account = await ctx.rpc
+    .getAccountInfo(statePda, { encoding: "base64" })
+    .send();
+  if (!account.value) {
+    return null;
+  }
+  const stateData = Buffer.from(account.value.data[0], "base64");
+  if (stateData.length >= STATE_SIZE) {
+    return null;
+  }
+
+  const { value: latestBlockhash } = await ctx.rpc.getLatestBlockhash().send();
+
+  const ix = {
+    programAddress: EXCLUSION_PROGRAM_ID,
+    accounts: [
+      { address: admin.address, role: 3 }, // signer + writable
+      { address: statePda, role: 1 }, // writable
+      { address: SYSTEM_PROGRAM_ADDRESS, role: 0 }, // readonly
+    ],
+    data: new Uint8Array([INSTRUCTION.MIGRATE_STATE, INSTRUCTION_VERSION]),
+  };
+
+  const transactionMessage = pipe(
+    createTransactionMessage({ version: 0 }),
+    (tx) => setTransactionMessageFeePayerSigner(admin, tx),
+    (tx) => setTransactionMessageLifetimeUsingBlockhash(latestBlockhash, tx),
+    (tx) => appendTransactionMessageInstructions([ix], tx)
+  );
+
+  const signedTx = await signTransactionMessageWithSigners(transactionMessage);
+  assertIsSendableTransaction(signedTx);
+  assertIsTransactionWithBlockhashLifetime(signedTx);
+  await ctx.sendAndConfirm(signedTx, { commitment: "confirmed" });
+  return getSignatureFromTransaction(signedTx);
+}
+
+// Compute units a confirmed transaction consumed, for comparing program builds
+async function getComputeUnits(
+  ctx: RpcContext,
+  signature: string
+): Promise<bigint | undefined> {
+  const tx = await ctx.rpc
+    .getTransaction(signature as Signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    })
+    .send();
+  return tx?.meta?.computeUnitsConsumed;
+}
+
+// Finalized slots are always in the SlotHashes sysvar the program checks
+// recent_slot against, and leave the proof most of its validity window
+async function getRecentSlot(ctx: RpcContext): Promise<bigint> {
+  return await ctx.rpc.getSlot({ commitment: "finalized" }).send();
+}
+
 async function setSmtRoot(
   ctx: RpcContext,
   admin: KeyPairSigner,
@@ -184,15 +258,17 @@ async function setSmtRoot(
   const [statePda] = await getStatePdaForAdmin(admin.address);
   const { value: latestBlockhash } = await ctx.rpc.getLatestBlockhash().send();
 
-  const data = new Uint8Array(1 + 32);
+  const data = new Uint8Array(2 + 32);
   data[0] = INSTRUCTION.SET_SMT_ROOT;
-  data.set(smtRoot, 1);
+  data[1] = INSTRUCTION_VERSION;
+  data.set(smtRoot, 2);
 
   const ix = {
     programAddress: EXCLUSION_PROGRAM_ID,
     accounts: [
       { address: admin.address, role: 2 }, // signer
       { address: statePda, role: 1 }, // writable
+      { address: NOOP_PROGRAM_ID, role: 0 }, // readonly
     ],
     data,
   };
@@ -223,12 +299,13 @@ async function transferSol(
   const [statePda] = await getStatePdaForAdmin(stateOwner);
   const { value: latestBlockhash } = await ctx.rpc.getLatestBlockhash().send();
 
-  const data = new Uint8Array(1 + 8 + 388 + 76);
+  const data = new Uint8Array(2 + 8 + 388 + witnessData.length);
   data[0] = INSTRUCTION.TRANSFER_SOL;
+  data[1] = INSTRUCTION_VERSION;
   const view = new DataView(data.buffer);
-  view.setBigUint64(1, amount, true);
-  data.set(proofData, 9);
-  data.set(witnessData, 9 + 388);
+  view.setBigUint64(2, amount, true);
+  data.set(proofData, 10);
+  data.set(witnessData, 10 + 388);
 
   const ix = {
     programAddress: EXCLUSION_PROGRAM_ID,
@@ -238,6 +315,7 @@ async function transferSol(
       { address: statePda, role: 0 }, // readonly
       { address: ZK_VERIFIER_PROGRAM_ID, role: 0 }, // readonly
       { address: SYSTEM_PROGRAM_ADDRESS, role: 0 }, // readonly
+      { address: NOOP_PROGRAM_ID, role: 0 }, // readonly
     ],
     data,
   };
@@ -427,8 +505,14 @@ async function main() {
     }
   }
 
+  const migrateSig = await migrateStateIfNeeded(ctx, admin);
+  if (migrateSig) {
+    console.log(`  State migrated: ${migrateSig.slice(0, 20)}...`);
+  }
+
   const setRootSig = await setSmtRoot(ctx, admin, smtRootBuffer);
   console.log(`  SMT root set: ${setRootSig.slice(0, 20)}...`);
+  console.log(`  Compute units: ${await getComputeUnits(ctx, setRootSig)}`);
   await new Promise((r) => setTimeout(r, 1000));
   console.log("");
 
@@ -459,6 +543,7 @@ async function main() {
   const allowedInputs: SmtExclusionInputs = {
     smt_root: smtRootHex,
     pubkey_hash: fieldToHex(allowedPubkeyHash),
+    recent_slot: (await getRecentSlot(ctx)).toString(),
     pubkey: allowedPubkeyBytes,
     siblings: allowedProof.siblings.map((s) => s.toString()),
     leaf_value: allowedProof.leafValue.toString(),
@@ -483,6 +568,7 @@ async function main() {
     );
     console.log(`\n  ✅ SUCCESS! Transfer completed`);
     console.log(`  TX: https://explorer.solana.com/tx/${sig}?cluster=devnet`);
+    console.log(`  Compute units: ${await getComputeUnits(ctx, sig)}`);
     test1Success = true;
     await new Promise((r) => setTimeout(r, 1000));
   } catch (err: any) {
@@ -551,6 +637,7 @@ async function main() {
   const blacklistedInputs: SmtExclusionInputs = {
     smt_root: smtRootHex,
     pubkey_hash: fieldToHex(blacklistedPubkeyHash),
+    recent_slot: (await getRecentSlot(ctx)).toString(),
     pubkey: blacklistedPubkeyBytes,
     siblings: blacklistedProof.siblings.map((s) => s.toString()),
     leaf_value: blacklistedProof.leafValue.toString(),
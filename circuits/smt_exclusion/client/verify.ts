@@ -94,6 +94,8 @@ async function main() {
     const inputs: SmtExclusionInputs = {
       smt_root: smtRoot,
       pubkey_hash: pubkeyHash,
+      // The verifier alone doesn't check recency; the exclusion program does
+      recent_slot: "0",
       pubkey: Array.from(pubkeyBytes),
       siblings: proof.siblings.map((s) => s.toString()),
       leaf_value: proof.leafValue.toString(),
@@ -0,0 +1,230 @@
+//! The program's errors, as `ProgramError::Custom` codes
+
+use solana_program_error::ProgramError;
+
+/// Custom errors - error code shown in logs as "Custom(N)"
+/// 0 = InvalidDataLength, 1 = InvalidStateAccount, etc.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(u32)]
+pub enum ExclusionError {
+    /// 0: Invalid instruction data length
+    InvalidDataLength = 0,
+    /// 1: Invalid state account discriminator
+    InvalidStateAccount = 1,
+    /// 2: SMT root in proof does not match stored root
+    SmtRootMismatch = 2,
+    /// 3: Pubkey hash in proof does not match signer
+    PubkeyHashMismatch = 3,
+    /// 4: Poseidon hash computation failed
+    PoseidonHashFailed = 4,
+    /// 5: Only admin can perform this action
+    UnauthorizedAdmin = 5,
+    /// 6: Invalid state account PDA
+    InvalidStatePda = 6,
+    /// 7: Invalid ZK verifier program
+    InvalidZkVerifier = 7,
+    /// 8: ZK proof failed client-side verification; on chain the verifier fails the transaction
+    ZkVerificationFailed = 8,
+    /// 9: Instruction data version is not supported by this program
+    UnsupportedInstructionVersion = 9,
+    /// 10: Invalid proof buffer PDA or account data
+    InvalidProofBuffer = 10,
+    /// 11: SMT root has passed its root_valid_until timestamp
+    RootExpired = 11,
+    /// 12: Old root in a tree-transition proof does not match stored root
+    TransitionRootMismatch = 12,
+    /// 13: Transfer must be a top-level instruction, not invoked via CPI
+    CpiNotAllowed = 13,
+    /// 14: Leaf update path does not reconstruct the stored root
+    LeafPathMismatch = 14,
+    /// 15: Invalid relay account PDA or account data
+    InvalidRelayAccount = 15,
+    /// 16: Missing or invalid ed25519 authorization from the sender
+    InvalidSenderAuthorization = 16,
+    /// 17: Relayed transfer nonce does not match the relay account
+    RelayNonceMismatch = 17,
+    /// 18: Proof's recent_slot is older than MAX_PROOF_AGE_SLOTS or in the future
+    StaleProof = 18,
+    /// 19: Proof's recent_slot is not in the SlotHashes sysvar
+    UnknownRecentSlot = 19,
+    /// 20: Invalid escrow PDA or account data
+    InvalidEscrowAccount = 20,
+    /// 21: Escrow cannot be claimed before its release time
+    EscrowLocked = 21,
+    /// 22: Escrow requires the recipient's exclusion proof at claim time
+    RecipientProofRequired = 22,
+    /// 23: Vault account does not match the admin's vault PDA
+    InvalidVaultAccount = 23,
+    /// 24: Missing or invalid rate limit PDA or account data
+    InvalidRateLimitAccount = 24,
+    /// 25: Transfer would exceed the sender's 24-hour limit
+    RateLimitExceeded = 25,
+    /// 26: Missing or invalid treasury PDA or account data
+    InvalidTreasuryAccount = 26,
+    /// 27: Fee is above MAX_FEE_BPS
+    InvalidFee = 27,
+    /// 28: State account uses an older layout; run MIGRATE_STATE first
+    StateMigrationRequired = 28,
+    /// 29: Hash scheme is unknown or not compiled into this build
+    UnsupportedHashScheme = 29,
+    /// 30: State account was flagged by a successful CHALLENGE
+    StateChallenged = 30,
+    /// 31: Bond account is not the state's bond PDA
+    InvalidBondAccount = 31,
+    /// 32: No bond withdrawal was requested, or its delay has not passed
+    BondWithdrawalLocked = 32,
+    /// 33: Exclusion queue is not the state's queue PDA
+    InvalidExclusionQueue = 33,
+    /// 34: Exclusion queue holds EXCLUSION_QUEUE_CAPACITY keys; the admin must drain it
+    ExclusionQueueFull = 34,
+    /// 35: Config account is not the program config PDA
+    InvalidConfigAccount = 35,
+    /// 36: Transfers are paused by the program config
+    ProgramPaused = 36,
+    /// 37: Signer is not the program's upgrade authority
+    UnauthorizedUpgradeAuthority = 37,
+    /// 38: Verifier registry is not the registry PDA, or the signer is not its authority
+    InvalidVerifierRegistry = 38,
+    /// 39: Verifier registry holds MAX_REGISTERED_VERIFIERS programs
+    VerifierRegistryFull = 39,
+    /// 40: Compressed proof has a point that is not on the curve, or more than one commitment
+    InvalidCompressedProof = 40,
+    /// 41: Attestation account is not the subject's attestation PDA for the state
+    InvalidAttestationAccount = 41,
+    /// 42: Not the Light system program, or not this program's Light CPI authority
+    InvalidLightAccount = 42,
+    /// 43: Not the Wormhole core bridge, or a message or emitter account is not this program's PDA
+    InvalidWormholeAccount = 43,
+    /// 44: Not the Bubblegum program
+    InvalidBubblegumProgram = 44,
+    /// 45: Tree kind is unknown, or the instruction needs a sparse Merkle tree
+    UnsupportedTreeKind = 45,
+    /// 46: Tree depth is unsupported, or the instruction needs the default depth
+    UnsupportedTreeDepth = 46,
+}
+
+impl From<ExclusionError> for ProgramError {
+    fn from(e: ExclusionError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl ExclusionError {
+    /// The error a `Custom(code)` program error stands for
+    pub fn from_code(code: u32) -> Option<Self> {
+        use ExclusionError::*;
+        Some(match code {
+            0 => InvalidDataLength,
+            1 => InvalidStateAccount,
+            2 => SmtRootMismatch,
+            3 => PubkeyHashMismatch,
+            4 => PoseidonHashFailed,
+            5 => UnauthorizedAdmin,
+            6 => InvalidStatePda,
+            7 => InvalidZkVerifier,
+            8 => ZkVerificationFailed,
+            9 => UnsupportedInstructionVersion,
+            10 => InvalidProofBuffer,
+            11 => RootExpired,
+            12 => TransitionRootMismatch,
+            13 => CpiNotAllowed,
+            14 => LeafPathMismatch,
+            15 => InvalidRelayAccount,
+            16 => InvalidSenderAuthorization,
+            17 => RelayNonceMismatch,
+            18 => StaleProof,
+            19 => UnknownRecentSlot,
+            20 => InvalidEscrowAccount,
+            21 => EscrowLocked,
+            22 => RecipientProofRequired,
+            23 => InvalidVaultAccount,
+            24 => InvalidRateLimitAccount,
+            25 => RateLimitExceeded,
+            26 => InvalidTreasuryAccount,
+            27 => InvalidFee,
+            28 => StateMigrationRequired,
+            29 => UnsupportedHashScheme,
+            30 => StateChallenged,
+            31 => InvalidBondAccount,
+            32 => BondWithdrawalLocked,
+            33 => InvalidExclusionQueue,
+            34 => ExclusionQueueFull,
+            35 => InvalidConfigAccount,
+            36 => ProgramPaused,
+            37 => UnauthorizedUpgradeAuthority,
+            38 => InvalidVerifierRegistry,
+            39 => VerifierRegistryFull,
+            40 => InvalidCompressedProof,
+            41 => InvalidAttestationAccount,
+            42 => InvalidLightAccount,
+            43 => InvalidWormholeAccount,
+            44 => InvalidBubblegumProgram,
+            45 => UnsupportedTreeKind,
+            46 => UnsupportedTreeDepth,
+            _ => return None,
+        })
+    }
+
+    /// What went wrong, in a sentence
+    pub fn description(&self) -> &'static str {
+        use ExclusionError::*;
+        match self {
+            InvalidDataLength => "Invalid instruction data length",
+            InvalidStateAccount => "Invalid state account discriminator",
+            SmtRootMismatch => "SMT root in proof does not match stored root; the proof was generated against a stale root",
+            PubkeyHashMismatch => "Pubkey hash in proof does not match signer",
+            PoseidonHashFailed => "Poseidon hash computation failed",
+            UnauthorizedAdmin => "Only admin can perform this action",
+            InvalidStatePda => "Invalid state account PDA",
+            InvalidZkVerifier => "Invalid ZK verifier program",
+            ZkVerificationFailed => "ZK proof verification failed",
+            UnsupportedInstructionVersion => "Instruction data version is not supported by this program",
+            InvalidProofBuffer => "Invalid proof buffer PDA or account data",
+            RootExpired => "SMT root has passed its root_valid_until timestamp",
+            TransitionRootMismatch => "Old root in a tree-transition proof does not match stored root",
+            CpiNotAllowed => "Transfer must be a top-level instruction, not invoked via CPI",
+            LeafPathMismatch => "Leaf update path does not reconstruct the stored root",
+            InvalidRelayAccount => "Invalid relay account PDA or account data",
+            InvalidSenderAuthorization => "Missing or invalid ed25519 authorization from the sender",
+            RelayNonceMismatch => "Relayed transfer nonce does not match the relay account",
+            StaleProof => "Proof's recent_slot is older than MAX_PROOF_AGE_SLOTS or in the future",
+            UnknownRecentSlot => "Proof's recent_slot is not in the SlotHashes sysvar",
+            InvalidEscrowAccount => "Invalid escrow PDA or account data",
+            EscrowLocked => "Escrow cannot be claimed before its release time",
+            RecipientProofRequired => "Escrow requires the recipient's exclusion proof at claim time",
+            InvalidVaultAccount => "Vault account does not match the admin's vault PDA",
+            InvalidRateLimitAccount => "Missing or invalid rate limit PDA or account data",
+            RateLimitExceeded => "Transfer would exceed the sender's 24-hour limit",
+            InvalidTreasuryAccount => "Missing or invalid treasury PDA or account data",
+            InvalidFee => "Fee is above MAX_FEE_BPS",
+            StateMigrationRequired => "State account uses an older layout; run MIGRATE_STATE first",
+            UnsupportedHashScheme => "Hash scheme is unknown or not compiled into this build",
+            StateChallenged => "State account was flagged by a successful CHALLENGE",
+            InvalidBondAccount => "Bond account is not the state's bond PDA",
+            BondWithdrawalLocked => "No bond withdrawal was requested, or its delay has not passed",
+            InvalidExclusionQueue => "Exclusion queue is not the state's queue PDA",
+            ExclusionQueueFull => "Exclusion queue holds EXCLUSION_QUEUE_CAPACITY keys; the admin must drain it",
+            InvalidConfigAccount => "Config account is not the program config PDA",
+            ProgramPaused => "Transfers are paused by the program config",
+            UnauthorizedUpgradeAuthority => "Signer is not the program's upgrade authority",
+            InvalidVerifierRegistry => "Verifier registry is not the registry PDA, or the signer is not its authority",
+            VerifierRegistryFull => "Verifier registry holds MAX_REGISTERED_VERIFIERS programs",
+            InvalidCompressedProof => "Compressed proof has a point that is not on the curve, or more than one commitment",
+            InvalidAttestationAccount => "Attestation account is not the subject's attestation PDA for the state",
+            InvalidLightAccount => "Not the Light system program, or not this program's Light CPI authority",
+            InvalidWormholeAccount => "Not the Wormhole core bridge, or a message or emitter account is not this program's PDA",
+            InvalidBubblegumProgram => "Not the Bubblegum program",
+            UnsupportedTreeKind => "Tree kind is unknown, or the instruction needs a sparse Merkle tree",
+            UnsupportedTreeDepth => "Tree depth is unsupported, or the instruction needs the default depth",
+        }
+    }
+}
+
+impl core::fmt::Display for ExclusionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{self:?}: {}", self.description())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ExclusionError {}
@@ -0,0 +1,151 @@
+//! Instruction discriminators and the sizes of the data clients send
+//!
+//! Every instruction's data begins with `[discriminator: u8][version: u8]`;
+//! the program's `instruction` module parses the payload that follows.
+
+pub const INITIALIZE: u8 = 0;
+pub const SET_SMT_ROOT: u8 = 1;
+pub const TRANSFER_SOL: u8 = 2;
+pub const CREATE_PROOF_BUFFER: u8 = 3;
+pub const WRITE_PROOF_CHUNK: u8 = 4;
+pub const TRANSFER_FROM_BUFFER: u8 = 5;
+pub const SUBMIT_ROOT_TRANSITION: u8 = 6;
+pub const SET_TRANSFER_FLAGS: u8 = 7;
+pub const INSERT_LEAF: u8 = 8;
+pub const REMOVE_LEAF: u8 = 9;
+pub const TRANSFER_SOL_DUAL: u8 = 10;
+pub const CREATE_RELAY_ACCOUNT: u8 = 11;
+pub const TRANSFER_SOL_RELAYED: u8 = 12;
+pub const CLOSE_RELAY_ACCOUNT: u8 = 13;
+pub const TRANSFER_TO_ESCROW: u8 = 14;
+pub const CLAIM_FROM_ESCROW: u8 = 15;
+pub const TRANSFER_FROM_VAULT: u8 = 16;
+pub const SET_RATE_LIMIT: u8 = 17;
+pub const SET_FEE: u8 = 18;
+pub const WITHDRAW_FEES: u8 = 19;
+pub const SET_APPROVED_CALLERS: u8 = 20;
+pub const TRANSFER_SOL_BATCH: u8 = 21;
+pub const MIGRATE_STATE: u8 = 22;
+pub const SET_HASH_SCHEME: u8 = 23;
+pub const SET_VERIFIER_FLAGS: u8 = 24;
+pub const SET_METADATA: u8 = 25;
+pub const CHALLENGE: u8 = 26;
+pub const DEPOSIT_BOND: u8 = 27;
+pub const REQUEST_BOND_WITHDRAWAL: u8 = 28;
+pub const WITHDRAW_BOND: u8 = 29;
+pub const SELF_EXCLUDE: u8 = 30;
+pub const SET_CONFIG: u8 = 31;
+pub const LINK_CONFIG: u8 = 32;
+pub const SET_REGISTRY_AUTHORITY: u8 = 33;
+pub const REGISTER_VERIFIER: u8 = 34;
+pub const UNREGISTER_VERIFIER: u8 = 35;
+pub const TRANSFER_SOL_COMPRESSED: u8 = 36;
+pub const TRANSFER_WSOL: u8 = 37;
+pub const TRANSFER_SOL_WITH_SEED: u8 = 38;
+pub const ATTEST: u8 = 39;
+pub const SET_ADMIN: u8 = 40;
+pub const ATTEST_COMPRESSED: u8 = 41;
+pub const PUBLISH_ROOT: u8 = 42;
+pub const TRANSFER_CNFT: u8 = 43;
+pub const SET_TREE_KIND: u8 = 44;
+pub const SET_TREE_DEPTH: u8 = 45;
+
+/// The constant's name for `discriminator`, as clients show instructions
+pub fn name(discriminator: u8) -> Option<&'static str> {
+    Some(match discriminator {
+        INITIALIZE => "INITIALIZE",
+        SET_SMT_ROOT => "SET_SMT_ROOT",
+        TRANSFER_SOL => "TRANSFER_SOL",
+        CREATE_PROOF_BUFFER => "CREATE_PROOF_BUFFER",
+        WRITE_PROOF_CHUNK => "WRITE_PROOF_CHUNK",
+        TRANSFER_FROM_BUFFER => "TRANSFER_FROM_BUFFER",
+        SUBMIT_ROOT_TRANSITION => "SUBMIT_ROOT_TRANSITION",
+        SET_TRANSFER_FLAGS => "SET_TRANSFER_FLAGS",
+        INSERT_LEAF => "INSERT_LEAF",
+        REMOVE_LEAF => "REMOVE_LEAF",
+        TRANSFER_SOL_DUAL => "TRANSFER_SOL_DUAL",
+        CREATE_RELAY_ACCOUNT => "CREATE_RELAY_ACCOUNT",
+        TRANSFER_SOL_RELAYED => "TRANSFER_SOL_RELAYED",
+        CLOSE_RELAY_ACCOUNT => "CLOSE_RELAY_ACCOUNT",
+        TRANSFER_TO_ESCROW => "TRANSFER_TO_ESCROW",
+        CLAIM_FROM_ESCROW => "CLAIM_FROM_ESCROW",
+        TRANSFER_FROM_VAULT => "TRANSFER_FROM_VAULT",
+        SET_RATE_LIMIT => "SET_RATE_LIMIT",
+        SET_FEE => "SET_FEE",
+        WITHDRAW_FEES => "WITHDRAW_FEES",
+        SET_APPROVED_CALLERS => "SET_APPROVED_CALLERS",
+        TRANSFER_SOL_BATCH => "TRANSFER_SOL_BATCH",
+        MIGRATE_STATE => "MIGRATE_STATE",
+        SET_HASH_SCHEME => "SET_HASH_SCHEME",
+        SET_VERIFIER_FLAGS => "SET_VERIFIER_FLAGS",
+        SET_METADATA => "SET_METADATA",
+        CHALLENGE => "CHALLENGE",
+        DEPOSIT_BOND => "DEPOSIT_BOND",
+        REQUEST_BOND_WITHDRAWAL => "REQUEST_BOND_WITHDRAWAL",
+        WITHDRAW_BOND => "WITHDRAW_BOND",
+        SELF_EXCLUDE => "SELF_EXCLUDE",
+        SET_CONFIG => "SET_CONFIG",
+        LINK_CONFIG => "LINK_CONFIG",
+        SET_REGISTRY_AUTHORITY => "SET_REGISTRY_AUTHORITY",
+        REGISTER_VERIFIER => "REGISTER_VERIFIER",
+        UNREGISTER_VERIFIER => "UNREGISTER_VERIFIER",
+        TRANSFER_SOL_COMPRESSED => "TRANSFER_SOL_COMPRESSED",
+        TRANSFER_WSOL => "TRANSFER_WSOL",
+        TRANSFER_SOL_WITH_SEED => "TRANSFER_SOL_WITH_SEED",
+        ATTEST => "ATTEST",
+        SET_ADMIN => "SET_ADMIN",
+        ATTEST_COMPRESSED => "ATTEST_COMPRESSED",
+        PUBLISH_ROOT => "PUBLISH_ROOT",
+        TRANSFER_CNFT => "TRANSFER_CNFT",
+        SET_TREE_KIND => "SET_TREE_KIND",
+        SET_TREE_DEPTH => "SET_TREE_DEPTH",
+        _ => return None,
+    })
+}
+
+/// Highest instruction data version this program understands
+///
+/// - 1: initial layout
+/// - 2: SET_SMT_ROOT appends root_valid_until
+/// - 3: SET_SMT_ROOT appends metadata
+/// - 4: INITIALIZE appends bond_lamports
+pub const CURRENT_VERSION: u8 = 4;
+
+/// Highest data version `discriminator` accepts: the version that last
+/// changed its layout, or 1 for instructions whose layout never changed
+pub const fn max_version(discriminator: u8) -> u8 {
+    match discriminator {
+        INITIALIZE => 4,
+        SET_SMT_ROOT => 3,
+        CLAIM_FROM_ESCROW => 2,
+        _ => 1,
+    }
+}
+
+/// Header size: 1 (discriminator) + 1 (version)
+pub const HEADER_LEN: usize = 2;
+
+/// Reserved trailing space accepted (and ignored) after the known fields
+pub const RESERVED_LEN: usize = 64;
+
+/// Groth16 proof size produced by sunspot/gnark
+pub const PROOF_SIZE: usize = 388;
+
+/// Proof size with every point compressed to its x coordinate: Ar (32) + Bs (64)
+/// + Krs (32) + commitment count (4) + commitment (32) + commitment PoK (32)
+pub const COMPRESSED_PROOF_SIZE: usize = 32 + 64 + 32 + 4 + 32 + 32;
+
+/// Public witness size: 12-byte header + smt_root (32) + pubkey_hash (32) + recent_slot (32)
+pub const WITNESS_SIZE: usize = 12 + 32 + 32 + 32;
+
+/// Tree-transition public witness size: 12-byte header + old_root + new_root + key
+pub const TRANSITION_WITNESS_SIZE: usize = 12 + 32 + 32 + 32;
+
+/// gnark public witness header: 4 (nb_public) + 4 (nb_secret) + 4 (vector length)
+pub const WITNESS_HEADER_SIZE: usize = 12;
+
+/// Number of public inputs in the exclusion circuit (smt_root, pubkey_hash, recent_slot)
+pub const NUM_PUBLIC_INPUTS: u32 = 3;
+
+/// Off-chain list pointer size (e.g. an IPFS CID or a URL hash, zero-padded)
+pub const METADATA_SIZE: usize = 64;
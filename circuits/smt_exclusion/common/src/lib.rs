@@ -0,0 +1,74 @@
+//! Definitions the program, its clients and its tests must agree on
+//!
+//! Instruction discriminators, account layouts, PDA seeds, errors, and the
+//! proof and witness types, each defined once, here. `no_std`, so the program
+//! builds it; `pubkey_to_index` is built with the Poseidon syscall on chain
+//! and, with the default `std` feature, with light-poseidon, the library
+//! behind that syscall, everywhere else.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![allow(unexpected_cfgs)]
+#![allow(deprecated)]
+
+pub mod error;
+pub mod instruction;
+pub mod seeds;
+pub mod state;
+pub mod witness;
+
+pub use crate::{
+    error::ExclusionError,
+    state::State,
+    witness::{CompressedProof, Proof, PublicWitness},
+};
+
+#[cfg(all(feature = "std", not(target_os = "solana")))]
+use std::cell::RefCell;
+
+#[cfg(all(feature = "std", not(target_os = "solana")))]
+use ark_bn254::Fr;
+#[cfg(all(feature = "std", not(target_os = "solana")))]
+use light_poseidon::{Poseidon, PoseidonBytesHasher};
+
+#[cfg(all(feature = "std", not(target_os = "solana")))]
+thread_local! {
+    /// Circom-compatible Poseidon, as behind the `sol_poseidon` syscall. Its
+    /// parameters take a while to set up, so each thread keeps one.
+    static POSEIDON: RefCell<Poseidon<Fr>> =
+        RefCell::new(Poseidon::<Fr>::new_circom(2).expect("two inputs are supported"));
+}
+
+/// Poseidon hash of two big-endian field elements
+#[cfg(all(feature = "std", not(target_os = "solana")))]
+fn poseidon_hash_2(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    POSEIDON
+        .with_borrow_mut(|poseidon| poseidon.hash_bytes_be(&[left, right]))
+        .expect("inputs are field elements")
+}
+
+/// Poseidon hash of two big-endian field elements
+#[cfg(target_os = "solana")]
+fn poseidon_hash_2(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    use solana_poseidon::{hashv, Endianness, Parameters};
+
+    hashv(Parameters::Bn254X5, Endianness::BigEndian, &[left, right])
+        .expect("inputs are field elements")
+        .to_bytes()
+}
+
+/// A pubkey's Poseidon hash, big-endian: the circuit's `pubkey_to_index` and
+/// public `pubkey_hash`, the program's check of it, and the tree's leaf index
+/// before it is cut to the tree's depth
+///
+/// `poseidon(low, high)`, where `low` and `high` are the pubkey's first and
+/// last 16 bytes read as little-endian integers.
+#[cfg(any(feature = "std", target_os = "solana"))]
+pub fn pubkey_to_index(pubkey: &[u8; 32]) -> [u8; 32] {
+    let mut low = [0u8; 32];
+    let mut high = [0u8; 32];
+    for i in 0..16 {
+        low[31 - i] = pubkey[i];
+        high[31 - i] = pubkey[16 + i];
+    }
+    poseidon_hash_2(&low, &high)
+}
@@ -0,0 +1,35 @@
+//! PDA seed prefixes. Each PDA's seeds are its prefix, then the accounts or
+//! values listed.
+
+/// State account: admin
+pub const STATE: &[u8] = b"state";
+/// Fee treasury: state
+pub const TREASURY: &[u8] = b"treasury";
+/// Admin bond: state
+pub const BOND: &[u8] = b"bond";
+/// Self-exclusion queue: state
+pub const EXCLUSION_QUEUE: &[u8] = b"exclusion_queue";
+/// Program config; no further seeds
+pub const CONFIG: &[u8] = b"config";
+/// Verifier registry; no further seeds
+pub const VERIFIER_REGISTRY: &[u8] = b"verifier_registry";
+/// Proof buffer: owner
+pub const PROOF_BUFFER: &[u8] = b"proof_buffer";
+/// Relay account: sender
+pub const RELAY: &[u8] = b"relay";
+/// Escrow: sender, recipient, escrow ID (u64 LE)
+pub const ESCROW: &[u8] = b"escrow";
+/// Admin vault: admin
+pub const VAULT: &[u8] = b"vault";
+/// Rate limit: state, sender
+pub const RATE_LIMIT: &[u8] = b"rate_limit";
+/// Attestation: state, subject
+pub const ATTESTATION: &[u8] = b"attestation";
+/// Temporary wSOL account: sender
+pub const WSOL: &[u8] = b"wsol";
+/// Wormhole message: state, root_seq (u64 LE)
+pub const WORMHOLE_MESSAGE: &[u8] = b"wormhole_message";
+/// Wormhole emitter; no further seeds
+pub const EMITTER: &[u8] = b"emitter";
+/// Light CPI authority; no further seeds
+pub const CPI_AUTHORITY: &[u8] = b"cpi_authority";
@@ -0,0 +1,369 @@
+//! Account discriminators, sizes and layouts, and the flags stored in them
+
+use crate::{
+    error::ExclusionError,
+    instruction::{METADATA_SIZE, PROOF_SIZE, WITNESS_SIZE},
+};
+
+/// State account size: 8 (discriminator) + 1 (version) + 32 (admin) + 32 (smt_root)
+/// + 8 (root_valid_until) + 1 (transfer_flags) + 8 (daily_limit) + 2 (fee_bps)
+/// + 8 (root_seq) + 1 (approved caller count) + 32 * MAX_APPROVED_CALLERS
+/// + 1 (bump) + 1 (treasury_bump) + 1 (hash_scheme) + 1 (verifier_flags)
+/// + 64 (metadata) + 1 (challenged) + 1 (bond_bump) + 8 (bond_amount)
+/// + 8 (bond_withdrawal_amount) + 8 (bond_withdrawal_at) + 32 (config)
+/// + 1 (tree_kind) + 2 (tree_depth) = 358 bytes
+pub const STATE_SIZE: usize =
+    (8 + 1 + 32 + 32 + 8 + 1 + 8 + 2 + 8 + 1 + 32 * MAX_APPROVED_CALLERS + 1 + 1 + 1 + 1)
+        + (64 + 1 + 1 + 8 + 8 + 8 + 32 + 1 + 2);
+
+/// Current state layout version, stored after the discriminator
+///
+/// - 0: unversioned layouts, which grew by appending zero-defaulted fields
+/// - 1: version byte added
+/// - 2: state and treasury PDA bumps appended
+/// - 3: hash_scheme appended
+/// - 4: verifier_flags appended
+/// - 5: metadata appended
+/// - 6: challenged appended
+/// - 7: bond accounting appended
+/// - 8: config appended
+/// - 9: tree_kind appended
+/// - 10: tree_depth appended
+pub const STATE_VERSION: u8 = 10;
+
+/// Largest unversioned state account; anything bigger carries a version byte
+pub const UNVERSIONED_STATE_MAX_SIZE: usize = 228;
+
+/// Byte offsets of state account fields in the current layout
+pub mod state_offsets {
+    pub const VERSION: usize = 8;
+    pub const ADMIN: usize = 9;
+    pub const SMT_ROOT: usize = 41;
+    pub const ROOT_VALID_UNTIL: usize = 73;
+    pub const TRANSFER_FLAGS: usize = 81;
+    pub const DAILY_LIMIT: usize = 82;
+    pub const FEE_BPS: usize = 90;
+    pub const ROOT_SEQ: usize = 92;
+    pub const APPROVED_CALLER_COUNT: usize = 100;
+    pub const APPROVED_CALLERS: usize = 101;
+    pub const BUMP: usize = 229;
+    pub const TREASURY_BUMP: usize = 230;
+    pub const HASH_SCHEME: usize = 231;
+    pub const VERIFIER_FLAGS: usize = 232;
+    pub const METADATA: usize = 233;
+    pub const CHALLENGED: usize = 297;
+    pub const BOND_BUMP: usize = 298;
+    pub const BOND_AMOUNT: usize = 299;
+    pub const BOND_WITHDRAWAL_AMOUNT: usize = 307;
+    pub const BOND_WITHDRAWAL_AT: usize = 315;
+    pub const CONFIG: usize = 323;
+    pub const TREE_KIND: usize = 355;
+    pub const TREE_DEPTH: usize = 356;
+}
+
+/// Byte offsets of program config fields
+pub mod config_offsets {
+    pub const BUMP: usize = 8;
+    pub const PAUSED: usize = 9;
+    pub const ZK_VERIFIER: usize = 10;
+    pub const TRANSITION_VERIFIER: usize = 42;
+    pub const MAX_PROOF_AGE_SLOTS: usize = 74;
+    pub const MAX_FEE_BPS: usize = 82;
+}
+
+/// Byte offsets of verifier registry fields
+pub mod verifier_registry_offsets {
+    pub const BUMP: usize = 8;
+    pub const AUTHORITY: usize = 9;
+    pub const COUNT: usize = 41;
+    /// `count` entries of VERIFIER_REGISTRY_ENTRY_SIZE
+    pub const VERIFIERS: usize = 42;
+}
+
+/// Proving backend options stored in state (`verifier_flags`)
+pub mod verifier_flags {
+    /// Public witness field elements are little-endian (e.g. Barretenberg)
+    /// rather than gnark's big-endian
+    pub const WITNESS_LITTLE_ENDIAN: u8 = 1 << 0;
+}
+
+/// Hash used for pubkey_hash, the leaf index, stored in state (`hash_scheme`).
+/// Tree nodes always use Poseidon.
+pub mod hash_scheme {
+    /// Circom-compatible Poseidon (Bn254X5), via the Poseidon syscall
+    pub const POSEIDON: u8 = 0;
+    /// Poseidon2 as in Noir's stdlib, computed in-program (`poseidon2` feature)
+    pub const POSEIDON2: u8 = 1;
+}
+
+/// Tree the stored root commits to, stored in state (`tree_kind`). Both prove
+/// exclusion with the same public witness; the kind picks the verifier.
+pub mod tree_kind {
+    /// Sparse Merkle tree, `tree_depth` levels deep (`circuits/smt_exclusion`)
+    pub const SPARSE: u8 = 0;
+    /// Indexed Merkle tree of sorted keys (`circuits/imt_exclusion`)
+    pub const INDEXED: u8 = 1;
+}
+
+/// Programs that may CPI into gated transfers under REQUIRE_APPROVED_CALLER
+pub const MAX_APPROVED_CALLERS: usize = 4;
+
+/// Maximum age of a proof's recent_slot, in slots (~2 minutes at 400ms/slot)
+pub const MAX_PROOF_AGE_SLOTS: u64 = 300;
+
+/// Rate limit account size: 8 (discriminator) + 8 (last_hour) + 24 hourly totals (8 each)
+/// = 208 bytes
+pub const RATE_LIMIT_ACCOUNT_SIZE: usize = 8 + 8 + RATE_LIMIT_BUCKETS * 8;
+
+/// Rate limit account discriminator
+pub const RATE_LIMIT_ACCOUNT_DISCRIMINATOR: [u8; 8] =
+    [0x72, 0x61, 0x74, 0x65, 0x5f, 0x6c, 0x69, 0x6d]; // "rate_lim"
+
+/// Hourly buckets in a rate limit account, covering a rolling 24 hours
+pub const RATE_LIMIT_BUCKETS: usize = 24;
+
+/// Treasury account size: 8 (discriminator); fees accrue as its lamports
+pub const TREASURY_ACCOUNT_SIZE: usize = 8;
+
+/// Treasury account discriminator
+pub const TREASURY_ACCOUNT_DISCRIMINATOR: [u8; 8] =
+    [0x74, 0x72, 0x65, 0x61, 0x73, 0x75, 0x72, 0x79]; // "treasury"
+
+/// Bond account size: 8 (discriminator); the bond is held as its lamports
+pub const BOND_ACCOUNT_SIZE: usize = 8;
+
+/// Bond account discriminator
+pub const BOND_ACCOUNT_DISCRIMINATOR: [u8; 8] = [0x62, 0x6f, 0x6e, 0x64, 0x5f, 0x61, 0x63, 0x63]; // "bond_acc"
+
+/// Seconds between REQUEST_BOND_WITHDRAWAL and WITHDRAW_BOND, so a bond stays
+/// slashable while challenges against the admin's latest roots are submitted
+pub const BOND_WITHDRAWAL_DELAY: i64 = 7 * 24 * 60 * 60;
+
+/// Pubkeys an exclusion queue holds before the admin must drain it
+pub const EXCLUSION_QUEUE_CAPACITY: usize = 32;
+
+/// Exclusion queue size: 8 (discriminator) + 1 (count) + 32 per queued pubkey
+/// = 1033 bytes
+pub const EXCLUSION_QUEUE_SIZE: usize = 8 + 1 + 32 * EXCLUSION_QUEUE_CAPACITY;
+
+/// Exclusion queue discriminator
+pub const EXCLUSION_QUEUE_DISCRIMINATOR: [u8; 8] = [0x65, 0x78, 0x63, 0x6c, 0x5f, 0x71, 0x75, 0x65]; // "excl_que"
+
+/// Fee denominator: fees are in basis points of the transferred amount
+pub const MAX_FEE_BPS: u16 = 10_000;
+
+/// Program config size: 8 (discriminator) + 1 (bump) + 1 (paused) + 32 (zk_verifier)
+/// + 32 (transition_verifier) + 8 (max_proof_age_slots) + 2 (max_fee_bps) = 84 bytes
+pub const CONFIG_SIZE: usize = 8 + 1 + 1 + 32 + 32 + 8 + 2;
+
+/// Program config discriminator
+pub const CONFIG_DISCRIMINATOR: [u8; 8] = [0x70, 0x72, 0x6f, 0x67, 0x5f, 0x63, 0x66, 0x67]; // "prog_cfg"
+
+/// Exclusion verifiers the registry can list besides each state's configured one
+pub const MAX_REGISTERED_VERIFIERS: usize = 8;
+
+/// Verifier registry entry size: 32 (program ID) + 32 (circuit hash) + 1 (tree_kind)
+/// + 2 (tree_depth, u16 LE) = 67 bytes
+///
+/// A verifier build checks proofs for one tree kind and depth, so states of any
+/// other reject it.
+pub const VERIFIER_REGISTRY_ENTRY_SIZE: usize = 32 + 32 + 1 + 2;
+
+/// Verifier registry size: 8 (discriminator) + 1 (bump) + 32 (authority) + 1 (count)
+/// + VERIFIER_REGISTRY_ENTRY_SIZE per verifier = 578 bytes
+pub const VERIFIER_REGISTRY_SIZE: usize =
+    8 + 1 + 32 + 1 + VERIFIER_REGISTRY_ENTRY_SIZE * MAX_REGISTERED_VERIFIERS;
+
+/// Verifier registry discriminator
+pub const VERIFIER_REGISTRY_DISCRIMINATOR: [u8; 8] =
+    [0x76, 0x72, 0x66, 0x5f, 0x72, 0x65, 0x67, 0x79]; // "vrf_regy"
+
+/// Attestation account size: 8 (discriminator) + 32 (subject) + 32 (state)
+/// + 32 (smt_root) + 8 (slot) = 112 bytes
+pub const ATTESTATION_SIZE: usize = 8 + 32 + 32 + 32 + 8;
+
+/// Attestation account discriminator
+pub const ATTESTATION_DISCRIMINATOR: [u8; 8] = [0x61, 0x74, 0x74, 0x65, 0x73, 0x74, 0x5f, 0x5f]; // "attest__"
+
+/// Byte offsets of attestation fields
+pub mod attestation_offsets {
+    pub const SUBJECT: usize = 8;
+    pub const STATE: usize = 40;
+    pub const SMT_ROOT: usize = 72;
+    pub const SLOT: usize = 104;
+}
+
+/// Transfer policy flags stored in state (`transfer_flags`)
+pub mod transfer_flags {
+    /// Reject transfers invoked via CPI; the transfer must be a top-level instruction
+    pub const REQUIRE_TOP_LEVEL: u8 = 1 << 0;
+    /// Accept only top-level invocations or direct CPIs from a program on the
+    /// state's approved caller list (ignored when REQUIRE_TOP_LEVEL is set)
+    pub const REQUIRE_APPROVED_CALLER: u8 = 1 << 1;
+}
+
+/// State account discriminator
+pub const STATE_DISCRIMINATOR: [u8; 8] = [0x73, 0x6d, 0x74, 0x5f, 0x72, 0x6f, 0x6f, 0x74]; // "smt_root"
+
+/// Proof buffer header: 8 (discriminator) + 32 (owner)
+pub const PROOF_BUFFER_HEADER_SIZE: usize = 8 + 32;
+
+/// Proof buffer size: header + 388 (proof) + 108 (witness) = 536 bytes
+pub const PROOF_BUFFER_SIZE: usize = PROOF_BUFFER_HEADER_SIZE + PROOF_SIZE + WITNESS_SIZE;
+
+/// Proof buffer discriminator
+pub const PROOF_BUFFER_DISCRIMINATOR: [u8; 8] = [0x70, 0x72, 0x66, 0x5f, 0x62, 0x75, 0x66, 0x66]; // "prf_buff"
+
+/// Relay account size: 8 (discriminator) + 32 (owner) + 8 (nonce) = 48 bytes
+pub const RELAY_ACCOUNT_SIZE: usize = 8 + 32 + 8;
+
+/// Relay account discriminator
+pub const RELAY_ACCOUNT_DISCRIMINATOR: [u8; 8] = [0x72, 0x65, 0x6c, 0x61, 0x79, 0x5f, 0x61, 0x63]; // "relay_ac"
+
+/// Escrow account size: 8 (discriminator) + 32 (sender) + 32 (recipient) + 32 (state)
+/// + 8 (escrow_id) + 8 (amount) + 8 (release_at) + 1 (escrow_flags) = 129 bytes
+pub const ESCROW_ACCOUNT_SIZE: usize = 8 + 32 + 32 + 32 + 8 + 8 + 8 + 1;
+
+/// Escrow account discriminator
+pub const ESCROW_ACCOUNT_DISCRIMINATOR: [u8; 8] = [0x65, 0x73, 0x63, 0x72, 0x6f, 0x77, 0x5f, 0x5f]; // "escrow__"
+
+/// Per-escrow flags chosen by the sender (`escrow_flags`)
+pub mod escrow_flags {
+    /// The recipient must provide their own exclusion proof to claim
+    pub const REQUIRE_RECIPIENT_PROOF: u8 = 1 << 0;
+}
+
+/// Layout version of state data; accounts created before versioning are v0
+pub fn state_version(state_data: &[u8]) -> u8 {
+    if state_data.len() <= UNVERSIONED_STATE_MAX_SIZE {
+        0
+    } else {
+        state_data[state_offsets::VERSION]
+    }
+}
+
+/// A state account's fields, in the current layout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct State {
+    pub admin: [u8; 32],
+    pub smt_root: [u8; 32],
+    /// Unix time the root expires at; 0 for never
+    pub root_valid_until: i64,
+    pub transfer_flags: u8,
+    /// Lamports each sender may send per 24 hours; 0 for unlimited
+    pub daily_limit: u64,
+    pub fee_bps: u16,
+    /// Root updates so far
+    pub root_seq: u64,
+    /// The first `approved_caller_count` are set, the rest zeroed
+    pub approved_callers: [[u8; 32]; MAX_APPROVED_CALLERS],
+    pub approved_caller_count: u8,
+    pub bump: u8,
+    pub treasury_bump: u8,
+    pub hash_scheme: u8,
+    pub verifier_flags: u8,
+    pub metadata: [u8; METADATA_SIZE],
+    pub challenged: bool,
+    pub bond_bump: u8,
+    pub bond_amount: u64,
+    pub bond_withdrawal_amount: u64,
+    /// Unix time a requested bond withdrawal is available at
+    pub bond_withdrawal_at: i64,
+    /// Program config the state follows; zero before LINK_CONFIG
+    pub config: [u8; 32],
+    pub tree_kind: u8,
+    pub tree_depth: u16,
+}
+
+impl State {
+    /// Decode state account data, which must use the current layout
+    pub fn unpack(data: &[u8]) -> Result<Self, ExclusionError> {
+        use state_offsets::*;
+
+        if data.len() < 8 || data[0..8] != STATE_DISCRIMINATOR {
+            return Err(ExclusionError::InvalidStateAccount);
+        }
+        let version = state_version(data);
+        if version < STATE_VERSION {
+            return Err(ExclusionError::StateMigrationRequired);
+        }
+        if version > STATE_VERSION || data.len() < STATE_SIZE {
+            return Err(ExclusionError::InvalidStateAccount);
+        }
+
+        let bytes = |at: usize| -> [u8; 32] { data[at..at + 32].try_into().unwrap() };
+        let u64_at = |at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+        let i64_at = |at: usize| i64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+        let u16_at = |at: usize| u16::from_le_bytes(data[at..at + 2].try_into().unwrap());
+        Ok(Self {
+            admin: bytes(ADMIN),
+            smt_root: bytes(SMT_ROOT),
+            root_valid_until: i64_at(ROOT_VALID_UNTIL),
+            transfer_flags: data[TRANSFER_FLAGS],
+            daily_limit: u64_at(DAILY_LIMIT),
+            fee_bps: u16_at(FEE_BPS),
+            root_seq: u64_at(ROOT_SEQ),
+            approved_callers: core::array::from_fn(|i| bytes(APPROVED_CALLERS + 32 * i)),
+            approved_caller_count: data[APPROVED_CALLER_COUNT],
+            bump: data[BUMP],
+            treasury_bump: data[TREASURY_BUMP],
+            hash_scheme: data[HASH_SCHEME],
+            verifier_flags: data[VERIFIER_FLAGS],
+            metadata: data[METADATA..METADATA + METADATA_SIZE].try_into().unwrap(),
+            challenged: data[CHALLENGED] != 0,
+            bond_bump: data[BOND_BUMP],
+            bond_amount: u64_at(BOND_AMOUNT),
+            bond_withdrawal_amount: u64_at(BOND_WITHDRAWAL_AMOUNT),
+            bond_withdrawal_at: i64_at(BOND_WITHDRAWAL_AT),
+            config: bytes(CONFIG),
+            tree_kind: data[TREE_KIND],
+            tree_depth: u16_at(TREE_DEPTH),
+        })
+    }
+
+    /// Encode into the first `STATE_SIZE` bytes of `data`, discriminator and
+    /// version included
+    ///
+    /// # Panics
+    ///
+    /// If `data` is shorter than `STATE_SIZE`
+    pub fn pack(&self, data: &mut [u8]) {
+        use state_offsets::*;
+
+        data[0..8].copy_from_slice(&STATE_DISCRIMINATOR);
+        data[VERSION] = STATE_VERSION;
+        data[ADMIN..ADMIN + 32].copy_from_slice(&self.admin);
+        data[SMT_ROOT..SMT_ROOT + 32].copy_from_slice(&self.smt_root);
+        data[ROOT_VALID_UNTIL..ROOT_VALID_UNTIL + 8]
+            .copy_from_slice(&self.root_valid_until.to_le_bytes());
+        data[TRANSFER_FLAGS] = self.transfer_flags;
+        data[DAILY_LIMIT..DAILY_LIMIT + 8].copy_from_slice(&self.daily_limit.to_le_bytes());
+        data[FEE_BPS..FEE_BPS + 2].copy_from_slice(&self.fee_bps.to_le_bytes());
+        data[ROOT_SEQ..ROOT_SEQ + 8].copy_from_slice(&self.root_seq.to_le_bytes());
+        data[APPROVED_CALLER_COUNT] = self.approved_caller_count;
+        for (i, caller) in self.approved_callers.iter().enumerate() {
+            data[APPROVED_CALLERS + 32 * i..APPROVED_CALLERS + 32 * (i + 1)]
+                .copy_from_slice(caller);
+        }
+        data[BUMP] = self.bump;
+        data[TREASURY_BUMP] = self.treasury_bump;
+        data[HASH_SCHEME] = self.hash_scheme;
+        data[VERIFIER_FLAGS] = self.verifier_flags;
+        data[METADATA..METADATA + METADATA_SIZE].copy_from_slice(&self.metadata);
+        data[CHALLENGED] = self.challenged as u8;
+        data[BOND_BUMP] = self.bond_bump;
+        data[BOND_AMOUNT..BOND_AMOUNT + 8].copy_from_slice(&self.bond_amount.to_le_bytes());
+        data[BOND_WITHDRAWAL_AMOUNT..BOND_WITHDRAWAL_AMOUNT + 8]
+            .copy_from_slice(&self.bond_withdrawal_amount.to_le_bytes());
+        data[BOND_WITHDRAWAL_AT..BOND_WITHDRAWAL_AT + 8]
+            .copy_from_slice(&self.bond_withdrawal_at.to_le_bytes());
+        data[CONFIG..CONFIG + 32].copy_from_slice(&self.config);
+        data[TREE_KIND] = self.tree_kind;
+        data[TREE_DEPTH..TREE_DEPTH + 2].copy_from_slice(&self.tree_depth.to_le_bytes());
+    }
+
+    /// The approved callers that are set
+    pub fn approved_callers(&self) -> &[[u8; 32]] {
+        &self.approved_callers[..(self.approved_caller_count as usize).min(MAX_APPROVED_CALLERS)]
+    }
+}
@@ -0,0 +1,194 @@
+//! Proofs and public witnesses as the program takes them
+//!
+//! Both come from sunspot (gnark): a Groth16 proof of `PROOF_SIZE` bytes, and
+//! a public witness of a 12-byte header, then one 32-byte field element per
+//! public input.
+
+use crate::{
+    error::ExclusionError,
+    instruction::{
+        COMPRESSED_PROOF_SIZE, NUM_PUBLIC_INPUTS, PROOF_SIZE, WITNESS_HEADER_SIZE, WITNESS_SIZE,
+    },
+};
+
+/// gnark public witness header (all fields big-endian u32)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WitnessHeader {
+    pub nb_public: u32,
+    pub nb_secret: u32,
+    pub len: u32,
+}
+
+impl WitnessHeader {
+    /// Parse the 12-byte header at the start of a public witness
+    pub fn unpack(witness: &[u8]) -> Option<Self> {
+        if witness.len() < WITNESS_HEADER_SIZE {
+            return None;
+        }
+        let read_u32 = |i: usize| u32::from_be_bytes(witness[i..i + 4].try_into().unwrap());
+        Some(Self {
+            nb_public: read_u32(0),
+            nb_secret: read_u32(4),
+            len: read_u32(8),
+        })
+    }
+
+    /// Total witness size implied by this header (32 bytes per field element)
+    pub fn witness_size(&self) -> usize {
+        WITNESS_HEADER_SIZE + self.len as usize * 32
+    }
+}
+
+/// An exclusion circuit's Groth16 proof
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Proof(pub [u8; PROOF_SIZE]);
+
+impl Proof {
+    pub fn as_bytes(&self) -> &[u8; PROOF_SIZE] {
+        &self.0
+    }
+}
+
+impl TryFrom<&[u8]> for Proof {
+    type Error = ExclusionError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, ExclusionError> {
+        Ok(Self(
+            bytes
+                .try_into()
+                .map_err(|_| ExclusionError::InvalidDataLength)?,
+        ))
+    }
+}
+
+/// A proof with each point compressed to its x coordinate, as
+/// `TRANSFER_SOL_COMPRESSED` takes it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompressedProof(pub [u8; COMPRESSED_PROOF_SIZE]);
+
+impl CompressedProof {
+    pub fn as_bytes(&self) -> &[u8; COMPRESSED_PROOF_SIZE] {
+        &self.0
+    }
+}
+
+impl TryFrom<&[u8]> for CompressedProof {
+    type Error = ExclusionError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, ExclusionError> {
+        Ok(Self(
+            bytes
+                .try_into()
+                .map_err(|_| ExclusionError::InvalidDataLength)?,
+        ))
+    }
+}
+
+/// Expand one point of a compressed proof as the `sol_alt_bn128_compression`
+/// syscall does, for programs running off chain where there is no syscall:
+/// a G1 (32-byte) or G2 (64-byte) point into `point`, twice its length
+///
+/// Flips the syscall's big-endian encoding to ark's little-endian one, reads
+/// the point, and writes its coordinates back big-endian.
+#[cfg(all(feature = "std", not(target_os = "solana")))]
+pub fn decompress_point(compressed: &[u8], point: &mut [u8]) -> Result<(), ExclusionError> {
+    use ark_bn254::{G1Affine, G2Affine};
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+
+    // The syscall's encoding of the point at infinity
+    if compressed.iter().all(|&byte| byte == 0) {
+        point.fill(0);
+        return Ok(());
+    }
+    let mut le = compressed.to_vec();
+    le.reverse();
+    // The coordinates alone: ark's uncompressed encoding would keep the
+    // y flag in the top bits
+    let mut coordinates = Vec::with_capacity(point.len());
+    let expanded = if compressed.len() == 32 {
+        G1Affine::deserialize_with_mode(&le[..], Compress::Yes, Validate::No).and_then(|decoded| {
+            decoded.x.serialize_uncompressed(&mut coordinates)?;
+            decoded.y.serialize_uncompressed(&mut coordinates)
+        })
+    } else {
+        G2Affine::deserialize_with_mode(&le[..], Compress::Yes, Validate::No).and_then(|decoded| {
+            decoded.x.serialize_uncompressed(&mut coordinates)?;
+            decoded.y.serialize_uncompressed(&mut coordinates)
+        })
+    };
+    if expanded.is_err() {
+        return Err(ExclusionError::InvalidCompressedProof);
+    }
+    // Each coordinate back to big-endian: 32 bytes in G1, 64 in G2 with the
+    // imaginary part first
+    for (to, from) in point
+        .chunks_mut(compressed.len())
+        .zip(coordinates.chunks(compressed.len()))
+    {
+        to.copy_from_slice(from);
+        to.reverse();
+    }
+    Ok(())
+}
+
+/// An exclusion circuit's public witness: `smt_root`, `pubkey_hash` and
+/// `recent_slot`, in gnark's big-endian field encoding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PublicWitness(pub [u8; WITNESS_SIZE]);
+
+impl PublicWitness {
+    /// The witness of a proof against `smt_root` for the pubkey hashing to
+    /// `pubkey_hash`, at `recent_slot`
+    pub fn new(smt_root: &[u8; 32], pubkey_hash: &[u8; 32], recent_slot: u64) -> Self {
+        let mut witness = [0u8; WITNESS_SIZE];
+        witness[..4].copy_from_slice(&NUM_PUBLIC_INPUTS.to_be_bytes());
+        witness[8..12].copy_from_slice(&NUM_PUBLIC_INPUTS.to_be_bytes());
+        witness[12..44].copy_from_slice(smt_root);
+        witness[44..76].copy_from_slice(pubkey_hash);
+        witness[100..].copy_from_slice(&recent_slot.to_be_bytes());
+        Self(witness)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; WITNESS_SIZE] {
+        &self.0
+    }
+
+    pub fn header(&self) -> WitnessHeader {
+        WitnessHeader::unpack(&self.0).expect("the witness holds a header")
+    }
+
+    pub fn smt_root(&self) -> [u8; 32] {
+        self.field(0)
+    }
+
+    pub fn pubkey_hash(&self) -> [u8; 32] {
+        self.field(1)
+    }
+
+    /// `recent_slot`, if its field element fits a u64
+    pub fn recent_slot(&self) -> Option<u64> {
+        let field = self.field(2);
+        field[..24]
+            .iter()
+            .all(|byte| *byte == 0)
+            .then(|| u64::from_be_bytes(field[24..].try_into().unwrap()))
+    }
+
+    /// Public input `i`, as encoded
+    fn field(&self, i: usize) -> [u8; 32] {
+        let at = WITNESS_HEADER_SIZE + 32 * i;
+        self.0[at..at + 32].try_into().unwrap()
+    }
+}
+
+impl TryFrom<&[u8]> for PublicWitness {
+    type Error = ExclusionError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, ExclusionError> {
+        Ok(Self(
+            bytes
+                .try_into()
+                .map_err(|_| ExclusionError::InvalidDataLength)?,
+        ))
+    }
+}
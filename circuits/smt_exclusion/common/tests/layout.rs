@@ -0,0 +1,126 @@
+//! Checks the state layout, errors and witness types against each other.
+
+use exclusion_common::{
+    instruction::{NUM_PUBLIC_INPUTS, WITNESS_SIZE},
+    state::{self, state_offsets, State, STATE_SIZE, STATE_VERSION},
+    ExclusionError, Proof, PublicWitness,
+};
+
+fn state() -> State {
+    State {
+        admin: [1; 32],
+        smt_root: [2; 32],
+        root_valid_until: -3,
+        transfer_flags: 4,
+        daily_limit: 5,
+        fee_bps: 6,
+        root_seq: 7,
+        approved_callers: [[8; 32], [9; 32], [0; 32], [0; 32]],
+        approved_caller_count: 2,
+        bump: 10,
+        treasury_bump: 11,
+        hash_scheme: 1,
+        verifier_flags: 1,
+        metadata: [12; 64],
+        challenged: true,
+        bond_bump: 13,
+        bond_amount: 14,
+        bond_withdrawal_amount: 15,
+        bond_withdrawal_at: 16,
+        config: [17; 32],
+        tree_kind: 1,
+        tree_depth: 254,
+    }
+}
+
+#[test]
+fn offsets_fill_the_state() {
+    assert_eq!(state_offsets::TREE_DEPTH + 2, STATE_SIZE);
+    assert_eq!(
+        state_offsets::APPROVED_CALLERS + 32 * state::MAX_APPROVED_CALLERS,
+        state_offsets::BUMP
+    );
+    assert_eq!(STATE_SIZE, 358);
+}
+
+#[test]
+fn state_round_trips() {
+    let mut data = vec![0u8; STATE_SIZE];
+    state().pack(&mut data);
+    assert_eq!(&data[..8], &state::STATE_DISCRIMINATOR);
+    assert_eq!(data[state_offsets::VERSION], STATE_VERSION);
+    assert_eq!(State::unpack(&data), Ok(state()));
+    assert_eq!(state().approved_callers(), &[[8; 32], [9; 32]]);
+
+    // A longer account still decodes
+    data.extend_from_slice(&[0xaa; 16]);
+    assert_eq!(State::unpack(&data), Ok(state()));
+}
+
+#[test]
+fn state_rejects_other_layouts() {
+    let mut data = vec![0u8; STATE_SIZE];
+    state().pack(&mut data);
+
+    let mut other = data.clone();
+    other[0] ^= 1;
+    assert_eq!(
+        State::unpack(&other),
+        Err(ExclusionError::InvalidStateAccount)
+    );
+    let mut older = data.clone();
+    older[state_offsets::VERSION] = STATE_VERSION - 1;
+    assert_eq!(
+        State::unpack(&older),
+        Err(ExclusionError::StateMigrationRequired)
+    );
+    let mut newer = data.clone();
+    newer[state_offsets::VERSION] = STATE_VERSION + 1;
+    assert_eq!(
+        State::unpack(&newer),
+        Err(ExclusionError::InvalidStateAccount)
+    );
+    // Unversioned accounts are v0
+    assert_eq!(
+        State::unpack(&data[..state::UNVERSIONED_STATE_MAX_SIZE]),
+        Err(ExclusionError::StateMigrationRequired)
+    );
+}
+
+#[test]
+fn error_codes_round_trip() {
+    for code in 0..=46 {
+        let error = ExclusionError::from_code(code).unwrap();
+        assert_eq!(error as u32, code);
+        let program_error: solana_program_error::ProgramError = error.into();
+        assert_eq!(
+            program_error,
+            solana_program_error::ProgramError::Custom(code)
+        );
+        assert!(!error.description().is_empty());
+    }
+    assert_eq!(ExclusionError::from_code(47), None);
+}
+
+#[test]
+fn witness_fields() {
+    let witness = PublicWitness::new(&[1; 32], &[2; 32], 300);
+    let header = witness.header();
+    assert_eq!(header.nb_public, NUM_PUBLIC_INPUTS);
+    assert_eq!(header.witness_size(), WITNESS_SIZE);
+    assert_eq!(witness.smt_root(), [1; 32]);
+    assert_eq!(witness.pubkey_hash(), [2; 32]);
+    assert_eq!(witness.recent_slot(), Some(300));
+    assert_eq!(
+        PublicWitness::try_from(&witness.as_bytes()[..]),
+        Ok(witness)
+    );
+    assert_eq!(
+        PublicWitness::try_from(&witness.as_bytes()[1..]),
+        Err(ExclusionError::InvalidDataLength)
+    );
+    assert_eq!(
+        Proof::try_from(&[0u8; 10][..]),
+        Err(ExclusionError::InvalidDataLength)
+    );
+}
@@ -0,0 +1,82 @@
+//! Checks `pubkey_to_index` against the circuit's shared vectors and the
+//! `sol_poseidon` syscall's implementation, with the parameters the program
+//! passes it.
+
+#![allow(deprecated)]
+
+use exclusion_common::pubkey_to_index;
+use solana_poseidon::{hashv, Endianness, Parameters};
+
+/// The circuit's vectors, from the file its `test_pubkey_vectors` reads
+fn vectors() -> Vec<([u8; 32], [u8; 32])> {
+    let source = include_str!("../../src/pubkey_vectors.nr");
+    // The global's value, without comments
+    let global = |name: &str| -> String {
+        let start = source.find(&format!("global {name}")).unwrap();
+        let value = &source[start..];
+        let value = &value[value.find('=').unwrap() + 1..];
+        let value = &value[..value.find("];").unwrap()];
+        value
+            .lines()
+            .map(|line| line.split("//").next().unwrap())
+            .collect()
+    };
+    let bytes: Vec<u8> = global("PUBKEYS")
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|number| !number.is_empty())
+        .map(|number| number.parse().unwrap())
+        .collect();
+    let indices: Vec<[u8; 32]> = global("INDICES")
+        .split(',')
+        .map(|field| field.trim_matches(|c: char| c == '[' || c.is_whitespace()))
+        .filter(|field| !field.is_empty())
+        .map(|field| {
+            let hex = field.trim_start_matches("0x");
+            std::array::from_fn(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap())
+        })
+        .collect();
+    assert_eq!(bytes.len(), 32 * indices.len(), "a pubkey per index");
+    bytes
+        .chunks_exact(32)
+        .map(|pubkey| pubkey.try_into().unwrap())
+        .zip(indices)
+        .collect()
+}
+
+#[test]
+fn matches_circuit_vectors() {
+    let vectors = vectors();
+    assert_eq!(vectors.len(), 5);
+    for (pubkey, index) in vectors {
+        assert_eq!(pubkey_to_index(&pubkey), index, "pubkey {pubkey:?}");
+    }
+}
+
+#[test]
+fn matches_syscall() {
+    for (pubkey, _) in vectors() {
+        let expected = pubkey_to_index(&pubkey);
+
+        // Little-endian halves, zero-padded, as the program hashed them before
+        // it shared this implementation
+        let mut low = [0u8; 32];
+        let mut high = [0u8; 32];
+        low[..16].copy_from_slice(&pubkey[..16]);
+        high[..16].copy_from_slice(&pubkey[16..]);
+        let mut hash = hashv(
+            Parameters::Bn254X5,
+            Endianness::LittleEndian,
+            &[&low, &high],
+        )
+        .unwrap()
+        .to_bytes();
+        hash.reverse();
+        assert_eq!(hash, expected);
+
+        // Big-endian, as this crate calls it on chain
+        low.reverse();
+        high.reverse();
+        let hash = hashv(Parameters::Bn254X5, Endianness::BigEndian, &[&low, &high]).unwrap();
+        assert_eq!(hash.to_bytes(), expected);
+    }
+}
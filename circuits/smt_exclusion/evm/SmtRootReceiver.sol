@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+/// Subset of the Wormhole core contract this receiver uses
+interface IWormhole {
+    struct Signature {
+        bytes32 r;
+        bytes32 s;
+        uint8 v;
+        uint8 guardianIndex;
+    }
+
+    struct VM {
+        uint8 version;
+        uint32 timestamp;
+        uint32 nonce;
+        uint16 emitterChainId;
+        bytes32 emitterAddress;
+        uint64 sequence;
+        uint8 consistencyLevel;
+        bytes payload;
+        uint32 guardianSetIndex;
+        Signature[] signatures;
+        bytes32 hash;
+    }
+
+    function parseAndVerifyVM(bytes calldata encodedVM)
+        external
+        view
+        returns (VM memory vm, bool valid, string memory reason);
+}
+
+/// Mirrors blacklist roots published by the exclusion program's PUBLISH_ROOT.
+///
+/// Anyone may relay a signed VAA. Roots are kept per Solana state account and
+/// only move forward in `root_seq`, so replaying an older VAA changes nothing.
+/// Gate actions with `isCurrentRoot` against the root an exclusion proof was
+/// made for.
+contract SmtRootReceiver {
+    uint16 public constant SOLANA_CHAIN_ID = 1;
+    uint8 public constant ROOT_PAYLOAD_ID = 1;
+    uint256 public constant ROOT_PAYLOAD_LEN = 81;
+
+    struct Root {
+        bytes32 smtRoot;
+        uint64 rootSeq;
+        /// Unix timestamp the root expires at (0 = no expiry)
+        int64 validUntil;
+    }
+
+    IWormhole public immutable wormhole;
+    /// The program's ["emitter"] PDA
+    bytes32 public immutable emitter;
+
+    /// Latest root per Solana state account
+    mapping(bytes32 => Root) public roots;
+
+    event RootMirrored(bytes32 indexed state, bytes32 smtRoot, uint64 rootSeq, int64 validUntil);
+
+    constructor(IWormhole wormhole_, bytes32 emitter_) {
+        wormhole = wormhole_;
+        emitter = emitter_;
+    }
+
+    function receiveRoot(bytes calldata encodedVM) external {
+        (IWormhole.VM memory vm, bool valid, string memory reason) = wormhole.parseAndVerifyVM(encodedVM);
+        require(valid, reason);
+        require(vm.emitterChainId == SOLANA_CHAIN_ID && vm.emitterAddress == emitter, "unknown emitter");
+
+        bytes memory payload = vm.payload;
+        require(payload.length == ROOT_PAYLOAD_LEN && uint8(payload[0]) == ROOT_PAYLOAD_ID, "not a root message");
+        bytes32 state;
+        bytes32 smtRoot;
+        uint64 rootSeq;
+        int64 validUntil;
+        assembly {
+            // Skip the length word and the payload ID; integers are big-endian
+            state := mload(add(payload, 33))
+            smtRoot := mload(add(payload, 65))
+            rootSeq := shr(192, mload(add(payload, 97)))
+            validUntil := sar(192, mload(add(payload, 105)))
+        }
+
+        Root storage current = roots[state];
+        require(rootSeq > current.rootSeq || current.smtRoot == bytes32(0), "stale root");
+        roots[state] = Root(smtRoot, rootSeq, validUntil);
+        emit RootMirrored(state, smtRoot, rootSeq, validUntil);
+    }
+
+    /// Whether `smtRoot` is the latest mirrored, unexpired root of `state`
+    function isCurrentRoot(bytes32 state, bytes32 smtRoot) external view returns (bool) {
+        Root memory root = roots[state];
+        return root.smtRoot != bytes32(0) && root.smtRoot == smtRoot
+            && (root.validUntil == 0 || int256(block.timestamp) <= root.validUntil);
+    }
+}
@@ -0,0 +1,98 @@
+//! The Bubblegum instruction gated cNFT transfers need
+//!
+//! Encoded by hand, like the SPL Token instructions: `transfer` is the only
+//! call, and its Anchor layout is fixed.
+//!
+//! A compressed NFT is a leaf in an SPL account-compression tree. Bubblegum
+//! checks the leaf's merkle path against the tree, so the path (less the nodes
+//! the tree caches in its canopy) is passed as trailing accounts.
+
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey,
+    pubkey::Pubkey,
+};
+
+use crate::events::NOOP_PROGRAM_ID;
+
+/// Metaplex Bubblegum
+pub const BUBBLEGUM_PROGRAM_ID: Pubkey = pubkey!("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY");
+
+/// SPL account compression, which owns Bubblegum's trees
+pub const ACCOUNT_COMPRESSION_PROGRAM_ID: Pubkey =
+    pubkey!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+
+/// `sha256("global:transfer")[..8]`
+const TRANSFER: [u8; 8] = [0xa3, 0x34, 0xc8, 0xe7, 0x8c, 0x03, 0x45, 0xba];
+
+/// The leaf being transferred, as read from a DAS indexer (`getAsset`,
+/// `getAssetProof`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Leaf {
+    /// Tree root the proof nodes were read against
+    pub root: [u8; 32],
+    pub data_hash: [u8; 32],
+    pub creator_hash: [u8; 32],
+    pub nonce: u64,
+    /// Leaf index; Bubblegum assigns it the nonce at mint
+    pub index: u32,
+}
+
+impl Leaf {
+    /// Encoded size: root + data hash + creator hash + 8 (nonce) + 4 (index)
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 4;
+
+    pub fn unpack(data: &[u8]) -> Option<Self> {
+        if data.len() != Self::LEN {
+            return None;
+        }
+        Some(Self {
+            root: data[0..32].try_into().unwrap(),
+            data_hash: data[32..64].try_into().unwrap(),
+            creator_hash: data[64..96].try_into().unwrap(),
+            nonce: u64::from_le_bytes(data[96..104].try_into().unwrap()),
+            index: u32::from_le_bytes(data[104..108].try_into().unwrap()),
+        })
+    }
+}
+
+/// Transfer `leaf` from `leaf_owner` (who signs) to `new_leaf_owner`
+///
+/// `tree_authority` is Bubblegum's tree config PDA, `[merkle_tree]`.
+pub fn transfer(
+    tree_authority: &Pubkey,
+    merkle_tree: &Pubkey,
+    leaf_owner: &Pubkey,
+    leaf_delegate: &Pubkey,
+    new_leaf_owner: &Pubkey,
+    leaf: &Leaf,
+    proof_nodes: &[Pubkey],
+) -> Instruction {
+    let mut data = TRANSFER.to_vec();
+    data.extend_from_slice(&leaf.root);
+    data.extend_from_slice(&leaf.data_hash);
+    data.extend_from_slice(&leaf.creator_hash);
+    data.extend_from_slice(&leaf.nonce.to_le_bytes());
+    data.extend_from_slice(&leaf.index.to_le_bytes());
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*tree_authority, false),
+        AccountMeta::new_readonly(*leaf_owner, true),
+        AccountMeta::new_readonly(*leaf_delegate, leaf_delegate == leaf_owner),
+        AccountMeta::new_readonly(*new_leaf_owner, false),
+        AccountMeta::new(*merkle_tree, false),
+        AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+        AccountMeta::new_readonly(ACCOUNT_COMPRESSION_PROGRAM_ID, false),
+        AccountMeta::new_readonly(solana_system_interface::program::ID, false),
+    ];
+    accounts.extend(
+        proof_nodes
+            .iter()
+            .map(|node| AccountMeta::new_readonly(*node, false)),
+    );
+    Instruction {
+        program_id: BUBBLEGUM_PROGRAM_ID,
+        accounts,
+        data,
+    }
+}
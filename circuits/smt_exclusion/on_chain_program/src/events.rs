@@ -0,0 +1,156 @@
+//! Structured events emitted with `sol_log_data`
+//!
+//! Events use Anchor's encoding, so Anchor's `EventParser` and IDL-driven
+//! indexers decode them without parsing `msg!` strings. Each event is one
+//! `Program data:` log entry: the 8-byte discriminator
+//! `sha256("event:<Name>")[..8]` followed by the Borsh encoding of its fields
+//! (fixed-size arrays as raw bytes, integers little-endian).
+//!
+//! Logs can be truncated when a transaction logs too much, so with the
+//! `noop-events` feature (on by default) each event is also CPI'd as
+//! instruction data into the noop program, the pattern Bubblegum uses. Indexers
+//! read it back from the transaction's inner instructions, which are never
+//! truncated. Emitting instructions then expect the noop program among their
+//! accounts; build with `--no-default-features` for minimal deployments.
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, log::sol_log_data, pubkey, pubkey::Pubkey,
+};
+
+/// SPL noop program, the CPI target for events
+pub const NOOP_PROGRAM_ID: Pubkey = pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
+/// An event with a fixed Anchor discriminator
+pub trait Event {
+    /// `sha256("event:<Name>")[..8]`
+    const DISCRIMINATOR: [u8; 8];
+
+    /// Append the Borsh encoding of the event's fields
+    fn serialize_fields(&self, out: &mut Vec<u8>);
+
+    /// Discriminator followed by the serialized fields
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Self::DISCRIMINATOR.to_vec();
+        self.serialize_fields(&mut out);
+        out
+    }
+
+    /// Log the event as a single `Program data:` entry and, with `noop-events`,
+    /// CPI it into the noop program found among `accounts`
+    #[cfg_attr(not(feature = "noop-events"), allow(unused_variables))]
+    fn emit(&self, accounts: &[AccountInfo]) -> ProgramResult {
+        let bytes = self.to_bytes();
+        sol_log_data(&[&bytes]);
+        #[cfg(feature = "noop-events")]
+        invoke_noop(accounts, bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "noop-events")]
+fn invoke_noop(accounts: &[AccountInfo], data: Vec<u8>) -> ProgramResult {
+    use solana_program::{
+        instruction::Instruction, msg, program::invoke, program_error::ProgramError,
+    };
+
+    let noop_program = accounts
+        .iter()
+        .find(|account| account.key == &NOOP_PROGRAM_ID)
+        .ok_or_else(|| {
+            msg!("Noop program account required for event CPI");
+            ProgramError::NotEnoughAccountKeys
+        })?;
+    invoke(
+        &Instruction {
+            program_id: NOOP_PROGRAM_ID,
+            accounts: vec![],
+            data,
+        },
+        std::slice::from_ref(noop_program),
+    )
+}
+
+/// The stored SMT root changed (SET_SMT_ROOT, INSERT_LEAF / REMOVE_LEAF,
+/// SUBMIT_ROOT_TRANSITION)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RootUpdated {
+    pub old: [u8; 32],
+    pub new: [u8; 32],
+    /// Number of root changes on this state account, including this one
+    pub seq: u64,
+}
+
+impl Event for RootUpdated {
+    const DISCRIMINATOR: [u8; 8] = [0x5e, 0x35, 0x16, 0x80, 0x8d, 0x71, 0x62, 0xe7];
+
+    fn serialize_fields(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.old);
+        out.extend_from_slice(&self.new);
+        out.extend_from_slice(&self.seq.to_le_bytes());
+    }
+}
+
+/// A CHALLENGE showed that the verifier accepted an exclusion proof for a key
+/// the stored tree contains; the state account is now flagged
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChallengeUpheld {
+    pub challenger: Pubkey,
+    /// Included key's pubkey hash (big-endian)
+    pub pubkey_hash: [u8; 32],
+    /// Root the key is included in and the exclusion proof was made against
+    pub root: [u8; 32],
+    /// Bond lamports paid to the challenger
+    pub slashed: u64,
+}
+
+impl Event for ChallengeUpheld {
+    const DISCRIMINATOR: [u8; 8] = [0xdc, 0xb0, 0x69, 0x89, 0x61, 0xab, 0xda, 0xdb];
+
+    fn serialize_fields(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.challenger.as_ref());
+        out.extend_from_slice(&self.pubkey_hash);
+        out.extend_from_slice(&self.root);
+        out.extend_from_slice(&self.slashed.to_le_bytes());
+    }
+}
+
+/// A transfer was made after the sender's exclusion proof verified
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TransferVerified {
+    /// Sender's pubkey hash from the verified witness (big-endian)
+    pub sender_hash: [u8; 32],
+    pub amount: u64,
+    pub recipient: Pubkey,
+}
+
+impl Event for TransferVerified {
+    const DISCRIMINATOR: [u8; 8] = [0x9c, 0xc7, 0x42, 0xea, 0xe5, 0xf4, 0x43, 0x9d];
+
+    fn serialize_fields(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.sender_hash);
+        out.extend_from_slice(&self.amount.to_le_bytes());
+        out.extend_from_slice(self.recipient.as_ref());
+    }
+}
+
+/// An ATTEST recorded that the subject's exclusion proof verified
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attested {
+    pub subject: Pubkey,
+    pub state: Pubkey,
+    /// Root the proof was verified against
+    pub smt_root: [u8; 32],
+    /// Slot the proof was verified in
+    pub slot: u64,
+}
+
+impl Event for Attested {
+    const DISCRIMINATOR: [u8; 8] = [0xb8, 0x66, 0x71, 0xc7, 0xdc, 0xc5, 0x60, 0x32];
+
+    fn serialize_fields(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.subject.as_ref());
+        out.extend_from_slice(self.state.as_ref());
+        out.extend_from_slice(&self.smt_root);
+        out.extend_from_slice(&self.slot.to_le_bytes());
+    }
+}
@@ -0,0 +1,343 @@
+//! Instruction discriminators and the versioned instruction-data envelope
+//!
+//! Every instruction's data begins with a two-byte header:
+//!
+//! ```text
+//! [discriminator: u8][version: u8][payload ...]
+//! ```
+//!
+//! The payload holds the fields known for that version. Bytes after the known
+//! fields are ignored, up to the instruction's declared maximum length, so future
+//! optional fields (memo, expiry, flags) can be appended without breaking
+//! clients that were built against an older layout.
+
+use solana_program::{msg, program_error::ProgramError, pubkey::Pubkey};
+
+pub use exclusion_common::{instruction::*, witness::WitnessHeader};
+
+use crate::{bubblegum::Leaf, light::VALIDITY_PROOF_SIZE, ExclusionError};
+
+/// Known payload length for INITIALIZE
+pub const INITIALIZE_LEN: usize = 0;
+
+/// Known payload length for INITIALIZE version 4: 8 (bond_lamports, 0 = no bond)
+pub const INITIALIZE_LEN_V4: usize = 8;
+
+/// Known payload length for SET_SMT_ROOT: 32 bytes (new SMT root)
+pub const SET_SMT_ROOT_LEN: usize = 32;
+
+/// Known payload length for SET_SMT_ROOT version 2: 32 (root) + 8 (root_valid_until)
+pub const SET_SMT_ROOT_LEN_V2: usize = 32 + 8;
+
+/// Known payload length for SET_SMT_ROOT version 3: version 2 fields + metadata
+pub const SET_SMT_ROOT_LEN_V3: usize = SET_SMT_ROOT_LEN_V2 + METADATA_SIZE;
+
+/// Known payload length for TRANSFER_SOL: 8 (amount) + proof + witness
+pub const TRANSFER_SOL_LEN: usize = 8 + PROOF_SIZE + WITNESS_SIZE;
+
+/// Known payload length for CREATE_PROOF_BUFFER
+pub const CREATE_PROOF_BUFFER_LEN: usize = 0;
+
+/// Fixed payload prefix for WRITE_PROOF_CHUNK: 2 (offset), followed by a
+/// u16 length-prefixed chunk
+pub const WRITE_PROOF_CHUNK_FIXED_LEN: usize = 2;
+
+/// Known payload length for TRANSFER_FROM_BUFFER: 8 (amount)
+pub const TRANSFER_FROM_BUFFER_LEN: usize = 8;
+
+/// Known payload length for SUBMIT_ROOT_TRANSITION: 32 (inserted pubkey) + proof + witness
+pub const SUBMIT_ROOT_TRANSITION_LEN: usize = 32 + PROOF_SIZE + TRANSITION_WITNESS_SIZE;
+
+/// Known payload length for SET_TRANSFER_FLAGS: 1 (transfer_flags)
+pub const SET_TRANSFER_FLAGS_LEN: usize = 1;
+
+/// Fixed payload prefix for INSERT_LEAF / REMOVE_LEAF: 32 (pubkey) + 32 (sibling
+/// bitmap), followed by 32 bytes per set bitmap bit
+pub const LEAF_UPDATE_FIXED_LEN: usize = 32 + 32;
+
+/// Known payload length for TRANSFER_SOL_DUAL: 8 (amount); proofs come from buffers
+pub const TRANSFER_SOL_DUAL_LEN: usize = 8;
+
+/// Known payload length for CREATE_RELAY_ACCOUNT
+pub const CREATE_RELAY_ACCOUNT_LEN: usize = 0;
+
+/// Known payload length for TRANSFER_SOL_RELAYED: 8 (amount) + 8 (nonce) + proof + witness
+pub const TRANSFER_SOL_RELAYED_LEN: usize = 8 + 8 + PROOF_SIZE + WITNESS_SIZE;
+
+/// Known payload length for CLOSE_RELAY_ACCOUNT
+pub const CLOSE_RELAY_ACCOUNT_LEN: usize = 0;
+
+/// Known payload length for TRANSFER_TO_ESCROW: 8 (amount) + 8 (escrow_id) +
+/// 8 (release_delay) + 1 (escrow_flags) + proof + witness
+pub const TRANSFER_TO_ESCROW_LEN: usize = 8 + 8 + 8 + 1 + PROOF_SIZE + WITNESS_SIZE;
+
+/// Known payload length for CLAIM_FROM_ESCROW, without a recipient proof
+pub const CLAIM_FROM_ESCROW_LEN: usize = 0;
+
+/// Known payload length for CLAIM_FROM_ESCROW version 2: the recipient's proof + witness
+pub const CLAIM_FROM_ESCROW_LEN_V2: usize = PROOF_SIZE + WITNESS_SIZE;
+
+/// Known payload length for TRANSFER_FROM_VAULT: 8 (amount) + recipient's proof + witness
+pub const TRANSFER_FROM_VAULT_LEN: usize = 8 + PROOF_SIZE + WITNESS_SIZE;
+
+/// Known payload length for SET_RATE_LIMIT: 8 (max lamports per sender per 24 hours)
+pub const SET_RATE_LIMIT_LEN: usize = 8;
+
+/// Known payload length for SET_FEE: 2 (fee_bps, u16 LE)
+pub const SET_FEE_LEN: usize = 2;
+
+/// Known payload length for WITHDRAW_FEES
+pub const WITHDRAW_FEES_LEN: usize = 0;
+
+/// Known payload length for SET_APPROVED_CALLERS: 1 (count) + 32 per slot, with
+/// unused slots zeroed
+pub const SET_APPROVED_CALLERS_LEN: usize = 1 + 32 * crate::MAX_APPROVED_CALLERS;
+
+/// Fixed payload prefix for TRANSFER_SOL_BATCH: 1 (recipient count) + proof + witness,
+/// followed by an 8-byte amount per recipient
+pub const TRANSFER_SOL_BATCH_FIXED_LEN: usize = 1 + PROOF_SIZE + WITNESS_SIZE;
+
+/// Maximum recipients in one TRANSFER_SOL_BATCH
+pub const MAX_BATCH_RECIPIENTS: usize = 16;
+
+/// Known payload length for MIGRATE_STATE
+pub const MIGRATE_STATE_LEN: usize = 0;
+
+/// Known payload length for SET_HASH_SCHEME: 1 (hash_scheme)
+pub const SET_HASH_SCHEME_LEN: usize = 1;
+
+/// Known payload length for SET_VERIFIER_FLAGS: 1 (verifier_flags)
+pub const SET_VERIFIER_FLAGS_LEN: usize = 1;
+
+/// Known payload length for SET_TREE_KIND: 1 (tree_kind)
+pub const SET_TREE_KIND_LEN: usize = 1;
+
+/// Known payload length for SET_TREE_DEPTH: 2 (tree_depth, u16 LE)
+pub const SET_TREE_DEPTH_LEN: usize = 2;
+
+/// Known payload length for SET_METADATA: metadata
+pub const SET_METADATA_LEN: usize = METADATA_SIZE;
+
+/// Fixed payload prefix for CHALLENGE: proof + witness + 32 (sibling bitmap),
+/// followed by 32 bytes per set bitmap bit
+pub const CHALLENGE_FIXED_LEN: usize = PROOF_SIZE + WITNESS_SIZE + 32;
+
+/// Known payload length for DEPOSIT_BOND: 8 (lamports)
+pub const DEPOSIT_BOND_LEN: usize = 8;
+
+/// Known payload length for REQUEST_BOND_WITHDRAWAL: 8 (lamports, 0 = cancel)
+pub const REQUEST_BOND_WITHDRAWAL_LEN: usize = 8;
+
+/// Known payload length for WITHDRAW_BOND
+pub const WITHDRAW_BOND_LEN: usize = 0;
+
+/// Known payload length for SELF_EXCLUDE
+pub const SELF_EXCLUDE_LEN: usize = 0;
+
+/// Known payload length for SET_CONFIG: 1 (paused) + 32 (zk_verifier)
+/// + 32 (transition_verifier) + 8 (max_proof_age_slots) + 2 (max_fee_bps)
+pub const SET_CONFIG_LEN: usize = 1 + 32 + 32 + 8 + 2;
+
+/// Known payload length for LINK_CONFIG
+pub const LINK_CONFIG_LEN: usize = 0;
+
+/// Known payload length for SET_REGISTRY_AUTHORITY: 32 (governance authority)
+pub const SET_REGISTRY_AUTHORITY_LEN: usize = 32;
+
+/// Known payload length for REGISTER_VERIFIER: 32 (program ID) + 32 (circuit hash)
+/// + 1 (tree_kind) + 2 (tree_depth)
+pub const REGISTER_VERIFIER_LEN: usize = 32 + 32 + 1 + 2;
+
+/// Known payload length for UNREGISTER_VERIFIER: 32 (program ID)
+pub const UNREGISTER_VERIFIER_LEN: usize = 32;
+
+/// Known payload length for TRANSFER_SOL_COMPRESSED: 8 (amount) + compressed proof + witness
+pub const TRANSFER_SOL_COMPRESSED_LEN: usize = 8 + COMPRESSED_PROOF_SIZE + WITNESS_SIZE;
+
+/// Known payload length for TRANSFER_WSOL: as for TRANSFER_SOL
+pub const TRANSFER_WSOL_LEN: usize = TRANSFER_SOL_LEN;
+
+/// Fixed payload prefix for TRANSFER_SOL_WITH_SEED: as for TRANSFER_SOL, followed
+/// by a u16 (LE) length-prefixed seed
+pub const TRANSFER_SOL_WITH_SEED_FIXED_LEN: usize = TRANSFER_SOL_LEN;
+
+/// Known payload length for ATTEST: proof + witness
+pub const ATTEST_LEN: usize = PROOF_SIZE + WITNESS_SIZE;
+
+/// Known payload length for ATTEST_COMPRESSED: proof + witness + Light validity
+/// proof + 2 (address tree root index)
+pub const ATTEST_COMPRESSED_LEN: usize = PROOF_SIZE + WITNESS_SIZE + VALIDITY_PROOF_SIZE + 2;
+
+/// Known payload length for TRANSFER_CNFT: proof + witness + Bubblegum leaf +
+/// 1 (proof node count)
+pub const TRANSFER_CNFT_LEN: usize = PROOF_SIZE + WITNESS_SIZE + Leaf::LEN + 1;
+
+/// Known payload length for PUBLISH_ROOT
+pub const PUBLISH_ROOT_LEN: usize = 0;
+
+/// Known payload length for SET_ADMIN: 32 (new admin)
+pub const SET_ADMIN_LEN: usize = 32;
+
+/// Length of the message a sender signs to authorize a relayed transfer
+pub const RELAY_MESSAGE_LEN: usize = 32 + 32 + 8 + 8;
+
+/// Message the sender signs off-chain to authorize TRANSFER_SOL_RELAYED:
+/// program_id || recipient || amount (u64 LE) || nonce (u64 LE)
+///
+/// The program ID keeps a signature from being replayed against another
+/// deployment; the nonce must match the sender's relay account.
+pub fn relay_message(
+    program_id: &Pubkey,
+    recipient: &Pubkey,
+    amount: u64,
+    nonce: u64,
+) -> [u8; RELAY_MESSAGE_LEN] {
+    let mut message = [0u8; RELAY_MESSAGE_LEN];
+    message[0..32].copy_from_slice(program_id.as_ref());
+    message[32..64].copy_from_slice(recipient.as_ref());
+    message[64..72].copy_from_slice(&amount.to_le_bytes());
+    message[72..80].copy_from_slice(&nonce.to_le_bytes());
+    message
+}
+
+/// Return data set by gated transfers, so CPI callers and simulations can read
+/// what was verified without scraping logs:
+/// smt_root (32) + pubkey_hash (32, big-endian as in the witness) + amount (u64 LE)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VerificationResult {
+    pub smt_root: [u8; 32],
+    pub pubkey_hash: [u8; 32],
+    pub amount: u64,
+}
+
+impl VerificationResult {
+    /// Encoded length
+    pub const LEN: usize = 32 + 32 + 8;
+
+    /// Encode for `set_return_data`
+    pub fn pack(&self) -> [u8; Self::LEN] {
+        let mut data = [0u8; Self::LEN];
+        data[0..32].copy_from_slice(&self.smt_root);
+        data[32..64].copy_from_slice(&self.pubkey_hash);
+        data[64..72].copy_from_slice(&self.amount.to_le_bytes());
+        data
+    }
+
+    /// Decode the return data of a gated transfer
+    pub fn unpack(data: &[u8]) -> Option<Self> {
+        if data.len() != Self::LEN {
+            return None;
+        }
+        Some(Self {
+            smt_root: data[0..32].try_into().unwrap(),
+            pubkey_hash: data[32..64].try_into().unwrap(),
+            amount: u64::from_le_bytes(data[64..72].try_into().unwrap()),
+        })
+    }
+}
+
+/// Parsed instruction header
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InstructionHeader {
+    pub discriminator: u8,
+    pub version: u8,
+}
+
+impl InstructionHeader {
+    /// Split instruction data into its header and the remaining payload
+    pub fn unpack(data: &[u8]) -> Result<(Self, &[u8]), ProgramError> {
+        if data.len() < HEADER_LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let header = Self {
+            discriminator: data[0],
+            version: data[1],
+        };
+        if header.version == 0 || header.version > max_version(header.discriminator) {
+            msg!(
+                "Unsupported instruction version {} for discriminator {}",
+                header.version,
+                header.discriminator
+            );
+            return Err(ExclusionError::UnsupportedInstructionVersion.into());
+        }
+
+        Ok((header, &data[HEADER_LEN..]))
+    }
+
+    /// Encode the header for the instruction's latest version
+    pub fn pack(discriminator: u8) -> [u8; HEADER_LEN] {
+        [discriminator, max_version(discriminator)]
+    }
+}
+
+/// Validate a payload against its known length and return only the known fields.
+/// Trailing bytes up to `RESERVED_LEN` are accepted and ignored.
+pub fn known_fields(payload: &[u8], known_len: usize) -> Result<&[u8], ProgramError> {
+    if payload.len() < known_len || payload.len() > known_len + RESERVED_LEN {
+        msg!(
+            "Invalid instruction data length: expected {} (up to {}), got {}",
+            known_len,
+            known_len + RESERVED_LEN,
+            payload.len()
+        );
+        return Err(ExclusionError::InvalidDataLength.into());
+    }
+    Ok(&payload[..known_len])
+}
+
+/// Validate a payload whose known fields are `fixed_len` bytes followed by a
+/// u16 (LE) length-prefixed byte string. Returns the fixed fields, the length
+/// prefix, and the byte string; trailing bytes are handled as in `known_fields`.
+pub fn known_fields_prefixed(payload: &[u8], fixed_len: usize) -> Result<&[u8], ProgramError> {
+    if payload.len() < fixed_len + 2 {
+        msg!("Invalid instruction data length: missing length prefix");
+        return Err(ExclusionError::InvalidDataLength.into());
+    }
+    let var_len = u16::from_le_bytes([payload[fixed_len], payload[fixed_len + 1]]) as usize;
+    known_fields(payload, fixed_len + 2 + var_len)
+}
+
+/// Validate a TRANSFER_SOL_BATCH payload: the recipient count determines how
+/// many 8-byte amounts follow the proof and witness.
+pub fn batch_transfer_fields(payload: &[u8]) -> Result<&[u8], ProgramError> {
+    let count = *payload.first().ok_or_else(|| {
+        msg!("Invalid instruction data length: missing recipient count");
+        ProgramError::from(ExclusionError::InvalidDataLength)
+    })? as usize;
+    if count == 0 || count > MAX_BATCH_RECIPIENTS {
+        msg!(
+            "Batch must have 1 to {} recipients, got {}",
+            MAX_BATCH_RECIPIENTS,
+            count
+        );
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    known_fields(payload, TRANSFER_SOL_BATCH_FIXED_LEN + 8 * count)
+}
+
+/// Validate an INSERT_LEAF / REMOVE_LEAF payload: the sibling bitmap determines how
+/// many 32-byte siblings follow. Bits beyond the tree depth must be clear.
+pub fn leaf_update_fields(payload: &[u8]) -> Result<&[u8], ProgramError> {
+    merkle_path_fields(payload, LEAF_UPDATE_FIXED_LEN)
+}
+
+/// Validate a CHALLENGE payload: as for INSERT_LEAF, the sibling bitmap ending the
+/// fixed prefix determines how many 32-byte siblings follow
+pub fn challenge_fields(payload: &[u8]) -> Result<&[u8], ProgramError> {
+    merkle_path_fields(payload, CHALLENGE_FIXED_LEN)
+}
+
+/// Validate a payload whose fixed prefix ends with a 32-byte sibling bitmap
+fn merkle_path_fields(payload: &[u8], fixed_len: usize) -> Result<&[u8], ProgramError> {
+    if payload.len() < fixed_len {
+        msg!("Invalid instruction data length: missing sibling bitmap");
+        return Err(ExclusionError::InvalidDataLength.into());
+    }
+
+    // Bits beyond the state's tree depth are checked against its state
+    let bitmap = &payload[fixed_len - 32..fixed_len];
+    let sibling_count: usize = bitmap.iter().map(|b| b.count_ones() as usize).sum();
+    known_fields(payload, fixed_len + 32 * sibling_count)
+}
@@ -0,0 +1,142 @@
+//! The Light Protocol system program CPI that compressed attestations need
+//!
+//! Encoded by hand, like the SPL Token instructions: creating one compressed
+//! account at a new address takes a single `invoke_cpi` to the v1 system
+//! program, whose Borsh layout is small and stable.
+//!
+//! Compressed accounts live in Light's state trees instead of rent-paying
+//! accounts. Creating one at an address needs a validity proof, from a Light
+//! indexer (Photon's `getValidityProof`), that the address is not yet in the
+//! address tree.
+
+use solana_keccak_hasher::hashv;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey,
+    pubkey::Pubkey,
+};
+
+/// Light system program (v1)
+pub const LIGHT_SYSTEM_PROGRAM_ID: Pubkey = pubkey!("SySTEM1eSU2p4BGQfQpimFEWWSC1XDFeun3Nqzz3rT7");
+
+/// Light account compression program
+pub const ACCOUNT_COMPRESSION_PROGRAM_ID: Pubkey =
+    pubkey!("compr6CUsB5m2jS4Y3831ztGSTnDpnKJTKS95d64XVq");
+
+/// Seed of the PDA a program signs Light CPIs with
+pub const CPI_AUTHORITY_SEED: &[u8] = exclusion_common::seeds::CPI_AUTHORITY;
+
+/// Compressed Groth16 validity proof: a (32) + b (64) + c (32)
+pub const VALIDITY_PROOF_SIZE: usize = 128;
+
+/// `sha256("global:invoke_cpi")[..8]`
+const INVOKE_CPI: [u8; 8] = [0x31, 0xd4, 0xbf, 0x81, 0x27, 0xc2, 0x2b, 0xc4];
+
+/// Keccak hash with the top byte cleared, so it is a BN254 field element
+pub fn hash_to_bn254_field_size_be(vals: &[&[u8]]) -> [u8; 32] {
+    let mut hash = hashv(vals).to_bytes();
+    hash[0] = 0;
+    hash
+}
+
+/// Address seed of a compressed account `program_id` derives from `seeds`
+pub fn derive_address_seed(seeds: &[&[u8]], program_id: &Pubkey) -> [u8; 32] {
+    let mut vals = vec![program_id.as_ref()];
+    vals.extend_from_slice(seeds);
+    hash_to_bn254_field_size_be(&vals)
+}
+
+/// Address the system program assigns to `seed` in `address_merkle_tree`
+pub fn derive_address(seed: &[u8; 32], address_merkle_tree: &Pubkey) -> [u8; 32] {
+    // Light's bump search always succeeds on its first seed (255)
+    hash_to_bn254_field_size_be(&[address_merkle_tree.as_ref(), seed, &[u8::MAX]])
+}
+
+/// A new compressed account owned by the invoking program, at a fresh address
+pub struct NewCompressedAccount<'a> {
+    pub owner: &'a Pubkey,
+    /// Address seed from `derive_address_seed`
+    pub seed: [u8; 32],
+    /// Address from `derive_address`
+    pub address: [u8; 32],
+    pub discriminator: [u8; 8],
+    pub data: &'a [u8],
+    /// Root of the address tree the validity proof was made against
+    pub address_merkle_tree_root_index: u16,
+}
+
+/// Accounts of an `invoke_cpi` that creates one compressed account
+///
+/// Trees follow the fixed accounts as: address tree, address queue, output
+/// state tree.
+pub struct InvokeCpiAccounts<'a> {
+    pub fee_payer: &'a Pubkey,
+    /// The invoking program's `CPI_AUTHORITY_SEED` PDA
+    pub authority: &'a Pubkey,
+    pub registered_program_pda: &'a Pubkey,
+    pub noop_program: &'a Pubkey,
+    pub account_compression_authority: &'a Pubkey,
+    pub invoking_program: &'a Pubkey,
+    pub address_merkle_tree: &'a Pubkey,
+    pub address_queue: &'a Pubkey,
+    pub output_state_tree: &'a Pubkey,
+}
+
+/// `invoke_cpi` creating `account`, proven new by `validity_proof`
+pub fn create_compressed_account(
+    accounts: &InvokeCpiAccounts,
+    account: &NewCompressedAccount,
+    validity_proof: &[u8],
+) -> Instruction {
+    // InstructionDataInvokeCpi
+    let mut inputs = Vec::with_capacity(512);
+    inputs.push(1); // proof: Some
+    inputs.extend_from_slice(validity_proof);
+    inputs.extend_from_slice(&1u32.to_le_bytes()); // new_address_params
+    inputs.extend_from_slice(&account.seed);
+    inputs.push(1); // address_queue_account_index
+    inputs.push(0); // address_merkle_tree_account_index
+    inputs.extend_from_slice(&account.address_merkle_tree_root_index.to_le_bytes());
+    inputs.extend_from_slice(&0u32.to_le_bytes()); // input_compressed_accounts
+    inputs.extend_from_slice(&1u32.to_le_bytes()); // output_compressed_accounts
+    inputs.extend_from_slice(account.owner.as_ref());
+    inputs.extend_from_slice(&0u64.to_le_bytes()); // lamports
+    inputs.push(1); // address: Some
+    inputs.extend_from_slice(&account.address);
+    inputs.push(1); // data: Some
+    inputs.extend_from_slice(&account.discriminator);
+    inputs.extend_from_slice(&(account.data.len() as u32).to_le_bytes());
+    inputs.extend_from_slice(account.data);
+    inputs.extend_from_slice(&hash_to_bn254_field_size_be(&[account.data]));
+    inputs.push(2); // merkle_tree_index: output state tree
+    inputs.push(0); // relay_fee: None
+    inputs.push(0); // compress_or_decompress_lamports: None
+    inputs.push(0); // is_compress
+    inputs.push(0); // cpi_context: None
+
+    let mut data = INVOKE_CPI.to_vec();
+    data.extend_from_slice(&(inputs.len() as u32).to_le_bytes());
+    data.extend_from_slice(&inputs);
+
+    Instruction {
+        program_id: LIGHT_SYSTEM_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*accounts.fee_payer, true),
+            AccountMeta::new_readonly(*accounts.authority, true),
+            AccountMeta::new_readonly(*accounts.registered_program_pda, false),
+            AccountMeta::new_readonly(*accounts.noop_program, false),
+            AccountMeta::new_readonly(*accounts.account_compression_authority, false),
+            AccountMeta::new_readonly(ACCOUNT_COMPRESSION_PROGRAM_ID, false),
+            AccountMeta::new_readonly(*accounts.invoking_program, false),
+            // Unused optional accounts are passed as the system program itself
+            AccountMeta::new_readonly(LIGHT_SYSTEM_PROGRAM_ID, false), // sol_pool_pda
+            AccountMeta::new_readonly(LIGHT_SYSTEM_PROGRAM_ID, false), // decompression_recipient
+            AccountMeta::new_readonly(solana_system_interface::program::ID, false),
+            AccountMeta::new_readonly(LIGHT_SYSTEM_PROGRAM_ID, false), // cpi_context_account
+            AccountMeta::new(*accounts.address_merkle_tree, false),
+            AccountMeta::new(*accounts.address_queue, false),
+            AccountMeta::new(*accounts.output_state_tree, false),
+        ],
+        data,
+    }
+}
@@ -0,0 +1,629 @@
+//! State account administration: creation, migration and the admin's settings
+
+use exclusion_common::seeds;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+use solana_system_interface::instruction as system_instruction;
+
+use super::{
+    bond::deposit_bond,
+    check_admin_state, set_root,
+    transfer::{check_treasury, treasury_bump},
+    verify::pubkey_hash_be,
+};
+use crate::{
+    events::Event, hash_scheme, instruction, smt, state_offsets::*, state_version, tree_kind,
+    ExclusionError, MAX_APPROVED_CALLERS, MAX_FEE_BPS, STATE_DISCRIMINATOR, STATE_SIZE,
+    STATE_VERSION, TREASURY_ACCOUNT_DISCRIMINATOR, TREASURY_ACCOUNT_SIZE,
+};
+
+/// Initialize a user-specific state account
+/// Each admin gets their own state account (PDA derived from their pubkey)
+/// Accounts:
+///   0. [signer, writable] Admin (payer)
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///   2. [] System program
+///   3. [writable] Bond account (PDA: ["bond", state]; required when depositing a bond)
+///
+/// Data:
+///   - 8 bytes: bond_lamports to deposit (u64 LE, 0 = no bond; version 4+)
+pub(crate) fn process_initialize(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let bond = account_iter.next();
+    let bond_lamports = match data.get(0..8) {
+        Some(bytes) => u64::from_le_bytes(bytes.try_into().unwrap()),
+        None => 0,
+    };
+
+    if !admin.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Derive PDA for user-specific state account
+    let (state_pda, bump) =
+        Pubkey::find_program_address(&[seeds::STATE, admin.key.as_ref()], program_id);
+    if state_account.key != &state_pda {
+        msg!("Invalid state account PDA");
+        return Err(ExclusionError::InvalidStatePda.into());
+    }
+
+    // Create state account
+    let rent = Rent::get()?;
+    let lamports = rent.minimum_balance(STATE_SIZE);
+    let signer_seeds: &[&[u8]] = &[seeds::STATE, admin.key.as_ref(), &[bump]];
+
+    invoke_signed(
+        &system_instruction::create_account(
+            admin.key,
+            state_account.key,
+            lamports,
+            STATE_SIZE as u64,
+            program_id,
+        ),
+        &[admin.clone(), state_account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )?;
+
+    // Initialize state data
+    let mut data = state_account.try_borrow_mut_data()?;
+    data[0..8].copy_from_slice(&STATE_DISCRIMINATOR);
+    data[VERSION] = STATE_VERSION;
+    data[ADMIN..ADMIN + 32].copy_from_slice(admin.key.as_ref()); // admin pubkey
+    data[SMT_ROOT..SMT_ROOT + 32].copy_from_slice(&[0u8; 32]); // smt_root (initially zero)
+    data[ROOT_VALID_UNTIL..ROOT_VALID_UNTIL + 8].copy_from_slice(&0i64.to_le_bytes()); // no expiry
+    data[TRANSFER_FLAGS] = 0; // transfer_flags (composition allowed)
+    data[DAILY_LIMIT..DAILY_LIMIT + 8].copy_from_slice(&0u64.to_le_bytes()); // unlimited
+    data[FEE_BPS..FEE_BPS + 2].copy_from_slice(&0u16.to_le_bytes()); // no fee
+    data[ROOT_SEQ..ROOT_SEQ + 8].copy_from_slice(&0u64.to_le_bytes()); // root_seq
+    data[APPROVED_CALLER_COUNT..BUMP].fill(0); // approved callers (none)
+    data[BUMP] = bump;
+    // Treasury bump, so fee checks can skip find_program_address
+    data[TREASURY_BUMP] =
+        Pubkey::find_program_address(&[seeds::TREASURY, state_account.key.as_ref()], program_id).1;
+    data[HASH_SCHEME] = hash_scheme::POSEIDON;
+    data[VERIFIER_FLAGS] = 0; // gnark witnesses (big-endian)
+    data[METADATA..METADATA + instruction::METADATA_SIZE].fill(0); // metadata (none published)
+    data[CHALLENGED] = 0;
+    data[BOND_BUMP] =
+        Pubkey::find_program_address(&[seeds::BOND, state_account.key.as_ref()], program_id).1;
+    data[BOND_AMOUNT..BOND_WITHDRAWAL_AT + 8].fill(0); // no bond, no pending withdrawal
+    data[TREE_KIND] = tree_kind::SPARSE;
+    data[TREE_DEPTH..TREE_DEPTH + 2].copy_from_slice(&(smt::TREE_DEPTH as u16).to_le_bytes());
+    drop(data);
+
+    msg!("State initialized with admin: {}", admin.key);
+
+    if bond_lamports > 0 {
+        let bond = bond.ok_or_else(|| {
+            msg!("Bond account required when depositing a bond");
+            ProgramError::NotEnoughAccountKeys
+        })?;
+        deposit_bond(
+            program_id,
+            admin,
+            state_account,
+            bond,
+            system_program,
+            bond_lamports,
+        )?;
+    }
+    Ok(())
+}
+
+/// Upgrade the caller's state account to the current layout, reallocating it
+///
+/// Unversioned layouts only ever appended zero-defaulted fields, so they upgrade
+/// by inserting the version byte and zero-filling the fields they lack; later
+/// layouts append fields, which are filled in here. Accounts already at
+/// STATE_VERSION are left unchanged.
+///
+/// Accounts:
+///   0. [signer, writable] Admin (pays rent for the larger account)
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///   2. [] System program
+pub(crate) fn process_migrate_state(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !admin.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (state_pda, bump) =
+        Pubkey::find_program_address(&[seeds::STATE, admin.key.as_ref()], program_id);
+    if state_account.key != &state_pda || state_account.owner != program_id {
+        msg!("State account does not match admin's PDA");
+        return Err(ExclusionError::InvalidStatePda.into());
+    }
+
+    let state_data = state_account.try_borrow_data()?;
+    if state_data[0..8] != STATE_DISCRIMINATOR {
+        msg!("Invalid state account");
+        return Err(ExclusionError::InvalidStateAccount.into());
+    }
+    let version = state_version(&state_data);
+    if version == STATE_VERSION {
+        msg!("State account already at layout v{}", STATE_VERSION);
+        return Ok(());
+    }
+    if version > STATE_VERSION {
+        msg!("Unknown state layout v{}", version);
+        return Err(ExclusionError::InvalidStateAccount.into());
+    }
+
+    let mut upgraded = vec![0u8; STATE_SIZE];
+    if version == 0 {
+        // v1: every field after the discriminator moves up by the version byte
+        upgraded[0..8].copy_from_slice(&state_data[0..8]);
+        upgraded[ADMIN..ADMIN + state_data.len() - 8].copy_from_slice(&state_data[8..]);
+    } else {
+        upgraded[..state_data.len()].copy_from_slice(&state_data);
+    }
+    drop(state_data);
+    upgraded[VERSION] = STATE_VERSION;
+    // v2: PDA bumps, searched for once here so later instructions don't have to
+    upgraded[BUMP] = bump;
+    upgraded[TREASURY_BUMP] =
+        Pubkey::find_program_address(&[seeds::TREASURY, state_account.key.as_ref()], program_id).1;
+    // v3: hash_scheme, zero-filled to POSEIDON
+    // v4: verifier_flags, zero-filled to gnark's big-endian witnesses
+    // v5: metadata, zero-filled (none published)
+    // v6: challenged, zero-filled (not flagged)
+    // v7: bond bump; bond_amount and pending withdrawal zero-filled (no bond)
+    upgraded[BOND_BUMP] =
+        Pubkey::find_program_address(&[seeds::BOND, state_account.key.as_ref()], program_id).1;
+    // v8: config, zero-filled (not linked)
+    // v9: tree_kind, zero-filled to SPARSE
+    // v10: tree_depth, the default depth every earlier tree has
+    upgraded[TREE_DEPTH..TREE_DEPTH + 2].copy_from_slice(&(smt::TREE_DEPTH as u16).to_le_bytes());
+
+    let shortfall = Rent::get()?
+        .minimum_balance(STATE_SIZE)
+        .saturating_sub(state_account.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(admin.key, state_account.key, shortfall),
+            &[admin.clone(), state_account.clone(), system_program.clone()],
+        )?;
+    }
+    state_account.resize(STATE_SIZE)?;
+
+    state_account
+        .try_borrow_mut_data()?
+        .copy_from_slice(&upgraded);
+
+    msg!(
+        "State account migrated from layout v{} to v{}",
+        version,
+        STATE_VERSION
+    );
+    Ok(())
+}
+
+/// Set the SMT root for the caller's state account
+///
+/// Accounts:
+///   0. [signer] Admin
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///   2. [] Noop program (with the `noop-events` feature)
+///
+/// Data:
+///   - 32 bytes: new SMT root
+///   - 8 bytes: root_valid_until (i64 LE unix timestamp, 0 = never expires; version 2+,
+///     and version 1 roots never expire)
+///   - 64 bytes: metadata (off-chain list pointer; version 3+)
+pub(crate) fn process_set_smt_root(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+
+    // A version 1 root has no expiry. An expiry set for the previous root
+    // would not describe the new one, and might already have passed.
+    let valid_until = data
+        .get(32..40)
+        .map_or(0, |bytes| i64::from_le_bytes(bytes.try_into().unwrap()));
+    // A pointer published for the previous root would not describe the new one
+    let metadata = data
+        .get(40..40 + instruction::METADATA_SIZE)
+        .unwrap_or(&[0; instruction::METADATA_SIZE]);
+
+    // Update SMT root
+    let mut state_data = state_account.try_borrow_mut_data()?;
+    let root_updated = set_root(&mut state_data, &data[0..32]);
+    state_data[ROOT_VALID_UNTIL..ROOT_VALID_UNTIL + 8].copy_from_slice(&valid_until.to_le_bytes());
+    state_data[METADATA..METADATA + instruction::METADATA_SIZE].copy_from_slice(metadata);
+
+    root_updated.emit(accounts)?;
+    msg!("SMT root valid until: {}", valid_until);
+    Ok(())
+}
+
+/// Set transfer policy flags for the caller's state account
+///
+/// Accounts:
+///   0. [signer] Admin
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///
+/// Data: 1 byte (transfer_flags, see `transfer_flags`)
+pub(crate) fn process_set_transfer_flags(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+
+    state_account.try_borrow_mut_data()?[TRANSFER_FLAGS] = data[0];
+
+    msg!("Transfer flags set to {:#04x}", data[0]);
+    Ok(())
+}
+
+/// Set the hash used for pubkey_hash in the caller's state account
+///
+/// The off-chain tree and the circuit must use the same scheme, so switching
+/// schemes needs a tree rebuilt under it (and a new root).
+///
+/// Accounts:
+///   0. [signer] Admin
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///
+/// Data: 1 byte (hash_scheme, see `hash_scheme`)
+pub(crate) fn process_set_hash_scheme(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+
+    // Fail now rather than on every later proof
+    pubkey_hash_be(admin.key, data[0])?;
+    state_account.try_borrow_mut_data()?[HASH_SCHEME] = data[0];
+
+    msg!("Hash scheme set to {}", data[0]);
+    Ok(())
+}
+
+/// Set proving backend options for the caller's state account
+///
+/// Accounts:
+///   0. [signer] Admin
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///
+/// Data: 1 byte (verifier_flags, see `verifier_flags`)
+pub(crate) fn process_set_verifier_flags(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+    state_account.try_borrow_mut_data()?[VERIFIER_FLAGS] = data[0];
+
+    msg!("Verifier flags set to {:#04x}", data[0]);
+    Ok(())
+}
+
+/// Set the kind of tree the caller's state account's root commits to
+///
+/// Like switching hash schemes, switching kinds needs the list rebuilt as the
+/// other tree and its root set. Indexed states take transfers proven by the
+/// indexed verifier; INSERT_LEAF, REMOVE_LEAF, SUBMIT_ROOT_TRANSITION and
+/// CHALLENGE check sparse-tree paths, so they reject them.
+///
+/// Accounts:
+///   0. [signer] Admin
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///
+/// Data: 1 byte (tree_kind, see `tree_kind`)
+pub(crate) fn process_set_tree_kind(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+    if !matches!(data[0], tree_kind::SPARSE | tree_kind::INDEXED) {
+        msg!("Unknown tree kind {}", data[0]);
+        return Err(ExclusionError::UnsupportedTreeKind.into());
+    }
+    state_account.try_borrow_mut_data()?[TREE_KIND] = data[0];
+
+    msg!("Tree kind set to {}", data[0]);
+    Ok(())
+}
+
+/// Set the depth of the sparse Merkle tree the caller's state account's root
+/// commits to
+///
+/// Each depth has its own circuit build, so switching depths needs the list
+/// rebuilt at the new depth, its root set, and that build's verifier
+/// registered for the depth. The program config's verifiers are built for
+/// `smt::TREE_DEPTH`, so transfers at other depths must use a registered one
+/// and SUBMIT_ROOT_TRANSITION rejects them. INSERT_LEAF, REMOVE_LEAF and
+/// CHALLENGE then take paths of the new depth.
+///
+/// Accounts:
+///   0. [signer] Admin
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///
+/// Data: 2 bytes (tree_depth, u16 LE; one of `smt::SUPPORTED_DEPTHS`)
+pub(crate) fn process_set_tree_depth(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+    let depth = u16::from_le_bytes([data[0], data[1]]);
+    if !smt::SUPPORTED_DEPTHS.contains(&(depth as usize)) {
+        msg!("Unsupported tree depth {}", depth);
+        return Err(ExclusionError::UnsupportedTreeDepth.into());
+    }
+    state_account.try_borrow_mut_data()?[TREE_DEPTH..TREE_DEPTH + 2].copy_from_slice(data);
+
+    msg!("Tree depth set to {}", depth);
+    Ok(())
+}
+
+/// Hand the caller's state account to a new admin
+///
+/// Takes effect immediately; the new admin doesn't sign, so it can be a PDA
+/// such as a multisig vault. The state account keeps its address, derived from
+/// the admin that initialized it.
+///
+/// Accounts:
+///   0. [signer] Admin
+///   1. [writable] State account
+///
+/// Data: 32 bytes (new admin)
+pub(crate) fn process_set_admin(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+
+    let new_admin = Pubkey::new_from_array(data.try_into().unwrap());
+    if new_admin == Pubkey::default() {
+        msg!("New admin must not be the default pubkey");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    state_account.try_borrow_mut_data()?[ADMIN..ADMIN + 32].copy_from_slice(data);
+
+    msg!("Admin changed from {} to {}", admin.key, new_admin);
+    Ok(())
+}
+
+/// Set the off-chain list pointer for the caller's state account without
+/// changing the root
+///
+/// Accounts:
+///   0. [signer] Admin
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///
+/// Data: 64 bytes (metadata, e.g. an IPFS CID or URL hash, zero-padded)
+pub(crate) fn process_set_metadata(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+    state_account.try_borrow_mut_data()?[METADATA..METADATA + instruction::METADATA_SIZE]
+        .copy_from_slice(data);
+
+    msg!("Metadata updated");
+    Ok(())
+}
+
+/// Set the per-sender 24-hour transfer limit for the caller's state account
+///
+/// Accounts:
+///   0. [signer] Admin
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///
+/// Data: 8 bytes (daily_limit in lamports, 0 = unlimited)
+pub(crate) fn process_set_rate_limit(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+
+    let mut state_data = state_account.try_borrow_mut_data()?;
+    state_data[DAILY_LIMIT..DAILY_LIMIT + 8].copy_from_slice(&data[0..8]);
+
+    msg!(
+        "Daily transfer limit set to {} lamports",
+        u64::from_le_bytes(data[0..8].try_into().unwrap())
+    );
+    Ok(())
+}
+
+/// Set the fee charged on gated transfers, creating the treasury it accrues in
+///
+/// Accounts:
+///   0. [signer, writable] Admin (pays for the treasury on first use)
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///   2. [writable] Treasury (PDA: ["treasury", state])
+///   3. [] System program
+///
+/// Data: 2 bytes (fee_bps, u16 LE, at most MAX_FEE_BPS)
+pub(crate) fn process_set_fee(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let treasury = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+
+    let fee_bps = u16::from_le_bytes(data[0..2].try_into().unwrap());
+    if fee_bps > MAX_FEE_BPS {
+        msg!("Fee of {} bps exceeds {}", fee_bps, MAX_FEE_BPS);
+        return Err(ExclusionError::InvalidFee.into());
+    }
+
+    let bump = treasury_bump(program_id, state_account)?;
+    let treasury_pda = Pubkey::create_program_address(
+        &[seeds::TREASURY, state_account.key.as_ref(), &[bump]],
+        program_id,
+    );
+    if treasury_pda.as_ref() != Ok(treasury.key) {
+        msg!("Treasury does not match state's PDA");
+        return Err(ExclusionError::InvalidTreasuryAccount.into());
+    }
+
+    if treasury.owner != program_id {
+        let lamports = Rent::get()?.minimum_balance(TREASURY_ACCOUNT_SIZE);
+        let signer_seeds: &[&[u8]] = &[seeds::TREASURY, state_account.key.as_ref(), &[bump]];
+        invoke_signed(
+            &system_instruction::create_account(
+                admin.key,
+                treasury.key,
+                lamports,
+                TREASURY_ACCOUNT_SIZE as u64,
+                program_id,
+            ),
+            &[admin.clone(), treasury.clone(), system_program.clone()],
+            &[signer_seeds],
+        )?;
+        treasury
+            .try_borrow_mut_data()?
+            .copy_from_slice(&TREASURY_ACCOUNT_DISCRIMINATOR);
+    }
+
+    state_account.try_borrow_mut_data()?[FEE_BPS..FEE_BPS + 2]
+        .copy_from_slice(&fee_bps.to_le_bytes());
+
+    msg!("Transfer fee set to {} bps", fee_bps);
+    Ok(())
+}
+
+/// Withdraw accrued fees from the treasury, leaving it rent-exempt
+///
+/// Accounts:
+///   0. [signer] Admin
+///   1. [] State account (PDA: ["state", admin_pubkey])
+///   2. [writable] Treasury (PDA: ["treasury", state])
+///   3. [writable] Destination
+pub(crate) fn process_withdraw_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let treasury = next_account_info(account_iter)?;
+    let destination = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+    check_treasury(program_id, state_account, treasury)?;
+
+    let rent_minimum = Rent::get()?.minimum_balance(TREASURY_ACCOUNT_SIZE);
+    let fees = treasury.lamports().saturating_sub(rent_minimum);
+    **treasury.try_borrow_mut_lamports()? -= fees;
+    **destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(fees)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    msg!("Withdrew {} lamports of fees to {}", fees, destination.key);
+    Ok(())
+}
+
+/// Set the programs allowed to CPI into gated transfers under
+/// REQUIRE_APPROVED_CALLER
+///
+/// Accounts:
+///   0. [signer] Admin
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///
+/// Data:
+///   - 1 byte: number of approved callers (at most MAX_APPROVED_CALLERS)
+///   - 32 bytes per slot (MAX_APPROVED_CALLERS slots): program IDs, unused slots zeroed
+pub(crate) fn process_set_approved_callers(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+
+    let count = data[0] as usize;
+    if count > MAX_APPROVED_CALLERS {
+        msg!("At most {} approved callers", MAX_APPROVED_CALLERS);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let mut state_data = state_account.try_borrow_mut_data()?;
+    // Store only the used slots so stale entries can't linger past `count`
+    state_data[APPROVED_CALLER_COUNT] = data[0];
+    state_data[APPROVED_CALLERS..BUMP].fill(0);
+    state_data[APPROVED_CALLERS..APPROVED_CALLERS + 32 * count]
+        .copy_from_slice(&data[1..1 + 32 * count]);
+
+    msg!("{} approved callers set", count);
+    Ok(())
+}
@@ -0,0 +1,310 @@
+//! Attestations: exclusion proofs recorded on-chain for other programs to read
+
+use exclusion_common::seeds;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+use solana_system_interface::instruction as system_instruction;
+
+use super::verify::verify_exclusion_proof;
+use crate::{
+    attestation_offsets,
+    events::{Attested, Event},
+    instruction, light,
+    state_offsets::*,
+    ExclusionError, ATTESTATION_DISCRIMINATOR, ATTESTATION_SIZE,
+};
+
+/// Record that the signer's exclusion proof verified, without moving funds
+///
+/// Writes the signer's attestation account for the state: "screened at root X
+/// on slot Y". Other protocols read it instead of integrating the verifier, and
+/// judge its freshness themselves (e.g. require the state's current root, or a
+/// recent slot). Attesting again overwrites the record. The account is owned by
+/// this program and has no transfer instruction, so it cannot be moved to
+/// another key.
+///
+/// Accounts:
+///   0. [signer, writable] Subject (must prove NOT blacklisted; pays for the
+///      attestation account on first use)
+///   1. [] State account (contains SMT root)
+///   2. [] ZK Verifier program
+///   3. [writable] Attestation account (PDA: ["attestation", state, subject])
+///   4. [] System program
+///   5. [] Instructions sysvar (optional; required when the state sets a CPI guard)
+///
+/// The noop program, program config and verifier registry are found in any
+/// position after the fixed accounts.
+///
+/// Data:
+///   - 388 bytes: ZK proof
+///   - 108 bytes: public witness (as for TRANSFER_SOL)
+pub(crate) fn process_attest(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let subject = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let zk_verifier = next_account_info(account_iter)?;
+    let attestation = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let instructions_sysvar = account_iter.next();
+
+    if !subject.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (attestation_pda, bump) = Pubkey::find_program_address(
+        &[
+            seeds::ATTESTATION,
+            state_account.key.as_ref(),
+            subject.key.as_ref(),
+        ],
+        program_id,
+    );
+    if attestation.key != &attestation_pda {
+        msg!("Attestation account does not match the subject's PDA");
+        return Err(ExclusionError::InvalidAttestationAccount.into());
+    }
+
+    let proof_data = &data[..instruction::PROOF_SIZE];
+    let witness_data = &data[instruction::PROOF_SIZE..];
+    verify_exclusion_proof(
+        program_id,
+        accounts,
+        subject,
+        state_account,
+        zk_verifier,
+        instructions_sysvar,
+        proof_data,
+        witness_data,
+    )?;
+
+    if attestation.owner != program_id {
+        let lamports = Rent::get()?.minimum_balance(ATTESTATION_SIZE);
+        let signer_seeds: &[&[u8]] = &[
+            seeds::ATTESTATION,
+            state_account.key.as_ref(),
+            subject.key.as_ref(),
+            &[bump],
+        ];
+        invoke_signed(
+            &system_instruction::create_account(
+                subject.key,
+                attestation.key,
+                lamports,
+                ATTESTATION_SIZE as u64,
+                program_id,
+            ),
+            &[subject.clone(), attestation.clone(), system_program.clone()],
+            &[signer_seeds],
+        )?;
+        attestation.try_borrow_mut_data()?[0..8].copy_from_slice(&ATTESTATION_DISCRIMINATOR);
+    }
+
+    // The proof's root was checked against the stored root
+    let smt_root: [u8; 32] = state_account.try_borrow_data()?[SMT_ROOT..SMT_ROOT + 32]
+        .try_into()
+        .unwrap();
+    let slot = Clock::get()?.slot;
+
+    let mut record = attestation.try_borrow_mut_data()?;
+    if record.len() != ATTESTATION_SIZE || record[0..8] != ATTESTATION_DISCRIMINATOR {
+        msg!("Invalid attestation account");
+        return Err(ExclusionError::InvalidAttestationAccount.into());
+    }
+    record[attestation_offsets::SUBJECT..attestation_offsets::SUBJECT + 32]
+        .copy_from_slice(subject.key.as_ref());
+    record[attestation_offsets::STATE..attestation_offsets::STATE + 32]
+        .copy_from_slice(state_account.key.as_ref());
+    record[attestation_offsets::SMT_ROOT..attestation_offsets::SMT_ROOT + 32]
+        .copy_from_slice(&smt_root);
+    record[attestation_offsets::SLOT..attestation_offsets::SLOT + 8]
+        .copy_from_slice(&slot.to_le_bytes());
+    drop(record);
+
+    Attested {
+        subject: *subject.key,
+        state: *state_account.key,
+        smt_root,
+        slot,
+    }
+    .emit(accounts)?;
+
+    msg!("Attested {} at slot {}", subject.key, slot);
+    Ok(())
+}
+
+/// Record that the signer's exclusion proof verified as a Light Protocol
+/// compressed account instead of a rent-paying PDA
+///
+/// The record holds the same fields as an ATTEST account (after its
+/// discriminator), with the attestation discriminator as the compressed
+/// account's. Its address is derived from ["attestation", state, subject,
+/// smt_root], so there is one record per root: attesting again at a new root
+/// creates another, and the validity proof (from a Light indexer) shows the
+/// address is still free.
+///
+/// Accounts:
+///   0. [signer, writable] Subject (must prove NOT blacklisted; pays Light's fees)
+///   1. [] State account (contains SMT root)
+///   2. [] ZK Verifier program
+///   3. [] Light system program
+///   4. [] Light CPI authority (PDA: ["cpi_authority"])
+///   5. [] Registered program PDA (the Light system program's, in account compression)
+///   6. [] Noop program
+///   7. [] Account compression authority
+///   8. [] Account compression program
+///   9. [] This program
+///  10. [] System program
+///  11. [writable] Address merkle tree
+///  12. [writable] Address queue
+///  13. [writable] Output state merkle tree
+///  14. [] Instructions sysvar (optional; required when the state sets a CPI guard)
+///
+/// Data:
+///   - 388 bytes: ZK proof
+///   - 108 bytes: public witness (as for TRANSFER_SOL)
+///   - 128 bytes: Light validity proof (the new address is not in the tree)
+///   - 2 bytes: address tree root index the validity proof was made against (u16 LE)
+pub(crate) fn process_attest_compressed(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let subject = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let zk_verifier = next_account_info(account_iter)?;
+    let light_system_program = next_account_info(account_iter)?;
+    let cpi_authority = next_account_info(account_iter)?;
+    let registered_program_pda = next_account_info(account_iter)?;
+    let noop_program = next_account_info(account_iter)?;
+    let account_compression_authority = next_account_info(account_iter)?;
+    let account_compression_program = next_account_info(account_iter)?;
+    let this_program = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let address_merkle_tree = next_account_info(account_iter)?;
+    let address_queue = next_account_info(account_iter)?;
+    let output_state_tree = next_account_info(account_iter)?;
+    let instructions_sysvar = account_iter.next();
+
+    if !subject.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // The CPI authority's signature must only ever reach the Light system program
+    if light_system_program.key != &light::LIGHT_SYSTEM_PROGRAM_ID {
+        msg!("Invalid Light system program");
+        return Err(ExclusionError::InvalidLightAccount.into());
+    }
+    let (authority_pda, bump) =
+        Pubkey::find_program_address(&[light::CPI_AUTHORITY_SEED], program_id);
+    if cpi_authority.key != &authority_pda {
+        msg!("Invalid Light CPI authority");
+        return Err(ExclusionError::InvalidLightAccount.into());
+    }
+
+    let proof_data = &data[..instruction::PROOF_SIZE];
+    let witness_data = &data[instruction::PROOF_SIZE..instruction::ATTEST_LEN];
+    let validity_proof =
+        &data[instruction::ATTEST_LEN..instruction::ATTEST_LEN + light::VALIDITY_PROOF_SIZE];
+    let root_index = u16::from_le_bytes(
+        data[instruction::ATTEST_COMPRESSED_LEN - 2..]
+            .try_into()
+            .unwrap(),
+    );
+    verify_exclusion_proof(
+        program_id,
+        accounts,
+        subject,
+        state_account,
+        zk_verifier,
+        instructions_sysvar,
+        proof_data,
+        witness_data,
+    )?;
+
+    // The proof's root was checked against the stored root
+    let smt_root: [u8; 32] = state_account.try_borrow_data()?[SMT_ROOT..SMT_ROOT + 32]
+        .try_into()
+        .unwrap();
+    let slot = Clock::get()?.slot;
+
+    // An ATTEST account's fields, without the discriminator
+    let mut record = [0u8; ATTESTATION_SIZE - 8];
+    record[..32].copy_from_slice(subject.key.as_ref());
+    record[32..64].copy_from_slice(state_account.key.as_ref());
+    record[64..96].copy_from_slice(&smt_root);
+    record[96..].copy_from_slice(&slot.to_le_bytes());
+
+    let seed = light::derive_address_seed(
+        &[
+            seeds::ATTESTATION,
+            state_account.key.as_ref(),
+            subject.key.as_ref(),
+            &smt_root,
+        ],
+        program_id,
+    );
+    invoke_signed(
+        &light::create_compressed_account(
+            &light::InvokeCpiAccounts {
+                fee_payer: subject.key,
+                authority: cpi_authority.key,
+                registered_program_pda: registered_program_pda.key,
+                noop_program: noop_program.key,
+                account_compression_authority: account_compression_authority.key,
+                invoking_program: this_program.key,
+                address_merkle_tree: address_merkle_tree.key,
+                address_queue: address_queue.key,
+                output_state_tree: output_state_tree.key,
+            },
+            &light::NewCompressedAccount {
+                owner: program_id,
+                seed,
+                address: light::derive_address(&seed, address_merkle_tree.key),
+                discriminator: ATTESTATION_DISCRIMINATOR,
+                data: &record,
+                address_merkle_tree_root_index: root_index,
+            },
+            validity_proof,
+        ),
+        &[
+            subject.clone(),
+            cpi_authority.clone(),
+            registered_program_pda.clone(),
+            noop_program.clone(),
+            account_compression_authority.clone(),
+            account_compression_program.clone(),
+            this_program.clone(),
+            light_system_program.clone(),
+            system_program.clone(),
+            address_merkle_tree.clone(),
+            address_queue.clone(),
+            output_state_tree.clone(),
+        ],
+        &[&[light::CPI_AUTHORITY_SEED, &[bump]]],
+    )?;
+
+    Attested {
+        subject: *subject.key,
+        state: *state_account.key,
+        smt_root,
+        slot,
+    }
+    .emit(accounts)?;
+
+    msg!("Attested {} at slot {} (compressed)", subject.key, slot);
+    Ok(())
+}
@@ -0,0 +1,366 @@
+//! The admin's bond and the challenges that slash it
+
+use exclusion_common::seeds;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+use solana_system_interface::instruction as system_instruction;
+
+use super::{
+    check_admin_state, check_sparse_tree, check_state_layout,
+    config::ProgramConfig,
+    tree_depth,
+    verify::{check_zk_verifier, invoke_verifier, witness_field_be},
+};
+use crate::{
+    events::{ChallengeUpheld, Event},
+    instruction, smt,
+    state_offsets::*,
+    ExclusionError, BOND_ACCOUNT_DISCRIMINATOR, BOND_ACCOUNT_SIZE, BOND_WITHDRAWAL_DELAY,
+};
+
+/// Add lamports to the admin's bond, creating the bond account on first use
+///
+/// Accounts:
+///   0. [signer, writable] Admin (pays the bond, and rent for the bond account)
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///   2. [writable] Bond account (PDA: ["bond", state])
+///   3. [] System program
+///
+/// Data: 8 bytes (lamports, u64 LE)
+pub(crate) fn process_deposit_bond(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let bond = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+    if state_account.try_borrow_data()?[CHALLENGED] != 0 {
+        msg!("State account was flagged by a successful challenge");
+        return Err(ExclusionError::StateChallenged.into());
+    }
+
+    let lamports = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    deposit_bond(
+        program_id,
+        admin,
+        state_account,
+        bond,
+        system_program,
+        lamports,
+    )
+}
+
+/// Start the delay before part of the bond can be withdrawn
+///
+/// Replaces any pending request and restarts the delay. The requested lamports
+/// stay in the bond, and can be slashed, until WITHDRAW_BOND.
+///
+/// Accounts:
+///   0. [signer] Admin
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///
+/// Data: 8 bytes (lamports, u64 LE, at most bond_amount; 0 cancels a pending request)
+pub(crate) fn process_request_bond_withdrawal(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+
+    let lamports = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    let mut state_data = state_account.try_borrow_mut_data()?;
+    let bonded = bond_amount(&state_data);
+    if lamports > bonded {
+        msg!(
+            "Withdrawal of {} lamports exceeds the bond of {}",
+            lamports,
+            bonded
+        );
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    let available_at = Clock::get()?.unix_timestamp + BOND_WITHDRAWAL_DELAY;
+    state_data[BOND_WITHDRAWAL_AMOUNT..BOND_WITHDRAWAL_AMOUNT + 8]
+        .copy_from_slice(&lamports.to_le_bytes());
+    state_data[BOND_WITHDRAWAL_AT..BOND_WITHDRAWAL_AT + 8]
+        .copy_from_slice(&available_at.to_le_bytes());
+
+    msg!(
+        "Bond withdrawal of {} lamports available at {}",
+        lamports,
+        available_at
+    );
+    Ok(())
+}
+
+/// Withdraw the requested part of the bond once its delay has passed
+///
+/// Accounts:
+///   0. [signer] Admin
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///   2. [writable] Bond account (PDA: ["bond", state])
+///   3. [writable] Destination
+pub(crate) fn process_withdraw_bond(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let bond = next_account_info(account_iter)?;
+    let destination = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+    check_bond(program_id, state_account, bond)?;
+
+    let mut state_data = state_account.try_borrow_mut_data()?;
+    let lamports = u64::from_le_bytes(
+        state_data[BOND_WITHDRAWAL_AMOUNT..BOND_WITHDRAWAL_AMOUNT + 8]
+            .try_into()
+            .unwrap(),
+    );
+    let available_at = i64::from_le_bytes(
+        state_data[BOND_WITHDRAWAL_AT..BOND_WITHDRAWAL_AT + 8]
+            .try_into()
+            .unwrap(),
+    );
+    if lamports == 0 || Clock::get()?.unix_timestamp < available_at {
+        msg!("No bond withdrawal is available yet");
+        return Err(ExclusionError::BondWithdrawalLocked.into());
+    }
+
+    let remaining = bond_amount(&state_data) - lamports;
+    state_data[BOND_AMOUNT..BOND_AMOUNT + 8].copy_from_slice(&remaining.to_le_bytes());
+    state_data[BOND_WITHDRAWAL_AMOUNT..BOND_WITHDRAWAL_AT + 8].fill(0);
+    drop(state_data);
+
+    pay_from_bond(bond, destination, lamports)?;
+
+    msg!(
+        "Withdrew {} lamports of bond to {}",
+        lamports,
+        destination.key
+    );
+    Ok(())
+}
+
+/// Flag a state account whose verifier let a blacklisted key through
+///
+/// Anyone may submit an exclusion proof made against the stored root (such as the
+/// one from a transfer, copied from transaction history) together with a merkle
+/// path showing that the proof's pubkey hash IS in that tree. A sound verifier
+/// never accepts both, so a successful challenge shows the admin's proving setup
+/// attests excluded keys that are blacklisted. The state account is flagged for
+/// good and every transfer against it is rejected with `StateChallenged`. The
+/// whole bond, including any pending withdrawal, is paid to the challenger.
+/// Proofs can only be challenged while their root is current. Recomputing the
+/// root over the default 254 levels costs a few hundred thousand CUs; request a
+/// compute budget.
+///
+/// Accounts:
+///   0. [signer, writable] Challenger (receives the bond)
+///   1. [writable] State account (any state account owned by this program)
+///   2. [] ZK Verifier program (the state's, or one the verifier registry lists
+///      for its tree, as for transfers)
+///   3. [writable] Bond account (PDA: ["bond", state]; need not exist)
+///   4. [] Noop program (with the `noop-events` feature)
+///
+/// Data:
+///   - 388 bytes: ZK proof
+///   - 108 bytes: public witness (12-byte header + smt_root + pubkey_hash + recent_slot)
+///   - 32 bytes: sibling bitmap (bit i set = sibling at level i is provided)
+///   - 32 bytes per set bit: non-default siblings, lowest level first
+pub(crate) fn process_challenge(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let challenger = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let zk_verifier = next_account_info(account_iter)?;
+    let bond = next_account_info(account_iter)?;
+
+    if !challenger.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if state_account.owner != program_id {
+        msg!("State account not owned by this program");
+        return Err(ExclusionError::InvalidStateAccount.into());
+    }
+
+    let (proof_data, rest) = data.split_at(instruction::PROOF_SIZE);
+    let (witness_data, rest) = rest.split_at(instruction::WITNESS_SIZE);
+    let (sibling_bitmap, siblings) = rest.split_at(32);
+
+    let state_data = state_account.try_borrow_data()?;
+    check_state_layout(&state_data)?;
+    if state_data[CHALLENGED] != 0 {
+        msg!("State account was already flagged");
+        return Err(ExclusionError::StateChallenged.into());
+    }
+    check_sparse_tree(&state_data)?;
+
+    // A pause does not block challenges
+    let config = ProgramConfig::for_state(program_id, &state_data, accounts)?;
+    check_zk_verifier(program_id, accounts, &state_data, &config, zk_verifier.key)?;
+
+    // Witness format: 12-byte header + smt_root (32) + pubkey_hash (32) + recent_slot (32)
+    let flags = state_data[VERIFIER_FLAGS];
+    let root: [u8; 32] = state_data[SMT_ROOT..SMT_ROOT + 32].try_into().unwrap();
+    if witness_field_be(&witness_data[12..44], flags) != root {
+        msg!("SMT root in proof does not match stored root");
+        return Err(ExclusionError::SmtRootMismatch.into());
+    }
+    let depth = tree_depth(&state_data);
+    drop(state_data);
+
+    // The key the proof claims is excluded must be included in the same tree
+    let pubkey_hash = witness_field_be(&witness_data[44..76], flags);
+    let included_root = smt::compute_root(
+        &pubkey_hash,
+        &smt::leaf_value(&pubkey_hash, depth),
+        depth,
+        sibling_bitmap,
+        siblings,
+    )?;
+    if included_root != root {
+        msg!("Merkle path does not include the key in the stored root");
+        return Err(ExclusionError::LeafPathMismatch.into());
+    }
+
+    // Proof recency is not checked: old transfer proofs are the evidence
+    msg!("Verifying challenged exclusion proof...");
+    invoke_verifier(zk_verifier.key, proof_data, witness_data)?;
+
+    // Slash the bond; an admin who never deposited one has nothing to pay
+    let mut state_data = state_account.try_borrow_mut_data()?;
+    state_data[CHALLENGED] = 1;
+    let slashed = bond_amount(&state_data);
+    state_data[BOND_AMOUNT..BOND_WITHDRAWAL_AT + 8].fill(0);
+    drop(state_data);
+    if slashed > 0 {
+        check_bond(program_id, state_account, bond)?;
+        pay_from_bond(bond, challenger, slashed)?;
+    }
+
+    ChallengeUpheld {
+        challenger: *challenger.key,
+        pubkey_hash,
+        root,
+        slashed,
+    }
+    .emit(accounts)?;
+
+    msg!("Challenge upheld - state account flagged");
+    Ok(())
+}
+
+/// Read bond_amount (lamports bonded, excluding the bond account's rent) from state data
+fn bond_amount(state_data: &[u8]) -> u64 {
+    u64::from_le_bytes(state_data[BOND_AMOUNT..BOND_AMOUNT + 8].try_into().unwrap())
+}
+
+/// Move `lamports` from the admin into the state's bond account, creating it
+/// on first use, and add them to bond_amount
+pub(super) fn deposit_bond<'a>(
+    program_id: &Pubkey,
+    admin: &AccountInfo<'a>,
+    state_account: &AccountInfo<'a>,
+    bond: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    lamports: u64,
+) -> ProgramResult {
+    let bump = state_account.try_borrow_data()?[BOND_BUMP];
+    let bond_pda = Pubkey::create_program_address(
+        &[seeds::BOND, state_account.key.as_ref(), &[bump]],
+        program_id,
+    );
+    if bond_pda.as_ref() != Ok(bond.key) {
+        msg!("Bond account does not match state's PDA");
+        return Err(ExclusionError::InvalidBondAccount.into());
+    }
+
+    if bond.owner != program_id {
+        let rent = Rent::get()?.minimum_balance(BOND_ACCOUNT_SIZE);
+        let signer_seeds: &[&[u8]] = &[seeds::BOND, state_account.key.as_ref(), &[bump]];
+        invoke_signed(
+            &system_instruction::create_account(
+                admin.key,
+                bond.key,
+                rent,
+                BOND_ACCOUNT_SIZE as u64,
+                program_id,
+            ),
+            &[admin.clone(), bond.clone(), system_program.clone()],
+            &[signer_seeds],
+        )?;
+        bond.try_borrow_mut_data()?
+            .copy_from_slice(&BOND_ACCOUNT_DISCRIMINATOR);
+    }
+    check_bond(program_id, state_account, bond)?;
+
+    invoke(
+        &system_instruction::transfer(admin.key, bond.key, lamports),
+        &[admin.clone(), bond.clone(), system_program.clone()],
+    )?;
+
+    let mut state_data = state_account.try_borrow_mut_data()?;
+    let bonded = bond_amount(&state_data)
+        .checked_add(lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    state_data[BOND_AMOUNT..BOND_AMOUNT + 8].copy_from_slice(&bonded.to_le_bytes());
+
+    msg!("Deposited {} lamports of bond, {} bonded", lamports, bonded);
+    Ok(())
+}
+
+/// Verify that a bond account is the state's PDA and has been created
+fn check_bond(
+    program_id: &Pubkey,
+    state_account: &AccountInfo,
+    bond: &AccountInfo,
+) -> ProgramResult {
+    let bump = state_account.try_borrow_data()?[BOND_BUMP];
+    let bond_pda = Pubkey::create_program_address(
+        &[seeds::BOND, state_account.key.as_ref(), &[bump]],
+        program_id,
+    );
+    if bond_pda.as_ref() != Ok(bond.key)
+        || bond.owner != program_id
+        || bond.try_borrow_data()?[..] != BOND_ACCOUNT_DISCRIMINATOR
+    {
+        msg!("Invalid bond account");
+        return Err(ExclusionError::InvalidBondAccount.into());
+    }
+    Ok(())
+}
+
+/// Move bonded lamports out of the (program-owned) bond account
+fn pay_from_bond(bond: &AccountInfo, destination: &AccountInfo, lamports: u64) -> ProgramResult {
+    **bond.try_borrow_mut_lamports()? -= lamports;
+    **destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    Ok(())
+}
@@ -0,0 +1,232 @@
+//! Proof buffers, for proofs written across several transactions
+
+use exclusion_common::seeds;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+use solana_system_interface::instruction as system_instruction;
+
+use super::{
+    close_account, set_verification_result,
+    transfer::{charge_fee, record_rate_limited_transfer},
+    verify::verify_exclusion_proof,
+};
+use crate::{
+    events::{Event, TransferVerified},
+    instruction, ExclusionError, PROOF_BUFFER_DISCRIMINATOR, PROOF_BUFFER_HEADER_SIZE,
+    PROOF_BUFFER_SIZE,
+};
+
+/// Create a proof buffer for uploading proof + witness across several transactions
+///
+/// Accounts:
+///   0. [signer, writable] Owner (payer, and the sender who will consume the buffer)
+///   1. [writable] Proof buffer account (PDA: ["proof_buffer", owner_pubkey])
+///   2. [] System program
+pub(crate) fn process_create_proof_buffer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let buffer_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (buffer_pda, bump) =
+        Pubkey::find_program_address(&[seeds::PROOF_BUFFER, owner.key.as_ref()], program_id);
+    if buffer_account.key != &buffer_pda {
+        msg!("Invalid proof buffer PDA");
+        return Err(ExclusionError::InvalidProofBuffer.into());
+    }
+
+    let rent = Rent::get()?;
+    let lamports = rent.minimum_balance(PROOF_BUFFER_SIZE);
+    let signer_seeds: &[&[u8]] = &[seeds::PROOF_BUFFER, owner.key.as_ref(), &[bump]];
+
+    invoke_signed(
+        &system_instruction::create_account(
+            owner.key,
+            buffer_account.key,
+            lamports,
+            PROOF_BUFFER_SIZE as u64,
+            program_id,
+        ),
+        &[
+            owner.clone(),
+            buffer_account.clone(),
+            system_program.clone(),
+        ],
+        &[signer_seeds],
+    )?;
+
+    let mut data = buffer_account.try_borrow_mut_data()?;
+    data[0..8].copy_from_slice(&PROOF_BUFFER_DISCRIMINATOR);
+    data[8..40].copy_from_slice(owner.key.as_ref());
+
+    msg!("Proof buffer created for {}", owner.key);
+    Ok(())
+}
+
+/// Write a chunk of proof + witness bytes into the owner's proof buffer
+///
+/// Accounts:
+///   0. [signer] Owner
+///   1. [writable] Proof buffer account (PDA: ["proof_buffer", owner_pubkey])
+///
+/// Data:
+///   - 2 bytes: offset into the proof + witness payload (u16 LE)
+///   - 2 bytes: chunk length (u16 LE)
+///   - N bytes: chunk
+pub(crate) fn process_write_proof_chunk(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let buffer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    check_proof_buffer(program_id, owner, buffer_account)?;
+
+    let offset = u16::from_le_bytes(data[0..2].try_into().unwrap()) as usize;
+    let chunk = &data[4..];
+    if offset + chunk.len() > instruction::PROOF_SIZE + instruction::WITNESS_SIZE {
+        msg!("Proof chunk out of bounds");
+        return Err(ExclusionError::InvalidDataLength.into());
+    }
+
+    let mut buffer_data = buffer_account.try_borrow_mut_data()?;
+    let start = PROOF_BUFFER_HEADER_SIZE + offset;
+    buffer_data[start..start + chunk.len()].copy_from_slice(chunk);
+
+    msg!("Wrote {} bytes at offset {}", chunk.len(), offset);
+    Ok(())
+}
+
+/// Transfer SOL using a proof + witness previously uploaded to a proof buffer.
+/// The buffer is closed and its rent refunded to the sender afterwards.
+///
+/// Accounts:
+///   0. [signer, writable] Sender (must prove NOT blacklisted, owner of the buffer)
+///   1. [writable] Recipient
+///   2. [] State account (contains SMT root)
+///   3. [] ZK Verifier program
+///   4. [] System program
+///   5. [writable] Proof buffer account (PDA: ["proof_buffer", sender_pubkey])
+///
+/// Then the proof accounts (see [`crate::processor`]).
+///
+/// Data: 8 bytes (amount in lamports)
+pub(crate) fn process_transfer_from_buffer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let sender = next_account_info(account_iter)?;
+    let recipient = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let zk_verifier = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let buffer_account = next_account_info(account_iter)?;
+    let instructions_sysvar = account_iter.next();
+    let rate_limit_account = account_iter.next();
+    let treasury = account_iter.next();
+
+    if !sender.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    check_proof_buffer(program_id, sender, buffer_account)?;
+
+    let amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
+
+    let buffer_data = buffer_account.try_borrow_data()?;
+    let payload = &buffer_data[PROOF_BUFFER_HEADER_SIZE..];
+    let (proof_data, witness_data) = payload.split_at(instruction::PROOF_SIZE);
+    let sender_hash = verify_exclusion_proof(
+        program_id,
+        accounts,
+        sender,
+        state_account,
+        zk_verifier,
+        instructions_sysvar,
+        proof_data,
+        witness_data,
+    )?;
+    drop(buffer_data);
+    record_rate_limited_transfer(
+        program_id,
+        sender,
+        sender,
+        state_account,
+        system_program,
+        rate_limit_account,
+        amount,
+    )?;
+    charge_fee(
+        program_id,
+        accounts,
+        sender,
+        state_account,
+        system_program,
+        treasury,
+        amount,
+    )?;
+
+    // Transfer SOL
+    invoke(
+        &system_instruction::transfer(sender.key, recipient.key, amount),
+        &[sender.clone(), recipient.clone(), system_program.clone()],
+    )?;
+
+    close_account(buffer_account, sender, system_program)?;
+
+    TransferVerified {
+        sender_hash,
+        amount,
+        recipient: *recipient.key,
+    }
+    .emit(accounts)?;
+    set_verification_result(state_account, sender_hash, amount)?;
+
+    msg!("Transfer complete, proof buffer closed");
+    Ok(())
+}
+
+/// Verify that a proof buffer is the owner's PDA and has been initialized
+pub(super) fn check_proof_buffer(
+    program_id: &Pubkey,
+    owner: &AccountInfo,
+    buffer_account: &AccountInfo,
+) -> ProgramResult {
+    let (buffer_pda, _bump) =
+        Pubkey::find_program_address(&[seeds::PROOF_BUFFER, owner.key.as_ref()], program_id);
+    if buffer_account.key != &buffer_pda {
+        msg!("Proof buffer does not match owner's PDA");
+        return Err(ExclusionError::InvalidProofBuffer.into());
+    }
+
+    let buffer_data = buffer_account.try_borrow_data()?;
+    if buffer_data.len() != PROOF_BUFFER_SIZE
+        || buffer_data[0..8] != PROOF_BUFFER_DISCRIMINATOR
+        || &buffer_data[8..40] != owner.key.as_ref()
+    {
+        msg!("Invalid proof buffer account");
+        return Err(ExclusionError::InvalidProofBuffer.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,240 @@
+//! The program config, set by the upgrade authority, that linked states follow
+
+use exclusion_common::seeds;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+use solana_sdk_ids::bpf_loader_upgradeable;
+use solana_system_interface::instruction as system_instruction;
+
+use super::check_admin_state;
+use crate::{
+    config_offsets, state_offsets::*, ExclusionError, CONFIG_DISCRIMINATOR, CONFIG_SIZE,
+    MAX_FEE_BPS, MAX_PROOF_AGE_SLOTS, TRANSITION_VERIFIER_PROGRAM_ID, ZK_VERIFIER_PROGRAM_ID,
+};
+
+/// Create or update the program config, the operational settings shared by every
+/// state account linked to it with LINK_CONFIG
+///
+/// Only the program's upgrade authority may call this, so the settings are held
+/// to the same trust as the code itself. Transfers against linked states are
+/// rejected while `paused` is set.
+///
+/// Accounts:
+///   0. [signer, writable] Upgrade authority (pays for the config on first use)
+///   1. [writable] Config account (PDA: ["config"])
+///   2. [] Program data account (PDA: [program_id] under the upgradeable loader)
+///   3. [] System program
+///
+/// Data:
+///   - 1 byte: paused (0 or 1)
+///   - 32 bytes: exclusion ZK verifier program ID
+///   - 32 bytes: tree-transition verifier program ID
+///   - 8 bytes: max_proof_age_slots (u64 LE)
+///   - 2 bytes: max_fee_bps (u16 LE, at most MAX_FEE_BPS; caps every linked state's fee)
+pub(crate) fn process_set_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let authority = next_account_info(account_iter)?;
+    let config = next_account_info(account_iter)?;
+    let program_data = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    check_upgrade_authority(program_id, authority, program_data)?;
+
+    let paused = data[0];
+    if paused > 1 {
+        msg!("Invalid paused flag {}", paused);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let max_fee_bps = u16::from_le_bytes(data[73..75].try_into().unwrap());
+    if max_fee_bps > MAX_FEE_BPS {
+        msg!("Fee cap of {} bps exceeds {}", max_fee_bps, MAX_FEE_BPS);
+        return Err(ExclusionError::InvalidFee.into());
+    }
+
+    let (config_pda, bump) = Pubkey::find_program_address(&[seeds::CONFIG], program_id);
+    if config.key != &config_pda {
+        msg!("Config account does not match the config PDA");
+        return Err(ExclusionError::InvalidConfigAccount.into());
+    }
+
+    if config.owner != program_id {
+        let lamports = Rent::get()?.minimum_balance(CONFIG_SIZE);
+        invoke_signed(
+            &system_instruction::create_account(
+                authority.key,
+                config.key,
+                lamports,
+                CONFIG_SIZE as u64,
+                program_id,
+            ),
+            &[authority.clone(), config.clone(), system_program.clone()],
+            &[&[seeds::CONFIG, &[bump]]],
+        )?;
+    }
+
+    let mut config_data = config.try_borrow_mut_data()?;
+    config_data[0..8].copy_from_slice(&CONFIG_DISCRIMINATOR);
+    config_data[config_offsets::BUMP] = bump;
+    config_data[config_offsets::PAUSED..].copy_from_slice(data);
+
+    msg!("Program config updated (paused: {})", paused == 1);
+    Ok(())
+}
+
+/// Link the caller's state account to the program config
+///
+/// From then on the state follows the config's pause flag, verifier programs,
+/// proof age and fee cap, and every transfer must pass the config account. The
+/// link is permanent so an admin cannot opt out of a pause.
+///
+/// Accounts:
+///   0. [signer] Admin
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///   2. [] Config account (PDA: ["config"]; created by SET_CONFIG)
+pub(crate) fn process_link_config(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let config = next_account_info(account_iter)?;
+
+    check_admin_state(program_id, admin, state_account)?;
+
+    let config_data = config.try_borrow_data()?;
+    if config.owner != program_id
+        || config_data.len() != CONFIG_SIZE
+        || config_data[0..8] != CONFIG_DISCRIMINATOR
+    {
+        msg!("Invalid program config account");
+        return Err(ExclusionError::InvalidConfigAccount.into());
+    }
+    let config_pda = Pubkey::create_program_address(
+        &[seeds::CONFIG, &[config_data[config_offsets::BUMP]]],
+        program_id,
+    );
+    if config_pda.as_ref() != Ok(config.key) {
+        msg!("Invalid program config account");
+        return Err(ExclusionError::InvalidConfigAccount.into());
+    }
+
+    state_account.try_borrow_mut_data()?[CONFIG..CONFIG + 32].copy_from_slice(config.key.as_ref());
+
+    msg!("State account linked to program config {}", config.key);
+    Ok(())
+}
+
+/// Operational settings a state account follows: the program config once the
+/// state is linked to it, otherwise the compiled-in defaults
+pub(super) struct ProgramConfig {
+    pub(super) paused: bool,
+    /// Built for `smt::TREE_DEPTH`; sparse trees of other depths use a verifier
+    /// registered for their depth
+    pub(super) zk_verifier: Pubkey,
+    /// Built for `smt::TREE_DEPTH`, the only depth SUBMIT_ROOT_TRANSITION takes
+    pub(super) transition_verifier: Pubkey,
+    pub(super) max_proof_age_slots: u64,
+    pub(super) max_fee_bps: u16,
+}
+
+impl ProgramConfig {
+    const DEFAULT: Self = Self {
+        paused: false,
+        zk_verifier: ZK_VERIFIER_PROGRAM_ID,
+        transition_verifier: TRANSITION_VERIFIER_PROGRAM_ID,
+        max_proof_age_slots: MAX_PROOF_AGE_SLOTS,
+        max_fee_bps: MAX_FEE_BPS,
+    };
+
+    /// Settings for a state account; a linked config account may be passed
+    /// anywhere in `accounts`
+    pub(super) fn for_state(
+        program_id: &Pubkey,
+        state_data: &[u8],
+        accounts: &[AccountInfo],
+    ) -> Result<Self, ProgramError> {
+        let config_key = &state_data[CONFIG..CONFIG + 32];
+        if config_key == [0u8; 32] {
+            return Ok(Self::DEFAULT);
+        }
+
+        // LINK_CONFIG checked the key is the config PDA
+        let config = accounts
+            .iter()
+            .find(|account| account.key.as_ref() == config_key)
+            .ok_or_else(|| {
+                msg!("Program config required for this state account");
+                ProgramError::NotEnoughAccountKeys
+            })?;
+        let data = config.try_borrow_data()?;
+        if config.owner != program_id
+            || data.len() != CONFIG_SIZE
+            || data[0..8] != CONFIG_DISCRIMINATOR
+        {
+            msg!("Invalid program config account");
+            return Err(ExclusionError::InvalidConfigAccount.into());
+        }
+
+        let pubkey_at =
+            |offset: usize| Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap());
+        Ok(Self {
+            paused: data[config_offsets::PAUSED] != 0,
+            zk_verifier: pubkey_at(config_offsets::ZK_VERIFIER),
+            transition_verifier: pubkey_at(config_offsets::TRANSITION_VERIFIER),
+            max_proof_age_slots: u64::from_le_bytes(
+                data[config_offsets::MAX_PROOF_AGE_SLOTS..config_offsets::MAX_PROOF_AGE_SLOTS + 8]
+                    .try_into()
+                    .unwrap(),
+            ),
+            max_fee_bps: u16::from_le_bytes(
+                data[config_offsets::MAX_FEE_BPS..config_offsets::MAX_FEE_BPS + 2]
+                    .try_into()
+                    .unwrap(),
+            ),
+        })
+    }
+}
+
+/// Verify `authority` signed and is the upgrade authority recorded in this
+/// program's ProgramData account
+pub(super) fn check_upgrade_authority(
+    program_id: &Pubkey,
+    authority: &AccountInfo,
+    program_data: &AccountInfo,
+) -> ProgramResult {
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (program_data_pda, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::ID);
+    if program_data.key != &program_data_pda || program_data.owner != &bpf_loader_upgradeable::ID {
+        msg!("Program data account does not match this program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // UpgradeableLoaderState::ProgramData: 4 (tag = 3) + 8 (slot)
+    // + 1 (Option tag) + 32 (upgrade authority)
+    let data = program_data.try_borrow_data()?;
+    let upgrade_authority = match data.get(0..45) {
+        Some(header) if header[0..4] == 3u32.to_le_bytes() && header[12] == 1 => {
+            Some(&header[13..45])
+        }
+        _ => None,
+    };
+    if upgrade_authority != Some(authority.key.as_ref()) {
+        msg!("Signer is not the program's upgrade authority");
+        return Err(ExclusionError::UnauthorizedUpgradeAuthority.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,290 @@
+//! Escrowed transfers, held until a release time
+
+use exclusion_common::seeds;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+use solana_system_interface::instruction as system_instruction;
+
+use super::{
+    close_account,
+    transfer::{charge_fee, record_rate_limited_transfer},
+    verify::verify_exclusion_proof,
+};
+use crate::{
+    escrow_flags, instruction, ExclusionError, ESCROW_ACCOUNT_DISCRIMINATOR, ESCROW_ACCOUNT_SIZE,
+};
+
+/// Lock lamports in an escrow PDA after checking the sender's exclusion proof.
+/// The recipient claims them with CLAIM_FROM_ESCROW once `release_delay` has passed.
+///
+/// Accounts:
+///   0. [signer, writable] Sender (must prove NOT blacklisted, funds the escrow)
+///   1. [] Recipient
+///   2. [writable] Escrow account (PDA: ["escrow", sender, recipient, escrow_id (u64 LE)])
+///   3. [] State account (contains SMT root)
+///   4. [] ZK Verifier program
+///   5. [] System program
+///
+/// Then the proof accounts. The escrowed amount counts against the sender's
+/// daily limit, and is charged any fee, when it's locked.
+///
+/// Data:
+///   - 8 bytes: amount (lamports)
+///   - 8 bytes: escrow_id (lets a sender hold several escrows for one recipient)
+///   - 8 bytes: release_delay (i64 seconds from now)
+///   - 1 byte: escrow_flags
+///   - 388 bytes: ZK proof
+///   - 108 bytes: public witness (must match smt_root from state + pubkey_hash from sender,
+///     recent_slot must be a recent slot in SlotHashes)
+pub(crate) fn process_transfer_to_escrow(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let sender = next_account_info(account_iter)?;
+    let recipient = next_account_info(account_iter)?;
+    let escrow_account = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let zk_verifier = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let instructions_sysvar = account_iter.next();
+    let rate_limit_account = account_iter.next();
+    let treasury = account_iter.next();
+
+    if !sender.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    let escrow_id = &data[8..16];
+    let release_delay = i64::from_le_bytes(data[16..24].try_into().unwrap());
+    let flags = data[24];
+    let proof_data = &data[25..25 + instruction::PROOF_SIZE];
+    let witness_data = &data[25 + instruction::PROOF_SIZE..];
+
+    if release_delay < 0 {
+        msg!("Escrow release delay must not be negative");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let (escrow_pda, bump) = Pubkey::find_program_address(
+        &[
+            seeds::ESCROW,
+            sender.key.as_ref(),
+            recipient.key.as_ref(),
+            escrow_id,
+        ],
+        program_id,
+    );
+    if escrow_account.key != &escrow_pda {
+        msg!("Invalid escrow account PDA");
+        return Err(ExclusionError::InvalidEscrowAccount.into());
+    }
+
+    verify_exclusion_proof(
+        program_id,
+        accounts,
+        sender,
+        state_account,
+        zk_verifier,
+        instructions_sysvar,
+        proof_data,
+        witness_data,
+    )?;
+    record_rate_limited_transfer(
+        program_id,
+        sender,
+        sender,
+        state_account,
+        system_program,
+        rate_limit_account,
+        amount,
+    )?;
+    charge_fee(
+        program_id,
+        accounts,
+        sender,
+        state_account,
+        system_program,
+        treasury,
+        amount,
+    )?;
+
+    let release_at = Clock::get()?
+        .unix_timestamp
+        .checked_add(release_delay)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    // Fund the escrow with rent plus the held amount in one account creation
+    let lamports = Rent::get()?
+        .minimum_balance(ESCROW_ACCOUNT_SIZE)
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let signer_seeds: &[&[u8]] = &[
+        seeds::ESCROW,
+        sender.key.as_ref(),
+        recipient.key.as_ref(),
+        escrow_id,
+        &[bump],
+    ];
+
+    invoke_signed(
+        &system_instruction::create_account(
+            sender.key,
+            escrow_account.key,
+            lamports,
+            ESCROW_ACCOUNT_SIZE as u64,
+            program_id,
+        ),
+        &[
+            sender.clone(),
+            escrow_account.clone(),
+            system_program.clone(),
+        ],
+        &[signer_seeds],
+    )?;
+
+    let mut escrow_data = escrow_account.try_borrow_mut_data()?;
+    escrow_data[0..8].copy_from_slice(&ESCROW_ACCOUNT_DISCRIMINATOR);
+    escrow_data[8..40].copy_from_slice(sender.key.as_ref());
+    escrow_data[40..72].copy_from_slice(recipient.key.as_ref());
+    escrow_data[72..104].copy_from_slice(state_account.key.as_ref());
+    escrow_data[104..112].copy_from_slice(escrow_id);
+    escrow_data[112..120].copy_from_slice(&amount.to_le_bytes());
+    escrow_data[120..128].copy_from_slice(&release_at.to_le_bytes());
+    escrow_data[128] = flags;
+
+    msg!(
+        "Escrowed {} lamports for {} until {}",
+        amount,
+        recipient.key,
+        release_at
+    );
+    Ok(())
+}
+
+/// Release an escrow to its recipient once its release time has passed,
+/// refunding the escrow's rent to the sender. If the sender set
+/// REQUIRE_RECIPIENT_PROOF, the recipient must prove they are not blacklisted
+/// against the same state account the sender's proof was checked against; a
+/// proof supplied without the flag is checked the same way.
+///
+/// Accounts:
+///   0. [signer, writable] Recipient
+///   1. [writable] Sender (receives the escrow's rent)
+///   2. [writable] Escrow account (PDA: ["escrow", sender, recipient, escrow_id (u64 LE)])
+///   3. [] State account (the one recorded in the escrow)
+///   4. [] ZK Verifier program
+///   5. [] System program
+///   6. [] Instructions sysvar (required when a caller restriction is set)
+///
+/// Data (none in version 1):
+///   - 388 bytes: recipient's ZK proof (version 2)
+///   - 108 bytes: public witness (version 2; must match smt_root from state + pubkey_hash
+///     from recipient, recent_slot must be a recent slot in SlotHashes)
+pub(crate) fn process_claim_from_escrow(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let recipient = next_account_info(account_iter)?;
+    let sender = next_account_info(account_iter)?;
+    let escrow_account = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let zk_verifier = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let instructions_sysvar = account_iter.next();
+
+    if !recipient.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (amount, release_at, flags) = {
+        let escrow_data = check_escrow_account(program_id, sender, recipient, escrow_account)?;
+        if &escrow_data[72..104] != state_account.key.as_ref() {
+            msg!("State account does not match the escrow's");
+            return Err(ExclusionError::InvalidStateAccount.into());
+        }
+        (
+            u64::from_le_bytes(escrow_data[112..120].try_into().unwrap()),
+            i64::from_le_bytes(escrow_data[120..128].try_into().unwrap()),
+            escrow_data[128],
+        )
+    };
+
+    if Clock::get()?.unix_timestamp < release_at {
+        msg!("Escrow is locked until {}", release_at);
+        return Err(ExclusionError::EscrowLocked.into());
+    }
+
+    if data.is_empty() {
+        if flags & escrow_flags::REQUIRE_RECIPIENT_PROOF != 0 {
+            msg!("Escrow requires the recipient's exclusion proof");
+            return Err(ExclusionError::RecipientProofRequired.into());
+        }
+    } else {
+        let (proof_data, witness_data) = data.split_at(instruction::PROOF_SIZE);
+        verify_exclusion_proof(
+            program_id,
+            accounts,
+            recipient,
+            state_account,
+            zk_verifier,
+            instructions_sysvar,
+            proof_data,
+            witness_data,
+        )?;
+    }
+
+    // Pay out the held amount, then close the escrow so its rent returns to the sender
+    let escrow_lamports = escrow_account
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(ProgramError::InsufficientFunds)?;
+    **escrow_account.try_borrow_mut_lamports()? = escrow_lamports;
+    **recipient.try_borrow_mut_lamports()? = recipient
+        .lamports()
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    close_account(escrow_account, sender, system_program)?;
+
+    msg!("Claimed {} lamports from escrow", amount);
+    Ok(())
+}
+
+/// Verify that an escrow account is program-owned, initialized, and between
+/// `sender` and `recipient`, returning its data. The parties are part of the
+/// PDA seeds, so matching them against a program-written account is enough.
+fn check_escrow_account<'a>(
+    program_id: &Pubkey,
+    sender: &AccountInfo,
+    recipient: &AccountInfo,
+    escrow_account: &'a AccountInfo,
+) -> Result<std::cell::Ref<'a, &'a mut [u8]>, ProgramError> {
+    if escrow_account.owner != program_id {
+        msg!("Escrow account is not owned by this program");
+        return Err(ExclusionError::InvalidEscrowAccount.into());
+    }
+
+    let escrow_data = escrow_account.try_borrow_data()?;
+    if escrow_data.len() != ESCROW_ACCOUNT_SIZE
+        || escrow_data[0..8] != ESCROW_ACCOUNT_DISCRIMINATOR
+        || &escrow_data[8..40] != sender.key.as_ref()
+        || &escrow_data[40..72] != recipient.key.as_ref()
+    {
+        msg!("Invalid escrow account");
+        return Err(ExclusionError::InvalidEscrowAccount.into());
+    }
+    Ok(escrow_data)
+}
@@ -0,0 +1,158 @@
+//! Instruction handlers, one module per kind of account they manage
+//!
+//! Conventions the handlers' account lists share:
+//!
+//! - Optional accounts are positional: pass this program's ID for any that are
+//!   unused but followed by one that is needed.
+//! - TRANSFER_SOL and the transfers built like it take the *proof accounts*
+//!   after their fixed ones, in this order:
+//!   - [] Instructions sysvar (required when a caller restriction is set or a
+//!     later account is)
+//!   - [writable] Rate limit account (PDA: ["rate_limit", state, sender];
+//!     required when a daily limit is set, created on first use)
+//!   - [writable] Treasury (PDA: ["treasury", state]; required when a fee is set)
+//! - The noop program (with the `noop-events` feature), the program config
+//!   (PDA: ["config"]; required when the state is linked to it) and the verifier
+//!   registry (PDA: ["verifier_registry"]; required when the verifier is a
+//!   registered one) may follow the fixed accounts in any position.
+
+pub(crate) mod admin;
+pub(crate) mod attest;
+pub(crate) mod bond;
+pub(crate) mod buffer;
+pub(crate) mod config;
+pub(crate) mod escrow;
+pub(crate) mod registry;
+pub(crate) mod relay;
+pub(crate) mod transfer;
+pub(crate) mod tree;
+pub(crate) mod verify;
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program::set_return_data,
+    program_error::ProgramError, pubkey::Pubkey,
+};
+
+use crate::{
+    events::RootUpdated, instruction::VerificationResult, state_offsets::*, state_version,
+    tree_kind, ExclusionError, STATE_DISCRIMINATOR, STATE_SIZE, STATE_VERSION,
+};
+
+/// Close a program-owned account, refunding all of its lamports to the owner
+fn close_account(
+    account: &AccountInfo,
+    owner: &AccountInfo,
+    system_program: &AccountInfo,
+) -> ProgramResult {
+    let account_lamports = account.lamports();
+    **account.try_borrow_mut_lamports()? = 0;
+    **owner.try_borrow_mut_lamports()? = owner
+        .lamports()
+        .checked_add(account_lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    account.resize(0)?;
+    account.assign(system_program.key);
+    Ok(())
+}
+
+/// Verify the admin signed and is the admin stored in the initialized state
+///
+/// The stored admin, not the state's PDA seed, is authoritative: after
+/// SET_ADMIN the state keeps the address derived from its first admin.
+fn check_admin_state(
+    program_id: &Pubkey,
+    admin: &AccountInfo,
+    state_account: &AccountInfo,
+) -> ProgramResult {
+    if !admin.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if state_account.owner != program_id {
+        msg!("State account not owned by this program");
+        return Err(ExclusionError::InvalidStateAccount.into());
+    }
+    let state_data = state_account.try_borrow_data()?;
+    check_state_layout(&state_data)?;
+
+    if state_data[ADMIN..ADMIN + 32] != admin.key.as_ref()[..] {
+        msg!("Signer is not the state's admin");
+        return Err(ExclusionError::UnauthorizedAdmin.into());
+    }
+    Ok(())
+}
+
+/// Verify state data's discriminator and that it uses the current layout
+fn check_state_layout(state_data: &[u8]) -> ProgramResult {
+    if state_data.len() < 8 || state_data[0..8] != STATE_DISCRIMINATOR {
+        msg!("Invalid state account");
+        return Err(ExclusionError::InvalidStateAccount.into());
+    }
+    let version = state_version(state_data);
+    if version < STATE_VERSION {
+        msg!(
+            "State account uses layout v{}; run MIGRATE_STATE to upgrade to v{}",
+            version,
+            STATE_VERSION
+        );
+        return Err(ExclusionError::StateMigrationRequired.into());
+    }
+    if version > STATE_VERSION || state_data.len() < STATE_SIZE {
+        msg!("Unknown state layout v{}", version);
+        return Err(ExclusionError::InvalidStateAccount.into());
+    }
+    Ok(())
+}
+
+/// Reject states whose root is not a sparse Merkle tree's, for instructions
+/// that check sparse-tree paths or transitions
+fn check_sparse_tree(state_data: &[u8]) -> ProgramResult {
+    if state_data[TREE_KIND] != tree_kind::SPARSE {
+        msg!("Instruction needs a sparse Merkle tree state");
+        return Err(ExclusionError::UnsupportedTreeKind.into());
+    }
+    Ok(())
+}
+
+/// Levels in the state's sparse Merkle tree
+fn tree_depth(state_data: &[u8]) -> usize {
+    u16::from_le_bytes([state_data[TREE_DEPTH], state_data[TREE_DEPTH + 1]]) as usize
+}
+
+/// Store a new SMT root, bumping root_seq
+fn set_root(state_data: &mut [u8], new_root: &[u8]) -> RootUpdated {
+    let old = state_data[SMT_ROOT..SMT_ROOT + 32].try_into().unwrap();
+    state_data[SMT_ROOT..SMT_ROOT + 32].copy_from_slice(new_root);
+
+    let seq_bytes = &mut state_data[ROOT_SEQ..ROOT_SEQ + 8];
+    let seq = u64::from_le_bytes((*seq_bytes).try_into().unwrap()).wrapping_add(1);
+    seq_bytes.copy_from_slice(&seq.to_le_bytes());
+
+    RootUpdated {
+        old,
+        new: new_root.try_into().unwrap(),
+        seq,
+    }
+}
+
+/// Set the transfer's `VerificationResult` as return data. Must follow the last
+/// CPI, since invoking another program clears return data.
+fn set_verification_result(
+    state_account: &AccountInfo,
+    pubkey_hash: [u8; 32],
+    amount: u64,
+) -> ProgramResult {
+    // The proof's root was checked against the stored root
+    let smt_root = state_account.try_borrow_data()?[SMT_ROOT..SMT_ROOT + 32]
+        .try_into()
+        .unwrap();
+    set_return_data(
+        &VerificationResult {
+            smt_root,
+            pubkey_hash,
+            amount,
+        }
+        .pack(),
+    );
+    Ok(())
+}
@@ -0,0 +1,267 @@
+//! The verifier registry: verifier programs governance approved, by circuit hash
+
+use exclusion_common::seeds;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+use solana_system_interface::instruction as system_instruction;
+
+use super::config::check_upgrade_authority;
+use crate::{
+    smt, tree_kind, verifier_registry_offsets, ExclusionError, MAX_REGISTERED_VERIFIERS,
+    VERIFIER_REGISTRY_DISCRIMINATOR, VERIFIER_REGISTRY_ENTRY_SIZE, VERIFIER_REGISTRY_SIZE,
+};
+
+/// Create the verifier registry or hand it to a new governance authority
+///
+/// The registry lists exclusion verifier programs that every state accepts
+/// alongside its configured one, so a verifier can be upgraded (for example to
+/// a new sunspot build) without touching each state. The upgrade authority
+/// appoints the governance authority that manages the list, and can replace it.
+///
+/// Accounts:
+///   0. [signer, writable] Upgrade authority (pays for the registry on first use)
+///   1. [writable] Verifier registry (PDA: ["verifier_registry"])
+///   2. [] Program data account (PDA: [program_id] under the upgradeable loader)
+///   3. [] System program
+///
+/// Data: 32 bytes (governance authority)
+pub(crate) fn process_set_registry_authority(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let upgrade_authority = next_account_info(account_iter)?;
+    let registry = next_account_info(account_iter)?;
+    let program_data = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    check_upgrade_authority(program_id, upgrade_authority, program_data)?;
+
+    let (registry_pda, bump) =
+        Pubkey::find_program_address(&[seeds::VERIFIER_REGISTRY], program_id);
+    if registry.key != &registry_pda {
+        msg!("Verifier registry does not match the registry PDA");
+        return Err(ExclusionError::InvalidVerifierRegistry.into());
+    }
+
+    if registry.owner != program_id {
+        let lamports = Rent::get()?.minimum_balance(VERIFIER_REGISTRY_SIZE);
+        invoke_signed(
+            &system_instruction::create_account(
+                upgrade_authority.key,
+                registry.key,
+                lamports,
+                VERIFIER_REGISTRY_SIZE as u64,
+                program_id,
+            ),
+            &[
+                upgrade_authority.clone(),
+                registry.clone(),
+                system_program.clone(),
+            ],
+            &[&[seeds::VERIFIER_REGISTRY, &[bump]]],
+        )?;
+        let mut registry_data = registry.try_borrow_mut_data()?;
+        registry_data[0..8].copy_from_slice(&VERIFIER_REGISTRY_DISCRIMINATOR);
+        registry_data[verifier_registry_offsets::BUMP] = bump;
+    }
+
+    registry.try_borrow_mut_data()?
+        [verifier_registry_offsets::AUTHORITY..verifier_registry_offsets::AUTHORITY + 32]
+        .copy_from_slice(data);
+
+    msg!(
+        "Verifier registry authority set to {}",
+        Pubkey::new_from_array(data.try_into().unwrap())
+    );
+    Ok(())
+}
+
+/// List an exclusion verifier program in the registry, or update the circuit
+/// of one already listed
+///
+/// Only states whose tree kind and depth match the entry's accept the verifier.
+///
+/// Accounts:
+///   0. [signer] Governance authority
+///   1. [writable] Verifier registry (PDA: ["verifier_registry"])
+///
+/// Data:
+///   - 32 bytes: verifier program ID
+///   - 32 bytes: circuit hash (identifies the circuit the verifier checks, e.g. a
+///     hash of its verifying key)
+///   - 1 byte: tree_kind the circuit proves exclusion from
+///   - 2 bytes: tree_depth the circuit was built for (u16 LE; one of
+///     `smt::SUPPORTED_DEPTHS`)
+pub(crate) fn process_register_verifier(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let authority = next_account_info(account_iter)?;
+    let registry = next_account_info(account_iter)?;
+
+    check_registry_authority(program_id, authority, registry)?;
+
+    let verifier = &data[..32];
+    if !matches!(data[64], tree_kind::SPARSE | tree_kind::INDEXED) {
+        msg!("Unknown tree kind {}", data[64]);
+        return Err(ExclusionError::UnsupportedTreeKind.into());
+    }
+    let depth = u16::from_le_bytes([data[65], data[66]]);
+    if !smt::SUPPORTED_DEPTHS.contains(&(depth as usize)) {
+        msg!("Unsupported tree depth {}", depth);
+        return Err(ExclusionError::UnsupportedTreeDepth.into());
+    }
+
+    let mut registry_data = registry.try_borrow_mut_data()?;
+    let count = registry_data[verifier_registry_offsets::COUNT] as usize;
+    let listed = registered_verifiers(&registry_data).position(|entry| &entry[..32] == verifier);
+    let index = match listed {
+        Some(index) => index,
+        None if count < MAX_REGISTERED_VERIFIERS => {
+            registry_data[verifier_registry_offsets::COUNT] = count as u8 + 1;
+            count
+        }
+        None => {
+            msg!("Verifier registry is full");
+            return Err(ExclusionError::VerifierRegistryFull.into());
+        }
+    };
+    let entry = verifier_registry_offsets::VERIFIERS + VERIFIER_REGISTRY_ENTRY_SIZE * index;
+    registry_data[entry..entry + VERIFIER_REGISTRY_ENTRY_SIZE].copy_from_slice(data);
+
+    msg!(
+        "Verifier {} registered",
+        Pubkey::new_from_array(verifier.try_into().unwrap())
+    );
+    Ok(())
+}
+
+/// Remove an exclusion verifier program from the registry
+///
+/// Accounts:
+///   0. [signer] Governance authority
+///   1. [writable] Verifier registry (PDA: ["verifier_registry"])
+///
+/// Data: 32 bytes (verifier program ID)
+pub(crate) fn process_unregister_verifier(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let authority = next_account_info(account_iter)?;
+    let registry = next_account_info(account_iter)?;
+
+    check_registry_authority(program_id, authority, registry)?;
+
+    let verifier = Pubkey::new_from_array(data.try_into().unwrap());
+    let mut registry_data = registry.try_borrow_mut_data()?;
+    let Some(index) =
+        registered_verifiers(&registry_data).position(|entry| entry[..32] == verifier.as_ref()[..])
+    else {
+        msg!("Verifier {} is not registered", verifier);
+        return Err(ExclusionError::InvalidZkVerifier.into());
+    };
+
+    // Move the last entry into the gap; registry order is not significant
+    let last = registry_data[verifier_registry_offsets::COUNT] as usize - 1;
+    let entry = |i: usize| verifier_registry_offsets::VERIFIERS + VERIFIER_REGISTRY_ENTRY_SIZE * i;
+    registry_data.copy_within(entry(last)..entry(last + 1), entry(index));
+    registry_data[entry(last)..entry(last + 1)].fill(0);
+    registry_data[verifier_registry_offsets::COUNT] = last as u8;
+
+    msg!("Verifier {} unregistered", verifier);
+    Ok(())
+}
+
+/// Verify the governance authority signed for an initialized verifier registry
+fn check_registry_authority(
+    program_id: &Pubkey,
+    authority: &AccountInfo,
+    registry: &AccountInfo,
+) -> ProgramResult {
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Only SET_REGISTRY_AUTHORITY writes the discriminator, at the registry PDA
+    let registry_data = registry.try_borrow_data()?;
+    if registry.owner != program_id
+        || registry_data.len() != VERIFIER_REGISTRY_SIZE
+        || registry_data[0..8] != VERIFIER_REGISTRY_DISCRIMINATOR
+        || registry_data
+            [verifier_registry_offsets::AUTHORITY..verifier_registry_offsets::AUTHORITY + 32]
+            != authority.key.as_ref()[..]
+    {
+        msg!("Invalid verifier registry or authority");
+        return Err(ExclusionError::InvalidVerifierRegistry.into());
+    }
+    Ok(())
+}
+
+/// Registry entries: 32 (program ID) + 32 (circuit hash) + 1 (tree_kind)
+/// + 2 (tree_depth) each
+fn registered_verifiers(registry_data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let count =
+        (registry_data[verifier_registry_offsets::COUNT] as usize).min(MAX_REGISTERED_VERIFIERS);
+    registry_data[verifier_registry_offsets::VERIFIERS..][..VERIFIER_REGISTRY_ENTRY_SIZE * count]
+        .chunks_exact(VERIFIER_REGISTRY_ENTRY_SIZE)
+}
+
+/// A verifier the registry lists, and the tree it checks proofs for
+pub(super) struct RegisteredVerifier {
+    pub(super) circuit_hash: [u8; 32],
+    pub(super) tree_kind: u8,
+    pub(super) tree_depth: u16,
+}
+
+/// Registry entry of `verifier` if the verifier registry lists it; the
+/// registry may be passed anywhere in `accounts`
+pub(super) fn registered_verifier(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    verifier: &Pubkey,
+) -> Result<Option<RegisteredVerifier>, ProgramError> {
+    let Some(registry) = accounts.iter().find(|account| {
+        account.owner == program_id
+            && account.try_borrow_data().is_ok_and(|data| {
+                data.len() == VERIFIER_REGISTRY_SIZE
+                    && data[0..8] == VERIFIER_REGISTRY_DISCRIMINATOR
+            })
+    }) else {
+        return Ok(None);
+    };
+    let registry_data = registry.try_borrow_data()?;
+    let registry_pda = Pubkey::create_program_address(
+        &[
+            seeds::VERIFIER_REGISTRY,
+            &[registry_data[verifier_registry_offsets::BUMP]],
+        ],
+        program_id,
+    );
+    if registry_pda.as_ref() != Ok(registry.key) {
+        msg!("Verifier registry does not match the registry PDA");
+        return Err(ExclusionError::InvalidVerifierRegistry.into());
+    }
+
+    let registered = registered_verifiers(&registry_data)
+        .find(|entry| entry[..32] == verifier.as_ref()[..])
+        .map(|entry| RegisteredVerifier {
+            circuit_hash: entry[32..64].try_into().unwrap(),
+            tree_kind: entry[64],
+            tree_depth: u16::from_le_bytes([entry[65], entry[66]]),
+        });
+    Ok(registered)
+}
@@ -0,0 +1,334 @@
+//! Relay accounts: transfers a relayer submits on the sender's signed authorization
+
+use exclusion_common::seeds;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    ed25519_program,
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::{
+        instructions::{load_current_index_checked, load_instruction_at_checked},
+        Sysvar,
+    },
+};
+use solana_system_interface::instruction as system_instruction;
+
+use super::{
+    close_account, set_verification_result,
+    transfer::{daily_limit, fee_due, record_rate_limited_transfer},
+    verify::verify_exclusion_proof,
+};
+use crate::{
+    events::{Event, TransferVerified},
+    instruction, ExclusionError, RELAY_ACCOUNT_DISCRIMINATOR, RELAY_ACCOUNT_SIZE,
+};
+
+/// Create a relay account that funds the sender's relayed (gasless) transfers.
+/// The sender tops it up with plain system transfers to the PDA.
+///
+/// Accounts:
+///   0. [signer, writable] Sender (payer and owner of the relay account)
+///   1. [writable] Relay account (PDA: ["relay", sender_pubkey])
+///   2. [] System program
+pub(crate) fn process_create_relay_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let sender = next_account_info(account_iter)?;
+    let relay_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !sender.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (relay_pda, bump) =
+        Pubkey::find_program_address(&[seeds::RELAY, sender.key.as_ref()], program_id);
+    if relay_account.key != &relay_pda {
+        msg!("Invalid relay account PDA");
+        return Err(ExclusionError::InvalidRelayAccount.into());
+    }
+
+    let rent = Rent::get()?;
+    let lamports = rent.minimum_balance(RELAY_ACCOUNT_SIZE);
+    let signer_seeds: &[&[u8]] = &[seeds::RELAY, sender.key.as_ref(), &[bump]];
+
+    invoke_signed(
+        &system_instruction::create_account(
+            sender.key,
+            relay_account.key,
+            lamports,
+            RELAY_ACCOUNT_SIZE as u64,
+            program_id,
+        ),
+        &[
+            sender.clone(),
+            relay_account.clone(),
+            system_program.clone(),
+        ],
+        &[signer_seeds],
+    )?;
+
+    let mut data = relay_account.try_borrow_mut_data()?;
+    data[0..8].copy_from_slice(&RELAY_ACCOUNT_DISCRIMINATOR);
+    data[8..40].copy_from_slice(sender.key.as_ref());
+
+    msg!("Relay account created for {}", sender.key);
+    Ok(())
+}
+
+/// Transfer SOL on the sender's behalf, submitted and paid for by a relayer.
+///
+/// The sender never signs the transaction. Instead it signs
+/// `instruction::relay_message(program_id, recipient, amount, nonce)` off-chain,
+/// and the relayer places an ed25519 program instruction verifying that
+/// signature immediately before this one. The exclusion proof still binds to the
+/// sender's pubkey hash, and the lamports come from the sender's relay account.
+///
+/// Accounts:
+///   0. [] Sender (must prove NOT blacklisted, signed the relay message)
+///   1. [writable] Recipient
+///   2. [] State account (contains SMT root)
+///   3. [] ZK Verifier program
+///   4. [writable] Relay account (PDA: ["relay", sender_pubkey])
+///   5. [] Instructions sysvar
+///   6. [writable] Rate limit account (PDA: ["rate_limit", state, sender]; required
+///      when a daily limit is set, created on first use)
+///   7. [writable] Treasury (PDA: ["treasury", state]; required when a fee is set,
+///      paid from the relay account)
+///   8. [signer, writable] Relayer (required when a daily limit is set; pays for the
+///      rate limit account)
+///   9. [] System program (required when a daily limit is set)
+///
+/// Data:
+///   - 8 bytes: amount (lamports)
+///   - 8 bytes: nonce (must equal the relay account's nonce)
+///   - 388 bytes: ZK proof
+///   - 108 bytes: public witness (must match smt_root from state + pubkey_hash from sender,
+///     recent_slot must be a recent slot in SlotHashes)
+pub(crate) fn process_transfer_sol_relayed(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let sender = next_account_info(account_iter)?;
+    let recipient = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let zk_verifier = next_account_info(account_iter)?;
+    let relay_account = next_account_info(account_iter)?;
+    let instructions_sysvar = next_account_info(account_iter)?;
+    let rate_limit_account = account_iter.next();
+    let treasury = account_iter.next();
+
+    let amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    let nonce = u64::from_le_bytes(data[8..16].try_into().unwrap());
+    let proof_data = &data[16..16 + instruction::PROOF_SIZE];
+    let witness_data = &data[16 + instruction::PROOF_SIZE..];
+
+    check_relay_account(program_id, sender, relay_account)?;
+    let stored_nonce = {
+        let relay_data = relay_account.try_borrow_data()?;
+        u64::from_le_bytes(relay_data[40..48].try_into().unwrap())
+    };
+    if nonce != stored_nonce {
+        msg!(
+            "Relay nonce {} does not match expected {}",
+            nonce,
+            stored_nonce
+        );
+        return Err(ExclusionError::RelayNonceMismatch.into());
+    }
+
+    let message = instruction::relay_message(program_id, recipient.key, amount, nonce);
+    check_sender_authorization(instructions_sysvar, sender.key, &message)?;
+
+    let sender_hash = verify_exclusion_proof(
+        program_id,
+        accounts,
+        sender,
+        state_account,
+        zk_verifier,
+        Some(instructions_sysvar),
+        proof_data,
+        witness_data,
+    )?;
+    // The sender never signs, so the relayer pays for a new rate limit account
+    if daily_limit(&state_account.try_borrow_data()?) > 0 {
+        let relayer = next_account_info(account_iter)?;
+        let system_program = next_account_info(account_iter)?;
+        record_rate_limited_transfer(
+            program_id,
+            sender,
+            relayer,
+            state_account,
+            system_program,
+            rate_limit_account,
+            amount,
+        )?;
+    }
+
+    let fee = fee_due(program_id, accounts, state_account, treasury, amount)?;
+    let charged = amount
+        .checked_add(fee.map_or(0, |(_, fee)| fee))
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    // The relay account is program-owned, so move lamports directly, keeping it rent-exempt
+    let rent_minimum = Rent::get()?.minimum_balance(RELAY_ACCOUNT_SIZE);
+    let remaining = relay_account
+        .lamports()
+        .checked_sub(charged)
+        .filter(|remaining| *remaining >= rent_minimum)
+        .ok_or_else(|| {
+            msg!("Relay account balance too low for {} lamports", charged);
+            ProgramError::InsufficientFunds
+        })?;
+    **relay_account.try_borrow_mut_lamports()? = remaining;
+    **recipient.try_borrow_mut_lamports()? = recipient
+        .lamports()
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    if let Some((treasury, fee)) = fee {
+        **treasury.try_borrow_mut_lamports()? = treasury
+            .lamports()
+            .checked_add(fee)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+    }
+
+    let mut relay_data = relay_account.try_borrow_mut_data()?;
+    let next_nonce = nonce
+        .checked_add(1)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    relay_data[40..48].copy_from_slice(&next_nonce.to_le_bytes());
+
+    TransferVerified {
+        sender_hash,
+        amount,
+        recipient: *recipient.key,
+    }
+    .emit(accounts)?;
+    set_verification_result(state_account, sender_hash, amount)?;
+    msg!(
+        "Relayed transfer of {} lamports to {} (nonce {})",
+        amount,
+        recipient.key,
+        nonce
+    );
+    Ok(())
+}
+
+/// Close the sender's relay account, returning its remaining balance
+///
+/// Accounts:
+///   0. [signer, writable] Sender (owner of the relay account)
+///   1. [writable] Relay account (PDA: ["relay", sender_pubkey])
+///   2. [] System program
+pub(crate) fn process_close_relay_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let sender = next_account_info(account_iter)?;
+    let relay_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !sender.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    check_relay_account(program_id, sender, relay_account)?;
+
+    close_account(relay_account, sender, system_program)?;
+
+    msg!("Relay account closed for {}", sender.key);
+    Ok(())
+}
+
+/// Verify that a relay account is the owner's PDA and has been initialized
+fn check_relay_account(
+    program_id: &Pubkey,
+    owner: &AccountInfo,
+    relay_account: &AccountInfo,
+) -> ProgramResult {
+    let (relay_pda, _bump) =
+        Pubkey::find_program_address(&[seeds::RELAY, owner.key.as_ref()], program_id);
+    if relay_account.key != &relay_pda || relay_account.owner != program_id {
+        msg!("Relay account does not match owner's PDA");
+        return Err(ExclusionError::InvalidRelayAccount.into());
+    }
+
+    let relay_data = relay_account.try_borrow_data()?;
+    if relay_data.len() != RELAY_ACCOUNT_SIZE
+        || relay_data[0..8] != RELAY_ACCOUNT_DISCRIMINATOR
+        || &relay_data[8..40] != owner.key.as_ref()
+    {
+        msg!("Invalid relay account");
+        return Err(ExclusionError::InvalidRelayAccount.into());
+    }
+    Ok(())
+}
+
+/// Verify, via the Instructions sysvar, that the instruction immediately before
+/// this one is an ed25519 program instruction checking `signer`'s signature over
+/// `message`. The ed25519 program fails the whole transaction on a bad
+/// signature, so only its offsets need inspecting here.
+fn check_sender_authorization(
+    instructions_sysvar: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> ProgramResult {
+    // Ed25519 instruction layout: num_signatures (u8), padding (u8), then per
+    // signature seven u16 LE offsets: signature, signature ix index, public key,
+    // public key ix index, message, message size, message ix index
+    const OFFSETS_START: usize = 2;
+    const OFFSETS_LEN: usize = 14;
+    // Instruction index meaning "data is in this ed25519 instruction"
+    const THIS_INSTRUCTION: u16 = u16::MAX;
+
+    let invalid = |reason: &str| -> ProgramError {
+        msg!("Invalid sender authorization: {}", reason);
+        ExclusionError::InvalidSenderAuthorization.into()
+    };
+
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    let ed25519_index = current_index
+        .checked_sub(1)
+        .ok_or_else(|| invalid("no preceding ed25519 instruction"))?;
+    let ed25519_ix = load_instruction_at_checked(ed25519_index as usize, instructions_sysvar)?;
+    if ed25519_ix.program_id != ed25519_program::ID {
+        return Err(invalid("preceding instruction is not ed25519"));
+    }
+
+    let ix_data = &ed25519_ix.data;
+    if ix_data.len() < OFFSETS_START + OFFSETS_LEN || ix_data[0] != 1 {
+        return Err(invalid("expected exactly one signature"));
+    }
+    let offset = |i: usize| -> u16 {
+        let at = OFFSETS_START + 2 * i;
+        u16::from_le_bytes([ix_data[at], ix_data[at + 1]])
+    };
+    let (public_key_offset, message_offset, message_size) =
+        (offset(2) as usize, offset(4) as usize, offset(5) as usize);
+
+    // All referenced data must live in the ed25519 instruction itself
+    if [offset(1), offset(3), offset(6)]
+        .iter()
+        .any(|&index| index != THIS_INSTRUCTION)
+    {
+        return Err(invalid("signature data must be inline"));
+    }
+
+    let public_key = ix_data.get(public_key_offset..public_key_offset + 32);
+    let signed_message = ix_data.get(message_offset..message_offset + message_size);
+    if public_key != Some(signer.as_ref()) {
+        return Err(invalid("signature is not from the sender"));
+    }
+    if signed_message != Some(message) {
+        return Err(invalid("signed message does not match the transfer"));
+    }
+    Ok(())
+}
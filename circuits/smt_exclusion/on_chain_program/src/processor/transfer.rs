@@ -0,0 +1,1034 @@
+//! Proof-gated transfers, and the rate limits and fees they pay
+
+use exclusion_common::seeds;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+use solana_system_interface::instruction as system_instruction;
+
+use super::{
+    buffer::check_proof_buffer,
+    check_admin_state, close_account,
+    config::ProgramConfig,
+    set_verification_result,
+    verify::{decompress_proof, verify_exclusion_proof},
+};
+use crate::{
+    bubblegum,
+    events::{Event, TransferVerified},
+    instruction,
+    state_offsets::*,
+    token, ExclusionError, MAX_FEE_BPS, PROOF_BUFFER_HEADER_SIZE, RATE_LIMIT_ACCOUNT_DISCRIMINATOR,
+    RATE_LIMIT_ACCOUNT_SIZE, RATE_LIMIT_BUCKETS, TREASURY_ACCOUNT_DISCRIMINATOR,
+};
+
+/// Transfer SOL after verifying exclusion proof
+///
+/// Accounts:
+///   0. [signer, writable] Sender (must prove NOT blacklisted)
+///   1. [writable] Recipient
+///   2. [] State account (contains SMT root)
+///   3. [] ZK Verifier program
+///   4. [] System program
+///
+/// Then the proof accounts (see [`crate::processor`]).
+///
+/// Data:
+///   - 8 bytes: amount (lamports)
+///   - 388 bytes: ZK proof
+///   - 108 bytes: public witness (must match smt_root from state + pubkey_hash from signer,
+///     recent_slot must be a recent slot in SlotHashes)
+pub(crate) fn process_transfer_sol(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let sender = next_account_info(account_iter)?;
+    let recipient = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let zk_verifier = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let instructions_sysvar = account_iter.next();
+    let rate_limit_account = account_iter.next();
+    let treasury = account_iter.next();
+
+    if !sender.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Parse instruction data
+    let amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    let proof_data = &data[8..8 + instruction::PROOF_SIZE];
+    let witness_data = &data[8 + instruction::PROOF_SIZE..];
+
+    let sender_hash = verify_exclusion_proof(
+        program_id,
+        accounts,
+        sender,
+        state_account,
+        zk_verifier,
+        instructions_sysvar,
+        proof_data,
+        witness_data,
+    )?;
+    record_rate_limited_transfer(
+        program_id,
+        sender,
+        sender,
+        state_account,
+        system_program,
+        rate_limit_account,
+        amount,
+    )?;
+    charge_fee(
+        program_id,
+        accounts,
+        sender,
+        state_account,
+        system_program,
+        treasury,
+        amount,
+    )?;
+
+    // Transfer SOL
+    invoke(
+        &system_instruction::transfer(sender.key, recipient.key, amount),
+        &[sender.clone(), recipient.clone(), system_program.clone()],
+    )?;
+
+    TransferVerified {
+        sender_hash,
+        amount,
+        recipient: *recipient.key,
+    }
+    .emit(accounts)?;
+    set_verification_result(state_account, sender_hash, amount)?;
+    Ok(())
+}
+
+/// Transfer SOL with a point-compressed proof, expanded on-chain before verification
+///
+/// The compressed proof is 192 bytes smaller, leaving room for compute-budget
+/// instructions. Decompression costs extra compute, mostly for the G2 point.
+///
+/// Accounts: as for TRANSFER_SOL
+///
+/// Data:
+///   - 8 bytes: amount (lamports)
+///   - 196 bytes: ZK proof with compressed points (COMPRESSED_PROOF_SIZE)
+///   - 108 bytes: public witness (as for TRANSFER_SOL)
+pub(crate) fn process_transfer_sol_compressed(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let (amount, rest) = data.split_at(8);
+    let (compressed_proof, witness_data) = rest.split_at(instruction::COMPRESSED_PROOF_SIZE);
+
+    let mut transfer_data = [0u8; instruction::TRANSFER_SOL_LEN];
+    let (transfer_amount, rest) = transfer_data.split_at_mut(8);
+    let (proof_data, transfer_witness) = rest.split_at_mut(instruction::PROOF_SIZE);
+    transfer_amount.copy_from_slice(amount);
+    decompress_proof(compressed_proof, proof_data)?;
+    transfer_witness.copy_from_slice(witness_data);
+
+    process_transfer_sol(program_id, accounts, &transfer_data)
+}
+
+/// Transfer SOL out of a system account derived with `create_with_seed`, proving
+/// the base key is not blacklisted
+///
+/// Custodial setups often hold funds in seed-derived accounts of one base key.
+/// The base key signs and is the proven identity; it also pays any fee and rate
+/// limit account.
+///
+/// Accounts:
+///   0. [signer, writable] Base (must prove NOT blacklisted)
+///   1. [writable] Source account (`create_with_seed(base, seed, system_program)`)
+///   2. [writable] Recipient
+///   3. [] State account (contains SMT root)
+///   4. [] ZK Verifier program
+///   5. [] System program
+///
+/// Then the proof accounts, with the rate limit account keyed by the base.
+///
+/// Data:
+///   - as for TRANSFER_SOL
+///   - 2 bytes: seed length (u16 LE, at most 32)
+///   - seed (UTF-8)
+pub(crate) fn process_transfer_sol_with_seed(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let base = next_account_info(account_iter)?;
+    let source = next_account_info(account_iter)?;
+    let recipient = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let zk_verifier = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let instructions_sysvar = account_iter.next();
+    let rate_limit_account = account_iter.next();
+    let treasury = account_iter.next();
+
+    if !base.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Parse instruction data
+    let amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    let proof_data = &data[8..8 + instruction::PROOF_SIZE];
+    let witness_data = &data[8 + instruction::PROOF_SIZE..instruction::TRANSFER_SOL_LEN];
+    let seed = std::str::from_utf8(&data[instruction::TRANSFER_SOL_WITH_SEED_FIXED_LEN + 2..])
+        .map_err(|_| {
+            msg!("Seed is not valid UTF-8");
+            ProgramError::InvalidInstructionData
+        })?;
+
+    let source_address =
+        Pubkey::create_with_seed(base.key, seed, &solana_system_interface::program::ID)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+    if source.key != &source_address {
+        msg!("Source account is not derived from the base with this seed");
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let sender_hash = verify_exclusion_proof(
+        program_id,
+        accounts,
+        base,
+        state_account,
+        zk_verifier,
+        instructions_sysvar,
+        proof_data,
+        witness_data,
+    )?;
+    record_rate_limited_transfer(
+        program_id,
+        base,
+        base,
+        state_account,
+        system_program,
+        rate_limit_account,
+        amount,
+    )?;
+    charge_fee(
+        program_id,
+        accounts,
+        base,
+        state_account,
+        system_program,
+        treasury,
+        amount,
+    )?;
+
+    invoke(
+        &system_instruction::transfer_with_seed(
+            source.key,
+            base.key,
+            seed.to_string(),
+            &solana_system_interface::program::ID,
+            recipient.key,
+            amount,
+        ),
+        &[
+            source.clone(),
+            base.clone(),
+            recipient.clone(),
+            system_program.clone(),
+        ],
+    )?;
+
+    TransferVerified {
+        sender_hash,
+        amount,
+        recipient: *recipient.key,
+    }
+    .emit(accounts)?;
+    set_verification_result(state_account, sender_hash, amount)?;
+    Ok(())
+}
+
+/// Transfer SOL as wrapped SOL into the recipient's wSOL associated token account
+///
+/// After the proof checks, the sender's lamports fund a temporary wSOL account
+/// owned by the sender, which pays the recipient's ATA and is closed again, so
+/// its rent returns to the sender within the instruction. The ATA must exist;
+/// clients create it idempotently earlier in the same transaction.
+///
+/// Accounts:
+///   0. [signer, writable] Sender (must prove NOT blacklisted)
+///   1. [] Recipient
+///   2. [writable] Recipient's wSOL associated token account
+///   3. [] State account (contains SMT root)
+///   4. [] ZK Verifier program
+///   5. [] System program
+///   6. [writable] Temporary wSOL account (PDA: ["wsol", sender]; must not exist)
+///   7. [] Native mint
+///   8. [] SPL Token program
+///
+/// Then the proof accounts.
+///
+/// Data: as for TRANSFER_SOL
+pub(crate) fn process_transfer_wsol(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let sender = next_account_info(account_iter)?;
+    let recipient = next_account_info(account_iter)?;
+    let recipient_token_account = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let zk_verifier = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let wsol_account = next_account_info(account_iter)?;
+    let native_mint = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+    let instructions_sysvar = account_iter.next();
+    let rate_limit_account = account_iter.next();
+    let treasury = account_iter.next();
+
+    if !sender.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if token_program.key != &token::TOKEN_PROGRAM_ID || native_mint.key != &token::NATIVE_MINT {
+        msg!("Expected the SPL Token program and the native mint");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if recipient_token_account.key
+        != &token::associated_token_address(recipient.key, &token::NATIVE_MINT)
+    {
+        msg!("Recipient token account is not the recipient's wSOL ATA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let (wsol_pda, bump) =
+        Pubkey::find_program_address(&[seeds::WSOL, sender.key.as_ref()], program_id);
+    if wsol_account.key != &wsol_pda {
+        msg!("Temporary wSOL account does not match sender's PDA");
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Parse instruction data
+    let amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    let proof_data = &data[8..8 + instruction::PROOF_SIZE];
+    let witness_data = &data[8 + instruction::PROOF_SIZE..];
+
+    let sender_hash = verify_exclusion_proof(
+        program_id,
+        accounts,
+        sender,
+        state_account,
+        zk_verifier,
+        instructions_sysvar,
+        proof_data,
+        witness_data,
+    )?;
+    record_rate_limited_transfer(
+        program_id,
+        sender,
+        sender,
+        state_account,
+        system_program,
+        rate_limit_account,
+        amount,
+    )?;
+    charge_fee(
+        program_id,
+        accounts,
+        sender,
+        state_account,
+        system_program,
+        treasury,
+        amount,
+    )?;
+
+    // Wrap: lamports above rent become the temporary account's token balance
+    let lamports = Rent::get()?
+        .minimum_balance(token::TOKEN_ACCOUNT_SIZE)
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    invoke_signed(
+        &system_instruction::create_account(
+            sender.key,
+            wsol_account.key,
+            lamports,
+            token::TOKEN_ACCOUNT_SIZE as u64,
+            &token::TOKEN_PROGRAM_ID,
+        ),
+        &[sender.clone(), wsol_account.clone(), system_program.clone()],
+        &[&[seeds::WSOL, sender.key.as_ref(), &[bump]]],
+    )?;
+    invoke(
+        &token::initialize_account3(wsol_account.key, native_mint.key, sender.key),
+        &[wsol_account.clone(), native_mint.clone()],
+    )?;
+
+    invoke(
+        &token::transfer_checked(
+            wsol_account.key,
+            native_mint.key,
+            recipient_token_account.key,
+            sender.key,
+            amount,
+            token::NATIVE_MINT_DECIMALS,
+        ),
+        &[
+            wsol_account.clone(),
+            native_mint.clone(),
+            recipient_token_account.clone(),
+            sender.clone(),
+        ],
+    )?;
+
+    // Unwrap the now-empty account, refunding its rent
+    invoke(
+        &token::close_account(wsol_account.key, sender.key, sender.key),
+        &[wsol_account.clone(), sender.clone()],
+    )?;
+
+    TransferVerified {
+        sender_hash,
+        amount,
+        recipient: *recipient.key,
+    }
+    .emit(accounts)?;
+    set_verification_result(state_account, sender_hash, amount)?;
+    Ok(())
+}
+
+/// Pay several recipients after verifying the sender's exclusion proof once
+///
+/// Rate limits and fees apply to the batch total.
+///
+/// Accounts:
+///   0. [signer, writable] Sender (must prove NOT blacklisted)
+///   1. [] State account (contains SMT root)
+///   2. [] ZK Verifier program
+///   3. [] System program
+///   4. [writable] Recipients: N accounts, in the same order as the amounts
+///
+/// Then the proof accounts.
+///
+/// Data:
+///   - 1 byte: recipient count N (1 to MAX_BATCH_RECIPIENTS)
+///   - 388 bytes: ZK proof
+///   - 108 bytes: public witness (must match smt_root from state + pubkey_hash from signer,
+///     recent_slot must be a recent slot in SlotHashes)
+///   - 8 bytes per recipient: amount (lamports)
+pub(crate) fn process_transfer_sol_batch(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let count = data[0] as usize;
+    let proof_data = &data[1..1 + instruction::PROOF_SIZE];
+    let witness_data =
+        &data[1 + instruction::PROOF_SIZE..instruction::TRANSFER_SOL_BATCH_FIXED_LEN];
+    let amounts = data[instruction::TRANSFER_SOL_BATCH_FIXED_LEN..]
+        .chunks_exact(8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+
+    let account_iter = &mut accounts.iter();
+    let sender = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let zk_verifier = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let recipients = account_iter.by_ref().take(count).collect::<Vec<_>>();
+    if recipients.len() != count {
+        msg!("Expected {} recipient accounts", count);
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    let instructions_sysvar = account_iter.next();
+    let rate_limit_account = account_iter.next();
+    let treasury = account_iter.next();
+
+    if !sender.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let total = amounts
+        .clone()
+        .try_fold(0u64, u64::checked_add)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    let sender_hash = verify_exclusion_proof(
+        program_id,
+        accounts,
+        sender,
+        state_account,
+        zk_verifier,
+        instructions_sysvar,
+        proof_data,
+        witness_data,
+    )?;
+    record_rate_limited_transfer(
+        program_id,
+        sender,
+        sender,
+        state_account,
+        system_program,
+        rate_limit_account,
+        total,
+    )?;
+    charge_fee(
+        program_id,
+        accounts,
+        sender,
+        state_account,
+        system_program,
+        treasury,
+        total,
+    )?;
+
+    for (recipient, amount) in recipients.into_iter().zip(amounts) {
+        invoke(
+            &system_instruction::transfer(sender.key, recipient.key, amount),
+            &[sender.clone(), recipient.clone(), system_program.clone()],
+        )?;
+        TransferVerified {
+            sender_hash,
+            amount,
+            recipient: *recipient.key,
+        }
+        .emit(accounts)?;
+    }
+
+    set_verification_result(state_account, sender_hash, total)?;
+    msg!("Batch of {} transfers complete", count);
+    Ok(())
+}
+
+/// Transfer a compressed NFT, gated by the owner's exclusion proof
+///
+/// Two merkle proofs meet here: the exclusion proof shows the owner is not in
+/// the blacklist tree, and Bubblegum checks the cNFT leaf's path in its own
+/// tree. The owner signs, and its signature is passed on to Bubblegum.
+///
+/// Accounts:
+///   0. [signer] Owner (must prove NOT blacklisted; writable to create a rate limit
+///      account)
+///   1. [] Leaf delegate (the owner when none is set)
+///   2. [] New owner
+///   3. [] State account (contains SMT root)
+///   4. [] ZK Verifier program
+///   5. [] Bubblegum program
+///   6. [] Tree authority (Bubblegum PDA: [merkle_tree])
+///   7. [writable] Merkle tree
+///   8. [] Noop program (Bubblegum's log wrapper)
+///   9. [] Account compression program
+///  10. [] System program
+///  11. [] cNFT proof nodes: N accounts, the leaf's path less the tree's canopy
+///
+/// Then the proof accounts. A cNFT carries no lamports, so it pays no fee and
+/// counts nothing against a daily limit, but is refused once the owner's limit
+/// is used up.
+///
+/// Data:
+///   - 388 bytes: ZK proof
+///   - 108 bytes: public witness (as for TRANSFER_SOL)
+///   - 108 bytes: Bubblegum leaf (root, data hash, creator hash, nonce, index)
+///   - 1 byte: proof node count N
+pub(crate) fn process_transfer_cnft(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let leaf_delegate = next_account_info(account_iter)?;
+    let new_owner = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let zk_verifier = next_account_info(account_iter)?;
+    let bubblegum_program = next_account_info(account_iter)?;
+    let tree_authority = next_account_info(account_iter)?;
+    let merkle_tree = next_account_info(account_iter)?;
+    let noop_program = next_account_info(account_iter)?;
+    let compression_program = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let node_count = data[instruction::TRANSFER_CNFT_LEN - 1] as usize;
+    let proof_nodes = account_iter.by_ref().take(node_count).collect::<Vec<_>>();
+    if proof_nodes.len() != node_count {
+        msg!("Expected {} cNFT proof node accounts", node_count);
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    let instructions_sysvar = account_iter.next();
+    let rate_limit_account = account_iter.next();
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    // The owner's signature must only ever reach Bubblegum
+    if bubblegum_program.key != &bubblegum::BUBBLEGUM_PROGRAM_ID {
+        msg!("Invalid Bubblegum program");
+        return Err(ExclusionError::InvalidBubblegumProgram.into());
+    }
+
+    let proof_data = &data[..instruction::PROOF_SIZE];
+    let witness_data = &data[instruction::PROOF_SIZE..instruction::ATTEST_LEN];
+    let leaf = bubblegum::Leaf::unpack(
+        &data[instruction::ATTEST_LEN..instruction::ATTEST_LEN + bubblegum::Leaf::LEN],
+    )
+    .ok_or(ExclusionError::InvalidDataLength)?;
+    verify_exclusion_proof(
+        program_id,
+        accounts,
+        owner,
+        state_account,
+        zk_verifier,
+        instructions_sysvar,
+        proof_data,
+        witness_data,
+    )?;
+    record_rate_limited_transfer(
+        program_id,
+        owner,
+        owner,
+        state_account,
+        system_program,
+        rate_limit_account,
+        0,
+    )?;
+
+    let node_keys: Vec<Pubkey> = proof_nodes.iter().map(|node| *node.key).collect();
+    let mut cpi_accounts = vec![
+        tree_authority.clone(),
+        owner.clone(),
+        leaf_delegate.clone(),
+        new_owner.clone(),
+        merkle_tree.clone(),
+        noop_program.clone(),
+        compression_program.clone(),
+        system_program.clone(),
+    ];
+    cpi_accounts.extend(proof_nodes.into_iter().cloned());
+    invoke(
+        &bubblegum::transfer(
+            tree_authority.key,
+            merkle_tree.key,
+            owner.key,
+            leaf_delegate.key,
+            new_owner.key,
+            &leaf,
+            &node_keys,
+        ),
+        &cpi_accounts,
+    )?;
+
+    msg!(
+        "Transferred cNFT #{} of tree {} to {}",
+        leaf.index,
+        merkle_tree.key,
+        new_owner.key
+    );
+    Ok(())
+}
+
+/// Transfer SOL after verifying exclusion proofs for BOTH sender and recipient.
+/// Some compliance regimes require screening both counterparties.
+///
+/// Two proofs do not fit in one transaction, so each party uploads its proof to
+/// its own proof buffer first (CREATE_PROOF_BUFFER / WRITE_PROOF_CHUNK). Both
+/// buffers are closed afterwards, refunding rent to their owners.
+///
+/// Accounts:
+///   0. [signer, writable] Sender (must prove NOT blacklisted)
+///   1. [writable] Recipient (must prove NOT blacklisted)
+///   2. [] State account (contains SMT root)
+///   3. [] ZK Verifier program
+///   4. [] System program
+///   5. [writable] Sender proof buffer (PDA: ["proof_buffer", sender_pubkey])
+///   6. [writable] Recipient proof buffer (PDA: ["proof_buffer", recipient_pubkey])
+///
+/// Then the proof accounts.
+///
+/// Data: 8 bytes (amount in lamports)
+pub(crate) fn process_transfer_sol_dual(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let sender = next_account_info(account_iter)?;
+    let recipient = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let zk_verifier = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let sender_buffer = next_account_info(account_iter)?;
+    let recipient_buffer = next_account_info(account_iter)?;
+    let instructions_sysvar = account_iter.next();
+    let rate_limit_account = account_iter.next();
+    let treasury = account_iter.next();
+
+    if !sender.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    check_proof_buffer(program_id, sender, sender_buffer)?;
+    check_proof_buffer(program_id, recipient, recipient_buffer)?;
+
+    let amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
+
+    let mut sender_hash = [0u8; 32];
+    for (party, buffer_account) in [(sender, sender_buffer), (recipient, recipient_buffer)] {
+        let buffer_data = buffer_account.try_borrow_data()?;
+        let payload = &buffer_data[PROOF_BUFFER_HEADER_SIZE..];
+        let (proof_data, witness_data) = payload.split_at(instruction::PROOF_SIZE);
+        let party_hash = verify_exclusion_proof(
+            program_id,
+            accounts,
+            party,
+            state_account,
+            zk_verifier,
+            instructions_sysvar,
+            proof_data,
+            witness_data,
+        )?;
+        if party.key == sender.key {
+            sender_hash = party_hash;
+        }
+    }
+    record_rate_limited_transfer(
+        program_id,
+        sender,
+        sender,
+        state_account,
+        system_program,
+        rate_limit_account,
+        amount,
+    )?;
+    charge_fee(
+        program_id,
+        accounts,
+        sender,
+        state_account,
+        system_program,
+        treasury,
+        amount,
+    )?;
+
+    // Transfer SOL
+    invoke(
+        &system_instruction::transfer(sender.key, recipient.key, amount),
+        &[sender.clone(), recipient.clone(), system_program.clone()],
+    )?;
+
+    close_account(sender_buffer, sender, system_program)?;
+    close_account(recipient_buffer, recipient, system_program)?;
+
+    TransferVerified {
+        sender_hash,
+        amount,
+        recipient: *recipient.key,
+    }
+    .emit(accounts)?;
+    set_verification_result(state_account, sender_hash, amount)?;
+
+    msg!("Dual-screened transfer complete, proof buffers closed");
+    Ok(())
+}
+
+/// Pay out of the admin's vault to a recipient who proves they are not blacklisted.
+///
+/// The vault is a system-owned PDA (`["vault", admin]`) that anyone can fund with
+/// plain system transfers. The program signs for it with `invoke_signed`, so a
+/// treasury's lamports never sit in a hot wallet: the admin key only authorizes
+/// payouts and can be a cold key or multisig.
+///
+/// Accounts:
+///   0. [signer] Admin (authorizes the payout; writable to create a rate limit account)
+///   1. [writable] Recipient (must prove NOT blacklisted)
+///   2. [] State account (PDA: ["state", admin_pubkey])
+///   3. [] ZK Verifier program
+///   4. [] System program
+///   5. [writable] Vault (PDA: ["vault", admin_pubkey])
+///
+/// Then the proof accounts, with the rate limit account keyed by the admin: the
+/// daily limit caps the vault's payouts, and the vault pays any fee.
+///
+/// Data:
+///   - 8 bytes: amount (lamports)
+///   - 388 bytes: recipient's ZK proof
+///   - 108 bytes: public witness (must match smt_root from state + pubkey_hash from recipient,
+///     recent_slot must be a recent slot in SlotHashes)
+pub(crate) fn process_transfer_from_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let recipient = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let zk_verifier = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let vault = next_account_info(account_iter)?;
+    let instructions_sysvar = account_iter.next();
+    let rate_limit_account = account_iter.next();
+    let treasury = account_iter.next();
+
+    check_admin_state(program_id, admin, state_account)?;
+
+    let (vault_pda, bump) =
+        Pubkey::find_program_address(&[seeds::VAULT, admin.key.as_ref()], program_id);
+    if vault.key != &vault_pda {
+        msg!("Vault does not match admin's PDA");
+        return Err(ExclusionError::InvalidVaultAccount.into());
+    }
+
+    let amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    let proof_data = &data[8..8 + instruction::PROOF_SIZE];
+    let witness_data = &data[8 + instruction::PROOF_SIZE..];
+
+    verify_exclusion_proof(
+        program_id,
+        accounts,
+        recipient,
+        state_account,
+        zk_verifier,
+        instructions_sysvar,
+        proof_data,
+        witness_data,
+    )?;
+    record_rate_limited_transfer(
+        program_id,
+        admin,
+        admin,
+        state_account,
+        system_program,
+        rate_limit_account,
+        amount,
+    )?;
+
+    let signer_seeds: &[&[u8]] = &[seeds::VAULT, admin.key.as_ref(), &[bump]];
+    if let Some((treasury, fee)) = fee_due(program_id, accounts, state_account, treasury, amount)? {
+        invoke_signed(
+            &system_instruction::transfer(vault.key, treasury.key, fee),
+            &[vault.clone(), treasury.clone(), system_program.clone()],
+            &[signer_seeds],
+        )?;
+    }
+
+    msg!("Paying {} lamports from vault to {}", amount, recipient.key);
+    invoke_signed(
+        &system_instruction::transfer(vault.key, recipient.key, amount),
+        &[vault.clone(), recipient.clone(), system_program.clone()],
+        &[signer_seeds],
+    )?;
+
+    msg!("Vault payout complete");
+    Ok(())
+}
+
+/// Read daily_limit from state data (0 = unlimited)
+pub(super) fn daily_limit(state_data: &[u8]) -> u64 {
+    u64::from_le_bytes(state_data[DAILY_LIMIT..DAILY_LIMIT + 8].try_into().unwrap())
+}
+
+/// Count `amount` against the sender's rolling 24-hour total when the state sets a
+/// daily limit, creating the sender's rate limit account on first use at `payer`'s
+/// expense. A zero amount, as for a cNFT, is refused once the window is used up.
+///
+/// Totals are kept in 24 hourly buckets indexed by `unix_timestamp / 3600`; buckets
+/// older than 24 hours are cleared before summing, so the window rolls hour by hour.
+pub(super) fn record_rate_limited_transfer<'a>(
+    program_id: &Pubkey,
+    sender: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    state_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    rate_limit_account: Option<&AccountInfo<'a>>,
+    amount: u64,
+) -> ProgramResult {
+    let limit = daily_limit(&state_account.try_borrow_data()?);
+    if limit == 0 {
+        return Ok(());
+    }
+
+    let rate_limit_account = rate_limit_account.ok_or_else(|| {
+        msg!("Rate limit account required when a daily limit is set");
+        ProgramError::NotEnoughAccountKeys
+    })?;
+    let (rate_limit_pda, bump) = Pubkey::find_program_address(
+        &[
+            seeds::RATE_LIMIT,
+            state_account.key.as_ref(),
+            sender.key.as_ref(),
+        ],
+        program_id,
+    );
+    if rate_limit_account.key != &rate_limit_pda {
+        msg!("Rate limit account does not match sender's PDA");
+        return Err(ExclusionError::InvalidRateLimitAccount.into());
+    }
+
+    if rate_limit_account.owner != program_id {
+        let lamports = Rent::get()?.minimum_balance(RATE_LIMIT_ACCOUNT_SIZE);
+        let signer_seeds: &[&[u8]] = &[
+            seeds::RATE_LIMIT,
+            state_account.key.as_ref(),
+            sender.key.as_ref(),
+            &[bump],
+        ];
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                rate_limit_account.key,
+                lamports,
+                RATE_LIMIT_ACCOUNT_SIZE as u64,
+                program_id,
+            ),
+            &[
+                payer.clone(),
+                rate_limit_account.clone(),
+                system_program.clone(),
+            ],
+            &[signer_seeds],
+        )?;
+        rate_limit_account.try_borrow_mut_data()?[0..8]
+            .copy_from_slice(&RATE_LIMIT_ACCOUNT_DISCRIMINATOR);
+    }
+
+    let mut data = rate_limit_account.try_borrow_mut_data()?;
+    if data.len() != RATE_LIMIT_ACCOUNT_SIZE || data[0..8] != RATE_LIMIT_ACCOUNT_DISCRIMINATOR {
+        msg!("Invalid rate limit account");
+        return Err(ExclusionError::InvalidRateLimitAccount.into());
+    }
+
+    let bucket_at = |hour: u64| 16 + (hour % RATE_LIMIT_BUCKETS as u64) as usize * 8;
+    let read_bucket = |data: &[u8], hour: u64| {
+        let at = bucket_at(hour);
+        u64::from_le_bytes(data[at..at + 8].try_into().unwrap())
+    };
+
+    // Clear buckets for the hours elapsed since the last transfer
+    let hour = (Clock::get()?.unix_timestamp.max(0) / 3600) as u64;
+    let last_hour = u64::from_le_bytes(data[8..16].try_into().unwrap());
+    let elapsed = hour.saturating_sub(last_hour);
+    for stale in hour - elapsed.min(RATE_LIMIT_BUCKETS as u64) + 1..=hour {
+        let at = bucket_at(stale);
+        data[at..at + 8].copy_from_slice(&0u64.to_le_bytes());
+    }
+
+    let total = (0..RATE_LIMIT_BUCKETS as u64)
+        .map(|h| read_bucket(&data, h))
+        .try_fold(amount, u64::checked_add)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    if total > limit || (amount == 0 && total == limit) {
+        msg!(
+            "Transfer of {} lamports exceeds the 24-hour limit of {}",
+            amount,
+            limit
+        );
+        return Err(ExclusionError::RateLimitExceeded.into());
+    }
+
+    let current = read_bucket(&data, hour) + amount;
+    let at = bucket_at(hour);
+    data[at..at + 8].copy_from_slice(&current.to_le_bytes());
+    data[8..16].copy_from_slice(&hour.to_le_bytes());
+    Ok(())
+}
+
+/// Read fee_bps from state data (0 = no fee)
+pub(super) fn fee_bps(state_data: &[u8]) -> u16 {
+    u16::from_le_bytes(state_data[FEE_BPS..FEE_BPS + 2].try_into().unwrap())
+}
+
+/// Charge the state's fee on `amount` to the sender, on top of the transfer,
+/// paying it into the state's treasury. A linked program config caps the fee.
+pub(super) fn charge_fee<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+    sender: &AccountInfo<'a>,
+    state_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    treasury: Option<&AccountInfo<'a>>,
+    amount: u64,
+) -> ProgramResult {
+    let Some((treasury, fee)) = fee_due(program_id, accounts, state_account, treasury, amount)?
+    else {
+        return Ok(());
+    };
+    invoke(
+        &system_instruction::transfer(sender.key, treasury.key, fee),
+        &[sender.clone(), treasury.clone(), system_program.clone()],
+    )
+}
+
+/// The state's fee on `amount` and the checked treasury it's paid into, or
+/// `None` when there's nothing to pay
+pub(super) fn fee_due<'b, 'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+    state_account: &AccountInfo<'a>,
+    treasury: Option<&'b AccountInfo<'a>>,
+    amount: u64,
+) -> Result<Option<(&'b AccountInfo<'a>, u64)>, ProgramError> {
+    let state_data = state_account.try_borrow_data()?;
+    let config = ProgramConfig::for_state(program_id, &state_data, accounts)?;
+    let fee_bps = fee_bps(&state_data).min(config.max_fee_bps);
+    drop(state_data);
+    if fee_bps == 0 {
+        return Ok(None);
+    }
+
+    let treasury = treasury.ok_or_else(|| {
+        msg!("Treasury required when a fee is set");
+        ProgramError::NotEnoughAccountKeys
+    })?;
+    check_treasury(program_id, state_account, treasury)?;
+
+    // fee_bps <= MAX_FEE_BPS, so the fee never exceeds the amount
+    let fee = (amount as u128 * fee_bps as u128 / MAX_FEE_BPS as u128) as u64;
+    if fee == 0 {
+        return Ok(None);
+    }
+
+    msg!("Charging {} lamports fee ({} bps)", fee, fee_bps);
+    Ok(Some((treasury, fee)))
+}
+
+/// The treasury PDA's bump as the state stores it, or searched for with the
+/// `search-bumps` feature
+pub(super) fn treasury_bump(
+    program_id: &Pubkey,
+    state_account: &AccountInfo,
+) -> Result<u8, ProgramError> {
+    if cfg!(feature = "search-bumps") {
+        let seeds = [seeds::TREASURY, state_account.key.as_ref()];
+        return Ok(Pubkey::find_program_address(&seeds, program_id).1);
+    }
+    Ok(state_account.try_borrow_data()?[TREASURY_BUMP])
+}
+
+/// Verify that a treasury is the state's PDA and has been created by SET_FEE
+pub(super) fn check_treasury(
+    program_id: &Pubkey,
+    state_account: &AccountInfo,
+    treasury: &AccountInfo,
+) -> ProgramResult {
+    let bump = treasury_bump(program_id, state_account)?;
+    let treasury_pda = Pubkey::create_program_address(
+        &[seeds::TREASURY, state_account.key.as_ref(), &[bump]],
+        program_id,
+    );
+    if treasury_pda.as_ref() != Ok(treasury.key)
+        || treasury.owner != program_id
+        || treasury.try_borrow_data()?[..] != TREASURY_ACCOUNT_DISCRIMINATOR
+    {
+        msg!("Invalid treasury account");
+        return Err(ExclusionError::InvalidTreasuryAccount.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,471 @@
+//! Tree maintenance on-chain: leaf updates, root transitions, published roots
+//! and the self-exclusion queue
+
+use exclusion_common::seeds;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+use solana_system_interface::instruction as system_instruction;
+
+use super::{
+    check_admin_state, check_sparse_tree, check_state_layout,
+    config::ProgramConfig,
+    set_root, tree_depth,
+    verify::{invoke_verifier, pubkey_hash_be, root_valid_until, witness_field_be},
+};
+use crate::{
+    events::Event, instruction, smt, state_offsets::*, wormhole, ExclusionError,
+    EXCLUSION_QUEUE_CAPACITY, EXCLUSION_QUEUE_DISCRIMINATOR, EXCLUSION_QUEUE_SIZE,
+};
+
+/// Insert or remove a blacklisted pubkey, recomputing the root on-chain
+///
+/// The admin supplies the key's merkle path; the program checks that it
+/// reconstructs the stored root with the current leaf value and then stores the
+/// root with the updated leaf. Root transitions are auditable from transaction
+/// history instead of being opaque `SET_SMT_ROOT` blobs. Recomputing two roots
+/// over the default 254 levels costs several hundred thousand CUs; request a
+/// compute budget. Shallower trees (`SET_TREE_DEPTH`) cost proportionally less.
+///
+/// INSERT_LEAF also removes the pubkey from the state's exclusion queue when the
+/// queue is passed, so self-excluded users leave the queue once they are in the tree.
+/// With the queue, a path showing the key is already in the tree only dequeues it.
+///
+/// Accounts:
+///   0. [signer] Admin
+///   1. [writable] State account (PDA: ["state", admin_pubkey])
+///   2. [writable] Exclusion queue (PDA: ["exclusion_queue", state]; optional, INSERT_LEAF only)
+///   3. [] Noop program (with the `noop-events` feature)
+///
+/// Data:
+///   - 32 bytes: pubkey to insert or remove
+///   - 32 bytes: sibling bitmap (bit i set = sibling at level i is provided)
+///   - 32 bytes per set bit: non-default siblings, lowest level first
+pub(crate) fn process_update_leaf(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+    insert: bool,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let admin = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    // The queue is the only program-owned account that can follow the state
+    let exclusion_queue = account_iter
+        .next()
+        .filter(|account| insert && account.owner == program_id);
+
+    check_admin_state(program_id, admin, state_account)?;
+    let state_data = state_account.try_borrow_data()?;
+    check_sparse_tree(&state_data)?;
+    let scheme = state_data[HASH_SCHEME];
+    let depth = tree_depth(&state_data);
+    drop(state_data);
+
+    let pubkey = Pubkey::new_from_array(data[0..32].try_into().unwrap());
+    let sibling_bitmap = &data[32..64];
+    let siblings = &data[64..];
+
+    // A shallow tree's leaf holding another key fails the root check: the
+    // admin has to move to a deeper tree to blacklist both
+    let key_hash = pubkey_hash_be(&pubkey, scheme)?;
+    let (old_leaf, new_leaf) = if insert {
+        (smt::EMPTY_LEAF, smt::leaf_value(&key_hash, depth))
+    } else {
+        (smt::leaf_value(&key_hash, depth), smt::EMPTY_LEAF)
+    };
+
+    let (old_root, new_root) = smt::compute_update_roots(
+        &key_hash,
+        &old_leaf,
+        &new_leaf,
+        depth,
+        sibling_bitmap,
+        siblings,
+    )?;
+
+    let mut state_data = state_account.try_borrow_mut_data()?;
+    if let Some(exclusion_queue) = exclusion_queue {
+        // A queued key the tree already holds only has to leave the queue
+        if state_data[SMT_ROOT..SMT_ROOT + 32] == new_root {
+            drop(state_data);
+            dequeue_exclusion(program_id, state_account, exclusion_queue, &pubkey)?;
+            msg!("{} is already blacklisted", pubkey);
+            return Ok(());
+        }
+    }
+    if state_data[SMT_ROOT..SMT_ROOT + 32] != old_root {
+        msg!("Merkle path does not reconstruct the stored root");
+        return Err(ExclusionError::LeafPathMismatch.into());
+    }
+    set_root(&mut state_data, &new_root).emit(accounts)?;
+    drop(state_data);
+
+    if let Some(exclusion_queue) = exclusion_queue {
+        dequeue_exclusion(program_id, state_account, exclusion_queue, &pubkey)?;
+    }
+
+    if insert {
+        msg!("Inserted {} into blacklist", pubkey);
+    } else {
+        msg!("Removed {} from blacklist", pubkey);
+    }
+    Ok(())
+}
+
+/// Advance the SMT root by proving a correct insertion
+///
+/// A tree-transition proof (see `circuits/smt_insertion`) shows that inserting
+/// `inserted_key` into the tree at the stored root yields `new_root`. The admin
+/// no longer has to be trusted to compute roots honestly: every root change made
+/// this way is a verified single-key insertion. The admin or the inserted key
+/// must sign, so a key can exclude itself but nobody else can blacklist it.
+///
+/// Accounts:
+///   0. [writable] State account (any state account owned by this program)
+///   1. [] Tree-transition ZK verifier program
+///   2. [signer] Authority: the state's admin, or the inserted pubkey
+///   3. [] Noop program (with the `noop-events` feature)
+///
+/// Data:
+///   - 32 bytes: inserted pubkey
+///   - 388 bytes: ZK proof
+///   - 108 bytes: public witness (12-byte header + old_root + new_root + key)
+pub(crate) fn process_submit_root_transition(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let state_account = next_account_info(account_iter)?;
+    let transition_verifier = next_account_info(account_iter)?;
+    let authority = next_account_info(account_iter)?;
+
+    if state_account.owner != program_id {
+        msg!("State account not owned by this program");
+        return Err(ExclusionError::InvalidStateAccount.into());
+    }
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let inserted_key = Pubkey::new_from_array(data[0..32].try_into().unwrap());
+    let proof_data = &data[32..32 + instruction::PROOF_SIZE];
+    let witness_data = &data[32 + instruction::PROOF_SIZE..];
+
+    let state_data = state_account.try_borrow_data()?;
+    check_state_layout(&state_data)?;
+    if state_data[ADMIN..ADMIN + 32] != authority.key.as_ref()[..] && authority.key != &inserted_key
+    {
+        msg!("Only the admin or the inserted key may submit a root transition");
+        return Err(ExclusionError::UnauthorizedAdmin.into());
+    }
+    check_sparse_tree(&state_data)?;
+    // The config names one transition verifier, built for the default depth
+    if tree_depth(&state_data) != smt::TREE_DEPTH {
+        msg!(
+            "Root transitions are proven for {}-level trees",
+            smt::TREE_DEPTH
+        );
+        return Err(ExclusionError::UnsupportedTreeDepth.into());
+    }
+
+    let config = ProgramConfig::for_state(program_id, &state_data, accounts)?;
+    if transition_verifier.key != &config.transition_verifier {
+        msg!("Invalid tree-transition verifier program");
+        return Err(ExclusionError::InvalidZkVerifier.into());
+    }
+
+    // Witness format: 12-byte header + old_root + new_root + key (32 bytes each)
+    let flags = state_data[VERIFIER_FLAGS];
+    let witness_old_root = witness_field_be(&witness_data[12..44], flags);
+    let witness_new_root = witness_field_be(&witness_data[44..76], flags);
+    let witness_key = witness_field_be(&witness_data[76..108], flags);
+
+    if witness_old_root != state_data[SMT_ROOT..SMT_ROOT + 32] {
+        msg!("Old root in transition proof does not match stored root");
+        return Err(ExclusionError::TransitionRootMismatch.into());
+    }
+    let scheme = state_data[HASH_SCHEME];
+    drop(state_data);
+
+    // Bind the proof to the inserted pubkey so the change is auditable from the tx
+    if witness_key != pubkey_hash_be(&inserted_key, scheme)? {
+        msg!("Key hash mismatch - proof inserts a different pubkey");
+        return Err(ExclusionError::PubkeyHashMismatch.into());
+    }
+
+    msg!("Verifying tree-transition proof...");
+    invoke_verifier(&config.transition_verifier, proof_data, witness_data)?;
+
+    let mut state_data = state_account.try_borrow_mut_data()?;
+    set_root(&mut state_data, &witness_new_root).emit(accounts)?;
+
+    msg!(
+        "SMT root advanced by verified insertion of {}",
+        inserted_key
+    );
+    Ok(())
+}
+
+/// Mirror a state's current root to other chains as a Wormhole message
+///
+/// Permissionless: the root is public, and anyone may pay to relay it. The
+/// payload is a `wormhole::RootMessage`, emitted by this program's
+/// ["emitter"] PDA; receivers accept a state's messages in `root_seq` order.
+/// Each root is published at most once, since the message account is derived
+/// from its `root_seq`. Roots of a challenged state are not published.
+///
+/// Accounts:
+///   0. [signer, writable] Payer (pays the Wormhole fee and the message's rent)
+///   1. [] State account
+///   2. [] Wormhole core bridge
+///   3. [writable] Bridge config (core bridge PDA: ["Bridge"])
+///   4. [writable] Message (PDA: ["wormhole_message", state, root_seq (u64 LE)])
+///   5. [] Emitter (PDA: ["emitter"])
+///   6. [writable] Emitter sequence (core bridge PDA: ["Sequence", emitter])
+///   7. [writable] Fee collector (core bridge PDA: ["fee_collector"])
+///   8. [] Clock sysvar
+///   9. [] Rent sysvar
+///  10. [] System program
+pub(crate) fn process_publish_root(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let payer = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let core_bridge = next_account_info(account_iter)?;
+    let bridge_config = next_account_info(account_iter)?;
+    let message = next_account_info(account_iter)?;
+    let emitter = next_account_info(account_iter)?;
+    let sequence = next_account_info(account_iter)?;
+    let fee_collector = next_account_info(account_iter)?;
+    let clock = next_account_info(account_iter)?;
+    let rent = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if state_account.owner != program_id {
+        msg!("State account not owned by this program");
+        return Err(ExclusionError::InvalidStateAccount.into());
+    }
+    let state_data = state_account.try_borrow_data()?;
+    check_state_layout(&state_data)?;
+    if state_data[CHALLENGED] != 0 {
+        msg!("State account was flagged by a successful challenge");
+        return Err(ExclusionError::StateChallenged.into());
+    }
+    let root_message = wormhole::RootMessage {
+        state: *state_account.key,
+        smt_root: state_data[SMT_ROOT..SMT_ROOT + 32].try_into().unwrap(),
+        root_seq: u64::from_le_bytes(state_data[ROOT_SEQ..ROOT_SEQ + 8].try_into().unwrap()),
+        root_valid_until: root_valid_until(&state_data),
+    };
+    drop(state_data);
+
+    // Our PDAs sign the message; the core bridge checks its own accounts
+    if core_bridge.key != &wormhole::CORE_BRIDGE_PROGRAM_ID {
+        msg!("Invalid Wormhole core bridge");
+        return Err(ExclusionError::InvalidWormholeAccount.into());
+    }
+    let (emitter_pda, emitter_bump) =
+        Pubkey::find_program_address(&[wormhole::EMITTER_SEED], program_id);
+    let seq_bytes = root_message.root_seq.to_le_bytes();
+    let (message_pda, message_bump) = Pubkey::find_program_address(
+        &[
+            seeds::WORMHOLE_MESSAGE,
+            state_account.key.as_ref(),
+            &seq_bytes,
+        ],
+        program_id,
+    );
+    if emitter.key != &emitter_pda || message.key != &message_pda {
+        msg!("Wormhole emitter or message account does not match this program's PDAs");
+        return Err(ExclusionError::InvalidWormholeAccount.into());
+    }
+
+    let fee = bridge_config
+        .try_borrow_data()?
+        .get(wormhole::BRIDGE_FEE_OFFSET..wormhole::BRIDGE_FEE_OFFSET + 8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(ExclusionError::InvalidWormholeAccount)?;
+    if fee > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, fee_collector.key, fee),
+            &[payer.clone(), fee_collector.clone(), system_program.clone()],
+        )?;
+    }
+
+    invoke_signed(
+        &wormhole::post_message(
+            bridge_config.key,
+            message.key,
+            emitter.key,
+            sequence.key,
+            payer.key,
+            fee_collector.key,
+            0,
+            &root_message.pack(),
+        ),
+        &[
+            bridge_config.clone(),
+            message.clone(),
+            emitter.clone(),
+            sequence.clone(),
+            payer.clone(),
+            fee_collector.clone(),
+            clock.clone(),
+            rent.clone(),
+            system_program.clone(),
+        ],
+        &[
+            &[wormhole::EMITTER_SEED, &[emitter_bump]],
+            &[
+                seeds::WORMHOLE_MESSAGE,
+                state_account.key.as_ref(),
+                &seq_bytes,
+                &[message_bump],
+            ],
+        ],
+    )?;
+
+    msg!(
+        "Published root #{} of {} to Wormhole",
+        root_message.root_seq,
+        state_account.key
+    );
+    Ok(())
+}
+
+/// Ask to be blacklisted by the state's admin
+///
+/// Appends the signer to the state's exclusion queue, creating the queue on first
+/// use. The admin drains the queue into the tree with INSERT_LEAF, which proves
+/// each key was inserted and removes it from the queue. Until then the signer's
+/// exclusion proofs still verify. Queueing a key twice does nothing, and there is
+/// no way to leave the queue, so a self-ban cannot be undone on impulse.
+///
+/// Accounts:
+///   0. [signer, writable] User to exclude (pays for the queue on first use)
+///   1. [] State account (PDA: ["state", admin_pubkey])
+///   2. [writable] Exclusion queue (PDA: ["exclusion_queue", state])
+///   3. [] System program
+pub(crate) fn process_self_exclude(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let user = next_account_info(account_iter)?;
+    let state_account = next_account_info(account_iter)?;
+    let exclusion_queue = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !user.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if state_account.owner != program_id {
+        msg!("State account not owned by this program");
+        return Err(ExclusionError::InvalidStateAccount.into());
+    }
+    check_state_layout(&state_account.try_borrow_data()?)?;
+
+    let (queue_pda, bump) = Pubkey::find_program_address(
+        &[seeds::EXCLUSION_QUEUE, state_account.key.as_ref()],
+        program_id,
+    );
+    if exclusion_queue.key != &queue_pda {
+        msg!("Exclusion queue does not match state's PDA");
+        return Err(ExclusionError::InvalidExclusionQueue.into());
+    }
+
+    if exclusion_queue.owner != program_id {
+        let lamports = Rent::get()?.minimum_balance(EXCLUSION_QUEUE_SIZE);
+        let signer_seeds: &[&[u8]] = &[seeds::EXCLUSION_QUEUE, state_account.key.as_ref(), &[bump]];
+        invoke_signed(
+            &system_instruction::create_account(
+                user.key,
+                exclusion_queue.key,
+                lamports,
+                EXCLUSION_QUEUE_SIZE as u64,
+                program_id,
+            ),
+            &[
+                user.clone(),
+                exclusion_queue.clone(),
+                system_program.clone(),
+            ],
+            &[signer_seeds],
+        )?;
+        exclusion_queue.try_borrow_mut_data()?[0..8]
+            .copy_from_slice(&EXCLUSION_QUEUE_DISCRIMINATOR);
+    }
+
+    let mut data = exclusion_queue.try_borrow_mut_data()?;
+    if data.len() != EXCLUSION_QUEUE_SIZE || data[0..8] != EXCLUSION_QUEUE_DISCRIMINATOR {
+        msg!("Invalid exclusion queue");
+        return Err(ExclusionError::InvalidExclusionQueue.into());
+    }
+
+    let count = data[8] as usize;
+    if queued_pubkeys(&data).any(|queued| queued == user.key.as_ref()) {
+        msg!("{} is already queued for exclusion", user.key);
+        return Ok(());
+    }
+    if count == EXCLUSION_QUEUE_CAPACITY {
+        msg!("Exclusion queue is full");
+        return Err(ExclusionError::ExclusionQueueFull.into());
+    }
+
+    data[9 + 32 * count..9 + 32 * (count + 1)].copy_from_slice(user.key.as_ref());
+    data[8] = count as u8 + 1;
+
+    msg!("{} queued for exclusion", user.key);
+    Ok(())
+}
+
+/// Pubkeys waiting in an exclusion queue, oldest first
+fn queued_pubkeys(queue_data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let count = (queue_data[8] as usize).min(EXCLUSION_QUEUE_CAPACITY);
+    queue_data[9..9 + 32 * count].chunks_exact(32)
+}
+
+/// Remove `pubkey` from the state's exclusion queue, if it is queued
+fn dequeue_exclusion(
+    program_id: &Pubkey,
+    state_account: &AccountInfo,
+    exclusion_queue: &AccountInfo,
+    pubkey: &Pubkey,
+) -> ProgramResult {
+    let queue_pda = Pubkey::find_program_address(
+        &[seeds::EXCLUSION_QUEUE, state_account.key.as_ref()],
+        program_id,
+    )
+    .0;
+    let mut data = exclusion_queue.try_borrow_mut_data()?;
+    if exclusion_queue.key != &queue_pda
+        || data.len() != EXCLUSION_QUEUE_SIZE
+        || data[0..8] != EXCLUSION_QUEUE_DISCRIMINATOR
+    {
+        msg!("Invalid exclusion queue");
+        return Err(ExclusionError::InvalidExclusionQueue.into());
+    }
+
+    let Some(index) = queued_pubkeys(&data).position(|queued| queued == pubkey.as_ref()) else {
+        return Ok(());
+    };
+
+    // Move the last queued key into the gap; queue order is not significant
+    let last = data[8] as usize - 1;
+    data.copy_within(9 + 32 * last..9 + 32 * (last + 1), 9 + 32 * index);
+    data[9 + 32 * last..9 + 32 * (last + 1)].fill(0);
+    data[8] = last as u8;
+
+    msg!("{} removed from exclusion queue", pubkey);
+    Ok(())
+}
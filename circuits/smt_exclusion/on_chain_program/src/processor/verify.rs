@@ -0,0 +1,387 @@
+//! Exclusion proof checks against the state, and the CPI into the verifier
+
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT},
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::{
+        instructions::{load_current_index_checked, load_instruction_at_checked},
+        slot_hashes::PodSlotHashes,
+        Sysvar,
+    },
+};
+
+use super::{check_state_layout, config::ProgramConfig, registry::registered_verifier, tree_depth};
+use crate::{
+    hash_scheme, instruction, smt, state_offsets::*, transfer_flags, tree_kind, verifier_flags,
+    ExclusionError, INDEXED_VERIFIER_PROGRAM_ID, MAX_APPROVED_CALLERS,
+};
+
+/// Enforce the state's caller restriction using the Instructions sysvar.
+///
+/// A top-level instruction of this program is always accepted. Under
+/// REQUIRE_APPROVED_CALLER (without REQUIRE_TOP_LEVEL), a CPI is also accepted if
+/// it comes directly from the top-level instruction's program and that program
+/// is approved. The sysvar only records top-level instructions, so deeper CPI
+/// chains can't be attributed and are rejected.
+fn check_caller(
+    program_id: &Pubkey,
+    instructions_sysvar: Option<&AccountInfo>,
+    flags: u8,
+    state_data: &[u8],
+) -> ProgramResult {
+    let instructions_sysvar = instructions_sysvar.ok_or_else(|| {
+        msg!("Instructions sysvar required for caller-restricted transfers");
+        ProgramError::NotEnoughAccountKeys
+    })?;
+
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    let current_ix = load_instruction_at_checked(current_index as usize, instructions_sysvar)?;
+    let stack_height = get_stack_height();
+    if current_ix.program_id == *program_id && stack_height == TRANSACTION_LEVEL_STACK_HEIGHT {
+        return Ok(());
+    }
+
+    let direct_cpi = stack_height == TRANSACTION_LEVEL_STACK_HEIGHT + 1;
+    if flags & transfer_flags::REQUIRE_TOP_LEVEL == 0
+        && direct_cpi
+        && approved_callers(state_data).any(|caller| caller == current_ix.program_id.as_ref())
+    {
+        return Ok(());
+    }
+
+    msg!("Transfer invoked via CPI from {}", current_ix.program_id);
+    Err(ExclusionError::CpiNotAllowed.into())
+}
+
+/// Approved caller program IDs stored in state
+fn approved_callers(state_data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let count = state_data[APPROVED_CALLER_COUNT] as usize;
+    state_data[APPROVED_CALLERS..BUMP]
+        .chunks_exact(32)
+        .take(count.min(MAX_APPROVED_CALLERS))
+}
+
+/// Compute pubkey_hash for a pubkey under the state's hash scheme, big-endian
+pub(super) fn pubkey_hash_be(pubkey: &Pubkey, scheme: u8) -> Result<[u8; 32], ProgramError> {
+    match scheme {
+        hash_scheme::POSEIDON => Ok(exclusion_common::pubkey_to_index(&pubkey.to_bytes())),
+        #[cfg(feature = "poseidon2")]
+        hash_scheme::POSEIDON2 => Ok(crate::poseidon2::pubkey_hash_be(&pubkey.to_bytes())),
+        _ => {
+            msg!("Hash scheme {} is not supported by this build", scheme);
+            Err(ExclusionError::UnsupportedHashScheme.into())
+        }
+    }
+}
+
+/// A public witness field element as big-endian bytes, the form roots and
+/// hashes are stored and compared in
+pub(super) fn witness_field_be(field: &[u8], flags: u8) -> [u8; 32] {
+    let mut be: [u8; 32] = field.try_into().unwrap();
+    if flags & verifier_flags::WITNESS_LITTLE_ENDIAN != 0 {
+        be.reverse();
+    }
+    be
+}
+
+/// Check a proof's recent_slot public input: it must be a slot the cluster
+/// actually produced (present in SlotHashes) no more than `max_age` slots ago
+fn check_recent_slot(witness_slot: &[u8], max_age: u64) -> ProgramResult {
+    // Big-endian field element; the circuit constrains it to 64 bits
+    let (high, low) = witness_slot.split_at(24);
+    if high.iter().any(|&b| b != 0) {
+        msg!("recent_slot does not fit in a u64");
+        return Err(ExclusionError::StaleProof.into());
+    }
+    let recent_slot = u64::from_be_bytes(low.try_into().unwrap());
+
+    let current_slot = Clock::get()?.slot;
+    match current_slot.checked_sub(recent_slot) {
+        Some(age) if age <= max_age => {}
+        _ => {
+            msg!(
+                "Proof recent_slot {} is outside the window at slot {}",
+                recent_slot,
+                current_slot
+            );
+            return Err(ExclusionError::StaleProof.into());
+        }
+    }
+
+    if PodSlotHashes::fetch()?.get(&recent_slot)?.is_none() {
+        msg!("Proof recent_slot {} not found in SlotHashes", recent_slot);
+        return Err(ExclusionError::UnknownRecentSlot.into());
+    }
+    Ok(())
+}
+
+/// Read root_valid_until from state data (0 = never expires)
+pub(super) fn root_valid_until(state_data: &[u8]) -> i64 {
+    i64::from_le_bytes(
+        state_data[ROOT_VALID_UNTIL..ROOT_VALID_UNTIL + 8]
+            .try_into()
+            .unwrap(),
+    )
+}
+
+/// Check that `zk_verifier` verifies proofs for the state's tree: the state's
+/// own verifier (the config's, or INDEXED_VERIFIER for indexed trees), or one
+/// the verifier registry lists for the tree's kind and depth.
+///
+/// `accounts` is searched for the verifier registry.
+pub(super) fn check_zk_verifier(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    state_data: &[u8],
+    config: &ProgramConfig,
+    zk_verifier: &Pubkey,
+) -> ProgramResult {
+    let state_verifier = match state_data[TREE_KIND] {
+        tree_kind::INDEXED => INDEXED_VERIFIER_PROGRAM_ID,
+        _ => config.zk_verifier,
+    };
+    if zk_verifier != &state_verifier {
+        let Some(registered) = registered_verifier(program_id, accounts, zk_verifier)? else {
+            msg!("Invalid ZK verifier program");
+            return Err(ExclusionError::InvalidZkVerifier.into());
+        };
+        // The verifier's circuit was built for one tree kind and depth
+        if registered.tree_kind != state_data[TREE_KIND]
+            || registered.tree_depth as usize != tree_depth(state_data)
+        {
+            msg!("Registered verifier is for a different tree kind or depth");
+            return Err(ExclusionError::InvalidZkVerifier.into());
+        }
+        msg!(
+            "Using registered verifier for circuit {}",
+            solana_program::hash::Hash::new_from_array(registered.circuit_hash)
+        );
+    } else if state_data[TREE_KIND] == tree_kind::SPARSE
+        && tree_depth(state_data) != smt::TREE_DEPTH
+    {
+        // The configured verifier is built for the default depth; other depths
+        // use a verifier registered for them
+        msg!(
+            "Configured verifier is built for {}-level trees; register one for depth {}",
+            smt::TREE_DEPTH,
+            tree_depth(state_data)
+        );
+        return Err(ExclusionError::UnsupportedTreeDepth.into());
+    }
+    Ok(())
+}
+
+/// Check the proof's public inputs against on-chain state and the prover, then
+/// CPI into the ZK verifier program. Returns the prover's verified pubkey hash.
+///
+/// `accounts` is searched for the program config when the state is linked to it.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn verify_exclusion_proof(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    prover: &AccountInfo,
+    state_account: &AccountInfo,
+    zk_verifier: &AccountInfo,
+    instructions_sysvar: Option<&AccountInfo>,
+    proof_data: &[u8],
+    witness_data: &[u8],
+) -> Result<[u8; 32], ProgramError> {
+    // Read SMT root from state account
+    let state_data = state_account.try_borrow_data()?;
+    check_state_layout(&state_data)?;
+    if state_data[CHALLENGED] != 0 {
+        msg!("State account was flagged by a successful challenge");
+        return Err(ExclusionError::StateChallenged.into());
+    }
+
+    let config = ProgramConfig::for_state(program_id, &state_data, accounts)?;
+    if config.paused {
+        msg!("Transfers are paused by the program config");
+        return Err(ExclusionError::ProgramPaused.into());
+    }
+
+    check_zk_verifier(program_id, accounts, &state_data, &config, zk_verifier.key)?;
+    let stored_smt_root = &state_data[SMT_ROOT..SMT_ROOT + 32];
+
+    // Reject proofs against a stale root
+    let valid_until = root_valid_until(&state_data);
+    if valid_until != 0 && Clock::get()?.unix_timestamp > valid_until {
+        msg!("SMT root expired at {}", valid_until);
+        return Err(ExclusionError::RootExpired.into());
+    }
+
+    // Optionally restrict who can invoke the transfer (no or approved wrapping programs)
+    let flags = state_data[TRANSFER_FLAGS];
+    if flags & (transfer_flags::REQUIRE_TOP_LEVEL | transfer_flags::REQUIRE_APPROVED_CALLER) != 0 {
+        check_caller(program_id, instructions_sysvar, flags, &state_data)?;
+    }
+
+    // Verify the public witness contains the correct SMT root
+    // Witness format: 12-byte header + smt_root (32) + pubkey_hash (32) + recent_slot (32)
+    let verifier_flags = state_data[VERIFIER_FLAGS];
+    let witness_smt_root = witness_field_be(&witness_data[12..44], verifier_flags);
+    if witness_smt_root != stored_smt_root {
+        msg!("SMT root in proof does not match stored root");
+        return Err(ExclusionError::SmtRootMismatch.into());
+    }
+
+    // Verify pubkey_hash in witness matches the prover's pubkey
+    let witness_pubkey_hash = witness_field_be(&witness_data[44..76], verifier_flags);
+    let computed_be = pubkey_hash_be(prover.key, state_data[HASH_SCHEME])?;
+
+    if witness_pubkey_hash != computed_be {
+        msg!("Pubkey hash mismatch - proof is for a different pubkey");
+        return Err(ExclusionError::PubkeyHashMismatch.into());
+    }
+
+    drop(state_data);
+
+    check_recent_slot(
+        &witness_field_be(&witness_data[76..108], verifier_flags),
+        config.max_proof_age_slots,
+    )?;
+
+    // CPI to ZK verifier program
+    msg!("Verifying exclusion proof...");
+    invoke_verifier(zk_verifier.key, proof_data, witness_data)?;
+    msg!(
+        "Exclusion proof verified - {} is NOT blacklisted",
+        prover.key
+    );
+    Ok(computed_be)
+}
+
+/// Expand a proof whose points are compressed to the 388-byte layout the
+/// verifier takes, using the alt_bn128 compression syscall
+///
+/// Points use the syscall's big-endian compressed encoding (as produced by the
+/// `solana-bn254` crate), not gnark's, whose flag bits differ.
+pub(super) fn decompress_proof(compressed: &[u8], proof: &mut [u8]) -> ProgramResult {
+    // Circuits with one commitment, as sunspot produces for these circuits
+    if compressed[128..132] != 1u32.to_be_bytes() {
+        msg!("Compressed proof must carry exactly one commitment");
+        return Err(ExclusionError::InvalidCompressedProof.into());
+    }
+    proof[256..260].copy_from_slice(&compressed[128..132]);
+
+    // (compressed range, uncompressed range) for Ar, Bs, Krs, commitment, PoK
+    let points = [
+        (0..32, 0..64),
+        (32..96, 64..192),
+        (96..128, 192..256),
+        (132..164, 260..324),
+        (164..196, 324..388),
+    ];
+    for (from, to) in points {
+        decompress_point(&compressed[from], &mut proof[to])?;
+    }
+    Ok(())
+}
+
+/// Decompress one G1 (32-byte) or G2 (64-byte) point
+fn decompress_point(compressed: &[u8], point: &mut [u8]) -> ProgramResult {
+    #[cfg(target_os = "solana")]
+    {
+        // ALT_BN128_G1_DECOMPRESS and ALT_BN128_G2_DECOMPRESS in the syscall ABI
+        const G1_DECOMPRESS: u64 = 1;
+        const G2_DECOMPRESS: u64 = 3;
+
+        let op = if compressed.len() == 32 {
+            G1_DECOMPRESS
+        } else {
+            G2_DECOMPRESS
+        };
+        // SAFETY: `point` is twice the compressed length, the size the syscall writes
+        let result = unsafe {
+            solana_program::syscalls::sol_alt_bn128_compression(
+                op,
+                compressed.as_ptr(),
+                compressed.len() as u64,
+                point.as_mut_ptr(),
+            )
+        };
+        if result != 0 {
+            msg!("Compressed proof point is not on the curve");
+            return Err(ExclusionError::InvalidCompressedProof.into());
+        }
+        Ok(())
+    }
+
+    // Off-chain there is no syscall, so decompress as it does
+    #[cfg(not(target_os = "solana"))]
+    {
+        exclusion_common::witness::decompress_point(compressed, point).map_err(|error| {
+            msg!("Compressed proof point is not on the curve");
+            error.into()
+        })
+    }
+}
+
+/// CPI into a sunspot verifier with `[proof][witness]` as instruction data
+///
+/// Verifiers take no accounts, so on chain this calls the C ABI invoke syscall
+/// directly with the data in a stack buffer. `invoke` would copy it into the
+/// instruction's `Vec` and again for the runtime's stable layout, on a heap the
+/// bump allocator never frees.
+pub(super) fn invoke_verifier(
+    verifier: &Pubkey,
+    proof_data: &[u8],
+    witness_data: &[u8],
+) -> ProgramResult {
+    // Exclusion and tree-transition witnesses are both 108 bytes
+    let mut data = [0u8; instruction::PROOF_SIZE + instruction::WITNESS_SIZE];
+    data[..instruction::PROOF_SIZE].copy_from_slice(proof_data);
+    data[instruction::PROOF_SIZE..].copy_from_slice(witness_data);
+
+    #[cfg(target_os = "solana")]
+    {
+        use std::ptr::NonNull;
+
+        /// `SolInstruction` from the runtime's C ABI
+        #[repr(C)]
+        struct SolInstruction {
+            program_id: *const Pubkey,
+            accounts: *const u8,
+            accounts_len: u64,
+            data: *const u8,
+            data_len: u64,
+        }
+
+        let instruction = SolInstruction {
+            program_id: verifier,
+            accounts: NonNull::<u8>::dangling().as_ptr(),
+            accounts_len: 0,
+            data: data.as_ptr(),
+            data_len: data.len() as u64,
+        };
+        // SAFETY: `instruction` and `data` outlive the call, and the empty
+        // account info and signer seed arrays are never read
+        let result = unsafe {
+            solana_program::syscalls::sol_invoke_signed_c(
+                &instruction as *const SolInstruction as *const u8,
+                NonNull::<u8>::dangling().as_ptr(),
+                0,
+                NonNull::<u8>::dangling().as_ptr(),
+                0,
+            )
+        };
+        match result {
+            solana_program::entrypoint::SUCCESS => Ok(()),
+            _ => Err(result.into()),
+        }
+    }
+
+    // Off chain there is no C ABI syscall, so go through `invoke` and the
+    // installed `program_stubs`, where tests stand in a verifier
+    #[cfg(not(target_os = "solana"))]
+    {
+        let instruction =
+            solana_program::instruction::Instruction::new_with_bytes(*verifier, &data, Vec::new());
+        solana_program::program::invoke(&instruction, &[])
+    }
+}
@@ -0,0 +1,419 @@
+//! On-chain Sparse Merkle Tree maintenance
+//!
+//! Recomputes the SMT root for a single-leaf update using the Poseidon syscall,
+//! matching the circuit's `compute_merkle_root` and the client's `SparseMerkleTree`.
+//! All field elements are big-endian, the same encoding as the stored root and the
+//! gnark public witness.
+
+use solana_poseidon::{hashv, Endianness, Parameters};
+use solana_program::{msg, program_error::ProgramError};
+
+use crate::ExclusionError;
+
+/// Default tree depth (must match the circuit's TREE_DEPTH)
+pub const TREE_DEPTH: usize = 254;
+
+/// Tree depths the program recomputes paths for, stored in state (`tree_depth`).
+/// Each needs the circuit generated with that TREE_DEPTH (`just smt-depth`).
+pub const SUPPORTED_DEPTHS: [usize; 5] = [20, 32, 64, 254, 256];
+
+/// Deepest supported tree: one level per sibling bitmap bit
+pub const MAX_TREE_DEPTH: usize = 256;
+
+/// Empty leaf value
+pub const EMPTY_LEAF: [u8; 32] = [0u8; 32];
+
+/// Leaf value for a blacklisted key in a tree of TREE_DEPTH or more levels
+/// (1, big-endian)
+pub const INSERTED_LEAF: [u8; 32] = {
+    let mut leaf = [0u8; 32];
+    leaf[31] = 1;
+    leaf
+};
+
+/// Value of the leaf holding a blacklisted key in a tree of `depth` levels.
+/// Shallower trees share leaves between keys, so theirs hold the key hash,
+/// which the circuit checks a key's leaf doesn't.
+pub fn leaf_value(key_hash: &[u8; 32], depth: usize) -> [u8; 32] {
+    if depth >= TREE_DEPTH {
+        INSERTED_LEAF
+    } else {
+        *key_hash
+    }
+}
+
+/// Decode a 64-character hex string into 32 bytes at compile time
+const fn hex(s: &str) -> [u8; 32] {
+    const fn nibble(c: u8) -> u8 {
+        match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'f' => c - b'a' + 10,
+            _ => panic!("invalid hex"),
+        }
+    }
+    let bytes = s.as_bytes();
+    let mut out = [0u8; 32];
+    let mut i = 0;
+    while i < 32 {
+        out[i] = (nibble(bytes[2 * i]) << 4) | nibble(bytes[2 * i + 1]);
+        i += 1;
+    }
+    out
+}
+
+/// Root of an empty subtree at each level: DEFAULT_HASHES[0] = empty leaf,
+/// DEFAULT_HASHES[i] = poseidon(DEFAULT_HASHES[i - 1], DEFAULT_HASHES[i - 1])
+pub const DEFAULT_HASHES: [[u8; 32]; MAX_TREE_DEPTH] = [
+    hex("0000000000000000000000000000000000000000000000000000000000000000"),
+    hex("2098f5fb9e239eab3ceac3f27b81e481dc3124d55ffed523a839ee8446b64864"),
+    hex("1069673dcdb12263df301a6ff584a7ec261a44cb9dc68df067a4774460b1f1e1"),
+    hex("18f43331537ee2af2e3d758d50f72106467c6eea50371dd528d57eb2b856d238"),
+    hex("07f9d837cb17b0d36320ffe93ba52345f1b728571a568265caac97559dbc952a"),
+    hex("2b94cf5e8746b3f5c9631f4c5df32907a699c58c94b2ad4d7b5cec1639183f55"),
+    hex("2dee93c5a666459646ea7d22cca9e1bcfed71e6951b953611d11dda32ea09d78"),
+    hex("078295e5a22b84e982cf601eb639597b8b0515a88cb5ac7fa8a4aabe3c87349d"),
+    hex("2fa5e5f18f6027a6501bec864564472a616b2e274a41211a444cbe3a99f3cc61"),
+    hex("0e884376d0d8fd21ecb780389e941f66e45e7acce3e228ab3e2156a614fcd747"),
+    hex("1b7201da72494f1e28717ad1a52eb469f95892f957713533de6175e5da190af2"),
+    hex("1f8d8822725e36385200c0b201249819a6e6e1e4650808b5bebc6bface7d7636"),
+    hex("2c5d82f66c914bafb9701589ba8cfcfb6162b0a12acf88a8d0879a0471b5f85a"),
+    hex("14c54148a0940bb820957f5adf3fa1134ef5c4aaa113f4646458f270e0bfbfd0"),
+    hex("190d33b12f986f961e10c0ee44d8b9af11be25588cad89d416118e4bf4ebe80c"),
+    hex("22f98aa9ce704152ac17354914ad73ed1167ae6596af510aa5b3649325e06c92"),
+    hex("2a7c7c9b6ce5880b9f6f228d72bf6a575a526f29c66ecceef8b753d38bba7323"),
+    hex("2e8186e558698ec1c67af9c14d463ffc470043c9c2988b954d75dd643f36b992"),
+    hex("0f57c5571e9a4eab49e2c8cf050dae948aef6ead647392273546249d1c1ff10f"),
+    hex("1830ee67b5fb554ad5f63d4388800e1cfe78e310697d46e43c9ce36134f72cca"),
+    hex("2134e76ac5d21aab186c2be1dd8f84ee880a1e46eaf712f9d371b6df22191f3e"),
+    hex("19df90ec844ebc4ffeebd866f33859b0c051d8c958ee3aa88f8f8df3db91a5b1"),
+    hex("18cca2a66b5c0787981e69aefd84852d74af0e93ef4912b4648c05f722efe52b"),
+    hex("2388909415230d1b4d1304d2d54f473a628338f2efad83fadf05644549d2538d"),
+    hex("27171fb4a97b6cc0e9e8f543b5294de866a2af2c9c8d0b1d96e673e4529ed540"),
+    hex("2ff6650540f629fd5711a0bc74fc0d28dcb230b9392583e5f8d59696dde6ae21"),
+    hex("120c58f143d491e95902f7f5277778a2e0ad5168f6add75669932630ce611518"),
+    hex("1f21feb70d3f21b07bf853d5e5db03071ec495a0a565a21da2d665d279483795"),
+    hex("24be905fa71335e14c638cc0f66a8623a826e768068a9e968bb1a1dde18a72d2"),
+    hex("0f8666b62ed17491c50ceadead57d4cd597ef3821d65c328744c74e553dac26d"),
+    hex("0918d46bf52d98b034413f4a1a1c41594e7a7a3f6ae08cb43d1a2a230e1959ef"),
+    hex("1bbeb01b4c479ecde76917645e404dfa2e26f90d0afc5a65128513ad375c5ff2"),
+    hex("2f68a1c58e257e42a17a6c61dff5551ed560b9922ab119d5ac8e184c9734ead9"),
+    hex("1102d2f8db05e4af4842e8ad3d85ed45eb28447eb7212235a2281d5ab5d81d11"),
+    hex("2af8c1caf560dd41f997a01ff895b21e0d1fedb786e7cad29901e12310638bdc"),
+    hex("011d923bc14b5a13972ac7dfe6420b15b0425c98ba80edaf5e0291b4a265e0a5"),
+    hex("224ccc25981822d4c5b6fc199fbc74828488741c7151a6159ecfaab7c2a8bac9"),
+    hex("27e839f6f555feae824ab433e34b1c0e64ce5c75962dffc13c68571d6b4a610e"),
+    hex("2aba203fbd04bfabc86b4d50d6abadc3c24f37efa00e700ef4d17764c2ccd57c"),
+    hex("11eff4f60c2cdcc548c1e0770c3d64b49c01e34da4af29cfea575a19befa669c"),
+    hex("1334fa4b552f00ef403361c935c1abcf896851003c40daa93bb0fd0a0bb9a881"),
+    hex("095465e700400e42508ff83ef3301c29548494a950d06a7e292d470578d03502"),
+    hex("2f8e86e00c5d50b519c70ecd13857ab4168a5a196005a24e01095cf35ebbca36"),
+    hex("23f9a91bd3f701120d161fc2977dd22725c30a9cfd197874184c4930251f5ae8"),
+    hex("1a6be2423333965b4780e29f3cbe0139e6d50a4d1f5c9ca30f04f2e86d944cf3"),
+    hex("1d38d91341feeb0e4c65d1fd5b9b6fae9d23270be2454700b29a16075094a539"),
+    hex("1791e53e02ea5d543426c549b8eedc0e9155e28c7ce82fff570b57e3ad942bb5"),
+    hex("13c87672e2e42af99431a3b87069a2a807edde5f5fc0ebc1e232b35462103fc5"),
+    hex("2560b1549e9ca7ccc6156bb4cf08d297c813a76bdb76eac625a469e8709ea347"),
+    hex("1103fc9acff0623d43ad6fb880ad407c6db5cac40d8f2e613e8cc96002d26764"),
+    hex("1c6b6e4c49e573fedc43f5eb38bc8ee22ec869898f8c53a24d9f08f4089c96fc"),
+    hex("0f2935c857a6eb5fb53acb90647bdef214b4bc44ae8f938be315665cdc35814f"),
+    hex("0f757df13d72190ea40a31e8fca3153e7ae1c2d838e883cfe7a879bb497ebdde"),
+    hex("00bd3f60b7a998f4df37484e0d82957b7c073f117a75bd12c78835a9f8424eee"),
+    hex("2e140381064d1bc4a03fba2fa77a96fd34483494a3bc67c817ff08af047bf03b"),
+    hex("2bf4f9eb8cd5603970dd6be39cafeb453fdac17ee7a58ee6f0dc3b1ab7a108ec"),
+    hex("2d350d01a57bc8b9ead45a63a80283fa9117deef1ce62db78c47fb1258043cd3"),
+    hex("2be2aca62bd64ff43e9205b872d3b72257ab0c9a55fb38b3c8e6d2d1d674711d"),
+    hex("0d3b12bb8d9b88193f04560f180e301faaa7a6eb4ceaeb6dc566e9f564d5db58"),
+    hex("1a6534d9b5b4eebdfb455c403d79982c7b62f030ed1dc17f6516856e1c8317fc"),
+    hex("0ad0c4b5f9e7ab0313935afa436a550ceb566d032b8f02a9a6de7483be2aea63"),
+    hex("0b2fe4bc9a7d7bb04c3e258b26107a2f4152383381d385197b3a06e6b5e7e6b3"),
+    hex("228871f617790cb6aca82625f1171477fb9c7e491d802a26dee25e4a8ea80cc7"),
+    hex("033aad2d7c550a01daa7efafc0eab3dcd7a52d1e124f44e2d18afebfbdff39e9"),
+    hex("217a61f1e65f5e1332c7fa259b7864875e5e2f9909249288ae139d95ff143f11"),
+    hex("0df83fafe180e7279620ac76d417ed7fc98efd75beaf9ca8dba1d21a6f0237fd"),
+    hex("093f4b8efa0a58bf250fdb5b85446cf60a436177bb8b70eabf25b52e41da7e0e"),
+    hex("1b234777c11332dd4eb0bfbe89f86f8ac77b11592fe8422399694a87363883ec"),
+    hex("304c60f6ec421e3d88b66dd8c95ff741e481b1755528112423b8f23efd2f0da6"),
+    hex("06e500455af2ab00bb5c83f35f22fef36b330bdcda565fd41335e5eec33e781f"),
+    hex("2e3cee276e180c16d8503cdb23470b3b91bd360827d3ac33dc5bd6b2a2029b4e"),
+    hex("126bbe4b5c03c41d60b2896fa87cc07456f707517dfa023eb8d7d924f9cfa118"),
+    hex("02694e2e920537f98f2ebcfcc01397cc1c46c538a3ce277e21d8d1c16b2f0c01"),
+    hex("1d80237758dd2c92f5d2805256cf515a2b62b4acf693c8e60f3c79181db7daa1"),
+    hex("10f6b1c3bbb7997d4e5a0556a02cd5b0ce6e0b421822d14681672bc9e4578103"),
+    hex("169f310b8e041dbc780b08414f869ec96abef3a10aa51c4cd7888498c85bdf18"),
+    hex("2f52e5a105d1a3644c358b0385b0adeb89ea9a1c3cceef435eb7d1a981e27c8a"),
+    hex("2c33663bdd9e3692f49888e7b485beb95911c6fb25267af1f216282963e7c702"),
+    hex("20b48b51dbd15c51216d21de1788f36eb214a41c3294bc7e1bab086bc6b05ae9"),
+    hex("113181ab113684b3be85f66062baaddd3a9ebae6a0f915e876ec4c304c987072"),
+    hex("1dc3b032c74b4ce7a8b30b4e27efdc39e74c72d1ca6f55fc4cdc532a2a036faf"),
+    hex("0e725a4e049f5c0bddf7ab45fb066b34ee7b5582ab05dfecb9db89402cb16613"),
+    hex("13ea84661ad34938e57bc9f28a80a4bc5e7d641e73e475866806548ad26ab6b7"),
+    hex("27780415da609ba7d42712bf36e1608d682f2a24ae92c24d5e62d48d8b0cc7c3"),
+    hex("23c3bae36677f468e5c380d975362e88b64b405b9dd90b6d517d468a52b954b0"),
+    hex("060f3cd3fcd8450351c10f2be6d318c3ee19f19b8e0d4fe5f8ab597418cec3fc"),
+    hex("1a10e439f89a80835c3a59b67047645bec4732ca20ce62cd4e76cf3dda968b71"),
+    hex("0bd2a119a607a0ee3ece8e6be64c8954e887df283ab29113a57a3108e25e863b"),
+    hex("15a0cd47e243e86684bb6df32503c3f9eca80c1759f060d835b1efdb206a483c"),
+    hex("16beb4758ec8ca756e1501d928354218e91095cf2eb9f4a8451ba6182c3cb2a4"),
+    hex("1e7479540824ffac6f0b5dce1d2552533c15e140cac4c27c72cf6adbc5e86619"),
+    hex("20cad5dca64ecafebf04fe3e3cd576319f5b47d8aca87a9be6b312752e1d1c29"),
+    hex("244eadd558b9b1e8e047acbe284844a6fbd49a3ab7be389dc1fd5d8d354627ca"),
+    hex("29c769cefdefd754fcedeb0120f96fe316f3b9248b9b5739a0da6ce77aa2b7b7"),
+    hex("2258239cefc66be8309b2aa6352481fa2c1924758b544e68d543ee59fb8f8170"),
+    hex("2e9437cb2fcddaa69f049c125952ed75655586b7ae559182b709c32140ffd787"),
+    hex("11d2afd5ff7946d0516792a9a387452be828debd39baf7e1e4c8023e72fa1635"),
+    hex("0bc15b1a9d9a4c5fc7c55a2a2f4809e91fa76ce64d5bd4dbe693221ec23ffded"),
+    hex("0aad4de23a9447b2b764145af72ba1b13f9f2e4009c520b80d098e9c1abf16e3"),
+    hex("06d71bfd44bbb6812f2d4d9b7772ed228586268c95e3f7ea6900abd1af4a33f7"),
+    hex("08d4fec7e72e0ffe0ac63f27a692c1def60b7b7c98625350044565687b9c0343"),
+    hex("27d72bb2023bc5b7d59b4e15668e709b8cb5a5a645e3f802d92f6bfd413cc1c7"),
+    hex("27ca7af7fbbeef5eb8b40d4291efe671f852f94611e5120116ce5c97306fee48"),
+    hex("27db8bf2411040bf086e90553eac2403728e44ac15f516569da98b219d80e866"),
+    hex("305f8f24068304bd3bc11bf22e66b412df99798e593cafa330699003c52a3402"),
+    hex("24b47b17377eb4aacacc0dd94df9749e2750aa773fa237b2f46381ba0cae3011"),
+    hex("2ac60d2dcb1211c67c8f7f8fac5432395c1a5b20d86cf76eac34a8a02fca1607"),
+    hex("2686b83e657cbf3ae86b1887f72a53df3f8d9e9b7d69cff83f7507473a0d9c30"),
+    hex("1b597ca7d621de05c3c5d1db9dbdb9bebd0e1c3d714476732ffe99e53759b712"),
+    hex("26db536f4d078dbb0da057a7c4920b34de3b7bfc4622d72b358e99d1305bdc56"),
+    hex("0cc5d49c74b92dde0272d403821d19dab9fc290a2735783f5b7291ad646d0cc8"),
+    hex("25b2ed30a750f22f21de6607ea4a9b31fd8160b01f1af544855f2e47ca2d3acc"),
+    hex("24d5ca324c8d8a8b77c7b8dfa749ef0eb8ca4dc441f2a3cb7365d19da96ebe36"),
+    hex("01f5877aa580f7d282f5e8fe55897e39c66d382c0afba588ac68716bea22b164"),
+    hex("2afa07c1d7e0f1b62977e1e395ff99202ae2a390e977455fed457e242233140c"),
+    hex("0d8423a2e4ee2fdfa563394c0ef07cd908f76941c88b8797053595b15a1f694c"),
+    hex("21e17b7a0dc6839c631cfdc9a9314f4e482e764b886d6b17346bab89a060d882"),
+    hex("2488d41fc981a6120baad7e54149033f8fe58d9eb8d61608d80a66ab30930cf7"),
+    hex("11c2ecf1e08cd24d987a4fc0234012b1e19d943d5e56c64b594a825d4fc6feeb"),
+    hex("2d4324cf982b4b0cee4e431edd552f8dc6b334b541f8ccc1137d7a59a38bce9b"),
+    hex("266279272352737c7941bb72caa5c8f78f3854adbacff3fec792f03e12475d3b"),
+    hex("103266bbc8ab98269b21a03f39ee4cf714b088f74c6ee628f3baacb2fc520a77"),
+    hex("029b396bdafaa0f9decfe7b46b9e5553348e1dfd2896057427d204b01b4eef71"),
+    hex("18a2373c9a012696d52067f20ab687bf2e626e371b4edac577cde77eac24a887"),
+    hex("15875f2e5cb10fd3e775575213a8ac03d1d61c85de096184e8b625a8e7393327"),
+    hex("2f2079f3b5b2009511145802d3287adf54e02944a28bcfec1fab5a2655e5710c"),
+    hex("17d246b291227a5d9ca413b7d9293da766cdd68063f3796cc7ca4a191e8b12ff"),
+    hex("2876f88c8cf4fa7bbd68668860eb6b8fc37882a050a8e70745a73f6ac66a88e4"),
+    hex("191d14df3bd6bc5fd14b5ca14c60e5ee8dcf3c7d7cf9f421fb3e92312ec2ae7a"),
+    hex("14935d601de8d6fbd04b084de8f8510dd25b5ed1d80ab5382b8f908dc0734b3a"),
+    hex("280d3b6cc72d32a8821b98bd80683dfa206fb5f440200e8d356dc74e7def0197"),
+    hex("0442f11a6fd5e21935722bf39fe83ecb69311953d0030ed993f26bffd26c7f3a"),
+    hex("2aad1d3f97958285ed7157d9afd1e5350d69392ec61777ec17f67df7d498594d"),
+    hex("11e08aa56f036675f04009e7de651922d6cee17e275d9cab84ed82bba7311d1e"),
+    hex("22e2fa9c438b48750f280097c922afcd2738d5f20bf63aae63f08a364365fa6a"),
+    hex("0630bc4fafaf0a6b3a5fa5843599d0522426c0369e6c554375d257f635bafe19"),
+    hex("2ea4923587848ef8ef9503b50a7b653bfbecbbd3a7634241865fdd9788581255"),
+    hex("0cb19c08f874d972f76dda4610d79adf95ac2931114591e2d1ebb5c5e591251e"),
+    hex("28486750cb3972380315d5f97aeae10355dfbe70ae965cf5276341e75637010e"),
+    hex("065bad4bd161fa331970387cf4e0e2aca78fc939f54653524312b2b9ecbcf67b"),
+    hex("0b13c22302d0ec7672fb1f54faf47d5a4c85a2d1feaae106552f7134e006f3d5"),
+    hex("2d7d2a0391748d8c8ef41c9dd0f6d3a2922db437391cbe1197be2ed196b4457f"),
+    hex("2aecf58eab132a0d395696737fc95f975e085818b45304c9437401ee5143f052"),
+    hex("0e601763ac46f0fc4fb2cc14d1d54fc43b07787608c2cb3550caae34e2d8e258"),
+    hex("1be72fe719c1d90d9a9f93e87d983fd51d7497bf88cbd437906c1287058be162"),
+    hex("185a0a77b6c9c3001259153d8e6c2c4b34e80197a41e69fa45e40a618f8bbcda"),
+    hex("0d4e063fd65ef8cc8ada36d22d905306f3aea0e44d61fa811a4a005e00550aaf"),
+    hex("0b7c11ec8ed3cfe25ae7fb3c3722576c459473d1f512ead4c63e26e70be12bd2"),
+    hex("235c44d1a77d459d98e3af0a7e55eb1a3f2e702ee34c9e64fda513b3599ce322"),
+    hex("2d70ac32519f2bdc29091262631aad57e21f8a2a74bab1101f2f7b57a54b25cc"),
+    hex("19afb658f804063e7ad00e318c27cebf4416b7ae78d842847e2cbc5c5404ab06"),
+    hex("2682a3ae14c59e2fed90362edd60ae405f14e6355cf0eec942ac1aaa609d614c"),
+    hex("1473bfe72fe963657f8efd5f739af0c5c66823547caa048cdd503484ccc5c9bd"),
+    hex("10c7a72ac9a77635b3081cdfee930e862678a040027e0618d0c1edaa6cecfd3d"),
+    hex("0ba249df3c9d0468183a277306e43fb37a057074d490ad26d710e36c6f4da526"),
+    hex("051ac14810b874b6de21b39a1673e621f98504bf51eff49769ca050a93c3bd0a"),
+    hex("2371fb11bc5fff7723eccdabf6251caffcfb186ccdfdcfc973e1d2ef8ae8b476"),
+    hex("0427aed8455d7317318d438d5eed5077ea9899b3007156c291ccbabad77438dd"),
+    hex("0d03e86224104550beee8f32b89500f491975059989af529d6a950067bfec431"),
+    hex("28904fff546ed6840b56f81c63b6d2f25c87157a1663aa25672426c7f8bfbdf2"),
+    hex("28180793b764369e9f836ff9b58a824abb0b1346b37e110797016482e9efcb90"),
+    hex("13e30a7166c6d6680a74b0e1659e646dffa30244912927562ff2271de1c6f3fe"),
+    hex("1a278944d6a29b7fd1baf39705dba8d36292056940072562c0ec74774ba7f686"),
+    hex("199adce2956ef1cc7cb4064dcaa12750b526e5e328e677b9a548beb3c9772f70"),
+    hex("1e3ca54eef30cdb63e52e45ae1911b47dd99a640bd8268e3ba044cdc210c29db"),
+    hex("0999e8c54428c4f9ee80a9f8efd8c5b4c9984287a9c4377bdfe62a7721fa1684"),
+    hex("04360d5d8ca8aa345231b07fcc324a762fcff1591527547babf6ec2612d4bfbf"),
+    hex("2eb9af1fed1049ea89a3eaf3677a2ad6329e323a9bed5aba0100a8e8b2377d80"),
+    hex("1596a4c622cbf5f0f838ed471c22bdfdc08705228fd5e923d0388f9fd59114ae"),
+    hex("1a9741e26d95c6878004379613a208fda391fb0550a2a42765fd5b7d10df6bcb"),
+    hex("26c35721be882b912684ccd4f8c76c8eb1e44bacd49f376fde13ac830c2c7b49"),
+    hex("039c5a58b60d648eef1eee90eff3da4023156288e1c42092d395d1a681991527"),
+    hex("1530cfb19b2988d33004c9397a8275d2c96da8c80f775be2b3acedb10bb4442f"),
+    hex("1ee76dfa7ebdcdaa49fd7026abd38bf2fdd23b1c1788ef8969e0cb4feefe7f86"),
+    hex("0e175a60cb72ce9554337d853ecc25b0a330a38dd1630cb26d6dfd394e252701"),
+    hex("0ef16063e8ae67754f9c79f8e553aa65b794d226d40d157a1faa21708daf47cd"),
+    hex("13c697754916b7a5f62cc01c502913d894fdcb9b24a608123b1b8e8e9e86ad6c"),
+    hex("1f34dab5ca5bfe37ce4f529c9a15dcea77119bfe391a053ba903360cf6d70922"),
+    hex("1b62ba9c417143a85080825ef43b5b5ed49118b02ff5313fd9b0725e3efe3dc6"),
+    hex("2e593c633966142cca4af6e2e126606534629481a6850a772c0f2f47579dcecf"),
+    hex("23354213e2d3df8ed30196c1447ba6821d1e8e0484c5263516ba4a78d5bd5d1b"),
+    hex("2b37b83746e0ed499247229306428ec184decadc09a227f777932aa8d74abda0"),
+    hex("1126b7dcd68388181f90e30f1be042ee4d88dde8ae69ea0a951b795f004a68ce"),
+    hex("0b274272b11f917915bb62dc37c3396db6a1a9e80a61e072020d1388c7a25ffe"),
+    hex("2ea7eb536d6501a22b6b1b00cdab32d7bf6f2809a2abae64899ca1cb68789a7c"),
+    hex("0b1f0ea61565419752f7faaf1162c96b760e14fd498cddc09e22825318e040fe"),
+    hex("0753627d2714f0544802a76bde19da35c878acee7f2f4ca3bf44b9fe0eec0e9c"),
+    hex("27144fd91f5dce1612a120b0e7d6d6d984476c4ee0186b1cd62ee4e0b62ccf54"),
+    hex("0fa2cc18e5f931cc1419bd0389affe25dfe7e6ca152b30240c8f554d4fd034a9"),
+    hex("03521ce9c7646ff3d0fb9e94658230f6543c565304aee4e1a9f2b4ec2ac3a302"),
+    hex("10cc77694c505b684501ecb7108682fc60afc673573eeb64d53e375a74ebb5a7"),
+    hex("2e40682c00cf082db2bc998d220a908687c8f83473d0ffd66aa0d30321c32ccc"),
+    hex("2b5f33c70aad65566b268d19bc79e27eec8bfe01a2044cc4faeefc08346b4674"),
+    hex("10f3dd45ecf58af8de4b6b21153d1d2c15270b6a01d469ccd03a93213333820e"),
+    hex("2aea33920c93a53c052ffe8b758b3151740ffb9aa636989f09253704bbe524e4"),
+    hex("29c85386839d6b751937959db3213d2b6653f1a71be8bd1d5414ea95a2aa6325"),
+    hex("22bcfda134e4049fc42504f32aa30ca9a587cfcce18d6a59e232428fa7755f6c"),
+    hex("24c668653d5174dc37ccfffa9af8912cdd211b2821c724b21b2e78454c790d8c"),
+    hex("2406628c2e65c3a9b0287b95a8b428f0d0c306b016132f5d90592d85dbdba44c"),
+    hex("174c4c80a56630c2030f595c51572d79261282be1ede964a50381cd850aaafa2"),
+    hex("086b4acaf40f36be0a3a2b4ef06eddc2a757671b21a8993bbf6396bfc5ffdc88"),
+    hex("0cc5210c045bcbe9eada5007493d4b62f04358c1e8bc6ab985c82cdf02ffa176"),
+    hex("05e1bde5655e6df6a54caaea5cabe204e12f5d291ecf4853f3fa53a05529dd66"),
+    hex("1422679a89cb624e08fe3a5f7ce44cb97822b003682a0e0090de9a6c0c786631"),
+    hex("21cf5ec9cf99638792d3b8fa7a64a3df3aeaa047a07e78a27ab64bb07733b9f8"),
+    hex("26beda430d84bb32db4929f73736aa5487da181d3c33add76a464fbbd80e3950"),
+    hex("02e65d6609919f8f75278efe42d4f2ab36eb8e5041ac1a355ac399795ca907ea"),
+    hex("01bbd009ca7046c755969f82c32f0f9ab6052db93ffdf521b41d270f5c008c15"),
+    hex("0d87dce55fd09f874f274cfe230dcbad9b9134a268c2a4674f8e41e8603a9c59"),
+    hex("2ee61774f1e890f6c75c2de31a8e6e45c1d155f29e883fa57d2293cc44a80d1a"),
+    hex("20c44157abea3923db17e92ddfa12e4478bad9f31abf84af6e4a3c8cb0bb224d"),
+    hex("0e39a6177553a5f5d22dc594f67942dcf1a9091a29655e5715c65e2a9efe2da2"),
+    hex("0c502db48070a4a2d8203107da9185b3f77ec7c7db51cefa5833b60e854c5240"),
+    hex("075bd526054b06f28160a153fd6739a77fb7941a5f1f627a92558c2bb2e621a7"),
+    hex("1c1473eca4972734f45cada131b3f2857d0940a5a6a99087129f376fe697907e"),
+    hex("265cb0ac4ea34fc7f7e1a7e6f8db55b7f305e773efcce9e6fc25a0a823264068"),
+    hex("1fd84e844aae06bbf387123cf86563bc658783f663703905ead855f98bbcfab6"),
+    hex("08cd1c63e9878724aa25adc7338d37dfa52e7e524f6ebfb7e11601104b53720a"),
+    hex("15bc7a29363d186cce414fb46a8fb4fe5821d0a03d8d68eefe53ed039220d334"),
+    hex("0398605c7c9d50f7533a8320293a4f23ca02bf337d838a8fc345e29306e6d77d"),
+    hex("1101c4f7b67e9777464abb8ed89a8870cc48365f294b904777ef32f814f08ea6"),
+    hex("161ea4fab9e94589d26e923faf997cf706041803d7674f0ec9d0956e329d7f12"),
+    hex("11ea79651a205c08ae8b2e1a6bf76374f52955401b3ab1a59d0fac92c8fbfb11"),
+    hex("0efb5726b0a5387f2aa20ce7e82e3ce65000f4e3f49ab6cb81812a0772da0e63"),
+    hex("0f2e9b654dfa5e6b88da89b11430ed70edaac183fa34c1c68da6477fe5dd4e65"),
+    hex("1edd204b2911bc0f4062ba340f35cbed8ca12a9c26d820b996b6201688a6db3f"),
+    hex("06a347179b8c593fb88eaf0314ea58f2da4e32f976df1666a2de21e4eb2a85b5"),
+    hex("2949e5092ca7b5a33d78c7955d272bddcd5e5c3104009e677cec3ba652e99c7d"),
+    hex("2744922008123f16528702e08aeb0cccdec4852e2c27cfc6ba628f64b7a9453a"),
+    hex("044670cc2b6f45b05e321fdc18cca7aee55c1e6e1d080633499db6f3ec5e46bb"),
+    hex("2d2f6feff2790e9bf42c438f73d34461d3c9e1a9ff1fc0dc619491fff49f7230"),
+    hex("0a66cb763abbc9769424c60922872940f0f6057372aaec7f2514373714d55ae0"),
+    hex("154e3cc251f5219bebc7e646fd5aab03da83f914537ca1932fc313aa9cf25a6a"),
+    hex("2deaf324c8207fb2bbec908386e45d17651432fb81c9dd07d0e89053bd76a37c"),
+    hex("206301573d7865fa0547f59840211a8352a548c5ee23382f1fdf35aa977c0bf3"),
+    hex("22f1eab184ae20a4a3e61127daaeec5becc93cc92af421874e6b7a17510455d4"),
+    hex("209d3edd951ffd6d583c85666e61d16c4d004965ddcc2e88ad0b210844ffef08"),
+    hex("05575df162b05e4b221c92fe3b7a734809525cf3b6145af6125623c28c24eabb"),
+    hex("27a6faac0f0bf5664c67aa7023d254429c218db4304609a6f9284e02ce690a0b"),
+    hex("1fc29c5d4d74d416da55e07fe3afc57c3fffa887e61d35576a50723666c5ef36"),
+    hex("2d856d7abbf4f3ad209d8f8a0744b2ed9c6fa9d171eb97d529cf6add1c6ce522"),
+    hex("299dae045d39796d3e51951976b4efabfb435087ca89328e66da61025770908b"),
+    hex("132ede13aa968a148a37a7935c713ba9672c576d379a934d3361788a95c30ff0"),
+    hex("2e4836257570bda6e49e6e7aba012f1371b91c5b0cf908686882dab6f73b81be"),
+    hex("2e0bac3d524dd9fa7895c19dd5af4490f0e3f81bc9f7cb9cb9048e408900fe21"),
+    hex("05938147b9477d25727cbc6bd9d6061131f57d858ed7d11ba6f289de4f8ec090"),
+    hex("0e80938e231105fe4097e524ae47b50056a18c72e721998c87e1300c2c31dc40"),
+    hex("2d135beddd00cac3b4eca8f7e981b85ead8b9a57353a43a41fae21b5eee82c82"),
+    hex("2737a9f66ed89e8952e23384a6d7181f7fbeae33a75b3194d73bb1767b0a2fbe"),
+    hex("22d44b21d94ffec06a62b039aaf963907ee2dab59db79f54e2cbf38ca24cc9e9"),
+    hex("027db90d6335121dba15313c56afaa5691288f0c1cb407b2cd69e749bdf4e136"),
+    hex("12a958a0352f39b588fec2c37be4032aaa382ccd590601652121b9aff2968f71"),
+    hex("15270d5473f0912e668bad43191fb29618ce0470608df28086d30ebfb85377d0"),
+    hex("1d9e31b6a3f3121a1bec36549f5a5ad55b91d8a5915b59cdf9f9e4c1484b4316"),
+    hex("1b54e3111a96213460e79f2466af16a85d2456b3b100811287ca4d8b46751974"),
+    hex("15569b65a4967399b866a5d8e00f503b416b6c4bd2f990be80241915724ed8a3"),
+];
+
+/// Poseidon hash of two big-endian field elements
+fn poseidon_hash_2(left: &[u8], right: &[u8]) -> Result<[u8; 32], ProgramError> {
+    hashv(Parameters::Bn254X5, Endianness::BigEndian, &[left, right])
+        .map(|hash| hash.to_bytes())
+        .map_err(|e| {
+            msg!("Poseidon hash failed: {:?}", e);
+            ExclusionError::PoseidonHashFailed.into()
+        })
+}
+
+/// Reject sibling bitmaps that provide siblings above a tree of `depth` levels
+fn check_sibling_bitmap(sibling_bitmap: &[u8], depth: usize) -> Result<(), ProgramError> {
+    let beyond_depth =
+        (depth..MAX_TREE_DEPTH).any(|level| sibling_bitmap[level / 8] & (1 << (level % 8)) != 0);
+    if beyond_depth {
+        msg!("Sibling bitmap sets levels beyond the tree depth");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    Ok(())
+}
+
+/// Compute the root of the tree containing `leaf` at `key_hash`
+///
+/// Arguments are as for `compute_update_roots`.
+pub fn compute_root(
+    key_hash: &[u8; 32],
+    leaf: &[u8; 32],
+    depth: usize,
+    sibling_bitmap: &[u8],
+    siblings: &[u8],
+) -> Result<[u8; 32], ProgramError> {
+    check_sibling_bitmap(sibling_bitmap, depth)?;
+    let mut current = *leaf;
+    let mut provided = siblings.chunks_exact(32);
+
+    for (level, default_hash) in DEFAULT_HASHES[..depth].iter().enumerate() {
+        let sibling = if sibling_bitmap[level / 8] & (1 << (level % 8)) != 0 {
+            provided.next().ok_or(ExclusionError::InvalidDataLength)?
+        } else {
+            default_hash
+        };
+
+        let is_right = key_hash[31 - level / 8] & (1 << (level % 8)) != 0;
+        current = if is_right {
+            poseidon_hash_2(sibling, &current)?
+        } else {
+            poseidon_hash_2(&current, sibling)?
+        };
+    }
+
+    Ok(current)
+}
+
+/// Compute the roots before and after changing one leaf along the same path
+///
+/// Arguments:
+/// - key_hash: Big-endian pubkey_hash; its low `depth` little-endian bits give the path
+/// - old_leaf / new_leaf: Leaf value before and after the update
+/// - depth: Levels in the tree (one of `SUPPORTED_DEPTHS`)
+/// - sibling_bitmap: Bit i set when the sibling at level i is not the default hash;
+///   bits from `depth` up must be clear
+/// - siblings: Concatenated non-default siblings, lowest level first
+pub fn compute_update_roots(
+    key_hash: &[u8; 32],
+    old_leaf: &[u8; 32],
+    new_leaf: &[u8; 32],
+    depth: usize,
+    sibling_bitmap: &[u8],
+    siblings: &[u8],
+) -> Result<([u8; 32], [u8; 32]), ProgramError> {
+    check_sibling_bitmap(sibling_bitmap, depth)?;
+    let mut old_current = *old_leaf;
+    let mut new_current = *new_leaf;
+    let mut provided = siblings.chunks_exact(32);
+
+    for (level, default_hash) in DEFAULT_HASHES[..depth].iter().enumerate() {
+        let sibling = if sibling_bitmap[level / 8] & (1 << (level % 8)) != 0 {
+            provided.next().ok_or(ExclusionError::InvalidDataLength)?
+        } else {
+            default_hash
+        };
+
+        // Path bit from the little-endian bits of the big-endian key hash
+        let is_right = key_hash[31 - level / 8] & (1 << (level % 8)) != 0;
+        if is_right {
+            old_current = poseidon_hash_2(sibling, &old_current)?;
+            new_current = poseidon_hash_2(sibling, &new_current)?;
+        } else {
+            old_current = poseidon_hash_2(&old_current, sibling)?;
+            new_current = poseidon_hash_2(&new_current, sibling)?;
+        }
+    }
+
+    Ok((old_current, new_current))
+}
@@ -0,0 +1,95 @@
+//! The Wormhole core bridge instruction root mirroring needs
+//!
+//! Encoded by hand, like the SPL Token instructions: `post_message` is the only
+//! call, and the core bridge's Borsh layout has been stable since launch.
+
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey,
+    pubkey::Pubkey,
+    sysvar,
+};
+
+/// Wormhole core bridge
+#[cfg(not(feature = "wormhole-devnet"))]
+pub const CORE_BRIDGE_PROGRAM_ID: Pubkey = pubkey!("worm2ZoG2kUd4vFXhvjh93UUH596ayRfgQ2MgjNMTth");
+
+/// Wormhole core bridge (devnet)
+#[cfg(feature = "wormhole-devnet")]
+pub const CORE_BRIDGE_PROGRAM_ID: Pubkey = pubkey!("3u8hJUVTA4jH1wYAyUur7FFZVQ8H635K3tSHHF4ssjQ5");
+
+/// Seed of the PDA this program emits messages as
+pub const EMITTER_SEED: &[u8] = exclusion_common::seeds::EMITTER;
+
+/// Offset of the message fee (u64 LE) in the bridge config account:
+/// guardian_set_index (4) + last_lamports (8) + guardian_set_expiration_time (4)
+pub const BRIDGE_FEE_OFFSET: usize = 16;
+
+/// Guardians sign once the posting slot is finalized
+pub const CONSISTENCY_LEVEL_FINALIZED: u8 = 1;
+
+const POST_MESSAGE: u8 = 1;
+
+/// Post `payload` as a Wormhole message from `emitter`. The message fee must
+/// already be in the fee collector.
+#[allow(clippy::too_many_arguments)]
+pub fn post_message(
+    bridge: &Pubkey,
+    message: &Pubkey,
+    emitter: &Pubkey,
+    sequence: &Pubkey,
+    payer: &Pubkey,
+    fee_collector: &Pubkey,
+    nonce: u32,
+    payload: &[u8],
+) -> Instruction {
+    let mut data = vec![POST_MESSAGE];
+    data.extend_from_slice(&nonce.to_le_bytes());
+    data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    data.extend_from_slice(payload);
+    data.push(CONSISTENCY_LEVEL_FINALIZED);
+    Instruction {
+        program_id: CORE_BRIDGE_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*bridge, false),
+            AccountMeta::new(*message, true),
+            AccountMeta::new_readonly(*emitter, true),
+            AccountMeta::new(*sequence, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*fee_collector, false),
+            AccountMeta::new_readonly(sysvar::clock::ID, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+            AccountMeta::new_readonly(solana_system_interface::program::ID, false),
+        ],
+        data,
+    }
+}
+
+/// Payload of a root mirroring message; integers are big-endian for EVM receivers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RootMessage {
+    pub state: Pubkey,
+    pub smt_root: [u8; 32],
+    /// The state's root change count when the root was published
+    pub root_seq: u64,
+    /// Unix timestamp the root expires at (0 = no expiry)
+    pub root_valid_until: i64,
+}
+
+impl RootMessage {
+    /// Payload ID of a root message
+    pub const PAYLOAD_ID: u8 = 1;
+
+    /// Encoded size: 1 (payload ID) + 32 (state) + 32 (root) + 8 (seq) + 8 (expiry)
+    pub const LEN: usize = 1 + 32 + 32 + 8 + 8;
+
+    pub fn pack(&self) -> [u8; Self::LEN] {
+        let mut out = [0u8; Self::LEN];
+        out[0] = Self::PAYLOAD_ID;
+        out[1..33].copy_from_slice(self.state.as_ref());
+        out[33..65].copy_from_slice(&self.smt_root);
+        out[65..73].copy_from_slice(&self.root_seq.to_be_bytes());
+        out[73..81].copy_from_slice(&self.root_valid_until.to_be_bytes());
+        out
+    }
+}
@@ -0,0 +1,41 @@
+//! Checks the hand-encoded Bubblegum `transfer` layout.
+
+use exclusion_program_example::bubblegum::{transfer, Leaf, BUBBLEGUM_PROGRAM_ID};
+use solana_program::pubkey::Pubkey;
+
+#[test]
+fn transfer_uses_anchor_layout() {
+    let keys: Vec<Pubkey> = (1..=7).map(|i| Pubkey::new_from_array([i; 32])).collect();
+    let mut packed = [1u8; 32].to_vec();
+    packed.extend_from_slice(&[2; 32]);
+    packed.extend_from_slice(&[3; 32]);
+    packed.extend_from_slice(&0x0102u64.to_le_bytes());
+    packed.extend_from_slice(&0x0304u32.to_le_bytes());
+    let leaf = Leaf::unpack(&packed).unwrap();
+    assert_eq!((leaf.nonce, leaf.index), (0x0102, 0x0304));
+
+    // Owner is its own delegate
+    let ix = transfer(
+        &keys[0],
+        &keys[1],
+        &keys[2],
+        &keys[2],
+        &keys[3],
+        &leaf,
+        &keys[4..],
+    );
+    assert_eq!(ix.program_id, BUBBLEGUM_PROGRAM_ID);
+    assert_eq!(ix.accounts.len(), 8 + 3);
+    assert!(ix.accounts[1].is_signer && ix.accounts[2].is_signer);
+    assert!(ix.accounts[4].is_writable);
+    assert_eq!(ix.accounts[8].pubkey, keys[4]);
+    assert_eq!(
+        ix.data[..8],
+        [0xa3, 0x34, 0xc8, 0xe7, 0x8c, 0x03, 0x45, 0xba]
+    );
+    assert_eq!(ix.data[8..], packed[..]);
+
+    // A separate delegate doesn't sign
+    let ix = transfer(&keys[0], &keys[1], &keys[2], &keys[5], &keys[3], &leaf, &[]);
+    assert!(!ix.accounts[2].is_signer);
+}
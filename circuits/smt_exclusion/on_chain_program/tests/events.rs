@@ -0,0 +1,82 @@
+//! Checks that events use Anchor's encoding, so IDL-driven indexers decode them.
+
+use exclusion_program_example::events::{
+    Attested, ChallengeUpheld, Event, RootUpdated, TransferVerified,
+};
+use solana_program::{hash::hash, pubkey::Pubkey};
+
+/// Anchor's event discriminator: `sha256("event:<Name>")[..8]`
+fn anchor_discriminator(name: &str) -> [u8; 8] {
+    hash(format!("event:{name}").as_bytes()).to_bytes()[..8]
+        .try_into()
+        .unwrap()
+}
+
+#[test]
+fn discriminators_match_anchor() {
+    assert_eq!(
+        RootUpdated::DISCRIMINATOR,
+        anchor_discriminator("RootUpdated")
+    );
+    assert_eq!(
+        TransferVerified::DISCRIMINATOR,
+        anchor_discriminator("TransferVerified")
+    );
+    assert_eq!(
+        ChallengeUpheld::DISCRIMINATOR,
+        anchor_discriminator("ChallengeUpheld")
+    );
+    assert_eq!(Attested::DISCRIMINATOR, anchor_discriminator("Attested"));
+}
+
+#[test]
+fn fields_use_borsh_layout() {
+    let root_updated = RootUpdated {
+        old: [1; 32],
+        new: [2; 32],
+        seq: 3,
+    };
+    let bytes = root_updated.to_bytes();
+    assert_eq!(bytes.len(), 8 + 32 + 32 + 8);
+    assert_eq!(&bytes[8..40], &[1; 32]);
+    assert_eq!(&bytes[40..72], &[2; 32]);
+    assert_eq!(&bytes[72..80], &3u64.to_le_bytes());
+
+    let recipient = Pubkey::new_from_array([5; 32]);
+    let transfer = TransferVerified {
+        sender_hash: [4; 32],
+        amount: 1_000_000,
+        recipient,
+    };
+    let bytes = transfer.to_bytes();
+    assert_eq!(bytes.len(), 8 + 32 + 8 + 32);
+    assert_eq!(&bytes[8..40], &[4; 32]);
+    assert_eq!(&bytes[40..48], &1_000_000u64.to_le_bytes());
+    assert_eq!(&bytes[48..80], recipient.as_ref());
+
+    let challenge = ChallengeUpheld {
+        challenger: recipient,
+        pubkey_hash: [6; 32],
+        root: [7; 32],
+        slashed: 8,
+    };
+    let bytes = challenge.to_bytes();
+    assert_eq!(bytes.len(), 8 + 32 + 32 + 32 + 8);
+    assert_eq!(&bytes[8..40], recipient.as_ref());
+    assert_eq!(&bytes[40..72], &[6; 32]);
+    assert_eq!(&bytes[72..104], &[7; 32]);
+    assert_eq!(&bytes[104..112], &8u64.to_le_bytes());
+
+    let attested = Attested {
+        subject: recipient,
+        state: Pubkey::new_from_array([9; 32]),
+        smt_root: [10; 32],
+        slot: 11,
+    };
+    let bytes = attested.to_bytes();
+    assert_eq!(bytes.len(), 8 + 32 + 32 + 32 + 8);
+    assert_eq!(&bytes[8..40], recipient.as_ref());
+    assert_eq!(&bytes[40..72], &[9; 32]);
+    assert_eq!(&bytes[72..104], &[10; 32]);
+    assert_eq!(&bytes[104..112], &11u64.to_le_bytes());
+}
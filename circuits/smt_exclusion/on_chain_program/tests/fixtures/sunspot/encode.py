@@ -0,0 +1,65 @@
+"""Encode proof and public witness fixtures in each gnark release's layout.
+
+The points are BN254's generators and the witness is `Prover.toml`'s public
+inputs, so every file is well formed, but no verifier accepts the proofs.
+Run from this directory: python3 encode.py
+"""
+
+import pathlib
+import re
+
+G1 = (1, 2)
+# (x, y), each (c0, c1); gnark writes c1 before c0
+G2 = (
+    (
+        10857046999023057135944570762232829481370756359578518086990519993285655852781,
+        11559732032986387107991004021392285783925812861821192530917403151452391805634,
+    ),
+    (
+        8495653923123431417604973247489272438418190587263600148770280649306958101930,
+        4082367875863433681332203403145435568316851327593401208105741076214120093531,
+    ),
+)
+
+
+def fe(value):
+    return value.to_bytes(32, "big")
+
+
+def g1(point):
+    return fe(point[0]) + fe(point[1])
+
+
+def g2(point):
+    (x0, x1), (y0, y1) = point
+    return fe(x1) + fe(x0) + fe(y1) + fe(y0)
+
+
+def proof(commitments):
+    """Ar, Bs, Krs, then (since gnark v0.9) the commitments and their PoK"""
+    body = g1(G1) + g2(G2) + g1(G1)
+    if commitments is None:
+        return body
+    return body + len(commitments).to_bytes(4, "big") + b"".join(map(g1, commitments)) + g1(G1)
+
+
+def witness():
+    toml = (pathlib.Path(__file__).parents[4] / "Prover.toml").read_text()
+    value = lambda key: re.search(rf'^{key} = "(.*)"', toml, re.M).group(1)
+    inputs = [int(value("smt_root"), 16), int(value("pubkey_hash"), 16), int(value("recent_slot"))]
+    header = b"".join(n.to_bytes(4, "big") for n in (len(inputs), 0, len(inputs)))
+    return header + b"".join(map(fe, inputs))
+
+
+RELEASES = {
+    # Before commitments: Ar, Bs and Krs only
+    "gnark-v0.8.1": None,
+    # sunspot's gnark: one commitment, for the circuit's range checks
+    "gnark-v0.10.0": [G1],
+}
+
+for name, commitments in RELEASES.items():
+    directory = pathlib.Path(name)
+    directory.mkdir(exist_ok=True)
+    (directory / "smt_exclusion.proof").write_bytes(proof(commitments))
+    (directory / "smt_exclusion.pw").write_bytes(witness())
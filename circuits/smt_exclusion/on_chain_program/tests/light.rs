@@ -0,0 +1,69 @@
+//! Checks the hand-encoded Light system program `invoke_cpi` layout.
+
+use exclusion_program_example::light::{
+    create_compressed_account, derive_address, derive_address_seed, InvokeCpiAccounts,
+    NewCompressedAccount, LIGHT_SYSTEM_PROGRAM_ID, VALIDITY_PROOF_SIZE,
+};
+use solana_program::pubkey::Pubkey;
+
+#[test]
+fn invoke_cpi_uses_borsh_layout() {
+    let keys: Vec<Pubkey> = (1..=9).map(|i| Pubkey::new_from_array([i; 32])).collect();
+    let owner = Pubkey::new_from_array([10; 32]);
+    let seed = derive_address_seed(&[b"attestation"], &owner);
+    let address = derive_address(&seed, &keys[6]);
+    // Field elements: the top byte is cleared
+    assert_eq!((seed[0], address[0]), (0, 0));
+
+    let record = [7u8; 104];
+    let ix = create_compressed_account(
+        &InvokeCpiAccounts {
+            fee_payer: &keys[0],
+            authority: &keys[1],
+            registered_program_pda: &keys[2],
+            noop_program: &keys[3],
+            account_compression_authority: &keys[4],
+            invoking_program: &keys[5],
+            address_merkle_tree: &keys[6],
+            address_queue: &keys[7],
+            output_state_tree: &keys[8],
+        },
+        &NewCompressedAccount {
+            owner: &owner,
+            seed,
+            address,
+            discriminator: [8; 8],
+            data: &record,
+            address_merkle_tree_root_index: 0x0102,
+        },
+        &[9; VALIDITY_PROOF_SIZE],
+    );
+
+    assert_eq!(ix.program_id, LIGHT_SYSTEM_PROGRAM_ID);
+    assert_eq!(ix.accounts.len(), 14);
+    assert!(ix.accounts[0].is_signer && ix.accounts[1].is_signer);
+    assert_eq!(ix.accounts[11].pubkey, keys[6]);
+    assert_eq!(ix.accounts[13].pubkey, keys[8]);
+
+    // Anchor discriminator, then the Borsh-encoded inputs as a Vec<u8>
+    let inputs = &ix.data[12..];
+    assert_eq!(
+        u32::from_le_bytes(ix.data[8..12].try_into().unwrap()) as usize,
+        inputs.len()
+    );
+    assert_eq!(inputs[0], 1);
+    assert_eq!(&inputs[1..129], &[9; VALIDITY_PROOF_SIZE]);
+    // new_address_params: one entry
+    assert_eq!(&inputs[129..133], &1u32.to_le_bytes());
+    assert_eq!(&inputs[133..165], &seed);
+    assert_eq!(&inputs[165..169], &[1, 0, 0x02, 0x01]);
+    // no inputs, one output
+    assert_eq!(&inputs[169..177], &[0, 0, 0, 0, 1, 0, 0, 0]);
+    assert_eq!(&inputs[177..209], owner.as_ref());
+    assert_eq!(&inputs[218..250], &address);
+    assert_eq!(&inputs[251..259], &[8; 8]);
+    assert_eq!(&inputs[263..367], &record);
+    // data hash, output tree index, then the four empty trailing options
+    assert_eq!(inputs.len(), 367 + 32 + 1 + 4);
+    assert_eq!(&inputs[399..], &[2, 0, 0, 0, 0]);
+}
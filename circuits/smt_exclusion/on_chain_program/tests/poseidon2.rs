@@ -0,0 +1,53 @@
+//! Checks the in-program Poseidon2 against Noir's stdlib vectors, so the
+//! POSEIDON2 hash scheme agrees with circuits built with `USE_POSEIDON2_INDEX`.
+
+#![cfg(feature = "poseidon2")]
+
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField};
+use exclusion_program_example::poseidon2;
+
+fn fr(hex: &str) -> Fr {
+    Fr::from_be_bytes_mod_order(&hex_bytes(hex))
+}
+
+fn hex_bytes(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[test]
+fn permutation_matches_reference_vector() {
+    let state = [0u64, 1, 2, 3].map(Fr::from);
+    let expected = [
+        "01bd538c2ee014ed5141b29e9ae240bf8db3fe5b9a38629a9647cf8d76c01737",
+        "239b62e7db98aa3a2a8f6a0d2fa1709e7a35959aa6c7034814d9daa90cbac662",
+        "04cbb44c61d928ed06808456bf758cbf0c18d1e15a7b6dbc8245fa7515d5e3cb",
+        "2e11c5cff2a22c64d01304b778d78f6998eff1ab73163a35603f54794c30847a",
+    ]
+    .map(fr);
+    assert_eq!(poseidon2::permutation(state), expected);
+}
+
+#[test]
+fn hash_matches_noir() {
+    // Poseidon2::hash([1, 2], 2) in Noir's stdlib
+    let hash = poseidon2::hash(&[Fr::from(1u64), Fr::from(2u64)]);
+    assert_eq!(
+        hash,
+        fr("038682aa1cb5ae4e0a3f13da432a95c77c5c111f6f030faf9cad641ce1ed7383")
+    );
+    assert_eq!(hash.into_bigint().to_bytes_be().len(), 32);
+}
+
+#[test]
+fn pubkey_hash_matches_circuit() {
+    // Same vector as the circuit's test_pubkey_to_index_poseidon2
+    let pubkey: [u8; 32] = core::array::from_fn(|i| i as u8 + 1);
+    assert_eq!(
+        poseidon2::pubkey_hash_be(&pubkey).to_vec(),
+        hex_bytes("06c89551c03679710797414e4c5eb1fc9b397d56713e6e427bf0af54eabf8bb9")
+    );
+}
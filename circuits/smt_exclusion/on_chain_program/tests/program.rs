@@ -0,0 +1,2296 @@
+//! Runs the program's instructions in solana-program-test's bank, and checks
+//! what they write and that every `ExclusionError` has a failing path here.
+//!
+//! The program runs as a builtin, or as its SBF build under `cargo
+//! test-sbf`. Stub programs are deployed at the verifiers' IDs: they take the
+//! proof and witness a sunspot verifier takes, and accept only the pairs a
+//! test `prove`d. Any other proof fails the CPI, and with it the transaction.
+//! A wrapper program stands in for an integration calling the program from a
+//! CPI.
+
+use std::{collections::HashMap, fs, path::Path, sync::Mutex};
+
+use exclusion_common::{
+    pubkey_to_index, seeds,
+    state::{
+        config_offsets, escrow_flags, hash_scheme, state_offsets, transfer_flags, tree_kind,
+        verifier_registry_offsets, BOND_WITHDRAWAL_DELAY, EXCLUSION_QUEUE_CAPACITY,
+        MAX_REGISTERED_VERIFIERS, PROOF_BUFFER_DISCRIMINATOR, RELAY_ACCOUNT_DISCRIMINATOR,
+        RELAY_ACCOUNT_SIZE, STATE_VERSION, VERIFIER_REGISTRY_DISCRIMINATOR,
+        VERIFIER_REGISTRY_ENTRY_SIZE, VERIFIER_REGISTRY_SIZE,
+    },
+    ExclusionError, Proof, PublicWitness, State,
+};
+use exclusion_program_example::{
+    bubblegum,
+    events::NOOP_PROGRAM_ID,
+    instruction::{self, InstructionHeader, VerificationResult, METADATA_SIZE},
+    light, process_instruction, smt, wormhole, CONFIG_DISCRIMINATOR, CONFIG_SIZE,
+    INDEXED_VERIFIER_PROGRAM_ID, MAX_PROOF_AGE_SLOTS, STATE_SIZE, TRANSITION_VERIFIER_PROGRAM_ID,
+    ZK_VERIFIER_PROGRAM_ID,
+};
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_ed25519_program::new_ed25519_instruction_with_signature;
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::{ProgramResult, SUCCESS},
+    hash::{hash, Hash},
+    instruction::{AccountMeta, Instruction},
+    program::invoke,
+    program_error::ProgramError,
+    program_stubs::{self, SyscallStubs},
+    pubkey::Pubkey,
+    rent::Rent,
+    slot_hashes::SlotHashes,
+    sysvar::{self, SysvarSerialize},
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    signature::Keypair,
+    signer::Signer,
+    transaction::{Transaction, TransactionError},
+};
+use solana_sdk_ids::{bpf_loader_upgradeable, system_program};
+use tokio::sync::OnceCell;
+
+/// The slot and time transfers are made at
+const SLOT: u64 = 10_000;
+const NOW: i64 = 1_700_000_000;
+/// A slot the cluster skipped, so SlotHashes lacks it
+const SKIPPED_SLOT: u64 = SLOT - 5;
+const ROOT: [u8; 32] = [3; 32];
+/// What the fixture's recipient holds before it's paid, enough to be rent-exempt
+const BALANCE: u64 = 1_000_000_000;
+
+/// A stub verifier at an ID of its own, for registry entries
+const REGISTERED_VERIFIER: Pubkey = Pubkey::new_from_array([0xbe; 32]);
+const WRAPPER_PROGRAM_ID: Pubkey = Pubkey::new_from_array([0xca; 32]);
+
+/// Hashes of the proof and witness pairs the stub verifiers accept
+static PROVEN: Mutex<Vec<Hash>> = Mutex::new(Vec::new());
+
+/// Have the stub verifiers accept `proof` for `witness`
+fn accept(proof: &[u8], witness: &[u8]) {
+    PROVEN
+        .lock()
+        .unwrap()
+        .push(hash(&[proof, witness].concat()));
+}
+
+/// A proof of `witness` the stub verifiers accept for it and nothing else
+fn prove(witness: &[u8]) -> Proof {
+    let mut proof = Proof([0; instruction::PROOF_SIZE]);
+    proof.0[..32].copy_from_slice(hash(witness).as_ref());
+    accept(proof.as_bytes(), witness);
+    proof
+}
+
+/// A sunspot verifier's interface: the proof and witness as data, and no
+/// accounts. Logs what it verified or rejected, for `Bank::verified` and
+/// `Bank::rejected`, through program-test's log stub: off chain `msg!`
+/// prints to stdout instead of the transaction's logs.
+fn stub_verifier(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if !accounts.is_empty() || data.len() != instruction::PROOF_SIZE + instruction::WITNESS_SIZE {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let data = hash(data);
+    if !PROVEN.lock().unwrap().contains(&data) {
+        program_stubs::sol_log(&format!("{program_id} rejected {data}"));
+        return Err(ProgramError::InvalidArgument);
+    }
+    program_stubs::sol_log(&format!("{program_id} verified {data}"));
+    Ok(())
+}
+
+fn stub_noop(_: &Pubkey, _: &[AccountInfo], _: &[u8]) -> ProgramResult {
+    Ok(())
+}
+
+/// Calls the program in its first account with the rest and its own data
+fn stub_wrapper(_: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let metas = accounts[1..]
+        .iter()
+        .map(|account| AccountMeta {
+            pubkey: *account.key,
+            is_signer: account.is_signer,
+            is_writable: account.is_writable,
+        })
+        .collect();
+    invoke(
+        &Instruction {
+            program_id: *accounts[0].key,
+            accounts: metas,
+            data: data.to_vec(),
+        },
+        accounts,
+    )
+}
+
+/// `instruction` made from the wrapper program's CPI
+fn wrapped(instruction: Instruction) -> Instruction {
+    let mut accounts = vec![AccountMeta::new_readonly(instruction.program_id, false)];
+    accounts.extend(instruction.accounts);
+    Instruction {
+        program_id: WRAPPER_PROGRAM_ID,
+        accounts,
+        data: instruction.data,
+    }
+}
+
+/// The slots before `SLOT` but `SKIPPED_SLOT`, each with a hash of its own
+fn slot_hashes() -> SlotHashes {
+    let slot_hashes: Vec<_> = (SLOT - 500..SLOT)
+        .filter(|slot| *slot != SKIPPED_SLOT)
+        .map(|slot| (slot, Hash::new_from_array([slot as u8; 32])))
+        .collect();
+    SlotHashes::new(&slot_hashes)
+}
+
+/// program-test's syscall stubs, which the builtin program calls, plus
+/// `sol_get_sysvar` for `slot_hashes`, which they lack. Every bank has the
+/// same SlotHashes. The SBF build calls the bank's syscall instead.
+struct Syscalls {
+    program_test: Box<dyn SyscallStubs>,
+    /// SlotHashes' account data
+    slot_hashes: Vec<u8>,
+}
+
+static SYSCALLS: OnceCell<()> = OnceCell::const_new();
+
+/// Start a bank so program-test sets its stubs, then wrap them in `Syscalls`,
+/// before any test's bank runs a transaction
+async fn install_syscalls() {
+    ProgramTest::default().start().await;
+    let mut slot_hashes = vec![0; SlotHashes::size_of()];
+    let entries = self::slot_hashes();
+    slot_hashes[..8].copy_from_slice(&(entries.len() as u64).to_le_bytes());
+    for (i, (slot, hash)) in entries.iter().enumerate() {
+        let entry = &mut slot_hashes[8 + i * 40..][..40];
+        entry[..8].copy_from_slice(&slot.to_le_bytes());
+        entry[8..].copy_from_slice(hash.as_ref());
+    }
+    // Nothing calls a stub between the swaps, as no other bank has started
+    let program_test = program_stubs::set_syscall_stubs(Box::new(NoSyscalls));
+    program_stubs::set_syscall_stubs(Box::new(Syscalls {
+        program_test,
+        slot_hashes,
+    }));
+}
+
+struct NoSyscalls;
+
+impl SyscallStubs for NoSyscalls {}
+
+impl SyscallStubs for Syscalls {
+    fn sol_get_sysvar(
+        &self,
+        sysvar_id_addr: *const u8,
+        var_addr: *mut u8,
+        offset: u64,
+        length: u64,
+    ) -> u64 {
+        // SAFETY: the syscall's caller passes a sysvar ID and a buffer of
+        // `length` bytes
+        let sysvar_id = unsafe { &*(sysvar_id_addr as *const Pubkey) };
+        if *sysvar_id != sysvar::slot_hashes::ID {
+            return self
+                .program_test
+                .sol_get_sysvar(sysvar_id_addr, var_addr, offset, length);
+        }
+        let (offset, length) = (offset as usize, length as usize);
+        let Some(data) = self.slot_hashes.get(offset..offset.saturating_add(length)) else {
+            return u64::from(ProgramError::InvalidArgument);
+        };
+        unsafe { std::slice::from_raw_parts_mut(var_addr, length) }.copy_from_slice(data);
+        SUCCESS
+    }
+
+    fn sol_log(&self, message: &str) {
+        self.program_test.sol_log(message)
+    }
+    fn sol_log_compute_units(&self) {
+        self.program_test.sol_log_compute_units()
+    }
+    fn sol_remaining_compute_units(&self) -> u64 {
+        self.program_test.sol_remaining_compute_units()
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        self.program_test
+            .sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.program_test.sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.program_test.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.program_test.sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.program_test.sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.program_test.sol_get_epoch_rewards_sysvar(var_addr)
+    }
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        self.program_test.sol_get_last_restart_slot(var_addr)
+    }
+    fn sol_get_epoch_stake(&self, vote_address: *const u8) -> u64 {
+        self.program_test.sol_get_epoch_stake(vote_address)
+    }
+    unsafe fn sol_memcpy(&self, dst: *mut u8, src: *const u8, n: usize) {
+        self.program_test.sol_memcpy(dst, src, n)
+    }
+    unsafe fn sol_memmove(&self, dst: *mut u8, src: *const u8, n: usize) {
+        self.program_test.sol_memmove(dst, src, n)
+    }
+    unsafe fn sol_memcmp(&self, s1: *const u8, s2: *const u8, n: usize, result: *mut i32) {
+        self.program_test.sol_memcmp(s1, s2, n, result)
+    }
+    unsafe fn sol_memset(&self, s: *mut u8, c: u8, n: usize) {
+        self.program_test.sol_memset(s, c, n)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.program_test.sol_get_return_data()
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.program_test.sol_set_return_data(data)
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        self.program_test.sol_log_data(fields)
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.program_test
+            .sol_get_processed_sibling_instruction(index)
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        self.program_test.sol_get_stack_height()
+    }
+}
+
+/// The program and the stubs in a bank at `SLOT` and `NOW`, and what the last
+/// transaction logged
+struct Bank {
+    program_id: Pubkey,
+    context: ProgramTestContext,
+    rent: Rent,
+    /// The keypairs of accounts `funded` made, to sign for them
+    keypairs: HashMap<Pubkey, Keypair>,
+    /// Transactions sent, which makes each one's unit limit unique
+    sent: u32,
+    logs: Vec<String>,
+    return_data: Option<(Pubkey, Vec<u8>)>,
+}
+
+impl Bank {
+    async fn new() -> Self {
+        SYSCALLS.get_or_init(install_syscalls).await;
+        let program_id = Pubkey::new_unique();
+        let mut test = ProgramTest::new(
+            "exclusion_program_example",
+            program_id,
+            processor!(process_instruction),
+        );
+        for (name, verifier) in [
+            ("zk_verifier", ZK_VERIFIER_PROGRAM_ID),
+            ("transition_verifier", TRANSITION_VERIFIER_PROGRAM_ID),
+            ("indexed_verifier", INDEXED_VERIFIER_PROGRAM_ID),
+            ("registered_verifier", REGISTERED_VERIFIER),
+        ] {
+            test.add_program(name, verifier, processor!(stub_verifier));
+        }
+        test.add_program("noop", NOOP_PROGRAM_ID, processor!(stub_noop));
+        test.add_program("wrapper", WRAPPER_PROGRAM_ID, processor!(stub_wrapper));
+
+        let context = test.start_with_context().await;
+        context.set_sysvar(&Clock {
+            slot: SLOT,
+            unix_timestamp: NOW,
+            ..Clock::default()
+        });
+        context.set_sysvar(&slot_hashes());
+        let rent = context.banks_client.get_rent().await.unwrap();
+        Self {
+            program_id,
+            context,
+            rent,
+            keypairs: HashMap::new(),
+            sent: 0,
+            logs: Vec::new(),
+            return_data: None,
+        }
+    }
+
+    /// A new system account holding `lamports`, that `process` signs for
+    fn funded(&mut self, lamports: u64) -> Pubkey {
+        let keypair = Keypair::new();
+        let key = keypair.pubkey();
+        if lamports > 0 {
+            let account = Account::new(lamports, 0, &system_program::ID);
+            self.context.set_account(&key, &account.into());
+        }
+        self.keypairs.insert(key, keypair);
+        key
+    }
+
+    /// Store `data` at `key`, owned by `owner` and rent-exempt
+    fn insert(&mut self, key: &Pubkey, owner: &Pubkey, data: Vec<u8>) {
+        let mut account = Account::new(self.rent.minimum_balance(data.len()), 0, owner);
+        account.data = data;
+        self.context.set_account(key, &account.into());
+    }
+
+    async fn account(&mut self, key: &Pubkey) -> Account {
+        let account = self.context.banks_client.get_account(*key).await.unwrap();
+        account.unwrap_or_default()
+    }
+
+    async fn lamports(&mut self, key: &Pubkey) -> u64 {
+        self.context.banks_client.get_balance(*key).await.unwrap()
+    }
+
+    async fn state(&mut self, key: &Pubkey) -> State {
+        State::unpack(&self.account(key).await.data).unwrap()
+    }
+
+    async fn edit(&mut self, key: &Pubkey, edit: impl FnOnce(&mut [u8])) {
+        let mut account = self.account(key).await;
+        edit(&mut account.data);
+        self.context.set_account(key, &account.into());
+    }
+
+    async fn edit_state(&mut self, key: &Pubkey, edit: impl FnOnce(&mut State)) {
+        self.edit(key, |data| edit_state(data, edit)).await;
+    }
+
+    /// Move the clock to `unix_timestamp`, at the same slot
+    fn set_time(&mut self, unix_timestamp: i64) {
+        self.context.set_sysvar(&Clock {
+            slot: SLOT,
+            unix_timestamp,
+            ..Clock::default()
+        });
+    }
+
+    /// Run `instruction` in a transaction of its own, paid for by the bank's
+    /// payer and signed by the `funded` accounts it marks as signers
+    async fn process(&mut self, instruction: &Instruction) -> ProgramResult {
+        self.process_all(std::slice::from_ref(instruction)).await
+    }
+
+    /// Run `instructions` in one transaction, as `process` runs one
+    async fn process_all(&mut self, instructions: &[Instruction]) -> ProgramResult {
+        // A repeated instruction is a new transaction, not one already processed
+        self.sent += 1;
+        let limit = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000 - self.sent);
+        let payer = &self.context.payer;
+        let mut signers = vec![payer];
+        let accounts = instructions.iter().flat_map(|ix| &ix.accounts);
+        for meta in accounts.filter(|meta| meta.is_signer) {
+            if signers.iter().all(|signer| signer.pubkey() != meta.pubkey) {
+                signers.push(&self.keypairs[&meta.pubkey]);
+            }
+        }
+        let transaction = Transaction::new_signed_with_payer(
+            &[&[limit], instructions].concat(),
+            Some(&payer.pubkey()),
+            &signers[..],
+            self.context.last_blockhash,
+        );
+
+        let processed = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .unwrap();
+        let metadata = processed.metadata.unwrap();
+        self.logs = metadata.log_messages;
+        self.return_data = metadata
+            .return_data
+            .map(|return_data| (return_data.program_id, return_data.data));
+        match processed.result {
+            Ok(()) => Ok(()),
+            Err(TransactionError::InstructionError(_, error)) => Err(ProgramError::try_from(error)
+                .unwrap_or_else(|error| panic!("runtime error: {error}"))),
+            Err(error) => panic!("transaction failed: {error}"),
+        }
+    }
+
+    /// The verifiers the last transaction called that accepted the proof,
+    /// each with a hash of the data it was passed
+    fn verified(&self) -> Vec<(Pubkey, Hash)> {
+        self.verifications(" verified ")
+    }
+
+    /// The verifiers the last transaction called that rejected the proof
+    fn rejected(&self) -> Vec<(Pubkey, Hash)> {
+        self.verifications(" rejected ")
+    }
+
+    fn verifications(&self, outcome: &str) -> Vec<(Pubkey, Hash)> {
+        self.logs
+            .iter()
+            .filter_map(|log| {
+                let (verifier, data) = log.strip_prefix("Program log: ")?.split_once(outcome)?;
+                Some((verifier.parse().ok()?, data.parse().ok()?))
+            })
+            .collect()
+    }
+
+    /// The compute units the program consumed in the last transaction,
+    /// including its CPIs. Only the SBF build is metered.
+    fn consumed(&self) -> u64 {
+        let prefix = format!("Program {} consumed ", self.program_id);
+        self.logs
+            .iter()
+            .find_map(|log| log.strip_prefix(&prefix)?.split_once(' ')?.0.parse().ok())
+            .unwrap()
+    }
+}
+
+fn edit_state(data: &mut [u8], edit: impl FnOnce(&mut State)) {
+    let mut state = State::unpack(data).unwrap();
+    edit(&mut state);
+    state.pack(data);
+}
+
+fn ix(
+    program_id: &Pubkey,
+    discriminator: u8,
+    accounts: Vec<AccountMeta>,
+    fields: &[&[u8]],
+) -> Instruction {
+    let mut data = InstructionHeader::pack(discriminator).to_vec();
+    for field in fields {
+        data.extend_from_slice(field);
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    }
+}
+
+fn state_pda(program_id: &Pubkey, admin: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[seeds::STATE, admin.as_ref()], program_id).0
+}
+
+fn initialize_ix(program_id: &Pubkey, admin: &Pubkey, bond_lamports: u64) -> Instruction {
+    let state = state_pda(program_id, admin);
+    let bond = Pubkey::find_program_address(&[seeds::BOND, state.as_ref()], program_id).0;
+    ix(
+        program_id,
+        instruction::INITIALIZE,
+        vec![
+            AccountMeta::new(*admin, true),
+            AccountMeta::new(state, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(bond, false),
+        ],
+        &[&bond_lamports.to_le_bytes()],
+    )
+}
+
+fn set_smt_root_ix(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    state: &Pubkey,
+    root: &[u8; 32],
+    valid_until: i64,
+) -> Instruction {
+    ix(
+        program_id,
+        instruction::SET_SMT_ROOT,
+        vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*state, false),
+            AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+        ],
+        &[root, &valid_until.to_le_bytes(), &[7; METADATA_SIZE]],
+    )
+}
+
+/// An admin's state account with `ROOT` set, a funded sender, and a recipient
+/// holding `BALANCE`
+struct Fixture {
+    bank: Bank,
+    admin: Pubkey,
+    state: Pubkey,
+    sender: Pubkey,
+    recipient: Pubkey,
+}
+
+impl Fixture {
+    async fn new() -> Self {
+        let mut bank = Bank::new().await;
+        let admin = bank.funded(10_000_000_000);
+        let program_id = bank.program_id;
+        let state = state_pda(&program_id, &admin);
+        bank.process(&initialize_ix(&program_id, &admin, 0))
+            .await
+            .unwrap();
+        bank.process(&set_smt_root_ix(&program_id, &admin, &state, &ROOT, 0))
+            .await
+            .unwrap();
+        let sender = bank.funded(10_000_000_000);
+        let recipient = bank.funded(BALANCE);
+        Self {
+            bank,
+            admin,
+            state,
+            sender,
+            recipient,
+        }
+    }
+
+    /// The witness of a proof `sender` could make now
+    fn witness(&self) -> PublicWitness {
+        PublicWitness::new(&ROOT, &pubkey_to_index(&self.sender.to_bytes()), SLOT - 1)
+    }
+
+    /// `TRANSFER_SOL` of `amount` to `recipient` with `witness`, and
+    /// `extra` accounts (instructions sysvar, rate limit, treasury) in their
+    /// places before the noop program
+    fn transfer_ix(
+        &self,
+        amount: u64,
+        witness: &PublicWitness,
+        extra: &[AccountMeta],
+    ) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(self.sender, true),
+            AccountMeta::new(self.recipient, false),
+            AccountMeta::new_readonly(self.state, false),
+            AccountMeta::new_readonly(ZK_VERIFIER_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ];
+        accounts.extend_from_slice(extra);
+        accounts.push(AccountMeta::new_readonly(NOOP_PROGRAM_ID, false));
+        ix(
+            &self.bank.program_id,
+            instruction::TRANSFER_SOL,
+            accounts,
+            &[
+                &amount.to_le_bytes(),
+                prove(witness.as_bytes()).as_bytes(),
+                witness.as_bytes(),
+            ],
+        )
+    }
+
+    async fn transfer(&mut self, amount: u64) -> ProgramResult {
+        let ix = self.transfer_ix(amount, &self.witness(), &[]);
+        self.bank.process(&ix).await
+    }
+
+    /// `SUBMIT_ROOT_TRANSITION` from `ROOT` to `new_root` inserting `key`,
+    /// signed by `authority`
+    fn transition_ix(&self, authority: &Pubkey, key: &Pubkey, new_root: &[u8; 32]) -> Instruction {
+        // old_root, new_root and key, where a transfer's witness has its slot
+        let mut witness = PublicWitness::new(&ROOT, new_root, 0);
+        witness.0[76..].copy_from_slice(&pubkey_to_index(&key.to_bytes()));
+        ix(
+            &self.bank.program_id,
+            instruction::SUBMIT_ROOT_TRANSITION,
+            vec![
+                AccountMeta::new(self.state, false),
+                AccountMeta::new_readonly(TRANSITION_VERIFIER_PROGRAM_ID, false),
+                AccountMeta::new_readonly(*authority, true),
+                AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+            ],
+            &[
+                key.as_ref(),
+                prove(witness.as_bytes()).as_bytes(),
+                witness.as_bytes(),
+            ],
+        )
+    }
+
+    /// An admin instruction on the state taking `fields`
+    fn admin_ix(&self, discriminator: u8, fields: &[&[u8]]) -> Instruction {
+        ix(
+            &self.bank.program_id,
+            discriminator,
+            vec![
+                AccountMeta::new(self.admin, true),
+                AccountMeta::new(self.state, false),
+            ],
+            fields,
+        )
+    }
+
+    /// The admin's vault, holding `lamports`
+    fn fund_vault(&mut self, lamports: u64) {
+        let seeds = [seeds::VAULT, self.admin.as_ref()];
+        let vault = Pubkey::find_program_address(&seeds, &self.bank.program_id).0;
+        let account = Account::new(lamports, 0, &system_program::ID);
+        self.bank.context.set_account(&vault, &account.into());
+    }
+
+    /// `TRANSFER_FROM_VAULT` of `amount` to `recipient`, with `proof_accounts`
+    /// as for `transfer_ix`
+    fn payout_ix(&self, amount: u64, proof_accounts: &[AccountMeta]) -> Instruction {
+        let program_id = self.bank.program_id;
+        let vault =
+            Pubkey::find_program_address(&[seeds::VAULT, self.admin.as_ref()], &program_id).0;
+        let witness = PublicWitness::new(
+            &ROOT,
+            &pubkey_to_index(&self.recipient.to_bytes()),
+            SLOT - 1,
+        );
+        let mut accounts = vec![
+            AccountMeta::new(self.admin, true),
+            AccountMeta::new(self.recipient, false),
+            AccountMeta::new_readonly(self.state, false),
+            AccountMeta::new_readonly(ZK_VERIFIER_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(vault, false),
+        ];
+        accounts.extend_from_slice(proof_accounts);
+        ix(
+            &program_id,
+            instruction::TRANSFER_FROM_VAULT,
+            accounts,
+            &[
+                &amount.to_le_bytes(),
+                prove(witness.as_bytes()).as_bytes(),
+                witness.as_bytes(),
+            ],
+        )
+    }
+}
+
+fn hex(hex: &str) -> [u8; 32] {
+    std::array::from_fn(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap())
+}
+
+fn error(error: ExclusionError) -> ProgramResult {
+    Err(error.into())
+}
+
+/// The gated transfers other than `TRANSFER_SOL` that the sender funds, each
+/// of `amount` with `proof_accounts` (instructions sysvar, rate limit,
+/// treasury) in their places before the noop program, after setting up the
+/// accounts they spend from
+fn sender_funded_transfers(
+    fixture: &mut Fixture,
+    amount: u64,
+    proof_accounts: &[AccountMeta],
+) -> [(&'static str, Vec<Instruction>); 4] {
+    let program_id = fixture.bank.program_id;
+    let (sender, recipient, state) = (fixture.sender, fixture.recipient, fixture.state);
+    let (witness, proof) = (fixture.witness(), prove(fixture.witness().as_bytes()));
+    let fixed = |accounts: &[AccountMeta]| {
+        let mut accounts = accounts.to_vec();
+        accounts.extend_from_slice(proof_accounts);
+        accounts
+    };
+
+    let id = 1u64.to_le_bytes();
+    let escrow = Pubkey::find_program_address(
+        &[seeds::ESCROW, sender.as_ref(), recipient.as_ref(), &id],
+        &program_id,
+    )
+    .0;
+    let escrow = ix(
+        &program_id,
+        instruction::TRANSFER_TO_ESCROW,
+        fixed(&[
+            AccountMeta::new(sender, true),
+            AccountMeta::new_readonly(recipient, false),
+            AccountMeta::new(escrow, false),
+            AccountMeta::new_readonly(state, false),
+            AccountMeta::new_readonly(ZK_VERIFIER_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]),
+        &[
+            &amount.to_le_bytes(),
+            &id,
+            &0i64.to_le_bytes(),
+            &[0],
+            proof.as_bytes(),
+            witness.as_bytes(),
+        ],
+    );
+
+    // Both parties' proofs, already in their buffers
+    let mut accounts = vec![
+        AccountMeta::new(sender, true),
+        AccountMeta::new(recipient, false),
+        AccountMeta::new_readonly(state, false),
+        AccountMeta::new_readonly(ZK_VERIFIER_PROGRAM_ID, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ];
+    for owner in [sender, recipient] {
+        let buffer =
+            Pubkey::find_program_address(&[seeds::PROOF_BUFFER, owner.as_ref()], &program_id).0;
+        let witness = PublicWitness::new(&ROOT, &pubkey_to_index(&owner.to_bytes()), SLOT - 1);
+        let data = [
+            &PROOF_BUFFER_DISCRIMINATOR[..],
+            owner.as_ref(),
+            prove(witness.as_bytes()).as_bytes(),
+            witness.as_bytes(),
+        ]
+        .concat();
+        fixture.bank.insert(&buffer, &program_id, data);
+        accounts.push(AccountMeta::new(buffer, false));
+    }
+    let dual = ix(
+        &program_id,
+        instruction::TRANSFER_SOL_DUAL,
+        fixed(&accounts),
+        &[&amount.to_le_bytes()],
+    );
+
+    // With no proof nodes, so only what comes before the Bubblegum CPI runs
+    let mut accounts = vec![
+        AccountMeta::new(sender, true),
+        AccountMeta::new_readonly(sender, false),
+        AccountMeta::new_readonly(recipient, false),
+        AccountMeta::new_readonly(state, false),
+        AccountMeta::new_readonly(ZK_VERIFIER_PROGRAM_ID, false),
+        AccountMeta::new_readonly(bubblegum::BUBBLEGUM_PROGRAM_ID, false),
+    ];
+    accounts.extend((0..5).map(|_| AccountMeta::new(Pubkey::new_unique(), false)));
+    let mut data = [&proof.as_bytes()[..], witness.as_bytes()].concat();
+    data.resize(instruction::TRANSFER_CNFT_LEN, 0);
+    let cnft = ix(
+        &program_id,
+        instruction::TRANSFER_CNFT,
+        fixed(&accounts),
+        &[&data],
+    );
+
+    // Signed by the sender off-chain, and by a relayer paying for the
+    // transaction and any rate limit account
+    let relay = Pubkey::find_program_address(&[seeds::RELAY, sender.as_ref()], &program_id).0;
+    let mut account = Account::new(
+        fixture.bank.rent.minimum_balance(RELAY_ACCOUNT_SIZE) + 2 * amount,
+        0,
+        &program_id,
+    );
+    account.data = [&RELAY_ACCOUNT_DISCRIMINATOR[..], sender.as_ref(), &[0; 8]].concat();
+    fixture.bank.context.set_account(&relay, &account.into());
+    let message = instruction::relay_message(&program_id, &recipient, amount, 0);
+    let signature = fixture.bank.keypairs[&sender].sign_message(&message);
+    let authorization =
+        new_ed25519_instruction_with_signature(&message, &signature.into(), &sender.to_bytes());
+    let relayer = fixture.bank.funded(1_000_000_000);
+    let mut accounts = fixed(&[
+        AccountMeta::new_readonly(sender, false),
+        AccountMeta::new(recipient, false),
+        AccountMeta::new_readonly(state, false),
+        AccountMeta::new_readonly(ZK_VERIFIER_PROGRAM_ID, false),
+        AccountMeta::new(relay, false),
+    ]);
+    accounts.push(AccountMeta::new(relayer, true));
+    accounts.push(AccountMeta::new_readonly(system_program::ID, false));
+    let relayed = ix(
+        &program_id,
+        instruction::TRANSFER_SOL_RELAYED,
+        accounts,
+        &[
+            &amount.to_le_bytes(),
+            &0u64.to_le_bytes(),
+            proof.as_bytes(),
+            witness.as_bytes(),
+        ],
+    );
+
+    let mut transfers = [
+        ("escrow", vec![escrow]),
+        ("dual", vec![dual]),
+        ("cNFT", vec![cnft]),
+        ("relayed", vec![authorization, relayed]),
+    ];
+    for (_, instructions) in &mut transfers {
+        let transfer = instructions.last_mut().unwrap();
+        transfer
+            .accounts
+            .push(AccountMeta::new_readonly(NOOP_PROGRAM_ID, false));
+    }
+    transfers
+}
+
+#[tokio::test]
+async fn initializes_a_state_account() {
+    let mut bank = Bank::new().await;
+    let program_id = bank.program_id;
+    let admin = bank.funded(10_000_000_000);
+    let state = state_pda(&program_id, &admin);
+    bank.process(&initialize_ix(&program_id, &admin, 5_000))
+        .await
+        .unwrap();
+
+    let account = bank.account(&state).await;
+    assert_eq!(account.owner, program_id);
+    assert_eq!(account.data.len(), STATE_SIZE);
+    assert_eq!(account.lamports, bank.rent.minimum_balance(STATE_SIZE));
+    assert_eq!(account.data[state_offsets::VERSION], STATE_VERSION);
+    let decoded = bank.state(&state).await;
+    assert_eq!(decoded.admin, admin.to_bytes());
+    assert_eq!((decoded.smt_root, decoded.root_seq), ([0; 32], 0));
+    assert_eq!(decoded.hash_scheme, hash_scheme::POSEIDON);
+    assert_eq!(decoded.bond_amount, 5_000);
+    let bond = Pubkey::find_program_address(&[seeds::BOND, state.as_ref()], &program_id).0;
+    assert_eq!(bank.account(&bond).await.owner, program_id);
+
+    // Once only
+    assert_eq!(
+        bank.process(&initialize_ix(&program_id, &admin, 0)).await,
+        Err(ProgramError::Custom(0))
+    );
+    // For the signer's own PDA
+    let other = bank.funded(10_000_000_000);
+    let mut wrong_pda = initialize_ix(&program_id, &other, 0);
+    wrong_pda.accounts[1].pubkey = state_pda(&program_id, &bank.funded(0));
+    assert_eq!(
+        bank.process(&wrong_pda).await,
+        error(ExclusionError::InvalidStatePda)
+    );
+    let mut unsigned = initialize_ix(&program_id, &other, 0);
+    unsigned.accounts[0].is_signer = false;
+    assert_eq!(
+        bank.process(&unsigned).await,
+        Err(ProgramError::MissingRequiredSignature)
+    );
+}
+
+#[tokio::test]
+async fn sets_the_root() {
+    let mut fixture = Fixture::new().await;
+    let program_id = fixture.bank.program_id;
+    let (admin, state) = (fixture.admin, fixture.state);
+    let bank = &mut fixture.bank;
+    bank.process(&set_smt_root_ix(
+        &program_id,
+        &admin,
+        &state,
+        &[4; 32],
+        NOW + 60,
+    ))
+    .await
+    .unwrap();
+    let decoded = bank.state(&state).await;
+    assert_eq!(decoded.smt_root, [4; 32]);
+    assert_eq!(decoded.root_seq, 2);
+    assert_eq!(decoded.root_valid_until, NOW + 60);
+    assert_eq!(decoded.metadata, [7; METADATA_SIZE]);
+
+    // A version 1 root has no expiry, and clears the one set
+    let mut ix = set_smt_root_ix(&program_id, &admin, &state, &[6; 32], 0);
+    ix.data[1] = 1;
+    ix.data
+        .truncate(instruction::HEADER_LEN + instruction::SET_SMT_ROOT_LEN);
+    bank.process(&ix).await.unwrap();
+    let decoded = bank.state(&state).await;
+    assert_eq!(decoded.smt_root, [6; 32]);
+    assert_eq!(decoded.root_valid_until, 0);
+    bank.process(&set_smt_root_ix(
+        &program_id,
+        &admin,
+        &state,
+        &[4; 32],
+        NOW + 60,
+    ))
+    .await
+    .unwrap();
+
+    // Only by the admin, signing
+    let intruder = bank.funded(1_000_000);
+    let mut ix = set_smt_root_ix(&program_id, &admin, &state, &[5; 32], 0);
+    ix.accounts[0].pubkey = intruder;
+    assert_eq!(
+        bank.process(&ix).await,
+        error(ExclusionError::UnauthorizedAdmin)
+    );
+    let mut ix = set_smt_root_ix(&program_id, &admin, &state, &[5; 32], 0);
+    ix.accounts[0].is_signer = false;
+    assert_eq!(
+        bank.process(&ix).await,
+        Err(ProgramError::MissingRequiredSignature)
+    );
+
+    // Of a state account of the current layout
+    let mut ix = set_smt_root_ix(&program_id, &admin, &state, &[5; 32], 0);
+    ix.accounts[1].pubkey = intruder;
+    assert_eq!(
+        bank.process(&ix).await,
+        error(ExclusionError::InvalidStateAccount)
+    );
+    bank.edit(&state, |data| {
+        data[state_offsets::VERSION] = STATE_VERSION - 1
+    })
+    .await;
+    let ix = set_smt_root_ix(&program_id, &admin, &state, &[5; 32], 0);
+    assert_eq!(
+        bank.process(&ix).await,
+        error(ExclusionError::StateMigrationRequired)
+    );
+    bank.edit(&state, |data| data[state_offsets::VERSION] = STATE_VERSION)
+        .await;
+
+    // With data of a known version and length
+    let mut ix = set_smt_root_ix(&program_id, &admin, &state, &[5; 32], 0);
+    ix.data[1] = instruction::max_version(instruction::SET_SMT_ROOT) + 1;
+    assert_eq!(
+        bank.process(&ix).await,
+        error(ExclusionError::UnsupportedInstructionVersion)
+    );
+    let mut ix = set_smt_root_ix(&program_id, &admin, &state, &[5; 32], 0);
+    ix.data.truncate(20);
+    assert_eq!(
+        bank.process(&ix).await,
+        error(ExclusionError::InvalidDataLength)
+    );
+    assert_eq!(bank.state(&state).await.smt_root, [4; 32]);
+}
+
+#[tokio::test]
+async fn clears_a_passed_expiry_with_a_version_1_root() {
+    let mut fixture = Fixture::new().await;
+    let program_id = fixture.bank.program_id;
+    let (admin, state) = (fixture.admin, fixture.state);
+    let ix = set_smt_root_ix(&program_id, &admin, &state, &ROOT, NOW - 1);
+    fixture.bank.process(&ix).await.unwrap();
+    assert_eq!(
+        fixture.transfer(1).await,
+        error(ExclusionError::RootExpired)
+    );
+
+    // An admin on a version 1 client republishes the root, which takes effect
+    let mut ix = set_smt_root_ix(&program_id, &admin, &state, &ROOT, 0);
+    ix.data[1] = 1;
+    ix.data
+        .truncate(instruction::HEADER_LEN + instruction::SET_SMT_ROOT_LEN);
+    fixture.bank.process(&ix).await.unwrap();
+    assert_eq!(fixture.bank.state(&state).await.root_valid_until, 0);
+    fixture.transfer(1).await.unwrap();
+}
+
+#[tokio::test]
+async fn transfers_with_a_proof() {
+    let mut fixture = Fixture::new().await;
+    let (sender, recipient) = (fixture.sender, fixture.recipient);
+    let balance = fixture.bank.lamports(&sender).await;
+    fixture.transfer(1_000_000).await.unwrap();
+    assert_eq!(fixture.bank.lamports(&sender).await, balance - 1_000_000);
+    assert_eq!(fixture.bank.lamports(&recipient).await, BALANCE + 1_000_000);
+
+    // CPI callers read what was verified from the return data
+    let (program_id, data) = fixture.bank.return_data.clone().unwrap();
+    assert_eq!(program_id, fixture.bank.program_id);
+    assert_eq!(
+        VerificationResult::unpack(&data),
+        Some(VerificationResult {
+            smt_root: ROOT,
+            pubkey_hash: pubkey_to_index(&sender.to_bytes()),
+            amount: 1_000_000,
+        })
+    );
+    // The verifier got the proof and witness, and nothing else
+    let data = [
+        &prove(fixture.witness().as_bytes()).as_bytes()[..],
+        fixture.witness().as_bytes(),
+    ]
+    .concat();
+    assert_eq!(
+        fixture.bank.verified(),
+        [(ZK_VERIFIER_PROGRAM_ID, hash(&data))]
+    );
+
+    // A proof of another witness is rejected for this one, and the failed CPI
+    // fails the transfer with the verifier's error
+    let other = PublicWitness::new(&ROOT, &pubkey_to_index(&recipient.to_bytes()), SLOT - 1);
+    let proof = prove(other.as_bytes());
+    let mut ix = fixture.transfer_ix(1, &fixture.witness(), &[]);
+    let start = instruction::HEADER_LEN + 8;
+    ix.data[start..start + instruction::PROOF_SIZE].copy_from_slice(proof.as_bytes());
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        Err(ProgramError::InvalidArgument)
+    );
+    let data = [&proof.as_bytes()[..], fixture.witness().as_bytes()].concat();
+    assert_eq!(
+        fixture.bank.rejected(),
+        [(ZK_VERIFIER_PROGRAM_ID, hash(&data))]
+    );
+    assert_eq!(fixture.bank.lamports(&recipient).await, BALANCE + 1_000_000);
+
+    // Only signed by the sender
+    let mut ix = fixture.transfer_ix(1, &fixture.witness(), &[]);
+    ix.accounts[0].is_signer = false;
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        Err(ProgramError::MissingRequiredSignature)
+    );
+}
+
+#[tokio::test]
+async fn expands_compressed_proofs() {
+    // A proof and its compression by the client's `compress_proof`
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/compressed");
+    let proof = fs::read(fixtures.join("uncompressed.proof")).unwrap();
+    let compressed = fs::read(fixtures.join("compressed.proof")).unwrap();
+    assert_eq!(compressed.len(), instruction::COMPRESSED_PROOF_SIZE);
+
+    let mut fixture = Fixture::new().await;
+    accept(&proof, fixture.witness().as_bytes());
+    // TRANSFER_SOL's accounts, with the compressed proof in the data
+    let compressed_ix = |fixture: &Fixture, compressed: &[u8]| {
+        let transfer = fixture.transfer_ix(1_000, &fixture.witness(), &[]);
+        ix(
+            &transfer.program_id,
+            instruction::TRANSFER_SOL_COMPRESSED,
+            transfer.accounts,
+            &[
+                &1_000u64.to_le_bytes(),
+                compressed,
+                fixture.witness().as_bytes(),
+            ],
+        )
+    };
+    fixture
+        .bank
+        .process(&compressed_ix(&fixture, &compressed))
+        .await
+        .unwrap();
+    assert_eq!(
+        fixture.bank.lamports(&fixture.recipient).await,
+        BALANCE + 1_000
+    );
+    // The verifier got the proof as it was before compression
+    let data = [&proof[..], fixture.witness().as_bytes()].concat();
+    assert_eq!(
+        fixture.bank.verified(),
+        [(ZK_VERIFIER_PROGRAM_ID, hash(&data))]
+    );
+
+    // Krs at x = 4, where x^3 + 3 has no square root
+    let mut off_curve = compressed.clone();
+    off_curve[96..128].fill(0);
+    off_curve[127] = 4;
+    // Two commitments
+    let mut two_commitments = compressed.clone();
+    two_commitments[128..132].copy_from_slice(&2u32.to_be_bytes());
+    for compressed in [off_curve, two_commitments] {
+        assert_eq!(
+            fixture
+                .bank
+                .process(&compressed_ix(&fixture, &compressed))
+                .await,
+            error(ExclusionError::InvalidCompressedProof)
+        );
+    }
+    assert_eq!(
+        fixture.bank.lamports(&fixture.recipient).await,
+        BALANCE + 1_000
+    );
+}
+
+#[tokio::test]
+async fn rejects_proofs_the_state_does_not_accept() {
+    // The error, an edit of the state account's data, and the transfer
+    type Case = (ExclusionError, fn(&mut [u8]), fn(&Fixture) -> Instruction);
+    let transfer: fn(&Fixture) -> Instruction = |f| f.transfer_ix(1, &f.witness(), &[]);
+    let cases: [Case; 11] = [
+        (
+            ExclusionError::UnsupportedInstructionVersion,
+            |_| {},
+            |f| {
+                // TRANSFER_SOL's layout never changed, so it claims no later version
+                let mut ix = f.transfer_ix(1, &f.witness(), &[]);
+                ix.data[1] = 2;
+                ix
+            },
+        ),
+        (
+            ExclusionError::SmtRootMismatch,
+            |_| {},
+            |f| {
+                let witness =
+                    PublicWitness::new(&[9; 32], &pubkey_to_index(&f.sender.to_bytes()), SLOT - 1);
+                f.transfer_ix(1, &witness, &[])
+            },
+        ),
+        (
+            ExclusionError::PubkeyHashMismatch,
+            |_| {},
+            |f| {
+                let witness =
+                    PublicWitness::new(&ROOT, &pubkey_to_index(&f.admin.to_bytes()), SLOT - 1);
+                f.transfer_ix(1, &witness, &[])
+            },
+        ),
+        (
+            ExclusionError::StaleProof,
+            |_| {},
+            |f| {
+                let old = SLOT - MAX_PROOF_AGE_SLOTS - 1;
+                let witness =
+                    PublicWitness::new(&ROOT, &pubkey_to_index(&f.sender.to_bytes()), old);
+                f.transfer_ix(1, &witness, &[])
+            },
+        ),
+        (
+            ExclusionError::StaleProof,
+            |_| {},
+            |f| {
+                let witness =
+                    PublicWitness::new(&ROOT, &pubkey_to_index(&f.sender.to_bytes()), SLOT + 1);
+                f.transfer_ix(1, &witness, &[])
+            },
+        ),
+        (
+            ExclusionError::UnknownRecentSlot,
+            |_| {},
+            |f| {
+                let witness =
+                    PublicWitness::new(&ROOT, &pubkey_to_index(&f.sender.to_bytes()), SKIPPED_SLOT);
+                f.transfer_ix(1, &witness, &[])
+            },
+        ),
+        (
+            ExclusionError::InvalidZkVerifier,
+            |_| {},
+            |f| {
+                let mut ix = f.transfer_ix(1, &f.witness(), &[]);
+                ix.accounts[3].pubkey = Pubkey::new_unique();
+                ix
+            },
+        ),
+        (
+            ExclusionError::RootExpired,
+            |data| edit_state(data, |state| state.root_valid_until = NOW - 1),
+            transfer,
+        ),
+        (
+            ExclusionError::StateChallenged,
+            |data| edit_state(data, |state| state.challenged = true),
+            transfer,
+        ),
+        (
+            ExclusionError::UnsupportedHashScheme,
+            |data| edit_state(data, |state| state.hash_scheme = u8::MAX),
+            transfer,
+        ),
+        (
+            ExclusionError::StateMigrationRequired,
+            |data| data[state_offsets::VERSION] = STATE_VERSION - 1,
+            transfer,
+        ),
+    ];
+    for (expected, edit, case) in cases {
+        let mut fixture = Fixture::new().await;
+        fixture.bank.edit(&fixture.state, edit).await;
+        let ix = case(&fixture);
+        assert_eq!(
+            fixture.bank.process(&ix).await,
+            error(expected),
+            "{expected:?}"
+        );
+        assert_eq!(fixture.bank.lamports(&fixture.recipient).await, BALANCE);
+    }
+}
+
+#[tokio::test]
+async fn updates_leaves_to_the_trees_roots() {
+    // `smt.ts`'s demo key, in an empty tree, whose roots with and without it
+    // come from the smt crate (see Prover.toml and smt_insertion's tests)
+    let key: Pubkey = "FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH"
+        .parse()
+        .unwrap();
+    let cases = [
+        (
+            254,
+            smt::DEFAULT_HASHES[254],
+            hex("2c5e751b8119ae384f24a6457b21f7b7928e2241b600009360ff2ed541d15945"),
+        ),
+        (
+            20,
+            smt::DEFAULT_HASHES[20],
+            hex("150385928ed8fa46194949cfb8cdf0e7ef1126bdb7f0c5cb273ba40ac0023723"),
+        ),
+    ];
+    for (depth, empty, inserted) in cases {
+        let mut fixture = Fixture::new().await;
+        let state = fixture.state;
+        fixture
+            .bank
+            .edit_state(&state, |state| {
+                state.tree_depth = depth;
+                state.smt_root = empty;
+            })
+            .await;
+        // Every sibling of a lone key is the default, so none are passed
+        let update = |discriminator| {
+            let mut ix = fixture.admin_ix(discriminator, &[key.as_ref(), &[0; 32]]);
+            ix.accounts
+                .push(AccountMeta::new_readonly(NOOP_PROGRAM_ID, false));
+            ix
+        };
+        let (insert, remove) = (
+            update(instruction::INSERT_LEAF),
+            update(instruction::REMOVE_LEAF),
+        );
+
+        assert_eq!(
+            fixture.bank.process(&remove).await,
+            error(ExclusionError::LeafPathMismatch),
+            "depth {depth}"
+        );
+        fixture.bank.process(&insert).await.unwrap();
+        assert_eq!(
+            fixture.bank.state(&state).await.smt_root,
+            inserted,
+            "depth {depth}"
+        );
+        assert_eq!(
+            fixture.bank.process(&insert).await,
+            error(ExclusionError::LeafPathMismatch),
+            "depth {depth}"
+        );
+        fixture.bank.process(&remove).await.unwrap();
+        assert_eq!(
+            fixture.bank.state(&state).await.smt_root,
+            empty,
+            "depth {depth}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn transitions_need_the_admin_or_the_inserted_key() {
+    let mut fixture = Fixture::new().await;
+    let (admin, state) = (fixture.admin, fixture.state);
+    let victim = fixture.sender;
+    let intruder = fixture.bank.funded(1_000_000);
+
+    // Nobody else can blacklist a key
+    let ix = fixture.transition_ix(&intruder, &victim, &[4; 32]);
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        error(ExclusionError::UnauthorizedAdmin)
+    );
+    let mut ix = fixture.transition_ix(&victim, &victim, &[4; 32]);
+    ix.accounts[2].is_signer = false;
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    assert_eq!(fixture.bank.state(&state).await.smt_root, ROOT);
+
+    // Only in a tree of the transition verifier's depth
+    fixture
+        .bank
+        .edit_state(&state, |state| state.tree_depth = 20)
+        .await;
+    let ix = fixture.transition_ix(&victim, &victim, &[4; 32]);
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        error(ExclusionError::UnsupportedTreeDepth)
+    );
+
+    // A key can exclude itself
+    fixture
+        .bank
+        .edit_state(&state, |state| state.tree_depth = smt::TREE_DEPTH as u16)
+        .await;
+    fixture.bank.process(&ix).await.unwrap();
+    assert_eq!(fixture.bank.state(&state).await.smt_root, [4; 32]);
+
+    // And the admin can insert any key
+    let mut ix = fixture.transition_ix(&admin, &intruder, &[5; 32]);
+    let (proof, witness) =
+        ix.data[instruction::HEADER_LEN + 32..].split_at_mut(instruction::PROOF_SIZE);
+    witness[12..44].copy_from_slice(&[4; 32]);
+    proof.copy_from_slice(prove(witness).as_bytes());
+    fixture.bank.process(&ix).await.unwrap();
+    assert_eq!(fixture.bank.state(&state).await.smt_root, [5; 32]);
+}
+
+#[tokio::test]
+async fn follows_the_linked_config() {
+    let mut fixture = Fixture::new().await;
+    let program_id = fixture.bank.program_id;
+    let config = Pubkey::find_program_address(&[seeds::CONFIG], &program_id).0;
+    fixture
+        .bank
+        .edit_state(&fixture.state, |state| state.config = config.to_bytes())
+        .await;
+    let mut data = vec![0; CONFIG_SIZE];
+    data[..8].copy_from_slice(&CONFIG_DISCRIMINATOR);
+    data[config_offsets::PAUSED] = 1;
+    data[config_offsets::ZK_VERIFIER..config_offsets::ZK_VERIFIER + 32]
+        .copy_from_slice(ZK_VERIFIER_PROGRAM_ID.as_ref());
+    data[config_offsets::MAX_PROOF_AGE_SLOTS..config_offsets::MAX_PROOF_AGE_SLOTS + 8]
+        .copy_from_slice(&MAX_PROOF_AGE_SLOTS.to_le_bytes());
+    fixture.bank.insert(&config, &program_id, data);
+
+    let ix = fixture.transfer_ix(1, &fixture.witness(), &[]);
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+    let mut ix = fixture.transfer_ix(1, &fixture.witness(), &[]);
+    ix.accounts.push(AccountMeta::new_readonly(config, false));
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        error(ExclusionError::ProgramPaused)
+    );
+
+    fixture.bank.edit(&config, |data| data[0] ^= 1).await;
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        error(ExclusionError::InvalidConfigAccount)
+    );
+}
+
+#[tokio::test]
+async fn rejects_cpi_callers_when_required() {
+    let mut fixture = Fixture::new().await;
+    let ix = fixture.admin_ix(
+        instruction::SET_TRANSFER_FLAGS,
+        &[&[transfer_flags::REQUIRE_TOP_LEVEL]],
+    );
+    fixture.bank.process(&ix).await.unwrap();
+    // Without the instructions sysvar in its place, the noop program is read
+    // as it
+    assert_eq!(
+        fixture.transfer(1).await,
+        Err(ProgramError::UnsupportedSysvar)
+    );
+
+    // The instructions sysvar records the top-level instruction
+    let instructions = [AccountMeta::new_readonly(sysvar::instructions::ID, false)];
+    let ix = fixture.transfer_ix(1, &fixture.witness(), &instructions);
+    fixture.bank.process(&ix).await.unwrap();
+
+    // Another program's, when it calls the transfer from a CPI
+    assert_eq!(
+        fixture.bank.process(&wrapped(ix)).await,
+        error(ExclusionError::CpiNotAllowed)
+    );
+}
+
+#[tokio::test]
+async fn limits_and_charges_transfers() {
+    let mut fixture = Fixture::new().await;
+    let program_id = fixture.bank.program_id;
+    let (state, sender) = (fixture.state, fixture.sender);
+
+    // A daily limit counts each sender's transfers in their rate limit account
+    let ix = fixture.admin_ix(instruction::SET_RATE_LIMIT, &[&1_000u64.to_le_bytes()]);
+    fixture.bank.process(&ix).await.unwrap();
+    let rate_limit = Pubkey::find_program_address(
+        &[seeds::RATE_LIMIT, state.as_ref(), sender.as_ref()],
+        &program_id,
+    )
+    .0;
+    let extra = |rate_limit| {
+        [
+            AccountMeta::new_readonly(sysvar::instructions::ID, false),
+            AccountMeta::new(rate_limit, false),
+        ]
+    };
+    let witness = fixture.witness();
+    let ix = fixture.transfer_ix(600, &witness, &extra(Pubkey::new_unique()));
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        error(ExclusionError::InvalidRateLimitAccount)
+    );
+    let ix = fixture.transfer_ix(600, &witness, &extra(rate_limit));
+    fixture.bank.process(&ix).await.unwrap();
+    assert_eq!(fixture.bank.account(&rate_limit).await.owner, program_id);
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        error(ExclusionError::RateLimitExceeded)
+    );
+    let ix = fixture.admin_ix(instruction::SET_RATE_LIMIT, &[&0u64.to_le_bytes()]);
+    fixture.bank.process(&ix).await.unwrap();
+
+    // A fee is paid into the state's treasury, created when it's set
+    let treasury = Pubkey::find_program_address(&[seeds::TREASURY, state.as_ref()], &program_id).0;
+    let set_fee = |fee_bps: u16| {
+        let mut ix = fixture.admin_ix(instruction::SET_FEE, &[&fee_bps.to_le_bytes()]);
+        ix.accounts.push(AccountMeta::new(treasury, false));
+        ix.accounts
+            .push(AccountMeta::new_readonly(system_program::ID, false));
+        ix
+    };
+    let (too_high, fee) = (set_fee(10_001), set_fee(100));
+    assert_eq!(
+        fixture.bank.process(&too_high).await,
+        error(ExclusionError::InvalidFee)
+    );
+    fixture.bank.process(&fee).await.unwrap();
+    let rent = fixture.bank.lamports(&treasury).await;
+
+    let mut extra = extra(rate_limit).to_vec();
+    extra.push(AccountMeta::new(Pubkey::new_unique(), false));
+    let ix = fixture.transfer_ix(10_000, &witness, &extra);
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        error(ExclusionError::InvalidTreasuryAccount)
+    );
+    extra[2].pubkey = treasury;
+    let ix = fixture.transfer_ix(10_000, &witness, &extra);
+    fixture.bank.process(&ix).await.unwrap();
+    assert_eq!(fixture.bank.lamports(&treasury).await, rent + 100);
+}
+
+#[tokio::test]
+async fn limits_every_sender_funded_transfer() {
+    let mut fixture = Fixture::new().await;
+    let program_id = fixture.bank.program_id;
+    let (admin, state, sender) = (fixture.admin, fixture.state, fixture.sender);
+    let set_limit = fixture.admin_ix(instruction::SET_RATE_LIMIT, &[&1_000u64.to_le_bytes()]);
+    fixture.bank.process(&set_limit).await.unwrap();
+    let proof_accounts = |key: &Pubkey| {
+        let seeds = [seeds::RATE_LIMIT, state.as_ref(), key.as_ref()];
+        [
+            AccountMeta::new_readonly(sysvar::instructions::ID, false),
+            AccountMeta::new(Pubkey::find_program_address(&seeds, &program_id).0, false),
+            AccountMeta::new_readonly(program_id, false),
+        ]
+    };
+
+    // The vault's payouts count against the admin's limit
+    let payout = |fixture: &Fixture, amount| fixture.payout_ix(amount, &proof_accounts(&admin));
+    fixture.fund_vault(10_000_000);
+    fixture
+        .bank
+        .process(&payout(&fixture, 1_000))
+        .await
+        .unwrap();
+    assert_eq!(
+        fixture.bank.process(&payout(&fixture, 1)).await,
+        error(ExclusionError::RateLimitExceeded)
+    );
+
+    // A sender who used up their limit can make no other transfer
+    let used_up = fixture.transfer_ix(1_000, &fixture.witness(), &proof_accounts(&sender));
+    fixture.bank.process(&used_up).await.unwrap();
+    for (path, instructions) in sender_funded_transfers(&mut fixture, 1, &proof_accounts(&sender)) {
+        assert_eq!(
+            fixture.bank.process_all(&instructions).await,
+            error(ExclusionError::RateLimitExceeded),
+            "{path}"
+        );
+        assert!(!fixture.bank.verified().is_empty(), "{path}");
+    }
+}
+
+#[tokio::test]
+async fn charges_every_sender_funded_transfer() {
+    let mut fixture = Fixture::new().await;
+    let program_id = fixture.bank.program_id;
+    let treasury =
+        Pubkey::find_program_address(&[seeds::TREASURY, fixture.state.as_ref()], &program_id).0;
+    let mut set_fee = fixture.admin_ix(instruction::SET_FEE, &[&100u16.to_le_bytes()]);
+    set_fee.accounts.push(AccountMeta::new(treasury, false));
+    set_fee
+        .accounts
+        .push(AccountMeta::new_readonly(system_program::ID, false));
+    fixture.bank.process(&set_fee).await.unwrap();
+    let proof_accounts = [
+        AccountMeta::new_readonly(sysvar::instructions::ID, false),
+        AccountMeta::new_readonly(program_id, false),
+        AccountMeta::new(treasury, false),
+    ];
+
+    // The vault pays the fee on its payouts
+    fixture.fund_vault(10_000_000);
+    let payout = fixture.payout_ix(10_000, &proof_accounts);
+    let mut paid = vec![("vault", vec![payout])];
+    // A cNFT carries no lamports to charge
+    paid.extend(
+        sender_funded_transfers(&mut fixture, 10_000, &proof_accounts)
+            .into_iter()
+            .filter(|(path, _)| *path != "cNFT"),
+    );
+    for (path, instructions) in paid {
+        let collected = fixture.bank.lamports(&treasury).await;
+        fixture.bank.process_all(&instructions).await.unwrap();
+        assert_eq!(
+            fixture.bank.lamports(&treasury).await,
+            collected + 100,
+            "{path}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn transitions_start_from_the_stored_root() {
+    let mut fixture = Fixture::new().await;
+    let (admin, state, key) = (fixture.admin, fixture.state, fixture.sender);
+    fixture
+        .bank
+        .edit_state(&state, |state| state.smt_root = [4; 32])
+        .await;
+    let ix = fixture.transition_ix(&admin, &key, &[5; 32]);
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        error(ExclusionError::TransitionRootMismatch)
+    );
+    assert_eq!(fixture.bank.state(&state).await.smt_root, [4; 32]);
+}
+
+#[tokio::test]
+async fn rejects_siblings_outside_the_field() {
+    let mut fixture = Fixture::new().await;
+    let key = fixture.sender;
+    // The level 0 sibling is the largest 32-byte value, past the BN254 modulus
+    let mut bitmap = [0; 32];
+    bitmap[0] = 1;
+    let ix = fixture.admin_ix(
+        instruction::INSERT_LEAF,
+        &[key.as_ref(), &bitmap, &[0xff; 32]],
+    );
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        error(ExclusionError::PoseidonHashFailed)
+    );
+    assert_eq!(fixture.bank.state(&fixture.state).await.smt_root, ROOT);
+}
+
+#[tokio::test]
+async fn sets_the_tree_kind_and_depth() {
+    let mut fixture = Fixture::new().await;
+    let state = fixture.state;
+    let ix = fixture.admin_ix(instruction::SET_TREE_KIND, &[&[u8::MAX]]);
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        error(ExclusionError::UnsupportedTreeKind)
+    );
+    let ix = fixture.admin_ix(instruction::SET_TREE_DEPTH, &[&21u16.to_le_bytes()]);
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        error(ExclusionError::UnsupportedTreeDepth)
+    );
+    let ix = fixture.admin_ix(instruction::SET_TREE_DEPTH, &[&20u16.to_le_bytes()]);
+    fixture.bank.process(&ix).await.unwrap();
+    assert_eq!(fixture.bank.state(&state).await.tree_depth, 20);
+
+    // An indexed tree has no sparse paths to update
+    let ix = fixture.admin_ix(instruction::SET_TREE_KIND, &[&[tree_kind::INDEXED]]);
+    fixture.bank.process(&ix).await.unwrap();
+    let key = fixture.sender;
+    let ix = fixture.admin_ix(instruction::INSERT_LEAF, &[key.as_ref(), &[0; 32]]);
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        error(ExclusionError::UnsupportedTreeKind)
+    );
+}
+
+#[tokio::test]
+async fn relays_only_what_the_sender_authorized() {
+    let mut fixture = Fixture::new().await;
+    let program_id = fixture.bank.program_id;
+    let sender = fixture.sender;
+    let relay = Pubkey::find_program_address(&[seeds::RELAY, sender.as_ref()], &program_id).0;
+    let create = |relay| {
+        ix(
+            &program_id,
+            instruction::CREATE_RELAY_ACCOUNT,
+            vec![
+                AccountMeta::new(sender, true),
+                AccountMeta::new(relay, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            &[],
+        )
+    };
+    assert_eq!(
+        fixture.bank.process(&create(Pubkey::new_unique())).await,
+        error(ExclusionError::InvalidRelayAccount)
+    );
+    fixture.bank.process(&create(relay)).await.unwrap();
+
+    // The relay transaction has no ed25519 instruction before this one
+    let relayed = |fixture: &Fixture, relay, nonce: u64| {
+        ix(
+            &program_id,
+            instruction::TRANSFER_SOL_RELAYED,
+            vec![
+                AccountMeta::new_readonly(sender, false),
+                AccountMeta::new(fixture.recipient, false),
+                AccountMeta::new_readonly(fixture.state, false),
+                AccountMeta::new_readonly(ZK_VERIFIER_PROGRAM_ID, false),
+                AccountMeta::new(relay, false),
+                AccountMeta::new_readonly(sysvar::instructions::ID, false),
+                AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+            ],
+            &[
+                &1_000u64.to_le_bytes(),
+                &nonce.to_le_bytes(),
+                &prove(fixture.witness().as_bytes()).as_bytes()[..],
+                fixture.witness().as_bytes(),
+            ],
+        )
+    };
+    let cases = [
+        (Pubkey::new_unique(), 0, ExclusionError::InvalidRelayAccount),
+        (relay, 1, ExclusionError::RelayNonceMismatch),
+        (relay, 0, ExclusionError::InvalidSenderAuthorization),
+    ];
+    for (relay, nonce, expected) in cases {
+        let ix = relayed(&fixture, relay, nonce);
+        assert_eq!(
+            fixture.bank.process(&ix).await,
+            error(expected),
+            "{expected:?}"
+        );
+    }
+    assert_eq!(fixture.bank.lamports(&fixture.recipient).await, BALANCE);
+}
+
+#[tokio::test]
+async fn holds_escrows_until_released() {
+    let mut fixture = Fixture::new().await;
+    let program_id = fixture.bank.program_id;
+    let (sender, recipient, state) = (fixture.sender, fixture.recipient, fixture.state);
+    let id = 1u64.to_le_bytes();
+    let escrow = Pubkey::find_program_address(
+        &[seeds::ESCROW, sender.as_ref(), recipient.as_ref(), &id],
+        &program_id,
+    )
+    .0;
+    let lock = |fixture: &Fixture, escrow| {
+        ix(
+            &program_id,
+            instruction::TRANSFER_TO_ESCROW,
+            vec![
+                AccountMeta::new(sender, true),
+                AccountMeta::new_readonly(recipient, false),
+                AccountMeta::new(escrow, false),
+                AccountMeta::new_readonly(state, false),
+                AccountMeta::new_readonly(ZK_VERIFIER_PROGRAM_ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            &[
+                &5_000u64.to_le_bytes(),
+                &id,
+                &60i64.to_le_bytes(),
+                &[escrow_flags::REQUIRE_RECIPIENT_PROOF],
+                &prove(fixture.witness().as_bytes()).as_bytes()[..],
+                fixture.witness().as_bytes(),
+            ],
+        )
+    };
+    let misplaced = lock(&fixture, Pubkey::new_unique());
+    assert_eq!(
+        fixture.bank.process(&misplaced).await,
+        error(ExclusionError::InvalidEscrowAccount)
+    );
+    let locked = lock(&fixture, escrow);
+    fixture.bank.process(&locked).await.unwrap();
+
+    // A claim of `version`, carrying the recipient's proof if given a witness
+    let claim_of = |version: u8, witness: Option<&PublicWitness>| {
+        let proof = witness.map(|witness| {
+            [
+                &prove(witness.as_bytes()).as_bytes()[..],
+                witness.as_bytes(),
+            ]
+            .concat()
+        });
+        let mut ix = ix(
+            &program_id,
+            instruction::CLAIM_FROM_ESCROW,
+            vec![
+                AccountMeta::new(recipient, true),
+                AccountMeta::new(sender, false),
+                AccountMeta::new(escrow, false),
+                AccountMeta::new_readonly(state, false),
+                AccountMeta::new_readonly(ZK_VERIFIER_PROGRAM_ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            &[proof.as_deref().unwrap_or_default()],
+        );
+        ix.data[1] = version;
+        ix
+    };
+    let claim = |witness: Option<&PublicWitness>| claim_of(1 + witness.is_some() as u8, witness);
+    assert_eq!(
+        fixture.bank.process(&claim(None)).await,
+        error(ExclusionError::EscrowLocked)
+    );
+    fixture.bank.set_time(NOW + 60);
+    assert_eq!(
+        fixture.bank.process(&claim(None)).await,
+        error(ExclusionError::RecipientProofRequired)
+    );
+    let witness = PublicWitness::new(&ROOT, &pubkey_to_index(&recipient.to_bytes()), SLOT - 1);
+    // The version, not the data's length, says whether a proof is carried
+    for (version, witness) in [(1, Some(&witness)), (2, None)] {
+        assert_eq!(
+            fixture.bank.process(&claim_of(version, witness)).await,
+            error(ExclusionError::InvalidDataLength),
+            "version {version}"
+        );
+    }
+    fixture.bank.process(&claim(Some(&witness))).await.unwrap();
+    assert_eq!(fixture.bank.lamports(&recipient).await, BALANCE + 5_000);
+    assert_eq!(fixture.bank.lamports(&escrow).await, 0);
+}
+
+#[tokio::test]
+async fn pays_only_from_the_admins_vault() {
+    let mut fixture = Fixture::new().await;
+    let recipient = fixture.recipient;
+    let witness = PublicWitness::new(&ROOT, &pubkey_to_index(&recipient.to_bytes()), SLOT - 1);
+    let vault = fixture.bank.funded(1_000_000);
+    let mut ix = fixture.admin_ix(
+        instruction::TRANSFER_FROM_VAULT,
+        &[
+            &1_000u64.to_le_bytes(),
+            prove(witness.as_bytes()).as_bytes(),
+            witness.as_bytes(),
+        ],
+    );
+    ix.accounts = vec![
+        AccountMeta::new_readonly(fixture.admin, true),
+        AccountMeta::new(recipient, false),
+        AccountMeta::new_readonly(fixture.state, false),
+        AccountMeta::new_readonly(ZK_VERIFIER_PROGRAM_ID, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new(vault, false),
+    ];
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        error(ExclusionError::InvalidVaultAccount)
+    );
+    assert_eq!(fixture.bank.lamports(&recipient).await, BALANCE);
+}
+
+#[tokio::test]
+async fn withdraws_the_bond_after_the_delay() {
+    let mut fixture = Fixture::new().await;
+    let program_id = fixture.bank.program_id;
+    let (admin, state) = (fixture.admin, fixture.state);
+    let bond = Pubkey::find_program_address(&[seeds::BOND, state.as_ref()], &program_id).0;
+    let mut deposit = fixture.admin_ix(instruction::DEPOSIT_BOND, &[&5_000u64.to_le_bytes()]);
+    deposit.accounts.extend([
+        AccountMeta::new(bond, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ]);
+    fixture.bank.process(&deposit).await.unwrap();
+
+    let withdraw = |bond| {
+        ix(
+            &program_id,
+            instruction::WITHDRAW_BOND,
+            vec![
+                AccountMeta::new(admin, true),
+                AccountMeta::new(state, false),
+                AccountMeta::new(bond, false),
+                AccountMeta::new(admin, false),
+            ],
+            &[],
+        )
+    };
+    assert_eq!(
+        fixture.bank.process(&withdraw(Pubkey::new_unique())).await,
+        error(ExclusionError::InvalidBondAccount)
+    );
+    // Nothing requested yet, then requested but still in its delay
+    assert_eq!(
+        fixture.bank.process(&withdraw(bond)).await,
+        error(ExclusionError::BondWithdrawalLocked)
+    );
+    let request = fixture.admin_ix(
+        instruction::REQUEST_BOND_WITHDRAWAL,
+        &[&2_000u64.to_le_bytes()],
+    );
+    fixture.bank.process(&request).await.unwrap();
+    fixture.bank.set_time(NOW + BOND_WITHDRAWAL_DELAY - 1);
+    assert_eq!(
+        fixture.bank.process(&withdraw(bond)).await,
+        error(ExclusionError::BondWithdrawalLocked)
+    );
+    fixture.bank.set_time(NOW + BOND_WITHDRAWAL_DELAY);
+    let balance = fixture.bank.lamports(&admin).await;
+    fixture.bank.process(&withdraw(bond)).await.unwrap();
+    assert_eq!(fixture.bank.lamports(&admin).await, balance + 2_000);
+    assert_eq!(fixture.bank.state(&state).await.bond_amount, 3_000);
+}
+
+#[tokio::test]
+async fn queues_self_exclusions_up_to_capacity() {
+    let mut fixture = Fixture::new().await;
+    let program_id = fixture.bank.program_id;
+    let state = fixture.state;
+    let queue =
+        Pubkey::find_program_address(&[seeds::EXCLUSION_QUEUE, state.as_ref()], &program_id).0;
+    let exclude = |user, queue| {
+        ix(
+            &program_id,
+            instruction::SELF_EXCLUDE,
+            vec![
+                AccountMeta::new(user, true),
+                AccountMeta::new_readonly(state, false),
+                AccountMeta::new(queue, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            &[],
+        )
+    };
+    let user = fixture.sender;
+    assert_eq!(
+        fixture
+            .bank
+            .process(&exclude(user, Pubkey::new_unique()))
+            .await,
+        error(ExclusionError::InvalidExclusionQueue)
+    );
+    for _ in 0..EXCLUSION_QUEUE_CAPACITY {
+        let user = fixture.bank.funded(1_000_000_000);
+        fixture.bank.process(&exclude(user, queue)).await.unwrap();
+    }
+    assert_eq!(
+        fixture.bank.process(&exclude(user, queue)).await,
+        error(ExclusionError::ExclusionQueueFull)
+    );
+}
+
+#[tokio::test]
+async fn manages_the_verifier_registry() {
+    let mut bank = Bank::new().await;
+    let program_id = bank.program_id;
+    let upgrade_authority = bank.funded(1_000_000_000);
+    let governance = bank.funded(0);
+    // ProgramData: tag 3, slot, then Some(upgrade authority)
+    let program_data =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::ID).0;
+    let mut data = [3u32.to_le_bytes().as_slice(), &[0; 8], &[1]].concat();
+    data.extend_from_slice(upgrade_authority.as_ref());
+    bank.insert(&program_data, &bpf_loader_upgradeable::ID, data);
+    let registry = Pubkey::find_program_address(&[seeds::VERIFIER_REGISTRY], &program_id).0;
+    let appoint = |signer, registry| {
+        ix(
+            &program_id,
+            instruction::SET_REGISTRY_AUTHORITY,
+            vec![
+                AccountMeta::new(signer, true),
+                AccountMeta::new(registry, false),
+                AccountMeta::new_readonly(program_data, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            &[governance.as_ref()],
+        )
+    };
+    let intruder = bank.funded(1_000_000_000);
+    assert_eq!(
+        bank.process(&appoint(intruder, registry)).await,
+        error(ExclusionError::UnauthorizedUpgradeAuthority)
+    );
+    assert_eq!(
+        bank.process(&appoint(upgrade_authority, Pubkey::new_unique()))
+            .await,
+        error(ExclusionError::InvalidVerifierRegistry)
+    );
+    bank.process(&appoint(upgrade_authority, registry))
+        .await
+        .unwrap();
+
+    let register_for = |signer, verifier: &Pubkey, kind: u8, depth: u16| {
+        ix(
+            &program_id,
+            instruction::REGISTER_VERIFIER,
+            vec![
+                AccountMeta::new_readonly(signer, true),
+                AccountMeta::new(registry, false),
+            ],
+            &[verifier.as_ref(), &[1; 32], &[kind], &depth.to_le_bytes()],
+        )
+    };
+    let register =
+        |signer, verifier: &Pubkey| register_for(signer, verifier, tree_kind::SPARSE, 254);
+    assert_eq!(
+        bank.process(&register(upgrade_authority, &Pubkey::new_unique()))
+            .await,
+        error(ExclusionError::InvalidVerifierRegistry)
+    );
+    // For a tree a state can have
+    assert_eq!(
+        bank.process(&register_for(governance, &Pubkey::new_unique(), 2, 254))
+            .await,
+        error(ExclusionError::UnsupportedTreeKind)
+    );
+    assert_eq!(
+        bank.process(&register_for(
+            governance,
+            &Pubkey::new_unique(),
+            tree_kind::SPARSE,
+            100
+        ))
+        .await,
+        error(ExclusionError::UnsupportedTreeDepth)
+    );
+    let verifiers: Vec<_> = (0..MAX_REGISTERED_VERIFIERS)
+        .map(|_| Pubkey::new_unique())
+        .collect();
+    for verifier in &verifiers {
+        bank.process(&register(governance, verifier)).await.unwrap();
+    }
+    assert_eq!(
+        bank.process(&register(governance, &Pubkey::new_unique()))
+            .await,
+        error(ExclusionError::VerifierRegistryFull)
+    );
+    // A listed verifier's circuit hash can still be updated
+    bank.process(&register(governance, &verifiers[0]))
+        .await
+        .unwrap();
+}
+
+/// Verifier registry data listing `REGISTERED_VERIFIER` for a tree of `kind`
+/// and `depth`
+fn registry_listing(program_id: &Pubkey, kind: u8, depth: u16) -> Vec<u8> {
+    let bump = Pubkey::find_program_address(&[seeds::VERIFIER_REGISTRY], program_id).1;
+    let mut data = vec![0; VERIFIER_REGISTRY_SIZE];
+    data[..8].copy_from_slice(&VERIFIER_REGISTRY_DISCRIMINATOR);
+    data[verifier_registry_offsets::BUMP] = bump;
+    data[verifier_registry_offsets::COUNT] = 1;
+    let entry = &mut data[verifier_registry_offsets::VERIFIERS..][..VERIFIER_REGISTRY_ENTRY_SIZE];
+    entry[..32].copy_from_slice(REGISTERED_VERIFIER.as_ref());
+    entry[64] = kind;
+    entry[65..].copy_from_slice(&depth.to_le_bytes());
+    data
+}
+
+#[tokio::test]
+async fn accepts_registered_verifiers_only_for_their_tree() {
+    let mut fixture = Fixture::new().await;
+    let program_id = fixture.bank.program_id;
+    let verifier = REGISTERED_VERIFIER;
+    let registry = Pubkey::find_program_address(&[seeds::VERIFIER_REGISTRY], &program_id).0;
+    let listing = |kind, depth| registry_listing(&program_id, kind, depth);
+    let transfer = |fixture: &Fixture, registry: Pubkey| {
+        let mut ix = fixture.transfer_ix(1, &fixture.witness(), &[]);
+        ix.accounts[3].pubkey = verifier;
+        ix.accounts.push(AccountMeta::new_readonly(registry, false));
+        ix
+    };
+    let ix = transfer(&fixture, registry);
+
+    // Built for the state's tree
+    let depth = smt::TREE_DEPTH as u16;
+    fixture
+        .bank
+        .insert(&registry, &program_id, listing(tree_kind::SPARSE, depth));
+    fixture.bank.process(&ix).await.unwrap();
+    assert_eq!(fixture.bank.verified()[0].0, verifier);
+
+    // Not for a tree of another depth
+    let set_depth = fixture.admin_ix(instruction::SET_TREE_DEPTH, &[&20u16.to_le_bytes()]);
+    fixture.bank.process(&set_depth).await.unwrap();
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        error(ExclusionError::InvalidZkVerifier)
+    );
+    // Which the configured verifier, built for the default depth, can't prove
+    let configured = fixture.transfer_ix(1, &fixture.witness(), &[]);
+    assert_eq!(
+        fixture.bank.process(&configured).await,
+        error(ExclusionError::UnsupportedTreeDepth)
+    );
+    fixture
+        .bank
+        .insert(&registry, &program_id, listing(tree_kind::SPARSE, 20));
+    fixture.bank.process(&ix).await.unwrap();
+
+    // Or kind
+    let set_kind = fixture.admin_ix(instruction::SET_TREE_KIND, &[&[tree_kind::INDEXED]]);
+    fixture.bank.process(&set_kind).await.unwrap();
+    assert_eq!(
+        fixture.bank.process(&ix).await,
+        error(ExclusionError::InvalidZkVerifier)
+    );
+
+    // And only from the registry PDA
+    let lookalike = Pubkey::new_unique();
+    fixture
+        .bank
+        .insert(&lookalike, &program_id, listing(tree_kind::INDEXED, 20));
+    assert_eq!(
+        fixture.bank.process(&transfer(&fixture, lookalike)).await,
+        error(ExclusionError::InvalidVerifierRegistry)
+    );
+}
+
+#[tokio::test]
+async fn upholds_challenges_to_registered_verifiers() {
+    let mut fixture = Fixture::new().await;
+    let program_id = fixture.bank.program_id;
+    let state = fixture.state;
+    let registry = Pubkey::find_program_address(&[seeds::VERIFIER_REGISTRY], &program_id).0;
+    let bond = Pubkey::find_program_address(&[seeds::BOND, state.as_ref()], &program_id).0;
+    let challenger = fixture.bank.funded(1_000_000_000);
+
+    // A 20-level tree holding the key a proof says is excluded
+    let depth = 20;
+    let key = pubkey_to_index(&fixture.sender.to_bytes());
+    let root =
+        smt::compute_root(&key, &smt::leaf_value(&key, depth), depth, &[0; 32], &[]).unwrap();
+    fixture
+        .bank
+        .edit_state(&state, |state| {
+            state.smt_root = root;
+            state.tree_depth = depth as u16;
+        })
+        .await;
+    let witness = PublicWitness::new(&root, &key, SLOT - 1);
+    let challenge = |verifier| {
+        ix(
+            &program_id,
+            instruction::CHALLENGE,
+            vec![
+                AccountMeta::new(challenger, true),
+                AccountMeta::new(state, false),
+                AccountMeta::new_readonly(verifier, false),
+                AccountMeta::new(bond, false),
+                AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+                AccountMeta::new_readonly(registry, false),
+            ],
+            &[
+                prove(witness.as_bytes()).as_bytes(),
+                witness.as_bytes(),
+                &[0; 32],
+            ],
+        )
+    };
+
+    // The configured verifier is built for the default depth
+    assert_eq!(
+        fixture
+            .bank
+            .process(&challenge(ZK_VERIFIER_PROGRAM_ID))
+            .await,
+        error(ExclusionError::UnsupportedTreeDepth)
+    );
+    // A verifier the registry doesn't list for the tree is no evidence
+    fixture.bank.insert(
+        &registry,
+        &program_id,
+        registry_listing(&program_id, tree_kind::SPARSE, smt::TREE_DEPTH as u16),
+    );
+    assert_eq!(
+        fixture.bank.process(&challenge(REGISTERED_VERIFIER)).await,
+        error(ExclusionError::InvalidZkVerifier)
+    );
+    assert!(!fixture.bank.state(&state).await.challenged);
+
+    // One it lists for the tree is, as it would be for a transfer
+    fixture.bank.insert(
+        &registry,
+        &program_id,
+        registry_listing(&program_id, tree_kind::SPARSE, depth as u16),
+    );
+    fixture
+        .bank
+        .process(&challenge(REGISTERED_VERIFIER))
+        .await
+        .unwrap();
+    assert_eq!(fixture.bank.verified()[0].0, REGISTERED_VERIFIER);
+    assert!(fixture.bank.state(&state).await.challenged);
+}
+
+#[tokio::test]
+async fn creates_proof_buffers_at_the_owners_pda() {
+    let mut bank = Bank::new().await;
+    let program_id = bank.program_id;
+    let owner = bank.funded(1_000_000_000);
+    let create = ix(
+        &program_id,
+        instruction::CREATE_PROOF_BUFFER,
+        vec![
+            AccountMeta::new(owner, true),
+            AccountMeta::new(Pubkey::new_unique(), false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        &[],
+    );
+    assert_eq!(
+        bank.process(&create).await,
+        error(ExclusionError::InvalidProofBuffer)
+    );
+}
+
+#[tokio::test]
+async fn signs_only_for_the_programs_it_integrates() {
+    type Case = (ExclusionError, fn(&Fixture) -> Instruction);
+    fn any() -> AccountMeta {
+        AccountMeta::new(Pubkey::new_unique(), false)
+    }
+    fn proof(fixture: &Fixture, len: usize) -> Vec<u8> {
+        let mut data = [
+            &prove(fixture.witness().as_bytes()).as_bytes()[..],
+            fixture.witness().as_bytes(),
+        ]
+        .concat();
+        data.resize(len, 0);
+        data
+    }
+    let cases: [Case; 6] = [
+        (ExclusionError::InvalidAttestationAccount, |f| {
+            let accounts = vec![
+                AccountMeta::new(f.sender, true),
+                AccountMeta::new_readonly(f.state, false),
+                AccountMeta::new_readonly(ZK_VERIFIER_PROGRAM_ID, false),
+                any(),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ];
+            let data = proof(f, instruction::ATTEST_LEN);
+            ix(&f.bank.program_id, instruction::ATTEST, accounts, &[&data])
+        }),
+        (ExclusionError::InvalidLightAccount, |f| {
+            let mut accounts = vec![
+                AccountMeta::new(f.sender, true),
+                AccountMeta::new_readonly(f.state, false),
+                AccountMeta::new_readonly(ZK_VERIFIER_PROGRAM_ID, false),
+            ];
+            accounts.extend((0..11).map(|_| any()));
+            let data = proof(f, instruction::ATTEST_COMPRESSED_LEN);
+            let program_id = f.bank.program_id;
+            ix(
+                &program_id,
+                instruction::ATTEST_COMPRESSED,
+                accounts,
+                &[&data],
+            )
+        }),
+        (ExclusionError::InvalidLightAccount, |f| {
+            // The Light system program, with another CPI authority
+            let mut accounts = vec![
+                AccountMeta::new(f.sender, true),
+                AccountMeta::new_readonly(f.state, false),
+                AccountMeta::new_readonly(ZK_VERIFIER_PROGRAM_ID, false),
+                AccountMeta::new_readonly(light::LIGHT_SYSTEM_PROGRAM_ID, false),
+            ];
+            accounts.extend((0..10).map(|_| any()));
+            let data = proof(f, instruction::ATTEST_COMPRESSED_LEN);
+            let program_id = f.bank.program_id;
+            ix(
+                &program_id,
+                instruction::ATTEST_COMPRESSED,
+                accounts,
+                &[&data],
+            )
+        }),
+        (ExclusionError::InvalidBubblegumProgram, |f| {
+            let mut accounts = vec![
+                AccountMeta::new_readonly(f.sender, true),
+                any(),
+                any(),
+                AccountMeta::new_readonly(f.state, false),
+                AccountMeta::new_readonly(ZK_VERIFIER_PROGRAM_ID, false),
+            ];
+            accounts.extend((0..6).map(|_| any()));
+            // No proof nodes
+            let data = proof(f, instruction::TRANSFER_CNFT_LEN);
+            ix(
+                &f.bank.program_id,
+                instruction::TRANSFER_CNFT,
+                accounts,
+                &[&data],
+            )
+        }),
+        (ExclusionError::InvalidWormholeAccount, |f| {
+            let mut accounts = vec![
+                AccountMeta::new(f.sender, true),
+                AccountMeta::new_readonly(f.state, false),
+            ];
+            accounts.extend((0..9).map(|_| any()));
+            ix(&f.bank.program_id, instruction::PUBLISH_ROOT, accounts, &[])
+        }),
+        (ExclusionError::InvalidWormholeAccount, |f| {
+            // The core bridge, with another emitter and message
+            let mut accounts = vec![
+                AccountMeta::new(f.sender, true),
+                AccountMeta::new_readonly(f.state, false),
+                AccountMeta::new_readonly(wormhole::CORE_BRIDGE_PROGRAM_ID, false),
+            ];
+            accounts.extend((0..8).map(|_| any()));
+            ix(&f.bank.program_id, instruction::PUBLISH_ROOT, accounts, &[])
+        }),
+    ];
+    for (expected, case) in cases {
+        let mut fixture = Fixture::new().await;
+        let ix = case(&fixture);
+        assert_eq!(
+            fixture.bank.process(&ix).await,
+            error(expected),
+            "{expected:?}"
+        );
+        assert!(fixture.bank.verified().is_empty(), "{expected:?}");
+    }
+}
+
+/// The compute units of the instructions that CPI into a verifier, under
+/// `cargo test-sbf -- --ignored --nocapture compute_units`. The stub verifier's
+/// own units, for a hash of the data and a log, are included.
+#[tokio::test]
+#[ignore = "the builtin isn't metered; run under cargo test-sbf"]
+async fn reports_verifier_cpi_compute_units() {
+    let mut fixture = Fixture::new().await;
+    fixture.transfer(1_000).await.unwrap();
+    println!("TRANSFER_SOL: {} CUs", fixture.bank.consumed());
+
+    let (admin, key) = (fixture.admin, fixture.sender);
+    let ix = fixture.transition_ix(&admin, &key, &[4; 32]);
+    fixture.bank.process(&ix).await.unwrap();
+    println!("SUBMIT_ROOT_TRANSITION: {} CUs", fixture.bank.consumed());
+}
+
+/// The compute units of `SET_SMT_ROOT` and a fee-charging `TRANSFER_SOL`, under
+/// `cargo test-sbf -- --ignored --nocapture compute_units`, with and without
+/// `--features search-bumps`. The transfer checks its treasury with the bump
+/// stored in the state, or with the feature searches for it. `SET_SMT_ROOT`
+/// derives no PDA, so the feature doesn't change its cost.
+#[tokio::test]
+#[ignore = "the builtin isn't metered; run under cargo test-sbf"]
+async fn reports_stored_bump_compute_units() {
+    let mut fixture = Fixture::new().await;
+    let program_id = fixture.bank.program_id;
+    let (admin, state) = (fixture.admin, fixture.state);
+    let ix = set_smt_root_ix(&program_id, &admin, &state, &ROOT, 0);
+    fixture.bank.process(&ix).await.unwrap();
+    println!("SET_SMT_ROOT: {} CUs", fixture.bank.consumed());
+
+    let treasury = Pubkey::find_program_address(&[seeds::TREASURY, state.as_ref()], &program_id).0;
+    let mut set_fee = fixture.admin_ix(instruction::SET_FEE, &[&100u16.to_le_bytes()]);
+    set_fee.accounts.extend([
+        AccountMeta::new(treasury, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ]);
+    fixture.bank.process(&set_fee).await.unwrap();
+    // The rate limit account goes unread without a daily limit
+    let extra = [
+        AccountMeta::new_readonly(sysvar::instructions::ID, false),
+        AccountMeta::new_readonly(Pubkey::new_unique(), false),
+        AccountMeta::new(treasury, false),
+    ];
+    let ix = fixture.transfer_ix(10_000, &fixture.witness(), &extra);
+    fixture.bank.process(&ix).await.unwrap();
+    let bump = fixture.bank.account(&state).await.data[state_offsets::TREASURY_BUMP];
+    let source = if cfg!(feature = "search-bumps") {
+        "searched"
+    } else {
+        "stored"
+    };
+    println!(
+        "TRANSFER_SOL with a fee, {source} treasury bump {bump}: {} CUs",
+        fixture.bank.consumed()
+    );
+}
@@ -0,0 +1,17 @@
+//! Checks the `VerificationResult` return data layout CPI callers decode.
+
+use exclusion_program_example::instruction::VerificationResult;
+
+#[test]
+fn round_trips_through_return_data() {
+    let result = VerificationResult {
+        smt_root: [1; 32],
+        pubkey_hash: [2; 32],
+        amount: 1_000_000,
+    };
+    let data = result.pack();
+    assert_eq!(data.len(), VerificationResult::LEN);
+    assert_eq!(&data[64..72], &1_000_000u64.to_le_bytes());
+    assert_eq!(VerificationResult::unpack(&data), Some(result));
+    assert_eq!(VerificationResult::unpack(&data[..71]), None);
+}
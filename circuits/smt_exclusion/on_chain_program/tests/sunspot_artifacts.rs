@@ -0,0 +1,103 @@
+//! Checks the fixed proof/witness layout assumed by the program against the
+//! artifacts of each sunspot/gnark release under `tests/fixtures/sunspot/`.
+
+use std::{fs, path::Path};
+
+use exclusion_program_example::instruction::{
+    WitnessHeader, NUM_PUBLIC_INPUTS, PROOF_SIZE, WITNESS_SIZE,
+};
+
+/// gnark's Groth16 proof: Ar (64), Bs (128) and Krs (64), then from v0.9 a
+/// big-endian u32 commitment count, 64 bytes per commitment and a 64-byte
+/// proof of knowledge of them
+const PROOF_POINTS_SIZE: usize = 64 + 128 + 64;
+
+/// The commitment count a proof's bytes declare, or `None` for a proof from
+/// before commitments
+fn commitment_count(proof: &[u8], name: &str) -> Option<u32> {
+    if proof.len() == PROOF_POINTS_SIZE {
+        return None;
+    }
+    let count = proof
+        .get(PROOF_POINTS_SIZE..PROOF_POINTS_SIZE + 4)
+        .unwrap_or_else(|| panic!("{name}: proof shorter than its points"));
+    let count = u32::from_be_bytes(count.try_into().unwrap());
+    assert_eq!(
+        proof.len(),
+        PROOF_POINTS_SIZE + 4 + 64 * count as usize + 64,
+        "{name}: proof length for {count} commitments"
+    );
+    Some(count)
+}
+
+struct Expected {
+    proof_size: usize,
+    nb_commitments: u32,
+    witness_size: usize,
+    nb_public: u32,
+    accepted: bool,
+}
+
+fn parse_expected(path: &Path) -> Expected {
+    let text = fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+    let value = |key: &str| -> &str {
+        text.lines()
+            .filter_map(|line| line.split('#').next()?.split_once('='))
+            .find(|(k, _)| k.trim() == key)
+            .map(|(_, v)| v.trim())
+            .unwrap_or_else(|| panic!("{}: missing `{key}`", path.display()))
+    };
+    Expected {
+        proof_size: value("proof_size").parse().unwrap(),
+        nb_commitments: value("nb_commitments").parse().unwrap(),
+        witness_size: value("witness_size").parse().unwrap(),
+        nb_public: value("nb_public").parse().unwrap(),
+        accepted: value("accepted").parse().unwrap(),
+    }
+}
+
+#[test]
+fn sunspot_releases_match_expected_layout() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sunspot");
+    let mut releases: Vec<_> = fs::read_dir(&root)
+        .unwrap()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    releases.sort();
+
+    assert!(
+        releases.len() >= 2,
+        "{} must hold at least two releases, has {}",
+        root.display(),
+        releases.len()
+    );
+
+    for release in releases {
+        let name = release.file_name().unwrap().to_string_lossy().into_owned();
+        let expected = parse_expected(&release.join("expected.toml"));
+        let proof = fs::read(release.join("smt_exclusion.proof")).unwrap();
+        let witness = fs::read(release.join("smt_exclusion.pw")).unwrap();
+        let header = WitnessHeader::unpack(&witness)
+            .unwrap_or_else(|| panic!("{name}: witness shorter than header"));
+
+        assert_eq!(proof.len(), expected.proof_size, "{name}: proof size");
+        assert_eq!(
+            commitment_count(&proof, &name).unwrap_or(0),
+            expected.nb_commitments,
+            "{name}: commitments"
+        );
+        assert_eq!(witness.len(), expected.witness_size, "{name}: witness size");
+        assert_eq!(header.nb_public, expected.nb_public, "{name}: nb_public");
+        assert_eq!(
+            header.witness_size(),
+            witness.len(),
+            "{name}: header length"
+        );
+
+        let accepted = proof.len() == PROOF_SIZE
+            && witness.len() == WITNESS_SIZE
+            && header.nb_public == NUM_PUBLIC_INPUTS;
+        assert_eq!(accepted, expected.accepted, "{name}: acceptance");
+    }
+}
@@ -0,0 +1,56 @@
+//! Checks the hand-encoded Wormhole `post_message` and root payload layouts.
+
+use exclusion_program_example::wormhole::{
+    post_message, RootMessage, CONSISTENCY_LEVEL_FINALIZED, CORE_BRIDGE_PROGRAM_ID,
+};
+use solana_program::pubkey::Pubkey;
+
+#[test]
+fn root_message_is_big_endian() {
+    let message = RootMessage {
+        state: Pubkey::new_from_array([1; 32]),
+        smt_root: [2; 32],
+        root_seq: 0x0102,
+        root_valid_until: -1,
+    };
+    let packed = message.pack();
+    assert_eq!(packed[0], RootMessage::PAYLOAD_ID);
+    assert_eq!(&packed[1..33], &[1; 32]);
+    assert_eq!(&packed[33..65], &[2; 32]);
+    assert_eq!(&packed[65..73], &[0, 0, 0, 0, 0, 0, 1, 2]);
+    assert_eq!(&packed[73..81], &[0xff; 8]);
+}
+
+#[test]
+fn post_message_uses_borsh_layout() {
+    let keys: Vec<Pubkey> = (1..=6).map(|i| Pubkey::new_from_array([i; 32])).collect();
+    let ix = post_message(
+        &keys[0], &keys[1], &keys[2], &keys[3], &keys[4], &keys[5], 7, &[9; 3],
+    );
+
+    assert_eq!(ix.program_id, CORE_BRIDGE_PROGRAM_ID);
+    assert_eq!(ix.accounts.len(), 9);
+    let signers: Vec<bool> = ix.accounts.iter().map(|meta| meta.is_signer).collect();
+    assert_eq!(
+        signers,
+        [false, true, true, false, true, false, false, false, false]
+    );
+    assert_eq!(
+        ix.data,
+        [
+            1,
+            7,
+            0,
+            0,
+            0,
+            3,
+            0,
+            0,
+            0,
+            9,
+            9,
+            9,
+            CONSISTENCY_LEVEL_FINALIZED
+        ]
+    );
+}
@@ -0,0 +1,19 @@
+//! Link Barretenberg for the `bb-sys` feature
+//!
+//! `BB_LIB_DIR` is the directory holding `libbarretenberg.a`, as built by
+//! `cmake --build --preset default --target bb` in barretenberg/cpp.
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=BB_LIB_DIR");
+    if std::env::var_os("CARGO_FEATURE_BB_SYS").is_none() {
+        return;
+    }
+    if let Some(dir) = std::env::var_os("BB_LIB_DIR") {
+        println!("cargo:rustc-link-search=native={}", dir.to_string_lossy());
+    }
+    println!("cargo:rustc-link-lib=static=barretenberg");
+    match std::env::var("CARGO_CFG_TARGET_OS").as_deref() {
+        Ok("macos" | "ios") => println!("cargo:rustc-link-lib=c++"),
+        _ => println!("cargo:rustc-link-lib=stdc++"),
+    }
+}
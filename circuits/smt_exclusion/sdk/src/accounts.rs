@@ -0,0 +1,219 @@
+//! Decoders for the program's accounts, from the layouts in `exclusion-common`
+
+use exclusion_common::{
+    instruction::{PROOF_SIZE, WITNESS_SIZE},
+    state::{
+        attestation_offsets, config_offsets, verifier_registry_offsets, ATTESTATION_DISCRIMINATOR,
+        ATTESTATION_SIZE, CONFIG_DISCRIMINATOR, CONFIG_SIZE, ESCROW_ACCOUNT_DISCRIMINATOR,
+        ESCROW_ACCOUNT_SIZE, EXCLUSION_QUEUE_CAPACITY, EXCLUSION_QUEUE_DISCRIMINATOR,
+        EXCLUSION_QUEUE_SIZE, PROOF_BUFFER_DISCRIMINATOR, PROOF_BUFFER_HEADER_SIZE,
+        PROOF_BUFFER_SIZE, RATE_LIMIT_ACCOUNT_DISCRIMINATOR, RATE_LIMIT_ACCOUNT_SIZE,
+        RATE_LIMIT_BUCKETS, RELAY_ACCOUNT_DISCRIMINATOR, RELAY_ACCOUNT_SIZE,
+        VERIFIER_REGISTRY_DISCRIMINATOR, VERIFIER_REGISTRY_ENTRY_SIZE, VERIFIER_REGISTRY_SIZE,
+    },
+    ExclusionError, Proof, PublicWitness, State,
+};
+use solana_pubkey::Pubkey;
+
+/// A state account's fields; fails on other accounts and on layouts that need
+/// `MIGRATE_STATE`
+pub fn decode_state(data: &[u8]) -> Result<State, ExclusionError> {
+    State::unpack(data)
+}
+
+/// A sender's proof buffer, as far as `WRITE_PROOF_CHUNK` has filled it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofBuffer {
+    pub owner: Pubkey,
+    pub proof: Proof,
+    pub witness: PublicWitness,
+}
+
+pub fn decode_proof_buffer(data: &[u8]) -> Result<ProofBuffer, ExclusionError> {
+    if data.len() < PROOF_BUFFER_SIZE || data[..8] != PROOF_BUFFER_DISCRIMINATOR {
+        return Err(ExclusionError::InvalidProofBuffer);
+    }
+    let proof_end = PROOF_BUFFER_HEADER_SIZE + PROOF_SIZE;
+    Ok(ProofBuffer {
+        owner: pubkey(&data[8..40]),
+        proof: Proof::try_from(&data[PROOF_BUFFER_HEADER_SIZE..proof_end])?,
+        witness: PublicWitness::try_from(&data[proof_end..proof_end + WITNESS_SIZE])?,
+    })
+}
+
+/// A relay account: the nonce the owner's next relayed transfer signs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RelayAccount {
+    pub owner: Pubkey,
+    pub nonce: u64,
+}
+
+pub fn decode_relay_account(data: &[u8]) -> Result<RelayAccount, ExclusionError> {
+    if data.len() < RELAY_ACCOUNT_SIZE || data[..8] != RELAY_ACCOUNT_DISCRIMINATOR {
+        return Err(ExclusionError::InvalidRelayAccount);
+    }
+    Ok(RelayAccount {
+        owner: pubkey(&data[8..40]),
+        nonce: u64::from_le_bytes(data[40..48].try_into().unwrap()),
+    })
+}
+
+/// Lamports held for a recipient until `release_at`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EscrowAccount {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    /// State account the sender's proof was checked against
+    pub state: Pubkey,
+    pub escrow_id: u64,
+    pub amount: u64,
+    /// Unix time the recipient may claim from
+    pub release_at: i64,
+    /// See `escrow_flags`
+    pub escrow_flags: u8,
+}
+
+pub fn decode_escrow_account(data: &[u8]) -> Result<EscrowAccount, ExclusionError> {
+    if data.len() < ESCROW_ACCOUNT_SIZE || data[..8] != ESCROW_ACCOUNT_DISCRIMINATOR {
+        return Err(ExclusionError::InvalidEscrowAccount);
+    }
+    let u64_at = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    Ok(EscrowAccount {
+        sender: pubkey(&data[8..40]),
+        recipient: pubkey(&data[40..72]),
+        state: pubkey(&data[72..104]),
+        escrow_id: u64_at(104),
+        amount: u64_at(112),
+        release_at: u64_at(120) as i64,
+        escrow_flags: data[128],
+    })
+}
+
+/// A subject's attestation that it proved exclusion from `state`'s root
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Attestation {
+    pub subject: Pubkey,
+    pub state: Pubkey,
+    pub smt_root: [u8; 32],
+    /// Slot the proof was verified at
+    pub slot: u64,
+}
+
+pub fn decode_attestation(data: &[u8]) -> Result<Attestation, ExclusionError> {
+    use attestation_offsets::*;
+
+    if data.len() < ATTESTATION_SIZE || data[..8] != ATTESTATION_DISCRIMINATOR {
+        return Err(ExclusionError::InvalidAttestationAccount);
+    }
+    Ok(Attestation {
+        subject: pubkey(&data[SUBJECT..SUBJECT + 32]),
+        state: pubkey(&data[STATE..STATE + 32]),
+        smt_root: data[SMT_ROOT..SMT_ROOT + 32].try_into().unwrap(),
+        slot: u64::from_le_bytes(data[SLOT..SLOT + 8].try_into().unwrap()),
+    })
+}
+
+/// The program's config: its pause switch, verifiers and limits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProgramConfig {
+    pub paused: bool,
+    pub zk_verifier: Pubkey,
+    pub transition_verifier: Pubkey,
+    pub max_proof_age_slots: u64,
+    pub max_fee_bps: u16,
+}
+
+pub fn decode_config(data: &[u8]) -> Result<ProgramConfig, ExclusionError> {
+    use config_offsets::*;
+
+    if data.len() < CONFIG_SIZE || data[..8] != CONFIG_DISCRIMINATOR {
+        return Err(ExclusionError::InvalidConfigAccount);
+    }
+    Ok(ProgramConfig {
+        paused: data[PAUSED] != 0,
+        zk_verifier: pubkey(&data[ZK_VERIFIER..ZK_VERIFIER + 32]),
+        transition_verifier: pubkey(&data[TRANSITION_VERIFIER..TRANSITION_VERIFIER + 32]),
+        max_proof_age_slots: u64::from_le_bytes(
+            data[MAX_PROOF_AGE_SLOTS..MAX_PROOF_AGE_SLOTS + 8]
+                .try_into()
+                .unwrap(),
+        ),
+        max_fee_bps: u16::from_le_bytes(data[MAX_FEE_BPS..MAX_FEE_BPS + 2].try_into().unwrap()),
+    })
+}
+
+/// Verifier programs registered with their circuits' hashes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifierRegistry {
+    pub authority: Pubkey,
+    pub verifiers: Vec<RegisteredVerifier>,
+}
+
+/// A registered verifier program, and the tree its circuit proves exclusion from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegisteredVerifier {
+    pub program_id: Pubkey,
+    pub circuit_hash: [u8; 32],
+    pub tree_kind: u8,
+    pub tree_depth: u16,
+}
+
+pub fn decode_verifier_registry(data: &[u8]) -> Result<VerifierRegistry, ExclusionError> {
+    use verifier_registry_offsets::*;
+
+    if data.len() < VERIFIER_REGISTRY_SIZE || data[..8] != VERIFIER_REGISTRY_DISCRIMINATOR {
+        return Err(ExclusionError::InvalidVerifierRegistry);
+    }
+    let count = usize::from(data[COUNT]);
+    if VERIFIERS + VERIFIER_REGISTRY_ENTRY_SIZE * count > data.len() {
+        return Err(ExclusionError::InvalidVerifierRegistry);
+    }
+    Ok(VerifierRegistry {
+        authority: pubkey(&data[AUTHORITY..AUTHORITY + 32]),
+        verifiers: data[VERIFIERS..VERIFIERS + VERIFIER_REGISTRY_ENTRY_SIZE * count]
+            .chunks(VERIFIER_REGISTRY_ENTRY_SIZE)
+            .map(|entry| RegisteredVerifier {
+                program_id: pubkey(&entry[..32]),
+                circuit_hash: entry[32..64].try_into().unwrap(),
+                tree_kind: entry[64],
+                tree_depth: u16::from_le_bytes([entry[65], entry[66]]),
+            })
+            .collect(),
+    })
+}
+
+/// A sender's transfers over the last 24 hours, by hour
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimitAccount {
+    /// Hours since the Unix epoch of the latest bucket
+    pub last_hour: u64,
+    /// Lamports sent in each hour, indexed by hour modulo 24
+    pub hourly: [u64; RATE_LIMIT_BUCKETS],
+}
+
+pub fn decode_rate_limit_account(data: &[u8]) -> Result<RateLimitAccount, ExclusionError> {
+    if data.len() < RATE_LIMIT_ACCOUNT_SIZE || data[..8] != RATE_LIMIT_ACCOUNT_DISCRIMINATOR {
+        return Err(ExclusionError::InvalidRateLimitAccount);
+    }
+    let u64_at = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    Ok(RateLimitAccount {
+        last_hour: u64_at(8),
+        hourly: core::array::from_fn(|i| u64_at(16 + 8 * i)),
+    })
+}
+
+/// Pubkeys that excluded themselves, waiting for the admin to add them
+pub fn decode_exclusion_queue(data: &[u8]) -> Result<Vec<Pubkey>, ExclusionError> {
+    if data.len() < EXCLUSION_QUEUE_SIZE || data[..8] != EXCLUSION_QUEUE_DISCRIMINATOR {
+        return Err(ExclusionError::InvalidExclusionQueue);
+    }
+    let count = usize::from(data[8]);
+    if count > EXCLUSION_QUEUE_CAPACITY {
+        return Err(ExclusionError::InvalidExclusionQueue);
+    }
+    Ok(data[9..9 + 32 * count].chunks(32).map(pubkey).collect())
+}
+
+fn pubkey(bytes: &[u8]) -> Pubkey {
+    Pubkey::new_from_array(bytes.try_into().unwrap())
+}
@@ -0,0 +1,434 @@
+//! Compiled Noir circuits, and the witness their inputs begin
+//!
+//! `nargo compile` writes `target/<name>.json`: the circuit's ACIR bytecode
+//! (gzipped, base64) and its ABI, the parameters `main` takes. ACIR numbers
+//! the parameters' field elements as witnesses 0, 1, ... in the ABI's order,
+//! flattening arrays and structs, and a solver fills in every other witness
+//! from those. `initial_witness` computes that first part from typed inputs.
+//!
+//! The public parameters, then a public return value, are the proof's public
+//! inputs. `witness_layout` places them in the public witness, so its size
+//! and offsets come from the circuit rather than constants.
+//!
+//! `solver` solves the rest in this process, for the opcodes Noir emits for
+//! the exclusion circuit; `nargo execute` (see `prover`) solves any circuit.
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{bail, ensure, Context};
+use base64::Engine;
+use exclusion_common::instruction::{NUM_PUBLIC_INPUTS, WITNESS_HEADER_SIZE, WITNESS_SIZE};
+use serde::Deserialize;
+use smt::ExclusionProofInputs;
+
+/// A parameter's type, as the ABI describes it
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum AbiType {
+    Field,
+    Boolean,
+    Integer {
+        sign: Sign,
+        width: u32,
+    },
+    Array {
+        length: usize,
+        #[serde(rename = "type")]
+        element: Box<AbiType>,
+    },
+    String {
+        length: usize,
+    },
+    Struct {
+        path: String,
+        fields: Vec<AbiField>,
+    },
+    Tuple {
+        fields: Vec<AbiType>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sign {
+    Unsigned,
+    Signed,
+}
+
+/// A struct field
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct AbiField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub typ: AbiType,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    Public,
+    Private,
+    Databus,
+}
+
+/// One of `main`'s parameters
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct AbiParameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub typ: AbiType,
+    pub visibility: Visibility,
+}
+
+/// What `main` returns
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct AbiReturn {
+    pub abi_type: AbiType,
+    pub visibility: Visibility,
+}
+
+/// What an assertion's error selector stands for
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "error_kind", rename_all = "lowercase")]
+pub enum AbiErrorType {
+    /// A plain message, as `assert(x, "message")` gives
+    String { string: String },
+    /// Formatted messages and custom error types
+    #[serde(other)]
+    Other,
+}
+
+/// The parameters `main` takes, in witness order, and what it returns
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct Abi {
+    pub parameters: Vec<AbiParameter>,
+    #[serde(default)]
+    pub return_type: Option<AbiReturn>,
+    /// Assertion errors by selector (a u64, in decimal)
+    #[serde(default)]
+    pub error_types: BTreeMap<String, AbiErrorType>,
+}
+
+/// A public input's place in the public witness
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublicInput {
+    /// The parameter's name, or `return` for `main`'s public return value
+    pub name: String,
+    pub typ: AbiType,
+    /// Its first field element's index among the public inputs
+    pub index: usize,
+}
+
+/// A circuit's public inputs in the order its public witness holds them:
+/// public parameters as `main` declares them, then a public return value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WitnessLayout {
+    pub inputs: Vec<PublicInput>,
+}
+
+impl Abi {
+    /// Where each public input sits in the public witness
+    pub fn witness_layout(&self) -> WitnessLayout {
+        let parameters = self
+            .parameters
+            .iter()
+            .filter(|p| p.visibility == Visibility::Public)
+            .map(|p| (p.name.as_str(), &p.typ));
+        let returned = self
+            .return_type
+            .iter()
+            .filter(|r| r.visibility == Visibility::Public)
+            .map(|r| ("return", &r.abi_type));
+        let mut index = 0;
+        let inputs = parameters
+            .chain(returned)
+            .map(|(name, typ)| {
+                let input = PublicInput {
+                    name: name.to_string(),
+                    typ: typ.clone(),
+                    index,
+                };
+                index += typ.field_count();
+                input
+            })
+            .collect();
+        WitnessLayout { inputs }
+    }
+}
+
+impl WitnessLayout {
+    /// Field elements in the public witness
+    pub fn num_public(&self) -> usize {
+        self.inputs.iter().map(|i| i.typ.field_count()).sum()
+    }
+
+    /// Bytes in the public witness as sunspot writes it: the 12-byte header,
+    /// then 32 per field element
+    pub fn witness_size(&self) -> usize {
+        WITNESS_HEADER_SIZE + 32 * self.num_public()
+    }
+
+    /// `name`'s public input
+    pub fn input(&self, name: &str) -> Option<&PublicInput> {
+        self.inputs.iter().find(|input| input.name == name)
+    }
+
+    /// Byte offset of `name`'s first field element in the public witness
+    pub fn offset(&self, name: &str) -> Option<usize> {
+        self.input(name)
+            .map(|input| WITNESS_HEADER_SIZE + 32 * input.index)
+    }
+
+    /// The public witness of `inputs`, by name, as sunspot writes it: a
+    /// header of big-endian u32s (public inputs, 0 secret, elements), then
+    /// each field element big-endian
+    pub fn encode(&self, inputs: &BTreeMap<String, InputValue>) -> anyhow::Result<Vec<u8>> {
+        let mut fields = Vec::with_capacity(self.num_public());
+        for input in &self.inputs {
+            inputs
+                .get(&input.name)
+                .with_context(|| format!("no value for {}", input.name))?
+                .flatten(&input.typ, &mut fields)
+                .with_context(|| input.name.clone())?;
+        }
+        let count = u32::try_from(fields.len()).context("too many public inputs")?;
+        let mut witness = Vec::with_capacity(self.witness_size());
+        witness.extend_from_slice(&count.to_be_bytes());
+        witness.extend_from_slice(&0u32.to_be_bytes());
+        witness.extend_from_slice(&count.to_be_bytes());
+        for field in fields {
+            witness.extend_from_slice(&field);
+        }
+        Ok(witness)
+    }
+
+    /// Fail unless this is the layout the program parses: `smt_root`,
+    /// `pubkey_hash` and `recent_slot`, one field element each
+    pub fn check_exclusion(&self) -> anyhow::Result<()> {
+        let names: Vec<&str> = self.inputs.iter().map(|i| i.name.as_str()).collect();
+        ensure!(
+            names == ["smt_root", "pubkey_hash", "recent_slot"]
+                && self.num_public() == NUM_PUBLIC_INPUTS as usize
+                && self.witness_size() == WITNESS_SIZE,
+            "the circuit's public inputs are {names:?} ({} bytes), not the {WITNESS_SIZE} the \
+             program parses (was the circuit changed?)",
+            self.witness_size()
+        );
+        Ok(())
+    }
+}
+
+impl AbiType {
+    /// Witnesses a value of this type takes
+    pub fn field_count(&self) -> usize {
+        match self {
+            Self::Field | Self::Boolean | Self::Integer { .. } => 1,
+            Self::Array { length, element } => length * element.field_count(),
+            Self::String { length } => *length,
+            Self::Struct { fields, .. } => fields.iter().map(|f| f.typ.field_count()).sum(),
+            Self::Tuple { fields } => fields.iter().map(AbiType::field_count).sum(),
+        }
+    }
+}
+
+/// A typed input value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputValue {
+    /// A field element, integer or boolean, big-endian
+    Field([u8; 32]),
+    /// An array, string, struct (fields in declaration order) or tuple
+    Vec(Vec<InputValue>),
+}
+
+impl InputValue {
+    pub fn from_u64(value: u64) -> Self {
+        let mut field = [0u8; 32];
+        field[24..].copy_from_slice(&value.to_be_bytes());
+        Self::Field(field)
+    }
+
+    /// Append this value's field elements, checked against `typ`, to
+    /// `witness`
+    fn flatten(&self, typ: &AbiType, witness: &mut Vec<[u8; 32]>) -> anyhow::Result<()> {
+        match (self, typ) {
+            (Self::Field(field), AbiType::Field) => witness.push(*field),
+            (Self::Field(field), AbiType::Boolean) => {
+                ensure!(field[..31] == [0; 31] && field[31] <= 1, "not a boolean");
+                witness.push(*field);
+            }
+            (Self::Field(field), AbiType::Integer { width, .. }) => {
+                let bits = 256 - leading_zeros(field);
+                ensure!(bits <= *width, "does not fit {width} bits");
+                witness.push(*field);
+            }
+            (Self::Vec(values), AbiType::Array { length, element }) => {
+                ensure!(
+                    values.len() == *length,
+                    "{} elements, not {length}",
+                    values.len()
+                );
+                for (i, value) in values.iter().enumerate() {
+                    value
+                        .flatten(element, witness)
+                        .with_context(|| format!("[{i}]"))?;
+                }
+            }
+            (Self::Vec(values), AbiType::String { length }) => {
+                ensure!(
+                    values.len() == *length,
+                    "{} bytes, not {length}",
+                    values.len()
+                );
+                let byte = AbiType::Integer {
+                    sign: Sign::Unsigned,
+                    width: 8,
+                };
+                for value in values {
+                    value.flatten(&byte, witness)?;
+                }
+            }
+            (Self::Vec(values), AbiType::Struct { fields, .. }) => {
+                ensure!(
+                    values.len() == fields.len(),
+                    "{} fields, not {}",
+                    values.len(),
+                    fields.len()
+                );
+                for (value, field) in values.iter().zip(fields) {
+                    value
+                        .flatten(&field.typ, witness)
+                        .with_context(|| format!(".{}", field.name))?;
+                }
+            }
+            (Self::Vec(values), AbiType::Tuple { fields }) => {
+                ensure!(
+                    values.len() == fields.len(),
+                    "{} fields, not {}",
+                    values.len(),
+                    fields.len()
+                );
+                for (i, (value, field)) in values.iter().zip(fields).enumerate() {
+                    value
+                        .flatten(field, witness)
+                        .with_context(|| format!(".{i}"))?;
+                }
+            }
+            (_, typ) => bail!("expected {typ:?}"),
+        }
+        Ok(())
+    }
+}
+
+fn leading_zeros(field: &[u8; 32]) -> u32 {
+    let mut zeros = 0;
+    for byte in field {
+        zeros += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    zeros
+}
+
+/// A `nargo compile` artifact
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitArtifact {
+    /// The Noir version that compiled it
+    pub noir_version: String,
+    pub abi: Abi,
+    /// Decompressed ACIR bytecode
+    pub bytecode: Vec<u8>,
+    /// The names of its Brillig functions, by id
+    pub brillig_names: Vec<String>,
+}
+
+pub(crate) fn gunzip(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    std::io::Read::read_to_end(&mut flate2::read::GzDecoder::new(bytes), &mut out)?;
+    Ok(out)
+}
+
+impl CircuitArtifact {
+    /// Parse an artifact's JSON
+    pub fn from_json(json: &[u8]) -> anyhow::Result<Self> {
+        #[derive(Deserialize)]
+        struct Artifact {
+            noir_version: String,
+            abi: Abi,
+            bytecode: String,
+            #[serde(default)]
+            brillig_names: Vec<String>,
+        }
+        let artifact: Artifact = serde_json::from_slice(json).context("parsing the artifact")?;
+        let compressed = base64::engine::general_purpose::STANDARD
+            .decode(artifact.bytecode)
+            .context("decoding the bytecode")?;
+        Ok(Self {
+            noir_version: artifact.noir_version,
+            abi: artifact.abi,
+            bytecode: gunzip(&compressed).context("decompressing the bytecode")?,
+            brillig_names: artifact.brillig_names,
+        })
+    }
+
+    /// Read the artifact at `path`
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        Self::from_json(
+            &std::fs::read(path).with_context(|| format!("reading {}", path.display()))?,
+        )
+    }
+
+    /// The values of witnesses 0, 1, ...: `inputs`, by parameter name,
+    /// flattened in the ABI's order
+    pub fn initial_witness(
+        &self,
+        inputs: &BTreeMap<String, InputValue>,
+    ) -> anyhow::Result<Vec<[u8; 32]>> {
+        let mut witness = Vec::new();
+        for parameter in &self.abi.parameters {
+            inputs
+                .get(&parameter.name)
+                .with_context(|| format!("no value for {}", parameter.name))?
+                .flatten(&parameter.typ, &mut witness)
+                .with_context(|| parameter.name.clone())?;
+        }
+        if let Some(extra) = inputs
+            .keys()
+            .find(|name| !self.abi.parameters.iter().any(|p| &p.name == *name))
+        {
+            bail!("the circuit takes no {extra}");
+        }
+        Ok(witness)
+    }
+}
+
+/// The exclusion circuit's inputs by parameter name, to prove at
+/// `recent_slot`
+pub fn exclusion_inputs(
+    inputs: &ExclusionProofInputs,
+    recent_slot: u64,
+) -> BTreeMap<String, InputValue> {
+    let byte = |b: u8| InputValue::from_u64(u64::from(b));
+    BTreeMap::from([
+        ("smt_root".into(), InputValue::Field(inputs.smt_root)),
+        ("pubkey_hash".into(), InputValue::Field(inputs.pubkey_hash)),
+        ("recent_slot".into(), InputValue::from_u64(recent_slot)),
+        (
+            "pubkey".into(),
+            InputValue::Vec(inputs.pubkey.iter().copied().map(byte).collect()),
+        ),
+        (
+            "siblings".into(),
+            InputValue::Vec(
+                inputs
+                    .siblings
+                    .iter()
+                    .copied()
+                    .map(InputValue::Field)
+                    .collect(),
+            ),
+        ),
+        ("leaf_value".into(), InputValue::Field(inputs.leaf_value)),
+    ])
+}
@@ -0,0 +1,199 @@
+//! Pinning the circuit, verifying key and verifier that proofs are made for
+//!
+//! Recompiling the circuit changes its bytecode and keys, and a proof from the
+//! new build fails against a verifier deployed for the old one, but only once
+//! it is on chain. `ArtifactPins` records SHA-256 hashes of the compiled
+//! circuit's ACIR, its sunspot verifying key and the deployed verifier's
+//! program bytecode. A `Prover` built `with_pins` refuses to prove, and
+//! `check_verifier` refuses a deployment to send to, once any has changed.
+
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use smt::inputs::field_hex;
+use solana_commitment_config::CommitmentConfig;
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+
+use crate::acir::CircuitArtifact;
+
+const CIRCUIT_NAME: &str = "smt_exclusion";
+
+/// Upgradeable loader `Program` account: 4 (tag) + 32 (programdata address)
+const PROGRAM_SIZE: usize = 4 + 32;
+/// Upgradeable loader `ProgramData` header: 4 (tag) + 8 (slot)
+/// + 1 + 32 (optional upgrade authority)
+const PROGRAMDATA_HEADER_SIZE: usize = 4 + 8 + 1 + 32;
+const PROGRAM_TAG: u32 = 2;
+const PROGRAMDATA_TAG: u32 = 3;
+
+/// Hashes of the artifacts a deployment's proofs are made and checked with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactPins {
+    /// `circuit_hash` of the compiled circuit
+    #[serde(with = "hex")]
+    pub circuit: [u8; 32],
+    /// SHA-256 of the verifying key sunspot wrote
+    #[serde(with = "hex")]
+    pub verifying_key: [u8; 32],
+    /// `program_hash` of the verifier program
+    #[serde(with = "hex")]
+    pub verifier: [u8; 32],
+}
+
+mod hex {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(hash: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&field_hex(hash))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        let digits = hex.strip_prefix("0x").unwrap_or(&hex);
+        let mut hash = [0u8; 32];
+        if digits.len() != 64 {
+            return Err(serde::de::Error::custom("expected 32 hex-encoded bytes"));
+        }
+        for (byte, pair) in hash.iter_mut().zip(digits.as_bytes().chunks(2)) {
+            *byte = std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| serde::de::Error::custom("expected hex digits"))?;
+        }
+        Ok(hash)
+    }
+}
+
+/// SHA-256 of a circuit's ACIR bytecode; unlike a hash of the artifact, it
+/// doesn't change with debug info or where the circuit was compiled
+pub fn circuit_hash(artifact: &CircuitArtifact) -> [u8; 32] {
+    Sha256::digest(&artifact.bytecode).into()
+}
+
+/// SHA-256 of a program's ELF, without the zero padding its account holds
+/// past it (as `solana-verify` hashes programs)
+pub fn program_hash(elf: &[u8]) -> [u8; 32] {
+    let len = elf.iter().rposition(|byte| *byte != 0).map_or(0, |i| i + 1);
+    Sha256::digest(&elf[..len]).into()
+}
+
+/// The ELF in an upgradeable loader `ProgramData` account's data
+pub fn programdata_elf(data: &[u8]) -> anyhow::Result<&[u8]> {
+    ensure!(
+        data.len() > PROGRAMDATA_HEADER_SIZE && data[..4] == PROGRAMDATA_TAG.to_le_bytes(),
+        "not a program data account"
+    );
+    Ok(&data[PROGRAMDATA_HEADER_SIZE..])
+}
+
+/// The bytecode of the program at `program`, upgradeable or not
+pub async fn fetch_program_elf(rpc: &RpcClient, program: &Pubkey) -> anyhow::Result<Vec<u8>> {
+    let fetch = |address: Pubkey| async move {
+        rpc.get_account_with_commitment(&address, CommitmentConfig::confirmed())
+            .await
+            .with_context(|| format!("fetching {address}"))?
+            .value
+            .with_context(|| format!("{address} does not exist"))
+    };
+    let account = fetch(*program).await?;
+    ensure!(account.executable, "{program} is not a program");
+    if account.owner == solana_sdk_ids::bpf_loader::ID {
+        return Ok(account.data);
+    }
+    ensure!(
+        account.owner == solana_sdk_ids::bpf_loader_upgradeable::ID,
+        "{program} is owned by {}, not a BPF loader",
+        account.owner
+    );
+    ensure!(
+        account.data.len() == PROGRAM_SIZE && account.data[..4] == PROGRAM_TAG.to_le_bytes(),
+        "{program} is not an upgradeable program account"
+    );
+    let programdata = Pubkey::try_from(&account.data[4..]).expect("32 bytes");
+    let data = fetch(programdata).await?.data;
+    Ok(programdata_elf(&data)
+        .with_context(|| format!("{program}'s program data {programdata}"))?
+        .to_vec())
+}
+
+fn target(circuit_dir: &Path, extension: &str) -> std::path::PathBuf {
+    circuit_dir
+        .join("target")
+        .join(format!("{CIRCUIT_NAME}.{extension}"))
+}
+
+/// The hashes of the circuit and verifying key built under
+/// `circuit_dir/target`
+fn local_hashes(circuit_dir: &Path) -> anyhow::Result<([u8; 32], [u8; 32])> {
+    let artifact = CircuitArtifact::load(target(circuit_dir, "json"))?;
+    let vk_path = target(circuit_dir, "vk");
+    let vk = std::fs::read(&vk_path).with_context(|| format!("reading {}", vk_path.display()))?;
+    Ok((circuit_hash(&artifact), Sha256::digest(vk).into()))
+}
+
+impl ArtifactPins {
+    /// Pin the circuit and key built under `circuit_dir/target` (by
+    /// `just build-verifier-smt`) and the verifier deployed at `verifier`
+    pub async fn record(
+        rpc: &RpcClient,
+        circuit_dir: impl AsRef<Path>,
+        verifier: &Pubkey,
+    ) -> anyhow::Result<Self> {
+        let (circuit, verifying_key) = local_hashes(circuit_dir.as_ref())?;
+        Ok(Self {
+            circuit,
+            verifying_key,
+            verifier: program_hash(&fetch_program_elf(rpc, verifier).await?),
+        })
+    }
+
+    /// Read pins `save` wrote
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        serde_json::from_slice(&json).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    /// Write the pins as JSON, to commit alongside the deployment's config
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("writing {}", path.display()))
+    }
+
+    /// Fail unless the circuit and key under `circuit_dir/target` are the
+    /// pinned ones
+    pub fn check_circuit(&self, circuit_dir: impl AsRef<Path>) -> anyhow::Result<()> {
+        let (circuit, verifying_key) = local_hashes(circuit_dir.as_ref())?;
+        if circuit != self.circuit {
+            bail!(
+                "the compiled circuit's hash is {}, not the pinned {} (was the circuit recompiled?)",
+                field_hex(&circuit),
+                field_hex(&self.circuit)
+            );
+        }
+        if verifying_key != self.verifying_key {
+            bail!(
+                "the verifying key's hash is {}, not the pinned {} (was setup rerun?)",
+                field_hex(&verifying_key),
+                field_hex(&self.verifying_key)
+            );
+        }
+        Ok(())
+    }
+
+    /// Fail unless the program at `verifier` is the pinned verifier
+    pub async fn check_verifier(&self, rpc: &RpcClient, verifier: &Pubkey) -> anyhow::Result<()> {
+        let hash = program_hash(&fetch_program_elf(rpc, verifier).await?);
+        ensure!(
+            hash == self.verifier,
+            "verifier {verifier}'s bytecode hash is {}, not the pinned {} (was it redeployed?)",
+            field_hex(&hash),
+            field_hex(&self.verifier)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+//! Many gated transfers from one sender, paid for by one proof
+//!
+//! A proof binds the sender and a recent slot, not a recipient or an
+//! amount, so one proof covers every transfer in a batch while it is fresh.
+//! A `TRANSFER_SOL` carries the proof inline, about 500 bytes of a
+//! transaction's 1232, so transfers are packed as many to a transaction as
+//! fit its size and compute limits, which is usually one. A transfer whose
+//! transaction can't hold the proof uploads it to the sender's proof buffer
+//! first and sends `TRANSFER_FROM_BUFFER`, which closes the buffer. A sender
+//! has one buffer, so buffered transfers go one after another, alongside the
+//! inline transactions, which go a few at a time.
+
+use anyhow::Context;
+use exclusion_common::{Proof, PublicWitness};
+use futures::stream::{self, StreamExt};
+use solana_commitment_config::CommitmentConfig;
+use solana_instruction::Instruction;
+use solana_message::AddressLookupTableAccount;
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_signature::Signature;
+
+use crate::{
+    accounts::decode_proof_buffer,
+    compute_budget::{
+        build_sized_transaction, transaction_size, ComputeBudgetOptions, MAX_COMPUTE_UNIT_LIMIT,
+        MAX_TRANSACTION_SIZE,
+    },
+    instructions::{
+        create_proof_buffer_ix, derive_proof_buffer_pda, transfer_from_buffer_ix, transfer_sol_ix,
+        write_proof_chunk_ix, TransferAccounts,
+    },
+    send::{send_and_confirm, SendOptions},
+    signer::AsyncSigner,
+};
+
+/// One transfer of a batch
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchTransfer {
+    pub recipient: Pubkey,
+    pub lamports: u64,
+}
+
+/// One transaction of a batch, or one buffered transfer
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    /// `TRANSFER_SOL`s for the transfers at `transfers`, their indices in the
+    /// batch, in one transaction
+    Inline {
+        transfers: Vec<usize>,
+        instructions: Vec<Instruction>,
+    },
+    /// The transfer at `transfer`, through the sender's proof buffer
+    Buffered {
+        transfer: usize,
+        instruction: Instruction,
+    },
+}
+
+/// How a batch is packed and sent
+pub struct BatchOptions {
+    /// Inline transactions in flight at once
+    pub concurrency: usize,
+    /// Compute units one transfer is assumed to take when packing several
+    /// into a transaction; each transaction's limit is still sized by
+    /// simulation
+    pub units_per_transfer: u32,
+    pub compute_budget: ComputeBudgetOptions,
+    pub send: SendOptions,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            units_per_transfer: 500_000,
+            compute_budget: ComputeBudgetOptions::default(),
+            send: SendOptions::default(),
+        }
+    }
+}
+
+/// What became of one transfer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransferOutcome {
+    pub transfer: BatchTransfer,
+    /// The signature of the transaction that made it, or why it failed
+    pub result: Result<Signature, String>,
+}
+
+/// `accounts` paying `transfer.recipient`
+fn to(accounts: &TransferAccounts, transfer: &BatchTransfer) -> TransferAccounts {
+    TransferAccounts {
+        recipient: transfer.recipient,
+        ..*accounts
+    }
+}
+
+/// Pack `transfers` from `accounts.sender` (whose `recipient` is ignored)
+/// into steps, with the proof inline where it fits
+pub fn plan_batch(
+    accounts: &TransferAccounts,
+    transfers: &[BatchTransfer],
+    proof: &Proof,
+    witness: &PublicWitness,
+    lookup_tables: &[AddressLookupTableAccount],
+    options: &BatchOptions,
+) -> anyhow::Result<Vec<Step>> {
+    let per_transaction = (MAX_COMPUTE_UNIT_LIMIT / options.units_per_transfer.max(1)).max(1);
+    let fits = |instructions: &[Instruction]| -> anyhow::Result<bool> {
+        Ok(
+            transaction_size(&accounts.sender, instructions, lookup_tables)?
+                <= MAX_TRANSACTION_SIZE,
+        )
+    };
+    let mut steps = Vec::new();
+    let mut current: Option<(Vec<usize>, Vec<Instruction>)> = None;
+    for (index, transfer) in transfers.iter().enumerate() {
+        let instruction =
+            transfer_sol_ix(&to(accounts, transfer), transfer.lamports, proof, witness);
+        if let Some((indices, instructions)) = &mut current {
+            instructions.push(instruction.clone());
+            if indices.len() < per_transaction as usize && fits(instructions)? {
+                indices.push(index);
+                continue;
+            }
+            instructions.pop();
+            let (transfers, instructions) = current.take().unwrap();
+            steps.push(Step::Inline {
+                transfers,
+                instructions,
+            });
+        }
+        if fits(std::slice::from_ref(&instruction))? {
+            current = Some((vec![index], vec![instruction]));
+        } else {
+            steps.push(Step::Buffered {
+                transfer: index,
+                instruction: transfer_from_buffer_ix(&to(accounts, transfer), transfer.lamports),
+            });
+        }
+    }
+    if let Some((transfers, instructions)) = current {
+        steps.push(Step::Inline {
+            transfers,
+            instructions,
+        });
+    }
+    Ok(steps)
+}
+
+/// Transactions' instructions writing `payload`, the proof and witness, to
+/// `owner`'s proof buffer, creating it first when `create` is set
+pub fn proof_upload_ixs(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payload: &[u8],
+    create: bool,
+    lookup_tables: &[AddressLookupTableAccount],
+) -> anyhow::Result<Vec<Vec<Instruction>>> {
+    let mut transactions = Vec::new();
+    let mut first: Vec<Instruction> = Vec::new();
+    if create {
+        first.push(create_proof_buffer_ix(program_id, owner));
+    }
+    let mut offset = 0;
+    while offset < payload.len() {
+        let mut instructions = std::mem::take(&mut first);
+        // The largest chunk that fits alongside what the transaction holds
+        let mut len = payload.len() - offset;
+        loop {
+            let write = write_proof_chunk_ix(
+                program_id,
+                owner,
+                offset as u16,
+                &payload[offset..offset + len],
+            );
+            instructions.push(write);
+            if transaction_size(owner, &instructions, lookup_tables)? <= MAX_TRANSACTION_SIZE {
+                break;
+            }
+            instructions.pop();
+            anyhow::ensure!(len > 1, "no proof chunk fits in a transaction");
+            len /= 2;
+        }
+        offset += len;
+        transactions.push(instructions);
+    }
+    if !first.is_empty() {
+        transactions.push(first);
+    }
+    Ok(transactions)
+}
+
+/// Sign `instructions` with `sender` and send them until they land
+async fn send_step(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    sender: &dyn AsyncSigner,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    options: &BatchOptions,
+) -> anyhow::Result<Signature> {
+    send_and_confirm(
+        rpc,
+        program_id,
+        || async {
+            let sized = build_sized_transaction(
+                rpc,
+                sender,
+                instructions,
+                lookup_tables,
+                &options.compute_budget,
+            )
+            .await?;
+            Ok(sized.transaction)
+        },
+        &options.send,
+    )
+    .await
+}
+
+/// Upload the proof to the sender's buffer, unless it already holds it, and
+/// send the buffered transfer
+#[allow(clippy::too_many_arguments)]
+async fn send_buffered(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    sender: &dyn AsyncSigner,
+    instruction: &Instruction,
+    proof: &Proof,
+    witness: &PublicWitness,
+    lookup_tables: &[AddressLookupTableAccount],
+    options: &BatchOptions,
+) -> anyhow::Result<Signature> {
+    let owner = sender.address();
+    let buffer = derive_proof_buffer_pda(program_id, &owner).0;
+    // A buffer a failed transfer left behind is written over, not recreated
+    let existing = rpc
+        .get_account_with_commitment(&buffer, CommitmentConfig::confirmed())
+        .await
+        .context("fetching the proof buffer")?
+        .value;
+    let holds_proof = existing
+        .as_ref()
+        .and_then(|account| decode_proof_buffer(&account.data).ok())
+        .is_some_and(|held| held.proof == *proof && held.witness == *witness);
+    if !holds_proof {
+        let payload = [&proof.as_bytes()[..], witness.as_bytes()].concat();
+        let uploads = proof_upload_ixs(
+            program_id,
+            &owner,
+            &payload,
+            existing.is_none(),
+            lookup_tables,
+        )?;
+        for instructions in uploads {
+            send_step(
+                rpc,
+                program_id,
+                sender,
+                &instructions,
+                lookup_tables,
+                options,
+            )
+            .await
+            .context("uploading the proof")?;
+        }
+    }
+    send_step(
+        rpc,
+        program_id,
+        sender,
+        std::slice::from_ref(instruction),
+        lookup_tables,
+        options,
+    )
+    .await
+}
+
+/// Send `steps`, planned by `plan_batch` for `transfers`, as `sender`, and
+/// report each transfer's outcome, in order
+///
+/// Inline transactions go `options.concurrency` at a time, each rebuilt
+/// and resent until it lands (see `send_and_confirm`); buffered transfers go
+/// one at a time alongside them. One transfer failing doesn't stop the
+/// others. Transfers packed into one transaction succeed or fail together.
+#[allow(clippy::too_many_arguments)]
+pub async fn send_batch(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    sender: &dyn AsyncSigner,
+    transfers: &[BatchTransfer],
+    steps: &[Step],
+    proof: &Proof,
+    witness: &PublicWitness,
+    lookup_tables: &[AddressLookupTableAccount],
+    options: &BatchOptions,
+) -> Vec<TransferOutcome> {
+    let inline = stream::iter(steps.iter().filter_map(|step| match step {
+        Step::Inline {
+            transfers,
+            instructions,
+        } => Some((transfers, instructions)),
+        Step::Buffered { .. } => None,
+    }))
+    .map(|(indices, instructions)| async move {
+        let result = send_step(
+            rpc,
+            program_id,
+            sender,
+            instructions,
+            lookup_tables,
+            options,
+        )
+        .await
+        .map_err(|error| format!("{error:#}"));
+        indices
+            .iter()
+            .map(|index| (*index, result.clone()))
+            .collect::<Vec<_>>()
+    })
+    .buffer_unordered(options.concurrency.max(1))
+    .collect::<Vec<_>>();
+    let buffered = async {
+        let mut results = Vec::new();
+        for step in steps {
+            if let Step::Buffered {
+                transfer,
+                instruction,
+            } = step
+            {
+                let result = send_buffered(
+                    rpc,
+                    program_id,
+                    sender,
+                    instruction,
+                    proof,
+                    witness,
+                    lookup_tables,
+                    options,
+                )
+                .await
+                .map_err(|error| format!("{error:#}"));
+                results.push((*transfer, result));
+            }
+        }
+        results
+    };
+    let (inline, buffered) = tokio::join!(inline, buffered);
+
+    let mut results: Vec<Option<Result<Signature, String>>> = vec![None; transfers.len()];
+    for (index, result) in inline.into_iter().flatten().chain(buffered) {
+        if let Some(slot) = results.get_mut(index) {
+            *slot = Some(result);
+        }
+    }
+    transfers
+        .iter()
+        .zip(results)
+        .map(|(transfer, result)| TransferOutcome {
+            transfer: *transfer,
+            result: result.unwrap_or_else(|| Err("not in the plan".to_string())),
+        })
+        .collect()
+}
@@ -0,0 +1,168 @@
+//! In-process proving with Barretenberg (`bb-sys` feature)
+//!
+//! Links `libbarretenberg` (see `build.rs`) and proves with its UltraHonk C
+//! bindings, so a service proves without spawning `bb`. The bindings are those
+//! of `barretenberg/dsl/acir_proofs/c_bind.hpp` as of Barretenberg 0.82: every
+//! buffer in or out is a big-endian u32 length then the bytes, and buffers
+//! Barretenberg allocates are freed with `bbfree`.
+//!
+//! UltraHonk proofs cannot be submitted to this program: its instructions take
+//! a fixed-size Groth16 `Proof` for the sunspot verifier, and an UltraHonk
+//! proof is far larger and needs a Barretenberg verifier. `Circuit::solve`
+//! solves the witness with `solver`, so nothing but this process runs.
+
+use std::{collections::BTreeMap, ffi::c_void, path::Path, ptr, sync::Mutex};
+
+use anyhow::{ensure, Context};
+
+use crate::{
+    acir::{CircuitArtifact, InputValue},
+    solver::{Program, Witness},
+};
+
+mod ffi {
+    use std::ffi::c_void;
+
+    extern "C" {
+        pub fn srs_init_srs(points_buf: *const u8, num_points: *const u32, g2_point_buf: *const u8);
+        pub fn acir_prove_ultra_honk(
+            acir_vec: *const u8,
+            witness_vec: *const u8,
+            out: *mut *mut u8,
+        );
+        pub fn acir_write_vk_ultra_honk(acir_vec: *const u8, out: *mut *mut u8);
+        pub fn acir_verify_ultra_honk(proof_buf: *const u8, vk_buf: *const u8, result: *mut bool);
+        pub fn bbfree(ptr: *mut c_void);
+    }
+}
+
+/// Bytes in one uncompressed G1 point of the SRS
+const G1_POINT_SIZE: usize = 64;
+/// Bytes in the SRS's G2 point
+const G2_POINT_SIZE: usize = 128;
+
+/// Barretenberg's global SRS isn't safe to load while proving
+static SRS: Mutex<bool> = Mutex::new(false);
+
+/// `bytes` as Barretenberg reads a byte vector
+fn vector(bytes: &[u8]) -> Vec<u8> {
+    let len = u32::try_from(bytes.len()).expect("buffers are under 4 GiB");
+    [&len.to_be_bytes()[..], bytes].concat()
+}
+
+/// Take ownership of a byte vector Barretenberg allocated
+///
+/// # Safety
+///
+/// `out` is null or a buffer Barretenberg wrote as a byte vector and has not
+/// been freed
+unsafe fn take_vector(out: *mut u8) -> anyhow::Result<Vec<u8>> {
+    ensure!(!out.is_null(), "Barretenberg returned no buffer");
+    let len = u32::from_be_bytes(*out.cast::<[u8; 4]>()) as usize;
+    let bytes = std::slice::from_raw_parts(out.add(4), len).to_vec();
+    ffi::bbfree(out.cast::<c_void>());
+    Ok(bytes)
+}
+
+/// Load the structured reference string: `g1` holds uncompressed G1 points,
+/// as many as the largest circuit proved needs, and `g2` the G2 point
+///
+/// Points are as Aztec's ignition ceremony publishes them (`bn254_g1.dat`
+/// and `bn254_g2.dat` under `~/.bb-crs`).
+pub fn init_srs(g1: &[u8], g2: &[u8]) -> anyhow::Result<()> {
+    ensure!(
+        !g1.is_empty() && g1.len().is_multiple_of(G1_POINT_SIZE),
+        "G1 points are {G1_POINT_SIZE} bytes each"
+    );
+    ensure!(
+        g2.len() == G2_POINT_SIZE,
+        "the G2 point is {G2_POINT_SIZE} bytes"
+    );
+    let num_points = u32::try_from(g1.len() / G1_POINT_SIZE).context("too many points")?;
+    let mut loaded = SRS.lock().unwrap();
+    // SAFETY: the buffers hold `num_points` G1 points and one G2 point
+    unsafe { ffi::srs_init_srs(g1.as_ptr(), &num_points, g2.as_ptr()) };
+    *loaded = true;
+    Ok(())
+}
+
+/// A compiled circuit's ACIR, ready to solve and prove
+pub struct Circuit {
+    artifact: CircuitArtifact,
+    program: Program,
+}
+
+impl TryFrom<CircuitArtifact> for Circuit {
+    type Error = anyhow::Error;
+
+    /// Fails if `solver` can't solve the circuit
+    fn try_from(artifact: CircuitArtifact) -> anyhow::Result<Self> {
+        let program = Program::from_artifact(&artifact)?;
+        Ok(Self { artifact, program })
+    }
+}
+
+/// An UltraHonk proof, as Barretenberg serializes it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BbProof(pub Vec<u8>);
+
+impl Circuit {
+    /// The circuit in a `nargo compile` artifact (`target/<name>.json`)
+    pub fn from_artifact(json: &[u8]) -> anyhow::Result<Self> {
+        CircuitArtifact::from_json(json)?.try_into()
+    }
+
+    /// Read the artifact at `path`
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        CircuitArtifact::load(path)?.try_into()
+    }
+
+    /// Solve the witness for `inputs`, by parameter name
+    ///
+    /// Fails with the circuit's assertion message if they don't satisfy it.
+    pub fn solve(&self, inputs: &BTreeMap<String, InputValue>) -> anyhow::Result<Witness> {
+        let initial = self.artifact.initial_witness(inputs)?;
+        self.program.solve(&initial)
+    }
+
+    /// Prove with a witness `solve` returned
+    pub fn prove(&self, witness: &Witness) -> anyhow::Result<BbProof> {
+        let witness = witness.to_witness_stack();
+        let loaded = SRS.lock().unwrap();
+        ensure!(*loaded, "load the SRS with init_srs first");
+        let mut out = ptr::null_mut();
+        // SAFETY: both inputs are length-prefixed vectors that outlive the
+        // call, and `out` is taken once
+        unsafe {
+            ffi::acir_prove_ultra_honk(
+                vector(&self.artifact.bytecode).as_ptr(),
+                vector(&witness).as_ptr(),
+                &mut out,
+            );
+            take_vector(out).map(BbProof)
+        }
+    }
+
+    /// The circuit's UltraHonk verification key
+    pub fn verification_key(&self) -> anyhow::Result<Vec<u8>> {
+        let loaded = SRS.lock().unwrap();
+        ensure!(*loaded, "load the SRS with init_srs first");
+        let mut out = ptr::null_mut();
+        // SAFETY: as in `prove`
+        unsafe {
+            ffi::acir_write_vk_ultra_honk(vector(&self.artifact.bytecode).as_ptr(), &mut out);
+            take_vector(out)
+        }
+    }
+}
+
+/// Whether `proof` verifies under `verification_key`
+pub fn verify(proof: &BbProof, verification_key: &[u8]) -> bool {
+    let _loaded = SRS.lock().unwrap();
+    let mut valid = false;
+    // SAFETY: both buffers are serialized as Barretenberg wrote them
+    unsafe {
+        ffi::acir_verify_ultra_honk(proof.0.as_ptr(), verification_key.as_ptr(), &mut valid);
+    }
+    valid
+}
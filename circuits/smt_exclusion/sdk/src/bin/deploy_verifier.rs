@@ -0,0 +1,57 @@
+//! Deploy or upgrade a sunspot verifier and print what to pin
+//!
+//! Run with: cargo run --bin deploy_verifier -- ../target/smt_exclusion.so --program-keypair verifier.json
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use clap::Parser;
+use exclusion_client::deploy::{deploy_verifier, VerifierProgram};
+use smt::inputs::field_hex;
+use solana_commitment_config::CommitmentConfig;
+use solana_keypair::read_keypair_file;
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+
+#[derive(Parser)]
+struct Args {
+    /// The verifier `sunspot deploy` built
+    so: PathBuf,
+    #[arg(long, env = "RPC_URL", default_value = "https://api.devnet.solana.com")]
+    rpc_url: String,
+    /// Payer, and the buffer's and program's upgrade authority
+    #[arg(long, default_value = "~/.config/solana/id.json")]
+    keypair: String,
+    /// Deploy a new program at this keypair's address
+    #[arg(long, conflicts_with = "upgrade", required_unless_present = "upgrade")]
+    program_keypair: Option<PathBuf>,
+    /// Upgrade this program instead
+    #[arg(long)]
+    upgrade: Option<Pubkey>,
+}
+
+fn read_keypair(path: &str) -> anyhow::Result<solana_keypair::Keypair> {
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => PathBuf::from(std::env::var("HOME").context("HOME is not set")?).join(rest),
+        None => PathBuf::from(path),
+    };
+    read_keypair_file(&path).map_err(|e| anyhow::anyhow!("reading {}: {e}", path.display()))
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let payer = read_keypair(&args.keypair)?;
+    let program = match (args.program_keypair, args.upgrade) {
+        (Some(path), _) => VerifierProgram::New(read_keypair(&path.to_string_lossy())?),
+        (None, Some(address)) => VerifierProgram::Upgrade(address),
+        (None, None) => unreachable!("clap requires one"),
+    };
+    let so = std::fs::read(&args.so).with_context(|| format!("reading {}", args.so.display()))?;
+    let rpc = RpcClient::new_with_commitment(args.rpc_url, CommitmentConfig::confirmed());
+
+    let deployed = deploy_verifier(&rpc, &payer, &program, &so).await?;
+    println!("Program ID: {}", deployed.program_id);
+    println!("Bytecode hash: {}", field_hex(&deployed.hash));
+    Ok(())
+}
@@ -0,0 +1,112 @@
+//! Point-compressed proofs for `TRANSFER_SOL_COMPRESSED`
+//!
+//! Each of a sunspot proof's points is cut to its x coordinate and a flag for
+//! its y, in the encoding of the `sol_alt_bn128_compression` syscall the
+//! program expands them with: big-endian, the flags in the first byte, G2
+//! coordinates imaginary part first, and the point at infinity all zeros.
+//! That is arkworks' compressed encoding with the bytes reversed, which is
+//! not gnark's.
+
+use ark_bn254::{G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_serialize::{CanonicalSerialize, Compress, SerializationError, Validate};
+use exclusion_common::{
+    instruction::{COMPRESSED_PROOF_SIZE, PROOF_SIZE},
+    CompressedProof, ExclusionError, Proof,
+};
+
+/// Where the commitment count sits in each layout
+const COUNT: (std::ops::Range<usize>, std::ops::Range<usize>) = (256..260, 128..132);
+
+/// (uncompressed range, compressed range) for Ar, Bs, Krs, the commitment and
+/// its proof of knowledge
+const POINTS: [(std::ops::Range<usize>, std::ops::Range<usize>); 5] = [
+    (0..64, 0..32),
+    (64..192, 32..96),
+    (192..256, 96..128),
+    (260..324, 132..164),
+    (324..388, 164..196),
+];
+
+/// `proof` with its points compressed, as the program expands them
+///
+/// Fails with `InvalidCompressedProof` if a point is not on the curve, or the
+/// proof doesn't carry exactly one commitment, as the program requires.
+pub fn compress_proof(proof: &Proof) -> Result<CompressedProof, ExclusionError> {
+    let proof = proof.as_bytes();
+    check_count(&proof[COUNT.0])?;
+    let mut compressed = [0u8; COMPRESSED_PROOF_SIZE];
+    compressed[COUNT.1].copy_from_slice(&proof[COUNT.0]);
+    for (from, to) in POINTS {
+        convert(&proof[from], &mut compressed[to], Compress::No)?;
+    }
+    Ok(CompressedProof(compressed))
+}
+
+/// The proof `compressed` expands to, as the program expands it before
+/// verifying
+pub fn decompress_proof(compressed: &CompressedProof) -> Result<Proof, ExclusionError> {
+    let compressed = compressed.as_bytes();
+    check_count(&compressed[COUNT.1])?;
+    let mut proof = [0u8; PROOF_SIZE];
+    proof[COUNT.0].copy_from_slice(&compressed[COUNT.1]);
+    for (to, from) in POINTS {
+        convert(&compressed[from], &mut proof[to], Compress::Yes)?;
+    }
+    Ok(Proof(proof))
+}
+
+fn check_count(count: &[u8]) -> Result<(), ExclusionError> {
+    if count != 1u32.to_be_bytes() {
+        return Err(ExclusionError::InvalidCompressedProof);
+    }
+    Ok(())
+}
+
+/// Re-encode the G1 or G2 point in `from` (encoded as `mode` says) into `to`,
+/// in the other mode
+fn convert(from: &[u8], to: &mut [u8], mode: Compress) -> Result<(), ExclusionError> {
+    if from.iter().all(|&byte| byte == 0) {
+        to.fill(0);
+        return Ok(());
+    }
+    // A coordinate is as long as the compressed point: 32 bytes for G1, 64
+    // (both parts) for G2
+    let coordinate = from.len().min(to.len());
+    let le = flip(from, coordinate);
+    let out = if coordinate == 32 {
+        reencode::<G1Affine>(&le, mode)
+    } else {
+        reencode::<G2Affine>(&le, mode)
+    }
+    .map_err(|_| ExclusionError::InvalidCompressedProof)?;
+    to.copy_from_slice(&flip(&out, coordinate));
+    Ok(())
+}
+
+/// The point in arkworks' little-endian `le` (encoded as `mode` says), in the
+/// other mode
+fn reencode<P: AffineRepr>(le: &[u8], mode: Compress) -> Result<Vec<u8>, SerializationError> {
+    let point = P::deserialize_with_mode(le, mode, Validate::Yes)?;
+    let mut out = Vec::new();
+    match mode {
+        Compress::No => point.serialize_compressed(&mut out)?,
+        // The coordinates alone, as arkworks' uncompressed encoding would
+        // keep the y flag in the top bits
+        Compress::Yes => {
+            let (x, y) = point.xy().unwrap_or_default();
+            x.serialize_uncompressed(&mut out)?;
+            y.serialize_uncompressed(&mut out)?;
+        }
+    }
+    Ok(out)
+}
+
+/// `bytes` with each `coordinate`-sized chunk reversed, between big-endian
+/// coordinates and arkworks' little-endian ones
+fn flip(bytes: &[u8], coordinate: usize) -> Vec<u8> {
+    bytes
+        .chunks(coordinate)
+        .flat_map(|chunk| chunk.iter().rev().copied())
+        .collect()
+}
@@ -0,0 +1,353 @@
+//! v0 transactions with a compute budget sized by simulation
+//!
+//! A gated transfer's compute goes mostly to the CPI into the ZK verifier, and
+//! how much depends on the verifier, the state's flags and the accounts the
+//! transfer touches, so no fixed limit suits every transfer. Too low and the
+//! transaction fails; too high and it pays priority fees on units it never
+//! uses, and is scheduled behind smaller ones. Simulating first gives the
+//! units the transfer consumes, and recent prioritization fees on the
+//! accounts it writes give a price that lands.
+//!
+//! A transaction that may not land within a blockhash's life is compiled
+//! against a durable nonce instead (see `nonce`).
+
+use anyhow::{bail, ensure, Context};
+use solana_commitment_config::CommitmentConfig;
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_hash::Hash;
+use solana_instruction::Instruction;
+use solana_message::{v0, AddressLookupTableAccount, VersionedMessage};
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::config::RpcSimulateTransactionConfig;
+use solana_signature::Signature;
+use solana_transaction::versioned::VersionedTransaction;
+
+use crate::{
+    nonce::NonceAccount,
+    signer::{sign_transaction, AsyncSigner},
+};
+
+/// Most compute units a transaction may request
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Most accounts `getRecentPrioritizationFees` takes
+const MAX_FEE_ACCOUNTS: usize = 128;
+
+/// How a transaction's compute budget is sized
+pub struct ComputeBudgetOptions {
+    /// Percent added to the simulated units, for state that changes before
+    /// the transaction lands
+    pub margin_percent: u32,
+    /// Percentile of recent prioritization fees to pay, 0 to 100; 0 adds no
+    /// price instruction
+    pub fee_percentile: u8,
+    /// Highest price paid, in micro-lamports per compute unit
+    pub max_compute_unit_price: u64,
+}
+
+impl Default for ComputeBudgetOptions {
+    fn default() -> Self {
+        Self {
+            margin_percent: 10,
+            fee_percentile: 75,
+            max_compute_unit_price: 1_000_000,
+        }
+    }
+}
+
+/// An unsigned message, and how its compute budget was set
+pub struct SizedMessage {
+    pub message: v0::Message,
+    /// Units the simulation consumed, including the compute budget instructions
+    pub units_consumed: u64,
+    pub compute_unit_limit: u32,
+    /// Micro-lamports per compute unit; 0 when no price instruction was added
+    pub compute_unit_price: u64,
+}
+
+/// A signed transaction, and how its compute budget was set
+pub struct SizedTransaction {
+    pub transaction: VersionedTransaction,
+    /// Units the simulation consumed, including the compute budget instructions
+    pub units_consumed: u64,
+    pub compute_unit_limit: u32,
+    /// Micro-lamports per compute unit; 0 when no price instruction was added
+    pub compute_unit_price: u64,
+}
+
+/// The limit for a transaction that consumed `units_consumed`, with
+/// `margin_percent` to spare
+pub fn compute_unit_limit(units_consumed: u64, margin_percent: u32) -> u32 {
+    let limit = units_consumed.saturating_mul(100 + u64::from(margin_percent)) / 100;
+    limit.min(u64::from(MAX_COMPUTE_UNIT_LIMIT)) as u32
+}
+
+/// The `percentile`th of `fees`, at most `max`; 0 for no fees
+pub fn compute_unit_price(fees: &[u64], percentile: u8, max: u64) -> u64 {
+    if fees.is_empty() || percentile == 0 {
+        return 0;
+    }
+    let mut fees = fees.to_vec();
+    fees.sort_unstable();
+    let rank = (fees.len() * usize::from(percentile.min(100))).div_ceil(100);
+    fees[rank.saturating_sub(1)].min(max)
+}
+
+/// A v0 message for `instructions`, paid by `payer`, after a compute budget,
+/// naming the accounts it can by their index in `lookup_tables`; against
+/// `nonce` when given, whose advance must come first
+fn compile(
+    payer: &Pubkey,
+    compute_unit_limit: u32,
+    compute_unit_price: u64,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    blockhash: Hash,
+    nonce: Option<&NonceAccount>,
+) -> anyhow::Result<v0::Message> {
+    let mut all: Vec<Instruction> = nonce.map(NonceAccount::advance_ix).into_iter().collect();
+    all.push(ComputeBudgetInstruction::set_compute_unit_limit(
+        compute_unit_limit,
+    ));
+    if compute_unit_price > 0 {
+        all.push(ComputeBudgetInstruction::set_compute_unit_price(
+            compute_unit_price,
+        ));
+    }
+    all.extend(
+        instructions
+            .iter()
+            .filter(|ix| ix.program_id != solana_sdk_ids::compute_budget::ID)
+            .cloned(),
+    );
+    v0::Message::try_compile(payer, &all, lookup_tables, blockhash).context("compiling the message")
+}
+
+/// Largest serialized transaction a validator accepts, signatures included
+pub const MAX_TRANSACTION_SIZE: usize = 1232;
+
+/// Bytes a transaction of `instructions` paid by `payer` takes, signatures
+/// and the compute budget `size_message` adds included
+pub fn transaction_size(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+) -> anyhow::Result<usize> {
+    // A price and the largest limit take as many bytes as any others
+    let message = compile(
+        payer,
+        MAX_COMPUTE_UNIT_LIMIT,
+        u64::MAX,
+        instructions,
+        lookup_tables,
+        Hash::default(),
+        None,
+    )?;
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); message.header.num_required_signatures as usize],
+        message: VersionedMessage::V0(message),
+    };
+    Ok(bincode::serialized_size(&transaction).context("sizing the transaction")? as usize)
+}
+
+/// Compile `instructions` into a v0 message whose compute budget fits them,
+/// for `payer` to sign later (see `offline`)
+///
+/// Simulates the instructions at the maximum limit, without signatures, then
+/// sets the limit to the units they consumed plus the margin, and the price
+/// to the chosen percentile of recent fees on the accounts they write.
+/// Compute budget instructions in `instructions` are replaced. Accounts in
+/// `lookup_tables` (see `lookup_table`) are named by index. Fails, with the
+/// simulation's logs, if the instructions fail.
+pub async fn size_message(
+    rpc: &RpcClient,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    options: &ComputeBudgetOptions,
+) -> anyhow::Result<SizedMessage> {
+    size(rpc, payer, instructions, lookup_tables, options, None).await
+}
+
+/// `size_message`, against `nonce` instead of a recent blockhash: the
+/// message stays valid until the nonce is advanced, and needs the nonce
+/// authority's signature as well as `payer`'s
+pub async fn size_nonced_message(
+    rpc: &RpcClient,
+    payer: &Pubkey,
+    nonce: &NonceAccount,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    options: &ComputeBudgetOptions,
+) -> anyhow::Result<SizedMessage> {
+    size(
+        rpc,
+        payer,
+        instructions,
+        lookup_tables,
+        options,
+        Some(nonce),
+    )
+    .await
+}
+
+async fn size(
+    rpc: &RpcClient,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    options: &ComputeBudgetOptions,
+    nonce: Option<&NonceAccount>,
+) -> anyhow::Result<SizedMessage> {
+    let blockhash = match nonce {
+        Some(nonce) => nonce.blockhash,
+        None => rpc
+            .get_latest_blockhash()
+            .await
+            .context("fetching a blockhash")?,
+    };
+
+    // The price doesn't change the units, so simulate without it
+    let message = compile(
+        payer,
+        MAX_COMPUTE_UNIT_LIMIT,
+        0,
+        instructions,
+        lookup_tables,
+        blockhash,
+        nonce,
+    )?;
+    let simulation = VersionedTransaction {
+        signatures: vec![Signature::default(); message.header.num_required_signatures as usize],
+        message: VersionedMessage::V0(message),
+    };
+    let result = rpc
+        .simulate_transaction_with_config(
+            &simulation,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .await
+        .context("simulating the transaction")?
+        .value;
+    if let Some(err) = result.err {
+        let logs = result.logs.unwrap_or_default().join("\n");
+        bail!("simulation failed: {err}\n{logs}");
+    }
+    let units_consumed = result
+        .units_consumed
+        .context("the simulation reported no units consumed")?;
+    let limit = compute_unit_limit(units_consumed, options.margin_percent);
+
+    let price = if options.fee_percentile == 0 {
+        0
+    } else {
+        let mut writable: Vec<Pubkey> = instructions
+            .iter()
+            .flat_map(|ix| &ix.accounts)
+            .filter(|meta| meta.is_writable)
+            .map(|meta| meta.pubkey)
+            .collect();
+        writable.sort_unstable();
+        writable.dedup();
+        writable.truncate(MAX_FEE_ACCOUNTS);
+        let fees: Vec<u64> = rpc
+            .get_recent_prioritization_fees(&writable)
+            .await
+            .context("fetching recent prioritization fees")?
+            .iter()
+            .map(|fee| fee.prioritization_fee)
+            .collect();
+        compute_unit_price(
+            &fees,
+            options.fee_percentile,
+            options.max_compute_unit_price,
+        )
+    };
+
+    let message = compile(
+        payer,
+        limit,
+        price,
+        instructions,
+        lookup_tables,
+        blockhash,
+        nonce,
+    )?;
+    Ok(SizedMessage {
+        message,
+        units_consumed,
+        compute_unit_limit: limit,
+        compute_unit_price: price,
+    })
+}
+
+/// Sign `instructions` into a v0 transaction whose compute budget fits them,
+/// as `size_message` sizes it; `payer` pays and is the only signer: a
+/// keypair, a `LedgerSigner` or a `RemoteSigner`
+pub async fn build_sized_transaction(
+    rpc: &RpcClient,
+    payer: &dyn AsyncSigner,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    options: &ComputeBudgetOptions,
+) -> anyhow::Result<SizedTransaction> {
+    let sized = size_message(rpc, &payer.address(), instructions, lookup_tables, options).await?;
+    let transaction = sign_transaction(VersionedMessage::V0(sized.message), &[payer])
+        .await
+        .context("signing the transaction")?;
+    Ok(SizedTransaction {
+        transaction,
+        units_consumed: sized.units_consumed,
+        compute_unit_limit: sized.compute_unit_limit,
+        compute_unit_price: sized.compute_unit_price,
+    })
+}
+
+/// `build_sized_transaction` against `nonce` (see `size_nonced_message`),
+/// signed by `payer` and by `authority`, the nonce's authority, which may be
+/// the same key
+pub async fn build_nonced_transaction(
+    rpc: &RpcClient,
+    payer: &dyn AsyncSigner,
+    authority: &dyn AsyncSigner,
+    nonce: &NonceAccount,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    options: &ComputeBudgetOptions,
+) -> anyhow::Result<SizedTransaction> {
+    ensure!(
+        authority.address() == nonce.authority,
+        "{} is not nonce account {}'s authority",
+        authority.address(),
+        nonce.address
+    );
+    let sized = size_nonced_message(
+        rpc,
+        &payer.address(),
+        nonce,
+        instructions,
+        lookup_tables,
+        options,
+    )
+    .await?;
+    let signers: &[&dyn AsyncSigner] = if authority.address() == payer.address() {
+        &[payer]
+    } else {
+        &[payer, authority]
+    };
+    let transaction = sign_transaction(VersionedMessage::V0(sized.message), signers)
+        .await
+        .context("signing the transaction")?;
+    Ok(SizedTransaction {
+        transaction,
+        units_consumed: sized.units_consumed,
+        compute_unit_limit: sized.compute_unit_limit,
+        compute_unit_price: sized.compute_unit_price,
+    })
+}
@@ -0,0 +1,159 @@
+//! Deploying a sunspot verifier
+//!
+//! `sunspot deploy` builds a circuit's verifier as a `.so`. This deploys it
+//! the way `solana program deploy` does: create a buffer account, write the
+//! ELF into it a transaction at a time, then deploy a new program from the
+//! buffer or upgrade an existing one. The buffer, a new program and an
+//! upgrade are all the payer's to authorize. The returned `program_hash` is
+//! what `ArtifactPins` pins and what `REGISTER_VERIFIER` can record.
+
+use anyhow::{ensure, Context};
+use solana_instruction::Instruction;
+use solana_keypair::Keypair;
+use solana_loader_v3_interface::{
+    get_program_data_address,
+    instruction::{self as loader, MINIMUM_EXTEND_PROGRAM_BYTES},
+    state::UpgradeableLoaderState,
+};
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+use crate::artifacts::{program_hash, programdata_elf};
+
+/// ELF bytes each write transaction carries, leaving room in the packet for
+/// one signature, three accounts and the instruction's header
+pub const WRITE_CHUNK_SIZE: usize = 1_000;
+
+/// The program a verifier is deployed as
+pub enum VerifierProgram {
+    /// A new program at this keypair's address
+    New(Keypair),
+    /// An existing upgradeable program whose upgrade authority is the payer
+    Upgrade(Pubkey),
+}
+
+impl VerifierProgram {
+    pub fn address(&self) -> Pubkey {
+        match self {
+            Self::New(keypair) => keypair.pubkey(),
+            Self::Upgrade(address) => *address,
+        }
+    }
+}
+
+/// A deployed verifier: its address and `program_hash`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeployedVerifier {
+    pub program_id: Pubkey,
+    pub hash: [u8; 32],
+}
+
+/// The instructions that create `buffer` and write `elf` into it, one
+/// transaction's worth each, with `payer` as the buffer's authority
+pub fn write_buffer_ixs(
+    payer: &Pubkey,
+    buffer: &Pubkey,
+    lamports: u64,
+    elf: &[u8],
+) -> anyhow::Result<Vec<Vec<Instruction>>> {
+    let mut transactions = vec![loader::create_buffer(
+        payer,
+        buffer,
+        payer,
+        lamports,
+        elf.len(),
+    )?];
+    for (i, chunk) in elf.chunks(WRITE_CHUNK_SIZE).enumerate() {
+        let offset = u32::try_from(i * WRITE_CHUNK_SIZE).context("the program is too large")?;
+        transactions.push(vec![loader::write(buffer, payer, offset, chunk.to_vec())]);
+    }
+    Ok(transactions)
+}
+
+/// Deploy `so`, a verifier `sunspot deploy` built, as `program`, paid for and
+/// authorized by `payer`
+pub async fn deploy_verifier(
+    rpc: &RpcClient,
+    payer: &Keypair,
+    program: &VerifierProgram,
+    so: &[u8],
+) -> anyhow::Result<DeployedVerifier> {
+    ensure!(so.starts_with(b"\x7fELF"), "not an ELF shared object");
+    let program_id = program.address();
+
+    // An upgrade larger than the program's data extends it in the same
+    // transaction
+    let mut finish = Vec::new();
+    if let VerifierProgram::Upgrade(address) = program {
+        let programdata = get_program_data_address(address);
+        let data = rpc
+            .get_account_data(&programdata)
+            .await
+            .with_context(|| format!("fetching {address}'s program data"))?;
+        let capacity = programdata_elf(&data)?.len();
+        if so.len() > capacity {
+            let shortfall =
+                u32::try_from(so.len() - capacity).context("the program is too large")?;
+            finish.push(loader::extend_program(
+                address,
+                Some(&payer.pubkey()),
+                shortfall.max(MINIMUM_EXTEND_PROGRAM_BYTES),
+            ));
+        }
+    }
+
+    let buffer = Keypair::new();
+    let lamports = rpc
+        .get_minimum_balance_for_rent_exemption(UpgradeableLoaderState::size_of_buffer(so.len()))
+        .await?;
+    let mut transactions = write_buffer_ixs(&payer.pubkey(), &buffer.pubkey(), lamports, so)?;
+    match program {
+        VerifierProgram::New(_) => {
+            let lamports = rpc
+                .get_minimum_balance_for_rent_exemption(UpgradeableLoaderState::size_of_program())
+                .await?;
+            finish.extend(loader::deploy_with_max_program_len(
+                &payer.pubkey(),
+                &program_id,
+                &buffer.pubkey(),
+                &payer.pubkey(),
+                lamports,
+                so.len(),
+            )?);
+        }
+        VerifierProgram::Upgrade(address) => finish.push(loader::upgrade(
+            address,
+            &buffer.pubkey(),
+            &payer.pubkey(),
+            &payer.pubkey(),
+        )),
+    }
+    transactions.push(finish);
+
+    let last = transactions.len() - 1;
+    for (i, ixs) in transactions.into_iter().enumerate() {
+        let mut signers: Vec<&Keypair> = vec![payer];
+        match (i, program) {
+            (0, _) => signers.push(&buffer),
+            (i, VerifierProgram::New(keypair)) if i == last => signers.push(keypair),
+            _ => {}
+        }
+        let blockhash = rpc.get_latest_blockhash().await?;
+        let transaction =
+            Transaction::new_signed_with_payer(&ixs, Some(&payer.pubkey()), &signers, blockhash);
+        rpc.send_and_confirm_transaction(&transaction)
+            .await
+            .with_context(|| match i {
+                0 => "creating the buffer".to_string(),
+                i if i == last => format!("deploying {program_id}"),
+                i => format!("writing the buffer at byte {}", (i - 1) * WRITE_CHUNK_SIZE),
+            })?;
+    }
+
+    Ok(DeployedVerifier {
+        program_id,
+        hash: program_hash(so),
+    })
+}
@@ -0,0 +1,334 @@
+//! Verifying sunspot's Groth16 proofs off chain
+//!
+//! Sunspot proves with gnark, whose Groth16 proofs for Noir circuits carry
+//! one BSB22 commitment: a Pedersen commitment to some of the witness, with a
+//! proof of knowledge, whose hash is an extra public input. Verifying one
+//! here takes the same pairings as the verifier program, without the fee of
+//! a transaction that fails.
+//!
+//! The verifying key is the `.vk` file `sunspot setup` writes, in gnark's
+//! encoding: points compressed or not (the top two bits of each point's first
+//! byte say which), big-endian, G2 coordinates imaginary part first.
+
+use anyhow::{bail, ensure, Context};
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G1Projective, G2Affine};
+use ark_ec::{pairing::Pairing, short_weierstrass::SWCurveConfig, AffineRepr, CurveGroup};
+use ark_ff::{BigInt, Field, PrimeField, Zero};
+use exclusion_common::{
+    instruction::{NUM_PUBLIC_INPUTS, WITNESS_HEADER_SIZE},
+    ExclusionError, Proof, PublicWitness,
+};
+use sha2::{Digest, Sha256};
+
+/// Domain separation tag gnark hashes commitments under
+const COMMITMENT_DST: &[u8] = b"bsb22-commitment";
+
+const FLAG_MASK: u8 = 0b11 << 6;
+const UNCOMPRESSED: u8 = 0b00 << 6;
+const COMPRESSED_SMALLEST: u8 = 0b10 << 6;
+const COMPRESSED_LARGEST: u8 = 0b11 << 6;
+
+/// The key a Pedersen commitment's proof of knowledge is checked with
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CommitmentKey {
+    g: G2Affine,
+    g_root_sigma_neg: G2Affine,
+}
+
+/// A circuit's Groth16 verifying key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifyingKey {
+    alpha: G1Affine,
+    beta: G2Affine,
+    gamma: G2Affine,
+    delta: G2Affine,
+    /// One point for the constant, one per public input, one per commitment
+    k: Vec<G1Affine>,
+    /// For each commitment, the (1-based) public inputs it also commits to
+    public_committed: Vec<Vec<u64>>,
+    commitment_key: CommitmentKey,
+}
+
+/// Reads gnark's encoding front to back
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        ensure!(self.bytes.len() >= len, "truncated");
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn flag(&self) -> anyhow::Result<u8> {
+        Ok(self.bytes.first().context("truncated")? & FLAG_MASK)
+    }
+
+    fn g1(&mut self) -> anyhow::Result<G1Affine> {
+        let flag = self.flag()?;
+        let point = if flag == UNCOMPRESSED {
+            let bytes = self.take(64)?;
+            G1Affine::new_unchecked(fq(&bytes[..32])?, fq(&bytes[32..])?)
+        } else {
+            ensure!(
+                flag == COMPRESSED_SMALLEST || flag == COMPRESSED_LARGEST,
+                "point at infinity"
+            );
+            let x = fq(&unflagged(self.take(32)?))?;
+            let y = (x * x * x + Fq::from(3u64))
+                .sqrt()
+                .context("G1 point is not on the curve")?;
+            let y = if lexicographically_largest(y) == (flag == COMPRESSED_LARGEST) {
+                y
+            } else {
+                -y
+            };
+            G1Affine::new_unchecked(x, y)
+        };
+        ensure!(
+            point.is_on_curve() && !point.is_zero(),
+            "G1 point is not on the curve"
+        );
+        Ok(point)
+    }
+
+    fn g2(&mut self) -> anyhow::Result<G2Affine> {
+        let flag = self.flag()?;
+        let point = if flag == UNCOMPRESSED {
+            let bytes = self.take(128)?;
+            G2Affine::new_unchecked(fq2(&bytes[..64])?, fq2(&bytes[64..])?)
+        } else {
+            ensure!(
+                flag == COMPRESSED_SMALLEST || flag == COMPRESSED_LARGEST,
+                "point at infinity"
+            );
+            let x = fq2(&unflagged(self.take(64)?))?;
+            let y = (x * x * x + ark_bn254::g2::Config::COEFF_B)
+                .sqrt()
+                .context("G2 point is not on the curve")?;
+            let largest = if y.c1.is_zero() {
+                lexicographically_largest(y.c0)
+            } else {
+                lexicographically_largest(y.c1)
+            };
+            let y = if largest == (flag == COMPRESSED_LARGEST) {
+                y
+            } else {
+                -y
+            };
+            G2Affine::new_unchecked(x, y)
+        };
+        ensure!(
+            point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve(),
+            "G2 point is not in the subgroup"
+        );
+        Ok(point)
+    }
+}
+
+/// A compressed point's x coordinate, without its flag bits
+fn unflagged(bytes: &[u8]) -> Vec<u8> {
+    let mut bytes = bytes.to_vec();
+    bytes[0] &= !FLAG_MASK;
+    bytes
+}
+
+/// A canonical big-endian base field element
+pub(crate) fn fq(bytes: &[u8]) -> anyhow::Result<Fq> {
+    Fq::from_bigint(be_bigint(bytes)).context("coordinate is not a field element")
+}
+
+/// A base field extension element, imaginary part first
+fn fq2(bytes: &[u8]) -> anyhow::Result<Fq2> {
+    Ok(Fq2::new(fq(&bytes[32..])?, fq(&bytes[..32])?))
+}
+
+fn be_bigint(bytes: &[u8]) -> BigInt<4> {
+    let mut limbs = [0u64; 4];
+    for (i, chunk) in bytes.chunks_exact(8).enumerate() {
+        limbs[3 - i] = u64::from_be_bytes(chunk.try_into().unwrap());
+    }
+    BigInt::new(limbs)
+}
+
+/// Whether `y` is the larger of `y` and `-y`, as gnark picks square roots
+fn lexicographically_largest(y: Fq) -> bool {
+    y.into_bigint() > Fq::MODULUS_MINUS_ONE_DIV_TWO
+}
+
+fn g1_uncompressed(point: &G1Affine) -> [u8; 64] {
+    let mut bytes = [0u8; 64];
+    bytes[..32].copy_from_slice(&be_bytes(point.x));
+    bytes[32..].copy_from_slice(&be_bytes(point.y));
+    bytes
+}
+
+fn be_bytes<F: PrimeField>(element: F) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (i, limb) in element.into_bigint().as_ref().iter().rev().enumerate() {
+        bytes[8 * i..8 * i + 8].copy_from_slice(&limb.to_be_bytes());
+    }
+    bytes
+}
+
+/// RFC 9380's expand_message_xmd with SHA-256
+fn expand_message_xmd(msg: &[u8], dst: &[u8], len: usize) -> Vec<u8> {
+    let dst_prime = [dst, &[dst.len() as u8]].concat();
+    let b0 = Sha256::new()
+        .chain_update([0u8; 64])
+        .chain_update(msg)
+        .chain_update((len as u16).to_be_bytes())
+        .chain_update([0u8])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut uniform = Vec::with_capacity(len + 32);
+    let mut previous = Sha256::new()
+        .chain_update(b0)
+        .chain_update([1u8])
+        .chain_update(&dst_prime)
+        .finalize();
+    uniform.extend_from_slice(&previous);
+    for i in 2..=len.div_ceil(32) {
+        let xored: Vec<u8> = b0.iter().zip(&previous).map(|(a, b)| a ^ b).collect();
+        previous = Sha256::new()
+            .chain_update(xored)
+            .chain_update([i as u8])
+            .chain_update(&dst_prime)
+            .finalize();
+        uniform.extend_from_slice(&previous);
+    }
+    uniform.truncate(len);
+    uniform
+}
+
+/// gnark's hash of `msg` to one scalar field element under `dst`: 48 bytes
+/// of expand_message_xmd, big-endian, reduced
+pub fn hash_to_field(msg: &[u8], dst: &[u8]) -> Fr {
+    Fr::from_be_bytes_mod_order(&expand_message_xmd(msg, dst, 48))
+}
+
+impl VerifyingKey {
+    /// Parse the verifying key `sunspot setup` writes (gnark's
+    /// `VerifyingKey.WriteTo` or `WriteRawTo`), for a circuit with one
+    /// commitment
+    pub fn from_gnark_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = Reader { bytes };
+        let alpha = reader.g1().context("alpha")?;
+        let _beta_g1 = reader.g1().context("beta (G1)")?;
+        let beta = reader.g2().context("beta")?;
+        let gamma = reader.g2().context("gamma")?;
+        let _delta_g1 = reader.g1().context("delta (G1)")?;
+        let delta = reader.g2().context("delta")?;
+        let k = (0..reader.u32()?)
+            .map(|i| reader.g1().with_context(|| format!("K[{i}]")))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let public_committed = (0..reader.u32()?)
+            .map(|_| (0..reader.u32()?).map(|_| reader.u64()).collect())
+            .collect::<anyhow::Result<Vec<Vec<u64>>>>()?;
+        let commitments = reader.u32()?;
+        if commitments != 1 || public_committed.len() != 1 {
+            bail!("circuit has {commitments} commitments; sunspot's have 1");
+        }
+        let commitment_key = CommitmentKey {
+            g: reader.g2().context("commitment key G")?,
+            g_root_sigma_neg: reader.g2().context("commitment key GRootSigmaNeg")?,
+        };
+        ensure!(
+            reader.bytes.is_empty(),
+            "{} trailing bytes",
+            reader.bytes.len()
+        );
+        ensure!(
+            k.len() == 1 + NUM_PUBLIC_INPUTS as usize + 1,
+            "key is for {} public inputs, not the exclusion circuit's {NUM_PUBLIC_INPUTS}",
+            k.len().saturating_sub(2)
+        );
+        ensure!(
+            public_committed[0]
+                .iter()
+                .all(|&index| (1..=u64::from(NUM_PUBLIC_INPUTS)).contains(&index)),
+            "commitment to an unknown public input"
+        );
+        Ok(Self {
+            alpha,
+            beta,
+            gamma,
+            delta,
+            k,
+            public_committed,
+            commitment_key,
+        })
+    }
+
+    /// Check `proof` of `witness` as the verifier program would
+    pub fn verify(&self, proof: &Proof, witness: &PublicWitness) -> Result<(), ExclusionError> {
+        match self.try_verify(proof, witness) {
+            Some(true) => Ok(()),
+            _ => Err(ExclusionError::ZkVerificationFailed),
+        }
+    }
+
+    /// Whether the pairings hold; None for a proof or witness that doesn't
+    /// parse
+    fn try_verify(&self, proof: &Proof, witness: &PublicWitness) -> Option<bool> {
+        let mut reader = Reader {
+            bytes: proof.as_bytes(),
+        };
+        let ar = reader.g1().ok()?;
+        let bs = reader.g2().ok()?;
+        let krs = reader.g1().ok()?;
+        if reader.u32().ok()? != 1 {
+            return None;
+        }
+        let commitment = reader.g1().ok()?;
+        let pok = reader.g1().ok()?;
+        for point in [ar, krs, commitment, pok] {
+            if !point.is_in_correct_subgroup_assuming_on_curve() {
+                return None;
+            }
+        }
+
+        let mut inputs = witness.as_bytes()[WITNESS_HEADER_SIZE..]
+            .chunks_exact(32)
+            .map(|field| Fr::from_bigint(be_bigint(field)))
+            .collect::<Option<Vec<Fr>>>()?;
+
+        // The commitment's hash is the last public input
+        let mut prehash = g1_uncompressed(&commitment).to_vec();
+        for &index in &self.public_committed[0] {
+            prehash.extend_from_slice(&be_bytes(inputs[index as usize - 1]));
+        }
+        inputs.push(hash_to_field(&prehash, COMMITMENT_DST));
+
+        let knowledge = Bn254::multi_pairing(
+            [commitment, pok],
+            [self.commitment_key.g_root_sigma_neg, self.commitment_key.g],
+        );
+        if !knowledge.is_zero() {
+            return Some(false);
+        }
+
+        let k_sum = self.k[1..]
+            .iter()
+            .zip(&inputs)
+            .fold(self.k[0].into_group() + commitment, |sum, (k, input)| {
+                sum + *k * input
+            });
+        let k_sum: G1Affine = G1Projective::into_affine(k_sum);
+        let check = Bn254::multi_pairing(
+            [ar, -self.alpha, -k_sum, -krs],
+            [bs, self.beta, self.gamma, self.delta],
+        );
+        Some(check.is_zero())
+    }
+}
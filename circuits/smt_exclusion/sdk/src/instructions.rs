@@ -0,0 +1,255 @@
+//! Instruction builders, in the account order the program's handlers document
+
+use exclusion_common::{instruction::METADATA_SIZE, seeds, CompressedProof, Proof, PublicWitness};
+use exclusion_program_example::{
+    events::NOOP_PROGRAM_ID,
+    instruction::{self, InstructionHeader},
+};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_pubkey::Pubkey;
+
+/// The accounts every gated SOL transfer names
+pub struct TransferAccounts {
+    pub program_id: Pubkey,
+    /// Proves its exclusion and pays
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    /// State account holding the root the proof is against
+    pub state: Pubkey,
+    pub verifier: Pubkey,
+}
+
+/// PDA `["state", admin]`
+pub fn derive_state_pda(program_id: &Pubkey, admin: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[seeds::STATE, admin.as_ref()], program_id)
+}
+
+/// PDA `["bond", state]`
+pub fn derive_bond_pda(program_id: &Pubkey, state: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[seeds::BOND, state.as_ref()], program_id)
+}
+
+/// PDA `["proof_buffer", owner]`
+pub fn derive_proof_buffer_pda(program_id: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[seeds::PROOF_BUFFER, owner.as_ref()], program_id)
+}
+
+/// PDA `["attestation", state, subject]`
+pub fn derive_attestation_pda(
+    program_id: &Pubkey,
+    state: &Pubkey,
+    subject: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[seeds::ATTESTATION, state.as_ref(), subject.as_ref()],
+        program_id,
+    )
+}
+
+/// PDA `["escrow", sender, recipient, escrow_id]`
+pub fn derive_escrow_pda(
+    program_id: &Pubkey,
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    escrow_id: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            seeds::ESCROW,
+            sender.as_ref(),
+            recipient.as_ref(),
+            &escrow_id.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+fn data(discriminator: u8, fields: &[&[u8]]) -> Vec<u8> {
+    let mut data = InstructionHeader::pack(discriminator).to_vec();
+    for field in fields {
+        data.extend_from_slice(field);
+    }
+    data
+}
+
+/// `INITIALIZE` `admin`'s state account, depositing `bond_lamports` as its
+/// bond (0 for none)
+pub fn initialize_ix(program_id: &Pubkey, admin: &Pubkey, bond_lamports: u64) -> Instruction {
+    let (state, _) = derive_state_pda(program_id, admin);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*admin, true),
+            AccountMeta::new(state, false),
+            AccountMeta::new_readonly(solana_sdk_ids::system_program::ID, false),
+            AccountMeta::new(derive_bond_pda(program_id, &state).0, false),
+        ],
+        data: data(instruction::INITIALIZE, &[&bond_lamports.to_le_bytes()]),
+    }
+}
+
+/// `SET_SMT_ROOT` of `admin`'s state account, valid until unix time
+/// `valid_until` (0 for never), with `metadata` pointing at the list
+pub fn set_smt_root_ix(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    smt_root: &[u8; 32],
+    valid_until: i64,
+    metadata: &[u8; METADATA_SIZE],
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(derive_state_pda(program_id, admin).0, false),
+            AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+        ],
+        data: data(
+            instruction::SET_SMT_ROOT,
+            &[smt_root, &valid_until.to_le_bytes(), metadata],
+        ),
+    }
+}
+
+/// `TRANSFER_SOL` of `amount` lamports with the proof and witness inline
+pub fn transfer_sol_ix(
+    accounts: &TransferAccounts,
+    amount: u64,
+    proof: &Proof,
+    witness: &PublicWitness,
+) -> Instruction {
+    Instruction {
+        program_id: accounts.program_id,
+        accounts: vec![
+            AccountMeta::new(accounts.sender, true),
+            AccountMeta::new(accounts.recipient, false),
+            AccountMeta::new_readonly(accounts.state, false),
+            AccountMeta::new_readonly(accounts.verifier, false),
+            AccountMeta::new_readonly(solana_sdk_ids::system_program::ID, false),
+            AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+        ],
+        data: data(
+            instruction::TRANSFER_SOL,
+            &[&amount.to_le_bytes(), proof.as_bytes(), witness.as_bytes()],
+        ),
+    }
+}
+
+/// `TRANSFER_SOL_COMPRESSED` of `amount` lamports, with a proof from
+/// `compress::compress_proof`
+pub fn transfer_sol_compressed_ix(
+    accounts: &TransferAccounts,
+    amount: u64,
+    proof: &CompressedProof,
+    witness: &PublicWitness,
+) -> Instruction {
+    Instruction {
+        program_id: accounts.program_id,
+        accounts: vec![
+            AccountMeta::new(accounts.sender, true),
+            AccountMeta::new(accounts.recipient, false),
+            AccountMeta::new_readonly(accounts.state, false),
+            AccountMeta::new_readonly(accounts.verifier, false),
+            AccountMeta::new_readonly(solana_sdk_ids::system_program::ID, false),
+            AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+        ],
+        data: data(
+            instruction::TRANSFER_SOL_COMPRESSED,
+            &[&amount.to_le_bytes(), proof.as_bytes(), witness.as_bytes()],
+        ),
+    }
+}
+
+/// `CREATE_PROOF_BUFFER` for `owner`, who pays its rent
+pub fn create_proof_buffer_ix(program_id: &Pubkey, owner: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(derive_proof_buffer_pda(program_id, owner).0, false),
+            AccountMeta::new_readonly(solana_sdk_ids::system_program::ID, false),
+        ],
+        data: data(instruction::CREATE_PROOF_BUFFER, &[]),
+    }
+}
+
+/// `WRITE_PROOF_CHUNK` of `chunk` at `offset` into the proof + witness payload
+pub fn write_proof_chunk_ix(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    offset: u16,
+    chunk: &[u8],
+) -> Instruction {
+    let len = u16::try_from(chunk.len()).expect("chunks fit in a transaction");
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(derive_proof_buffer_pda(program_id, owner).0, false),
+        ],
+        data: data(
+            instruction::WRITE_PROOF_CHUNK,
+            &[&offset.to_le_bytes(), &len.to_le_bytes(), chunk],
+        ),
+    }
+}
+
+/// `TRANSFER_FROM_BUFFER` of `amount` lamports with the sender's buffered proof
+pub fn transfer_from_buffer_ix(accounts: &TransferAccounts, amount: u64) -> Instruction {
+    Instruction {
+        program_id: accounts.program_id,
+        accounts: vec![
+            AccountMeta::new(accounts.sender, true),
+            AccountMeta::new(accounts.recipient, false),
+            AccountMeta::new_readonly(accounts.state, false),
+            AccountMeta::new_readonly(accounts.verifier, false),
+            AccountMeta::new_readonly(solana_sdk_ids::system_program::ID, false),
+            AccountMeta::new(
+                derive_proof_buffer_pda(&accounts.program_id, &accounts.sender).0,
+                false,
+            ),
+            AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+        ],
+        data: data(instruction::TRANSFER_FROM_BUFFER, &[&amount.to_le_bytes()]),
+    }
+}
+
+/// `CLAIM_FROM_ESCROW` of `accounts.sender`'s escrow `escrow_id`, signed by
+/// `accounts.recipient` with their own proof if one is given. A claim with a
+/// proof is version 2, and one without is version 1.
+pub fn claim_from_escrow_ix(
+    accounts: &TransferAccounts,
+    escrow_id: u64,
+    proof: Option<(&Proof, &PublicWitness)>,
+) -> Instruction {
+    let escrow = derive_escrow_pda(
+        &accounts.program_id,
+        &accounts.sender,
+        &accounts.recipient,
+        escrow_id,
+    )
+    .0;
+    let data = match proof {
+        Some((proof, witness)) => data(
+            instruction::CLAIM_FROM_ESCROW,
+            &[proof.as_bytes(), witness.as_bytes()],
+        ),
+        None => {
+            let mut data = data(instruction::CLAIM_FROM_ESCROW, &[]);
+            data[1] = 1;
+            data
+        }
+    };
+    Instruction {
+        program_id: accounts.program_id,
+        accounts: vec![
+            AccountMeta::new(accounts.recipient, true),
+            AccountMeta::new(accounts.sender, false),
+            AccountMeta::new(escrow, false),
+            AccountMeta::new_readonly(accounts.state, false),
+            AccountMeta::new_readonly(accounts.verifier, false),
+            AccountMeta::new_readonly(solana_sdk_ids::system_program::ID, false),
+        ],
+        data,
+    }
+}
@@ -0,0 +1,338 @@
+//! Gated transfers submitted as Jito bundles
+//!
+//! A gated transfer is a large transaction, and a buffered one is several, so
+//! under congestion they tend to be dropped or land only in part. A bundle goes
+//! to the Jito block engine, which lands its transactions in order, all in one
+//! slot, or none of them. The tip to a Jito tip account rides in the last
+//! transaction, so it is only paid when the transfer lands. See
+//! <https://docs.jito.wtf/lowlatencytxnsend/>.
+
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use exclusion_common::{Proof, PublicWitness};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use smt::inputs::field_hex;
+use solana_commitment_config::CommitmentConfig;
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_hash::Hash;
+use solana_instruction::Instruction;
+use solana_keypair::Keypair;
+use solana_message::Message;
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_signature::Signature;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+use crate::{
+    instructions::{self, TransferAccounts},
+    state::get_state_account,
+};
+
+/// Mainnet block engine; regional ones are listed in Jito's docs
+pub const MAINNET_BLOCK_ENGINE_URL: &str = "https://mainnet.block-engine.jito.wtf";
+
+/// Most transactions a bundle may hold
+pub const MAX_BUNDLE_TRANSACTIONS: usize = 5;
+
+/// Smallest tip the block engine accepts
+pub const MIN_TIP_LAMPORTS: u64 = 1_000;
+
+/// How often `send_via_jito` polls the bundle's status
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// JSON-RPC client for a block engine's bundle API
+pub struct JitoClient {
+    http: reqwest::Client,
+    url: String,
+}
+
+/// A bundle's progress, from `getInflightBundleStatuses`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum BundleStatus {
+    /// Not yet landed (or not yet seen)
+    Pending,
+    /// Landed; every transaction in it executed
+    Landed,
+    /// Simulated or auctioned, but not landed
+    Failed,
+    /// Unknown to the block engine, or older than five minutes
+    Invalid,
+}
+
+#[derive(Deserialize)]
+struct InflightStatus {
+    status: BundleStatus,
+}
+
+#[derive(Deserialize)]
+struct Statuses<T> {
+    value: Vec<Option<T>>,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<Value>,
+}
+
+impl JitoClient {
+    /// A client for the block engine at `url`, e.g. [`MAINNET_BLOCK_ENGINE_URL`]
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            url: url.into(),
+        }
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> anyhow::Result<T> {
+        let response: RpcResponse<T> = self
+            .http
+            .post(format!("{}/api/v1/bundles", self.url))
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .send()
+            .await
+            .with_context(|| format!("calling {method}"))?
+            .json()
+            .await
+            .with_context(|| format!("decoding the {method} response"))?;
+        match (response.result, response.error) {
+            (Some(result), None) => Ok(result),
+            (_, Some(error)) => bail!("{method} failed: {error}"),
+            (None, None) => bail!("{method} returned no result"),
+        }
+    }
+
+    /// The accounts tips may go to
+    pub async fn tip_accounts(&self) -> anyhow::Result<Vec<Pubkey>> {
+        let accounts: Vec<String> = self.call("getTipAccounts", json!([])).await?;
+        accounts
+            .iter()
+            .map(|account| account.parse().context("parsing a tip account"))
+            .collect()
+    }
+
+    /// Submit signed transactions as one bundle; returns its ID
+    pub async fn send_bundle(&self, transactions: &[Transaction]) -> anyhow::Result<String> {
+        if transactions.is_empty() || transactions.len() > MAX_BUNDLE_TRANSACTIONS {
+            bail!(
+                "a bundle holds 1 to {MAX_BUNDLE_TRANSACTIONS} transactions, got {}",
+                transactions.len()
+            );
+        }
+        let encoded: Vec<String> = transactions
+            .iter()
+            .map(|transaction| {
+                STANDARD.encode(bincode::serialize(transaction).expect("transactions serialize"))
+            })
+            .collect();
+        self.call("sendBundle", json!([encoded, { "encoding": "base64" }]))
+            .await
+    }
+
+    /// A recent bundle's status
+    pub async fn bundle_status(&self, bundle_id: &str) -> anyhow::Result<BundleStatus> {
+        let statuses: Statuses<InflightStatus> = self
+            .call("getInflightBundleStatuses", json!([[bundle_id]]))
+            .await?;
+        Ok(statuses
+            .value
+            .into_iter()
+            .next()
+            .flatten()
+            .map_or(BundleStatus::Invalid, |status| status.status))
+    }
+}
+
+/// A gated SOL transfer, with a proof of the sender's exclusion
+pub struct GatedTransfer<'a> {
+    pub accounts: TransferAccounts,
+    pub amount: u64,
+    pub proof: &'a Proof,
+    pub witness: &'a PublicWitness,
+    /// Upload the proof to the sender's proof buffer and `TRANSFER_FROM_BUFFER`,
+    /// leaving the transfer transaction room for more instructions, instead of
+    /// a single `TRANSFER_SOL`
+    pub buffered: bool,
+}
+
+/// Tip and compute budget for a bundled transfer
+pub struct JitoOptions {
+    pub tip_lamports: u64,
+    pub compute_unit_limit: u32,
+    /// Priority fee in micro-lamports per compute unit; 0 adds no price instruction
+    pub compute_unit_price: u64,
+}
+
+impl Default for JitoOptions {
+    fn default() -> Self {
+        Self {
+            tip_lamports: 10_000,
+            // Verifying the proof takes most of a transaction's compute
+            compute_unit_limit: 500_000,
+            compute_unit_price: 0,
+        }
+    }
+}
+
+/// A bundle that landed
+pub struct LandedBundle {
+    pub bundle_id: String,
+    /// In bundle order; the last is the transfer
+    pub signatures: Vec<Signature>,
+}
+
+/// Signed bundle transactions for `transfer`, tipping `tip_account`
+///
+/// A buffered transfer first creates the sender's proof buffer, unless
+/// `create_buffer` is false because it already exists, and writes the proof and
+/// witness into it. The last transaction sets the compute budget, transfers and
+/// tips.
+pub fn build_bundle(
+    transfer: &GatedTransfer,
+    sender: &Keypair,
+    tip_account: &Pubkey,
+    options: &JitoOptions,
+    create_buffer: bool,
+    blockhash: Hash,
+) -> Vec<Transaction> {
+    let accounts = &transfer.accounts;
+    let sign = |instructions: &[Instruction]| {
+        let message = Message::new_with_blockhash(instructions, Some(&sender.pubkey()), &blockhash);
+        Transaction::new(&[sender], message, blockhash)
+    };
+    let mut bundle = Vec::new();
+
+    let mut transfer_instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+        options.compute_unit_limit,
+    )];
+    if options.compute_unit_price > 0 {
+        transfer_instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            options.compute_unit_price,
+        ));
+    }
+    if transfer.buffered {
+        let mut upload = Vec::new();
+        if create_buffer {
+            upload.push(instructions::create_proof_buffer_ix(
+                &accounts.program_id,
+                &accounts.sender,
+            ));
+        }
+        // The whole payload fits in one chunk
+        let payload = [&transfer.proof.as_bytes()[..], transfer.witness.as_bytes()].concat();
+        upload.push(instructions::write_proof_chunk_ix(
+            &accounts.program_id,
+            &accounts.sender,
+            0,
+            &payload,
+        ));
+        bundle.push(sign(&upload));
+        transfer_instructions.push(instructions::transfer_from_buffer_ix(
+            accounts,
+            transfer.amount,
+        ));
+    } else {
+        transfer_instructions.push(instructions::transfer_sol_ix(
+            accounts,
+            transfer.amount,
+            transfer.proof,
+            transfer.witness,
+        ));
+    }
+    transfer_instructions.push(solana_system_interface::instruction::transfer(
+        &accounts.sender,
+        tip_account,
+        options.tip_lamports,
+    ));
+    bundle.push(sign(&transfer_instructions));
+    bundle
+}
+
+/// Send `transfer` as a Jito bundle and wait for it to land
+///
+/// `sender` signs and pays for every transaction. Fails before sending if the
+/// witness isn't against the state's current root, and after if the block
+/// engine reports the bundle failed, or if it hasn't landed by the time its
+/// blockhash expires.
+pub async fn send_via_jito(
+    rpc: &RpcClient,
+    jito: &JitoClient,
+    sender: &Keypair,
+    transfer: &GatedTransfer<'_>,
+    options: &JitoOptions,
+) -> anyhow::Result<LandedBundle> {
+    if options.tip_lamports < MIN_TIP_LAMPORTS {
+        bail!("tips must be at least {MIN_TIP_LAMPORTS} lamports");
+    }
+    // A proof against a replaced root would fail on chain, after the tip
+    let state =
+        get_state_account(rpc, &transfer.accounts.program_id, &transfer.accounts.state).await?;
+    if transfer.witness.smt_root() != state.smt_root {
+        bail!(
+            "the proof is against {}, not the current root",
+            field_hex(&transfer.witness.smt_root())
+        );
+    }
+
+    let tip_accounts = jito.tip_accounts().await?;
+    let (blockhash, _) = rpc
+        .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+        .await
+        .context("fetching a blockhash")?;
+    if tip_accounts.is_empty() {
+        bail!("the block engine listed no tip accounts");
+    }
+    // Spread tips across the accounts; they are write-locked by every bundle
+    let tip_account = &tip_accounts[blockhash.as_ref()[0] as usize % tip_accounts.len()];
+    let create_buffer = transfer.buffered && {
+        let (buffer, _) = instructions::derive_proof_buffer_pda(
+            &transfer.accounts.program_id,
+            &transfer.accounts.sender,
+        );
+        rpc.get_account_with_commitment(&buffer, CommitmentConfig::confirmed())
+            .await
+            .context("fetching the proof buffer")?
+            .value
+            .is_none()
+    };
+
+    let bundle = build_bundle(
+        transfer,
+        sender,
+        tip_account,
+        options,
+        create_buffer,
+        blockhash,
+    );
+    let signatures = bundle
+        .iter()
+        .map(|transaction| transaction.signatures[0])
+        .collect();
+    let bundle_id = jito.send_bundle(&bundle).await?;
+
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        match jito.bundle_status(&bundle_id).await? {
+            BundleStatus::Landed => {
+                return Ok(LandedBundle {
+                    bundle_id,
+                    signatures,
+                })
+            }
+            BundleStatus::Failed => bail!("bundle {bundle_id} failed"),
+            // Just submitted bundles can be unknown for a moment
+            BundleStatus::Pending | BundleStatus::Invalid => {}
+        }
+        let valid = rpc
+            .is_blockhash_valid(&blockhash, CommitmentConfig::processed())
+            .await
+            .context("checking the blockhash")?;
+        if !valid {
+            bail!("bundle {bundle_id} did not land before its blockhash expired");
+        }
+    }
+}
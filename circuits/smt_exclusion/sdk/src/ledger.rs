@@ -0,0 +1,419 @@
+//! Signing with a Ledger running the Solana app
+//!
+//! Whoever holds a state account's admin key can point every transfer at a
+//! different blacklist, so in a serious deployment that key lives on a
+//! hardware wallet, not in a keypair file. `LedgerSigner` implements
+//! `solana_signer::Signer`, so it signs wherever a keypair does
+//! (`build_sized_transaction` for `INITIALIZE` and `SET_SMT_ROOT`,
+//! `offline`), and the device shows each message for approval. It speaks the
+//! Solana app's APDU protocol, as `solana-remote-wallet` does, over a
+//! `Transport`: `HidTransport` for a device on USB (Linux hidraw), or
+//! `TcpTransport` for the Speculos emulator.
+
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    net::TcpStream,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+};
+
+use anyhow::{ensure, Context};
+use solana_pubkey::Pubkey;
+use solana_signature::Signature;
+use solana_signer::{Signer, SignerError};
+
+/// Ledger's USB vendor ID
+pub const LEDGER_VENDOR_ID: u16 = 0x2c97;
+
+const CLA: u8 = 0xe0;
+const INS_GET_PUBKEY: u8 = 0x05;
+const INS_SIGN_MESSAGE: u8 = 0x06;
+const P1_NON_CONFIRM: u8 = 0x00;
+const P1_CONFIRM: u8 = 0x01;
+/// This APDU continues the previous one's payload
+const P2_EXTEND: u8 = 0x01;
+/// More APDUs follow with the rest of the payload
+const P2_MORE: u8 = 0x02;
+/// Most payload bytes in one APDU
+const MAX_CHUNK_SIZE: usize = 255;
+/// Longest message the app signs
+const MAX_MESSAGE_SIZE: usize = u16::MAX as usize;
+
+const SW_SUCCESS: u16 = 0x9000;
+
+/// HID reports carry 64 bytes: channel (2), tag (1), sequence (2), then the
+/// APDU's length (2) in the first report, then the APDU
+pub const HID_PACKET_SIZE: usize = 64;
+const HID_CHANNEL: u16 = 0x0101;
+const HID_TAG_APDU: u8 = 0x05;
+/// The report descriptor's vendor usage page, 0xffa0, on the APDU interface
+/// (the device's others are for FIDO and keyboards)
+const APDU_USAGE_PAGE: [u8; 3] = [0x06, 0xa0, 0xff];
+
+/// Carries APDUs to the device and its responses back
+pub trait Transport: Send {
+    /// Send `apdu`; the response ends with its two-byte status word
+    fn exchange(&mut self, apdu: &[u8]) -> anyhow::Result<Vec<u8>>;
+}
+
+/// `m/44'/501'`, then the account and change indexes, as the Solana CLI's
+/// `usb://ledger?key=ACCOUNT/CHANGE` names them; every index hardened
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DerivationPath {
+    pub account: Option<u32>,
+    /// Only with an account
+    pub change: Option<u32>,
+}
+
+impl DerivationPath {
+    fn indexes(&self) -> Vec<u32> {
+        let mut indexes = vec![44, 501];
+        indexes.extend(self.account);
+        if self.account.is_some() {
+            indexes.extend(self.change);
+        }
+        indexes
+    }
+
+    /// The index count, then each hardened index, big-endian
+    fn serialize(&self) -> Vec<u8> {
+        let indexes = self.indexes();
+        let mut bytes = vec![indexes.len() as u8];
+        for index in indexes {
+            bytes.extend_from_slice(&(index | 0x8000_0000).to_be_bytes());
+        }
+        bytes
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = anyhow::Error;
+
+    /// `ACCOUNT` or `ACCOUNT/CHANGE`, each optionally with a trailing `'`;
+    /// empty for `m/44'/501'`
+    fn from_str(key: &str) -> anyhow::Result<Self> {
+        let index = |part: &str| -> anyhow::Result<u32> {
+            let index: u32 = part
+                .trim_end_matches('\'')
+                .parse()
+                .with_context(|| format!("invalid derivation index {part}"))?;
+            ensure!(index < 0x8000_0000, "derivation index {index} is too large");
+            Ok(index)
+        };
+        let mut parts = key.split('/').filter(|part| !part.is_empty());
+        let path = Self {
+            account: parts.next().map(index).transpose()?,
+            change: parts.next().map(index).transpose()?,
+        };
+        ensure!(parts.next().is_none(), "derivation path {key} is too long");
+        Ok(path)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for index in self.indexes() {
+            write!(f, "/{index}'")?;
+        }
+        Ok(())
+    }
+}
+
+/// A key on a Ledger
+pub struct LedgerSigner {
+    transport: Mutex<Box<dyn Transport>>,
+    path: DerivationPath,
+    pubkey: Pubkey,
+}
+
+impl fmt::Debug for LedgerSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LedgerSigner")
+            .field("path", &self.path)
+            .field("pubkey", &self.pubkey)
+            .finish()
+    }
+}
+
+impl LedgerSigner {
+    /// The key at `path` on the device behind `transport`; reads its pubkey
+    /// without asking the user
+    pub fn new(transport: Box<dyn Transport>, path: DerivationPath) -> anyhow::Result<Self> {
+        let mut signer = Self {
+            transport: Mutex::new(transport),
+            path,
+            pubkey: Pubkey::default(),
+        };
+        let pubkey = signer.send(INS_GET_PUBKEY, P1_NON_CONFIRM, 0, &path.serialize())?;
+        signer.pubkey = Pubkey::try_from(pubkey.as_slice())
+            .map_err(|_| anyhow::anyhow!("the device returned a {}-byte pubkey", pubkey.len()))?;
+        Ok(signer)
+    }
+
+    /// The key at `path` on the first Ledger found on USB
+    pub fn usb(path: DerivationPath) -> anyhow::Result<Self> {
+        Self::new(Box::new(HidTransport::open_first()?), path)
+    }
+
+    pub fn path(&self) -> DerivationPath {
+        self.path
+    }
+
+    /// Sign `message` once the user approves it on the device
+    pub fn sign(&self, message: &[u8]) -> anyhow::Result<Signature> {
+        ensure!(
+            message.len() <= MAX_MESSAGE_SIZE,
+            "a {}-byte message is too long for the Solana app",
+            message.len()
+        );
+        let mut payload = vec![1];
+        payload.extend(self.path.serialize());
+        let (first, rest) = message.split_at(message.len().min(MAX_CHUNK_SIZE - payload.len()));
+        payload.extend_from_slice(first);
+        let mut chunks = vec![(if rest.is_empty() { 0 } else { P2_MORE }, payload)];
+        chunks.extend(
+            rest.chunks(MAX_CHUNK_SIZE)
+                .map(|chunk| (P2_EXTEND | P2_MORE, chunk.to_vec())),
+        );
+        if chunks.len() > 1 {
+            // The last chunk says no more follow
+            chunks.last_mut().unwrap().0 &= !P2_MORE;
+        }
+        let mut response = Vec::new();
+        for (p2, chunk) in chunks {
+            response = self.send(INS_SIGN_MESSAGE, P1_CONFIRM, p2, &chunk)?;
+        }
+        Signature::try_from(response.as_slice())
+            .map_err(|_| anyhow::anyhow!("the device returned a {}-byte signature", response.len()))
+    }
+
+    /// Send one APDU and return its response without the status word
+    fn send(&self, ins: u8, p1: u8, p2: u8, payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut apdu = vec![CLA, ins, p1, p2, payload.len() as u8];
+        apdu.extend_from_slice(payload);
+        let mut response = self
+            .transport
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .exchange(&apdu)?;
+        ensure!(response.len() >= 2, "the device sent no status");
+        let status =
+            u16::from_be_bytes([response[response.len() - 2], response[response.len() - 1]]);
+        response.truncate(response.len() - 2);
+        match status {
+            SW_SUCCESS => Ok(response),
+            status => Err(LedgerStatus(status).into()),
+        }
+    }
+}
+
+/// A status word other than success
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LedgerStatus(pub u16);
+
+impl LedgerStatus {
+    pub const REJECTED: Self = Self(0x6985);
+}
+
+impl fmt::Display for LedgerStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0x6985 => write!(f, "rejected on the device"),
+            0x5515 => write!(f, "the Ledger is locked: unlock it"),
+            0x6d00 | 0x6e00 | 0x6511 => write!(f, "open the Solana app on the Ledger"),
+            0x6a80 => write!(f, "the Solana app couldn't parse the message"),
+            0x6808 => write!(
+                f,
+                "the Solana app can't show this message; enable blind signing in its settings"
+            ),
+            status => write!(f, "the Ledger returned status {status:#06x}"),
+        }
+    }
+}
+
+impl std::error::Error for LedgerStatus {}
+
+impl Signer for LedgerSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.sign(message)
+            .map_err(|error| match error.downcast_ref::<LedgerStatus>() {
+                Some(&LedgerStatus::REJECTED) => SignerError::UserCancel(error.to_string()),
+                _ => SignerError::Protocol(format!("{error:#}")),
+            })
+    }
+
+    fn is_interactive(&self) -> bool {
+        true
+    }
+}
+
+/// `apdu` as the HID reports that carry it
+pub fn hid_packets(apdu: &[u8]) -> Vec<[u8; HID_PACKET_SIZE]> {
+    let mut framed = (apdu.len() as u16).to_be_bytes().to_vec();
+    framed.extend_from_slice(apdu);
+    framed
+        .chunks(HID_PACKET_SIZE - 5)
+        .enumerate()
+        .map(|(sequence, chunk)| {
+            let mut packet = [0; HID_PACKET_SIZE];
+            packet[..2].copy_from_slice(&HID_CHANNEL.to_be_bytes());
+            packet[2] = HID_TAG_APDU;
+            packet[3..5].copy_from_slice(&(sequence as u16).to_be_bytes());
+            packet[5..5 + chunk.len()].copy_from_slice(chunk);
+            packet
+        })
+        .collect()
+}
+
+/// Reassembles a response from the HID reports that carry it
+#[derive(Debug, Default)]
+pub struct HidResponse {
+    length: Option<usize>,
+    data: Vec<u8>,
+    sequence: u16,
+}
+
+impl HidResponse {
+    /// Add the next report; the response, once it is complete
+    pub fn push(&mut self, packet: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        ensure!(
+            packet.len() >= 5
+                && packet[..2] == HID_CHANNEL.to_be_bytes()
+                && packet[2] == HID_TAG_APDU,
+            "unexpected HID report from the device"
+        );
+        let sequence = u16::from_be_bytes([packet[3], packet[4]]);
+        ensure!(
+            sequence == self.sequence,
+            "HID report {sequence} out of order (expected {})",
+            self.sequence
+        );
+        self.sequence += 1;
+        let mut body = &packet[5..];
+        let length = match self.length {
+            Some(length) => length,
+            None => {
+                ensure!(body.len() >= 2, "HID report too short");
+                let length = usize::from(u16::from_be_bytes([body[0], body[1]]));
+                body = &body[2..];
+                *self.length.insert(length)
+            }
+        };
+        let take = body.len().min(length - self.data.len());
+        self.data.extend_from_slice(&body[..take]);
+        Ok((self.data.len() == length).then(|| std::mem::take(&mut self.data)))
+    }
+}
+
+/// A Ledger on USB, through Linux's hidraw driver
+pub struct HidTransport {
+    device: File,
+}
+
+impl HidTransport {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let device = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("opening {} (check the udev rules)", path.display()))?;
+        Ok(Self { device })
+    }
+
+    /// The first Ledger's APDU interface under `/dev/hidraw*`
+    pub fn open_first() -> anyhow::Result<Self> {
+        let path = find_ledgers()?
+            .into_iter()
+            .next()
+            .context("no Ledger found: plug it in, unlock it and open the Solana app")?;
+        Self::open(&path)
+    }
+}
+
+/// The hidraw devices that are a Ledger's APDU interface
+pub fn find_ledgers() -> anyhow::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    let entries = match std::fs::read_dir("/sys/class/hidraw") {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(found),
+        Err(error) => return Err(error).context("listing /sys/class/hidraw"),
+    };
+    let vendor = format!("HID_ID=0003:0000{LEDGER_VENDOR_ID:04X}:");
+    for entry in entries {
+        let entry = entry?;
+        let device = entry.path().join("device");
+        let Ok(uevent) = std::fs::read_to_string(device.join("uevent")) else {
+            continue;
+        };
+        let Ok(descriptor) = std::fs::read(device.join("report_descriptor")) else {
+            continue;
+        };
+        if uevent.lines().any(|line| line.starts_with(&vendor))
+            && descriptor.starts_with(&APDU_USAGE_PAGE)
+        {
+            found.push(Path::new("/dev").join(entry.file_name()));
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+impl Transport for HidTransport {
+    fn exchange(&mut self, apdu: &[u8]) -> anyhow::Result<Vec<u8>> {
+        for packet in hid_packets(apdu) {
+            // Report ID 0: the device doesn't number its reports
+            let mut report = [0; HID_PACKET_SIZE + 1];
+            report[1..].copy_from_slice(&packet);
+            self.device
+                .write_all(&report)
+                .context("writing to the Ledger")?;
+        }
+        let mut response = HidResponse::default();
+        loop {
+            let mut packet = [0; HID_PACKET_SIZE];
+            let read = self
+                .device
+                .read(&mut packet)
+                .context("reading from the Ledger")?;
+            if let Some(response) = response.push(&packet[..read])? {
+                return Ok(response);
+            }
+        }
+    }
+}
+
+/// The Speculos emulator's APDU port, for testing against the Solana app
+/// without a device: each APDU, and each response before its status word,
+/// goes with a big-endian u32 length
+pub struct TcpTransport {
+    stream: TcpStream,
+}
+
+impl TcpTransport {
+    pub fn connect(address: &str) -> anyhow::Result<Self> {
+        let stream =
+            TcpStream::connect(address).with_context(|| format!("connecting to {address}"))?;
+        Ok(Self { stream })
+    }
+}
+
+impl Transport for TcpTransport {
+    fn exchange(&mut self, apdu: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut request = (apdu.len() as u32).to_be_bytes().to_vec();
+        request.extend_from_slice(apdu);
+        self.stream.write_all(&request)?;
+        let mut length = [0; 4];
+        self.stream.read_exact(&mut length)?;
+        let mut response = vec![0; u32::from_be_bytes(length) as usize + 2];
+        self.stream.read_exact(&mut response)?;
+        Ok(response)
+    }
+}
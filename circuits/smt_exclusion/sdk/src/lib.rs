@@ -0,0 +1,51 @@
+//! Rust client for the exclusion program
+//!
+//! Proves with the Noir toolchain (`prover`; `acir` reads the compiled circuit
+//! and lays out its inputs, and `witness` encodes the public inputs), builds
+//! the program's instructions from the proof (`instructions`), decodes its
+//! accounts (`accounts`), and submits gated transfers, including
+//! as Jito bundles (`jito`) so the large transfer transactions land under
+//! congestion, or as v0 transactions with a compute budget sized by simulation
+//! (`compute_budget`) and accounts from lookup tables (`lookup_table`), and
+//! against a durable nonce (`nonce`) when they can't land within a
+//! blockhash's life. `batch` sends many transfers from one proof, and
+//! `compress` shrinks a proof to fit a transfer in fewer bytes.
+//! `prevalidate` (with `groth16`) catches proofs the program would reject
+//! before they are sent, `artifacts` pins the circuit and verifier they are
+//! for (`deploy` deploys that verifier), and `send` decodes failures into the
+//! program's errors and resends until a transaction lands. `squads` proposes
+//! admin instructions to a multisig that holds the admin role. `signer` signs
+//! with keys a service doesn't hold, through a signing service or a KMS,
+//! `ledger` as an admin whose key is on a hardware wallet, and `offline`
+//! carries a transaction to a signer on another machine and back.
+//! `state` fetches a state account and its root (`pubsub` streams its
+//! changes), and `snapshot` checks a published, signed blacklist against that
+//! root.
+
+pub mod accounts;
+pub mod acir;
+pub mod artifacts;
+pub mod batch;
+#[cfg(feature = "bb-sys")]
+pub mod bb;
+pub mod compress;
+pub mod compute_budget;
+pub mod deploy;
+pub mod groth16;
+pub mod instructions;
+pub mod jito;
+pub mod ledger;
+pub mod lookup_table;
+pub mod nonce;
+pub mod offline;
+pub mod prevalidate;
+pub mod prover;
+pub mod pubsub;
+pub mod send;
+pub mod signer;
+pub mod snapshot;
+pub mod solver;
+pub mod squads;
+pub mod srs;
+pub mod state;
+pub mod witness;
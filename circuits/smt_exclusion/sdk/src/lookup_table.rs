@@ -0,0 +1,172 @@
+//! Address lookup tables for the accounts gated transfers share
+//!
+//! A v0 transaction names an account in a lookup table with a 1-byte index
+//! instead of its 32-byte address. The accounts most transfers against one
+//! state account name—the state, the verifier, the system and noop programs,
+//! and those of token or compressed transfers—can go in one table, leaving
+//! room in the transaction for the proof. Programs a transaction calls
+//! directly must still be named in full.
+//!
+//! A table is created at an address derived from its authority and a recent
+//! slot, and extended a batch of addresses at a time. Addresses added in a
+//! slot can be looked up from the next.
+
+use anyhow::{ensure, Context};
+use exclusion_program_example::events::NOOP_PROGRAM_ID;
+use solana_address_lookup_table_interface::{instruction, state::AddressLookupTable};
+use solana_commitment_config::CommitmentConfig;
+use solana_instruction::Instruction;
+use solana_keypair::Keypair;
+use solana_message::{AddressLookupTableAccount, Message};
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+use crate::instructions::TransferAccounts;
+
+/// Addresses one `ExtendLookupTable` adds, so that it fits a transaction
+/// alongside `CreateLookupTable`
+pub const MAX_ADDRESSES_PER_EXTEND: usize = 20;
+
+/// The accounts every transfer against `accounts.state` names, other than
+/// the sender, the recipient and the program itself
+pub fn transfer_addresses(accounts: &TransferAccounts) -> Vec<Pubkey> {
+    vec![
+        accounts.state,
+        accounts.verifier,
+        solana_sdk_ids::system_program::ID,
+        NOOP_PROGRAM_ID,
+    ]
+}
+
+/// Instructions adding `addresses` to `table`, one transaction's worth each
+pub fn extend_lookup_table_ixs(
+    table: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    addresses: &[Pubkey],
+) -> Vec<Instruction> {
+    addresses
+        .chunks(MAX_ADDRESSES_PER_EXTEND)
+        .map(|chunk| {
+            instruction::extend_lookup_table(*table, *authority, Some(*payer), chunk.to_vec())
+        })
+        .collect()
+}
+
+/// Transactions' instructions creating a table of `addresses` as of
+/// `recent_slot`, and the table's address
+///
+/// The first transaction creates the table and adds the first addresses; the
+/// rest add the remainder, in order.
+pub fn create_lookup_table_ixs(
+    authority: &Pubkey,
+    payer: &Pubkey,
+    recent_slot: u64,
+    addresses: &[Pubkey],
+) -> (Vec<Vec<Instruction>>, Pubkey) {
+    let (create, table) = instruction::create_lookup_table(*authority, *payer, recent_slot);
+    let mut transactions: Vec<Vec<Instruction>> =
+        extend_lookup_table_ixs(&table, authority, payer, addresses)
+            .into_iter()
+            .map(|extend| vec![extend])
+            .collect();
+    match transactions.first_mut() {
+        Some(first) => first.insert(0, create),
+        None => transactions.push(vec![create]),
+    }
+    (transactions, table)
+}
+
+/// Create a table of `addresses`, with `authority` paying and able to extend
+/// it, and return its address once every addition is confirmed
+pub async fn create_lookup_table(
+    rpc: &RpcClient,
+    authority: &Keypair,
+    addresses: &[Pubkey],
+) -> anyhow::Result<Pubkey> {
+    let recent_slot = rpc
+        .get_slot_with_commitment(CommitmentConfig::finalized())
+        .await
+        .context("fetching a recent slot")?;
+    let (transactions, table) = create_lookup_table_ixs(
+        &authority.pubkey(),
+        &authority.pubkey(),
+        recent_slot,
+        addresses,
+    );
+    send_all(rpc, authority, &transactions).await?;
+    Ok(table)
+}
+
+/// Add `addresses` to `table`, skipping those it already holds
+pub async fn extend_lookup_table(
+    rpc: &RpcClient,
+    authority: &Keypair,
+    table: &Pubkey,
+    addresses: &[Pubkey],
+) -> anyhow::Result<()> {
+    let existing = fetch_lookup_table(rpc, table).await?.addresses;
+    let mut new: Vec<Pubkey> = Vec::new();
+    for address in addresses {
+        if !existing.contains(address) && !new.contains(address) {
+            new.push(*address);
+        }
+    }
+    let transactions: Vec<Vec<Instruction>> =
+        extend_lookup_table_ixs(table, &authority.pubkey(), &authority.pubkey(), &new)
+            .into_iter()
+            .map(|extend| vec![extend])
+            .collect();
+    send_all(rpc, authority, &transactions).await
+}
+
+/// Fetch `table`'s addresses, to compile v0 messages against
+pub async fn fetch_lookup_table(
+    rpc: &RpcClient,
+    table: &Pubkey,
+) -> anyhow::Result<AddressLookupTableAccount> {
+    let account = rpc
+        .get_account_with_commitment(table, CommitmentConfig::confirmed())
+        .await
+        .context("fetching the lookup table")?
+        .value
+        .with_context(|| format!("lookup table {table} does not exist"))?;
+    ensure!(
+        account.owner == solana_sdk_ids::address_lookup_table::ID,
+        "{table} is not a lookup table"
+    );
+    decode_lookup_table(table, &account.data)
+}
+
+/// A lookup table account's addresses
+pub fn decode_lookup_table(
+    table: &Pubkey,
+    data: &[u8],
+) -> anyhow::Result<AddressLookupTableAccount> {
+    let decoded = AddressLookupTable::deserialize(data)
+        .map_err(|e| anyhow::anyhow!("decoding lookup table {table}: {e}"))?;
+    Ok(AddressLookupTableAccount {
+        key: *table,
+        addresses: decoded.addresses.to_vec(),
+    })
+}
+
+async fn send_all(
+    rpc: &RpcClient,
+    payer: &Keypair,
+    transactions: &[Vec<Instruction>],
+) -> anyhow::Result<()> {
+    for instructions in transactions {
+        let blockhash = rpc
+            .get_latest_blockhash()
+            .await
+            .context("fetching a blockhash")?;
+        let message = Message::new_with_blockhash(instructions, Some(&payer.pubkey()), &blockhash);
+        rpc.send_and_confirm_transaction(&Transaction::new(&[payer], message, blockhash))
+            .await
+            .context("sending a lookup table transaction")?;
+    }
+    Ok(())
+}
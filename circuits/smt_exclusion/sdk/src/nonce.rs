@@ -0,0 +1,115 @@
+//! Durable nonces, for transactions that can't land within a blockhash's life
+//!
+//! A transaction's recent blockhash expires about 150 slots (a minute or so)
+//! after it was produced. Proving a transfer can take longer than that, and a
+//! transaction signed offline (see `offline`) may wait longer still. A nonce
+//! account holds a blockhash of its own that doesn't expire: a transaction
+//! carrying it instead of a recent blockhash stays valid until the nonce is
+//! advanced, which the transaction's first instruction does, so it lands at
+//! most once.
+//!
+//! The nonce only lifts the transaction's limit. A transfer's proof is still
+//! bound to its recent slot, which the program accepts for its config's
+//! `max_proof_age_slots`, and for no longer than SlotHashes holds it (512
+//! slots, about three and a half minutes).
+
+use anyhow::{bail, ensure, Context};
+use solana_commitment_config::CommitmentConfig;
+use solana_hash::Hash;
+use solana_instruction::Instruction;
+use solana_keypair::Keypair;
+use solana_message::Message;
+use solana_nonce::{state::State, versions::Versions};
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_signer::Signer;
+use solana_system_interface::instruction as system_instruction;
+use solana_transaction::Transaction;
+
+/// An initialized nonce account
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NonceAccount {
+    pub address: Pubkey,
+    /// The key that must sign every transaction using the nonce
+    pub authority: Pubkey,
+    /// The blockhash a transaction using the nonce carries
+    pub blockhash: Hash,
+    pub lamports_per_signature: u64,
+}
+
+impl NonceAccount {
+    /// The instruction a transaction using the nonce starts with
+    pub fn advance_ix(&self) -> Instruction {
+        system_instruction::advance_nonce_account(&self.address, &self.authority)
+    }
+}
+
+/// Instructions creating a nonce account at `nonce` that `authority` uses,
+/// funded by `payer` with `lamports`, its rent exemption
+pub fn create_nonce_account_ixs(
+    payer: &Pubkey,
+    nonce: &Pubkey,
+    authority: &Pubkey,
+    lamports: u64,
+) -> Vec<Instruction> {
+    system_instruction::create_nonce_account(payer, nonce, authority, lamports)
+}
+
+/// Create a nonce account at `nonce`'s address that `authority` uses, with
+/// `payer` paying its rent exemption, and return it once confirmed
+pub async fn create_nonce_account(
+    rpc: &RpcClient,
+    payer: &dyn Signer,
+    nonce: &Keypair,
+    authority: &Pubkey,
+) -> anyhow::Result<NonceAccount> {
+    let lamports = rpc
+        .get_minimum_balance_for_rent_exemption(State::size())
+        .await
+        .context("fetching the nonce account's rent exemption")?;
+    let instructions =
+        create_nonce_account_ixs(&payer.pubkey(), &nonce.pubkey(), authority, lamports);
+    let blockhash = rpc
+        .get_latest_blockhash()
+        .await
+        .context("fetching a blockhash")?;
+    let message = Message::new_with_blockhash(&instructions, Some(&payer.pubkey()), &blockhash);
+    let signers: [&dyn Signer; 2] = [payer, nonce];
+    rpc.send_and_confirm_transaction(&Transaction::new(&signers, message, blockhash))
+        .await
+        .context("sending the nonce account transaction")?;
+    fetch_nonce_account(rpc, &nonce.pubkey()).await
+}
+
+/// Fetch `address`'s nonce, to compile a message against
+pub async fn fetch_nonce_account(
+    rpc: &RpcClient,
+    address: &Pubkey,
+) -> anyhow::Result<NonceAccount> {
+    let account = rpc
+        .get_account_with_commitment(address, CommitmentConfig::confirmed())
+        .await
+        .context("fetching the nonce account")?
+        .value
+        .with_context(|| format!("nonce account {address} does not exist"))?;
+    ensure!(
+        account.owner == solana_sdk_ids::system_program::ID,
+        "{address} is not a nonce account"
+    );
+    decode_nonce_account(address, &account.data)
+}
+
+/// A nonce account's authority and blockhash
+pub fn decode_nonce_account(address: &Pubkey, data: &[u8]) -> anyhow::Result<NonceAccount> {
+    let versions: Versions =
+        bincode::deserialize(data).with_context(|| format!("decoding nonce account {address}"))?;
+    match versions.state() {
+        State::Initialized(nonce) => Ok(NonceAccount {
+            address: *address,
+            authority: nonce.authority,
+            blockhash: nonce.blockhash(),
+            lamports_per_signature: nonce.get_lamports_per_signature(),
+        }),
+        State::Uninitialized => bail!("nonce account {address} is not initialized"),
+    }
+}
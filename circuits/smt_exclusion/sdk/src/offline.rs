@@ -0,0 +1,241 @@
+//! Transactions signed away from the machine that builds them
+//!
+//! A transfer is proved where nargo, sunspot and the blacklist are, which is
+//! not where a treasurer keeps the sender's key. The proving machine sizes
+//! the transaction and writes it, unsigned, to a file; the signer reads the
+//! file on an air-gapped machine, checks what the message does, decoded from
+//! the message itself, and adds its signature; anyone with RPC access then
+//! submits it. The file is JSON holding the transaction as `sendTransaction`
+//! takes it: bincode, base64-encoded.
+//!
+//! The message carries a recent blockhash, which expires about 150 slots (a
+//! minute or so) after it was fetched: sign and submit within that, or
+//! compile it against a durable nonce (see `nonce`), which doesn't expire.
+
+use anyhow::{bail, ensure, Context};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use exclusion_common::instruction;
+use serde::{Deserialize, Serialize};
+use solana_message::VersionedMessage;
+use solana_pubkey::Pubkey;
+use solana_signature::Signature;
+use solana_signer::Signer;
+use solana_transaction::versioned::VersionedTransaction;
+
+/// The file layout `to_json` writes
+pub const FORMAT_VERSION: u32 = 1;
+
+/// System program `Transfer` and `AdvanceNonceAccount`
+const SYSTEM_TRANSFER: u32 = 2;
+const ADVANCE_NONCE_ACCOUNT: u32 = 4;
+
+/// Compute budget program `SetComputeUnitLimit` and `SetComputeUnitPrice`
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+#[derive(Serialize, Deserialize)]
+struct File {
+    version: u32,
+    transaction: String,
+}
+
+/// A transaction waiting for some or all of its signatures
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OfflineTransaction {
+    transaction: VersionedTransaction,
+}
+
+impl OfflineTransaction {
+    /// `message`, with none of its signatures
+    pub fn new(message: VersionedMessage) -> Self {
+        let signatures =
+            vec![Signature::default(); usize::from(message.header().num_required_signatures)];
+        Self {
+            transaction: VersionedTransaction {
+                signatures,
+                message,
+            },
+        }
+    }
+
+    pub fn message(&self) -> &VersionedMessage {
+        &self.transaction.message
+    }
+
+    /// The keys that must sign, the fee payer first
+    pub fn signers(&self) -> &[Pubkey] {
+        let keys = self.transaction.message.static_account_keys();
+        &keys[..self.transaction.signatures.len().min(keys.len())]
+    }
+
+    /// The signers that haven't signed yet
+    pub fn missing(&self) -> Vec<Pubkey> {
+        self.signers()
+            .iter()
+            .zip(&self.transaction.signatures)
+            .filter(|(_, signature)| **signature == Signature::default())
+            .map(|(signer, _)| *signer)
+            .collect()
+    }
+
+    /// Add `signer`'s signature; fails if the message doesn't need it
+    pub fn sign(&mut self, signer: &dyn Signer) -> anyhow::Result<()> {
+        let pubkey = signer.try_pubkey().context("reading the signer's pubkey")?;
+        let position = self
+            .signers()
+            .iter()
+            .position(|key| *key == pubkey)
+            .with_context(|| format!("{pubkey} isn't one of the transaction's signers"))?;
+        let signature = signer
+            .try_sign_message(&self.transaction.message.serialize())
+            .with_context(|| format!("signing as {pubkey}"))?;
+        self.transaction.signatures[position] = signature;
+        Ok(())
+    }
+
+    /// The transaction, once every signer has signed
+    pub fn into_signed(self) -> anyhow::Result<VersionedTransaction> {
+        let missing = self.missing();
+        ensure!(
+            missing.is_empty(),
+            "not signed by {}",
+            missing
+                .iter()
+                .map(Pubkey::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Ok(self.transaction)
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        let bytes = bincode::serialize(&self.transaction).context("encoding the transaction")?;
+        let file = File {
+            version: FORMAT_VERSION,
+            transaction: STANDARD.encode(bytes),
+        };
+        Ok(serde_json::to_string_pretty(&file)? + "\n")
+    }
+
+    /// Read a file `to_json` wrote; fails if a signature in it doesn't
+    /// verify, as when the message was changed after someone signed it
+    pub fn from_json(text: &str) -> anyhow::Result<Self> {
+        let file: File = serde_json::from_str(text).context("not an offline transaction file")?;
+        ensure!(
+            file.version == FORMAT_VERSION,
+            "unsupported offline transaction version {} (expected {FORMAT_VERSION})",
+            file.version
+        );
+        let bytes = STANDARD
+            .decode(file.transaction.trim())
+            .context("decoding the transaction's base64")?;
+        let transaction: VersionedTransaction =
+            bincode::deserialize(&bytes).context("decoding the transaction")?;
+        transaction
+            .sanitize()
+            .context("the transaction is malformed")?;
+        let offline = Self { transaction };
+        let message = offline.transaction.message.serialize();
+        for (signer, signature) in offline
+            .signers()
+            .iter()
+            .zip(&offline.transaction.signatures)
+        {
+            if *signature != Signature::default() && !signature.verify(signer.as_ref(), &message) {
+                bail!("{signer}'s signature doesn't match the message");
+            }
+        }
+        Ok(offline)
+    }
+
+    /// What the message does, a line per instruction after the fee payer
+    /// and blockhash, with `program_id`'s instructions named
+    pub fn describe(&self, program_id: &Pubkey) -> Vec<String> {
+        let message = &self.transaction.message;
+        let keys = message.static_account_keys();
+        let key = |index: u8| match keys.get(usize::from(index)) {
+            Some(key) => key.to_string(),
+            None => format!("lookup table account {}", usize::from(index) - keys.len()),
+        };
+        let mut lines = vec![
+            format!(
+                "Fee payer: {}",
+                keys.first().map_or("none".into(), Pubkey::to_string)
+            ),
+            format!("Blockhash: {}", message.recent_blockhash()),
+        ];
+        for ix in message.instructions() {
+            let program = keys.get(usize::from(ix.program_id_index));
+            let data = &ix.data;
+            let account = |position: usize| {
+                ix.accounts
+                    .get(position)
+                    .map_or("?".into(), |index| key(*index))
+            };
+            let line = match program {
+                Some(program) if program == program_id => {
+                    let name = data
+                        .first()
+                        .and_then(|discriminator| instruction::name(*discriminator));
+                    match (name, data.first()) {
+                        (Some(name), Some(&instruction::TRANSFER_SOL)) => match data.get(2..10) {
+                            Some(amount) => format!(
+                                "{name}: {} lamports from {} to {}",
+                                u64::from_le_bytes(amount.try_into().unwrap()),
+                                account(0),
+                                account(1)
+                            ),
+                            None => format!("{name}: malformed"),
+                        },
+                        (Some(name), _) => name.to_string(),
+                        (None, _) => format!("{program_id}: unknown instruction"),
+                    }
+                }
+                Some(program) if *program == solana_sdk_ids::system_program::ID => {
+                    let kind = data
+                        .get(..4)
+                        .map(|kind| u32::from_le_bytes(kind.try_into().unwrap()));
+                    match (kind, data.get(4..12)) {
+                        (Some(SYSTEM_TRANSFER), Some(amount)) => format!(
+                            "System transfer: {} lamports from {} to {}",
+                            u64::from_le_bytes(amount.try_into().unwrap()),
+                            account(0),
+                            account(1)
+                        ),
+                        (Some(ADVANCE_NONCE_ACCOUNT), _) => format!(
+                            "Advance nonce account {}, authority {}",
+                            account(0),
+                            account(2)
+                        ),
+                        _ => "System program instruction".to_string(),
+                    }
+                }
+                Some(program) if *program == solana_sdk_ids::compute_budget::ID => {
+                    match (data.first(), data.get(1..)) {
+                        (Some(&SET_COMPUTE_UNIT_LIMIT), Some(limit)) if limit.len() >= 4 => {
+                            format!(
+                                "Compute unit limit: {}",
+                                u32::from_le_bytes(limit[..4].try_into().unwrap())
+                            )
+                        }
+                        (Some(&SET_COMPUTE_UNIT_PRICE), Some(price)) if price.len() >= 8 => {
+                            format!(
+                                "Compute unit price: {} micro-lamports",
+                                u64::from_le_bytes(price[..8].try_into().unwrap())
+                            )
+                        }
+                        _ => "Compute budget instruction".to_string(),
+                    }
+                }
+                _ => format!(
+                    "{}: {} accounts, {} bytes of data",
+                    key(ix.program_id_index),
+                    ix.accounts.len(),
+                    data.len()
+                ),
+            };
+            lines.push(line);
+        }
+        lines
+    }
+}
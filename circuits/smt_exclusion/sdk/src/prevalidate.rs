@@ -0,0 +1,86 @@
+//! Checking a transfer's proof before paying to send it
+//!
+//! The program rejects a proof against a root it no longer holds, for a
+//! pubkey other than the sender's, or that the verifier rejects, and the
+//! sender pays for the failed transaction each time. These checks fail the
+//! same way, with the same `ExclusionError`s, before anything is sent.
+
+use anyhow::Context;
+use exclusion_common::{
+    pubkey_to_index,
+    state::{hash_scheme, verifier_flags},
+    ExclusionError, Proof, PublicWitness,
+};
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+
+use crate::{
+    groth16::VerifyingKey,
+    state::{get_state_account, ExclusionState},
+};
+
+/// A witness field as big-endian bytes, the way `state` stores roots and
+/// hashes
+fn field_be(field: [u8; 32], state: &ExclusionState) -> [u8; 32] {
+    let mut be = field;
+    if state.state.verifier_flags & verifier_flags::WITNESS_LITTLE_ENDIAN != 0 {
+        be.reverse();
+    }
+    be
+}
+
+/// `sender`'s pubkey hash under `state`'s hash scheme
+fn pubkey_hash(sender: &Pubkey, state: &ExclusionState) -> Result<[u8; 32], ExclusionError> {
+    match state.state.hash_scheme {
+        hash_scheme::POSEIDON => Ok(pubkey_to_index(&sender.to_bytes())),
+        hash_scheme::POSEIDON2 => Ok(exclusion_program_example::poseidon2::pubkey_hash_be(
+            &sender.to_bytes(),
+        )),
+        _ => Err(ExclusionError::UnsupportedHashScheme),
+    }
+}
+
+/// Check that a transfer by `sender` with `proof` and `witness` would pass
+/// the program's proof checks against `state`
+///
+/// The witness must be against `state`'s root (`SmtRootMismatch`) and for
+/// `sender`'s pubkey hash (`PubkeyHashMismatch`). With `vk`, the key of the
+/// state's sunspot verifier, the proof must also verify
+/// (`ZkVerificationFailed`); pass `None` for verifiers of other backends.
+pub fn check_transfer(
+    state: &ExclusionState,
+    sender: &Pubkey,
+    proof: &Proof,
+    witness: &PublicWitness,
+    vk: Option<&VerifyingKey>,
+) -> Result<(), ExclusionError> {
+    if field_be(witness.smt_root(), state) != state.smt_root {
+        return Err(ExclusionError::SmtRootMismatch);
+    }
+    if field_be(witness.pubkey_hash(), state) != pubkey_hash(sender, state)? {
+        return Err(ExclusionError::PubkeyHashMismatch);
+    }
+    match vk {
+        Some(vk) => vk.verify(proof, witness),
+        None => Ok(()),
+    }
+}
+
+/// Fetch the state account at `state` and `check_transfer` against it
+pub async fn prevalidate_transfer(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    state: &Pubkey,
+    sender: &Pubkey,
+    proof: &Proof,
+    witness: &PublicWitness,
+    vk: Option<&VerifyingKey>,
+) -> anyhow::Result<()> {
+    let state = get_state_account(rpc, program_id, state).await?;
+    check_transfer(&state, sender, proof, witness, vk).with_context(|| {
+        format!(
+            "the proof would fail against {} at slot {}",
+            state.address, state.slot
+        )
+    })
+}
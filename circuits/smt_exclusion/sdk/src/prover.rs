@@ -0,0 +1,198 @@
+//! Exclusion proofs from the Noir toolchain
+//!
+//! Proves the way `just prove-smt` and the server do: write `Prover.toml`,
+//! `nargo execute` to solve the witness, then `sunspot prove` with the
+//! circuit's compiled constraint system and proving key. The proof and public
+//! witness sunspot writes are read back into `Proof` and `PublicWitness`, and
+//! the witness is checked against the inputs, so a stale build of the circuit
+//! fails here rather than on chain.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context};
+use exclusion_common::{Proof, PublicWitness};
+use smt::{store::Store, ExclusionProofInputs, SparseMerkleTree};
+use solana_pubkey::Pubkey;
+use tokio::{process::Command, sync::Mutex};
+
+use crate::{artifacts::ArtifactPins, witness::ExclusionWitness};
+
+const CIRCUIT_NAME: &str = "smt_exclusion";
+
+/// A proof, the public witness it was made with, and the inputs that
+/// witness holds
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneratedProof {
+    pub proof: Proof,
+    pub witness: PublicWitness,
+    pub inputs: ExclusionWitness,
+}
+
+/// Proves in one circuit directory, one proof at a time (they share
+/// `Prover.toml` and the solved witness)
+pub struct Prover {
+    circuit_dir: PathBuf,
+    nargo: PathBuf,
+    sunspot: PathBuf,
+    pins: Option<ArtifactPins>,
+    lock: Mutex<()>,
+}
+
+impl Prover {
+    /// Prove in `circuit_dir`, which holds `Nargo.toml` and the artifacts of
+    /// `just build-verifier-smt` under `target/`, with `nargo` and `sunspot`
+    /// from `PATH`
+    pub fn new(circuit_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            circuit_dir: circuit_dir.into(),
+            nargo: "nargo".into(),
+            sunspot: "sunspot".into(),
+            pins: None,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Run `nargo` and `sunspot` from these paths instead
+    pub fn with_binaries(mut self, nargo: impl Into<PathBuf>, sunspot: impl Into<PathBuf>) -> Self {
+        self.nargo = nargo.into();
+        self.sunspot = sunspot.into();
+        self
+    }
+
+    /// Refuse to prove unless the circuit and verifying key under `target/`
+    /// are the pinned ones
+    pub fn with_pins(mut self, pins: ArtifactPins) -> Self {
+        self.pins = Some(pins);
+        self
+    }
+
+    fn target(&self, extension: &str) -> PathBuf {
+        self.circuit_dir
+            .join("target")
+            .join(format!("{CIRCUIT_NAME}.{extension}"))
+    }
+
+    async fn run(&self, program: &Path, args: &[&Path]) -> anyhow::Result<()> {
+        let output = Command::new(program)
+            .args(args)
+            .current_dir(&self.circuit_dir)
+            .output()
+            .await
+            .with_context(|| format!("running {}", program.display()))?;
+        if !output.status.success() {
+            bail!(
+                "{} failed: {}",
+                program.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+
+    /// Write `Prover.toml` and solve the witness into `target/<name>.gz`
+    async fn execute(&self, inputs: &ExclusionProofInputs, recent_slot: u64) -> anyhow::Result<()> {
+        if let Some(pins) = &self.pins {
+            pins.check_circuit(&self.circuit_dir)?;
+        }
+        tokio::fs::write(
+            self.circuit_dir.join("Prover.toml"),
+            inputs.to_prover_toml(recent_slot),
+        )
+        .await
+        .context("writing Prover.toml")?;
+        self.run(&self.nargo, &[Path::new("execute")]).await
+    }
+
+    /// Prove `inputs` bound to `recent_slot`
+    pub async fn prove(
+        &self,
+        inputs: &ExclusionProofInputs,
+        recent_slot: u64,
+    ) -> anyhow::Result<GeneratedProof> {
+        let _guard = self.lock.lock().await;
+        self.execute(inputs, recent_slot).await?;
+        self.run(
+            &self.sunspot,
+            &[
+                Path::new("prove"),
+                &self.target("json"),
+                &self.target("gz"),
+                &self.target("ccs"),
+                &self.target("pk"),
+            ],
+        )
+        .await?;
+
+        let proof = tokio::fs::read(self.target("proof"))
+            .await
+            .context("reading the proof")?;
+        let witness = tokio::fs::read(self.target("pw"))
+            .await
+            .context("reading the public witness")?;
+        let proof = Proof::try_from(&proof[..]).map_err(|_| {
+            anyhow::anyhow!(
+                "sunspot wrote a {}-byte proof (is sunspot up to date?)",
+                proof.len()
+            )
+        })?;
+        let witness = PublicWitness::try_from(&witness[..]).map_err(|_| {
+            anyhow::anyhow!(
+                "sunspot wrote a {}-byte public witness (is sunspot up to date?)",
+                witness.len()
+            )
+        })?;
+        let parsed = ExclusionWitness::from_gnark_bytes(witness.as_bytes())
+            .context("parsing the public witness")?;
+        let expected = ExclusionWitness {
+            smt_root: inputs.smt_root,
+            pubkey_hash: inputs.pubkey_hash,
+            recent_slot,
+        };
+        ensure!(
+            parsed == expected,
+            "the public witness is not for these inputs (was the circuit recompiled?)"
+        );
+        Ok(GeneratedProof {
+            proof,
+            witness,
+            inputs: parsed,
+        })
+    }
+
+    /// Prove `inputs` bound to `recent_slot` with Barretenberg in this
+    /// process, rather than with `nargo` and `sunspot`
+    ///
+    /// Solving and proving run on a blocking thread, and touch neither the
+    /// circuit directory nor its lock.
+    #[cfg(feature = "bb-sys")]
+    pub async fn prove_with_bb(
+        &self,
+        circuit: std::sync::Arc<crate::bb::Circuit>,
+        inputs: &ExclusionProofInputs,
+        recent_slot: u64,
+    ) -> anyhow::Result<crate::bb::BbProof> {
+        let inputs = crate::acir::exclusion_inputs(inputs, recent_slot);
+        tokio::task::spawn_blocking(move || {
+            let witness = circuit.solve(&inputs).context("solving the witness")?;
+            circuit.prove(&witness)
+        })
+        .await?
+    }
+}
+
+/// Prove `pubkey` is not in `tree`, bound to `recent_slot`; fails if it is
+pub async fn generate_exclusion_proof<S>(
+    prover: &Prover,
+    tree: &SparseMerkleTree<S>,
+    pubkey: &Pubkey,
+    recent_slot: u64,
+) -> anyhow::Result<GeneratedProof>
+where
+    S: Store,
+    S::Error: std::error::Error + Sync + 'static,
+{
+    let inputs = tree
+        .generate_exclusion_proof(&pubkey.to_bytes())?
+        .with_context(|| format!("{pubkey} is blacklisted"))?;
+    prover.prove(&inputs, recent_slot).await
+}
@@ -0,0 +1,304 @@
+//! Account notifications over the RPC node's websocket
+//!
+//! `accountSubscribe` pushes an account's data each time it changes, so a
+//! client learns of a new root without polling. This speaks as much of the
+//! websocket protocol (RFC 6455) as that needs: reqwest sends the HTTP
+//! upgrade, and text frames are written masked and read back here, answering
+//! pings. The server's `Sec-WebSocket-Accept` isn't checked; a `wss://`
+//! connection is authenticated by TLS.
+//!
+//! `subscribe_state` and `subscribe_attestation` decode the updates into the
+//! client's account types, for UIs and services reacting to root changes.
+
+use anyhow::{bail, ensure, Context};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::stream::{self, Stream};
+use rand::RngCore;
+use reqwest::{header, StatusCode, Url};
+use serde_json::{json, Value};
+use solana_commitment_config::CommitmentConfig;
+use solana_pubkey::Pubkey;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{
+    accounts::{decode_attestation, Attestation},
+    instructions::{derive_attestation_pda, derive_state_pda},
+    state::ExclusionState,
+};
+
+/// Largest message read, well above a state account's notification
+const MAX_MESSAGE_SIZE: usize = 1 << 20;
+
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xa;
+
+/// The websocket URL for `rpc_url`, as the Solana CLI derives it: `ws` for
+/// `http`, `wss` for `https`, and the next port up when one is given
+/// (`http://localhost:8899` → `ws://localhost:8900`)
+pub fn websocket_url(rpc_url: &str) -> anyhow::Result<String> {
+    let mut url = Url::parse(rpc_url).with_context(|| format!("parsing {rpc_url}"))?;
+    let scheme = match url.scheme() {
+        "http" | "ws" => "ws",
+        "https" | "wss" => "wss",
+        scheme => bail!("{rpc_url}: unsupported scheme {scheme}"),
+    };
+    if let Some(port) = url.port() {
+        if matches!(url.scheme(), "http" | "https") {
+            url.set_port(Some(port + 1))
+                .map_err(|_| anyhow::anyhow!("{rpc_url} has no host"))?;
+        }
+    }
+    url.set_scheme(scheme)
+        .map_err(|_| anyhow::anyhow!("{rpc_url} can't be a websocket URL"))?;
+    Ok(url.into())
+}
+
+/// An account's data as of `slot`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountUpdate {
+    pub slot: u64,
+    /// Empty once the account is closed
+    pub data: Vec<u8>,
+}
+
+/// An open `accountSubscribe` subscription
+pub struct AccountSubscription {
+    io: reqwest::Upgraded,
+    subscription: u64,
+}
+
+impl AccountSubscription {
+    /// Subscribe to `account` at `commitment` on the websocket at `url`
+    /// (`ws://` or `wss://`; see `websocket_url`)
+    pub async fn subscribe(
+        url: &str,
+        account: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> anyhow::Result<Self> {
+        let mut http = Url::parse(url).with_context(|| format!("parsing {url}"))?;
+        let scheme = match http.scheme() {
+            "ws" => "http",
+            "wss" => "https",
+            scheme => bail!("{url}: unsupported scheme {scheme}"),
+        };
+        http.set_scheme(scheme)
+            .map_err(|_| anyhow::anyhow!("{url} can't be upgraded"))?;
+
+        let mut key = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut key);
+        let response = reqwest::Client::new()
+            .get(http)
+            .header(header::CONNECTION, "Upgrade")
+            .header(header::UPGRADE, "websocket")
+            .header(header::SEC_WEBSOCKET_VERSION, "13")
+            .header(header::SEC_WEBSOCKET_KEY, STANDARD.encode(key))
+            .send()
+            .await
+            .with_context(|| format!("connecting to {url}"))?;
+        ensure!(
+            response.status() == StatusCode::SWITCHING_PROTOCOLS,
+            "{url} answered the websocket upgrade with {}",
+            response.status()
+        );
+        let io = response
+            .upgrade()
+            .await
+            .with_context(|| format!("upgrading the connection to {url}"))?;
+
+        let mut subscription = Self {
+            io,
+            subscription: 0,
+        };
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "accountSubscribe",
+            "params": [
+                account.to_string(),
+                { "encoding": "base64", "commitment": commitment.commitment },
+            ],
+        });
+        subscription
+            .send(TEXT, request.to_string().as_bytes())
+            .await?;
+        loop {
+            let message = subscription.read_json().await?;
+            if message["id"] != 1 {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                bail!("accountSubscribe failed: {error}");
+            }
+            subscription.subscription = message["result"]
+                .as_u64()
+                .context("accountSubscribe returned no subscription ID")?;
+            return Ok(subscription);
+        }
+    }
+
+    /// The account's next change
+    pub async fn next(&mut self) -> anyhow::Result<AccountUpdate> {
+        loop {
+            let message = self.read_json().await?;
+            let params = &message["params"];
+            if message["method"] != "accountNotification"
+                || params["subscription"] != self.subscription
+            {
+                continue;
+            }
+            let result = &params["result"];
+            let slot = result["context"]["slot"]
+                .as_u64()
+                .context("an account notification without a slot")?;
+            let data = match result["value"]["data"][0].as_str() {
+                Some(data) => STANDARD
+                    .decode(data)
+                    .context("decoding an account notification")?,
+                None => Vec::new(),
+            };
+            return Ok(AccountUpdate { slot, data });
+        }
+    }
+
+    /// Write one frame, masked as a client must
+    async fn send(&mut self, opcode: u8, payload: &[u8]) -> anyhow::Result<()> {
+        let mut frame = vec![0x80 | opcode];
+        match payload.len() {
+            len @ 0..=125 => frame.push(0x80 | len as u8),
+            len @ 126..=0xffff => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        let mut mask = [0u8; 4];
+        rand::thread_rng().fill_bytes(&mut mask);
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().zip(mask.iter().cycle()).map(|(b, m)| b ^ m));
+        self.io
+            .write_all(&frame)
+            .await
+            .context("writing to the websocket")?;
+        self.io.flush().await.context("writing to the websocket")
+    }
+
+    /// Read the next data message, answering pings on the way
+    async fn read_message(&mut self) -> anyhow::Result<Vec<u8>> {
+        let mut message = Vec::new();
+        loop {
+            let mut header = [0u8; 2];
+            self.io
+                .read_exact(&mut header)
+                .await
+                .context("reading from the websocket")?;
+            let fin = header[0] & 0x80 != 0;
+            let opcode = header[0] & 0x0f;
+            let len = match header[1] & 0x7f {
+                126 => u64::from(self.io.read_u16().await?),
+                127 => self.io.read_u64().await?,
+                len => u64::from(len),
+            };
+            let mask = if header[1] & 0x80 != 0 {
+                let mut mask = [0u8; 4];
+                self.io.read_exact(&mut mask).await?;
+                Some(mask)
+            } else {
+                None
+            };
+            ensure!(
+                (message.len() as u64).saturating_add(len) <= MAX_MESSAGE_SIZE as u64,
+                "a websocket message over {MAX_MESSAGE_SIZE} bytes"
+            );
+            let mut payload = vec![0u8; len as usize];
+            self.io
+                .read_exact(&mut payload)
+                .await
+                .context("reading from the websocket")?;
+            if let Some(mask) = mask {
+                for (byte, m) in payload.iter_mut().zip(mask.iter().cycle()) {
+                    *byte ^= m;
+                }
+            }
+
+            match opcode {
+                PING => self.send(PONG, &payload).await?,
+                PONG => {}
+                CLOSE => bail!("the server closed the websocket"),
+                TEXT | BINARY | CONTINUATION => {
+                    message.extend_from_slice(&payload);
+                    if fin {
+                        return Ok(message);
+                    }
+                }
+                opcode => bail!("unknown websocket opcode {opcode}"),
+            }
+        }
+    }
+
+    async fn read_json(&mut self) -> anyhow::Result<Value> {
+        let message = self.read_message().await?;
+        serde_json::from_slice(&message).context("a websocket message that isn't JSON")
+    }
+}
+
+/// `admin`'s state account each time it changes, from the websocket at
+/// `url` (see `websocket_url`)
+///
+/// Changes made before the subscription is open aren't streamed; fetch the
+/// account (`state::get_exclusion_state`) after subscribing to start from its
+/// current root. The stream ends after its first error, the connection
+/// dropping or the account being closed among them; subscribe again to
+/// carry on.
+pub async fn subscribe_state(
+    url: &str,
+    program_id: &Pubkey,
+    admin: &Pubkey,
+) -> anyhow::Result<impl Stream<Item = anyhow::Result<ExclusionState>>> {
+    let (address, _) = derive_state_pda(program_id, admin);
+    let subscription =
+        AccountSubscription::subscribe(url, &address, CommitmentConfig::confirmed()).await?;
+    Ok(decoded(subscription, address, |address, update| {
+        ExclusionState::from_account_data(address, update.slot, &update.data)
+    }))
+}
+
+/// `subject`'s attestation for `state` each time it's written, from the
+/// websocket at `url`, ending as `subscribe_state`'s stream does
+pub async fn subscribe_attestation(
+    url: &str,
+    program_id: &Pubkey,
+    state: &Pubkey,
+    subject: &Pubkey,
+) -> anyhow::Result<impl Stream<Item = anyhow::Result<Attestation>>> {
+    let (address, _) = derive_attestation_pda(program_id, state, subject);
+    let subscription =
+        AccountSubscription::subscribe(url, &address, CommitmentConfig::confirmed()).await?;
+    Ok(decoded(subscription, address, |address, update| {
+        decode_attestation(&update.data).with_context(|| format!("decoding attestation {address}"))
+    }))
+}
+
+/// `subscription`'s updates to `address`, decoded, until the first error
+fn decoded<T>(
+    subscription: AccountSubscription,
+    address: Pubkey,
+    decode: fn(Pubkey, &AccountUpdate) -> anyhow::Result<T>,
+) -> impl Stream<Item = anyhow::Result<T>> {
+    stream::unfold(Some(subscription), move |subscription| async move {
+        let mut subscription = subscription?;
+        let item = match subscription.next().await {
+            Ok(update) if update.data.is_empty() => Err(anyhow::anyhow!("{address} was closed")),
+            Ok(update) => decode(address, &update),
+            Err(error) => Err(error),
+        };
+        let next = item.is_ok().then_some(subscription);
+        Some((item, next))
+    })
+}
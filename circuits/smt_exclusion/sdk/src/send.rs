@@ -0,0 +1,405 @@
+//! Sending transactions, with failures decoded into the program's errors
+//!
+//! A failed transfer comes back from RPC as `Custom(2)` on some instruction,
+//! which could be the exclusion program's `SmtRootMismatch` or a code from
+//! the ZK verifier it invoked. The logs tell the two apart: the innermost
+//! program that failed logs `Program <id> failed: <reason>` first, and each
+//! caller it failed through logs the same after it.
+//!
+//! A transaction that was sent can still be dropped: the leader never saw it,
+//! or its blockhash expired first. `send_and_confirm` rebuilds, re-simulates
+//! and resends it, with a fresh blockhash, until one lands. It only signs a
+//! new transaction once the last one has expired unseen, so no two can land:
+//! the program keeps no nullifiers that would refuse the second, and a second
+//! `ATTEST` would just overwrite the first's slot.
+
+use std::{future::Future, time::Duration};
+
+use anyhow::{bail, Context};
+use exclusion_common::ExclusionError;
+use solana_commitment_config::CommitmentConfig;
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::{
+    client_error::{Error as ClientError, ErrorKind},
+    config::RpcSendTransactionConfig,
+    config::RpcSimulateTransactionConfig,
+    request::{RpcError, RpcResponseErrorData},
+    response::RpcSimulateTransactionResult,
+};
+use solana_signature::Signature;
+use solana_transaction::{versioned::VersionedTransaction, InstructionError, TransactionError};
+
+/// Why a transaction didn't land
+#[derive(Debug)]
+pub enum SendError {
+    /// The exclusion program failed instruction `instruction` with `error`
+    Program {
+        instruction: u8,
+        error: ExclusionError,
+        logs: Vec<String>,
+    },
+    /// A program the exclusion program invoked, normally the ZK verifier
+    /// rejecting the proof, failed instruction `instruction`
+    Cpi {
+        instruction: u8,
+        program: Pubkey,
+        /// What the runtime logged the program failed with
+        reason: String,
+        /// The program's last `Program log:` line before it failed
+        log: Option<String>,
+        logs: Vec<String>,
+    },
+    /// The transaction failed other than in the exclusion program: another
+    /// program's instruction, fees, an expired blockhash
+    Transaction {
+        error: TransactionError,
+        logs: Vec<String>,
+    },
+    /// The RPC request itself failed
+    Rpc(Box<ClientError>),
+}
+
+impl SendError {
+    /// The program logs of the failed simulation or preflight, if any
+    pub fn logs(&self) -> &[String] {
+        match self {
+            Self::Program { logs, .. }
+            | Self::Cpi { logs, .. }
+            | Self::Transaction { logs, .. } => logs,
+            Self::Rpc(_) => &[],
+        }
+    }
+}
+
+impl std::fmt::Display for SendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Program {
+                instruction, error, ..
+            } => write!(f, "instruction {instruction}: {error}"),
+            Self::Cpi {
+                instruction,
+                program,
+                reason,
+                log,
+                ..
+            } => {
+                write!(
+                    f,
+                    "instruction {instruction}: {program}, invoked by the exclusion program, failed: {reason}"
+                )?;
+                match log {
+                    Some(log) => write!(f, " ({log})"),
+                    None => Ok(()),
+                }
+            }
+            Self::Transaction { error, .. } => write!(f, "transaction failed: {error}"),
+            Self::Rpc(error) => write!(f, "RPC request failed: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SendError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Program { error, .. } => Some(error),
+            Self::Transaction { error, .. } => Some(error),
+            Self::Rpc(error) => Some(error.as_ref()),
+            Self::Cpi { .. } => None,
+        }
+    }
+}
+
+/// The innermost program that failed, what it failed with, and its last
+/// `Program log:` line before failing
+fn first_failure(logs: &[String]) -> Option<(Pubkey, String, Option<String>)> {
+    let index = logs.iter().position(|line| {
+        line.strip_prefix("Program ")
+            .is_some_and(|rest| rest.contains(" failed: "))
+    })?;
+    let (program, reason) = logs[index]
+        .strip_prefix("Program ")?
+        .split_once(" failed: ")?;
+    let log = index
+        .checked_sub(1)
+        .and_then(|previous| logs[previous].strip_prefix("Program log: "))
+        .map(str::to_string);
+    Some((program.parse().ok()?, reason.to_string(), log))
+}
+
+/// What `error`, with the simulation or preflight's `logs`, means for
+/// `transaction`, which calls the exclusion program at `program_id`
+///
+/// Failures in instructions that call the exclusion program are `Program`
+/// when the program failed with one of its codes, or `Cpi` when a program it
+/// invoked failed. Everything else is `Transaction`.
+pub fn decode_failure(
+    program_id: &Pubkey,
+    transaction: &VersionedTransaction,
+    error: TransactionError,
+    logs: Vec<String>,
+) -> SendError {
+    let TransactionError::InstructionError(instruction, ref instruction_error) = error else {
+        return SendError::Transaction { error, logs };
+    };
+    let message = &transaction.message;
+    let calls_program = message
+        .instructions()
+        .get(usize::from(instruction))
+        .and_then(|ix| {
+            message
+                .static_account_keys()
+                .get(usize::from(ix.program_id_index))
+        })
+        == Some(program_id);
+    if !calls_program {
+        return SendError::Transaction { error, logs };
+    }
+    if let Some((program, reason, log)) = first_failure(&logs) {
+        if program != *program_id {
+            return SendError::Cpi {
+                instruction,
+                program,
+                reason,
+                log,
+                logs,
+            };
+        }
+    }
+    match instruction_error {
+        InstructionError::Custom(code) => match ExclusionError::from_code(*code) {
+            Some(error) => SendError::Program {
+                instruction,
+                error,
+                logs,
+            },
+            None => SendError::Transaction { error, logs },
+        },
+        _ => SendError::Transaction { error, logs },
+    }
+}
+
+/// Simulate `transaction`, failing as `decode_failure` decodes it
+async fn simulate_checked(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    transaction: &VersionedTransaction,
+) -> Result<(), SendError> {
+    let result = rpc
+        .simulate_transaction_with_config(
+            transaction,
+            RpcSimulateTransactionConfig {
+                sig_verify: true,
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .await
+        .map_err(|e| SendError::Rpc(Box::new(e)))?
+        .value;
+    if let Some(error) = result.err {
+        return Err(decode_failure(
+            program_id,
+            transaction,
+            error.into(),
+            result.logs.unwrap_or_default(),
+        ));
+    }
+    Ok(())
+}
+
+/// Simulate `transaction`, then send it and wait for it to be confirmed
+///
+/// A failing simulation isn't sent, and comes back decoded against the
+/// exclusion program at `program_id` (see `decode_failure`), as does a
+/// failing preflight or a transaction that fails on chain.
+pub async fn send_checked(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    transaction: &VersionedTransaction,
+) -> Result<Signature, SendError> {
+    simulate_checked(rpc, program_id, transaction).await?;
+    rpc.send_and_confirm_transaction(transaction)
+        .await
+        .map_err(|e| match e.kind() {
+            ErrorKind::RpcError(RpcError::RpcResponseError {
+                data:
+                    RpcResponseErrorData::SendTransactionPreflightFailure(
+                        RpcSimulateTransactionResult {
+                            err: Some(error),
+                            logs,
+                            ..
+                        },
+                    ),
+                ..
+            }) => decode_failure(
+                program_id,
+                transaction,
+                error.clone().into(),
+                logs.clone().unwrap_or_default(),
+            ),
+            ErrorKind::TransactionError(error) => {
+                decode_failure(program_id, transaction, error.clone(), Vec::new())
+            }
+            _ => SendError::Rpc(Box::new(e)),
+        })
+}
+
+/// How `send_and_confirm` retries
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SendOptions {
+    /// Transactions built in all, the first included
+    pub attempts: u32,
+    /// The wait before the second, doubling before each one after it
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// How often a sent transaction's status is checked, and the
+    /// transaction resent while it can still land
+    pub poll_interval: Duration,
+}
+
+impl Default for SendOptions {
+    fn default() -> Self {
+        Self {
+            attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+            poll_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+impl SendOptions {
+    /// The wait before attempt `attempt + 1`, after `attempt` expired
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+}
+
+/// Send the transaction `build` builds until one is confirmed, and return
+/// its signature
+///
+/// `build` signs a transaction with a fresh blockhash (or the nonce's
+/// current one) each time it is called, as `build_sized_transaction` and
+/// `build_nonced_transaction` do. Each is simulated first: a failure other
+/// than an unknown blockhash comes back at once as a `SendError` (see
+/// `send_checked`), since another try would fail the same way. A transaction
+/// that lands but fails does too. One that is still unseen when its
+/// blockhash expires, or its nonce is advanced, can no longer land, and only
+/// then is the next built, after a backoff.
+pub async fn send_and_confirm<F, Fut>(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    mut build: F,
+    options: &SendOptions,
+) -> anyhow::Result<Signature>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<VersionedTransaction>>,
+{
+    for attempt in 1..=options.attempts {
+        if attempt > 1 {
+            tokio::time::sleep(options.backoff(attempt - 1)).await;
+        }
+        let transaction = build().await.context("building the transaction")?;
+        match simulate_checked(rpc, program_id, &transaction).await {
+            Ok(()) => {}
+            Err(SendError::Transaction {
+                error: TransactionError::BlockhashNotFound,
+                ..
+            }) => continue,
+            Err(error) => return Err(error.into()),
+        }
+        let config = RpcSendTransactionConfig {
+            skip_preflight: true,
+            ..RpcSendTransactionConfig::default()
+        };
+        // A send that fails may still have reached the leader; its status
+        // says whether it did
+        let _ = rpc.send_transaction_with_config(&transaction, config).await;
+        loop {
+            tokio::time::sleep(options.poll_interval).await;
+            if let Some(signature) = landed(rpc, program_id, &transaction).await? {
+                return Ok(signature);
+            }
+            if expired(rpc, &transaction).await? {
+                // It may have landed in the slots before it expired
+                if let Some(signature) = landed(rpc, program_id, &transaction).await? {
+                    return Ok(signature);
+                }
+                break;
+            }
+            let _ = rpc.send_transaction_with_config(&transaction, config).await;
+        }
+    }
+    bail!(
+        "the transaction expired unconfirmed {} times",
+        options.attempts
+    )
+}
+
+/// `transaction`'s signature once it is confirmed; fails, decoded, if it
+/// landed and failed
+async fn landed(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    transaction: &VersionedTransaction,
+) -> anyhow::Result<Option<Signature>> {
+    let signature = transaction.signatures[0];
+    let status = rpc
+        .get_signature_statuses(&[signature])
+        .await
+        .context("fetching the transaction's status")?
+        .value
+        .into_iter()
+        .next()
+        .flatten();
+    match status {
+        Some(status) if status.satisfies_commitment(CommitmentConfig::confirmed()) => {
+            match status.err {
+                Some(error) => {
+                    Err(decode_failure(program_id, transaction, error, Vec::new()).into())
+                }
+                None => Ok(Some(signature)),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Whether `transaction` can no longer land: its blockhash expired, or, for
+/// a durable nonce transaction, its nonce was advanced
+async fn expired(rpc: &RpcClient, transaction: &VersionedTransaction) -> anyhow::Result<bool> {
+    let blockhash = *transaction.message.recent_blockhash();
+    match nonce_address(transaction) {
+        Some(address) => {
+            let nonce = crate::nonce::fetch_nonce_account(rpc, &address).await?;
+            Ok(nonce.blockhash != blockhash)
+        }
+        None => Ok(!rpc
+            .is_blockhash_valid(&blockhash, CommitmentConfig::processed())
+            .await
+            .context("checking the transaction's blockhash")?),
+    }
+}
+
+/// The nonce account a durable nonce transaction's first instruction
+/// advances
+fn nonce_address(transaction: &VersionedTransaction) -> Option<Pubkey> {
+    /// System program `AdvanceNonceAccount`
+    const ADVANCE_NONCE_ACCOUNT: [u8; 4] = 4u32.to_le_bytes();
+
+    let message = &transaction.message;
+    let keys = message.static_account_keys();
+    let first = message.instructions().first()?;
+    let program = keys.get(usize::from(first.program_id_index))?;
+    if *program != solana_sdk_ids::system_program::ID
+        || first.data.get(..4) != Some(&ADVANCE_NONCE_ACCOUNT[..])
+    {
+        return None;
+    }
+    keys.get(usize::from(*first.accounts.first()?)).copied()
+}
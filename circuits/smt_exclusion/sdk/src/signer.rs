@@ -0,0 +1,370 @@
+//! Signers that may live on another machine
+//!
+//! `solana_signer::Signer` signs synchronously, which suits a keypair or a
+//! Ledger on USB. A service, such as a relayer paying fees for relayed
+//! transfers, shouldn't hold a raw private key at all. It asks a signing
+//! service or a KMS over HTTP instead, which takes time and can fail for a
+//! while. `AsyncSigner` is what the client signs through: every `Signer` is
+//! one, and `RemoteSigner` asks a remote service. It retries transient
+//! failures, checks each signature against the key it expects, and reports
+//! every attempt to an `AuditLog`.
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{ensure, Context};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use smt::inputs::field_hex;
+use solana_message::VersionedMessage;
+use solana_pubkey::Pubkey;
+use solana_signature::Signature;
+use solana_signer::Signer;
+use solana_transaction::versioned::VersionedTransaction;
+
+/// Google Cloud KMS's API
+pub const GCP_KMS_URL: &str = "https://cloudkms.googleapis.com";
+
+/// Signs messages, perhaps by asking another machine
+#[async_trait]
+pub trait AsyncSigner: Send + Sync {
+    /// The signing key's pubkey
+    fn address(&self) -> Pubkey;
+    async fn sign(&self, message: &[u8]) -> anyhow::Result<Signature>;
+}
+
+#[async_trait]
+impl<T: Signer + Send + Sync + ?Sized> AsyncSigner for T {
+    fn address(&self) -> Pubkey {
+        self.pubkey()
+    }
+
+    async fn sign(&self, message: &[u8]) -> anyhow::Result<Signature> {
+        Ok(self.try_sign_message(message)?)
+    }
+}
+
+/// `message` signed by each of `signers`, which must be exactly the keys it
+/// needs signatures from
+pub async fn sign_transaction(
+    message: VersionedMessage,
+    signers: &[&dyn AsyncSigner],
+) -> anyhow::Result<VersionedTransaction> {
+    let required = usize::from(message.header().num_required_signatures);
+    let keys = &message.static_account_keys()[..required];
+    ensure!(
+        signers.len() == required,
+        "the message needs {required} signatures, not {}",
+        signers.len()
+    );
+    let bytes = message.serialize();
+    let mut signatures = vec![Signature::default(); required];
+    for signer in signers {
+        let pubkey = signer.address();
+        let position = keys
+            .iter()
+            .position(|key| *key == pubkey)
+            .with_context(|| format!("{pubkey} isn't one of the message's signers"))?;
+        signatures[position] = signer.sign(&bytes).await?;
+    }
+    Ok(VersionedTransaction {
+        signatures,
+        message,
+    })
+}
+
+/// Where a `RemoteSigner` asks for signatures
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// POST `{"pubkey", "message"}` (the message base64-encoded) to `url`,
+    /// and read the signature, base58-encoded, from `{"signature"}`
+    Http { url: String },
+    /// Google Cloud KMS `asymmetricSign` with an `EC_SIGN_ED25519` key:
+    /// `key_version` is
+    /// `projects/P/locations/L/keyRings/R/cryptoKeys/K/cryptoKeyVersions/V`
+    GcpKms { url: String, key_version: String },
+}
+
+impl Backend {
+    pub fn gcp_kms(key_version: &str) -> Self {
+        Self::GcpKms {
+            url: GCP_KMS_URL.into(),
+            key_version: key_version.into(),
+        }
+    }
+}
+
+/// How a `RemoteSigner` retries
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Tries in all, the first included
+    pub attempts: u32,
+    /// The wait after the first failure, doubling after each one after it
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// How long one request may take
+    pub timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 4,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(5),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The wait before try `attempt + 1`, after `attempt` failed
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+}
+
+/// One attempt to sign, as an `AuditLog` receives it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningRecord {
+    /// Unix time the attempt ended
+    pub time: u64,
+    pub signer: String,
+    /// SHA-256 of the message: what was signed, without the message
+    pub message_hash: String,
+    /// 1 for the first try
+    pub attempt: u32,
+    pub signature: Option<String>,
+    /// Why the attempt failed
+    pub error: Option<String>,
+}
+
+/// Where every signing attempt is recorded; a signature whose record can't
+/// be written is not used
+pub trait AuditLog: Send + Sync {
+    fn record(&self, record: &SigningRecord) -> anyhow::Result<()>;
+}
+
+impl<F: Fn(&SigningRecord) -> anyhow::Result<()> + Send + Sync> AuditLog for F {
+    fn record(&self, record: &SigningRecord) -> anyhow::Result<()> {
+        self(record)
+    }
+}
+
+/// An `AuditLog` appending one JSON object per line to a file
+pub struct JsonLinesAudit {
+    pub path: PathBuf,
+}
+
+impl AuditLog for JsonLinesAudit {
+    fn record(&self, record: &SigningRecord) -> anyhow::Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .with_context(|| format!("writing {}", self.path.display()))
+    }
+}
+
+/// Why one request failed, and whether another might not
+struct Failure {
+    error: anyhow::Error,
+    transient: bool,
+}
+
+impl Failure {
+    fn fatal(error: anyhow::Error) -> Self {
+        Self {
+            error,
+            transient: false,
+        }
+    }
+}
+
+/// A key held by a signing service or a KMS
+pub struct RemoteSigner {
+    pubkey: Pubkey,
+    backend: Backend,
+    http: reqwest::Client,
+    /// Bearer token; replaced with `set_token` as it expires
+    token: RwLock<Option<String>>,
+    retry: RetryPolicy,
+    audit: Option<Arc<dyn AuditLog>>,
+}
+
+impl RemoteSigner {
+    /// The key `pubkey`, which `backend` signs with; signatures that don't
+    /// verify against it are refused
+    pub fn new(pubkey: Pubkey, backend: Backend) -> Self {
+        Self {
+            pubkey,
+            backend,
+            http: reqwest::Client::new(),
+            token: RwLock::new(None),
+            retry: RetryPolicy::default(),
+            audit: None,
+        }
+    }
+
+    pub fn with_token(self, token: &str) -> Self {
+        self.set_token(token);
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_audit(mut self, audit: Arc<dyn AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Send `token` as the bearer token from now on, as a short-lived KMS
+    /// access token is refreshed
+    pub fn set_token(&self, token: &str) {
+        *self
+            .token
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(token.to_string());
+    }
+
+    /// Ask the backend once
+    async fn request(&self, message: &[u8]) -> Result<Signature, Failure> {
+        let (url, body) = match &self.backend {
+            Backend::Http { url } => (
+                url.clone(),
+                json!({
+                    "pubkey": self.pubkey.to_string(),
+                    "message": STANDARD.encode(message),
+                }),
+            ),
+            Backend::GcpKms { url, key_version } => (
+                format!(
+                    "{}/v1/{key_version}:asymmetricSign",
+                    url.trim_end_matches('/')
+                ),
+                json!({ "data": STANDARD.encode(message) }),
+            ),
+        };
+        let mut request = self.http.post(&url).timeout(self.retry.timeout).json(&body);
+        let token = self
+            .token
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.map_err(|error| Failure {
+            error: anyhow::Error::new(error).context(format!("requesting {url}")),
+            transient: true,
+        })?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(Failure {
+                error: anyhow::anyhow!("{url} returned {status}: {}", text.trim()),
+                transient: status.is_server_error() || status.as_u16() == 429,
+            });
+        }
+        let body: serde_json::Value = response.json().await.map_err(|error| Failure {
+            error: anyhow::Error::new(error).context(format!("reading {url}'s response")),
+            transient: true,
+        })?;
+        let signature = body["signature"]
+            .as_str()
+            .context("the response has no signature")
+            .and_then(|signature| match self.backend {
+                Backend::Http { .. } => signature
+                    .parse::<Signature>()
+                    .context("the signature isn't base58"),
+                Backend::GcpKms { .. } => {
+                    let bytes = STANDARD
+                        .decode(signature)
+                        .context("the signature isn't base64")?;
+                    Signature::try_from(bytes.as_slice())
+                        .map_err(|_| anyhow::anyhow!("a {}-byte signature", bytes.len()))
+                }
+            })
+            .map_err(Failure::fatal)?;
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err(Failure::fatal(anyhow::anyhow!(
+                "{url} signed with a key other than {}",
+                self.pubkey
+            )));
+        }
+        Ok(signature)
+    }
+
+    fn audit(
+        &self,
+        message_hash: &str,
+        attempt: u32,
+        result: &Result<Signature, Failure>,
+    ) -> anyhow::Result<()> {
+        let Some(audit) = &self.audit else {
+            return Ok(());
+        };
+        let (signature, error) = match result {
+            Ok(signature) => (Some(signature.to_string()), None),
+            Err(failure) => (None, Some(format!("{:#}", failure.error))),
+        };
+        audit
+            .record(&SigningRecord {
+                time: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_secs()),
+                signer: self.pubkey.to_string(),
+                message_hash: message_hash.to_string(),
+                attempt,
+                signature,
+                error,
+            })
+            .context("recording the signature in the audit log")
+    }
+}
+
+#[async_trait]
+impl AsyncSigner for RemoteSigner {
+    fn address(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    async fn sign(&self, message: &[u8]) -> anyhow::Result<Signature> {
+        let message_hash = field_hex(&Sha256::digest(message).into());
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let result = self.request(message).await;
+            self.audit(&message_hash, attempt, &result)?;
+            match result {
+                Ok(signature) => return Ok(signature),
+                Err(failure) if failure.transient && attempt < self.retry.attempts => {
+                    tokio::time::sleep(self.retry.backoff(attempt)).await;
+                }
+                Err(failure) if failure.transient => {
+                    return Err(failure
+                        .error
+                        .context(format!("signing failed {attempt} times")));
+                }
+                Err(failure) => return Err(failure.error),
+            }
+        }
+    }
+}
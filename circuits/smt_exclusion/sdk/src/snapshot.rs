@@ -0,0 +1,79 @@
+//! Signed blacklist snapshots, checked against the chain
+//!
+//! The admin signs each published snapshot (see the `smt` crate's `signed`
+//! module). A downstream consumer checks the signature, that the signer is the
+//! state account's admin, and that the snapshot's root is the one on chain, so
+//! the list they hold is the list transfers are proven against.
+
+use anyhow::{ensure, Context};
+use exclusion_program_example::{state_offsets, STATE_DISCRIMINATOR, STATE_SIZE};
+use smt::{
+    inputs::field_hex,
+    signed::{SignedSnapshot, SnapshotStatement},
+};
+use solana_commitment_config::CommitmentConfig;
+use solana_keypair::Keypair;
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_signer::Signer;
+
+/// Sign `snapshot`, as `export_snapshot` writes it, with the admin's keypair
+pub fn sign_snapshot(
+    admin: &Keypair,
+    snapshot: Vec<u8>,
+    unix_time: i64,
+) -> anyhow::Result<SignedSnapshot> {
+    let statement = SnapshotStatement::for_snapshot(&snapshot, unix_time)?;
+    let signature = admin.sign_message(&statement.message());
+    Ok(SignedSnapshot {
+        statement,
+        signer: admin.pubkey().to_bytes(),
+        signature: signature.into(),
+        snapshot,
+    })
+}
+
+/// Check `signed` against a state account's data: a valid signature by the
+/// state's admin, over the root the state holds
+pub fn verify_against_state(signed: &SignedSnapshot, state_data: &[u8]) -> anyhow::Result<()> {
+    signed.verify()?;
+    ensure!(
+        state_data.len() >= STATE_SIZE && state_data[..8] == STATE_DISCRIMINATOR,
+        "not a current exclusion state account"
+    );
+    let admin = &state_data[state_offsets::ADMIN..state_offsets::ADMIN + 32];
+    ensure!(
+        admin == signed.signer,
+        "snapshot signed by {}, but the state's admin is {}",
+        Pubkey::new_from_array(signed.signer),
+        Pubkey::try_from(admin).expect("32 bytes")
+    );
+    let root = &state_data[state_offsets::SMT_ROOT..state_offsets::SMT_ROOT + 32];
+    ensure!(
+        root == signed.statement.root,
+        "snapshot root {} is not the on-chain root {}",
+        field_hex(&signed.statement.root),
+        field_hex(root.try_into().expect("32 bytes"))
+    );
+    Ok(())
+}
+
+/// Fetch the `state` account and check `signed` against it
+pub async fn verify_on_chain(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    state: &Pubkey,
+    signed: &SignedSnapshot,
+) -> anyhow::Result<()> {
+    let account = rpc
+        .get_account_with_commitment(state, CommitmentConfig::confirmed())
+        .await
+        .context("fetching the state account")?
+        .value
+        .with_context(|| format!("state account {state} does not exist"))?;
+    ensure!(
+        account.owner == *program_id,
+        "state account {state} is not owned by {program_id}"
+    );
+    verify_against_state(signed, &account.data)
+}
@@ -0,0 +1,455 @@
+//! Solving a compiled circuit's witness in this process, as `nargo execute`
+//! does
+//!
+//! An artifact's bytecode is an ACIR program, serialized with bincode: enum
+//! tags as u32, lengths as u64 and field elements as 64 hex digits. The
+//! initial witness (`CircuitArtifact::initial_witness`) holds the inputs, and
+//! each opcode in order then fills in the witnesses it leaves unknown, or
+//! checks them if none are.
+//!
+//! The solver covers what Noir emits for circuits like the exclusion circuit:
+//! `AssertZero`, `RANGE`, and calls to the compiler's `directive_invert` and
+//! `directive_to_radix` Brillig functions, whose results the circuit then
+//! constrains. Circuits with other black box functions, memory blocks, calls
+//! between functions or `unconstrained` functions of their own fail to load,
+//! and `nargo execute` still solves those.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, ensure, Context};
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, Field, PrimeField, Zero};
+
+use crate::acir::{AbiErrorType, CircuitArtifact};
+
+/// `AND`, `XOR`, then `RANGE` among ACIR's black box functions
+const RANGE: u32 = 3;
+
+/// `a * b` terms, then linear terms, then a constant, summing to zero
+#[derive(Clone, Debug, PartialEq)]
+struct Expression {
+    mul_terms: Vec<(Fr, u32, u32)>,
+    linear_terms: Vec<(Fr, u32)>,
+    constant: Fr,
+}
+
+/// A Brillig function the compiler generates, run here natively
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Directive {
+    /// `[x]` to `x⁻¹`, or 0 for 0
+    Invert,
+    /// `[x, limbs, radix]` to `x`'s `limbs` digits, least significant first
+    ToRadix,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum FunctionInput {
+    Constant(Fr),
+    Witness(u32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum BrilligInput {
+    Single(Expression),
+    Array(Vec<Expression>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum BrilligOutput {
+    Simple(u32),
+    Array(Vec<u32>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Opcode {
+    AssertZero(Expression),
+    Range {
+        input: FunctionInput,
+        bits: u32,
+    },
+    Brillig {
+        directive: Directive,
+        inputs: Vec<BrilligInput>,
+        outputs: Vec<BrilligOutput>,
+        predicate: Option<Expression>,
+    },
+}
+
+/// A circuit's opcodes, ready to solve
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program {
+    opcodes: Vec<Opcode>,
+    /// The assertion message of each opcode that has one
+    messages: HashMap<usize, String>,
+}
+
+/// Every witness a solved circuit assigns, by index
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Witness(BTreeMap<u32, Fr>);
+
+/// Reads the program's bincode front to back
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        ensure!(self.bytes.len() >= len, "truncated");
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn len(&mut self) -> anyhow::Result<usize> {
+        let len = usize::try_from(self.u64()?)?;
+        // Every element takes at least a byte
+        ensure!(len <= self.bytes.len(), "length {len} past the end");
+        Ok(len)
+    }
+
+    fn vec<T>(
+        &mut self,
+        mut read: impl FnMut(&mut Self) -> anyhow::Result<T>,
+    ) -> anyhow::Result<Vec<T>> {
+        (0..self.len()?).map(|_| read(self)).collect()
+    }
+
+    fn field(&mut self) -> anyhow::Result<Fr> {
+        let len = self.len()?;
+        let hex = std::str::from_utf8(self.take(len)?)?;
+        ensure!(hex.len() <= 64, "field element of {} digits", hex.len());
+        let mut bytes = [0u8; 32];
+        let padded = format!("{hex:0>64}");
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&padded[2 * i..2 * i + 2], 16)?;
+        }
+        Ok(Fr::from_be_bytes_mod_order(&bytes))
+    }
+
+    fn expression(&mut self) -> anyhow::Result<Expression> {
+        Ok(Expression {
+            mul_terms: self.vec(|r| Ok((r.field()?, r.u32()?, r.u32()?)))?,
+            linear_terms: self.vec(|r| Ok((r.field()?, r.u32()?)))?,
+            constant: self.field()?,
+        })
+    }
+
+    fn opcode(&mut self, directives: &[Option<Directive>]) -> anyhow::Result<Opcode> {
+        Ok(match self.u32()? {
+            0 => Opcode::AssertZero(self.expression()?),
+            1 => match self.u32()? {
+                RANGE => {
+                    let input = match self.u32()? {
+                        0 => FunctionInput::Constant(self.field()?),
+                        1 => FunctionInput::Witness(self.u32()?),
+                        tag => bail!("function input {tag}"),
+                    };
+                    Opcode::Range {
+                        input,
+                        bits: self.u32()?,
+                    }
+                }
+                function => bail!("black box function {function} is not supported"),
+            },
+            4 => {
+                let id = self.u32()? as usize;
+                let directive = directives.get(id).copied().flatten().with_context(|| {
+                    format!("Brillig function {id} is not a directive this solver runs")
+                })?;
+                let inputs = self.vec(|r| match r.u32()? {
+                    0 => Ok(BrilligInput::Single(r.expression()?)),
+                    1 => Ok(BrilligInput::Array(r.vec(Self::expression)?)),
+                    _ => bail!("memory inputs are not supported"),
+                })?;
+                let outputs = self.vec(|r| match r.u32()? {
+                    0 => Ok(BrilligOutput::Simple(r.u32()?)),
+                    1 => Ok(BrilligOutput::Array(r.vec(Self::u32)?)),
+                    tag => bail!("Brillig output {tag}"),
+                })?;
+                let predicate = match self.u8()? {
+                    0 => None,
+                    _ => Some(self.expression()?),
+                };
+                Opcode::Brillig {
+                    directive,
+                    inputs,
+                    outputs,
+                    predicate,
+                }
+            }
+            2 | 3 => bail!("memory opcodes are not supported"),
+            5 => bail!("calls between functions are not supported"),
+            tag => bail!("opcode {tag}"),
+        })
+    }
+}
+
+impl Program {
+    /// The program in `artifact`'s bytecode
+    pub fn from_artifact(artifact: &CircuitArtifact) -> anyhow::Result<Self> {
+        let directives: Vec<_> = artifact
+            .brillig_names
+            .iter()
+            .map(|name| match name.as_str() {
+                "directive_invert" => Some(Directive::Invert),
+                "directive_to_radix" => Some(Directive::ToRadix),
+                _ => None,
+            })
+            .collect();
+        let mut reader = Reader {
+            bytes: &artifact.bytecode,
+        };
+        ensure!(reader.u64()? == 1, "the program must be `main` alone");
+        let _current_witness_index = reader.u32()?;
+        let opcodes = reader
+            .vec(|r| r.opcode(&directives))
+            .context("reading the opcodes")?;
+
+        // The expression width, the parameters and return values, then each
+        // assertion's error
+        if reader.u32()? == 1 {
+            reader.u64()?;
+        }
+        for _ in 0..3 {
+            reader.vec(Reader::u32)?;
+        }
+        let mut messages = HashMap::new();
+        for _ in 0..reader.len()? {
+            let location = match reader.u32()? {
+                0 => Some(reader.u64()? as usize),
+                _ => {
+                    reader.u64()?;
+                    reader.u64()?;
+                    None
+                }
+            };
+            let selector = reader.u64()?.to_string();
+            reader.vec(|r| match r.u32()? {
+                0 => r.expression().map(drop),
+                _ => r.u32().map(drop),
+            })?;
+            if let (Some(location), Some(AbiErrorType::String { string })) =
+                (location, artifact.abi.error_types.get(&selector))
+            {
+                messages.insert(location, string.clone());
+            }
+        }
+        Ok(Self { opcodes, messages })
+    }
+
+    /// Solve from `initial`, the values of witnesses 0, 1, ...
+    ///
+    /// Fails with the circuit's assertion message, if it has one, when the
+    /// inputs don't satisfy it.
+    pub fn solve(&self, initial: &[[u8; 32]]) -> anyhow::Result<Witness> {
+        let mut witness = Witness(
+            (0..)
+                .zip(initial)
+                .map(|(index, value)| (index, Fr::from_be_bytes_mod_order(value)))
+                .collect(),
+        );
+        for (index, opcode) in self.opcodes.iter().enumerate() {
+            witness.run(opcode).with_context(|| {
+                self.messages
+                    .get(&index)
+                    .cloned()
+                    .unwrap_or_else(|| format!("opcode {index} failed"))
+            })?;
+        }
+        Ok(witness)
+    }
+}
+
+impl Witness {
+    /// Witness `index`'s value, big-endian
+    pub fn get(&self, index: u32) -> Option<[u8; 32]> {
+        let value = self.0.get(&index)?.into_bigint().to_bytes_be();
+        value.try_into().ok()
+    }
+
+    /// The witness stack `nargo execute` writes, before it gzips it: one
+    /// frame, for `main`
+    pub fn to_witness_stack(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(12 + self.0.len() * 76);
+        out.extend_from_slice(&1u64.to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out.extend_from_slice(&(self.0.len() as u64).to_le_bytes());
+        for (index, value) in &self.0 {
+            out.extend_from_slice(&index.to_le_bytes());
+            out.extend_from_slice(&64u64.to_le_bytes());
+            for byte in value.into_bigint().to_bytes_be() {
+                out.extend_from_slice(format!("{byte:02x}").as_bytes());
+            }
+        }
+        out
+    }
+
+    fn value(&self, expression: &Expression) -> anyhow::Result<Fr> {
+        let known = |index| {
+            self.0
+                .get(&index)
+                .with_context(|| format!("witness {index} is unknown"))
+        };
+        let mut sum = expression.constant;
+        for (coefficient, a, b) in &expression.mul_terms {
+            sum += *coefficient * known(*a)? * known(*b)?;
+        }
+        for (coefficient, index) in &expression.linear_terms {
+            sum += *coefficient * known(*index)?;
+        }
+        Ok(sum)
+    }
+
+    /// Assign the one witness `expression` leaves unknown so it is zero, or
+    /// check it is zero if none are
+    fn assert_zero(&mut self, expression: &Expression) -> anyhow::Result<()> {
+        let mut sum = expression.constant;
+        let mut unknown: Option<(u32, Fr)> = None;
+        let mut add_unknown = |index: u32, coefficient: Fr| match &mut unknown {
+            Some((known, total)) if *known == index => {
+                *total += coefficient;
+                Ok(())
+            }
+            Some(_) => bail!("more than one witness is unknown"),
+            None => {
+                unknown = Some((index, coefficient));
+                Ok(())
+            }
+        };
+        for &(coefficient, a, b) in &expression.mul_terms {
+            match (self.0.get(&a), self.0.get(&b)) {
+                (Some(a), Some(b)) => sum += coefficient * a * b,
+                (Some(a), None) => add_unknown(b, coefficient * a)?,
+                (None, Some(b)) => add_unknown(a, coefficient * b)?,
+                (None, None) => bail!("a product of unknown witnesses"),
+            }
+        }
+        for &(coefficient, index) in &expression.linear_terms {
+            match self.0.get(&index) {
+                Some(value) => sum += coefficient * value,
+                None => add_unknown(index, coefficient)?,
+            }
+        }
+        match unknown {
+            None => ensure!(sum.is_zero(), "constraint is not satisfied"),
+            Some((index, coefficient)) => {
+                let inverse = coefficient
+                    .inverse()
+                    .with_context(|| format!("witness {index} is unconstrained"))?;
+                self.0.insert(index, -sum * inverse);
+            }
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, opcode: &Opcode) -> anyhow::Result<()> {
+        match opcode {
+            Opcode::AssertZero(expression) => self.assert_zero(expression),
+            Opcode::Range { input, bits } => {
+                let value = match input {
+                    FunctionInput::Witness(index) => *self
+                        .0
+                        .get(index)
+                        .context("range check of an unknown witness")?,
+                    FunctionInput::Constant(constant) => *constant,
+                };
+                let bits_used = value.into_bigint().num_bits();
+                ensure!(bits_used <= *bits, "value does not fit in {bits} bits");
+                Ok(())
+            }
+            Opcode::Brillig {
+                directive,
+                inputs,
+                outputs,
+                predicate,
+            } => {
+                let outputs: Vec<u32> = outputs
+                    .iter()
+                    .flat_map(|output| match output {
+                        BrilligOutput::Simple(index) => std::slice::from_ref(index),
+                        BrilligOutput::Array(indices) => indices,
+                    })
+                    .copied()
+                    .collect();
+                // A call whose predicate is false returns zeros
+                let values = match predicate {
+                    Some(predicate) if self.value(predicate)?.is_zero() => {
+                        vec![Fr::zero(); outputs.len()]
+                    }
+                    _ => {
+                        let mut args = Vec::new();
+                        for input in inputs {
+                            match input {
+                                BrilligInput::Single(expression) => {
+                                    args.push(self.value(expression)?)
+                                }
+                                BrilligInput::Array(expressions) => {
+                                    for expression in expressions {
+                                        args.push(self.value(expression)?);
+                                    }
+                                }
+                            }
+                        }
+                        directive.run(&args, outputs.len())?
+                    }
+                };
+                self.0.extend(outputs.into_iter().zip(values));
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Directive {
+    fn run(self, args: &[Fr], outputs: usize) -> anyhow::Result<Vec<Fr>> {
+        match (self, args) {
+            (Self::Invert, [value]) => {
+                ensure!(outputs == 1, "directive_invert returns one value");
+                Ok(vec![value.inverse().unwrap_or_default()])
+            }
+            (Self::ToRadix, [value, limbs, radix]) => {
+                ensure!(
+                    *limbs == Fr::from(outputs as u64),
+                    "directive_to_radix returns its limbs"
+                );
+                let radix = small(radix).filter(|radix| (2..=256).contains(radix));
+                let radix = radix.context("radix outside 2..=256")?;
+                // Digits of the value as an integer, dropping any past `limbs`
+                let mut digits = value.into_bigint().to_bytes_le();
+                let mut limbs = Vec::with_capacity(outputs);
+                for _ in 0..outputs {
+                    let mut remainder = 0u64;
+                    for byte in digits.iter_mut().rev() {
+                        let current = (remainder << 8) | u64::from(*byte);
+                        *byte = (current / radix) as u8;
+                        remainder = current % radix;
+                    }
+                    limbs.push(Fr::from(remainder));
+                }
+                Ok(limbs)
+            }
+            _ => bail!("{self:?} takes other arguments"),
+        }
+    }
+}
+
+/// `value` if it fits in a u64
+fn small(value: &Fr) -> Option<u64> {
+    let bigint = value.into_bigint();
+    bigint.0[1..]
+        .iter()
+        .all(|&limb| limb == 0)
+        .then_some(bigint.0[0])
+}
@@ -0,0 +1,223 @@
+//! Admin instructions proposed to a Squads v4 multisig
+//!
+//! When a state account's admin is a Squads vault, no one key signs its
+//! `SET_SMT_ROOT`. A member instead creates a vault transaction holding the
+//! instructions, and a proposal for it, and approves it; once enough members
+//! have approved, anyone executes it and the Squads program signs for the
+//! vault by CPI. This builds the member's half, as `client/squads.ts` does,
+//! for any instructions the vault signs.
+
+use anyhow::{ensure, Context};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_pubkey::{pubkey, Pubkey};
+
+/// Squads v4, on mainnet and devnet
+pub const SQUADS_PROGRAM_ID: Pubkey = pubkey!("SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf");
+
+/// Anchor discriminators: `sha256("global:<name>")[..8]`
+const VAULT_TRANSACTION_CREATE: [u8; 8] = [48, 250, 78, 168, 208, 226, 218, 211];
+const PROPOSAL_CREATE: [u8; 8] = [220, 60, 73, 224, 30, 108, 79, 159];
+const PROPOSAL_APPROVE: [u8; 8] = [144, 37, 164, 136, 188, 216, 42, 248];
+
+/// Multisig account: discriminator (8) + create_key (32) + config_authority
+/// (32) + threshold (2) + time_lock (4), then transaction_index (u64 LE)
+const MULTISIG_TRANSACTION_INDEX_OFFSET: usize = 78;
+
+/// PDA `["multisig", multisig, "vault", vault_index]`: the account that signs
+pub fn derive_vault_pda(squads: &Pubkey, multisig: &Pubkey, vault_index: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"multisig", multisig.as_ref(), b"vault", &[vault_index]],
+        squads,
+    )
+}
+
+/// PDA `["multisig", multisig, "transaction", index]`
+pub fn derive_transaction_pda(squads: &Pubkey, multisig: &Pubkey, index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"multisig",
+            multisig.as_ref(),
+            b"transaction",
+            &index.to_le_bytes(),
+        ],
+        squads,
+    )
+}
+
+/// PDA `["multisig", multisig, "transaction", index, "proposal"]`
+pub fn derive_proposal_pda(squads: &Pubkey, multisig: &Pubkey, index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"multisig",
+            multisig.as_ref(),
+            b"transaction",
+            &index.to_le_bytes(),
+            b"proposal",
+        ],
+        squads,
+    )
+}
+
+/// The index of the multisig's latest transaction; the next is one more
+pub fn transaction_index(multisig_data: &[u8]) -> anyhow::Result<u64> {
+    let bytes = multisig_data
+        .get(MULTISIG_TRANSACTION_INDEX_OFFSET..MULTISIG_TRANSACTION_INDEX_OFFSET + 8)
+        .context("not a Squads multisig account: too short")?;
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// `instructions` as a Squads `TransactionMessage`, with `vault` as its only
+/// signer and first key
+///
+/// Keys go writable signers, readonly signers, writable non-signers, then
+/// readonly non-signers (programs among them), each in the order the
+/// instructions first name them. Fails if an instruction needs a signer the
+/// vault can't stand in for.
+pub fn vault_message(vault: &Pubkey, instructions: &[Instruction]) -> anyhow::Result<Vec<u8>> {
+    let mut metas: Vec<AccountMeta> = vec![AccountMeta::new(*vault, true)];
+    let mut add = |meta: AccountMeta| match metas.iter_mut().find(|m| m.pubkey == meta.pubkey) {
+        Some(existing) => {
+            existing.is_signer |= meta.is_signer;
+            existing.is_writable |= meta.is_writable;
+        }
+        None => metas.push(meta),
+    };
+    for instruction in instructions {
+        instruction.accounts.iter().cloned().for_each(&mut add);
+        add(AccountMeta::new_readonly(instruction.program_id, false));
+    }
+    let signers: Vec<&AccountMeta> = metas.iter().filter(|meta| meta.is_signer).collect();
+    ensure!(
+        signers.len() == 1,
+        "only the vault can sign a vault transaction, not {}",
+        signers[1..]
+            .iter()
+            .map(|meta| meta.pubkey.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let group = |signer: bool, writable: bool| {
+        metas
+            .iter()
+            .filter(move |meta| meta.is_signer == signer && meta.is_writable == writable)
+            .map(|meta| meta.pubkey)
+    };
+    let keys: Vec<Pubkey> = group(true, true)
+        .chain(group(true, false))
+        .chain(group(false, true))
+        .chain(group(false, false))
+        .collect();
+    let count = |signer: bool, writable: bool| group(signer, writable).count() as u8;
+    let index = |pubkey: &Pubkey| keys.iter().position(|key| key == pubkey).unwrap() as u8;
+
+    let mut message = vec![
+        count(true, true) + count(true, false),
+        count(true, true),
+        count(false, true),
+    ];
+    message.push(u8::try_from(keys.len()).context("too many accounts")?);
+    for key in &keys {
+        message.extend_from_slice(key.as_ref());
+    }
+    message.push(u8::try_from(instructions.len()).context("too many instructions")?);
+    for instruction in instructions {
+        message.push(index(&instruction.program_id));
+        message.push(u8::try_from(instruction.accounts.len()).context("too many accounts")?);
+        message.extend(instruction.accounts.iter().map(|meta| index(&meta.pubkey)));
+        let len = u16::try_from(instruction.data.len()).context("instruction data too long")?;
+        message.extend_from_slice(&len.to_le_bytes());
+        message.extend_from_slice(&instruction.data);
+    }
+    // No address lookup tables
+    message.push(0);
+    Ok(message)
+}
+
+/// A vault transaction and its proposal, as a member creates them
+pub struct Proposal {
+    /// The vault transaction's index in the multisig
+    pub index: u64,
+    pub transaction: Pubkey,
+    pub proposal: Pubkey,
+    /// Create the vault transaction and the proposal, and approve it as the
+    /// member; one transaction, signed and paid for by the member
+    pub instructions: Vec<Instruction>,
+}
+
+/// Propose that the multisig's vault `vault_index` run `message` (see
+/// `vault_message`), as transaction `index`, on behalf of `member`
+pub fn propose(
+    squads: &Pubkey,
+    multisig: &Pubkey,
+    vault_index: u8,
+    index: u64,
+    member: &Pubkey,
+    message: &[u8],
+) -> Proposal {
+    let (transaction, _) = derive_transaction_pda(squads, multisig, index);
+    let (proposal, _) = derive_proposal_pda(squads, multisig, index);
+    let system = solana_sdk_ids::system_program::ID;
+
+    let mut create = VAULT_TRANSACTION_CREATE.to_vec();
+    create.push(vault_index);
+    // No ephemeral signers
+    create.push(0);
+    create.extend_from_slice(&(message.len() as u32).to_le_bytes());
+    create.extend_from_slice(message);
+    // No memo
+    create.push(0);
+
+    let mut open = PROPOSAL_CREATE.to_vec();
+    open.extend_from_slice(&index.to_le_bytes());
+    // Not a draft: open for voting
+    open.push(0);
+
+    let instructions = vec![
+        Instruction {
+            program_id: *squads,
+            accounts: vec![
+                AccountMeta::new(*multisig, false),
+                AccountMeta::new(transaction, false),
+                AccountMeta::new_readonly(*member, true),
+                AccountMeta::new(*member, true),
+                AccountMeta::new_readonly(system, false),
+            ],
+            data: create,
+        },
+        Instruction {
+            program_id: *squads,
+            accounts: vec![
+                AccountMeta::new_readonly(*multisig, false),
+                AccountMeta::new(proposal, false),
+                AccountMeta::new_readonly(*member, true),
+                AccountMeta::new(*member, true),
+                AccountMeta::new_readonly(system, false),
+            ],
+            data: open,
+        },
+        approve_ix(squads, multisig, index, member),
+    ];
+    Proposal {
+        index,
+        transaction,
+        proposal,
+        instructions,
+    }
+}
+
+/// Approve transaction `index` as `member`
+pub fn approve_ix(squads: &Pubkey, multisig: &Pubkey, index: u64, member: &Pubkey) -> Instruction {
+    let mut data = PROPOSAL_APPROVE.to_vec();
+    // No memo
+    data.push(0);
+    Instruction {
+        program_id: *squads,
+        accounts: vec![
+            AccountMeta::new_readonly(*multisig, false),
+            AccountMeta::new(*member, true),
+            AccountMeta::new(derive_proposal_pda(squads, multisig, index).0, false),
+        ],
+        data,
+    }
+}
@@ -0,0 +1,211 @@
+//! The structured reference string Barretenberg proves with, downloaded once
+//!
+//! UltraHonk proofs (`bb`) commit with powers of the secret `x` from Aztec's
+//! ignition ceremony: G1 points `x^i·G` and the G2 point `x·H`, from the
+//! curve's generators. A circuit needs as many G1 points as its padded gate
+//! count. `SrsCache` keeps them where `bb` does (`~/.bb-crs`), and downloads
+//! what it lacks from Aztec's CRS host. It checks what it downloads before
+//! caching it: every point is on its curve, the first G1 point is the
+//! generator, and the G1 points are successive powers of the G2 point's `x`
+//! (one pairing check of random combinations of them).
+//!
+//! Sunspot's Groth16 keys come from `sunspot setup` and need no SRS.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context};
+use ark_bn254::{Bn254, Fq2, Fr, G1Affine, G1Projective, G2Affine};
+use ark_ec::{pairing::Pairing, AffineRepr, VariableBaseMSM};
+use ark_ff::{Field, PrimeField};
+use sha2::{Digest, Sha256};
+
+use crate::groth16::fq;
+
+/// Aztec's CRS host, serving `g1.dat` and `g2.dat`
+pub const CRS_URL: &str = "https://crs.aztec.network";
+
+/// Bytes in a G1 point: x then y, big-endian
+pub const G1_POINT_SIZE: usize = 64;
+/// Bytes in the G2 point: x then y, real part first, big-endian
+pub const G2_POINT_SIZE: usize = 128;
+
+const G1_FILE: &str = "bn254_g1.dat";
+const G2_FILE: &str = "bn254_g2.dat";
+
+/// The first G1 points of the SRS and its G2 point, as `bb` reads them
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Srs {
+    pub g1: Vec<u8>,
+    pub g2: Vec<u8>,
+}
+
+fn g1(bytes: &[u8]) -> anyhow::Result<G1Affine> {
+    let point = G1Affine::new_unchecked(fq(&bytes[..32])?, fq(&bytes[32..])?);
+    ensure!(point.is_on_curve(), "G1 point is not on the curve");
+    Ok(point)
+}
+
+fn g2(bytes: &[u8]) -> anyhow::Result<G2Affine> {
+    let x = Fq2::new(fq(&bytes[..32])?, fq(&bytes[32..64])?);
+    let y = Fq2::new(fq(&bytes[64..96])?, fq(&bytes[96..])?);
+    let point = G2Affine::new_unchecked(x, y);
+    ensure!(
+        point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve(),
+        "G2 point is not in the group"
+    );
+    Ok(point)
+}
+
+impl Srs {
+    /// G1 points held
+    pub fn num_points(&self) -> usize {
+        self.g1.len() / G1_POINT_SIZE
+    }
+
+    /// Check the points are an SRS: see the module docs
+    pub fn check(&self) -> anyhow::Result<()> {
+        ensure!(
+            !self.g1.is_empty() && self.g1.len().is_multiple_of(G1_POINT_SIZE),
+            "G1 points are {G1_POINT_SIZE} bytes each"
+        );
+        ensure!(
+            self.g2.len() == G2_POINT_SIZE,
+            "the G2 point is {G2_POINT_SIZE} bytes"
+        );
+        let points = self
+            .g1
+            .chunks_exact(G1_POINT_SIZE)
+            .enumerate()
+            .map(|(i, point)| g1(point).with_context(|| format!("G1 point {i}")))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let x_h = g2(&self.g2)?;
+        ensure!(
+            points[0] == G1Affine::generator(),
+            "the first G1 point is not the generator"
+        );
+        if points.len() < 2 {
+            return Ok(());
+        }
+
+        // x^(i+1)·G = x·(x^i·G) for every i, checked at once: with scalars
+        // r^i, e(Σ r^i·x^(i+1)·G, H) = e(Σ r^i·x^i·G, x·H)
+        let r = Fr::from_be_bytes_mod_order(
+            &Sha256::new()
+                .chain_update(&self.g1)
+                .chain_update(&self.g2)
+                .finalize(),
+        );
+        let scalars: Vec<Fr> = std::iter::successors(Some(Fr::ONE), |s| Some(*s * r))
+            .take(points.len() - 1)
+            .collect();
+        let higher = G1Projective::msm(&points[1..], &scalars).expect("as many scalars as points");
+        let lower = G1Projective::msm(&points[..points.len() - 1], &scalars)
+            .expect("as many scalars as points");
+        ensure!(
+            Bn254::pairing(higher, G2Affine::generator()) == Bn254::pairing(lower, x_h),
+            "the G1 points are not powers of the G2 point's secret"
+        );
+        Ok(())
+    }
+
+    /// Load these points into Barretenberg
+    #[cfg(feature = "bb-sys")]
+    pub fn load_into_bb(&self) -> anyhow::Result<()> {
+        crate::bb::init_srs(&self.g1, &self.g2)
+    }
+}
+
+/// An SRS cached on disk, downloaded when too few points are cached
+pub struct SrsCache {
+    dir: PathBuf,
+    url: String,
+    http: reqwest::Client,
+}
+
+impl SrsCache {
+    /// Cache in `dir`, downloading from `CRS_URL`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            url: CRS_URL.into(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Where `bb` caches the SRS: `~/.bb-crs`
+    pub fn default_dir() -> anyhow::Result<PathBuf> {
+        Ok(PathBuf::from(std::env::var_os("HOME").context("HOME is not set")?).join(".bb-crs"))
+    }
+
+    /// Download from this host instead, e.g. a mirror
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    /// The first `num_points` G1 points and the G2 point, from the cache if
+    /// it holds enough, otherwise downloaded, checked and cached
+    pub async fn get(&self, num_points: usize) -> anyhow::Result<Srs> {
+        ensure!(num_points > 0, "no points asked for");
+        let g1_len = num_points * G1_POINT_SIZE;
+        let g1_path = self.dir.join(G1_FILE);
+        let g2_path = self.dir.join(G2_FILE);
+        if let (Ok(g1), Ok(g2)) = (
+            tokio::fs::read(&g1_path).await,
+            tokio::fs::read(&g2_path).await,
+        ) {
+            if g1.len() >= g1_len && g2.len() == G2_POINT_SIZE {
+                return Ok(Srs {
+                    g1: g1[..g1_len].to_vec(),
+                    g2,
+                });
+            }
+        }
+
+        let srs = Srs {
+            g1: self.download("g1.dat", g1_len).await?,
+            g2: self.download("g2.dat", G2_POINT_SIZE).await?,
+        };
+        srs.check()
+            .context("the downloaded SRS failed its checks")?;
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("creating {}", self.dir.display()))?;
+        write_atomically(&g1_path, &srs.g1).await?;
+        write_atomically(&g2_path, &srs.g2).await?;
+        Ok(srs)
+    }
+
+    /// The first `len` bytes of `file` on the host
+    async fn download(&self, file: &str, len: usize) -> anyhow::Result<Vec<u8>> {
+        let url = format!("{}/{file}", self.url.trim_end_matches('/'));
+        let bytes = self
+            .http
+            .get(&url)
+            .header(reqwest::header::RANGE, format!("bytes=0-{}", len - 1))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .with_context(|| format!("downloading {url}"))?
+            .bytes()
+            .await
+            .with_context(|| format!("downloading {url}"))?;
+        ensure!(
+            bytes.len() >= len,
+            "{url} has {} bytes, not the {len} needed",
+            bytes.len()
+        );
+        Ok(bytes[..len].to_vec())
+    }
+}
+
+/// Write `path` so a reader never sees it half-written
+async fn write_atomically(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    let partial = path.with_extension("partial");
+    tokio::fs::write(&partial, bytes)
+        .await
+        .with_context(|| format!("writing {}", partial.display()))?;
+    tokio::fs::rename(&partial, path)
+        .await
+        .with_context(|| format!("writing {}", path.display()))
+}
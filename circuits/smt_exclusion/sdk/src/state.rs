@@ -0,0 +1,86 @@
+//! An admin's state account, fetched over RPC
+//!
+//! Proofs are made against the root the state account holds, so a client
+//! checks that root before proving and again before sending.
+
+use anyhow::{ensure, Context};
+use exclusion_common::State;
+use solana_commitment_config::CommitmentConfig;
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+
+use crate::instructions::derive_state_pda;
+
+/// A state account as of `slot`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExclusionState {
+    /// The account's address, `["state", admin]`
+    pub address: Pubkey,
+    pub admin: Pubkey,
+    pub smt_root: [u8; 32],
+    /// Slot the account was read at
+    pub slot: u64,
+    /// Every field of the layout, including those added after the above
+    pub state: State,
+}
+
+impl ExclusionState {
+    /// Decode the account at `address`; fails on layouts that need
+    /// `MIGRATE_STATE`
+    pub fn from_account_data(address: Pubkey, slot: u64, data: &[u8]) -> anyhow::Result<Self> {
+        let state = State::unpack(data)
+            .with_context(|| format!("{address} is not a current exclusion state account"))?;
+        Ok(Self {
+            address,
+            admin: Pubkey::new_from_array(state.admin),
+            smt_root: state.smt_root,
+            slot,
+            state,
+        })
+    }
+
+    /// Whether the program accepts proofs against the root at unix time `now`:
+    /// the root hasn't expired and the state wasn't successfully challenged
+    pub fn accepts_proofs_at(&self, now: i64) -> bool {
+        let valid_until = self.state.root_valid_until;
+        !self.state.challenged && (valid_until == 0 || now <= valid_until)
+    }
+}
+
+/// Fetch and decode `admin`'s state account
+pub async fn get_exclusion_state(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    admin: &Pubkey,
+) -> anyhow::Result<ExclusionState> {
+    get_state_account(rpc, program_id, &derive_state_pda(program_id, admin).0).await
+}
+
+/// Fetch and decode the state account at `address`
+pub async fn get_state_account(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    address: &Pubkey,
+) -> anyhow::Result<ExclusionState> {
+    let response = rpc
+        .get_account_with_commitment(address, CommitmentConfig::confirmed())
+        .await
+        .context("fetching the state account")?;
+    let account = response
+        .value
+        .with_context(|| format!("state account {address} does not exist"))?;
+    ensure!(
+        account.owner == *program_id,
+        "state account {address} is not owned by {program_id}"
+    );
+    ExclusionState::from_account_data(*address, response.context.slot, &account.data)
+}
+
+/// The root proofs against `admin`'s state account must be made against
+pub async fn get_current_root(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    admin: &Pubkey,
+) -> anyhow::Result<[u8; 32]> {
+    Ok(get_exclusion_state(rpc, program_id, admin).await?.smt_root)
+}
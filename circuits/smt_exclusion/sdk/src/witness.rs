@@ -0,0 +1,59 @@
+//! The exclusion circuit's public inputs, and gnark's encoding of them
+
+use exclusion_common::{
+    instruction::NUM_PUBLIC_INPUTS, pubkey_to_index, ExclusionError, PublicWitness,
+};
+use solana_pubkey::Pubkey;
+
+/// What an exclusion proof proves: the pubkey hashing to `pubkey_hash` is not
+/// a leaf of the tree with root `smt_root`, as of `recent_slot`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExclusionWitness {
+    /// Big-endian field element
+    pub smt_root: [u8; 32],
+    /// Big-endian field element
+    pub pubkey_hash: [u8; 32],
+    pub recent_slot: u64,
+}
+
+impl ExclusionWitness {
+    /// The inputs of `pubkey`'s proof against `smt_root`
+    pub fn for_pubkey(smt_root: [u8; 32], pubkey: &Pubkey, recent_slot: u64) -> Self {
+        Self {
+            smt_root,
+            pubkey_hash: pubkey_to_index(&pubkey.to_bytes()),
+            recent_slot,
+        }
+    }
+
+    /// The public witness as sunspot writes it and the program parses it: a
+    /// 12-byte header of big-endian u32s (3 public inputs, 0 secret, 3
+    /// elements), then each input as a big-endian field element
+    pub fn to_gnark_bytes(&self) -> PublicWitness {
+        PublicWitness::new(&self.smt_root, &self.pubkey_hash, self.recent_slot)
+    }
+
+    /// The inputs a public witness holds; fails unless it has the exclusion
+    /// circuit's header and a `recent_slot` that fits a u64
+    pub fn from_gnark_bytes(bytes: &[u8]) -> Result<Self, ExclusionError> {
+        let witness = PublicWitness::try_from(bytes)?;
+        let header = witness.header();
+        if header.nb_public != NUM_PUBLIC_INPUTS
+            || header.nb_secret != 0
+            || header.len != NUM_PUBLIC_INPUTS
+        {
+            return Err(ExclusionError::InvalidDataLength);
+        }
+        Ok(Self {
+            smt_root: witness.smt_root(),
+            pubkey_hash: witness.pubkey_hash(),
+            recent_slot: witness.recent_slot().ok_or(ExclusionError::StaleProof)?,
+        })
+    }
+}
+
+impl From<ExclusionWitness> for PublicWitness {
+    fn from(witness: ExclusionWitness) -> Self {
+        witness.to_gnark_bytes()
+    }
+}
@@ -0,0 +1,206 @@
+//! Checks artifacts are read as `nargo compile` writes them, inputs laid out
+//! as ACIR numbers its witnesses, and public inputs as sunspot writes them.
+
+use std::{collections::BTreeMap, io::Write};
+
+use base64::Engine;
+use exclusion_client::{
+    acir::{exclusion_inputs, Abi, AbiType, CircuitArtifact, InputValue, Visibility},
+    witness::ExclusionWitness,
+};
+use exclusion_common::instruction::WITNESS_SIZE;
+use serde_json::json;
+use smt::{SparseMerkleTree, TREE_DEPTH};
+use solana_pubkey::Pubkey;
+
+/// The exclusion circuit's ABI, as nargo writes it
+fn abi() -> serde_json::Value {
+    let field = json!({ "kind": "field" });
+    json!({
+        "parameters": [
+            { "name": "smt_root", "type": field, "visibility": "public" },
+            { "name": "pubkey_hash", "type": field, "visibility": "public" },
+            { "name": "recent_slot", "type": field, "visibility": "public" },
+            {
+                "name": "pubkey",
+                "type": {
+                    "kind": "array",
+                    "length": 32,
+                    "type": { "kind": "integer", "sign": "unsigned", "width": 8 }
+                },
+                "visibility": "private"
+            },
+            {
+                "name": "siblings",
+                "type": { "kind": "array", "length": TREE_DEPTH, "type": field },
+                "visibility": "private"
+            },
+            { "name": "leaf_value", "type": field, "visibility": "private" }
+        ],
+        "return_type": null,
+        "error_types": {}
+    })
+}
+
+fn artifact(bytecode: &[u8]) -> Vec<u8> {
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(bytecode).unwrap();
+    serde_json::to_vec(&json!({
+        "noir_version": "1.0.0-beta.3",
+        "hash": 0,
+        "abi": abi(),
+        "bytecode": base64::engine::general_purpose::STANDARD.encode(gz.finish().unwrap()),
+        "debug_symbols": "",
+        "file_map": {},
+        "names": ["main"],
+        "brillig_names": []
+    }))
+    .unwrap()
+}
+
+#[test]
+fn reads_artifacts() {
+    let circuit = CircuitArtifact::from_json(&artifact(b"acir")).unwrap();
+    assert_eq!(circuit.noir_version, "1.0.0-beta.3");
+    assert_eq!(circuit.bytecode, b"acir");
+    let parameters = &circuit.abi.parameters;
+    assert_eq!(parameters.len(), 6);
+    assert_eq!(parameters[2].visibility, Visibility::Public);
+    assert_eq!(parameters[3].visibility, Visibility::Private);
+    assert_eq!(parameters[4].typ.field_count(), TREE_DEPTH);
+    assert!(matches!(
+        parameters[3].typ,
+        AbiType::Array { length: 32, .. }
+    ));
+
+    assert!(CircuitArtifact::from_json(b"{}").is_err());
+}
+
+#[test]
+fn lays_out_the_exclusion_inputs() {
+    let circuit = CircuitArtifact::from_json(&artifact(b"acir")).unwrap();
+    let mut tree = SparseMerkleTree::new();
+    tree.insert(&Pubkey::new_unique().to_bytes()).unwrap();
+    let inputs = tree
+        .generate_exclusion_proof(&Pubkey::new_unique().to_bytes())
+        .unwrap()
+        .unwrap();
+
+    let witness = circuit
+        .initial_witness(&exclusion_inputs(&inputs, 300))
+        .unwrap();
+    assert_eq!(witness.len(), 3 + 32 + TREE_DEPTH + 1);
+    assert_eq!(witness[0], inputs.smt_root);
+    assert_eq!(witness[1], inputs.pubkey_hash);
+    assert_eq!(InputValue::Field(witness[2]), InputValue::from_u64(300));
+    for (i, byte) in inputs.pubkey.iter().enumerate() {
+        assert_eq!(witness[3 + i][31], *byte);
+    }
+    assert_eq!(&witness[35..35 + TREE_DEPTH], &inputs.siblings[..]);
+    assert_eq!(witness[35 + TREE_DEPTH], inputs.leaf_value);
+}
+
+#[test]
+fn rejects_inputs_that_do_not_fit() {
+    let circuit = CircuitArtifact::from_json(&artifact(b"acir")).unwrap();
+    let inputs = SparseMerkleTree::new()
+        .generate_exclusion_proof(&Pubkey::new_unique().to_bytes())
+        .unwrap()
+        .unwrap();
+    let valid = exclusion_inputs(&inputs, 300);
+
+    let mut missing = valid.clone();
+    missing.remove("leaf_value");
+    let error = circuit.initial_witness(&missing).unwrap_err().to_string();
+    assert!(error.contains("leaf_value"), "{error}");
+
+    let mut extra = valid.clone();
+    extra.insert("nonce".into(), InputValue::from_u64(1));
+    let error = circuit.initial_witness(&extra).unwrap_err().to_string();
+    assert!(error.contains("nonce"), "{error}");
+
+    let mut wide = valid.clone();
+    wide.insert(
+        "pubkey".into(),
+        InputValue::Vec(vec![InputValue::from_u64(256); 32]),
+    );
+    let error = format!("{:#}", circuit.initial_witness(&wide).unwrap_err());
+    assert!(error.contains("8 bits"), "{error}");
+
+    let mut short = valid;
+    short.insert("siblings".into(), InputValue::Vec(Vec::new()));
+    assert!(circuit.initial_witness(&short).is_err());
+
+    assert!(circuit.initial_witness(&BTreeMap::new()).is_err());
+}
+
+#[test]
+fn lays_out_the_public_witness() {
+    let circuit = CircuitArtifact::from_json(&artifact(b"acir")).unwrap();
+    let layout = circuit.abi.witness_layout();
+    layout.check_exclusion().unwrap();
+    assert_eq!(layout.num_public(), 3);
+    assert_eq!(layout.witness_size(), WITNESS_SIZE);
+    assert_eq!(layout.offset("pubkey_hash"), Some(44));
+    assert_eq!(layout.offset("recent_slot"), Some(76));
+    assert_eq!(layout.offset("pubkey"), None);
+
+    let inputs = SparseMerkleTree::new()
+        .generate_exclusion_proof(&Pubkey::new_unique().to_bytes())
+        .unwrap()
+        .unwrap();
+    let expected = ExclusionWitness {
+        smt_root: inputs.smt_root,
+        pubkey_hash: inputs.pubkey_hash,
+        recent_slot: 300,
+    };
+    assert_eq!(
+        layout.encode(&exclusion_inputs(&inputs, 300)).unwrap(),
+        expected.to_gnark_bytes().as_bytes()
+    );
+}
+
+#[test]
+fn follows_the_circuit_abi() {
+    // A build from before `recent_slot` was public
+    let mut stale = abi();
+    stale["parameters"].as_array_mut().unwrap().remove(2);
+    let stale: Abi = serde_json::from_value(stale).unwrap();
+    let layout = stale.witness_layout();
+    assert_eq!(layout.witness_size(), 12 + 2 * 32);
+    let error = layout.check_exclusion().unwrap_err().to_string();
+    assert!(error.contains("108"), "{error}");
+
+    // Public arrays take a field element each, then the public return value
+    let abi: Abi = serde_json::from_value(json!({
+        "parameters": [
+            { "name": "secret", "type": { "kind": "field" }, "visibility": "private" },
+            {
+                "name": "roots",
+                "type": { "kind": "array", "length": 2, "type": { "kind": "field" } },
+                "visibility": "public"
+            }
+        ],
+        "return_type": {
+            "abi_type": { "kind": "boolean" },
+            "visibility": "public"
+        }
+    }))
+    .unwrap();
+    let layout = abi.witness_layout();
+    assert_eq!(layout.num_public(), 3);
+    assert_eq!(layout.input("return").unwrap().index, 2);
+    let witness = layout
+        .encode(&BTreeMap::from([
+            (
+                "roots".into(),
+                InputValue::Vec(vec![InputValue::from_u64(1), InputValue::from_u64(2)]),
+            ),
+            ("return".into(), InputValue::from_u64(1)),
+        ]))
+        .unwrap();
+    assert_eq!(witness.len(), layout.witness_size());
+    assert_eq!(witness[..12], [0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 3]);
+    assert_eq!(witness[12 + 32 + 31], 2);
+    assert!(layout.check_exclusion().is_err());
+}
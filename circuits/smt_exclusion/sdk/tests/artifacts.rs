@@ -0,0 +1,126 @@
+//! Checks pins catch a recompiled circuit, a new verifying key and a
+//! redeployed verifier, and that a pinned prover refuses to prove.
+
+use std::{collections::HashMap, fs, io::Write, path::Path};
+
+use base64::Engine;
+use exclusion_client::{
+    artifacts::{program_hash, programdata_elf, ArtifactPins},
+    prover::Prover,
+};
+use serde_json::json;
+use smt::SparseMerkleTree;
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::request::RpcRequest;
+
+/// Write a circuit built to `bytecode` with verifying key `vk` under
+/// `dir/target`
+fn build(dir: &Path, bytecode: &[u8], vk: &[u8]) {
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(bytecode).unwrap();
+    let artifact = json!({
+        "noir_version": "1.0.0-beta.13",
+        "abi": { "parameters": [] },
+        "bytecode": base64::engine::general_purpose::STANDARD.encode(gz.finish().unwrap()),
+    });
+    fs::create_dir_all(dir.join("target")).unwrap();
+    fs::write(
+        dir.join("target/smt_exclusion.json"),
+        serde_json::to_vec(&artifact).unwrap(),
+    )
+    .unwrap();
+    fs::write(dir.join("target/smt_exclusion.vk"), vk).unwrap();
+}
+
+/// An RPC whose `program` account is a non-upgradeable program holding `elf`
+fn rpc_with_program(elf: &[u8]) -> RpcClient {
+    let mut mocks = HashMap::new();
+    mocks.insert(
+        RpcRequest::GetAccountInfo,
+        json!({
+            "context": { "slot": 1 },
+            "value": {
+                "data": [base64::engine::general_purpose::STANDARD.encode(elf), "base64"],
+                "executable": true,
+                "lamports": 1_000_000,
+                "owner": solana_sdk_ids::bpf_loader::ID.to_string(),
+                "rentEpoch": 0,
+                "space": elf.len(),
+            },
+        }),
+    );
+    RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks)
+}
+
+#[test]
+fn hashes_programs_without_padding() {
+    let elf = b"\x7fELF program";
+    let mut padded = elf.to_vec();
+    padded.resize(100, 0);
+    assert_eq!(program_hash(elf), program_hash(&padded));
+    assert_ne!(program_hash(elf), program_hash(b"\x7fELF other"));
+
+    let mut programdata = vec![3, 0, 0, 0];
+    programdata.extend_from_slice(&[0; 8 + 1 + 32]);
+    programdata.extend_from_slice(&padded);
+    assert_eq!(programdata_elf(&programdata).unwrap(), &padded[..]);
+    programdata[0] = 2;
+    assert!(programdata_elf(&programdata).is_err());
+}
+
+#[tokio::test]
+async fn catches_changed_artifacts() {
+    let dir = tempfile::tempdir().unwrap();
+    let verifier = Pubkey::new_unique();
+    build(dir.path(), b"acir", b"vk");
+    let pins = ArtifactPins::record(&rpc_with_program(b"\x7fELF"), dir.path(), &verifier)
+        .await
+        .unwrap();
+
+    let path = dir.path().join("pins.json");
+    pins.save(&path).unwrap();
+    assert_eq!(ArtifactPins::load(&path).unwrap(), pins);
+
+    pins.check_circuit(dir.path()).unwrap();
+    pins.check_verifier(&rpc_with_program(b"\x7fELF\0\0"), &verifier)
+        .await
+        .unwrap();
+
+    let error = pins
+        .check_verifier(&rpc_with_program(b"\x7fELF v2"), &verifier)
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("redeployed"), "{error}");
+
+    build(dir.path(), b"acir", b"new vk");
+    let error = pins.check_circuit(dir.path()).unwrap_err().to_string();
+    assert!(error.contains("verifying key"), "{error}");
+
+    build(dir.path(), b"recompiled", b"vk");
+    let error = pins.check_circuit(dir.path()).unwrap_err().to_string();
+    assert!(error.contains("recompiled"), "{error}");
+}
+
+#[tokio::test]
+async fn pinned_provers_refuse_other_builds() {
+    let dir = tempfile::tempdir().unwrap();
+    build(dir.path(), b"acir", b"vk");
+    let pins = ArtifactPins {
+        circuit: [1; 32],
+        verifying_key: [2; 32],
+        verifier: [3; 32],
+    };
+    // Neither tool exists, so proving fails there if the pins pass
+    let prover = Prover::new(dir.path())
+        .with_binaries(dir.path().join("nargo"), dir.path().join("sunspot"))
+        .with_pins(pins);
+    let inputs = SparseMerkleTree::new()
+        .generate_exclusion_proof(&Pubkey::new_unique().to_bytes())
+        .unwrap()
+        .unwrap();
+    let error = prover.prove(&inputs, 300).await.unwrap_err().to_string();
+    assert!(error.contains("pinned"), "{error}");
+    assert!(!dir.path().join("Prover.toml").exists());
+}
@@ -0,0 +1,176 @@
+//! Checks batches are packed into transactions that fit, and that every
+//! transfer's outcome is reported when some fail.
+
+use std::time::Duration;
+
+use exclusion_client::{
+    batch::{plan_batch, proof_upload_ixs, send_batch, BatchOptions, BatchTransfer, Step},
+    compute_budget::{transaction_size, ComputeBudgetOptions, MAX_TRANSACTION_SIZE},
+    instructions::TransferAccounts,
+    send::SendOptions,
+};
+use exclusion_common::{
+    instruction::{CREATE_PROOF_BUFFER, PROOF_SIZE, TRANSFER_SOL, WITNESS_SIZE, WRITE_PROOF_CHUNK},
+    Proof, PublicWitness,
+};
+use serde_json::json;
+use solana_keypair::Keypair;
+use solana_pubkey::Pubkey;
+use solana_rpc_client::{mock_sender::MocksMap, nonblocking::rpc_client::RpcClient};
+use solana_rpc_client_api::request::RpcRequest;
+use solana_signature::Signature;
+use solana_signer::Signer;
+
+fn accounts(sender: &Pubkey) -> TransferAccounts {
+    TransferAccounts {
+        program_id: Pubkey::new_unique(),
+        sender: *sender,
+        recipient: Pubkey::default(),
+        state: Pubkey::new_unique(),
+        verifier: Pubkey::new_unique(),
+    }
+}
+
+fn transfers(count: u64) -> Vec<BatchTransfer> {
+    (1..=count)
+        .map(|lamports| BatchTransfer {
+            recipient: Pubkey::new_unique(),
+            lamports,
+        })
+        .collect()
+}
+
+fn proof() -> (Proof, PublicWitness) {
+    (
+        Proof([4; PROOF_SIZE]),
+        PublicWitness::new(&[5; 32], &[6; 32], 77),
+    )
+}
+
+#[test]
+fn packs_transfers_into_transactions_that_fit() {
+    let sender = Pubkey::new_unique();
+    let accounts = accounts(&sender);
+    let (proof, witness) = proof();
+    let transfers = transfers(3);
+    let steps = plan_batch(
+        &accounts,
+        &transfers,
+        &proof,
+        &witness,
+        &[],
+        &BatchOptions::default(),
+    )
+    .unwrap();
+
+    // Two proofs don't fit in one transaction
+    assert_eq!(steps.len(), 3);
+    for (index, step) in steps.iter().enumerate() {
+        let Step::Inline {
+            transfers: packed,
+            instructions,
+        } = step
+        else {
+            panic!("{step:?}");
+        };
+        assert_eq!(packed, &[index]);
+        assert_eq!(instructions[0].data[0], TRANSFER_SOL);
+        assert_eq!(
+            instructions[0].accounts[1].pubkey,
+            transfers[index].recipient
+        );
+        assert!(transaction_size(&sender, instructions, &[]).unwrap() <= MAX_TRANSACTION_SIZE);
+    }
+
+    // The upload creates the buffer and writes the payload in order
+    let payload: Vec<u8> = (0..PROOF_SIZE + WITNESS_SIZE).map(|i| i as u8).collect();
+    let uploads = proof_upload_ixs(&accounts.program_id, &sender, &payload, true, &[]).unwrap();
+    assert_eq!(uploads[0][0].data[0], CREATE_PROOF_BUFFER);
+    let mut written = Vec::new();
+    for instructions in &uploads {
+        assert!(transaction_size(&sender, instructions, &[]).unwrap() <= MAX_TRANSACTION_SIZE);
+        for ix in instructions
+            .iter()
+            .filter(|ix| ix.data[0] == WRITE_PROOF_CHUNK)
+        {
+            let offset = u16::from_le_bytes(ix.data[2..4].try_into().unwrap());
+            assert_eq!(usize::from(offset), written.len());
+            written.extend_from_slice(&ix.data[6..]);
+        }
+    }
+    assert_eq!(written, payload);
+}
+
+#[tokio::test]
+async fn reports_each_transfer() {
+    let sender = Keypair::new();
+    let accounts = accounts(&sender.pubkey());
+    let (proof, witness) = proof();
+    let transfers = transfers(3);
+    let options = BatchOptions {
+        concurrency: 1,
+        compute_budget: ComputeBudgetOptions {
+            fee_percentile: 0,
+            ..ComputeBudgetOptions::default()
+        },
+        send: SendOptions {
+            poll_interval: Duration::from_millis(1),
+            ..SendOptions::default()
+        },
+        ..BatchOptions::default()
+    };
+    let steps = plan_batch(&accounts, &transfers, &proof, &witness, &[], &options).unwrap();
+
+    // Each transaction is simulated to size it, then again before it's sent;
+    // the second transfer's state account turns out to be wrong
+    let ok = json!({
+        "context": { "slot": 1 },
+        "value": { "err": null, "logs": [], "unitsConsumed": 300_000 },
+    });
+    let mut mocks = MocksMap::default();
+    for response in [
+        ok.clone(),
+        ok.clone(),
+        json!({
+            "context": { "slot": 1 },
+            "value": {
+                "err": { "InstructionError": [1, { "Custom": 4 }] },
+                "logs": ["Program log: Invalid state account PDA"],
+            },
+        }),
+        ok.clone(),
+        ok,
+    ] {
+        mocks.insert(RpcRequest::SimulateTransaction, response);
+    }
+    // What the node answers to a send doesn't matter: the statuses say the
+    // transactions landed
+    for _ in 0..2 {
+        mocks.insert(
+            RpcRequest::SendTransaction,
+            json!(Signature::default().to_string()),
+        );
+    }
+    let rpc = RpcClient::new_mock_with_mocks_map("succeeds".to_string(), mocks);
+
+    let outcomes = send_batch(
+        &rpc,
+        &accounts.program_id,
+        &sender,
+        &transfers,
+        &steps,
+        &proof,
+        &witness,
+        &[],
+        &options,
+    )
+    .await;
+    assert_eq!(outcomes.len(), 3);
+    for (outcome, transfer) in outcomes.iter().zip(&transfers) {
+        assert_eq!(outcome.transfer, *transfer);
+    }
+    assert!(outcomes[0].result.is_ok());
+    let error = outcomes[1].result.as_ref().unwrap_err();
+    assert!(error.contains("Invalid state account PDA"), "{error}");
+    assert!(outcomes[2].result.is_ok());
+}
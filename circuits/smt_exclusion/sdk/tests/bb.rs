@@ -0,0 +1,96 @@
+//! Proves an exclusion with Barretenberg in this process and verifies it,
+//! then checks an occupied leaf fails to solve.
+//!
+//! Needs `libbarretenberg` (`BB_LIB_DIR`) and the compiled circuit: run
+//! `nargo compile` in `circuits/smt_exclusion` first. The SRS is made from a
+//! known secret, so the proofs only show prover and verifier agree.
+
+#![cfg(feature = "bb-sys")]
+
+use std::{path::Path, sync::Arc};
+
+use ark_bn254::{Fr, G1Affine, G1Projective, G2Affine};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{BigInteger, PrimeField};
+use exclusion_client::{
+    acir::exclusion_inputs,
+    bb::{self, Circuit},
+    prover::Prover,
+};
+use smt::SparseMerkleTree;
+use solana_pubkey::Pubkey;
+
+/// Points the SRS holds, past the circuit's size
+const SRS_POINTS: usize = (1 << 19) + 1;
+
+fn be<F: PrimeField>(element: F) -> Vec<u8> {
+    element.into_bigint().to_bytes_be()
+}
+
+/// `len` G1 points `x^i·G` and the G2 point `x·H`, as `tests/srs.rs` makes
+/// them
+fn srs(x: u64, len: usize) -> (Vec<u8>, Vec<u8>) {
+    let x = Fr::from(x);
+    let mut powers: Vec<G1Projective> = Vec::with_capacity(len);
+    let mut point = G1Affine::generator().into_group();
+    for _ in 0..len {
+        powers.push(point);
+        point *= x;
+    }
+    let g1 = G1Projective::normalize_batch(&powers)
+        .into_iter()
+        .flat_map(|point| [be(point.x), be(point.y)].concat())
+        .collect();
+    let point = (G2Affine::generator() * x).into_affine();
+    let g2 = [
+        be(point.x.c0),
+        be(point.x.c1),
+        be(point.y.c0),
+        be(point.y.c1),
+    ]
+    .concat();
+    (g1, g2)
+}
+
+fn circuit() -> Arc<Circuit> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../target/smt_exclusion.json");
+    Arc::new(Circuit::load(path).expect("run `nargo compile` first"))
+}
+
+#[tokio::test]
+async fn proves_and_verifies_in_process() {
+    let (g1, g2) = srs(7, SRS_POINTS);
+    bb::init_srs(&g1, &g2).unwrap();
+    let circuit = circuit();
+
+    let mut tree = SparseMerkleTree::new();
+    tree.insert(&Pubkey::new_unique().to_bytes()).unwrap();
+    let inputs = tree
+        .generate_exclusion_proof(&Pubkey::new_unique().to_bytes())
+        .unwrap()
+        .unwrap();
+
+    // No nargo: the circuit directory doesn't exist
+    let prover = Prover::new("/nonexistent");
+    let proof = prover
+        .prove_with_bb(circuit.clone(), &inputs, 300)
+        .await
+        .unwrap();
+    let verification_key = circuit.verification_key().unwrap();
+    assert!(bb::verify(&proof, &verification_key));
+
+    // One bit of the proof flipped
+    let mut tampered = proof.clone();
+    let last = tampered.0.len() - 1;
+    tampered.0[last] ^= 1;
+    assert!(!bb::verify(&tampered, &verification_key));
+
+    // A leaf that isn't empty has no witness to prove
+    let mut forged = inputs.clone();
+    forged.leaf_value[31] = 1;
+    let error = circuit.solve(&exclusion_inputs(&forged, 300)).unwrap_err();
+    assert!(
+        format!("{error:#}").contains("Exclusion failed"),
+        "{error:#}"
+    );
+}
@@ -0,0 +1,110 @@
+//! Round-trips proofs through `compress`, and checks the compressed proof the
+//! program's tests expand (`tests/fixtures/compressed/`) is this one's.
+
+use std::{fs, path::Path};
+
+use ark_bn254::{Fq, Fr, G1Affine, G2Affine};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{BigInteger, Field, PrimeField};
+use exclusion_client::compress::{compress_proof, decompress_proof};
+use exclusion_common::{CompressedProof, ExclusionError, Proof};
+
+fn fq(element: Fq) -> Vec<u8> {
+    element.into_bigint().to_bytes_be()
+}
+
+fn g1(scalar: i64) -> Vec<u8> {
+    let point = (G1Affine::generator() * Fr::from(scalar)).into_affine();
+    [fq(point.x), fq(point.y)].concat()
+}
+
+/// G2 coordinates imaginary part first, as gnark writes them
+fn g2(scalar: i64) -> Vec<u8> {
+    let point = (G2Affine::generator() * Fr::from(scalar)).into_affine();
+    [point.x.c1, point.x.c0, point.y.c1, point.y.c0]
+        .into_iter()
+        .flat_map(fq)
+        .collect()
+}
+
+/// A proof of distinct multiples of the generators, negative ones included so
+/// both y flags occur, with one commitment
+fn proof() -> Proof {
+    let bytes = [
+        g1(2),
+        g2(3),
+        g1(-5),
+        1u32.to_be_bytes().to_vec(),
+        g1(7),
+        g1(-11),
+    ]
+    .concat();
+    Proof::try_from(&bytes[..]).unwrap()
+}
+
+fn fixture(name: &str) -> Vec<u8> {
+    let dir =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../on_chain_program/tests/fixtures/compressed");
+    fs::read(dir.join(name)).unwrap()
+}
+
+#[test]
+fn compressed_proofs_expand_to_the_original() {
+    let proof = proof();
+    let compressed = compress_proof(&proof).unwrap();
+    assert_eq!(decompress_proof(&compressed).unwrap(), proof);
+}
+
+#[test]
+fn program_fixture_is_this_proof_compressed() {
+    let proof = proof();
+    assert_eq!(fixture("uncompressed.proof"), proof.as_bytes());
+    assert_eq!(
+        fixture("compressed.proof"),
+        compress_proof(&proof).unwrap().as_bytes()
+    );
+}
+
+#[test]
+fn point_at_infinity_stays_zero() {
+    let mut bytes = *proof().as_bytes();
+    bytes[..64].fill(0);
+    let compressed = compress_proof(&Proof(bytes)).unwrap();
+    assert_eq!(compressed.as_bytes()[..32], [0; 32]);
+    assert_eq!(decompress_proof(&compressed).unwrap(), Proof(bytes));
+}
+
+#[test]
+fn off_curve_points_are_invalid() {
+    // (1, 3) is not on y^2 = x^3 + 3
+    let mut bytes = *proof().as_bytes();
+    bytes[..64].fill(0);
+    bytes[31] = 1;
+    bytes[63] = 3;
+    assert_eq!(
+        compress_proof(&Proof(bytes)),
+        Err(ExclusionError::InvalidCompressedProof)
+    );
+
+    // An x with no y on the curve
+    let x = (1u64..)
+        .map(Fq::from)
+        .find(|x| (x.square() * x + Fq::from(3)).sqrt().is_none())
+        .unwrap();
+    let mut compressed = *compress_proof(&proof()).unwrap().as_bytes();
+    compressed[96..128].copy_from_slice(&fq(x));
+    assert_eq!(
+        decompress_proof(&CompressedProof(compressed)),
+        Err(ExclusionError::InvalidCompressedProof)
+    );
+}
+
+#[test]
+fn only_one_commitment_compresses() {
+    let mut bytes = *proof().as_bytes();
+    bytes[256..260].copy_from_slice(&2u32.to_be_bytes());
+    assert_eq!(
+        compress_proof(&Proof(bytes)),
+        Err(ExclusionError::InvalidCompressedProof)
+    );
+}
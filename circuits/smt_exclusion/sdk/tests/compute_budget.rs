@@ -0,0 +1,118 @@
+//! Checks compute budgets are sized from a simulation and recent fees.
+
+use std::collections::HashMap;
+
+use exclusion_client::compute_budget::{
+    build_sized_transaction, compute_unit_limit, compute_unit_price, ComputeBudgetOptions,
+    MAX_COMPUTE_UNIT_LIMIT,
+};
+use serde_json::json;
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_keypair::Keypair;
+use solana_message::VersionedMessage;
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::request::RpcRequest;
+use solana_signer::Signer;
+
+#[test]
+fn limit_adds_the_margin_up_to_the_maximum() {
+    assert_eq!(compute_unit_limit(200_000, 10), 220_000);
+    assert_eq!(compute_unit_limit(200_000, 0), 200_000);
+    assert_eq!(compute_unit_limit(1_300_000, 10), MAX_COMPUTE_UNIT_LIMIT);
+}
+
+#[test]
+fn price_is_a_capped_percentile() {
+    let fees = [40, 10, 30, 20];
+    assert_eq!(compute_unit_price(&fees, 50, 100), 20);
+    assert_eq!(compute_unit_price(&fees, 75, 100), 30);
+    assert_eq!(compute_unit_price(&fees, 100, 100), 40);
+    assert_eq!(compute_unit_price(&fees, 100, 35), 35);
+    assert_eq!(compute_unit_price(&fees, 0, 100), 0);
+    assert_eq!(compute_unit_price(&[], 75, 100), 0);
+}
+
+#[tokio::test]
+async fn sizes_from_simulation() {
+    let mut mocks = HashMap::new();
+    mocks.insert(
+        RpcRequest::SimulateTransaction,
+        json!({
+            "context": { "slot": 1 },
+            "value": { "err": null, "logs": [], "unitsConsumed": 300_000 },
+        }),
+    );
+    mocks.insert(
+        RpcRequest::GetRecentPrioritizationFees,
+        json!([
+            { "slot": 1, "prioritizationFee": 0 },
+            { "slot": 2, "prioritizationFee": 5_000 },
+            { "slot": 3, "prioritizationFee": 1_000 },
+            { "slot": 4, "prioritizationFee": 2_000 },
+        ]),
+    );
+    let rpc = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+    let payer = Keypair::new();
+    let instructions = [
+        // Replaced by the sized limit
+        ComputeBudgetInstruction::set_compute_unit_limit(1),
+        solana_system_interface::instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1),
+    ];
+
+    let sized = build_sized_transaction(
+        &rpc,
+        &payer,
+        &instructions,
+        &[],
+        &ComputeBudgetOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(sized.units_consumed, 300_000);
+    assert_eq!(sized.compute_unit_limit, 330_000);
+    assert_eq!(sized.compute_unit_price, 2_000);
+
+    let VersionedMessage::V0(message) = &sized.transaction.message else {
+        panic!("not a v0 message");
+    };
+    let data: Vec<&[u8]> = message.instructions.iter().map(|ix| &ix.data[..]).collect();
+    assert_eq!(
+        data,
+        [
+            &ComputeBudgetInstruction::set_compute_unit_limit(330_000).data[..],
+            &ComputeBudgetInstruction::set_compute_unit_price(2_000).data,
+            &instructions[1].data,
+        ]
+    );
+    assert_eq!(sized.transaction.signatures.len(), 1);
+    assert!(sized.transaction.signatures[0].verify(
+        payer.pubkey().as_ref(),
+        &sized.transaction.message.serialize()
+    ));
+}
+
+#[tokio::test]
+async fn fails_with_the_simulation() {
+    let mut mocks = HashMap::new();
+    mocks.insert(
+        RpcRequest::SimulateTransaction,
+        json!({
+            "context": { "slot": 1 },
+            "value": {
+                "err": { "InstructionError": [0, { "Custom": 4 }] },
+                "logs": ["Program log: Invalid state account PDA"],
+            },
+        }),
+    );
+    let rpc = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+    let payer = Keypair::new();
+    let ix =
+        solana_system_interface::instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+    let error = build_sized_transaction(&rpc, &payer, &[ix], &[], &ComputeBudgetOptions::default())
+        .await
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(error.contains("Invalid state account PDA"), "{error}");
+}
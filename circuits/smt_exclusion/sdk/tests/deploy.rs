@@ -0,0 +1,63 @@
+//! Checks verifier deployment writes the ELF in transactions that fit and
+//! reports the hash pins record.
+
+use exclusion_client::{
+    artifacts::program_hash,
+    deploy::{deploy_verifier, write_buffer_ixs, VerifierProgram, WRITE_CHUNK_SIZE},
+};
+use solana_hash::Hash;
+use solana_keypair::Keypair;
+use solana_message::Message;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+/// Largest serialized transaction the network accepts
+const PACKET_DATA_SIZE: usize = 1232;
+
+fn elf(len: usize) -> Vec<u8> {
+    let mut elf = b"\x7fELF".to_vec();
+    elf.extend((0..len - 4).map(|i| (i % 251) as u8 + 1));
+    elf
+}
+
+#[test]
+fn writes_fit_transactions() {
+    let payer = Keypair::new();
+    let buffer = Keypair::new();
+    let elf = elf(2 * WRITE_CHUNK_SIZE + 10);
+    let transactions = write_buffer_ixs(&payer.pubkey(), &buffer.pubkey(), 1, &elf).unwrap();
+    assert_eq!(transactions.len(), 1 + 3);
+    for (i, ixs) in transactions.iter().enumerate() {
+        let signers: Vec<&Keypair> = match i {
+            0 => vec![&payer, &buffer],
+            _ => vec![&payer],
+        };
+        let message = Message::new(ixs, Some(&payer.pubkey()));
+        let transaction = Transaction::new(&signers, message, Hash::default());
+        let size = bincode::serialize(&transaction).unwrap().len();
+        assert!(size <= PACKET_DATA_SIZE, "{size}-byte transaction {i}");
+    }
+
+    // Each write carries the chunk at its offset: tag, offset, then the bytes
+    let last = &transactions[3][0].data;
+    assert_eq!(last[4..8], (2 * WRITE_CHUNK_SIZE as u32).to_le_bytes());
+    assert_eq!(last[16..], elf[2 * WRITE_CHUNK_SIZE..]);
+}
+
+#[tokio::test]
+async fn deploys_new_programs() {
+    let rpc = RpcClient::new_mock("succeeds".to_string());
+    let payer = Keypair::new();
+    let program = VerifierProgram::New(Keypair::new());
+    let elf = elf(3_000);
+    let deployed = deploy_verifier(&rpc, &payer, &program, &elf).await.unwrap();
+    assert_eq!(deployed.program_id, program.address());
+    assert_eq!(deployed.hash, program_hash(&elf));
+
+    let error = deploy_verifier(&rpc, &payer, &program, b"not a program")
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("ELF"), "{error}");
+}
@@ -0,0 +1,246 @@
+//! Checks the builders against the program's documented accounts and data,
+//! and the decoders against packed accounts.
+
+use exclusion_client::{
+    accounts::{
+        decode_attestation, decode_config, decode_escrow_account, decode_exclusion_queue,
+        decode_proof_buffer, decode_rate_limit_account, decode_state, decode_verifier_registry,
+        Attestation, RegisteredVerifier,
+    },
+    instructions::{
+        claim_from_escrow_ix, derive_bond_pda, derive_escrow_pda, derive_state_pda, initialize_ix,
+        set_smt_root_ix, transfer_sol_compressed_ix, transfer_sol_ix, TransferAccounts,
+    },
+};
+use exclusion_common::{
+    instruction::{self, COMPRESSED_PROOF_SIZE, METADATA_SIZE, PROOF_SIZE},
+    state::{
+        self, ATTESTATION_DISCRIMINATOR, CONFIG_DISCRIMINATOR, CONFIG_SIZE,
+        EXCLUSION_QUEUE_DISCRIMINATOR, EXCLUSION_QUEUE_SIZE, PROOF_BUFFER_DISCRIMINATOR,
+        PROOF_BUFFER_SIZE, RATE_LIMIT_ACCOUNT_DISCRIMINATOR, RATE_LIMIT_ACCOUNT_SIZE, STATE_SIZE,
+        VERIFIER_REGISTRY_DISCRIMINATOR, VERIFIER_REGISTRY_SIZE,
+    },
+    CompressedProof, ExclusionError, Proof, PublicWitness, State,
+};
+use exclusion_program_example::{events::NOOP_PROGRAM_ID, instruction::InstructionHeader};
+use solana_pubkey::Pubkey;
+
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+const ADMIN: Pubkey = Pubkey::new_from_array([8; 32]);
+
+fn header(data: &[u8], discriminator: u8) -> &[u8] {
+    let (header, payload) = InstructionHeader::unpack(data).unwrap();
+    assert_eq!(header.discriminator, discriminator);
+    assert_eq!(header.version, instruction::max_version(discriminator));
+    payload
+}
+
+#[test]
+fn initialize_names_state_and_bond() {
+    let ix = initialize_ix(&PROGRAM_ID, &ADMIN, 5_000);
+    let (state, _) = derive_state_pda(&PROGRAM_ID, &ADMIN);
+    let keys: Vec<_> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(
+        keys,
+        [
+            ADMIN,
+            state,
+            solana_sdk_ids::system_program::ID,
+            derive_bond_pda(&PROGRAM_ID, &state).0
+        ]
+    );
+    assert!(ix.accounts[0].is_signer && ix.accounts[1].is_writable);
+    assert_eq!(
+        header(&ix.data, instruction::INITIALIZE),
+        5_000u64.to_le_bytes()
+    );
+}
+
+#[test]
+fn set_smt_root_carries_expiry_and_metadata() {
+    let ix = set_smt_root_ix(&PROGRAM_ID, &ADMIN, &[1; 32], -2, &[3; METADATA_SIZE]);
+    assert_eq!(
+        ix.accounts[1].pubkey,
+        derive_state_pda(&PROGRAM_ID, &ADMIN).0
+    );
+    assert_eq!(ix.accounts[2].pubkey, NOOP_PROGRAM_ID);
+    let payload = header(&ix.data, instruction::SET_SMT_ROOT);
+    assert_eq!(payload.len(), 32 + 8 + METADATA_SIZE);
+    assert_eq!(payload[..32], [1; 32]);
+    assert_eq!(payload[32..40], (-2i64).to_le_bytes());
+    assert_eq!(payload[40..], [3; METADATA_SIZE]);
+}
+
+#[test]
+fn transfer_sol_lays_out_proof_and_witness() {
+    let accounts = TransferAccounts {
+        program_id: PROGRAM_ID,
+        sender: ADMIN,
+        recipient: Pubkey::new_from_array([9; 32]),
+        state: derive_state_pda(&PROGRAM_ID, &ADMIN).0,
+        verifier: Pubkey::new_from_array([10; 32]),
+    };
+    let proof = Proof([4; PROOF_SIZE]);
+    let witness = PublicWitness::new(&[5; 32], &[6; 32], 77);
+    let ix = transfer_sol_ix(&accounts, 1_000, &proof, &witness);
+    let payload = header(&ix.data, instruction::TRANSFER_SOL);
+    assert_eq!(payload[..8], 1_000u64.to_le_bytes());
+    assert_eq!(payload[8..8 + PROOF_SIZE], proof.0);
+    assert_eq!(payload[8 + PROOF_SIZE..], witness.0);
+
+    // The compressed transfer takes the same accounts
+    let compressed = CompressedProof([4; COMPRESSED_PROOF_SIZE]);
+    let compressed_ix = transfer_sol_compressed_ix(&accounts, 1_000, &compressed, &witness);
+    assert_eq!(compressed_ix.accounts, ix.accounts);
+    let payload = header(&compressed_ix.data, instruction::TRANSFER_SOL_COMPRESSED);
+    assert_eq!(payload[..8], 1_000u64.to_le_bytes());
+    assert_eq!(payload[8..8 + COMPRESSED_PROOF_SIZE], compressed.0);
+    assert_eq!(payload[8 + COMPRESSED_PROOF_SIZE..], witness.0);
+}
+
+#[test]
+fn claim_from_escrow_versions_by_its_proof() {
+    let accounts = TransferAccounts {
+        program_id: PROGRAM_ID,
+        sender: ADMIN,
+        recipient: Pubkey::new_from_array([9; 32]),
+        state: derive_state_pda(&PROGRAM_ID, &ADMIN).0,
+        verifier: Pubkey::new_from_array([10; 32]),
+    };
+    let proof = Proof([4; PROOF_SIZE]);
+    let witness = PublicWitness::new(&[5; 32], &[6; 32], 77);
+    let ix = claim_from_escrow_ix(&accounts, 3, Some((&proof, &witness)));
+    let escrow = derive_escrow_pda(&PROGRAM_ID, &ADMIN, &accounts.recipient, 3).0;
+    assert_eq!(
+        (ix.accounts[0].pubkey, ix.accounts[2].pubkey),
+        (accounts.recipient, escrow)
+    );
+    assert!(ix.accounts[0].is_signer && ix.accounts[2].is_writable);
+    let payload = header(&ix.data, instruction::CLAIM_FROM_ESCROW);
+    assert_eq!(payload[..PROOF_SIZE], proof.0);
+    assert_eq!(payload[PROOF_SIZE..], witness.0);
+
+    // Without a proof, version 1
+    let ix = claim_from_escrow_ix(&accounts, 3, None);
+    assert_eq!(ix.data, [instruction::CLAIM_FROM_ESCROW, 1]);
+}
+
+#[test]
+fn decodes_packed_accounts() {
+    let state = State {
+        admin: ADMIN.to_bytes(),
+        smt_root: [1; 32],
+        root_valid_until: 0,
+        transfer_flags: 0,
+        daily_limit: 0,
+        fee_bps: 25,
+        root_seq: 3,
+        approved_callers: [[0; 32]; state::MAX_APPROVED_CALLERS],
+        approved_caller_count: 0,
+        bump: 254,
+        treasury_bump: 253,
+        hash_scheme: 0,
+        verifier_flags: 0,
+        metadata: [0; METADATA_SIZE],
+        challenged: false,
+        bond_bump: 252,
+        bond_amount: 0,
+        bond_withdrawal_amount: 0,
+        bond_withdrawal_at: 0,
+        config: [0; 32],
+        tree_kind: 0,
+        tree_depth: 254,
+    };
+    let mut data = vec![0; STATE_SIZE];
+    state.pack(&mut data);
+    assert_eq!(decode_state(&data), Ok(state));
+
+    let witness = PublicWitness::new(&[5; 32], &[6; 32], 77);
+    let mut data = PROOF_BUFFER_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(ADMIN.as_ref());
+    data.extend_from_slice(&[4; PROOF_SIZE]);
+    data.extend_from_slice(&witness.0);
+    assert_eq!(data.len(), PROOF_BUFFER_SIZE);
+    let buffer = decode_proof_buffer(&data).unwrap();
+    assert_eq!(buffer.owner, ADMIN);
+    assert_eq!(buffer.witness.recent_slot(), Some(77));
+
+    // Not an escrow
+    assert_eq!(
+        decode_escrow_account(&data),
+        Err(ExclusionError::InvalidEscrowAccount)
+    );
+}
+
+#[test]
+fn decodes_attestations_configs_and_queues() {
+    let state = Pubkey::new_from_array([3; 32]);
+    let mut data = ATTESTATION_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(ADMIN.as_ref());
+    data.extend_from_slice(state.as_ref());
+    data.extend_from_slice(&[1; 32]);
+    data.extend_from_slice(&900u64.to_le_bytes());
+    assert_eq!(
+        decode_attestation(&data),
+        Ok(Attestation {
+            subject: ADMIN,
+            state,
+            smt_root: [1; 32],
+            slot: 900,
+        })
+    );
+    assert_eq!(
+        decode_attestation(&data[..data.len() - 1]),
+        Err(ExclusionError::InvalidAttestationAccount)
+    );
+
+    let mut data = vec![0; CONFIG_SIZE];
+    data[..8].copy_from_slice(&CONFIG_DISCRIMINATOR);
+    data[9] = 1;
+    data[10..42].copy_from_slice(&[4; 32]);
+    data[74..82].copy_from_slice(&150u64.to_le_bytes());
+    data[82..84].copy_from_slice(&500u16.to_le_bytes());
+    let config = decode_config(&data).unwrap();
+    assert!(config.paused);
+    assert_eq!(config.zk_verifier, Pubkey::new_from_array([4; 32]));
+    assert_eq!(config.transition_verifier, Pubkey::default());
+    assert_eq!((config.max_proof_age_slots, config.max_fee_bps), (150, 500));
+
+    let mut data = vec![0; VERIFIER_REGISTRY_SIZE];
+    data[..8].copy_from_slice(&VERIFIER_REGISTRY_DISCRIMINATOR);
+    data[9..41].copy_from_slice(ADMIN.as_ref());
+    data[41] = 1;
+    data[42..74].copy_from_slice(&[5; 32]);
+    data[74..106].copy_from_slice(&[6; 32]);
+    data[106] = 1;
+    data[107..109].copy_from_slice(&20u16.to_le_bytes());
+    let registry = decode_verifier_registry(&data).unwrap();
+    assert_eq!(registry.authority, ADMIN);
+    assert_eq!(
+        registry.verifiers,
+        [RegisteredVerifier {
+            program_id: Pubkey::new_from_array([5; 32]),
+            circuit_hash: [6; 32],
+            tree_kind: 1,
+            tree_depth: 20,
+        }]
+    );
+
+    let mut data = vec![0; RATE_LIMIT_ACCOUNT_SIZE];
+    data[..8].copy_from_slice(&RATE_LIMIT_ACCOUNT_DISCRIMINATOR);
+    data[8..16].copy_from_slice(&480_000u64.to_le_bytes());
+    data[16..24].copy_from_slice(&7u64.to_le_bytes());
+    let limit = decode_rate_limit_account(&data).unwrap();
+    assert_eq!((limit.last_hour, limit.hourly[0]), (480_000, 7));
+
+    let mut data = vec![0; EXCLUSION_QUEUE_SIZE];
+    data[..8].copy_from_slice(&EXCLUSION_QUEUE_DISCRIMINATOR);
+    data[8] = 1;
+    data[9..41].copy_from_slice(ADMIN.as_ref());
+    assert_eq!(decode_exclusion_queue(&data), Ok(vec![ADMIN]));
+    data[8] = 33;
+    assert_eq!(
+        decode_exclusion_queue(&data),
+        Err(ExclusionError::InvalidExclusionQueue)
+    );
+}
@@ -0,0 +1,128 @@
+//! Checks bundled transfers fit in transactions and tip last.
+
+use exclusion_client::{
+    instructions::{derive_proof_buffer_pda, TransferAccounts},
+    jito::{build_bundle, GatedTransfer, JitoOptions},
+};
+use exclusion_common::{Proof, PublicWitness};
+use exclusion_program_example::instruction::{self, PROOF_SIZE, WITNESS_SIZE};
+use solana_hash::Hash;
+use solana_keypair::Keypair;
+use solana_pubkey::Pubkey;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+/// Largest serialized transaction the network accepts
+const PACKET_DATA_SIZE: usize = 1232;
+
+const PROOF: Proof = Proof([1; PROOF_SIZE]);
+const WITNESS: PublicWitness = PublicWitness([2; WITNESS_SIZE]);
+
+fn transfer(sender: &Keypair, buffered: bool) -> GatedTransfer<'static> {
+    GatedTransfer {
+        accounts: TransferAccounts {
+            program_id: Pubkey::new_from_array([1; 32]),
+            sender: sender.pubkey(),
+            recipient: Pubkey::new_from_array([2; 32]),
+            state: Pubkey::new_from_array([3; 32]),
+            verifier: Pubkey::new_from_array([4; 32]),
+        },
+        amount: 1_000_000,
+        proof: &PROOF,
+        witness: &WITNESS,
+        buffered,
+    }
+}
+
+fn check_sizes(bundle: &[Transaction]) {
+    for transaction in bundle {
+        let size = bincode::serialize(transaction).unwrap().len();
+        assert!(size <= PACKET_DATA_SIZE, "{size}-byte transaction");
+        assert!(transaction.is_signed());
+    }
+}
+
+/// The program ID, accounts and data of the transaction's last instruction
+fn last_instruction(transaction: &Transaction) -> (Pubkey, Vec<Pubkey>, &[u8]) {
+    let message = &transaction.message;
+    let ix = message.instructions.last().unwrap();
+    let key = |index: u8| message.account_keys[index as usize];
+    (
+        key(ix.program_id_index),
+        ix.accounts.iter().copied().map(key).collect(),
+        &ix.data,
+    )
+}
+
+#[test]
+fn inline_transfer_is_one_transaction() {
+    let sender = Keypair::new();
+    let tip_account = Pubkey::new_from_array([9; 32]);
+    let bundle = build_bundle(
+        &transfer(&sender, false),
+        &sender,
+        &tip_account,
+        &JitoOptions {
+            compute_unit_price: 1_000,
+            ..JitoOptions::default()
+        },
+        true,
+        Hash::new_from_array([7; 32]),
+    );
+    assert_eq!(bundle.len(), 1);
+    check_sizes(&bundle);
+
+    let message = &bundle[0].message;
+    let transfer_ix = &message.instructions[2];
+    assert_eq!(transfer_ix.data[0], instruction::TRANSFER_SOL);
+    assert_eq!(transfer_ix.data.len(), 2 + instruction::TRANSFER_SOL_LEN);
+
+    let (program, accounts, _) = last_instruction(&bundle[0]);
+    assert_eq!(program, solana_sdk_ids::system_program::ID);
+    assert_eq!(accounts, [sender.pubkey(), tip_account]);
+}
+
+#[test]
+fn buffered_transfer_uploads_first() {
+    let sender = Keypair::new();
+    let tip_account = Pubkey::new_from_array([9; 32]);
+    let transfer = transfer(&sender, true);
+    let bundle = build_bundle(
+        &transfer,
+        &sender,
+        &tip_account,
+        &JitoOptions::default(),
+        true,
+        Hash::new_from_array([7; 32]),
+    );
+    assert_eq!(bundle.len(), 2);
+    check_sizes(&bundle);
+
+    let upload = &bundle[0].message;
+    let buffer = derive_proof_buffer_pda(&transfer.accounts.program_id, &sender.pubkey()).0;
+    assert!(upload.account_keys.contains(&buffer));
+    assert_eq!(
+        upload.instructions[0].data[0],
+        instruction::CREATE_PROOF_BUFFER
+    );
+    let write = &upload.instructions[1].data;
+    assert_eq!(write[0], instruction::WRITE_PROOF_CHUNK);
+    assert_eq!(write[6..], [&PROOF.0[..], &WITNESS.0].concat());
+
+    let transfer_ix = &bundle[1].message.instructions[1];
+    assert_eq!(transfer_ix.data[0], instruction::TRANSFER_FROM_BUFFER);
+    let (_, accounts, data) = last_instruction(&bundle[1]);
+    assert_eq!(accounts[1], tip_account);
+    assert_eq!(data[4..], JitoOptions::default().tip_lamports.to_le_bytes());
+
+    // An existing buffer is only written
+    let bundle = build_bundle(
+        &transfer,
+        &sender,
+        &tip_account,
+        &JitoOptions::default(),
+        false,
+        Hash::new_from_array([7; 32]),
+    );
+    assert_eq!(bundle[0].message.instructions.len(), 1);
+}
@@ -0,0 +1,133 @@
+//! Checks the APDUs sent to the Solana app, and the HID framing that carries
+//! them, against a device that signs with a keypair.
+
+use std::sync::{Arc, Mutex};
+
+use exclusion_client::{
+    instructions::set_smt_root_ix,
+    ledger::{
+        hid_packets, DerivationPath, HidResponse, LedgerSigner, LedgerStatus, Transport,
+        HID_PACKET_SIZE,
+    },
+    offline::OfflineTransaction,
+};
+use exclusion_common::instruction::METADATA_SIZE;
+use solana_hash::Hash;
+use solana_keypair::Keypair;
+use solana_message::{v0, VersionedMessage};
+use solana_pubkey::Pubkey;
+use solana_signer::{Signer, SignerError};
+
+/// A Solana app holding `keypair`, rejecting signatures when `reject` is set
+struct Device {
+    keypair: Keypair,
+    reject: bool,
+    apdus: Arc<Mutex<Vec<Vec<u8>>>>,
+    message: Vec<u8>,
+}
+
+impl Transport for Device {
+    fn exchange(&mut self, apdu: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.apdus.lock().unwrap().push(apdu.to_vec());
+        assert_eq!(apdu[0], 0xe0);
+        assert_eq!(usize::from(apdu[4]), apdu.len() - 5);
+        let (ins, p2, payload) = (apdu[1], apdu[3], &apdu[5..]);
+        let mut response = match ins {
+            0x05 => self.keypair.pubkey().to_bytes().to_vec(),
+            0x06 => {
+                if p2 & 0x01 == 0 {
+                    // One path: count, then 44'/501'/0'/0'
+                    assert_eq!(payload[..2], [1, 4]);
+                    self.message = payload[2 + 16..].to_vec();
+                } else {
+                    self.message.extend_from_slice(payload);
+                }
+                if p2 & 0x02 != 0 {
+                    Vec::new()
+                } else if self.reject {
+                    return Ok(vec![0x69, 0x85]);
+                } else {
+                    self.keypair.sign_message(&self.message).as_ref().to_vec()
+                }
+            }
+            _ => return Ok(vec![0x6d, 0x00]),
+        };
+        response.extend([0x90, 0x00]);
+        Ok(response)
+    }
+}
+
+fn ledger(keypair: Keypair, reject: bool) -> (LedgerSigner, Arc<Mutex<Vec<Vec<u8>>>>) {
+    let apdus = Arc::new(Mutex::new(Vec::new()));
+    let device = Device {
+        keypair,
+        reject,
+        apdus: apdus.clone(),
+        message: Vec::new(),
+    };
+    let path = "0/0".parse().unwrap();
+    (LedgerSigner::new(Box::new(device), path).unwrap(), apdus)
+}
+
+#[test]
+fn signs_set_smt_root_in_chunks() {
+    let keypair = Keypair::new();
+    let admin = keypair.pubkey();
+    let (ledger, apdus) = ledger(keypair, false);
+    assert_eq!(ledger.pubkey(), admin);
+    assert_eq!(ledger.path().to_string(), "m/44'/501'/0'/0'");
+
+    // SET_SMT_ROOT with its metadata is longer than one APDU
+    let program_id = Pubkey::new_unique();
+    let ix = set_smt_root_ix(&program_id, &admin, &[7; 32], 0, &[1; METADATA_SIZE]);
+    let message = v0::Message::try_compile(&admin, &[ix], &[], Hash::new_unique()).unwrap();
+    let mut transaction = OfflineTransaction::new(VersionedMessage::V0(message));
+    transaction.sign(&ledger).unwrap();
+    let transaction = transaction.into_signed().unwrap();
+    assert!(transaction.signatures[0].verify(admin.as_ref(), &transaction.message.serialize()));
+
+    let apdus = apdus.lock().unwrap();
+    // Public key, without confirmation, for 44'/501'/0'/0'
+    assert_eq!(apdus[0][..6], [0xe0, 0x05, 0x00, 0x00, 17, 4]);
+    assert_eq!(apdus[0][6..10], 0x8000_002cu32.to_be_bytes());
+    let sign: Vec<_> = apdus[1..]
+        .iter()
+        .map(|apdu| (apdu[1], apdu[2], apdu[3]))
+        .collect();
+    assert_eq!(sign, [(0x06, 0x01, 0x02), (0x06, 0x01, 0x01)]);
+    assert_eq!(apdus[1].len(), 5 + 255);
+}
+
+#[test]
+fn reports_a_rejection() {
+    let (ledger, _) = ledger(Keypair::new(), true);
+    let error = ledger.sign(b"message").unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<LedgerStatus>(),
+        Some(&LedgerStatus::REJECTED)
+    );
+    assert!(matches!(
+        ledger.try_sign_message(b"message"),
+        Err(SignerError::UserCancel(_))
+    ));
+}
+
+#[test]
+fn frames_apdus_in_hid_reports() {
+    let apdu: Vec<u8> = (0..100).collect();
+    let packets = hid_packets(&apdu);
+    assert_eq!(packets.len(), 2);
+    assert_eq!(packets[0][..7], [0x01, 0x01, 0x05, 0, 0, 0, 100]);
+    assert_eq!(packets[0][7..], apdu[..HID_PACKET_SIZE - 7]);
+    assert_eq!(packets[1][..5], [0x01, 0x01, 0x05, 0, 1]);
+
+    // The device answers in the same framing
+    let mut response = HidResponse::default();
+    assert_eq!(response.push(&packets[0]).unwrap(), None);
+    assert_eq!(response.push(&packets[1]).unwrap(), Some(apdu));
+    assert!(HidResponse::default().push(&packets[1]).is_err());
+
+    let path: DerivationPath = "".parse().unwrap();
+    assert_eq!(path.to_string(), "m/44'/501'");
+    assert!("1/2/3".parse::<DerivationPath>().is_err());
+}
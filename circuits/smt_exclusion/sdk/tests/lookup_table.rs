@@ -0,0 +1,133 @@
+//! Checks lookup table transactions fit, tables decode, and transfers compiled
+//! against one shrink.
+
+use std::{borrow::Cow, collections::HashMap};
+
+use exclusion_client::{
+    compute_budget::{build_sized_transaction, ComputeBudgetOptions},
+    instructions::{transfer_sol_ix, TransferAccounts},
+    lookup_table::{
+        create_lookup_table_ixs, decode_lookup_table, transfer_addresses, MAX_ADDRESSES_PER_EXTEND,
+    },
+};
+use exclusion_common::{instruction::PROOF_SIZE, Proof, PublicWitness};
+use serde_json::json;
+use solana_address_lookup_table_interface::state::{AddressLookupTable, LookupTableMeta};
+use solana_hash::Hash;
+use solana_instruction::Instruction;
+use solana_keypair::Keypair;
+use solana_message::{AddressLookupTableAccount, Message, VersionedMessage};
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::request::RpcRequest;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+/// Largest serialized transaction the network accepts
+const PACKET_DATA_SIZE: usize = 1232;
+
+fn addresses(count: usize) -> Vec<Pubkey> {
+    (0..count).map(|_| Pubkey::new_unique()).collect()
+}
+
+#[test]
+fn creation_fits_transactions() {
+    let authority = Keypair::new();
+    let addresses = addresses(2 * MAX_ADDRESSES_PER_EXTEND + 5);
+    let (transactions, table) =
+        create_lookup_table_ixs(&authority.pubkey(), &authority.pubkey(), 100, &addresses);
+    assert_eq!(transactions.len(), 3);
+    assert_eq!(transactions[0].len(), 2);
+    assert_eq!(transactions[0][0].accounts[0].pubkey, table);
+    for instructions in &transactions {
+        let message = Message::new(instructions, Some(&authority.pubkey()));
+        let transaction = Transaction::new(&[&authority], message, Hash::default());
+        let size = bincode::serialize(&transaction).unwrap().len();
+        assert!(size <= PACKET_DATA_SIZE, "{size}-byte transaction");
+    }
+
+    // An empty table is just created
+    let (transactions, _) =
+        create_lookup_table_ixs(&authority.pubkey(), &authority.pubkey(), 100, &[]);
+    assert_eq!(transactions.len(), 1);
+    assert_eq!(transactions[0].len(), 1);
+}
+
+#[test]
+fn decodes_tables() {
+    let addresses = addresses(3);
+    let table = AddressLookupTable {
+        meta: LookupTableMeta::new(Pubkey::new_unique()),
+        addresses: Cow::Borrowed(&addresses),
+    };
+    let key = Pubkey::new_unique();
+    let decoded = decode_lookup_table(&key, &table.serialize_for_tests().unwrap()).unwrap();
+    assert_eq!(decoded.key, key);
+    assert_eq!(decoded.addresses, addresses);
+    assert!(decode_lookup_table(&key, &[0; 4]).is_err());
+}
+
+/// The serialized size of `ix` sized into a v0 transaction against `tables`,
+/// and how many tables it looks accounts up in
+async fn compiled(
+    sender: &Keypair,
+    ix: Instruction,
+    tables: &[AddressLookupTableAccount],
+) -> (usize, usize) {
+    let mut mocks = HashMap::new();
+    mocks.insert(
+        RpcRequest::SimulateTransaction,
+        json!({
+            "context": { "slot": 1 },
+            "value": { "err": null, "logs": [], "unitsConsumed": 300_000 },
+        }),
+    );
+    mocks.insert(RpcRequest::GetRecentPrioritizationFees, json!([]));
+    let rpc = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+    let sized = build_sized_transaction(
+        &rpc,
+        sender,
+        &[ix],
+        tables,
+        &ComputeBudgetOptions::default(),
+    )
+    .await
+    .unwrap();
+    let VersionedMessage::V0(message) = &sized.transaction.message else {
+        panic!("not a v0 message");
+    };
+    (
+        bincode::serialize(&sized.transaction).unwrap().len(),
+        message.address_table_lookups.len(),
+    )
+}
+
+#[tokio::test]
+async fn transfers_name_table_accounts_by_index() {
+    let sender = Keypair::new();
+    let accounts = TransferAccounts {
+        program_id: Pubkey::new_unique(),
+        sender: sender.pubkey(),
+        recipient: Pubkey::new_unique(),
+        state: Pubkey::new_unique(),
+        verifier: Pubkey::new_unique(),
+    };
+    let ix = transfer_sol_ix(
+        &accounts,
+        1_000,
+        &Proof([1; PROOF_SIZE]),
+        &PublicWitness::new(&[2; 32], &[3; 32], 4),
+    );
+    let table = AddressLookupTableAccount {
+        key: Pubkey::new_unique(),
+        addresses: transfer_addresses(&accounts),
+    };
+
+    let (full, no_lookups) = compiled(&sender, ix.clone(), &[]).await;
+    let (with_table, lookups) = compiled(&sender, ix, &[table]).await;
+    assert_eq!((no_lookups, lookups), (0, 1));
+    // Each address in the table takes an index in place of 32 bytes; the
+    // table's own address and count bytes take some back
+    assert!(with_table < full, "{with_table} >= {full}");
+    assert!(with_table <= PACKET_DATA_SIZE);
+}
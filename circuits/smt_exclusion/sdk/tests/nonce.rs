@@ -0,0 +1,121 @@
+//! Checks nonce accounts decode, and that transactions compiled against one
+//! advance it first and carry its blockhash.
+
+use std::collections::HashMap;
+
+use exclusion_client::{
+    compute_budget::{build_nonced_transaction, ComputeBudgetOptions},
+    nonce::{decode_nonce_account, NonceAccount},
+    offline::OfflineTransaction,
+};
+use serde_json::json;
+use solana_hash::Hash;
+use solana_keypair::Keypair;
+use solana_nonce::{
+    state::{DurableNonce, State},
+    versions::Versions,
+};
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::request::RpcRequest;
+use solana_signer::Signer;
+
+#[test]
+fn decodes_a_nonce_account() {
+    let (address, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let durable_nonce = DurableNonce::from_blockhash(&Hash::new_unique());
+    let data = bincode::serialize(&Versions::new(State::new_initialized(
+        &authority,
+        durable_nonce,
+        5_000,
+    )))
+    .unwrap();
+    assert_eq!(data.len(), State::size());
+    assert_eq!(
+        decode_nonce_account(&address, &data).unwrap(),
+        NonceAccount {
+            address,
+            authority,
+            blockhash: *durable_nonce.as_hash(),
+            lamports_per_signature: 5_000,
+        }
+    );
+
+    let uninitialized = bincode::serialize(&Versions::new(State::Uninitialized)).unwrap();
+    let error = decode_nonce_account(&address, &uninitialized).unwrap_err();
+    assert!(error.to_string().contains("not initialized"), "{error}");
+}
+
+#[tokio::test]
+async fn compiles_against_the_nonce() {
+    let mut mocks = HashMap::new();
+    mocks.insert(
+        RpcRequest::SimulateTransaction,
+        json!({
+            "context": { "slot": 1 },
+            "value": { "err": null, "logs": [], "unitsConsumed": 200_000 },
+        }),
+    );
+    mocks.insert(RpcRequest::GetRecentPrioritizationFees, json!([]));
+    let rpc = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+    let (payer, authority) = (Keypair::new(), Keypair::new());
+    let nonce = NonceAccount {
+        address: Pubkey::new_unique(),
+        authority: authority.pubkey(),
+        blockhash: Hash::new_unique(),
+        lamports_per_signature: 5_000,
+    };
+    let ix =
+        solana_system_interface::instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+
+    let sized = build_nonced_transaction(
+        &rpc,
+        &payer,
+        &authority,
+        &nonce,
+        std::slice::from_ref(&ix),
+        &[],
+        &ComputeBudgetOptions::default(),
+    )
+    .await
+    .unwrap();
+    let message = &sized.transaction.message;
+    assert_eq!(*message.recent_blockhash(), nonce.blockhash);
+    let keys = message.static_account_keys();
+    let first = &message.instructions()[0];
+    assert_eq!(
+        keys[usize::from(first.program_id_index)],
+        solana_sdk_ids::system_program::ID
+    );
+    assert_eq!(first.data, nonce.advance_ix().data);
+    assert_eq!(keys[usize::from(first.accounts[0])], nonce.address);
+
+    let bytes = message.serialize();
+    assert_eq!(sized.transaction.signatures.len(), 2);
+    for (key, signature) in keys.iter().zip(&sized.transaction.signatures) {
+        assert!(signature.verify(key.as_ref(), &bytes));
+    }
+
+    // An offline signer is shown the nonce being advanced
+    let offline = OfflineTransaction::new(message.clone());
+    let advance = format!(
+        "Advance nonce account {}, authority {}",
+        nonce.address, nonce.authority
+    );
+    assert_eq!(offline.describe(&Pubkey::new_unique())[2], advance);
+
+    // The nonce's authority must sign
+    let error = build_nonced_transaction(
+        &rpc,
+        &payer,
+        &payer,
+        &nonce,
+        &[ix],
+        &[],
+        &ComputeBudgetOptions::default(),
+    )
+    .await
+    .err()
+    .unwrap();
+    assert!(error.to_string().contains("authority"), "{error}");
+}
@@ -0,0 +1,85 @@
+//! Checks a transaction survives the round trip to an offline signer, and
+//! that the signer is shown what it signs.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use exclusion_client::{
+    instructions::{derive_state_pda, transfer_sol_ix, TransferAccounts},
+    offline::{OfflineTransaction, FORMAT_VERSION},
+};
+use exclusion_common::{instruction::PROOF_SIZE, Proof, PublicWitness};
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_hash::Hash;
+use solana_keypair::Keypair;
+use solana_message::{v0, VersionedMessage};
+use solana_pubkey::Pubkey;
+use solana_signer::Signer;
+
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+fn transfer(sender: &Pubkey, recipient: &Pubkey) -> OfflineTransaction {
+    let accounts = TransferAccounts {
+        program_id: PROGRAM_ID,
+        sender: *sender,
+        recipient: *recipient,
+        state: derive_state_pda(&PROGRAM_ID, sender).0,
+        verifier: Pubkey::new_from_array([10; 32]),
+    };
+    let instructions = [
+        ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+        transfer_sol_ix(
+            &accounts,
+            5_000,
+            &Proof([4; PROOF_SIZE]),
+            &PublicWitness::new(&[5; 32], &[6; 32], 77),
+        ),
+    ];
+    let message = v0::Message::try_compile(sender, &instructions, &[], Hash::new_unique()).unwrap();
+    OfflineTransaction::new(VersionedMessage::V0(message))
+}
+
+#[test]
+fn signs_offline_and_round_trips() {
+    let sender = Keypair::new();
+    let unsigned = transfer(&sender.pubkey(), &Pubkey::new_unique());
+    assert_eq!(unsigned.missing(), [sender.pubkey()]);
+    assert!(unsigned.clone().into_signed().is_err());
+
+    // The file goes to the signer and comes back
+    let mut signing = OfflineTransaction::from_json(&unsigned.to_json().unwrap()).unwrap();
+    assert_eq!(signing, unsigned);
+    assert!(signing.sign(&Keypair::new()).is_err());
+    signing.sign(&sender).unwrap();
+    let signed = OfflineTransaction::from_json(&signing.to_json().unwrap()).unwrap();
+    assert!(signed.missing().is_empty());
+    let transaction = signed.into_signed().unwrap();
+    assert!(transaction.signatures[0]
+        .verify(sender.pubkey().as_ref(), &transaction.message.serialize()));
+
+    // A message changed after it was signed is caught
+    let mut tampered = transaction;
+    let VersionedMessage::V0(message) = &mut tampered.message else {
+        unreachable!()
+    };
+    message.recent_blockhash = Hash::new_unique();
+    let file = serde_json::json!({
+        "version": FORMAT_VERSION,
+        "transaction": STANDARD.encode(bincode::serialize(&tampered).unwrap()),
+    });
+    let error = OfflineTransaction::from_json(&file.to_string()).unwrap_err();
+    assert!(error.to_string().contains("doesn't match the message"));
+}
+
+#[test]
+fn describes_the_transfer() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let lines = transfer(&sender, &recipient).describe(&PROGRAM_ID);
+    assert_eq!(lines[0], format!("Fee payer: {sender}"));
+    assert!(lines[1].starts_with("Blockhash: "));
+    assert_eq!(lines[2], "Compute unit limit: 300000");
+    assert_eq!(
+        lines[3],
+        format!("TRANSFER_SOL: 5000 lamports from {sender} to {recipient}")
+    );
+    assert_eq!(lines.len(), 4);
+}
@@ -0,0 +1,260 @@
+//! Checks proofs are validated locally before they're sent.
+//!
+//! No sunspot key is checked in, so these build a gnark-encoded key and
+//! proof from known scalars that satisfy the verifier's pairings.
+
+use ark_bn254::{Fq, Fr, G1Affine, G2Affine};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{BigInteger, Field, PrimeField};
+use exclusion_client::{
+    groth16::{hash_to_field, VerifyingKey},
+    prevalidate::check_transfer,
+    state::ExclusionState,
+};
+use exclusion_common::{
+    instruction::METADATA_SIZE,
+    pubkey_to_index,
+    state::{verifier_flags, MAX_APPROVED_CALLERS},
+    ExclusionError, Proof, PublicWitness, State,
+};
+use solana_pubkey::Pubkey;
+
+const SENDER: Pubkey = Pubkey::new_from_array([9; 32]);
+const ROOT: [u8; 32] = [1; 32];
+
+fn be(element: Fq) -> Vec<u8> {
+    element.into_bigint().to_bytes_be()
+}
+
+fn g1_raw(point: G1Affine) -> Vec<u8> {
+    [be(point.x), be(point.y)].concat()
+}
+
+fn g2_raw(point: G2Affine) -> Vec<u8> {
+    [
+        be(point.x.c1),
+        be(point.x.c0),
+        be(point.y.c1),
+        be(point.y.c0),
+    ]
+    .concat()
+}
+
+fn largest(y: Fq) -> bool {
+    y.into_bigint() > Fq::MODULUS_MINUS_ONE_DIV_TWO
+}
+
+fn with_flag(mut bytes: Vec<u8>, largest: bool) -> Vec<u8> {
+    bytes[0] |= if largest { 0b11 << 6 } else { 0b10 << 6 };
+    bytes
+}
+
+fn g1_compressed(point: G1Affine) -> Vec<u8> {
+    with_flag(be(point.x), largest(point.y))
+}
+
+fn g2_compressed(point: G2Affine) -> Vec<u8> {
+    let y = if point.y.c1 == Fq::from(0u64) {
+        point.y.c0
+    } else {
+        point.y.c1
+    };
+    with_flag([be(point.x.c1), be(point.x.c0)].concat(), largest(y))
+}
+
+fn g1(scalar: Fr) -> G1Affine {
+    (G1Affine::generator() * scalar).into_affine()
+}
+
+fn g2(scalar: Fr) -> G2Affine {
+    (G2Affine::generator() * scalar).into_affine()
+}
+
+fn witness_inputs(witness: &PublicWitness) -> Vec<Fr> {
+    witness.as_bytes()[12..]
+        .chunks(32)
+        .map(Fr::from_be_bytes_mod_order)
+        .collect()
+}
+
+/// A verifying key, encoded raw and compressed, and a proof of `witness`
+/// under it
+fn key_and_proof(witness: &PublicWitness) -> (Vec<u8>, Vec<u8>, Proof) {
+    let s = |n: u64| Fr::from(n);
+    let (alpha, beta, gamma, delta) = (s(11), s(13), s(17), s(19));
+    let k: Vec<Fr> = (0..5).map(|i| s(23 + i)).collect();
+    let (commitment, sigma, t) = (s(31), s(37), s(41));
+    let (a, b) = (s(43), s(47));
+
+    let commitment_point = g1(commitment);
+    let hash = hash_to_field(&g1_raw(commitment_point), b"bsb22-commitment");
+    let mut x = k[0] + commitment;
+    for (k, input) in k[1..]
+        .iter()
+        .zip(witness_inputs(witness).into_iter().chain([hash]))
+    {
+        x += *k * input;
+    }
+    let r = (a * b - alpha * beta - x * gamma) * delta.inverse().unwrap();
+
+    let encode = |g1e: &dyn Fn(G1Affine) -> Vec<u8>, g2e: &dyn Fn(G2Affine) -> Vec<u8>| {
+        let mut vk = [
+            g1e(g1(alpha)),
+            g1e(g1(beta)),
+            g2e(g2(beta)),
+            g2e(g2(gamma)),
+            g1e(g1(delta)),
+            g2e(g2(delta)),
+        ]
+        .concat();
+        vk.extend_from_slice(&5u32.to_be_bytes());
+        for k in &k {
+            vk.extend(g1e(g1(*k)));
+        }
+        // One commitment, to no public inputs
+        vk.extend_from_slice(&1u32.to_be_bytes());
+        vk.extend_from_slice(&0u32.to_be_bytes());
+        vk.extend_from_slice(&1u32.to_be_bytes());
+        vk.extend(g2e(g2(t)));
+        vk.extend(g2e(g2(-sigma * t)));
+        vk
+    };
+    let raw = encode(&g1_raw, &g2_raw);
+    let compressed = encode(&g1_compressed, &g2_compressed);
+
+    let proof = [
+        g1_raw(g1(a)),
+        g2_raw(g2(b)),
+        g1_raw(g1(r)),
+        1u32.to_be_bytes().to_vec(),
+        g1_raw(commitment_point),
+        g1_raw(g1(sigma * commitment)),
+    ]
+    .concat();
+    (raw, compressed, Proof::try_from(&proof[..]).unwrap())
+}
+
+fn witness() -> PublicWitness {
+    PublicWitness::new(&ROOT, &pubkey_to_index(&SENDER.to_bytes()), 300)
+}
+
+#[test]
+fn verifies_sunspot_proofs() {
+    let witness = witness();
+    let (raw, compressed, proof) = key_and_proof(&witness);
+    let vk = VerifyingKey::from_gnark_bytes(&raw).unwrap();
+    assert_eq!(VerifyingKey::from_gnark_bytes(&compressed).unwrap(), vk);
+    assert_eq!(vk.verify(&proof, &witness), Ok(()));
+
+    let other_slot = PublicWitness::new(&ROOT, &pubkey_to_index(&SENDER.to_bytes()), 301);
+    assert_eq!(
+        vk.verify(&proof, &other_slot),
+        Err(ExclusionError::ZkVerificationFailed)
+    );
+    // A proof of knowledge for another commitment
+    let mut forged = proof;
+    forged.0[324..388].copy_from_slice(&g1_raw(g1(Fr::from(5u64))));
+    assert_eq!(
+        vk.verify(&forged, &witness),
+        Err(ExclusionError::ZkVerificationFailed)
+    );
+    // Not a point
+    let mut garbled = proof;
+    garbled.0[0] ^= 1;
+    assert_eq!(
+        vk.verify(&garbled, &witness),
+        Err(ExclusionError::ZkVerificationFailed)
+    );
+}
+
+#[test]
+fn rejects_malformed_keys() {
+    let (raw, _, _) = key_and_proof(&witness());
+    assert!(VerifyingKey::from_gnark_bytes(&raw[..raw.len() - 1]).is_err());
+    assert!(VerifyingKey::from_gnark_bytes(&[raw.clone(), vec![0]].concat()).is_err());
+    let mut off_curve = raw;
+    off_curve[63] ^= 1;
+    assert!(VerifyingKey::from_gnark_bytes(&off_curve).is_err());
+}
+
+fn state(verifier_flags: u8) -> ExclusionState {
+    let state = State {
+        admin: [8; 32],
+        smt_root: ROOT,
+        root_valid_until: 0,
+        transfer_flags: 0,
+        daily_limit: 0,
+        fee_bps: 0,
+        root_seq: 1,
+        approved_callers: [[0; 32]; MAX_APPROVED_CALLERS],
+        approved_caller_count: 0,
+        bump: 255,
+        treasury_bump: 255,
+        hash_scheme: 0,
+        verifier_flags,
+        metadata: [0; METADATA_SIZE],
+        challenged: false,
+        bond_bump: 255,
+        bond_amount: 0,
+        bond_withdrawal_amount: 0,
+        bond_withdrawal_at: 0,
+        config: [0; 32],
+        tree_kind: 0,
+        tree_depth: 254,
+    };
+    ExclusionState {
+        address: Pubkey::new_unique(),
+        admin: Pubkey::new_from_array(state.admin),
+        smt_root: state.smt_root,
+        slot: 42,
+        state,
+    }
+}
+
+#[test]
+fn checks_root_sender_and_proof() {
+    let witness = witness();
+    let (raw, _, proof) = key_and_proof(&witness);
+    let vk = VerifyingKey::from_gnark_bytes(&raw).unwrap();
+    let state = state(0);
+
+    assert_eq!(
+        check_transfer(&state, &SENDER, &proof, &witness, Some(&vk)),
+        Ok(())
+    );
+    let stale = PublicWitness::new(&[2; 32], &pubkey_to_index(&SENDER.to_bytes()), 300);
+    assert_eq!(
+        check_transfer(&state, &SENDER, &proof, &stale, None),
+        Err(ExclusionError::SmtRootMismatch)
+    );
+    assert_eq!(
+        check_transfer(&state, &Pubkey::new_unique(), &proof, &witness, None),
+        Err(ExclusionError::PubkeyHashMismatch)
+    );
+    let other_slot = PublicWitness::new(&ROOT, &pubkey_to_index(&SENDER.to_bytes()), 301);
+    assert_eq!(
+        check_transfer(&state, &SENDER, &proof, &other_slot, Some(&vk)),
+        Err(ExclusionError::ZkVerificationFailed)
+    );
+    assert_eq!(
+        check_transfer(&state, &SENDER, &proof, &other_slot, None),
+        Ok(())
+    );
+}
+
+#[test]
+fn reads_little_endian_witnesses() {
+    let mut root = ROOT;
+    root[0] = 3;
+    let mut hash = pubkey_to_index(&SENDER.to_bytes());
+    hash.reverse();
+    let mut state = state(verifier_flags::WITNESS_LITTLE_ENDIAN);
+    state.smt_root = root;
+    root.reverse();
+    let witness = PublicWitness::new(&root, &hash, 300);
+    let (_, _, proof) = key_and_proof(&witness);
+    assert_eq!(
+        check_transfer(&state, &SENDER, &proof, &witness, None),
+        Ok(())
+    );
+}
@@ -0,0 +1,95 @@
+//! Checks the prover drives the toolchain and reads back what it wrote.
+//!
+//! `nargo` and `sunspot` are stand-in scripts: nargo checks `Prover.toml` was
+//! written, and sunspot copies a prepared proof and witness into `target/`.
+
+use std::{fs, os::unix::fs::PermissionsExt, path::Path};
+
+use exclusion_client::{
+    prover::{generate_exclusion_proof, Prover},
+    witness::ExclusionWitness,
+};
+use smt::SparseMerkleTree;
+use solana_pubkey::Pubkey;
+
+fn script(path: &Path, body: &str) {
+    fs::write(path, format!("#!/bin/sh\nset -e\n{body}\n")).unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+/// A circuit directory whose sunspot "proves" `witness`
+fn circuit_dir(witness: &[u8]) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("target")).unwrap();
+    fs::write(dir.path().join("prepared.proof"), [7u8; 388]).unwrap();
+    fs::write(dir.path().join("prepared.pw"), witness).unwrap();
+    script(
+        &dir.path().join("nargo"),
+        r#"[ "$1" = execute ] && grep -q recent_slot Prover.toml"#,
+    );
+    script(
+        &dir.path().join("sunspot"),
+        "[ \"$1\" = prove ]\n\
+         cp prepared.proof target/smt_exclusion.proof\n\
+         cp prepared.pw target/smt_exclusion.pw",
+    );
+    dir
+}
+
+fn prover(dir: &tempfile::TempDir) -> Prover {
+    Prover::new(dir.path()).with_binaries(dir.path().join("nargo"), dir.path().join("sunspot"))
+}
+
+#[tokio::test]
+async fn proves_with_nargo_and_sunspot() {
+    let mut tree = SparseMerkleTree::new();
+    tree.insert(&Pubkey::new_unique().to_bytes()).unwrap();
+    let sender = Pubkey::new_unique();
+    let expected = ExclusionWitness::for_pubkey(tree.root(), &sender, 300);
+    let dir = circuit_dir(expected.to_gnark_bytes().as_bytes());
+
+    let proof = generate_exclusion_proof(&prover(&dir), &tree, &sender, 300)
+        .await
+        .unwrap();
+    assert_eq!(proof.inputs, expected);
+    assert_eq!(proof.witness, expected.to_gnark_bytes());
+    assert_eq!(proof.proof.as_bytes(), &[7u8; 388]);
+    assert!(fs::read_to_string(dir.path().join("Prover.toml"))
+        .unwrap()
+        .contains("recent_slot"));
+
+    // The witness sunspot wrote is for another slot
+    let error = generate_exclusion_proof(&prover(&dir), &tree, &sender, 301)
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("not for these inputs"), "{error}");
+}
+
+#[tokio::test]
+async fn refuses_blacklisted_keys() {
+    let mut tree = SparseMerkleTree::new();
+    let blacklisted = Pubkey::new_unique();
+    tree.insert(&blacklisted.to_bytes()).unwrap();
+    let dir = circuit_dir(&[]);
+    let error = generate_exclusion_proof(&prover(&dir), &tree, &blacklisted, 300)
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("blacklisted"), "{error}");
+}
+
+#[tokio::test]
+async fn reports_toolchain_failures() {
+    let tree = SparseMerkleTree::new();
+    let dir = circuit_dir(&[]);
+    script(
+        &dir.path().join("nargo"),
+        "echo 'Failed constraint' >&2; exit 1",
+    );
+    let error = generate_exclusion_proof(&prover(&dir), &tree, &Pubkey::new_unique(), 300)
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("Failed constraint"), "{error}");
+}
@@ -0,0 +1,237 @@
+//! Checks account subscriptions against a local websocket server: the
+//! subscribe request, pings answered, and notifications decoded, into state
+//! accounts for `subscribe_state`.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use exclusion_client::{
+    instructions::derive_state_pda,
+    pubsub::{subscribe_state, websocket_url, AccountSubscription, AccountUpdate},
+};
+use exclusion_common::{
+    instruction::METADATA_SIZE,
+    state::{MAX_APPROVED_CALLERS, STATE_SIZE},
+    State,
+};
+use futures::StreamExt;
+use serde_json::{json, Value};
+use solana_commitment_config::CommitmentConfig;
+use solana_pubkey::Pubkey;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// An unmasked server frame, split into `fragments` frames
+fn frames(opcode: u8, payload: &[u8], fragments: usize) -> Vec<u8> {
+    let size = payload.len().div_ceil(fragments).max(1);
+    let mut chunks: Vec<&[u8]> = payload.chunks(size).collect();
+    if chunks.is_empty() {
+        chunks.push(&[]);
+    }
+    let mut bytes = Vec::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let fin = if i == chunks.len() - 1 { 0x80 } else { 0 };
+        bytes.push(fin | if i == 0 { opcode } else { 0 });
+        if chunk.len() < 126 {
+            bytes.push(chunk.len() as u8);
+        } else {
+            bytes.push(126);
+            bytes.extend_from_slice(&(chunk.len() as u16).to_be_bytes());
+        }
+        bytes.extend_from_slice(chunk);
+    }
+    bytes
+}
+
+/// Read one masked client frame: its opcode and unmasked payload
+async fn read_frame(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header).await.unwrap();
+    assert_ne!(header[1] & 0x80, 0, "client frames are masked");
+    let len = match header[1] & 0x7f {
+        126 => stream.read_u16().await.unwrap() as usize,
+        len => len as usize,
+    };
+    let mut mask = [0u8; 4];
+    stream.read_exact(&mut mask).await.unwrap();
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).await.unwrap();
+    for (byte, m) in payload.iter_mut().zip(mask.iter().cycle()) {
+        *byte ^= m;
+    }
+    (header[0] & 0x0f, payload)
+}
+
+/// Accept the client's connection, upgrade it, and answer its subscription
+/// to `account` with `subscription`
+async fn accept(listener: &TcpListener, account: &Pubkey, subscription: u64) -> TcpStream {
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let mut request = Vec::new();
+    while !request.ends_with(b"\r\n\r\n") {
+        request.push(stream.read_u8().await.unwrap());
+    }
+    let request = String::from_utf8(request).unwrap().to_ascii_lowercase();
+    assert!(request.contains("upgrade: websocket"), "{request}");
+    assert!(request.contains("sec-websocket-key: "), "{request}");
+    stream
+        .write_all(b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\r\n")
+        .await
+        .unwrap();
+
+    let (opcode, payload) = read_frame(&mut stream).await;
+    assert_eq!(opcode, 1);
+    let subscribe: Value = serde_json::from_slice(&payload).unwrap();
+    assert_eq!(subscribe["method"], "accountSubscribe");
+    assert_eq!(subscribe["params"][0], account.to_string());
+    assert_eq!(subscribe["params"][1]["encoding"], "base64");
+    assert_eq!(subscribe["params"][1]["commitment"], "confirmed");
+    let result = json!({ "jsonrpc": "2.0", "id": subscribe["id"], "result": subscription });
+    stream
+        .write_all(&frames(1, result.to_string().as_bytes(), 1))
+        .await
+        .unwrap();
+    stream
+}
+
+fn notification(subscription: u64, slot: u64, data: &[u8]) -> Vec<u8> {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "accountNotification",
+        "params": {
+            "subscription": subscription,
+            "result": {
+                "context": { "slot": slot },
+                "value": { "data": [STANDARD.encode(data), "base64"], "lamports": 1 },
+            },
+        },
+    })
+    .to_string()
+    .into_bytes()
+}
+
+#[tokio::test]
+async fn streams_account_changes() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let account = Pubkey::new_unique();
+    let server = tokio::spawn(async move {
+        let mut stream = accept(&listener, &account, 7).await;
+
+        // Another subscription's notification, a ping, then ours in pieces
+        stream
+            .write_all(&frames(1, &notification(8, 1, b"other"), 1))
+            .await
+            .unwrap();
+        stream.write_all(&frames(9, b"hi", 1)).await.unwrap();
+        let (opcode, payload) = read_frame(&mut stream).await;
+        assert_eq!((opcode, &payload[..]), (0xa, &b"hi"[..]));
+        stream
+            .write_all(&frames(1, &notification(7, 10, &[5; 300]), 3))
+            .await
+            .unwrap();
+        stream
+            .write_all(&frames(1, &notification(7, 11, &[]), 1))
+            .await
+            .unwrap();
+        stream.write_all(&frames(8, &[], 1)).await.unwrap();
+    });
+
+    let mut subscription =
+        AccountSubscription::subscribe(&url, &account, CommitmentConfig::confirmed())
+            .await
+            .unwrap();
+    assert_eq!(
+        subscription.next().await.unwrap(),
+        AccountUpdate {
+            slot: 10,
+            data: vec![5; 300]
+        }
+    );
+    assert_eq!(subscription.next().await.unwrap().slot, 11);
+    let error = subscription.next().await.unwrap_err().to_string();
+    assert!(error.contains("closed"), "{error}");
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn streams_decoded_states() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let (program_id, admin) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (address, _) = derive_state_pda(&program_id, &admin);
+    let state = |smt_root, root_seq| {
+        let mut data = vec![0; STATE_SIZE];
+        State {
+            admin: admin.to_bytes(),
+            smt_root,
+            root_valid_until: 0,
+            transfer_flags: 0,
+            daily_limit: 0,
+            fee_bps: 0,
+            root_seq,
+            approved_callers: [[0; 32]; MAX_APPROVED_CALLERS],
+            approved_caller_count: 0,
+            bump: 255,
+            treasury_bump: 255,
+            hash_scheme: 0,
+            verifier_flags: 0,
+            metadata: [0; METADATA_SIZE],
+            challenged: false,
+            bond_bump: 255,
+            bond_amount: 0,
+            bond_withdrawal_amount: 0,
+            bond_withdrawal_at: 0,
+            config: [0; 32],
+            tree_kind: 0,
+            tree_depth: 254,
+        }
+        .pack(&mut data);
+        data
+    };
+    let (first, second) = (state([1; 32], 1), state([2; 32], 2));
+    let server = tokio::spawn(async move {
+        let mut stream = accept(&listener, &address, 3).await;
+        for (slot, data) in [(10, &first[..]), (11, &second[..]), (12, &[])] {
+            stream
+                .write_all(&frames(1, &notification(3, slot, data), 1))
+                .await
+                .unwrap();
+        }
+    });
+
+    let states = subscribe_state(&url, &program_id, &admin).await.unwrap();
+    let states: Vec<_> = states.collect().await;
+    server.await.unwrap();
+    assert_eq!(states.len(), 3);
+    let roots: Vec<_> = states[..2]
+        .iter()
+        .map(|state| {
+            let state = state.as_ref().unwrap();
+            assert_eq!((state.address, state.admin), (address, admin));
+            (state.slot, state.smt_root, state.state.root_seq)
+        })
+        .collect();
+    assert_eq!(roots, [(10, [1; 32], 1), (11, [2; 32], 2)]);
+    // A closed account ends the stream
+    let error = states[2].as_ref().unwrap_err().to_string();
+    assert!(error.contains("closed"), "{error}");
+}
+
+#[test]
+fn derives_websocket_urls() {
+    for (rpc, ws) in [
+        (
+            "https://api.devnet.solana.com",
+            "wss://api.devnet.solana.com/",
+        ),
+        ("http://localhost:8899", "ws://localhost:8900/"),
+        (
+            "https://rpc.example.com:8443/key",
+            "wss://rpc.example.com:8444/key",
+        ),
+        ("wss://rpc.example.com:8443/", "wss://rpc.example.com:8443/"),
+    ] {
+        assert_eq!(websocket_url(rpc).unwrap(), ws);
+    }
+    assert!(websocket_url("ftp://example.com").is_err());
+}
@@ -0,0 +1,312 @@
+//! Checks failed transactions decode into the program's errors, and that
+//! transactions are rebuilt and resent until one lands.
+
+use std::{collections::HashMap, time::Duration};
+
+use exclusion_client::send::{
+    decode_failure, send_and_confirm, send_checked, SendError, SendOptions,
+};
+use exclusion_common::ExclusionError;
+use serde_json::json;
+use solana_hash::Hash;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_keypair::Keypair;
+use solana_message::Message;
+use solana_pubkey::Pubkey;
+use solana_rpc_client::{mock_sender::MocksMap, nonblocking::rpc_client::RpcClient};
+use solana_rpc_client_api::request::RpcRequest;
+use solana_signer::Signer;
+use solana_transaction::{
+    versioned::VersionedTransaction, InstructionError, Transaction, TransactionError,
+};
+
+/// A transfer of 1 lamport, then a call to `program_id`, signed by `payer`
+fn transaction(payer: &Keypair, program_id: &Pubkey) -> VersionedTransaction {
+    let instructions = [
+        solana_system_interface::instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1),
+        Instruction::new_with_bytes(
+            *program_id,
+            &[0],
+            vec![AccountMeta::new(payer.pubkey(), true)],
+        ),
+    ];
+    let message = Message::new(&instructions, Some(&payer.pubkey()));
+    Transaction::new(&[payer], message, Default::default()).into()
+}
+
+fn logs(lines: &[&str]) -> Vec<String> {
+    lines.iter().map(|line| line.to_string()).collect()
+}
+
+#[test]
+fn program_codes_decode() {
+    let program_id = Pubkey::new_unique();
+    let tx = transaction(&Keypair::new(), &program_id);
+    let error = decode_failure(
+        &program_id,
+        &tx,
+        TransactionError::InstructionError(1, InstructionError::Custom(2)),
+        logs(&[
+            &format!("Program {program_id} invoke [1]"),
+            &format!("Program {program_id} failed: custom program error: 0x2"),
+        ]),
+    );
+    assert!(matches!(
+        error,
+        SendError::Program {
+            instruction: 1,
+            error: ExclusionError::SmtRootMismatch,
+            ..
+        }
+    ));
+    let message = error.to_string();
+    assert!(message.contains("SmtRootMismatch: "), "{message}");
+    assert!(message.contains("stale root"), "{message}");
+}
+
+#[test]
+fn verifier_failures_decode() {
+    let program_id = Pubkey::new_unique();
+    let verifier = Pubkey::new_unique();
+    let tx = transaction(&Keypair::new(), &program_id);
+    // The verifier's code is not the program's SmtRootMismatch
+    let error = decode_failure(
+        &program_id,
+        &tx,
+        TransactionError::InstructionError(1, InstructionError::Custom(2)),
+        logs(&[
+            &format!("Program {program_id} invoke [1]"),
+            &format!("Program {verifier} invoke [2]"),
+            "Program log: pairing check failed",
+            &format!("Program {verifier} failed: custom program error: 0x2"),
+            &format!("Program {program_id} failed: custom program error: 0x2"),
+        ]),
+    );
+    let SendError::Cpi {
+        instruction,
+        program,
+        reason,
+        log,
+        ..
+    } = &error
+    else {
+        panic!("{error:?}");
+    };
+    assert_eq!(*instruction, 1);
+    assert_eq!(*program, verifier);
+    assert_eq!(reason, "custom program error: 0x2");
+    assert_eq!(log.as_deref(), Some("pairing check failed"));
+}
+
+#[test]
+fn other_failures_stay_transaction_errors() {
+    let program_id = Pubkey::new_unique();
+    let tx = transaction(&Keypair::new(), &program_id);
+    for error in [
+        // The system transfer, not the program, failed
+        TransactionError::InstructionError(0, InstructionError::Custom(1)),
+        // Not one of the program's codes
+        TransactionError::InstructionError(1, InstructionError::Custom(1000)),
+        TransactionError::InstructionError(1, InstructionError::MissingRequiredSignature),
+        TransactionError::BlockhashNotFound,
+    ] {
+        let decoded = decode_failure(&program_id, &tx, error.clone(), Vec::new());
+        assert!(
+            matches!(&decoded, SendError::Transaction { error: e, .. } if *e == error),
+            "{decoded:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn failing_simulations_are_not_sent() {
+    let program_id = Pubkey::new_unique();
+    let mut mocks = HashMap::new();
+    mocks.insert(
+        RpcRequest::SimulateTransaction,
+        json!({
+            "context": { "slot": 1 },
+            "value": {
+                "err": { "InstructionError": [1, { "Custom": 11 }] },
+                "logs": [format!("Program {program_id} failed: custom program error: 0xb")],
+            },
+        }),
+    );
+    let rpc = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+    let error = send_checked(
+        &rpc,
+        &program_id,
+        &transaction(&Keypair::new(), &program_id),
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        matches!(
+            error,
+            SendError::Program {
+                error: ExclusionError::RootExpired,
+                ..
+            }
+        ),
+        "{error:?}"
+    );
+    assert_eq!(error.logs().len(), 1);
+}
+
+#[tokio::test]
+async fn sends_after_simulating() {
+    let program_id = Pubkey::new_unique();
+    let tx = transaction(&Keypair::new(), &program_id);
+    let rpc = RpcClient::new_mock("succeeds".to_string());
+    let signature = send_checked(&rpc, &program_id, &tx).await.unwrap();
+    assert_eq!(signature, tx.signatures[0]);
+
+    // Fails on chain after a clean simulation
+    let rpc = RpcClient::new_mock("instruction_error".to_string());
+    let error = send_checked(&rpc, &program_id, &tx).await.unwrap_err();
+    assert!(
+        matches!(
+            error,
+            SendError::Transaction {
+                error: TransactionError::InstructionError(
+                    0,
+                    InstructionError::UninitializedAccount
+                ),
+                ..
+            }
+        ),
+        "{error:?}"
+    );
+}
+
+fn fast() -> SendOptions {
+    SendOptions {
+        attempts: 3,
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(1),
+        poll_interval: Duration::from_millis(1),
+    }
+}
+
+/// Transactions as `send_and_confirm`'s `build` would make them, each with
+/// its own blockhash
+fn rebuilt(program_id: &Pubkey, count: usize) -> Vec<VersionedTransaction> {
+    let payer = Keypair::new();
+    (0..count)
+        .map(|_| {
+            let message = Message::new(
+                &[Instruction::new_with_bytes(
+                    *program_id,
+                    &[0],
+                    vec![AccountMeta::new(payer.pubkey(), true)],
+                )],
+                Some(&payer.pubkey()),
+            );
+            Transaction::new(&[&payer], message, Hash::new_unique()).into()
+        })
+        .collect()
+}
+
+fn context(value: serde_json::Value) -> serde_json::Value {
+    json!({ "context": { "slot": 1 }, "value": value })
+}
+
+#[tokio::test]
+async fn rebuilds_until_one_lands() {
+    let program_id = Pubkey::new_unique();
+    let transactions = rebuilt(&program_id, 3);
+    let mut mocks = MocksMap::default();
+    // The first's blockhash is unknown to the node; the second expires
+    // unseen; the third lands
+    mocks.insert(
+        RpcRequest::SimulateTransaction,
+        context(json!({ "err": "BlockhashNotFound", "logs": [] })),
+    );
+    mocks.insert(RpcRequest::GetSignatureStatuses, context(json!([null])));
+    mocks.insert(RpcRequest::IsBlockhashValid, context(json!(false)));
+    mocks.insert(RpcRequest::GetSignatureStatuses, context(json!([null])));
+    let rpc = RpcClient::new_mock_with_mocks_map("succeeds".to_string(), mocks);
+
+    let mut built = transactions.clone().into_iter();
+    let signature = send_and_confirm(
+        &rpc,
+        &program_id,
+        || {
+            let transaction = built.next().unwrap();
+            async move { Ok(transaction) }
+        },
+        &fast(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(signature, transactions[2].signatures[0]);
+    assert_eq!(built.len(), 0);
+
+    assert_eq!(
+        SendOptions::default().backoff(1),
+        Duration::from_millis(500)
+    );
+    assert_eq!(SendOptions::default().backoff(10), Duration::from_secs(8));
+}
+
+#[tokio::test]
+async fn stops_at_failures_a_retry_would_repeat() {
+    let program_id = Pubkey::new_unique();
+    let mut mocks = MocksMap::default();
+    mocks.insert(
+        RpcRequest::SimulateTransaction,
+        context(json!({ "err": { "InstructionError": [0, { "Custom": 11 }] }, "logs": [] })),
+    );
+    let rpc = RpcClient::new_mock_with_mocks_map("succeeds".to_string(), mocks);
+    let mut builds = 0;
+    let error = send_and_confirm(
+        &rpc,
+        &program_id,
+        || {
+            builds += 1;
+            let transaction = rebuilt(&program_id, 1).remove(0);
+            async move { Ok(transaction) }
+        },
+        &fast(),
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        matches!(
+            error.downcast_ref::<SendError>(),
+            Some(SendError::Program {
+                error: ExclusionError::RootExpired,
+                ..
+            })
+        ),
+        "{error:?}"
+    );
+    assert_eq!(builds, 1);
+
+    // Every attempt expires unseen
+    let mut mocks = MocksMap::default();
+    for _ in 0..3 {
+        mocks.insert(RpcRequest::GetSignatureStatuses, context(json!([null])));
+        mocks.insert(RpcRequest::GetSignatureStatuses, context(json!([null])));
+        mocks.insert(RpcRequest::IsBlockhashValid, context(json!(false)));
+    }
+    let rpc = RpcClient::new_mock_with_mocks_map("succeeds".to_string(), mocks);
+    let mut builds = 0;
+    let error = send_and_confirm(
+        &rpc,
+        &program_id,
+        || {
+            builds += 1;
+            let transaction = rebuilt(&program_id, 1).remove(0);
+            async move { Ok(transaction) }
+        },
+        &fast(),
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        error.to_string().contains("expired unconfirmed 3 times"),
+        "{error}"
+    );
+    assert_eq!(builds, 3);
+}
@@ -0,0 +1,163 @@
+//! Checks remote signers against a signing service that fails at first, and
+//! a KMS holding the wrong key.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use exclusion_client::signer::{
+    sign_transaction, AsyncSigner, Backend, RemoteSigner, RetryPolicy, SigningRecord,
+};
+use serde_json::{json, Value};
+use solana_hash::Hash;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_keypair::Keypair;
+use solana_message::{v0, VersionedMessage};
+use solana_pubkey::Pubkey;
+use solana_signer::Signer;
+
+/// A request as the server saw it: path, `Authorization` header and body
+type Request = (String, Option<String>, Value);
+
+/// Answer each request with `respond`'s status and body; returns the URL and
+/// the requests served
+fn serve(
+    respond: impl Fn(usize, &Request) -> (u16, Value) + Send + 'static,
+) -> (String, Arc<Mutex<Vec<Request>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let served = requests.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let path = line.split(' ').nth(1).unwrap().to_string();
+            let (mut length, mut authorization) = (0, None);
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                let header = header.trim_end();
+                if header.is_empty() {
+                    break;
+                }
+                let (name, value) = header.split_once(": ").unwrap();
+                match name.to_ascii_lowercase().as_str() {
+                    "content-length" => length = value.parse().unwrap(),
+                    "authorization" => authorization = Some(value.to_string()),
+                    _ => {}
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let request = (path, authorization, serde_json::from_slice(&body).unwrap());
+            let count = served.lock().unwrap().len();
+            let (status, body) = respond(count, &request);
+            served.lock().unwrap().push(request);
+            let body = body.to_string();
+            write!(
+                stream,
+                "HTTP/1.1 {status} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        }
+    });
+    (url, requests)
+}
+
+fn fast_retry() -> RetryPolicy {
+    RetryPolicy {
+        initial_backoff: Duration::from_millis(1),
+        ..RetryPolicy::default()
+    }
+}
+
+#[tokio::test]
+async fn retries_and_audits_a_signing_service() {
+    let key = Arc::new(Keypair::new());
+    let service = key.clone();
+    let (url, requests) = serve(move |count, (_, _, body)| {
+        if count == 0 {
+            return (503, json!({ "error": "warming up" }));
+        }
+        let message = STANDARD.decode(body["message"].as_str().unwrap()).unwrap();
+        let signature = service.sign_message(&message);
+        (200, json!({ "signature": signature.to_string() }))
+    });
+    let records = Arc::new(Mutex::new(Vec::<SigningRecord>::new()));
+    let audit = records.clone();
+    let signer = RemoteSigner::new(key.pubkey(), Backend::Http { url })
+        .with_token("secret")
+        .with_retry(fast_retry())
+        .with_audit(Arc::new(move |record: &SigningRecord| {
+            audit.lock().unwrap().push(record.clone());
+            Ok(())
+        }));
+
+    let ix = Instruction {
+        program_id: Pubkey::new_unique(),
+        accounts: vec![AccountMeta::new(key.pubkey(), true)],
+        data: vec![1, 2, 3],
+    };
+    let message = v0::Message::try_compile(&key.pubkey(), &[ix], &[], Hash::new_unique()).unwrap();
+    let transaction = sign_transaction(VersionedMessage::V0(message), &[&signer])
+        .await
+        .unwrap();
+    let bytes = transaction.message.serialize();
+    assert!(transaction.signatures[0].verify(key.pubkey().as_ref(), &bytes));
+
+    // A keypair signs through the same trait
+    let local: &dyn AsyncSigner = key.as_ref();
+    assert_eq!(local.sign(&bytes).await.unwrap(), transaction.signatures[0]);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].1.as_deref(), Some("Bearer secret"));
+    assert_eq!(requests[1].2["pubkey"], key.pubkey().to_string());
+
+    let records = records.lock().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!((records[0].attempt, records[1].attempt), (1, 2));
+    assert!(records[0].error.as_ref().unwrap().contains("503"));
+    assert_eq!(
+        records[1].signature.as_deref(),
+        Some(transaction.signatures[0].to_string().as_str())
+    );
+    assert_eq!(records[0].message_hash, records[1].message_hash);
+
+    assert_eq!(fast_retry().backoff(3), Duration::from_millis(4));
+    assert_eq!(RetryPolicy::default().backoff(10), Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn refuses_a_kms_signature_from_another_key() {
+    let expected = Pubkey::new_unique();
+    let (url, requests) = serve(|_, (_, _, body)| {
+        let message = STANDARD.decode(body["data"].as_str().unwrap()).unwrap();
+        let signature = Keypair::new().sign_message(&message);
+        (200, json!({ "signature": STANDARD.encode(signature) }))
+    });
+    let key_version = "projects/p/locations/global/keyRings/r/cryptoKeys/k/cryptoKeyVersions/1";
+    let signer = RemoteSigner::new(
+        expected,
+        Backend::GcpKms {
+            url,
+            key_version: key_version.into(),
+        },
+    )
+    .with_retry(fast_retry());
+
+    let error = signer.sign(b"message").await.unwrap_err();
+    assert!(error.to_string().contains("a key other than"), "{error:#}");
+    // Not retried: another try would be no better
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].0, format!("/v1/{key_version}:asymmetricSign"));
+}
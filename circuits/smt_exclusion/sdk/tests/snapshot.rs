@@ -0,0 +1,43 @@
+//! Checks signed snapshots against a state account's admin and root.
+
+use exclusion_client::snapshot::{sign_snapshot, verify_against_state};
+use exclusion_program_example::{state_offsets, STATE_DISCRIMINATOR, STATE_SIZE};
+use smt::{signed::SignedSnapshot, SparseMerkleTree};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+fn state(admin: &Keypair, root: &[u8; 32]) -> Vec<u8> {
+    let mut data = vec![0u8; STATE_SIZE];
+    data[..8].copy_from_slice(&STATE_DISCRIMINATOR);
+    data[state_offsets::ADMIN..state_offsets::ADMIN + 32].copy_from_slice(admin.pubkey().as_ref());
+    data[state_offsets::SMT_ROOT..state_offsets::SMT_ROOT + 32].copy_from_slice(root);
+    data
+}
+
+#[test]
+fn verifies_admin_and_root() {
+    let mut tree = SparseMerkleTree::new();
+    tree.insert(&[1; 32]).unwrap();
+    let mut snapshot = Vec::new();
+    tree.export_snapshot(&mut snapshot, false).unwrap();
+
+    let admin = Keypair::new();
+    let signed = sign_snapshot(&admin, snapshot, 1_700_000_000).unwrap();
+    let published = SignedSnapshot::from_bytes(&signed.to_bytes()).unwrap();
+    assert_eq!(published, signed);
+    verify_against_state(&published, &state(&admin, &tree.root())).unwrap();
+
+    let error = verify_against_state(&published, &state(&Keypair::new(), &tree.root()))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("admin"), "{error}");
+    let error = verify_against_state(&published, &state(&admin, &[0; 32]))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("on-chain root"), "{error}");
+
+    // A signature over another time doesn't carry over
+    let mut backdated = published.clone();
+    backdated.statement.unix_time -= 1;
+    assert!(verify_against_state(&backdated, &state(&admin, &tree.root())).is_err());
+}
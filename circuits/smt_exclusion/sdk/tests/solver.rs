@@ -0,0 +1,227 @@
+//! Solves a small program encoded as `nargo compile` encodes ACIR: a byte
+//! split into bits by `directive_to_radix`, a product, and an inequality
+//! through `directive_invert`.
+
+use std::{collections::BTreeMap, io::Write};
+
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, Field, PrimeField};
+use base64::Engine;
+use exclusion_client::{
+    acir::{CircuitArtifact, InputValue},
+    solver::Program,
+};
+use serde_json::json;
+
+fn be(value: Fr) -> [u8; 32] {
+    value.into_bigint().to_bytes_be().try_into().unwrap()
+}
+
+/// Writes bincode as the ACIR serializer does
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, value: u8) -> &mut Self {
+        self.0.push(value);
+        self
+    }
+
+    fn u32(&mut self, value: u32) -> &mut Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn u64(&mut self, value: u64) -> &mut Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn field(&mut self, value: i64) -> &mut Self {
+        self.u64(64);
+        for byte in be(Fr::from(value)) {
+            self.0.extend_from_slice(format!("{byte:02x}").as_bytes());
+        }
+        self
+    }
+
+    /// `mul` and `linear` terms, by coefficient, plus `constant`
+    fn expression(
+        &mut self,
+        mul: &[(i64, u32, u32)],
+        linear: &[(i64, u32)],
+        constant: i64,
+    ) -> &mut Self {
+        self.u64(mul.len() as u64);
+        for &(coefficient, a, b) in mul {
+            self.field(coefficient).u32(a).u32(b);
+        }
+        self.u64(linear.len() as u64);
+        for &(coefficient, witness) in linear {
+            self.field(coefficient).u32(witness);
+        }
+        self.field(constant)
+    }
+
+    fn assert_zero(
+        &mut self,
+        mul: &[(i64, u32, u32)],
+        linear: &[(i64, u32)],
+        constant: i64,
+    ) -> &mut Self {
+        self.u32(0).expression(mul, linear, constant)
+    }
+}
+
+/// `main(x: pub Field, byte: u8)`, with `byte`'s bits in witnesses 2..10,
+/// `x * byte` in 10, and the inverse of `x - 3` in 11
+fn bytecode() -> Vec<u8> {
+    let mut w = Writer::default();
+    w.u64(1).u32(11).u64(14);
+    // RANGE(byte, 8)
+    w.u32(1).u32(3).u32(1).u32(1).u32(8);
+    // Brillig function 0, directive_to_radix(byte, 8, 2), into witnesses 2..10
+    w.u32(4).u32(0).u64(3);
+    w.u32(0).expression(&[], &[(1, 1)], 0);
+    w.u32(0).expression(&[], &[], 8);
+    w.u32(0).expression(&[], &[], 2);
+    w.u64(1).u32(1).u64(8);
+    for bit in 2..10 {
+        w.u32(bit);
+    }
+    w.u8(0);
+    // byte is the sum of its bits, and each bit is 0 or 1
+    let mut recomposed = vec![(-1, 1)];
+    recomposed.extend((2..10).map(|bit| (1 << (bit - 2), bit)));
+    w.assert_zero(&[], &recomposed, 0);
+    for bit in 2..10 {
+        w.assert_zero(&[(1, bit, bit)], &[(-1, bit)], 0);
+    }
+    // x * byte
+    w.assert_zero(&[(1, 0, 1)], &[(-1, 10)], 0);
+    // Brillig function 1, directive_invert(x - 3), into witness 11; then
+    // (x - 3) * inverse = 1
+    w.u32(4).u32(1).u64(1);
+    w.u32(0).expression(&[], &[(1, 0)], -3);
+    w.u64(1).u32(0).u32(11);
+    w.u8(0);
+    w.assert_zero(&[(1, 0, 11)], &[(-3, 11)], -1);
+
+    // Bounded expression width 4, private {1}, public {0}, no return values
+    w.u32(1).u64(4).u64(1).u32(1).u64(1).u32(0).u64(0);
+    // Selector 42 on the last opcode, without a payload
+    w.u64(1).u32(0).u64(13).u64(42).u64(0);
+    // The Brillig functions' bytecode, which the solver doesn't read
+    w.u64(0);
+    w.0
+}
+
+fn artifact(bytecode: &[u8]) -> CircuitArtifact {
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(bytecode).unwrap();
+    let json = serde_json::to_vec(&json!({
+        "noir_version": "1.0.0-beta.13",
+        "hash": 0,
+        "abi": {
+            "parameters": [
+                { "name": "x", "type": { "kind": "field" }, "visibility": "public" },
+                {
+                    "name": "byte",
+                    "type": { "kind": "integer", "sign": "unsigned", "width": 8 },
+                    "visibility": "private"
+                }
+            ],
+            "return_type": null,
+            "error_types": {
+                "42": { "error_kind": "string", "string": "x is 3" },
+                "7": { "error_kind": "fmtstring", "length": 4, "item_types": [] }
+            }
+        },
+        "bytecode": base64::engine::general_purpose::STANDARD.encode(gz.finish().unwrap()),
+        "debug_symbols": "",
+        "file_map": {},
+        "names": ["main"],
+        "brillig_names": ["directive_to_radix", "directive_invert"]
+    }))
+    .unwrap();
+    CircuitArtifact::from_json(&json).unwrap()
+}
+
+fn initial(artifact: &CircuitArtifact, x: u64, byte: u64) -> Vec<[u8; 32]> {
+    artifact
+        .initial_witness(&BTreeMap::from([
+            ("x".into(), InputValue::from_u64(x)),
+            ("byte".into(), InputValue::from_u64(byte)),
+        ]))
+        .unwrap()
+}
+
+#[test]
+fn solves_every_witness() {
+    let artifact = artifact(&bytecode());
+    let program = Program::from_artifact(&artifact).unwrap();
+    let witness = program.solve(&initial(&artifact, 5, 0b1011_0010)).unwrap();
+
+    let bits: Vec<_> = (2..10).map(|bit| witness.get(bit).unwrap()[31]).collect();
+    assert_eq!(bits, [0, 1, 0, 0, 1, 1, 0, 1]);
+    assert_eq!(witness.get(10), Some(be(Fr::from(5 * 0b1011_0010))));
+    assert_eq!(witness.get(11), Some(be(Fr::from(2).inverse().unwrap())));
+    assert_eq!(witness.get(12), None);
+}
+
+#[test]
+fn fails_with_the_assertion_message() {
+    let artifact = artifact(&bytecode());
+    let program = Program::from_artifact(&artifact).unwrap();
+    // The inverse of 0 is 0, which leaves (x - 3) * inverse at 0
+    let error = program.solve(&initial(&artifact, 3, 1)).unwrap_err();
+    assert!(format!("{error:#}").starts_with("x is 3"), "{error:#}");
+}
+
+#[test]
+fn checks_ranges() {
+    let artifact = artifact(&bytecode());
+    let program = Program::from_artifact(&artifact).unwrap();
+    // Past a u8, as a field the ABI can't stop
+    let mut start = initial(&artifact, 5, 1);
+    start[1] = be(Fr::from(256));
+    let error = program.solve(&start).unwrap_err();
+    assert!(format!("{error:#}").contains("8 bits"), "{error:#}");
+}
+
+#[test]
+fn writes_the_witness_stack() {
+    let artifact = artifact(&bytecode());
+    let program = Program::from_artifact(&artifact).unwrap();
+    let stack = program
+        .solve(&initial(&artifact, 5, 0b1011_0010))
+        .unwrap()
+        .to_witness_stack();
+
+    // One frame, index 0, of the 12 witnesses, each a u32 index and 64 hex digits
+    assert_eq!(stack[..12], [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(stack[12..20], 12u64.to_le_bytes());
+    assert_eq!(stack.len(), 20 + 12 * (4 + 8 + 64));
+    let first = &stack[20..20 + 76];
+    assert_eq!(first[..4], 0u32.to_le_bytes());
+    assert_eq!(first[4..12], 64u64.to_le_bytes());
+    assert_eq!(&first[12..], format!("{:064x}", 5).as_bytes());
+}
+
+#[test]
+fn rejects_what_it_cannot_solve() {
+    // A Brillig function of the circuit's own
+    let mut unknown = artifact(&bytecode());
+    unknown.brillig_names[1] = "my_hint".into();
+    let error = Program::from_artifact(&unknown).unwrap_err();
+    assert!(
+        format!("{error:#}").contains("Brillig function 1"),
+        "{error:#}"
+    );
+
+    // A memory block
+    let mut w = Writer::default();
+    w.u64(1).u32(0).u64(1).u32(3);
+    let error = Program::from_artifact(&artifact(&w.0)).unwrap_err();
+    assert!(format!("{error:#}").contains("memory"), "{error:#}");
+}
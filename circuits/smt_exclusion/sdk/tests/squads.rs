@@ -0,0 +1,83 @@
+//! Checks admin instructions are wrapped in Squads vault transactions the way
+//! the Squads program lays them out.
+
+use exclusion_client::{
+    instructions::{derive_state_pda, set_smt_root_ix},
+    squads::{
+        derive_proposal_pda, derive_transaction_pda, derive_vault_pda, propose, transaction_index,
+        vault_message, SQUADS_PROGRAM_ID,
+    },
+};
+use exclusion_common::instruction::METADATA_SIZE;
+use exclusion_program_example::{events::NOOP_PROGRAM_ID, ID as PROGRAM_ID};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_pubkey::Pubkey;
+
+#[test]
+fn wraps_set_smt_root_for_the_vault() {
+    let multisig = Pubkey::new_unique();
+    let (vault, _) = derive_vault_pda(&SQUADS_PROGRAM_ID, &multisig, 0);
+    let set_root = set_smt_root_ix(&PROGRAM_ID, &vault, &[7; 32], 0, &[0; METADATA_SIZE]);
+    let message = vault_message(&vault, std::slice::from_ref(&set_root)).unwrap();
+
+    let state = derive_state_pda(&PROGRAM_ID, &vault).0;
+    // One signer, writable; the state account writable; then the noop
+    // program and the exclusion program
+    let mut expected = vec![1, 1, 1, 4];
+    for key in [vault, state, NOOP_PROGRAM_ID, PROGRAM_ID] {
+        expected.extend_from_slice(key.as_ref());
+    }
+    expected.extend([1, 3, 3, 0, 1, 2]);
+    expected.extend((set_root.data.len() as u16).to_le_bytes());
+    expected.extend(&set_root.data);
+    expected.push(0);
+    assert_eq!(message, expected);
+
+    let other = Pubkey::new_unique();
+    let needs_another_signer = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![AccountMeta::new_readonly(other, true)],
+        data: vec![],
+    };
+    let error = vault_message(&vault, &[needs_another_signer]).unwrap_err();
+    assert!(error.to_string().contains(&other.to_string()), "{error}");
+}
+
+#[test]
+fn proposes_and_approves_as_the_member() {
+    let multisig = Pubkey::new_unique();
+    let member = Pubkey::new_unique();
+    let mut multisig_data = vec![0; 78];
+    multisig_data.extend(41u64.to_le_bytes());
+    multisig_data.extend([0; 16]);
+    let index = transaction_index(&multisig_data).unwrap() + 1;
+    assert_eq!(index, 42);
+    assert!(transaction_index(&multisig_data[..80]).is_err());
+
+    let proposal = propose(&SQUADS_PROGRAM_ID, &multisig, 0, index, &member, &[9; 20]);
+    assert_eq!(
+        proposal.transaction,
+        derive_transaction_pda(&SQUADS_PROGRAM_ID, &multisig, 42).0
+    );
+    assert_eq!(
+        proposal.proposal,
+        derive_proposal_pda(&SQUADS_PROGRAM_ID, &multisig, 42).0
+    );
+    let [create, open, approve] = &proposal.instructions[..] else {
+        panic!("expected three instructions");
+    };
+    // vault index, no ephemeral signers, the message's length and bytes, no
+    // memo
+    assert_eq!(&create.data[8..14], &[0, 0, 20, 0, 0, 0]);
+    assert_eq!(&create.data[14..34], &[9; 20]);
+    assert_eq!(create.data.len(), 35);
+    assert_eq!(&open.data[8..16], &42u64.to_le_bytes());
+    assert_eq!(approve.accounts[2].pubkey, proposal.proposal);
+    for instruction in &proposal.instructions {
+        assert_eq!(instruction.program_id, SQUADS_PROGRAM_ID);
+        assert!(instruction
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == member && meta.is_signer));
+    }
+}
@@ -0,0 +1,146 @@
+//! Checks the SRS cache downloads what it lacks, serves later requests from
+//! disk, and refuses points that aren't an SRS.
+//!
+//! A local HTTP server stands in for the CRS host, serving an SRS made from a
+//! known secret.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use ark_bn254::{Fr, G1Affine, G2Affine};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{BigInteger, PrimeField};
+use exclusion_client::srs::{Srs, SrsCache};
+
+fn be<F: PrimeField>(element: F) -> Vec<u8> {
+    element.into_bigint().to_bytes_be()
+}
+
+/// `len` G1 points `x^i·G` and the G2 point `x·H`, serialized as `bb` reads
+/// them
+fn srs(x: u64, len: usize) -> (Vec<u8>, Vec<u8>) {
+    let x = Fr::from(x);
+    let mut g1 = Vec::new();
+    let mut power = Fr::from(1u64);
+    for _ in 0..len {
+        let point = (G1Affine::generator() * power).into_affine();
+        g1.extend(be(point.x));
+        g1.extend(be(point.y));
+        power *= x;
+    }
+    let point = (G2Affine::generator() * x).into_affine();
+    let g2 = [
+        be(point.x.c0),
+        be(point.x.c1),
+        be(point.y.c0),
+        be(point.y.c1),
+    ]
+    .concat();
+    (g1, g2)
+}
+
+/// Serve `g1.dat` and `g2.dat`, honouring `Range: bytes=0-N`; returns the
+/// URL and a count of requests served
+fn serve(g1: Vec<u8>, g2: Vec<u8>) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let served = Arc::new(AtomicUsize::new(0));
+    let count = served.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut lines = BufReader::new(&stream).lines();
+            let request = lines.next().unwrap().unwrap();
+            let mut end = None;
+            for line in lines.map(Result::unwrap).take_while(|l| !l.is_empty()) {
+                if let Some(range) = line.to_ascii_lowercase().strip_prefix("range: bytes=0-") {
+                    end = Some(range.trim().parse::<usize>().unwrap());
+                }
+            }
+            let file = if request.contains("/g1.dat") {
+                &g1
+            } else {
+                &g2
+            };
+            let body = &file[..end.map_or(file.len(), |end| (end + 1).min(file.len()))];
+            write!(
+                stream,
+                "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(body).unwrap();
+            count.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    (url, served)
+}
+
+#[test]
+fn checks_points_are_an_srs() {
+    let (g1, g2) = srs(7, 16);
+    Srs { g1, g2 }.check().unwrap();
+
+    // G1 powers of one secret, G2 of another
+    let (g1, _) = srs(7, 16);
+    let (_, g2) = srs(8, 1);
+    let error = Srs { g1, g2 }.check().unwrap_err().to_string();
+    assert!(error.contains("powers"), "{error}");
+
+    // A point swapped out
+    let (mut g1, g2) = srs(7, 16);
+    let (other, _) = srs(9, 3);
+    g1[64 * 2..64 * 3].copy_from_slice(&other[64 * 2..]);
+    assert!(Srs { g1, g2 }.check().is_err());
+
+    let (mut g1, g2) = srs(7, 2);
+    g1[63] ^= 1;
+    let error = format!("{:#}", Srs { g1, g2 }.check().unwrap_err());
+    assert!(error.contains("G1 point 0"), "{error}");
+}
+
+#[tokio::test]
+async fn downloads_once_then_reads_the_cache() {
+    let (g1, g2) = srs(5, 64);
+    let (url, served) = serve(g1.clone(), g2.clone());
+    let dir = tempfile::tempdir().unwrap();
+    let cache = SrsCache::new(dir.path()).with_url(url);
+
+    let srs = cache.get(32).await.unwrap();
+    assert_eq!(srs.num_points(), 32);
+    assert_eq!(srs.g1, g1[..32 * 64]);
+    assert_eq!(srs.g2, g2);
+    assert_eq!(served.load(Ordering::SeqCst), 2);
+
+    // Fewer points come from the cache, more are downloaded again
+    assert_eq!(cache.get(16).await.unwrap().g1, g1[..16 * 64]);
+    assert_eq!(served.load(Ordering::SeqCst), 2);
+    assert_eq!(cache.get(64).await.unwrap().g1, g1);
+    assert_eq!(served.load(Ordering::SeqCst), 4);
+
+    // More than the host has
+    let error = cache.get(65).await.unwrap_err().to_string();
+    assert!(error.contains("bytes"), "{error}");
+}
+
+#[tokio::test]
+async fn refuses_to_cache_a_bad_srs() {
+    let (g1, _) = srs(5, 8);
+    let (_, g2) = srs(6, 1);
+    let (url, _) = serve(g1, g2);
+    let dir = tempfile::tempdir().unwrap();
+    let error = SrsCache::new(dir.path())
+        .with_url(url)
+        .get(8)
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("failed its checks"), "{error}");
+    assert!(!dir.path().join("bn254_g1.dat").exists());
+}
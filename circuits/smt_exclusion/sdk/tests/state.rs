@@ -0,0 +1,68 @@
+//! Checks state accounts decode, and when their root takes proofs.
+
+use exclusion_client::{instructions::derive_state_pda, state::ExclusionState};
+use exclusion_common::{
+    instruction::METADATA_SIZE,
+    state::{MAX_APPROVED_CALLERS, STATE_SIZE, UNVERSIONED_STATE_MAX_SIZE},
+    State,
+};
+use solana_pubkey::Pubkey;
+
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+const ADMIN: Pubkey = Pubkey::new_from_array([8; 32]);
+
+fn state(root_valid_until: i64, challenged: bool) -> State {
+    State {
+        admin: ADMIN.to_bytes(),
+        smt_root: [1; 32],
+        root_valid_until,
+        transfer_flags: 0,
+        daily_limit: 0,
+        fee_bps: 0,
+        root_seq: 1,
+        approved_callers: [[0; 32]; MAX_APPROVED_CALLERS],
+        approved_caller_count: 0,
+        bump: 255,
+        treasury_bump: 255,
+        hash_scheme: 0,
+        verifier_flags: 0,
+        metadata: [0; METADATA_SIZE],
+        challenged,
+        bond_bump: 255,
+        bond_amount: 0,
+        bond_withdrawal_amount: 0,
+        bond_withdrawal_at: 0,
+        config: [0; 32],
+        tree_kind: 0,
+        tree_depth: 254,
+    }
+}
+
+fn decode(state: State) -> ExclusionState {
+    let mut data = vec![0; STATE_SIZE];
+    state.pack(&mut data);
+    let (address, _) = derive_state_pda(&PROGRAM_ID, &ADMIN);
+    ExclusionState::from_account_data(address, 42, &data).unwrap()
+}
+
+#[test]
+fn decodes_admin_and_root() {
+    let decoded = decode(state(0, false));
+    assert_eq!(decoded.admin, ADMIN);
+    assert_eq!(decoded.smt_root, [1; 32]);
+    assert_eq!(decoded.slot, 42);
+    assert_eq!(decoded.state, state(0, false));
+
+    let error =
+        ExclusionState::from_account_data(ADMIN, 0, &[0; UNVERSIONED_STATE_MAX_SIZE]).unwrap_err();
+    assert!(error.to_string().contains("not a current"), "{error}");
+}
+
+#[test]
+fn roots_expire_and_challenges_stick() {
+    assert!(decode(state(0, false)).accepts_proofs_at(i64::MAX));
+    let expiring = decode(state(1_000, false));
+    assert!(expiring.accepts_proofs_at(1_000));
+    assert!(!expiring.accepts_proofs_at(1_001));
+    assert!(!decode(state(0, true)).accepts_proofs_at(0));
+}
@@ -0,0 +1,108 @@
+//! Checks the witness encoding against the repo's circuit inputs and the
+//! sunspot releases under the program's test fixtures.
+
+use std::{fs, path::Path};
+
+use exclusion_client::witness::ExclusionWitness;
+use exclusion_common::{instruction::WITNESS_SIZE, ExclusionError};
+use solana_pubkey::Pubkey;
+
+fn field(hex: &str) -> [u8; 32] {
+    let hex = hex.trim_start_matches("0x");
+    std::array::from_fn(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap())
+}
+
+/// `key`'s value in `Prover.toml`
+fn value(key: &str) -> &'static str {
+    include_str!("../../Prover.toml")
+        .lines()
+        .find_map(|line| line.strip_prefix(&format!("{key} = ")))
+        .unwrap()
+        .trim_matches('"')
+}
+
+/// The public inputs of `Prover.toml`
+fn prover_toml() -> ExclusionWitness {
+    ExclusionWitness {
+        smt_root: field(value("smt_root")),
+        pubkey_hash: field(value("pubkey_hash")),
+        recent_slot: value("recent_slot").parse().unwrap(),
+    }
+}
+
+#[test]
+fn encodes_prover_toml() {
+    let inputs = prover_toml();
+    let witness = inputs.to_gnark_bytes();
+    let mut expected = vec![0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 3];
+    expected.extend_from_slice(&inputs.smt_root);
+    expected.extend_from_slice(&inputs.pubkey_hash);
+    expected.extend_from_slice(&[0; 31]);
+    expected.push(1);
+    assert_eq!(witness.as_bytes()[..], expected);
+    assert_eq!(
+        ExclusionWitness::from_gnark_bytes(witness.as_bytes()),
+        Ok(inputs)
+    );
+
+    // The pubkey the inputs were generated for
+    let pubkey: Vec<u8> = value("pubkey")
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|byte| !byte.is_empty())
+        .map(|byte| byte.parse().unwrap())
+        .collect();
+    let pubkey = Pubkey::try_from(pubkey.as_slice()).unwrap();
+    assert_eq!(
+        ExclusionWitness::for_pubkey(inputs.smt_root, &pubkey, 1),
+        inputs
+    );
+}
+
+#[test]
+fn rejects_other_witnesses() {
+    let witness = prover_toml().to_gnark_bytes();
+    assert_eq!(
+        ExclusionWitness::from_gnark_bytes(&witness.as_bytes()[..WITNESS_SIZE - 32]),
+        Err(ExclusionError::InvalidDataLength)
+    );
+    let mut secret = witness;
+    secret.0[7] = 1;
+    assert_eq!(
+        ExclusionWitness::from_gnark_bytes(secret.as_bytes()),
+        Err(ExclusionError::InvalidDataLength)
+    );
+    let mut wide_slot = witness;
+    wide_slot.0[WITNESS_SIZE - 9] = 1;
+    assert_eq!(
+        ExclusionWitness::from_gnark_bytes(wide_slot.as_bytes()),
+        Err(ExclusionError::StaleProof)
+    );
+}
+
+#[test]
+fn round_trips_sunspot_releases() {
+    let root =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../on_chain_program/tests/fixtures/sunspot");
+    let mut witnesses: Vec<_> = fs::read_dir(&root)
+        .unwrap()
+        .filter_map(|entry| entry.ok().map(|e| e.path().join("smt_exclusion.pw")))
+        .filter(|path| path.is_file())
+        .collect();
+    witnesses.sort();
+    if witnesses.is_empty() {
+        eprintln!("no sunspot releases in {}, skipping", root.display());
+        return;
+    }
+
+    for path in witnesses {
+        let bytes = fs::read(&path).unwrap();
+        let inputs = ExclusionWitness::from_gnark_bytes(&bytes)
+            .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+        assert_eq!(
+            inputs.to_gnark_bytes().as_bytes()[..],
+            bytes,
+            "{}",
+            path.display()
+        );
+    }
+}
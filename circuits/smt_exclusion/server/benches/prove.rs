@@ -0,0 +1,67 @@
+//! Proof latency: `sunspot prove` on the CPU, and on the GPU when given a
+//! GPU build
+//!
+//! Proves with the circuit's build in `../target`, so run `just
+//! build-verifier-smt` first, with `nargo` and `sunspot` on `PATH`. The GPU
+//! prover is the sunspot build at `ICICLE_SUNSPOT`, with ICICLE's backend in
+//! `ICICLE_BACKEND_INSTALL_DIR` (default `/opt/icicle/lib/backend`).
+//!
+//! Run with: cargo bench, or ICICLE_SUNSPOT=<path> cargo bench
+
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use smt::{ExclusionProofInputs, SparseMerkleTree};
+use smt_exclusion_server::prover::Prover;
+use tokio::runtime::Runtime;
+
+const RECENT_SLOT: u64 = 1;
+
+fn inputs() -> ExclusionProofInputs {
+    let mut tree = SparseMerkleTree::new();
+    for i in 1..=64u8 {
+        tree.insert(&[i; 32]).unwrap();
+    }
+    tree.generate_exclusion_proof(&[0xee; 32]).unwrap().unwrap()
+}
+
+fn circuit_dir() -> PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("..")
+}
+
+/// Time `prover`, or say why it can't prove and skip it
+fn bench(c: &mut Criterion, runtime: &Runtime, name: &str, prover: Prover) {
+    let inputs = inputs();
+    if let Err(error) = runtime.block_on(prover.prove(&inputs, RECENT_SLOT)) {
+        eprintln!("skipping prove/{name}: {error:#}");
+        return;
+    }
+    let mut group = c.benchmark_group("prove");
+    group.sample_size(10);
+    group.bench_function(name, |b| {
+        b.iter(|| {
+            runtime
+                .block_on(prover.prove(&inputs, RECENT_SLOT))
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn prove(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    bench(c, &runtime, "cpu", Prover::new(circuit_dir()));
+
+    match std::env::var_os("ICICLE_SUNSPOT") {
+        Some(sunspot) => {
+            let backend = std::env::var_os("ICICLE_BACKEND_INSTALL_DIR")
+                .unwrap_or_else(|| "/opt/icicle/lib/backend".into());
+            let prover = Prover::new(circuit_dir()).with_icicle(sunspot, backend);
+            bench(c, &runtime, "icicle", prover);
+        }
+        None => eprintln!("skipping prove/icicle: ICICLE_SUNSPOT is not set"),
+    }
+}
+
+criterion_group!(benches, prove);
+criterion_main!(benches);
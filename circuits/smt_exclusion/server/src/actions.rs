@@ -0,0 +1,153 @@
+//! Solana Actions, so the gated transfer unfurls as a Blink
+//!
+//! `/actions.json` maps the site's `/transfer` path to the action API. The
+//! action's GET describes buttons for fixed and custom amounts; its POST takes
+//! the wallet's account and returns the gated transfer to sign. See
+//! <https://solana.com/docs/advanced/actions>.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_pubkey::Pubkey;
+
+use crate::{transfer, ApiError, Server};
+
+/// Actions spec version the responses follow (`X-Action-Version`)
+pub const ACTION_VERSION: &str = "2.4";
+
+/// Path of the action API
+pub const TRANSFER_PATH: &str = "/api/actions/transfer";
+
+/// Amounts offered as buttons, in SOL
+const PRESET_AMOUNTS: [&str; 3] = ["0.1", "0.5", "1"];
+
+#[derive(Serialize)]
+pub struct LinkedAction {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub label: String,
+    pub href: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<Value>,
+}
+
+#[derive(Serialize)]
+pub struct ActionGetResponse {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub icon: String,
+    pub title: String,
+    pub description: String,
+    pub label: String,
+    pub links: Value,
+}
+
+#[derive(Deserialize)]
+pub struct TransferQuery {
+    /// SOL, as a decimal string
+    pub amount: String,
+}
+
+#[derive(Deserialize)]
+pub struct ActionPostRequest {
+    pub account: String,
+}
+
+#[derive(Serialize)]
+pub struct ActionPostResponse {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Base64 unsigned transaction, with the account as fee payer
+    pub transaction: String,
+    pub message: String,
+}
+
+/// Parse a decimal SOL amount into lamports
+pub fn parse_sol(amount: &str) -> Option<u64> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if fraction.len() > 9
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let whole: u64 = match whole {
+        "" => 0,
+        digits => digits.parse().ok()?,
+    };
+    let fraction: u64 = format!("{fraction:0<9}").parse().ok()?;
+    whole.checked_mul(1_000_000_000)?.checked_add(fraction)
+}
+
+pub async fn rules() -> Json<Value> {
+    Json(json!({
+        "rules": [
+            { "pathPattern": "/transfer", "apiPath": TRANSFER_PATH },
+            { "pathPattern": "/api/actions/**", "apiPath": "/api/actions/**" },
+        ]
+    }))
+}
+
+pub async fn metadata(State(server): State<Arc<Server>>) -> Json<ActionGetResponse> {
+    let mut actions: Vec<LinkedAction> = PRESET_AMOUNTS
+        .iter()
+        .map(|amount| LinkedAction {
+            kind: "transaction",
+            label: format!("Send {amount} SOL"),
+            href: format!("{TRANSFER_PATH}?amount={amount}"),
+            parameters: Vec::new(),
+        })
+        .collect();
+    actions.push(LinkedAction {
+        kind: "transaction",
+        label: "Send".into(),
+        href: format!("{TRANSFER_PATH}?amount={{amount}}"),
+        parameters: vec![json!({
+            "type": "number",
+            "name": "amount",
+            "label": "Amount in SOL",
+            "required": true,
+            "min": 0,
+        })],
+    });
+
+    Json(ActionGetResponse {
+        kind: "action",
+        icon: server.icon.clone(),
+        title: server.label.clone(),
+        description: format!(
+            "Send SOL to {}. The transfer carries a zero-knowledge proof that your \
+             wallet is not among the {} keys on the sender blacklist.",
+            server.recipient,
+            server.blacklist.len()
+        ),
+        label: "Send".into(),
+        links: json!({ "actions": actions }),
+    })
+}
+
+pub async fn transaction(
+    State(server): State<Arc<Server>>,
+    Query(query): Query<TransferQuery>,
+    Json(request): Json<ActionPostRequest>,
+) -> Result<Json<ActionPostResponse>, ApiError> {
+    let account: Pubkey = request
+        .account
+        .parse()
+        .map_err(|_| ApiError::bad_request("Invalid account"))?;
+    let lamports = parse_sol(&query.amount)
+        .ok_or_else(|| ApiError::bad_request(format!("Invalid amount {}", query.amount)))?;
+    let transaction = transfer::gated_transfer(&server, &account, lamports).await?;
+    Ok(Json(ActionPostResponse {
+        kind: "transaction",
+        transaction: transfer::encode(&transaction),
+        message: format!("Sending {} SOL with an exclusion proof", query.amount),
+    }))
+}
@@ -0,0 +1,74 @@
+//! Servers that hand wallets proof-gated transfers to sign
+//!
+//! The server keeps the admin's blacklist, proves the customer's exclusion
+//! with the Noir toolchain, and returns an unsigned `TRANSFER_SOL` transaction
+//! with the customer as fee payer, through Solana Pay transaction requests
+//! (`pay`) or Solana Actions (`actions`).
+
+pub mod actions;
+pub mod pay;
+pub mod prover;
+pub mod transfer;
+pub mod tree;
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use smt::SparseMerkleTree;
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+
+use crate::prover::Prover;
+
+/// Everything a request handler needs, shared across requests
+pub struct Server {
+    pub rpc: RpcClient,
+    pub program_id: Pubkey,
+    pub verifier_id: Pubkey,
+    /// The state account whose root the blacklist must match
+    pub state: Pubkey,
+    /// Where payments go
+    pub recipient: Pubkey,
+    pub blacklist: SparseMerkleTree,
+    pub prover: Prover,
+    /// Merchant name wallets display
+    pub label: String,
+    /// Merchant icon URL wallets display
+    pub icon: String,
+}
+
+/// An error response: `{"message": "..."}`, the shape Solana Pay and Actions
+/// wallets display
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{error:#}"))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({ "message": self.message }))).into_response()
+    }
+}
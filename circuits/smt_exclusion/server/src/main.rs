@@ -0,0 +1,125 @@
+//! Proof-gated payments server (Solana Pay and Actions)
+//!
+//! Run with: cargo run -- --program-id <ID> --state <STATE> --recipient <MERCHANT> --blacklist list.json
+
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+
+use axum::{
+    http::{HeaderName, HeaderValue},
+    routing::get,
+    Router,
+};
+use clap::Parser;
+use exclusion_program_example::ZK_VERIFIER_PROGRAM_ID;
+use smt::inputs::field_hex;
+use smt_exclusion_server::{actions, pay, prover::Prover, tree::load_blacklist, Server};
+use solana_commitment_config::CommitmentConfig;
+use solana_pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use tower_http::{cors::CorsLayer, set_header::SetResponseHeaderLayer};
+
+#[derive(Parser)]
+struct Args {
+    #[arg(long, env = "RPC_URL", default_value = "https://api.devnet.solana.com")]
+    rpc_url: String,
+    /// Exclusion program
+    #[arg(long, env = "EXCLUSION_PROGRAM_ID")]
+    program_id: Pubkey,
+    /// ZK verifier program
+    #[arg(long, env = "ZK_VERIFIER_PROGRAM_ID", default_value_t = ZK_VERIFIER_PROGRAM_ID)]
+    verifier_id: Pubkey,
+    /// State account (PDA ["state", initial admin])
+    #[arg(long)]
+    state: Pubkey,
+    /// Merchant account payments go to
+    #[arg(long)]
+    recipient: Pubkey,
+    /// The admin's blacklist: a JSON array of base58 pubkeys
+    #[arg(long)]
+    blacklist: PathBuf,
+    /// Circuit directory, with its compiled circuit and keys in target/
+    #[arg(long, default_value = "..")]
+    circuit_dir: PathBuf,
+    #[arg(long, default_value = "SMT Exclusion Example")]
+    label: String,
+    #[arg(long, default_value = "https://solana.com/favicon.png")]
+    icon: String,
+    /// CAIP-2 ID of the cluster, for Actions clients (`X-Blockchain-Ids`); devnet by default
+    #[arg(long, default_value = "solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1")]
+    blockchain_id: String,
+    #[arg(long, default_value = "0.0.0.0:3000")]
+    listen: SocketAddr,
+    /// A sunspot built with -tags=icicle, to prove on the GPU
+    #[arg(long)]
+    icicle_sunspot: Option<PathBuf>,
+    /// ICICLE's CUDA backend directory
+    #[arg(
+        long,
+        env = "ICICLE_BACKEND_INSTALL_DIR",
+        default_value = "/opt/icicle/lib/backend"
+    )]
+    icicle_backend: PathBuf,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let blacklist = load_blacklist(&args.blacklist)?;
+    println!(
+        "Loaded {} blacklisted keys, root {}",
+        blacklist.len(),
+        field_hex(&blacklist.root())
+    );
+
+    let prover = Prover::new(args.circuit_dir);
+    let prover = match args.icicle_sunspot {
+        Some(sunspot) => {
+            println!("Proving on the GPU with {}", sunspot.display());
+            prover.with_icicle(sunspot, args.icicle_backend)
+        }
+        None => prover,
+    };
+
+    let server = Arc::new(Server {
+        rpc: RpcClient::new_with_commitment(args.rpc_url, CommitmentConfig::confirmed()),
+        program_id: args.program_id,
+        verifier_id: args.verifier_id,
+        state: args.state,
+        recipient: args.recipient,
+        blacklist,
+        prover,
+        label: args.label,
+        icon: args.icon,
+    });
+
+    let actions = Router::new()
+        .route("/actions.json", get(actions::rules))
+        .route(
+            actions::TRANSFER_PATH,
+            get(actions::metadata).post(actions::transaction),
+        )
+        .layer(SetResponseHeaderLayer::overriding(
+            HeaderName::from_static("x-action-version"),
+            HeaderValue::from_static(actions::ACTION_VERSION),
+        ))
+        .layer(SetResponseHeaderLayer::overriding(
+            HeaderName::from_static("x-blockchain-ids"),
+            HeaderValue::from_str(&args.blockchain_id)?,
+        ));
+
+    // Wallets and Blink clients fetch cross-origin
+    let app = Router::new()
+        .route("/pay", get(pay::metadata).post(pay::transaction))
+        .merge(actions)
+        .layer(CorsLayer::permissive())
+        .with_state(server);
+
+    let listener = tokio::net::TcpListener::bind(args.listen).await?;
+    println!(
+        "Listening on http://{0}/pay and http://{0}{1}",
+        args.listen,
+        actions::TRANSFER_PATH
+    );
+    axum::serve(listener, app).await?;
+    Ok(())
+}
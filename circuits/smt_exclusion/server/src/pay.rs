@@ -0,0 +1,67 @@
+//! Solana Pay transaction requests
+//!
+//! A point of sale shows `solana:https://<host>/pay?lamports=<amount>` as a QR
+//! code. The wallet GETs the merchant's label and icon, then POSTs the
+//! customer's account and receives the gated transfer to sign. See
+//! <https://docs.solanapay.com/spec#specification-transaction-request>.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use solana_pubkey::Pubkey;
+
+use crate::{transfer, ApiError, Server};
+
+#[derive(Serialize)]
+pub struct Metadata {
+    pub label: String,
+    pub icon: String,
+}
+
+#[derive(Deserialize)]
+pub struct PayQuery {
+    pub lamports: u64,
+}
+
+#[derive(Deserialize)]
+pub struct PayRequest {
+    pub account: String,
+}
+
+#[derive(Serialize)]
+pub struct PayResponse {
+    /// Base64 unsigned transaction, with the customer as fee payer
+    pub transaction: String,
+    pub message: String,
+}
+
+pub async fn metadata(State(server): State<Arc<Server>>) -> Json<Metadata> {
+    Json(Metadata {
+        label: server.label.clone(),
+        icon: server.icon.clone(),
+    })
+}
+
+pub async fn transaction(
+    State(server): State<Arc<Server>>,
+    Query(query): Query<PayQuery>,
+    Json(request): Json<PayRequest>,
+) -> Result<Json<PayResponse>, ApiError> {
+    let account: Pubkey = request
+        .account
+        .parse()
+        .map_err(|_| ApiError::bad_request("Invalid account"))?;
+    let transaction = transfer::gated_transfer(&server, &account, query.lamports).await?;
+    Ok(Json(PayResponse {
+        transaction: transfer::encode(&transaction),
+        message: format!(
+            "Pay {} SOL to {} (exclusion proof attached)",
+            query.lamports as f64 / 1e9,
+            server.label
+        ),
+    }))
+}
@@ -0,0 +1,143 @@
+//! Exclusion proofs from the Noir toolchain, as the TypeScript client's
+//! `generateProof` makes them: write `Prover.toml`, `nargo execute`, then
+//! `sunspot prove` with the circuit's committed keys.
+//!
+//! Each proof is made in a temporary copy of the circuit's package, with its
+//! own `Prover.toml` and `target/`, so proofs run concurrently and the
+//! circuit directory is only read.
+//!
+//! `sunspot prove` can run on a GPU: gnark does the prover's multi-scalar
+//! multiplications with ICICLE when sunspot is built with `-tags=icicle`.
+//! That build loads ICICLE's CUDA backend from `ICICLE_BACKEND_INSTALL_DIR`.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use exclusion_program_example::instruction::{PROOF_SIZE, WITNESS_SIZE};
+use smt::ExclusionProofInputs;
+use tempfile::TempDir;
+use tokio::process::Command;
+
+const CIRCUIT_NAME: &str = "smt_exclusion";
+
+/// A Groth16 proof and its gnark public witness, as the program takes them
+pub struct Proof {
+    pub proof: Vec<u8>,
+    pub witness: Vec<u8>,
+}
+
+/// Proves with one circuit directory's compiled circuit and keys
+pub struct Prover {
+    circuit_dir: PathBuf,
+    sunspot: PathBuf,
+    /// ICICLE's backend directory, for a GPU build of `sunspot`
+    icicle_backend: Option<PathBuf>,
+}
+
+impl Prover {
+    pub fn new(circuit_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            circuit_dir: circuit_dir.into(),
+            sunspot: "sunspot".into(),
+            icicle_backend: None,
+        }
+    }
+
+    /// Prove on the GPU with `sunspot`, built with `-tags=icicle`, loading
+    /// ICICLE's backend from `backend_dir` (e.g. `/opt/icicle/lib/backend`)
+    pub fn with_icicle(
+        mut self,
+        sunspot: impl Into<PathBuf>,
+        backend_dir: impl Into<PathBuf>,
+    ) -> Self {
+        self.sunspot = sunspot.into();
+        self.icicle_backend = Some(backend_dir.into());
+        self
+    }
+
+    /// A copy of the circuit's package to prove in: `Nargo.toml` and `src/`,
+    /// removed when dropped
+    async fn package(&self) -> anyhow::Result<TempDir> {
+        let package = tempfile::Builder::new().prefix(CIRCUIT_NAME).tempdir()?;
+        tokio::fs::copy(
+            self.circuit_dir.join("Nargo.toml"),
+            package.path().join("Nargo.toml"),
+        )
+        .await
+        .context("copying Nargo.toml")?;
+        let src = package.path().join("src");
+        tokio::fs::create_dir(&src).await?;
+        let mut sources = tokio::fs::read_dir(self.circuit_dir.join("src")).await?;
+        while let Some(source) = sources.next_entry().await? {
+            tokio::fs::copy(source.path(), src.join(source.file_name()))
+                .await
+                .with_context(|| format!("copying {}", source.path().display()))?;
+        }
+        Ok(package)
+    }
+
+    /// `target/<circuit>.<extension>` in the package at `dir`
+    fn target(dir: &Path, extension: &str) -> PathBuf {
+        dir.join("target")
+            .join(format!("{CIRCUIT_NAME}.{extension}"))
+    }
+
+    async fn run(&self, dir: &Path, program: &Path, args: &[&Path]) -> anyhow::Result<()> {
+        let mut command = Command::new(program);
+        if let Some(backend) = &self.icicle_backend {
+            command.env("ICICLE_BACKEND_INSTALL_DIR", backend);
+        }
+        let output = command
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .await
+            .with_context(|| format!("running {}", program.display()))?;
+        if !output.status.success() {
+            bail!(
+                "{} failed: {}",
+                program.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+
+    /// Prove `inputs` bound to `recent_slot`
+    pub async fn prove(
+        &self,
+        inputs: &ExclusionProofInputs,
+        recent_slot: u64,
+    ) -> anyhow::Result<Proof> {
+        // The copy compiles and solves the circuit into its own target/, and
+        // sunspot proves with the keys set up in the circuit directory
+        let package = self.package().await?;
+        let dir = package.path();
+        tokio::fs::write(dir.join("Prover.toml"), inputs.to_prover_toml(recent_slot)).await?;
+        self.run(dir, Path::new("nargo"), &[Path::new("execute")])
+            .await?;
+        self.run(
+            dir,
+            &self.sunspot,
+            &[
+                Path::new("prove"),
+                &Self::target(dir, "json"),
+                &Self::target(dir, "gz"),
+                &Self::target(&self.circuit_dir, "ccs"),
+                &Self::target(&self.circuit_dir, "pk"),
+            ],
+        )
+        .await?;
+
+        let proof = tokio::fs::read(Self::target(dir, "proof")).await?;
+        let witness = tokio::fs::read(Self::target(dir, "pw")).await?;
+        if proof.len() != PROOF_SIZE || witness.len() != WITNESS_SIZE {
+            bail!(
+                "unexpected proof/witness sizes {}/{} (is sunspot up to date?)",
+                proof.len(),
+                witness.len()
+            );
+        }
+        Ok(Proof { proof, witness })
+    }
+}
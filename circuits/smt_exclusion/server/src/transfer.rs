@@ -0,0 +1,153 @@
+//! The proof-gated `TRANSFER_SOL` transaction the endpoints hand out
+
+use anyhow::Context;
+use axum::http::StatusCode;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use exclusion_program_example::{
+    events::NOOP_PROGRAM_ID,
+    hash_scheme,
+    instruction::{self, InstructionHeader, WitnessHeader},
+    state_offsets, STATE_DISCRIMINATOR, STATE_SIZE,
+};
+use smt::pubkey_to_index;
+use solana_commitment_config::CommitmentConfig;
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_message::Message;
+use solana_pubkey::Pubkey;
+use solana_transaction::Transaction;
+
+use crate::{prover::Proof, ApiError, Server};
+
+/// Verifying the proof takes most of a transaction's compute
+pub const COMPUTE_UNIT_LIMIT: u32 = 500_000;
+
+/// The state account's current SMT root
+pub async fn current_root(server: &Server) -> Result<[u8; 32], ApiError> {
+    let account = server
+        .rpc
+        .get_account(&server.state)
+        .await
+        .context("fetching the state account")?;
+    let data = &account.data;
+    if account.owner != server.program_id
+        || data.len() < STATE_SIZE
+        || data[..8] != STATE_DISCRIMINATOR
+    {
+        return Err(ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The configured state account is not a current exclusion state",
+        ));
+    }
+    if data[state_offsets::HASH_SCHEME] != hash_scheme::POSEIDON {
+        return Err(ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The state indexes pubkeys with Poseidon2, which this server does not build",
+        ));
+    }
+    Ok(data[state_offsets::SMT_ROOT..state_offsets::SMT_ROOT + 32]
+        .try_into()
+        .unwrap())
+}
+
+/// Check a fresh proof's public witness against `sender` and the chain, so a
+/// transaction that would fail isn't handed out to be signed and paid for
+pub async fn precheck_witness(
+    server: &Server,
+    sender: &Pubkey,
+    proof: &Proof,
+) -> Result<(), ApiError> {
+    let witness = &proof.witness;
+    let well_formed = WitnessHeader::unpack(witness)
+        .is_some_and(|header| header.nb_public == 3 && header.witness_size() == witness.len());
+    if !well_formed {
+        return Err(anyhow::anyhow!("malformed public witness").into());
+    }
+    let smt_root = &witness[12..44];
+    let pubkey_hash = &witness[44..76];
+    if pubkey_hash != pubkey_to_index(&sender.to_bytes()) {
+        return Err(anyhow::anyhow!("the witness is for another pubkey").into());
+    }
+    // Proving takes seconds; the admin may have moved the root meanwhile
+    if smt_root != current_root(server).await? {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "The on-chain root changed while proving; try again",
+        ));
+    }
+    Ok(())
+}
+
+/// Prove `sender` is not blacklisted and build its unsigned transfer of
+/// `lamports` to the server's recipient, with `sender` as fee payer
+pub async fn gated_transfer(
+    server: &Server,
+    sender: &Pubkey,
+    lamports: u64,
+) -> Result<Transaction, ApiError> {
+    if lamports == 0 {
+        return Err(ApiError::bad_request("Amount must be positive"));
+    }
+    // The tree is in memory, so the only way this fails is a blacklisted sender
+    let Ok(Some(inputs)) = server
+        .blacklist
+        .generate_exclusion_proof(&sender.to_bytes())
+    else {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            format!("{sender} is blacklisted"),
+        ));
+    };
+    // A proof against any other root fails on chain
+    if current_root(server).await? != inputs.smt_root {
+        return Err(ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The server's blacklist does not match the on-chain root",
+        ));
+    }
+
+    let recent_slot = server
+        .rpc
+        .get_slot_with_commitment(CommitmentConfig::finalized())
+        .await
+        .context("fetching a recent slot")?;
+    let proof = server.prover.prove(&inputs, recent_slot).await?;
+    precheck_witness(server, sender, &proof).await?;
+
+    let mut data = InstructionHeader::pack(instruction::TRANSFER_SOL).to_vec();
+    data.extend_from_slice(&lamports.to_le_bytes());
+    data.extend_from_slice(&proof.proof);
+    data.extend_from_slice(&proof.witness);
+    let transfer = Instruction {
+        program_id: server.program_id,
+        accounts: vec![
+            AccountMeta::new(*sender, true),
+            AccountMeta::new(server.recipient, false),
+            AccountMeta::new_readonly(server.state, false),
+            AccountMeta::new_readonly(server.verifier_id, false),
+            AccountMeta::new_readonly(solana_sdk_ids::system_program::ID, false),
+            AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+        ],
+        data,
+    };
+
+    let blockhash = server
+        .rpc
+        .get_latest_blockhash()
+        .await
+        .context("fetching a blockhash")?;
+    let message = Message::new_with_blockhash(
+        &[
+            ComputeBudgetInstruction::set_compute_unit_limit(COMPUTE_UNIT_LIMIT),
+            transfer,
+        ],
+        Some(sender),
+        &blockhash,
+    );
+    Ok(Transaction::new_unsigned(message))
+}
+
+/// Base64 wire encoding of an unsigned transaction, as wallets expect it
+pub fn encode(transaction: &Transaction) -> String {
+    STANDARD.encode(bincode::serialize(transaction).expect("transactions serialize"))
+}
@@ -0,0 +1,27 @@
+//! The blacklist tree, rebuilt from the admin's list of blacklisted pubkeys
+
+use std::{fs, path::Path};
+
+use anyhow::Context;
+use smt::{Op, SparseMerkleTree};
+use solana_pubkey::Pubkey;
+
+/// Load a JSON array of base58 pubkeys, as written by `recover.ts`
+pub fn load_blacklist(path: &Path) -> anyhow::Result<SparseMerkleTree> {
+    let json = fs::read_to_string(path)
+        .with_context(|| format!("reading blacklist {}", path.display()))?;
+    let keys: Vec<String> = serde_json::from_str(&json)
+        .with_context(|| format!("{} is not a JSON array of pubkeys", path.display()))?;
+    let ops = keys
+        .iter()
+        .map(|key| {
+            let pubkey: Pubkey = key
+                .parse()
+                .with_context(|| format!("invalid pubkey {key}"))?;
+            Ok(Op::Insert(pubkey.to_bytes()))
+        })
+        .collect::<anyhow::Result<Vec<Op>>>()?;
+    let mut tree = SparseMerkleTree::new();
+    tree.apply_batch(&ops)?;
+    Ok(tree)
+}
@@ -0,0 +1,15 @@
+//! Checks the Actions amount parsing.
+
+use smt_exclusion_server::actions::parse_sol;
+
+#[test]
+fn parses_decimal_sol() {
+    assert_eq!(parse_sol("1"), Some(1_000_000_000));
+    assert_eq!(parse_sol("0.5"), Some(500_000_000));
+    assert_eq!(parse_sol(".000000001"), Some(1));
+    assert_eq!(parse_sol("2.25"), Some(2_250_000_000));
+    assert_eq!(parse_sol("0.0000000001"), None);
+    assert_eq!(parse_sol("-1"), None);
+    assert_eq!(parse_sol("1e9"), None);
+    assert_eq!(parse_sol("18446744074"), None);
+}
@@ -0,0 +1,35 @@
+//! Checks the blacklist file loads into the tree.
+
+use smt::SparseMerkleTree;
+use smt_exclusion_server::tree::load_blacklist;
+use solana_pubkey::Pubkey;
+
+fn write_list(name: &str, json: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("smt-exclusion-server-{name}.json"));
+    std::fs::write(&path, json).unwrap();
+    path
+}
+
+#[test]
+fn loads_recovered_list() {
+    let blacklisted: Vec<Pubkey> = (1..=3).map(|i| Pubkey::new_from_array([i; 32])).collect();
+    let keys: Vec<String> = blacklisted.iter().map(Pubkey::to_string).collect();
+    // Duplicates count once
+    let json = serde_json::to_string(&[&keys[..], &keys[..1]].concat()).unwrap();
+    let tree = load_blacklist(&write_list("loads", &json)).unwrap();
+
+    let mut expected = SparseMerkleTree::new();
+    for pubkey in &blacklisted {
+        expected.insert(&pubkey.to_bytes()).unwrap();
+    }
+    assert_eq!(tree.len(), 3);
+    assert_eq!(tree.root(), expected.root());
+    assert!(tree.contains(&blacklisted[1].to_bytes()).unwrap());
+}
+
+#[test]
+fn rejects_invalid_keys() {
+    let error = load_blacklist(&write_list("invalid", r#"["not a pubkey"]"#)).unwrap_err();
+    assert!(format!("{error:#}").contains("invalid pubkey"));
+    assert!(load_blacklist(&write_list("object", r#"{"keys": []}"#)).is_err());
+}
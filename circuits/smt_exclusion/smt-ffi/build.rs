@@ -0,0 +1,13 @@
+//! Regenerate `include/smt_exclusion.h` from the `extern "C"` functions
+
+fn main() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(cbindgen::Config::from_root_or_default(&crate_dir))
+        .generate()
+        .expect("generating the C header")
+        .write_to_file(format!("{crate_dir}/include/smt_exclusion.h"));
+}
@@ -0,0 +1,140 @@
+#ifndef SMT_EXCLUSION_H
+#define SMT_EXCLUSION_H
+
+/* Generated by cbindgen from src/lib.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Success
+#define SMT_OK 0
+
+// The pubkey is blacklisted, so it has no exclusion proof
+#define SMT_BLACKLISTED 1
+
+// A required pointer was null
+#define SMT_ERR_NULL -1
+
+// A pubkey's leaf holds another pubkey, in a tree shallower than 254 levels
+#define SMT_ERR_COLLISION -2
+
+// Levels in the tree, and sibling hashes in a proof
+#define SMT_TREE_DEPTH 254
+
+// Bytes in a public witness
+#define SMT_PUBLIC_WITNESS_SIZE 108
+
+// A sparse Merkle tree of blacklisted pubkeys; free with `smt_tree_free`
+typedef struct SmtTree SmtTree;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// An empty tree
+struct SmtTree *smt_tree_new(void);
+
+// The tree in a snapshot of `len` bytes, or null if the snapshot is damaged
+// or its leaves don't hash to its root
+//
+// # Safety
+//
+// `snapshot` points to `len` readable bytes
+struct SmtTree *smt_tree_from_snapshot(const uint8_t *snapshot, size_t len);
+
+// Free a tree from `smt_tree_new` or `smt_tree_from_snapshot`
+//
+// # Safety
+//
+// `tree` is null or a tree not yet freed
+void smt_tree_free(struct SmtTree *tree);
+
+// Blacklisted pubkeys in the tree (0 for null)
+//
+// # Safety
+//
+// `tree` is null or a live tree
+size_t smt_tree_len(const struct SmtTree *tree);
+
+// Blacklist `count` pubkeys, 32 bytes each back to back, hashing each changed
+// node once; writes how many were added to `added` if it isn't null. Adds
+// none and returns `SMT_ERR_COLLISION` if one's leaf holds another pubkey.
+//
+// # Safety
+//
+// `tree` is a live tree, `pubkeys` points to `32 * count` readable bytes and
+// `added` is null or writable
+int32_t smt_tree_insert(struct SmtTree *tree, const uint8_t *pubkeys, size_t count, size_t *added);
+
+// Remove `pubkey` from the blacklist; writes whether it was on it to
+// `removed` if it isn't null
+//
+// # Safety
+//
+// `tree` is a live tree, `pubkey` points to 32 readable bytes and `removed`
+// is null or writable
+int32_t smt_tree_delete(struct SmtTree *tree, const uint8_t *pubkey, bool *removed);
+
+// Write the root to `root`
+//
+// # Safety
+//
+// `tree` is a live tree and `root` points to 32 writable bytes
+int32_t smt_tree_root(const struct SmtTree *tree, uint8_t *root);
+
+// Write the exclusion proof for `pubkey`: its leaf index to `pubkey_hash` and
+// `SMT_TREE_DEPTH` sibling hashes, leaf level first, to `siblings`. Returns
+// `SMT_BLACKLISTED`, writing nothing, if it is blacklisted.
+//
+// # Safety
+//
+// `tree` is a live tree, `pubkey` points to 32 readable bytes, `pubkey_hash`
+// to 32 writable bytes and `siblings` to `32 * SMT_TREE_DEPTH` writable bytes
+int32_t smt_tree_exclusion_proof(const struct SmtTree *tree,
+                                 const uint8_t *pubkey,
+                                 uint8_t *pubkey_hash,
+                                 uint8_t *siblings);
+
+// The `Prover.toml` proving `pubkey` is not blacklisted at `recent_slot`, as
+// a string to free with `smt_string_free`; null if it is blacklisted
+//
+// # Safety
+//
+// `tree` is a live tree and `pubkey` points to 32 readable bytes
+char *smt_tree_prover_toml(const struct SmtTree *tree, const uint8_t *pubkey, uint64_t recent_slot);
+
+// Free a string from this library
+//
+// # Safety
+//
+// `string` is null or a string from this library not yet freed
+void smt_string_free(char *string);
+
+// Write `pubkey`'s leaf index to `index`
+//
+// # Safety
+//
+// `pubkey` points to 32 readable bytes and `index` to 32 writable bytes
+int32_t smt_pubkey_to_index(const uint8_t *pubkey, uint8_t *index);
+
+// Write the `SMT_PUBLIC_WITNESS_SIZE`-byte public witness for proving
+// `pubkey` is excluded from `smt_root` at `recent_slot` to `witness`, as the
+// program takes it with the proof
+//
+// # Safety
+//
+// `smt_root` and `pubkey` point to 32 readable bytes and `witness` to
+// `SMT_PUBLIC_WITNESS_SIZE` writable bytes
+int32_t smt_public_witness(const uint8_t *smt_root,
+                           const uint8_t *pubkey,
+                           uint64_t recent_slot,
+                           uint8_t *witness);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SMT_EXCLUSION_H */
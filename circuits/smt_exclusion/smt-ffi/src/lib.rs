@@ -0,0 +1,288 @@
+//! C interface to the blacklist tree and the public witness
+//!
+//! For wallets building proof inputs natively (Swift, Kotlin through JNI or
+//! JNA). `include/smt_exclusion.h` is generated from this file by cbindgen on
+//! every build.
+//!
+//! Pubkeys, roots and hashes are 32-byte buffers, field elements big-endian.
+//! Functions returning `int32_t` return `SMT_OK` or another `SMT_` code.
+
+use std::{
+    ffi::{c_char, CString},
+    ptr, slice,
+};
+
+use smt::{inputs::PUBLIC_WITNESS_SIZE, MemoryStore, Op, TREE_DEPTH};
+
+/// Success
+pub const SMT_OK: i32 = 0;
+/// The pubkey is blacklisted, so it has no exclusion proof
+pub const SMT_BLACKLISTED: i32 = 1;
+/// A required pointer was null
+pub const SMT_ERR_NULL: i32 = -1;
+/// A pubkey's leaf holds another pubkey, in a tree shallower than 254 levels
+pub const SMT_ERR_COLLISION: i32 = -2;
+
+// Literals, so cbindgen can write them into the header
+/// Levels in the tree, and sibling hashes in a proof
+pub const SMT_TREE_DEPTH: usize = 254;
+/// Bytes in a public witness
+pub const SMT_PUBLIC_WITNESS_SIZE: usize = 108;
+const _: () =
+    assert!(SMT_TREE_DEPTH == TREE_DEPTH && SMT_PUBLIC_WITNESS_SIZE == PUBLIC_WITNESS_SIZE);
+
+/// A sparse Merkle tree of blacklisted pubkeys; free with `smt_tree_free`
+pub struct SmtTree {
+    tree: smt::SparseMerkleTree,
+}
+
+/// # Safety
+///
+/// `pointer` is null or points to 32 readable bytes
+unsafe fn read32(pointer: *const u8) -> Option<[u8; 32]> {
+    match pointer.is_null() {
+        true => None,
+        false => Some(*pointer.cast::<[u8; 32]>()),
+    }
+}
+
+/// # Safety
+///
+/// `out` points to `bytes.len()` writable bytes
+unsafe fn write(out: *mut u8, bytes: &[u8]) {
+    ptr::copy_nonoverlapping(bytes.as_ptr(), out, bytes.len());
+}
+
+/// An empty tree
+#[no_mangle]
+pub extern "C" fn smt_tree_new() -> *mut SmtTree {
+    Box::into_raw(Box::new(SmtTree {
+        tree: smt::SparseMerkleTree::new(),
+    }))
+}
+
+/// The tree in a snapshot of `len` bytes, or null if the snapshot is damaged
+/// or its leaves don't hash to its root
+///
+/// # Safety
+///
+/// `snapshot` points to `len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn smt_tree_from_snapshot(snapshot: *const u8, len: usize) -> *mut SmtTree {
+    if snapshot.is_null() {
+        return ptr::null_mut();
+    }
+    let snapshot = slice::from_raw_parts(snapshot, len);
+    match smt::SparseMerkleTree::import_snapshot(MemoryStore::default(), snapshot) {
+        Ok(tree) => Box::into_raw(Box::new(SmtTree { tree })),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Free a tree from `smt_tree_new` or `smt_tree_from_snapshot`
+///
+/// # Safety
+///
+/// `tree` is null or a tree not yet freed
+#[no_mangle]
+pub unsafe extern "C" fn smt_tree_free(tree: *mut SmtTree) {
+    if !tree.is_null() {
+        drop(Box::from_raw(tree));
+    }
+}
+
+/// Blacklisted pubkeys in the tree (0 for null)
+///
+/// # Safety
+///
+/// `tree` is null or a live tree
+#[no_mangle]
+pub unsafe extern "C" fn smt_tree_len(tree: *const SmtTree) -> usize {
+    tree.as_ref().map_or(0, |tree| tree.tree.len())
+}
+
+/// Blacklist `count` pubkeys, 32 bytes each back to back, hashing each changed
+/// node once; writes how many were added to `added` if it isn't null. Adds
+/// none and returns `SMT_ERR_COLLISION` if one's leaf holds another pubkey.
+///
+/// # Safety
+///
+/// `tree` is a live tree, `pubkeys` points to `32 * count` readable bytes and
+/// `added` is null or writable
+#[no_mangle]
+pub unsafe extern "C" fn smt_tree_insert(
+    tree: *mut SmtTree,
+    pubkeys: *const u8,
+    count: usize,
+    added: *mut usize,
+) -> i32 {
+    let Some(tree) = tree.as_mut() else {
+        return SMT_ERR_NULL;
+    };
+    if pubkeys.is_null() {
+        return SMT_ERR_NULL;
+    }
+    let ops: Vec<Op> = slice::from_raw_parts(pubkeys, 32 * count)
+        .chunks_exact(32)
+        .map(|pubkey| Op::Insert(pubkey.try_into().expect("32 bytes")))
+        .collect();
+    let Ok(changed) = tree.tree.apply_batch(&ops) else {
+        return SMT_ERR_COLLISION;
+    };
+    if let Some(added) = added.as_mut() {
+        *added = changed;
+    }
+    SMT_OK
+}
+
+/// Remove `pubkey` from the blacklist; writes whether it was on it to
+/// `removed` if it isn't null
+///
+/// # Safety
+///
+/// `tree` is a live tree, `pubkey` points to 32 readable bytes and `removed`
+/// is null or writable
+#[no_mangle]
+pub unsafe extern "C" fn smt_tree_delete(
+    tree: *mut SmtTree,
+    pubkey: *const u8,
+    removed: *mut bool,
+) -> i32 {
+    let (Some(tree), Some(pubkey)) = (tree.as_mut(), read32(pubkey)) else {
+        return SMT_ERR_NULL;
+    };
+    let Ok(deleted) = tree.tree.delete(&pubkey) else {
+        return SMT_ERR_COLLISION;
+    };
+    if let Some(removed) = removed.as_mut() {
+        *removed = deleted;
+    }
+    SMT_OK
+}
+
+/// Write the root to `root`
+///
+/// # Safety
+///
+/// `tree` is a live tree and `root` points to 32 writable bytes
+#[no_mangle]
+pub unsafe extern "C" fn smt_tree_root(tree: *const SmtTree, root: *mut u8) -> i32 {
+    let Some(tree) = tree.as_ref() else {
+        return SMT_ERR_NULL;
+    };
+    if root.is_null() {
+        return SMT_ERR_NULL;
+    }
+    write(root, &tree.tree.root());
+    SMT_OK
+}
+
+/// Write the exclusion proof for `pubkey`: its leaf index to `pubkey_hash` and
+/// `SMT_TREE_DEPTH` sibling hashes, leaf level first, to `siblings`. Returns
+/// `SMT_BLACKLISTED`, writing nothing, if it is blacklisted.
+///
+/// # Safety
+///
+/// `tree` is a live tree, `pubkey` points to 32 readable bytes, `pubkey_hash`
+/// to 32 writable bytes and `siblings` to `32 * SMT_TREE_DEPTH` writable bytes
+#[no_mangle]
+pub unsafe extern "C" fn smt_tree_exclusion_proof(
+    tree: *const SmtTree,
+    pubkey: *const u8,
+    pubkey_hash: *mut u8,
+    siblings: *mut u8,
+) -> i32 {
+    let (Some(tree), Some(pubkey)) = (tree.as_ref(), read32(pubkey)) else {
+        return SMT_ERR_NULL;
+    };
+    if pubkey_hash.is_null() || siblings.is_null() {
+        return SMT_ERR_NULL;
+    }
+    let Ok(Some(proof)) = tree.tree.exclusion_proof(&pubkey) else {
+        return SMT_BLACKLISTED;
+    };
+    write(pubkey_hash, &proof.pubkey_hash);
+    write(siblings, &proof.siblings.concat());
+    SMT_OK
+}
+
+/// The `Prover.toml` proving `pubkey` is not blacklisted at `recent_slot`, as
+/// a string to free with `smt_string_free`; null if it is blacklisted
+///
+/// # Safety
+///
+/// `tree` is a live tree and `pubkey` points to 32 readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn smt_tree_prover_toml(
+    tree: *const SmtTree,
+    pubkey: *const u8,
+    recent_slot: u64,
+) -> *mut c_char {
+    let (Some(tree), Some(pubkey)) = (tree.as_ref(), read32(pubkey)) else {
+        return ptr::null_mut();
+    };
+    let Ok(Some(inputs)) = tree.tree.generate_exclusion_proof(&pubkey) else {
+        return ptr::null_mut();
+    };
+    CString::new(inputs.to_prover_toml(recent_slot))
+        .expect("TOML has no NUL bytes")
+        .into_raw()
+}
+
+/// Free a string from this library
+///
+/// # Safety
+///
+/// `string` is null or a string from this library not yet freed
+#[no_mangle]
+pub unsafe extern "C" fn smt_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Write `pubkey`'s leaf index to `index`
+///
+/// # Safety
+///
+/// `pubkey` points to 32 readable bytes and `index` to 32 writable bytes
+#[no_mangle]
+pub unsafe extern "C" fn smt_pubkey_to_index(pubkey: *const u8, index: *mut u8) -> i32 {
+    let Some(pubkey) = read32(pubkey) else {
+        return SMT_ERR_NULL;
+    };
+    if index.is_null() {
+        return SMT_ERR_NULL;
+    }
+    write(index, &smt::pubkey_to_index(&pubkey));
+    SMT_OK
+}
+
+/// Write the `SMT_PUBLIC_WITNESS_SIZE`-byte public witness for proving
+/// `pubkey` is excluded from `smt_root` at `recent_slot` to `witness`, as the
+/// program takes it with the proof
+///
+/// # Safety
+///
+/// `smt_root` and `pubkey` point to 32 readable bytes and `witness` to
+/// `SMT_PUBLIC_WITNESS_SIZE` writable bytes
+#[no_mangle]
+pub unsafe extern "C" fn smt_public_witness(
+    smt_root: *const u8,
+    pubkey: *const u8,
+    recent_slot: u64,
+    witness: *mut u8,
+) -> i32 {
+    let (Some(smt_root), Some(pubkey)) = (read32(smt_root), read32(pubkey)) else {
+        return SMT_ERR_NULL;
+    };
+    if witness.is_null() {
+        return SMT_ERR_NULL;
+    }
+    let pubkey_hash = smt::pubkey_to_index(&pubkey);
+    write(
+        witness,
+        &smt::inputs::public_witness(&smt_root, &pubkey_hash, recent_slot),
+    );
+    SMT_OK
+}
@@ -0,0 +1,123 @@
+//! Calls the C interface as a wallet would and checks it against the `smt`
+//! crate.
+
+use std::{ffi::CStr, ptr};
+
+use smt_exclusion::*;
+
+#[test]
+fn matches_smt_crate() {
+    let keys = [[1u8; 32], [2; 32], [3; 32]];
+    let mut expected = smt::SparseMerkleTree::new();
+    for key in &keys {
+        expected.insert(key).unwrap();
+    }
+    let customer = [9u8; 32];
+    let proof = expected.exclusion_proof(&customer).unwrap().unwrap();
+
+    unsafe {
+        let tree = smt_tree_new();
+        let mut added = 0;
+        assert_eq!(
+            smt_tree_insert(tree, keys.concat().as_ptr(), 3, &mut added),
+            SMT_OK
+        );
+        assert_eq!(added, 3);
+        assert_eq!(smt_tree_len(tree), 3);
+        let mut root = [0u8; 32];
+        assert_eq!(smt_tree_root(tree, root.as_mut_ptr()), SMT_OK);
+        assert_eq!(root, expected.root());
+
+        let mut pubkey_hash = [0u8; 32];
+        let mut siblings = vec![0u8; 32 * SMT_TREE_DEPTH];
+        assert_eq!(
+            smt_tree_exclusion_proof(
+                tree,
+                customer.as_ptr(),
+                pubkey_hash.as_mut_ptr(),
+                siblings.as_mut_ptr()
+            ),
+            SMT_OK
+        );
+        assert_eq!(pubkey_hash, proof.pubkey_hash);
+        assert_eq!(siblings, proof.siblings.concat());
+        assert_eq!(
+            smt_tree_exclusion_proof(
+                tree,
+                keys[0].as_ptr(),
+                pubkey_hash.as_mut_ptr(),
+                siblings.as_mut_ptr()
+            ),
+            SMT_BLACKLISTED
+        );
+
+        let mut index = [0u8; 32];
+        assert_eq!(
+            smt_pubkey_to_index(customer.as_ptr(), index.as_mut_ptr()),
+            SMT_OK
+        );
+        assert_eq!(index, proof.pubkey_hash);
+
+        let toml = smt_tree_prover_toml(tree, customer.as_ptr(), 7);
+        let inputs = expected
+            .generate_exclusion_proof(&customer)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            CStr::from_ptr(toml).to_str().unwrap(),
+            inputs.to_prover_toml(7)
+        );
+        smt_string_free(toml);
+        assert!(smt_tree_prover_toml(tree, keys[0].as_ptr(), 7).is_null());
+
+        let mut witness = [0u8; SMT_PUBLIC_WITNESS_SIZE];
+        assert_eq!(
+            smt_public_witness(root.as_ptr(), customer.as_ptr(), 7, witness.as_mut_ptr()),
+            SMT_OK
+        );
+        assert_eq!(witness, inputs.public_witness(7));
+
+        let mut removed = false;
+        assert_eq!(
+            smt_tree_delete(tree, keys[0].as_ptr(), &mut removed),
+            SMT_OK
+        );
+        assert!(removed);
+        assert_eq!(smt_tree_root(ptr::null(), root.as_mut_ptr()), SMT_ERR_NULL);
+        assert_eq!(smt_tree_len(ptr::null()), 0);
+        smt_tree_free(tree);
+        assert!(smt_tree_from_snapshot(b"not a snapshot".as_ptr(), 14).is_null());
+    }
+}
+
+#[test]
+fn rejects_a_second_pubkey_in_a_shallow_leaf() {
+    let mut seen = std::collections::HashMap::new();
+    let (first, second) = (0u32..)
+        .map(|i| {
+            let mut pubkey = [0u8; 32];
+            pubkey[..4].copy_from_slice(&i.to_le_bytes());
+            pubkey
+        })
+        .find_map(|pubkey| {
+            let index = smt::leaf_index(&smt::pubkey_to_index(&pubkey), 20);
+            seen.insert(index, pubkey).map(|first| (first, pubkey))
+        })
+        .unwrap();
+    let mut shallow = smt::SparseMerkleTree::with_depth(20);
+    shallow.insert(&first).unwrap();
+    let mut snapshot = Vec::new();
+    shallow.export_snapshot(&mut snapshot, false).unwrap();
+
+    unsafe {
+        let tree = smt_tree_from_snapshot(snapshot.as_ptr(), snapshot.len());
+        assert!(!tree.is_null());
+        let mut added = 0;
+        assert_eq!(
+            smt_tree_insert(tree, second.as_ptr(), 1, &mut added),
+            SMT_ERR_COLLISION
+        );
+        assert_eq!(smt_tree_len(tree), 1);
+        smt_tree_free(tree);
+    }
+}
@@ -0,0 +1,177 @@
+//! The blacklist tree for Python, through pyo3
+//!
+//! Wraps the `smt` crate, so a tree built from a Python pipeline has the same
+//! root as the server's, ready for the admin to set on chain. Build and install
+//! with `maturin develop` (or `maturin build` for a wheel).
+//!
+//! Pubkeys are 32 `bytes` or base58 `str`s; roots and hashes are `bytes`.
+
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+use smt::{inputs::field_hex, MemoryStore, Op, TREE_DEPTH};
+
+/// A pubkey as Python passes it
+#[derive(FromPyObject)]
+enum PubkeyArg {
+    Bytes(Vec<u8>),
+    Base58(String),
+}
+
+impl PubkeyArg {
+    fn bytes(&self) -> PyResult<[u8; 32]> {
+        let bytes = match self {
+            Self::Bytes(bytes) => bytes.clone(),
+            Self::Base58(key) => bs58::decode(key)
+                .into_vec()
+                .map_err(|error| PyValueError::new_err(format!("invalid pubkey {key}: {error}")))?,
+        };
+        bytes.as_slice().try_into().map_err(|_| {
+            PyValueError::new_err(format!(
+                "expected a 32-byte pubkey, got {} bytes",
+                bytes.len()
+            ))
+        })
+    }
+}
+
+/// Sibling hashes proving a pubkey's leaf is empty
+#[pyclass(frozen, name = "ExclusionProof")]
+pub struct ExclusionProof {
+    proof: smt::ExclusionProof,
+}
+
+#[pymethods]
+impl ExclusionProof {
+    /// The pubkey's leaf index (the circuit's public `pubkey_hash`)
+    #[getter]
+    fn pubkey_hash<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.proof.pubkey_hash)
+    }
+
+    /// `TREE_DEPTH` sibling hashes, leaf level first
+    #[getter]
+    fn siblings<'py>(&self, py: Python<'py>) -> Vec<Bound<'py, PyBytes>> {
+        self.proof
+            .siblings
+            .iter()
+            .map(|sibling| PyBytes::new(py, sibling))
+            .collect()
+    }
+
+    /// The root the siblings prove the empty leaf under
+    fn root<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.proof.root())
+    }
+}
+
+/// A sparse Merkle tree of blacklisted pubkeys, in memory
+#[pyclass(name = "SparseMerkleTree")]
+#[derive(Default)]
+pub struct SparseMerkleTree {
+    tree: smt::SparseMerkleTree,
+}
+
+#[pymethods]
+impl SparseMerkleTree {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// The tree in a snapshot; raises `ValueError` unless its leaves hash to
+    /// its root
+    #[staticmethod]
+    fn from_snapshot(snapshot: &[u8]) -> PyResult<Self> {
+        smt::SparseMerkleTree::import_snapshot(MemoryStore::default(), snapshot)
+            .map(|tree| Self { tree })
+            .map_err(|error| PyValueError::new_err(error.to_string()))
+    }
+
+    /// The tree's snapshot, zstd-compressed if `compressed`
+    #[pyo3(signature = (compressed = false))]
+    fn export_snapshot<'py>(
+        &self,
+        py: Python<'py>,
+        compressed: bool,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let mut snapshot = Vec::new();
+        self.tree
+            .export_snapshot(&mut snapshot, compressed)
+            .map_err(|error| PyValueError::new_err(error.to_string()))?;
+        Ok(PyBytes::new(py, &snapshot))
+    }
+
+    fn __len__(&self) -> usize {
+        self.tree.len()
+    }
+
+    fn __contains__(&self, pubkey: PubkeyArg) -> PyResult<bool> {
+        let Ok(contains) = self.tree.contains(&pubkey.bytes()?);
+        Ok(contains)
+    }
+
+    /// The root, big-endian, as `SET_SMT_ROOT` takes it
+    fn root<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.tree.root())
+    }
+
+    /// The root as a `0x` hex field
+    fn root_hex(&self) -> String {
+        field_hex(&self.tree.root())
+    }
+
+    /// Blacklist `pubkey`; False if it already was. Raises `ValueError` if a
+    /// shallow tree's leaf for it holds another pubkey.
+    fn insert(&mut self, pubkey: PubkeyArg) -> PyResult<bool> {
+        self.tree
+            .insert(&pubkey.bytes()?)
+            .map_err(|error| PyValueError::new_err(error.to_string()))
+    }
+
+    /// Remove `pubkey` from the blacklist; False if it wasn't on it
+    fn delete(&mut self, pubkey: PubkeyArg) -> PyResult<bool> {
+        self.tree
+            .delete(&pubkey.bytes()?)
+            .map_err(|error| PyValueError::new_err(error.to_string()))
+    }
+
+    /// Blacklist every pubkey in `pubkeys`, hashing each changed node once.
+    /// Returns how many were added; raises `ValueError`, adding none, if a
+    /// shallow tree's leaf for one holds another pubkey.
+    fn insert_many(&mut self, pubkeys: Vec<PubkeyArg>) -> PyResult<usize> {
+        let ops = pubkeys
+            .iter()
+            .map(|pubkey| Ok(Op::Insert(pubkey.bytes()?)))
+            .collect::<PyResult<Vec<Op>>>()?;
+        self.tree
+            .apply_batch(&ops)
+            .map_err(|error| PyValueError::new_err(error.to_string()))
+    }
+
+    /// Siblings proving `pubkey` is not blacklisted, or None if it is
+    fn exclusion_proof(&self, pubkey: PubkeyArg) -> PyResult<Option<ExclusionProof>> {
+        let Ok(proof) = self.tree.exclusion_proof(&pubkey.bytes()?);
+        Ok(proof.map(|proof| ExclusionProof { proof }))
+    }
+
+    /// `Prover.toml` proving `pubkey` is not blacklisted at `recent_slot`, or
+    /// None if it is
+    fn prover_toml(&self, pubkey: PubkeyArg, recent_slot: u64) -> PyResult<Option<String>> {
+        let Ok(inputs) = self.tree.generate_exclusion_proof(&pubkey.bytes()?);
+        Ok(inputs.map(|inputs| inputs.to_prover_toml(recent_slot)))
+    }
+}
+
+/// A pubkey's leaf index, big-endian
+#[pyfunction]
+fn pubkey_to_index<'py>(py: Python<'py>, pubkey: PubkeyArg) -> PyResult<Bound<'py, PyBytes>> {
+    Ok(PyBytes::new(py, &smt::pubkey_to_index(&pubkey.bytes()?)))
+}
+
+#[pymodule]
+pub fn exclusion_smt(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add("TREE_DEPTH", TREE_DEPTH)?;
+    module.add_class::<SparseMerkleTree>()?;
+    module.add_class::<ExclusionProof>()?;
+    module.add_function(wrap_pyfunction!(pubkey_to_index, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+//! Runs the module from embedded Python against the `smt` crate's roots.
+
+use pyo3::{ffi::c_str, prelude::*, types::PyDict};
+
+#[test]
+fn matches_smt_crate() {
+    let mut expected = smt::SparseMerkleTree::new();
+    for i in 1..=3u8 {
+        expected.insert(&[i; 32]).unwrap();
+    }
+    let customer = [9u8; 32];
+    let proof = expected.exclusion_proof(&customer).unwrap().unwrap();
+
+    Python::with_gil(|py| {
+        let module = pyo3::wrap_pymodule!(exclusion_smt::exclusion_smt)(py);
+        let locals = PyDict::new(py);
+        locals.set_item("m", module).unwrap();
+        locals
+            .set_item("expected_root", expected.root().to_vec())
+            .unwrap();
+        locals
+            .set_item("expected_hash", proof.pubkey_hash.to_vec())
+            .unwrap();
+        py.run(
+            c_str!(
+                r#"
+tree = m.SparseMerkleTree()
+assert tree.insert(bytes([1] * 32))
+assert not tree.insert(bytes([1] * 32))
+# Base58 strings work too: [2] * 32 and [3] * 32
+assert tree.insert_many(["8qbHbw2BbbTHBW1sbeqakYXVKRQM8Ne7pLK7m6CVfeR", "CktRuQ2mttgRGkXJtyksdKHjUdc2C4TgDzyB98oEzy8"]) == 2
+assert len(tree) == 3 and bytes([2] * 32) in tree
+assert tree.root() == bytes(expected_root)
+assert tree.root_hex() == "0x" + bytes(expected_root).hex()
+
+proof = tree.exclusion_proof(bytes([9] * 32))
+assert proof.pubkey_hash == bytes(expected_hash) == m.pubkey_to_index(bytes([9] * 32))
+assert len(proof.siblings) == m.TREE_DEPTH
+assert proof.root() == tree.root()
+assert tree.exclusion_proof(bytes([1] * 32)) is None
+assert "recent_slot = \"7\"" in tree.prover_toml(bytes([9] * 32), 7)
+
+copy = m.SparseMerkleTree.from_snapshot(tree.export_snapshot(compressed=True))
+assert copy.root() == tree.root()
+try:
+    tree.insert(b"short")
+    raise AssertionError("accepted a short pubkey")
+except ValueError:
+    pass
+"#
+            ),
+            None,
+            Some(&locals),
+        )
+        .unwrap();
+    });
+}
@@ -0,0 +1,155 @@
+//! The blacklist tree for the browser, through wasm-bindgen
+//!
+//! Wraps the `smt` crate, so a dApp gets the same roots and proof inputs as
+//! the server and the Rust client, and can prove exclusion with `noir_js`
+//! without a backend. Build with `wasm-pack build --target web`.
+//!
+//! Pubkeys are 32-byte `Uint8Array`s; slots are `bigint`s.
+
+use serde::Serialize;
+use smt::{inputs::field_hex, ExclusionProofInputs, MemoryStore, Op};
+use wasm_bindgen::prelude::*;
+
+fn pubkey(bytes: &[u8]) -> Result<[u8; 32], JsError> {
+    bytes.try_into().map_err(|_| {
+        JsError::new(&format!(
+            "expected a 32-byte pubkey, got {} bytes",
+            bytes.len()
+        ))
+    })
+}
+
+/// The exclusion circuit's inputs as `noir_js` takes them: fields as `0x` hex
+/// strings, the pubkey as bytes
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CircuitInputs {
+    pub smt_root: String,
+    pub pubkey_hash: String,
+    pub recent_slot: String,
+    pub pubkey: Vec<u8>,
+    pub siblings: Vec<String>,
+    pub leaf_value: String,
+}
+
+impl CircuitInputs {
+    pub fn new(inputs: &ExclusionProofInputs, recent_slot: u64) -> Self {
+        Self {
+            smt_root: field_hex(&inputs.smt_root),
+            pubkey_hash: field_hex(&inputs.pubkey_hash),
+            recent_slot: recent_slot.to_string(),
+            pubkey: inputs.pubkey.to_vec(),
+            siblings: inputs.siblings.iter().map(field_hex).collect(),
+            leaf_value: field_hex(&inputs.leaf_value),
+        }
+    }
+}
+
+/// A sparse Merkle tree of blacklisted pubkeys, in memory
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct SparseMerkleTree {
+    tree: smt::SparseMerkleTree,
+}
+
+#[wasm_bindgen]
+impl SparseMerkleTree {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The tree in a snapshot, as `export_snapshot` writes it. Fails unless its
+    /// leaves hash to its root.
+    #[wasm_bindgen(js_name = fromSnapshot)]
+    pub fn from_snapshot(snapshot: &[u8]) -> Result<SparseMerkleTree, JsError> {
+        smt::SparseMerkleTree::import_snapshot(MemoryStore::default(), snapshot)
+            .map(|tree| Self { tree })
+            .map_err(|error| JsError::new(&error.to_string()))
+    }
+
+    /// The tree's snapshot, uncompressed
+    #[wasm_bindgen(js_name = exportSnapshot)]
+    pub fn export_snapshot(&self) -> Result<Vec<u8>, JsError> {
+        let mut snapshot = Vec::new();
+        self.tree
+            .export_snapshot(&mut snapshot, false)
+            .map_err(|error| JsError::new(&error.to_string()))?;
+        Ok(snapshot)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// The root, big-endian
+    pub fn root(&self) -> Vec<u8> {
+        self.tree.root().to_vec()
+    }
+
+    /// The root as a `0x` hex field
+    #[wasm_bindgen(js_name = rootHex)]
+    pub fn root_hex(&self) -> String {
+        field_hex(&self.tree.root())
+    }
+
+    pub fn contains(&self, key: &[u8]) -> Result<bool, JsError> {
+        let Ok(contains) = self.tree.contains(&pubkey(key)?);
+        Ok(contains)
+    }
+
+    /// Blacklist `key`; false if it already was. Throws if a shallow tree's
+    /// leaf for it holds another pubkey.
+    pub fn insert(&mut self, key: &[u8]) -> Result<bool, JsError> {
+        Ok(self.tree.insert(&pubkey(key)?)?)
+    }
+
+    /// Remove `key` from the blacklist; false if it wasn't on it
+    pub fn delete(&mut self, key: &[u8]) -> Result<bool, JsError> {
+        Ok(self.tree.delete(&pubkey(key)?)?)
+    }
+
+    /// Blacklist every pubkey in `keys`, 32 bytes each back to back, hashing
+    /// each changed node once. Returns how many were added; throws, adding
+    /// none, if a shallow tree's leaf for one holds another pubkey.
+    #[wasm_bindgen(js_name = insertBatch)]
+    pub fn insert_batch(&mut self, keys: &[u8]) -> Result<usize, JsError> {
+        if !keys.len().is_multiple_of(32) {
+            return Err(JsError::new("keys must be 32 bytes each"));
+        }
+        let ops: Vec<Op> = keys
+            .chunks_exact(32)
+            .map(|key| Op::Insert(key.try_into().expect("32 bytes")))
+            .collect();
+        Ok(self.tree.apply_batch(&ops)?)
+    }
+
+    /// The inputs proving `key` is not blacklisted, for `noir.execute`, or
+    /// `null` if it is
+    #[wasm_bindgen(js_name = exclusionInputs)]
+    pub fn exclusion_inputs(&self, key: &[u8], recent_slot: u64) -> Result<JsValue, JsError> {
+        match self.circuit_inputs(key, recent_slot)? {
+            Some(inputs) => Ok(serde_wasm_bindgen::to_value(&inputs)?),
+            None => Ok(JsValue::NULL),
+        }
+    }
+
+    /// `Prover.toml` proving `key` is not blacklisted, or `undefined` if it is
+    #[wasm_bindgen(js_name = proverToml)]
+    pub fn prover_toml(&self, key: &[u8], recent_slot: u64) -> Result<Option<String>, JsError> {
+        let Ok(inputs) = self.tree.generate_exclusion_proof(&pubkey(key)?);
+        Ok(inputs.map(|inputs| inputs.to_prover_toml(recent_slot)))
+    }
+}
+
+impl SparseMerkleTree {
+    /// `exclusionInputs` before conversion to a JS object
+    pub fn circuit_inputs(
+        &self,
+        key: &[u8],
+        recent_slot: u64,
+    ) -> Result<Option<CircuitInputs>, JsError> {
+        let Ok(inputs) = self.tree.generate_exclusion_proof(&pubkey(key)?);
+        Ok(inputs.map(|inputs| CircuitInputs::new(&inputs, recent_slot)))
+    }
+}
@@ -0,0 +1,42 @@
+//! Checks the bindings give the same roots and inputs as the `smt` crate.
+
+use smt_wasm::SparseMerkleTree;
+
+#[test]
+fn matches_smt_crate() {
+    let keys: Vec<[u8; 32]> = (1..=5u8).map(|i| [i; 32]).collect();
+    let mut expected = smt::SparseMerkleTree::new();
+    for key in &keys {
+        expected.insert(key).unwrap();
+    }
+
+    let mut tree = SparseMerkleTree::new();
+    assert!(tree.insert(&keys[0]).unwrap());
+    assert!(!tree.insert(&keys[0]).unwrap());
+    assert_eq!(tree.insert_batch(&keys[1..].concat()).unwrap(), 4);
+    assert_eq!(tree.size(), 5);
+    assert_eq!(tree.root(), expected.root());
+    assert_eq!(tree.root_hex(), smt::inputs::field_hex(&expected.root()));
+    assert!(tree.contains(&keys[2]).unwrap());
+
+    let customer = [9u8; 32];
+    let inputs = expected
+        .generate_exclusion_proof(&customer)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        tree.prover_toml(&customer, 42).unwrap().unwrap(),
+        inputs.to_prover_toml(42)
+    );
+    let circuit = tree.circuit_inputs(&customer, 42).unwrap().unwrap();
+    assert_eq!(circuit.recent_slot, "42");
+    assert_eq!(circuit.pubkey, customer);
+    assert_eq!(circuit.siblings.len(), smt::TREE_DEPTH);
+    assert_eq!(circuit.smt_root, tree.root_hex());
+    assert!(tree.circuit_inputs(&keys[0], 42).unwrap().is_none());
+
+    let copy = SparseMerkleTree::from_snapshot(&tree.export_snapshot().unwrap()).unwrap();
+    assert_eq!(copy.root(), tree.root());
+    assert!(tree.delete(&keys[0]).unwrap());
+    assert_ne!(copy.root(), tree.root());
+}
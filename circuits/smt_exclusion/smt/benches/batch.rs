@@ -0,0 +1,66 @@
+//! Loading a list one insert at a time versus in one batch, and a daily update
+//! to a loaded list
+//!
+//! Run with: cargo bench
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use smt::{Op, SparseMerkleTree};
+
+fn keys(count: u32, seed: u32) -> Vec<[u8; 32]> {
+    (0..count)
+        .map(|i| {
+            let mut key = [0u8; 32];
+            key[..4].copy_from_slice(&i.to_le_bytes());
+            key[4..8].copy_from_slice(&seed.to_le_bytes());
+            key
+        })
+        .collect()
+}
+
+fn load(c: &mut Criterion) {
+    let keys = keys(256, 0);
+    let mut group = c.benchmark_group("load_256");
+    group.sample_size(10);
+    group.bench_function("insert", |b| {
+        b.iter(|| {
+            let mut tree = SparseMerkleTree::new();
+            for key in &keys {
+                tree.insert(key).unwrap();
+            }
+            tree
+        })
+    });
+    group.bench_function("apply_batch", |b| {
+        let ops: Vec<Op> = keys.iter().copied().map(Op::Insert).collect();
+        b.iter(|| {
+            let mut tree = SparseMerkleTree::new();
+            tree.apply_batch(&ops).unwrap();
+            tree
+        })
+    });
+    group.finish();
+}
+
+fn update(c: &mut Criterion) {
+    let mut tree = SparseMerkleTree::new();
+    let loaded = keys(4096, 0);
+    tree.apply_batch(&loaded.iter().copied().map(Op::Insert).collect::<Vec<_>>())
+        .unwrap();
+    // A day's changes: 32 additions and 32 removals
+    let mut ops: Vec<Op> = keys(32, 1).into_iter().map(Op::Insert).collect();
+    ops.extend(loaded[..32].iter().copied().map(Op::Delete));
+
+    let mut group = c.benchmark_group("update_4096");
+    group.sample_size(10);
+    group.bench_function("apply_batch_64", |b| {
+        b.iter_batched(
+            || tree.clone(),
+            |mut tree| tree.apply_batch(&ops).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, load, update);
+criterion_main!(benches);
@@ -0,0 +1,161 @@
+//! Reads from a tree of 50 million leaves in memory-mapped files
+//!
+//! The tree is synthetic: leaves spread evenly over the index space, and
+//! cached nodes with filler hashes where two or more leaves share a node. The
+//! store's layout and size are a real list's, so lookups page in as they
+//! would, but its root and proofs are meaningless. It is written once, about
+//! 5 GB, under the target directory, and reused.
+//!
+//! Run with: cargo bench --features mmap --bench mmap
+//! Set `SMT_MMAP_LEAVES` for another size.
+
+use std::{env, hint::black_box, path::Path};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use smt::{mmap_store::MmapStore, store::Checkpoint, Op, SparseMerkleTree, Store, TREE_DEPTH};
+
+fn splitmix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// The top 64 bits of leaf `i` of `count`: increasing, below 2^62 so the
+/// index fits 254 bits
+fn high(i: u64, count: u64) -> u64 {
+    let step = (1 << 62) / count;
+    i * step + splitmix(i) % step
+}
+
+/// Leaf `i` of `count`, in index order
+fn leaf(i: u64, count: u64) -> [u8; 32] {
+    let mut index = [0u8; 32];
+    index[..8].copy_from_slice(&high(i, count).to_be_bytes());
+    for (j, chunk) in index[8..].chunks_exact_mut(8).enumerate() {
+        chunk.copy_from_slice(&splitmix((i << 2) | j as u64).to_be_bytes());
+    }
+    index
+}
+
+/// A field element standing in for a node's hash
+fn filler(height: usize, high: u64) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash[1..9].copy_from_slice(&splitmix(high ^ ((height as u64) << 56)).to_be_bytes());
+    hash
+}
+
+/// The cached nodes, in store order: by height, then first index. Leaves
+/// differ in their top 64 bits, which sit above height 192, so only nodes
+/// from there up are above two or more.
+fn nodes(count: u64) -> impl Iterator<Item = ((usize, [u8; 32]), [u8; 32])> {
+    (193..=TREE_DEPTH).flat_map(move |height| {
+        let shift = height - 192;
+        let mut i = 0;
+        std::iter::from_fn(move || {
+            while i < count {
+                let group = high(i, count) >> shift;
+                let start = i;
+                while i < count && high(i, count) >> shift == group {
+                    i += 1;
+                }
+                if i - start >= 2 {
+                    let mut first = [0u8; 32];
+                    first[..8].copy_from_slice(&(group << shift).to_be_bytes());
+                    return Some(((height, first), filler(height, group)));
+                }
+            }
+            None
+        })
+    })
+}
+
+fn tree(count: u64) -> SparseMerkleTree<MmapStore> {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("mmap-{count}"));
+    let store = MmapStore::open(&dir).unwrap();
+    if store.checkpoint().unwrap().map(|c| c.leaf_count) == Some(count) {
+        return SparseMerkleTree::open(store).unwrap();
+    }
+    drop(store);
+    std::fs::remove_dir_all(&dir).unwrap();
+    let checkpoint = Checkpoint {
+        root: filler(TREE_DEPTH, 0),
+        leaf_count: count,
+        sequence: 1,
+    };
+    // Each leaf of a 254-level tree holds its index
+    let leaves = (0..count).map(|i| (leaf(i, count), leaf(i, count)));
+    let store = MmapStore::bulk_load(&dir, leaves, nodes(count), checkpoint).unwrap();
+    SparseMerkleTree::open(store).unwrap()
+}
+
+fn reads(c: &mut Criterion) {
+    let count = env::var("SMT_MMAP_LEAVES")
+        .map(|count| count.parse().expect("SMT_MMAP_LEAVES is a number"))
+        .unwrap_or(50_000_000);
+    let mut tree = tree(count);
+    let store = tree.store();
+
+    let mut group = c.benchmark_group(format!("mmap_{count}"));
+    let mut i = 0u64;
+    group.bench_function("contains_leaf", |b| {
+        b.iter(|| {
+            i = splitmix(i);
+            store.contains(black_box(&leaf(i % count, count))).unwrap()
+        })
+    });
+    group.bench_function("contains_miss", |b| {
+        b.iter(|| {
+            i = splitmix(i);
+            let mut index = leaf(i % count, count);
+            index[31] ^= 1;
+            store.contains(black_box(&index)).unwrap()
+        })
+    });
+    group.bench_function("node", |b| {
+        b.iter(|| {
+            i = splitmix(i);
+            let mut first = [0u8; 32];
+            first[..8].copy_from_slice(&(high(i % count, count) >> 20 << 20).to_be_bytes());
+            store.node(black_box(212), &first).unwrap()
+        })
+    });
+    group.bench_function("leaves_under_height_220", |b| {
+        b.iter(|| {
+            i = splitmix(i);
+            let high = high(i % count, count) >> 28 << 28;
+            let mut first = [0u8; 32];
+            first[..8].copy_from_slice(&high.to_be_bytes());
+            let mut last = [0xff; 32];
+            last[..8].copy_from_slice(&(high | ((1 << 28) - 1)).to_be_bytes());
+            store.leaves(&first, &last).count()
+        })
+    });
+    group.sample_size(10);
+    group.bench_function("exclusion_proof", |b| {
+        b.iter(|| {
+            i = splitmix(i);
+            let mut pubkey = [0u8; 32];
+            pubkey[..8].copy_from_slice(&i.to_le_bytes());
+            tree.exclusion_proof(black_box(&pubkey)).unwrap()
+        })
+    });
+    group.finish();
+
+    // Writes go to the overlay: add and remove the same 64 pubkeys
+    let pubkeys: Vec<[u8; 32]> = (0..64u8).map(|i| [i; 32]).collect();
+    let insert: Vec<Op> = pubkeys.iter().copied().map(Op::Insert).collect();
+    let delete: Vec<Op> = pubkeys.iter().copied().map(Op::Delete).collect();
+    let mut group = c.benchmark_group(format!("mmap_{count}"));
+    group.sample_size(10);
+    group.bench_function("apply_batch_64_and_back", |b| {
+        b.iter(|| {
+            tree.apply_batch(&insert).unwrap();
+            tree.apply_batch(&delete).unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, reads);
+criterion_main!(benches);
@@ -0,0 +1,38 @@
+//! Building a list on one thread versus rayon's whole pool
+//!
+//! The speedup tracks the core count; set `RAYON_NUM_THREADS` to compare
+//! others. Run with: cargo bench --bench parallel
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rayon::ThreadPoolBuilder;
+use smt::{Op, SparseMerkleTree};
+
+fn build(c: &mut Criterion) {
+    let ops: Vec<Op> = (0..1024u32)
+        .map(|i| {
+            let mut key = [0u8; 32];
+            key[..4].copy_from_slice(&i.to_le_bytes());
+            Op::Insert(key)
+        })
+        .collect();
+    let single = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+    let all = ThreadPoolBuilder::new().build().unwrap();
+
+    let mut group = c.benchmark_group("build_1024");
+    group.sample_size(10);
+    for (name, pool) in [("1_thread", &single), ("all_threads", &all)] {
+        group.bench_function(format!("{name}_{}", pool.current_num_threads()), |b| {
+            b.iter(|| {
+                pool.install(|| {
+                    let mut tree = SparseMerkleTree::new();
+                    tree.apply_batch(&ops).unwrap();
+                    tree
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, build);
+criterion_main!(benches);
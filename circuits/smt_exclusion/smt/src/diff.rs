@@ -0,0 +1,122 @@
+//! The leaves two trees disagree on, for applying a list refresh as a batch
+//!
+//! Trees hold pubkey hashes, not pubkeys, so a diff is in pubkey hashes: it
+//! turns one tree into the other with [`SparseMerkleTree::apply_leaf_ops`],
+//! but names the pubkeys only to whoever knows the lists. Subtrees whose
+//! cached hashes match are skipped, so diffing two versions of a list costs
+//! about the change, not the list. Nothing is hashed.
+
+use std::cmp::Ordering;
+
+use crate::{last_under, leaf_index, sibling_of, SparseMerkleTree, Store, TreeError, TREE_DEPTH};
+
+/// One change to the blacklist, by pubkey hash
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LeafOp {
+    Insert([u8; 32]),
+    Delete([u8; 32]),
+}
+
+impl LeafOp {
+    pub fn pubkey_hash(&self) -> &[u8; 32] {
+        match self {
+            LeafOp::Insert(pubkey_hash) | LeafOp::Delete(pubkey_hash) => pubkey_hash,
+        }
+    }
+}
+
+/// Whether `store` has two or more leaves under the node of `height` levels
+/// whose first index is `first`
+fn shared<S: Store>(store: &S, first: &[u8; 32], height: usize) -> Result<bool, S::Error> {
+    let mut leaves = store.leaves(first, &last_under(first, height));
+    Ok(leaves.next().transpose()?.is_some() && leaves.next().transpose()?.is_some())
+}
+
+impl<S: Store> SparseMerkleTree<S> {
+    /// The fewest leaf changes that turn this tree into `other`, in leaf index
+    /// order: inserts for `other`'s pubkey hashes this tree lacks, deletes for
+    /// the reverse. A shallow tree's leaf passing to another pubkey is a delete,
+    /// then an insert.
+    ///
+    /// # Panics
+    ///
+    /// If the trees have different depths
+    pub fn diff(&self, other: &Self) -> Result<Vec<LeafOp>, S::Error> {
+        assert_eq!(self.depth, other.depth, "trees of different depths");
+        let mut ops = Vec::new();
+        self.diff_under(other, &[0; 32], self.depth, &mut ops)?;
+        Ok(ops)
+    }
+
+    /// The ops under the node of `height` levels whose first index is `first`
+    fn diff_under(
+        &self,
+        other: &Self,
+        first: &[u8; 32],
+        height: usize,
+        ops: &mut Vec<LeafOp>,
+    ) -> Result<(), S::Error> {
+        if shared(&self.store, first, height)? && shared(&other.store, first, height)? {
+            // Both nodes are cached; equal hashes mean equal leaves
+            if self.store.node(height, first)? == other.store.node(height, first)? {
+                return Ok(());
+            }
+            let below = height - 1;
+            self.diff_under(other, first, below, ops)?;
+            return self.diff_under(other, &sibling_of(first, below), below, ops);
+        }
+        // One side has a leaf at most: walk both
+        let last = last_under(first, height);
+        let ours = self
+            .store
+            .leaves(first, &last)
+            .collect::<Result<Vec<_>, _>>()?;
+        let theirs = other
+            .store
+            .leaves(first, &last)
+            .collect::<Result<Vec<_>, _>>()?;
+        let (mut ours, mut theirs) = (ours.into_iter().peekable(), theirs.into_iter().peekable());
+        loop {
+            let order = match (ours.peek(), theirs.peek()) {
+                (None, None) => return Ok(()),
+                (Some((our, _)), Some((their, _))) => our.cmp(their),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+            };
+            match order {
+                Ordering::Equal => {
+                    let (_, our) = ours.next().expect("peeked");
+                    let (_, their) = theirs.next().expect("peeked");
+                    if our != their {
+                        ops.extend([LeafOp::Delete(our), LeafOp::Insert(their)]);
+                    }
+                }
+                Ordering::Less => ops.push(LeafOp::Delete(ours.next().expect("peeked").1)),
+                Ordering::Greater => ops.push(LeafOp::Insert(theirs.next().expect("peeked").1)),
+            }
+        }
+    }
+
+    /// [`apply_batch`](Self::apply_batch) for ops by pubkey hash, such as a
+    /// [`diff`](Self::diff)
+    ///
+    /// # Panics
+    ///
+    /// If a pubkey hash has bits beyond the field's 254
+    pub fn apply_leaf_ops(&mut self, ops: &[LeafOp]) -> Result<usize, TreeError<S::Error>> {
+        let hashed = ops
+            .iter()
+            .map(|op| {
+                let pubkey_hash = *op.pubkey_hash();
+                assert_eq!(
+                    leaf_index(&pubkey_hash, TREE_DEPTH),
+                    pubkey_hash,
+                    "not a pubkey hash"
+                );
+                (pubkey_hash, matches!(op, LeafOp::Insert(_)))
+            })
+            .collect();
+        let batch = self.stage_hashed(hashed)?;
+        self.commit(batch).map_err(TreeError::Store)
+    }
+}
@@ -0,0 +1,367 @@
+//! An indexed Merkle tree of the blacklist, for cheaper exclusion proofs
+//!
+//! Aztec-style: each leaf holds a key (a pubkey's [`pubkey_to_index`]), and
+//! the position and key of the next larger one, so the leaves form a sorted
+//! linked list. A key is excluded when some "low leaf" below it points past
+//! it, which takes one membership path of [`INDEXED_TREE_DEPTH`] levels
+//! instead of the sparse tree's 254. `circuits/imt_exclusion` checks it with
+//! the same public inputs, so the program takes its proofs in the same
+//! witness, from the indexed verifier.
+//!
+//! Leaf 0 is the list's head, with key 0; a next key of 0 marks the largest.
+//! Keys take the next free position as they are inserted, so the root
+//! depends on insertion order, unlike the sparse tree's. Deleted keys are
+//! unlinked and their position emptied, not reused.
+
+use std::{cell::RefCell, collections::BTreeMap, sync::OnceLock};
+
+use ark_bn254::Fr;
+use light_poseidon::{Poseidon, PoseidonBytesHasher};
+use serde::Serialize;
+
+use crate::{
+    inputs::{public_witness, PUBLIC_WITNESS_SIZE},
+    poseidon_hash_2,
+    prover_toml::{self, Decimal, Field},
+    pubkey_to_index,
+};
+
+/// Tree depth (must match `circuits/imt_exclusion`'s TREE_DEPTH): room for
+/// 2^32 keys, counting deleted ones
+pub const INDEXED_TREE_DEPTH: usize = 32;
+
+/// Hash of an unused position
+const EMPTY_NODE: [u8; 32] = [0u8; 32];
+
+thread_local! {
+    static POSEIDON_3: RefCell<Poseidon<Fr>> =
+        RefCell::new(Poseidon::<Fr>::new_circom(3).expect("three inputs are supported"));
+}
+
+/// Circom-compatible Poseidon hash of three big-endian field elements (the
+/// circuit's `poseidon_hash_3`)
+pub fn poseidon_hash_3(a: &[u8; 32], b: &[u8; 32], c: &[u8; 32]) -> [u8; 32] {
+    POSEIDON_3
+        .with_borrow_mut(|poseidon| poseidon.hash_bytes_be(&[a, b, c]))
+        .expect("inputs are field elements")
+}
+
+/// Root of an unused subtree of `height` levels
+fn empty_hash(height: usize) -> [u8; 32] {
+    static EMPTY_HASHES: OnceLock<Vec<[u8; 32]>> = OnceLock::new();
+    EMPTY_HASHES.get_or_init(|| {
+        let mut hashes = vec![EMPTY_NODE];
+        for level in 0..INDEXED_TREE_DEPTH {
+            hashes.push(poseidon_hash_2(&hashes[level], &hashes[level]));
+        }
+        hashes
+    })[height]
+}
+
+/// A position as a big-endian field element
+fn position_field(position: u64) -> [u8; 32] {
+    let mut field = [0u8; 32];
+    field[24..].copy_from_slice(&position.to_be_bytes());
+    field
+}
+
+/// One key in the sorted list
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Leaf {
+    /// The key, big-endian
+    pub value: [u8; 32],
+    /// Position of the next larger key's leaf (0 if this is the largest)
+    pub next_index: u64,
+    /// The next larger key (0 if this is the largest)
+    pub next_value: [u8; 32],
+}
+
+impl Leaf {
+    /// The leaf's hash, as the circuit computes it
+    pub fn hash(&self) -> [u8; 32] {
+        poseidon_hash_3(
+            &self.value,
+            &position_field(self.next_index),
+            &self.next_value,
+        )
+    }
+
+    /// Whether `key` falls strictly between this leaf's key and the next
+    pub fn is_low_leaf_of(&self, key: &[u8; 32]) -> bool {
+        self.value < *key && (self.next_value == [0u8; 32] || *key < self.next_value)
+    }
+}
+
+/// A low leaf and its path, proving a key is not in the tree
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexedExclusionProof {
+    /// The excluded key (the circuit's public `pubkey_hash`)
+    pub pubkey_hash: [u8; 32],
+    pub low_leaf: Leaf,
+    /// The low leaf's position; its little-endian bits are the path
+    pub low_leaf_index: u64,
+    /// `INDEXED_TREE_DEPTH` sibling hashes, leaf level first
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl IndexedExclusionProof {
+    /// The root the low leaf's path leads to, as the circuit recomputes it
+    pub fn root(&self) -> [u8; 32] {
+        self.siblings
+            .iter()
+            .enumerate()
+            .fold(self.low_leaf.hash(), |current, (level, sibling)| {
+                match self.low_leaf_index >> level & 1 {
+                    1 => poseidon_hash_2(sibling, &current),
+                    _ => poseidon_hash_2(&current, sibling),
+                }
+            })
+    }
+
+    /// Whether this proves `pubkey_hash` is not under `root`
+    pub fn verify(&self, root: &[u8; 32]) -> bool {
+        self.low_leaf.is_low_leaf_of(&self.pubkey_hash) && self.root() == *root
+    }
+}
+
+/// An indexed Merkle tree of blacklisted pubkeys, in memory
+#[derive(Clone, Debug)]
+pub struct IndexedMerkleTree {
+    /// Every position used so far; `None` once deleted
+    leaves: Vec<Option<Leaf>>,
+    /// Position of each key's leaf, head included
+    positions: BTreeMap<[u8; 32], u64>,
+    /// Hashes of used nodes by level, leaf level first; beyond the end of a
+    /// level, nodes are unused
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl Default for IndexedMerkleTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IndexedMerkleTree {
+    /// A tree holding only the head leaf
+    pub fn new() -> Self {
+        let mut tree = Self {
+            leaves: Vec::new(),
+            positions: BTreeMap::new(),
+            levels: vec![Vec::new(); INDEXED_TREE_DEPTH + 1],
+        };
+        tree.set(0, Some(Leaf::default()));
+        tree.positions.insert([0u8; 32], 0);
+        tree
+    }
+
+    /// Blacklisted pubkeys in the tree
+    pub fn len(&self) -> usize {
+        self.positions.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.levels[INDEXED_TREE_DEPTH][0]
+    }
+
+    pub fn contains(&self, pubkey: &[u8; 32]) -> bool {
+        self.positions.contains_key(&pubkey_to_index(pubkey))
+    }
+
+    /// The leaf at `position`, if it is in use
+    pub fn leaf(&self, position: u64) -> Option<Leaf> {
+        self.leaves.get(position as usize).copied().flatten()
+    }
+
+    /// Position and leaf of the largest key below `key`
+    fn low_leaf(&self, key: &[u8; 32]) -> (u64, Leaf) {
+        let (_, &position) = self
+            .positions
+            .range(..*key)
+            .next_back()
+            .expect("the head's key 0 is below every other");
+        (
+            position,
+            self.leaf(position).expect("listed leaves are used"),
+        )
+    }
+
+    /// Blacklist `pubkey`; false if it already was
+    ///
+    /// # Panics
+    ///
+    /// If all 2^32 positions have been used.
+    pub fn insert(&mut self, pubkey: &[u8; 32]) -> bool {
+        let key = pubkey_to_index(pubkey);
+        if self.positions.contains_key(&key) {
+            return false;
+        }
+        let position = self.leaves.len() as u64;
+        assert!(position < 1 << INDEXED_TREE_DEPTH, "indexed tree is full");
+        let (low_position, low) = self.low_leaf(&key);
+        self.set(
+            position,
+            Some(Leaf {
+                value: key,
+                next_index: low.next_index,
+                next_value: low.next_value,
+            }),
+        );
+        self.set(
+            low_position,
+            Some(Leaf {
+                next_index: position,
+                next_value: key,
+                ..low
+            }),
+        );
+        self.positions.insert(key, position);
+        true
+    }
+
+    /// Remove `pubkey` from the blacklist; false if it wasn't on it
+    pub fn delete(&mut self, pubkey: &[u8; 32]) -> bool {
+        let key = pubkey_to_index(pubkey);
+        let Some(position) = self.positions.remove(&key) else {
+            return false;
+        };
+        let removed = self.leaf(position).expect("listed leaves are used");
+        let (low_position, low) = self.low_leaf(&key);
+        self.set(
+            low_position,
+            Some(Leaf {
+                next_index: removed.next_index,
+                next_value: removed.next_value,
+                ..low
+            }),
+        );
+        self.set(position, None);
+        true
+    }
+
+    /// The low leaf and path proving `pubkey` is not blacklisted, or `None` if
+    /// it is
+    pub fn exclusion_proof(&self, pubkey: &[u8; 32]) -> Option<IndexedExclusionProof> {
+        let key = pubkey_to_index(pubkey);
+        if self.positions.contains_key(&key) {
+            return None;
+        }
+        let (low_leaf_index, low_leaf) = self.low_leaf(&key);
+        let siblings = (0..INDEXED_TREE_DEPTH)
+            .map(|level| self.node(level, (low_leaf_index >> level) ^ 1))
+            .collect();
+        Some(IndexedExclusionProof {
+            pubkey_hash: key,
+            low_leaf,
+            low_leaf_index,
+            siblings,
+        })
+    }
+
+    /// Everything `circuits/imt_exclusion` takes to prove `pubkey` is not
+    /// blacklisted, or `None` if it is
+    pub fn generate_exclusion_proof(&self, pubkey: &[u8; 32]) -> Option<IndexedExclusionInputs> {
+        let proof = self.exclusion_proof(pubkey)?;
+        Some(IndexedExclusionInputs {
+            smt_root: self.root(),
+            pubkey: *pubkey,
+            proof,
+        })
+    }
+
+    /// Hash of the node at `level` and `offset` along it
+    fn node(&self, level: usize, offset: u64) -> [u8; 32] {
+        self.levels[level]
+            .get(offset as usize)
+            .copied()
+            .unwrap_or_else(|| empty_hash(level))
+    }
+
+    /// Put `leaf` at `position`, extending the used positions if it is the
+    /// next, and rehash its path
+    fn set(&mut self, position: u64, leaf: Option<Leaf>) {
+        let index = position as usize;
+        if index == self.leaves.len() {
+            self.leaves.push(leaf);
+        } else {
+            self.leaves[index] = leaf;
+        }
+        let mut hash = leaf.map_or(EMPTY_NODE, |leaf| leaf.hash());
+        let mut offset = position;
+        for level in 0..=INDEXED_TREE_DEPTH {
+            let nodes = &mut self.levels[level];
+            match nodes.get_mut(offset as usize) {
+                Some(node) => *node = hash,
+                None => nodes.push(hash),
+            }
+            if level == INDEXED_TREE_DEPTH {
+                break;
+            }
+            let sibling = self.node(level, offset ^ 1);
+            hash = match offset & 1 {
+                1 => poseidon_hash_2(&sibling, &hash),
+                _ => poseidon_hash_2(&hash, &sibling),
+            };
+            offset >>= 1;
+        }
+    }
+}
+
+/// `circuits/imt_exclusion`'s inputs, except the public `recent_slot`, which
+/// comes from the chain when proving
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexedExclusionInputs {
+    /// Public: the tree's root
+    pub smt_root: [u8; 32],
+    pub pubkey: [u8; 32],
+    /// The low leaf and its path; its `pubkey_hash` is public
+    pub proof: IndexedExclusionProof,
+}
+
+/// `circuits/imt_exclusion`'s `Prover.toml`, field for parameter
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct IndexedProverToml {
+    pub smt_root: Field,
+    pub pubkey_hash: Field,
+    pub recent_slot: Decimal,
+    pub pubkey: [u8; 32],
+    pub low_leaf_value: Field,
+    pub low_leaf_next_index: Decimal,
+    pub low_leaf_next_value: Field,
+    pub low_leaf_index: Decimal,
+    pub siblings: Vec<Field>,
+}
+
+impl IndexedExclusionInputs {
+    /// The inputs to prove at `recent_slot`, as `Prover.toml` holds them
+    pub fn prover_toml(&self, recent_slot: u64) -> IndexedProverToml {
+        let low_leaf = &self.proof.low_leaf;
+        IndexedProverToml {
+            smt_root: Field(self.smt_root),
+            pubkey_hash: Field(self.proof.pubkey_hash),
+            recent_slot: Decimal(recent_slot),
+            pubkey: self.pubkey,
+            low_leaf_value: Field(low_leaf.value),
+            low_leaf_next_index: Decimal(low_leaf.next_index),
+            low_leaf_next_value: Field(low_leaf.next_value),
+            low_leaf_index: Decimal(self.proof.low_leaf_index),
+            siblings: prover_toml::fields(&self.proof.siblings),
+        }
+    }
+
+    /// `Prover.toml` contents for `nargo execute` in `circuits/imt_exclusion`
+    pub fn to_prover_toml(&self, recent_slot: u64) -> String {
+        prover_toml::to_string("IMT Exclusion Proof", &self.prover_toml(recent_slot))
+            .expect("a struct of values and arrays")
+    }
+
+    /// The public witness a proof of these inputs at `recent_slot` comes with;
+    /// laid out as the sparse circuit's
+    pub fn public_witness(&self, recent_slot: u64) -> [u8; PUBLIC_WITNESS_SIZE] {
+        public_witness(&self.smt_root, &self.proof.pubkey_hash, recent_slot)
+    }
+}
@@ -0,0 +1,90 @@
+//! Circuit inputs for an exclusion proof, and the `Prover.toml` that carries
+//! them to `nargo execute`
+
+use std::{fmt::Write, io, path::Path};
+
+use serde::Serialize;
+
+use crate::prover_toml::{self, Decimal, Field};
+
+/// The exclusion circuit's inputs, except the public `recent_slot`, which comes
+/// from the chain when proving
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExclusionProofInputs {
+    /// Public: the tree's root
+    pub smt_root: [u8; 32],
+    /// Public: the pubkey's hash, whose low bits are its leaf index
+    pub pubkey_hash: [u8; 32],
+    pub pubkey: [u8; 32],
+    /// The pubkey's leaf, which the circuit requires to be empty
+    pub leaf_value: [u8; 32],
+    /// The path from the leaf, as the circuit derives it from `pubkey_hash`
+    /// (its little-endian bits; set means the path goes right)
+    pub path_bits: Vec<bool>,
+    /// Sibling hashes along the path, leaf level first
+    pub siblings: Vec<[u8; 32]>,
+}
+
+/// Size of the public witness the program takes with a proof: gnark's 12-byte
+/// header, then `smt_root`, `pubkey_hash` and `recent_slot`
+pub const PUBLIC_WITNESS_SIZE: usize = exclusion_common::instruction::WITNESS_SIZE;
+
+/// The public witness for proving `pubkey_hash` is excluded from `smt_root` at
+/// `recent_slot`, as sunspot writes it: three public inputs and no secret ones
+/// (big-endian u32s), then each input as a big-endian field element
+pub fn public_witness(
+    smt_root: &[u8; 32],
+    pubkey_hash: &[u8; 32],
+    recent_slot: u64,
+) -> [u8; PUBLIC_WITNESS_SIZE] {
+    exclusion_common::PublicWitness::new(smt_root, pubkey_hash, recent_slot).0
+}
+
+/// A field element as the circuit's inputs write it: `0x` and 64 hex digits
+pub fn field_hex(field: &[u8; 32]) -> String {
+    field.iter().fold(String::from("0x"), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// The exclusion circuit's `Prover.toml`, field for parameter
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ExclusionProverToml {
+    pub smt_root: Field,
+    pub pubkey_hash: Field,
+    pub recent_slot: Decimal,
+    pub pubkey: [u8; 32],
+    pub siblings: Vec<Field>,
+    pub leaf_value: Field,
+}
+
+impl ExclusionProofInputs {
+    /// The inputs to prove at `recent_slot`, as `Prover.toml` holds them
+    pub fn prover_toml(&self, recent_slot: u64) -> ExclusionProverToml {
+        ExclusionProverToml {
+            smt_root: Field(self.smt_root),
+            pubkey_hash: Field(self.pubkey_hash),
+            recent_slot: Decimal(recent_slot),
+            pubkey: self.pubkey,
+            siblings: prover_toml::fields(&self.siblings),
+            leaf_value: Field(self.leaf_value),
+        }
+    }
+
+    /// `Prover.toml` contents
+    pub fn to_prover_toml(&self, recent_slot: u64) -> String {
+        prover_toml::to_string("SMT Exclusion Proof", &self.prover_toml(recent_slot))
+            .expect("a struct of values and arrays")
+    }
+
+    /// The public witness a proof of these inputs at `recent_slot` comes with
+    pub fn public_witness(&self, recent_slot: u64) -> [u8; PUBLIC_WITNESS_SIZE] {
+        public_witness(&self.smt_root, &self.pubkey_hash, recent_slot)
+    }
+
+    /// Write `Prover.toml` for proving at `recent_slot`
+    pub fn write_prover_toml(&self, path: impl AsRef<Path>, recent_slot: u64) -> io::Result<()> {
+        std::fs::write(path, self.to_prover_toml(recent_slot))
+    }
+}
@@ -0,0 +1,420 @@
+//! An append-only journal of every change to a tree, with the root after each
+//!
+//! Each record is one batch: when it was applied, its inserts and deletes by
+//! pubkey, and the root it led to. Records are chained by hash, so none can be
+//! edited, dropped or reordered without breaking every later one. Replaying a
+//! journal rebuilds the tree and checks each root, which lets an auditor tell
+//! whether a pubkey was blacklisted when a disputed transfer was proven, and
+//! which root was current then.
+//!
+//! [`SparseMerkleTree::apply_journaled`] writes a batch's record before the
+//! store commits it, so the store never holds a change the journal lacks.
+//!
+//! Layout, integers little-endian: magic `SMTJRNL\0` (8), version (1) and tree
+//! depth (2), then one record per batch:
+//!
+//! | Field | Size |
+//! |-------|------|
+//! | Sequence, from 0 | 8 |
+//! | Unix time | 8 |
+//! | Op count | 4 |
+//! | Ops: 1 (insert) or 0 (delete), then the pubkey | 33 each |
+//! | Root after the batch | 32 |
+//! | SHA-256 of the previous record's hash (the header's, for the first) and everything above | 32 |
+
+use std::{
+    convert::Infallible,
+    fmt,
+    io::{self, Read, Write},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{default_hash, Op, SparseMerkleTree, Store, TreeError, SUPPORTED_DEPTHS};
+
+pub const MAGIC: [u8; 8] = *b"SMTJRNL\0";
+pub const VERSION: u8 = 1;
+
+const HEADER_SIZE: usize = 8 + 1 + 2;
+
+/// Why a journal couldn't be written or replayed
+#[derive(Debug)]
+pub enum JournalError<E> {
+    Io(io::Error),
+    /// Not a journal this version reads
+    Format(String),
+    /// A record's hash doesn't chain from the one before: the journal was
+    /// edited or is damaged from this record on
+    Chain {
+        sequence: u64,
+    },
+    /// A record's ops lead to a different root than it names
+    RootMismatch {
+        sequence: u64,
+        expected: [u8; 32],
+        actual: [u8; 32],
+    },
+    /// The tree isn't at the root the journal last recorded
+    Diverged,
+    /// A batch blacklists a pubkey whose leaf holds another
+    Collision {
+        pubkey_hash: [u8; 32],
+        holder: [u8; 32],
+    },
+    Store(E),
+}
+
+impl<E: fmt::Display> fmt::Display for JournalError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "journal I/O: {error}"),
+            Self::Format(reason) => write!(f, "invalid journal: {reason}"),
+            Self::Chain { sequence } => write!(f, "journal record {sequence} breaks the chain"),
+            Self::RootMismatch {
+                sequence,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "journal record {sequence} leads to {} instead of its root {}",
+                crate::inputs::field_hex(actual),
+                crate::inputs::field_hex(expected)
+            ),
+            Self::Diverged => write!(f, "the tree is not at the journal's last root"),
+            Self::Collision {
+                pubkey_hash,
+                holder,
+            } => write!(
+                f,
+                "journal batch blacklists pubkey hash {}, whose leaf holds {}",
+                crate::inputs::field_hex(pubkey_hash),
+                crate::inputs::field_hex(holder)
+            ),
+            Self::Store(error) => write!(f, "tree store: {error}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for JournalError<E> {}
+
+impl<E> From<io::Error> for JournalError<E> {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl<E> From<TreeError<E>> for JournalError<E> {
+    fn from(error: TreeError<E>) -> Self {
+        match error {
+            TreeError::Collision {
+                pubkey_hash,
+                holder,
+            } => Self::Collision {
+                pubkey_hash,
+                holder,
+            },
+            TreeError::Store(error) => Self::Store(error),
+        }
+    }
+}
+
+fn format_error<E>(reason: impl Into<String>) -> JournalError<E> {
+    JournalError::Format(reason.into())
+}
+
+/// One batch in a journal
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub sequence: u64,
+    /// Unix time the batch was applied, in seconds
+    pub timestamp: u64,
+    /// The batch's ops, as given, in order
+    pub ops: Vec<Op>,
+    /// The root after the batch
+    pub root: [u8; 32],
+}
+
+impl JournalEntry {
+    /// The record's bytes, without its hash
+    fn encode(&self) -> Vec<u8> {
+        let mut record = Vec::with_capacity(8 + 8 + 4 + self.ops.len() * 33 + 32);
+        record.extend_from_slice(&self.sequence.to_le_bytes());
+        record.extend_from_slice(&self.timestamp.to_le_bytes());
+        record.extend_from_slice(&(self.ops.len() as u32).to_le_bytes());
+        for op in &self.ops {
+            let (kind, pubkey) = match op {
+                Op::Insert(pubkey) => (1, pubkey),
+                Op::Delete(pubkey) => (0, pubkey),
+            };
+            record.push(kind);
+            record.extend_from_slice(pubkey);
+        }
+        record.extend_from_slice(&self.root);
+        record
+    }
+}
+
+fn header(depth: usize) -> [u8; HEADER_SIZE] {
+    let mut header = [0u8; HEADER_SIZE];
+    header[..8].copy_from_slice(&MAGIC);
+    header[8] = VERSION;
+    header[9..].copy_from_slice(&(depth as u16).to_le_bytes());
+    header
+}
+
+fn chain(previous: &[u8; 32], record: &[u8]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(previous)
+        .chain_update(record)
+        .finalize()
+        .into()
+}
+
+/// The writing end of a journal
+#[derive(Debug)]
+pub struct Journal<W> {
+    writer: W,
+    depth: usize,
+    sequence: u64,
+    root: [u8; 32],
+    chain: [u8; 32],
+}
+
+impl<W: Write> Journal<W> {
+    /// Start a journal for an empty tree of `depth` levels, writing its header
+    pub fn create(mut writer: W, depth: usize) -> io::Result<Self> {
+        assert!(
+            SUPPORTED_DEPTHS.contains(&depth),
+            "unsupported tree depth {depth}"
+        );
+        let header = header(depth);
+        writer.write_all(&header)?;
+        writer.flush()?;
+        Ok(Self {
+            writer,
+            depth,
+            sequence: 0,
+            root: default_hash(depth),
+            chain: Sha256::digest(header).into(),
+        })
+    }
+
+    /// Continue a replayed journal, appending to `writer` (the same file,
+    /// opened for appending)
+    pub fn resume(writer: W, replay: &Replay) -> Self {
+        Self {
+            writer,
+            depth: replay.tree.depth(),
+            sequence: replay.entries.len() as u64,
+            root: replay.tree.root(),
+            chain: replay.chain,
+        }
+    }
+
+    /// The root the last record names, or the empty tree's
+    pub fn root(&self) -> [u8; 32] {
+        self.root
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Append a record of `ops` leading to `root`, and flush it
+    pub fn append(
+        &mut self,
+        timestamp: u64,
+        ops: &[Op],
+        root: [u8; 32],
+    ) -> io::Result<JournalEntry> {
+        let entry = JournalEntry {
+            sequence: self.sequence,
+            timestamp,
+            ops: ops.to_vec(),
+            root,
+        };
+        let mut record = entry.encode();
+        let hash = chain(&self.chain, &record);
+        record.extend_from_slice(&hash);
+        self.writer.write_all(&record)?;
+        self.writer.flush()?;
+        self.sequence += 1;
+        self.root = root;
+        self.chain = hash;
+        Ok(entry)
+    }
+}
+
+impl<S: Store> SparseMerkleTree<S> {
+    /// [`apply_batch`](Self::apply_batch), recording the batch in `journal`
+    /// at `timestamp` before committing it. Fails with `Diverged`, changing
+    /// nothing, if the tree isn't at the journal's last root.
+    pub fn apply_journaled<W: Write>(
+        &mut self,
+        journal: &mut Journal<W>,
+        timestamp: u64,
+        ops: &[Op],
+    ) -> Result<usize, JournalError<S::Error>> {
+        if journal.depth != self.depth || journal.root != self.root() {
+            return Err(JournalError::Diverged);
+        }
+        let batch = self.stage_ops(ops)?;
+        journal.append(timestamp, ops, batch.checkpoint.root)?;
+        self.commit(batch).map_err(JournalError::Store)
+    }
+}
+
+/// A replayed journal: its records, checked, and the tree they lead to
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub entries: Vec<JournalEntry>,
+    pub tree: SparseMerkleTree,
+    /// The last record's hash, which the next record chains from
+    chain: [u8; 32],
+}
+
+impl Replay {
+    /// Whether `pubkey` was blacklisted after every batch applied at or before
+    /// `timestamp`
+    pub fn blacklisted_at(&self, pubkey: &[u8; 32], timestamp: u64) -> bool {
+        let mut blacklisted = false;
+        for entry in self
+            .entries
+            .iter()
+            .take_while(|entry| entry.timestamp <= timestamp)
+        {
+            for op in &entry.ops {
+                match op {
+                    Op::Insert(other) if other == pubkey => blacklisted = true,
+                    Op::Delete(other) if other == pubkey => blacklisted = false,
+                    _ => {}
+                }
+            }
+        }
+        blacklisted
+    }
+
+    /// The records with an op on `pubkey` itself, in order
+    pub fn history<'a>(&'a self, pubkey: &'a [u8; 32]) -> impl Iterator<Item = &'a JournalEntry> {
+        self.entries.iter().filter(move |entry| {
+            entry
+                .ops
+                .iter()
+                .any(|op| matches!(op, Op::Insert(other) | Op::Delete(other) if other == pubkey))
+        })
+    }
+
+    /// The root after every batch applied at or before `timestamp`
+    pub fn root_at(&self, timestamp: u64) -> [u8; 32] {
+        self.entries
+            .iter()
+            .take_while(|entry| entry.timestamp <= timestamp)
+            .last()
+            .map_or(default_hash(self.tree.depth()), |entry| entry.root)
+    }
+
+    /// The records in which the tree's root was `root`, such as a proof's
+    /// public `smt_root`: it was current from each one's timestamp until the
+    /// next record's
+    pub fn entries_with_root<'a>(
+        &'a self,
+        root: &'a [u8; 32],
+    ) -> impl Iterator<Item = &'a JournalEntry> {
+        self.entries.iter().filter(move |entry| &entry.root == root)
+    }
+}
+
+/// Fill `buffer`, or return false at a clean end of the journal
+fn read_exact_or_end(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<bool> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..])? {
+            0 if read == 0 => return Ok(false),
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => read += n,
+        }
+    }
+    Ok(true)
+}
+
+/// Check a journal record by record and rebuild its tree in memory: each
+/// record must chain from the one before, follow it in sequence, and its ops
+/// must lead to the root it names
+pub fn replay(mut reader: impl Read) -> Result<Replay, JournalError<Infallible>> {
+    let mut header = [0u8; HEADER_SIZE];
+    if !read_exact_or_end(&mut reader, &mut header)? {
+        return Err(format_error("truncated"));
+    }
+    if header[..8] != MAGIC {
+        return Err(format_error("not a tree journal"));
+    }
+    if header[8] != VERSION {
+        return Err(format_error(format!("version {}", header[8])));
+    }
+    let depth = u16::from_le_bytes([header[9], header[10]]) as usize;
+    if !SUPPORTED_DEPTHS.contains(&depth) {
+        return Err(format_error(format!("unsupported depth {depth}")));
+    }
+
+    let mut tree = SparseMerkleTree::with_depth(depth);
+    let mut entries = Vec::new();
+    let mut previous: [u8; 32] = Sha256::digest(header).into();
+    let mut fixed = [0u8; 20];
+    while read_exact_or_end(&mut reader, &mut fixed)? {
+        let sequence = u64::from_le_bytes(fixed[..8].try_into().expect("8 bytes"));
+        if sequence != entries.len() as u64 {
+            return Err(format_error(format!("record {sequence} out of sequence")));
+        }
+        let count = u32::from_le_bytes(fixed[16..20].try_into().expect("4 bytes")) as usize;
+        // Read rather than allocate what a damaged count claims
+        let len = count as u64 * 33 + 64;
+        let mut rest = Vec::new();
+        if reader.by_ref().take(len).read_to_end(&mut rest)? as u64 != len {
+            return Err(format_error(format!("record {sequence} truncated")));
+        }
+        let (record, hash) = rest.split_at(rest.len() - 32);
+        let hash: [u8; 32] = hash.try_into().expect("32 bytes");
+        let expected = Sha256::new()
+            .chain_update(previous)
+            .chain_update(fixed)
+            .chain_update(record)
+            .finalize();
+        if expected.as_slice() != hash {
+            return Err(JournalError::Chain { sequence });
+        }
+
+        let (ops, root) = record.split_at(count * 33);
+        let ops = ops
+            .chunks_exact(33)
+            .map(|op| {
+                let pubkey = op[1..].try_into().expect("32 bytes");
+                match op[0] {
+                    1 => Ok(Op::Insert(pubkey)),
+                    0 => Ok(Op::Delete(pubkey)),
+                    kind => Err(format_error(format!("record {sequence}: op kind {kind}"))),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let root: [u8; 32] = root.try_into().expect("32 bytes");
+        tree.apply_batch(&ops)?;
+        if tree.root() != root {
+            return Err(JournalError::RootMismatch {
+                sequence,
+                expected: root,
+                actual: tree.root(),
+            });
+        }
+        entries.push(JournalEntry {
+            sequence,
+            timestamp: u64::from_le_bytes(fixed[8..16].try_into().expect("8 bytes")),
+            ops,
+            root,
+        });
+        previous = hash;
+    }
+    Ok(Replay {
+        entries,
+        tree,
+        chain: previous,
+    })
+}
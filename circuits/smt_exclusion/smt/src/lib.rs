@@ -0,0 +1,634 @@
+//! The blacklist's sparse Merkle tree, off chain
+//!
+//! Hashes and indexes exactly as the circuit, the program and the TypeScript
+//! client's `SparseMerkleTree` do: each pubkey's leaf sits at the Poseidon hash
+//! of its little-endian halves (`pubkey_to_index`), its path follows that
+//! index's little-endian bits, blacklisted leaves are 1 and empty ones 0. Field
+//! elements are big-endian, as stored on chain and in the public witness.
+//!
+//! The tree keeps its leaves sorted and caches the hash of every node above two
+//! or more of them, so it stays proportional to the list. Single-leaf subtrees
+//! are hashed from their leaf when needed. Both live in a [`Store`]: in memory,
+//! on disk with the `sled` feature, or in memory-mapped files with the `mmap`
+//! feature for lists larger than memory.
+//!
+//! Trees are 254 levels deep unless built with [`SparseMerkleTree::with_depth`].
+//! A shallower tree's leaf index is the low bits of the pubkey's hash, so its
+//! proofs are shorter, but pubkeys can share a leaf. Its blacklisted leaves
+//! hold the whole hash instead of 1 ([`leaf_value`]), so a pubkey sharing a
+//! leaf with a blacklisted one still proves exclusion; a second blacklisted
+//! pubkey can't take the leaf, and inserting it fails with
+//! [`TreeError::Collision`].
+//!
+//! [`SparseMerkleTree::exclusion_multiproof`] proves several pubkeys at once
+//! for the batch circuit, sending the siblings their paths share once.
+//!
+//! [`IndexedMerkleTree`] holds the same list as a sorted linked list instead,
+//! for the indexed circuit's shorter proofs.
+//!
+//! Batches hash on rayon's thread pool: their pubkeys' indices, then each
+//! level's changed nodes, which depend only on the level below. Run a build
+//! inside `ThreadPool::install` to bound the threads it takes.
+
+pub mod diff;
+pub mod indexed;
+pub mod inputs;
+pub mod journal;
+#[cfg(all(feature = "mmap", unix))]
+pub mod mmap_store;
+pub mod multiproof;
+mod pages;
+pub mod prover_toml;
+pub mod shared;
+pub mod signed;
+#[cfg(feature = "sled")]
+pub mod sled_store;
+pub mod snapshot;
+pub mod store;
+
+use std::{
+    cell::RefCell,
+    collections::{btree_map::Entry, BTreeMap},
+    fmt,
+    sync::OnceLock,
+};
+
+use ark_bn254::Fr;
+use light_poseidon::{Poseidon, PoseidonBytesHasher};
+use rayon::prelude::*;
+
+pub use exclusion_common::pubkey_to_index;
+
+pub use crate::{
+    diff::LeafOp,
+    indexed::IndexedMerkleTree,
+    inputs::ExclusionProofInputs,
+    multiproof::{ExclusionMultiproof, ExclusionMultiproofInputs},
+    store::{Batch, Checkpoint, MemoryStore, Store},
+};
+
+/// Default tree depth (must match the circuit's TREE_DEPTH)
+pub const TREE_DEPTH: usize = 254;
+
+/// Depths the circuit can be generated for (`just smt-depth`) and the program
+/// accepts
+pub const SUPPORTED_DEPTHS: [usize; 5] = [20, 32, 64, 254, 256];
+
+/// Deepest supported tree
+pub const MAX_TREE_DEPTH: usize = 256;
+
+/// Empty leaf value
+pub const EMPTY_LEAF: [u8; 32] = [0u8; 32];
+
+/// Leaf value for a blacklisted key in a tree of [`TREE_DEPTH`] or more
+/// levels (1, big-endian)
+pub const INSERTED_LEAF: [u8; 32] = {
+    let mut leaf = [0u8; 32];
+    leaf[31] = 1;
+    leaf
+};
+
+/// Value of the leaf holding the pubkey that hashes to `pubkey_hash`, in a tree
+/// of `depth` levels: 1 where every hash has a leaf of its own, the hash itself
+/// where leaves are shared, for the circuit to tell the pubkey from others
+pub fn leaf_value(pubkey_hash: &[u8; 32], depth: usize) -> [u8; 32] {
+    match depth >= TREE_DEPTH {
+        true => INSERTED_LEAF,
+        false => *pubkey_hash,
+    }
+}
+
+thread_local! {
+    /// Circom-compatible Poseidon, as behind the `sol_poseidon` syscall. Its
+    /// parameters take a while to set up, so each thread keeps one.
+    static POSEIDON: RefCell<Poseidon<Fr>> =
+        RefCell::new(Poseidon::<Fr>::new_circom(2).expect("two inputs are supported"));
+}
+
+/// Poseidon hash of two big-endian field elements (the circuit's `poseidon_hash_2`)
+pub fn poseidon_hash_2(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    POSEIDON
+        .with_borrow_mut(|poseidon| poseidon.hash_bytes_be(&[left, right]))
+        .expect("inputs are field elements")
+}
+
+/// Leaf index of a pubkey hash in a tree of `depth` levels: its low `depth`
+/// bits, big-endian
+pub fn leaf_index(pubkey_hash: &[u8; 32], depth: usize) -> [u8; 32] {
+    let mut index = *pubkey_hash;
+    if depth < MAX_TREE_DEPTH {
+        index[..31 - depth / 8].fill(0);
+        index[31 - depth / 8] &= (1 << (depth % 8)) - 1;
+    }
+    index
+}
+
+/// Root of an empty subtree of `height` levels: the empty leaf at 0, the empty
+/// tree's root at the tree's depth
+pub fn default_hash(height: usize) -> [u8; 32] {
+    static DEFAULT_HASHES: OnceLock<Vec<[u8; 32]>> = OnceLock::new();
+    DEFAULT_HASHES.get_or_init(|| {
+        let mut hashes = vec![EMPTY_LEAF];
+        for level in 0..MAX_TREE_DEPTH {
+            hashes.push(poseidon_hash_2(&hashes[level], &hashes[level]));
+        }
+        hashes
+    })[height]
+}
+
+/// Path bit of `index` at `level` (0 = leaf level): set when the path goes right
+pub fn path_bit(index: &[u8; 32], level: usize) -> bool {
+    index[31 - level / 8] & (1 << (level % 8)) != 0
+}
+
+/// Hash a node's child at `level` with its sibling, in path order
+fn parent(index: &[u8; 32], level: usize, child: &[u8; 32], sibling: &[u8; 32]) -> [u8; 32] {
+    match path_bit(index, level) {
+        true => poseidon_hash_2(sibling, child),
+        false => poseidon_hash_2(child, sibling),
+    }
+}
+
+/// First index under the node of `height` levels above `index`'s leaf
+fn first_under(index: &[u8; 32], height: usize) -> [u8; 32] {
+    if height == MAX_TREE_DEPTH {
+        return [0; 32];
+    }
+    let mut first = *index;
+    first[32 - height / 8..].fill(0);
+    first[31 - height / 8] &= !((1 << (height % 8)) - 1);
+    first
+}
+
+/// Last index under the node of `height` levels above `index`'s leaf
+fn last_under(index: &[u8; 32], height: usize) -> [u8; 32] {
+    if height == MAX_TREE_DEPTH {
+        return [0xff; 32];
+    }
+    let mut last = *index;
+    last[32 - height / 8..].fill(0xff);
+    last[31 - height / 8] |= (1 << (height % 8)) - 1;
+    last
+}
+
+/// The sibling of `index`'s ancestor at `level`: the node of `level` levels
+/// whose path differs only in bit `level`
+fn sibling_of(index: &[u8; 32], level: usize) -> [u8; 32] {
+    let mut sibling = first_under(index, level);
+    sibling[31 - level / 8] ^= 1 << (level % 8);
+    sibling
+}
+
+/// Sibling hashes proving a pubkey's leaf doesn't hold it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExclusionProof {
+    /// The pubkey's hash (the circuit's public `pubkey_hash`), whose low bits
+    /// are its leaf index
+    pub pubkey_hash: [u8; 32],
+    /// The leaf's value: empty, or in a shallow tree another pubkey's hash
+    pub leaf_value: [u8; 32],
+    /// One sibling hash per level of the tree, leaf level first
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl ExclusionProof {
+    /// The root these siblings prove the leaf under, as the circuit
+    /// recomputes it
+    pub fn root(&self) -> [u8; 32] {
+        self.siblings
+            .iter()
+            .enumerate()
+            .fold(self.leaf_value, |current, (level, sibling)| {
+                parent(&self.pubkey_hash, level, &current, sibling)
+            })
+    }
+}
+
+/// One change to the blacklist
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Insert([u8; 32]),
+    Delete([u8; 32]),
+}
+
+/// Why a batch couldn't be applied
+#[derive(Debug)]
+pub enum TreeError<E> {
+    /// A pubkey's leaf holds another blacklisted pubkey. Only shallow trees
+    /// share leaves; a deeper tree holds both.
+    Collision {
+        pubkey_hash: [u8; 32],
+        holder: [u8; 32],
+    },
+    Store(E),
+}
+
+impl<E: fmt::Display> fmt::Display for TreeError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Collision {
+                pubkey_hash,
+                holder,
+            } => write!(
+                f,
+                "pubkey hash {} shares its leaf with the blacklisted {}; use a deeper tree",
+                inputs::field_hex(pubkey_hash),
+                inputs::field_hex(holder)
+            ),
+            Self::Store(error) => write!(f, "tree store: {error}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for TreeError<E> {}
+
+/// A sparse Merkle tree of blacklisted pubkeys, kept in a [`Store`]
+#[derive(Clone, Debug)]
+pub struct SparseMerkleTree<S = MemoryStore> {
+    store: S,
+    depth: usize,
+    checkpoint: Checkpoint,
+}
+
+impl Default for SparseMerkleTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SparseMerkleTree {
+    /// An empty tree in memory
+    pub fn new() -> Self {
+        Self::with_depth(TREE_DEPTH)
+    }
+
+    /// An empty tree of `depth` levels in memory
+    ///
+    /// # Panics
+    ///
+    /// If `depth` isn't one of [`SUPPORTED_DEPTHS`]
+    pub fn with_depth(depth: usize) -> Self {
+        let Ok(tree) = Self::open_with_depth(MemoryStore::default(), depth);
+        tree
+    }
+}
+
+impl<S: Store> SparseMerkleTree<S> {
+    /// The tree in `store`, as of its last commit
+    pub fn open(store: S) -> Result<Self, S::Error> {
+        Self::open_with_depth(store, TREE_DEPTH)
+    }
+
+    /// The tree of `depth` levels in `store`, as of its last commit. Stores
+    /// don't record the depth: open one with the depth it was built at.
+    ///
+    /// # Panics
+    ///
+    /// If `depth` isn't one of [`SUPPORTED_DEPTHS`]
+    pub fn open_with_depth(store: S, depth: usize) -> Result<Self, S::Error> {
+        assert!(
+            SUPPORTED_DEPTHS.contains(&depth),
+            "unsupported tree depth {depth}"
+        );
+        let checkpoint = store
+            .checkpoint()?
+            .unwrap_or_else(|| Checkpoint::empty(depth));
+        Ok(Self {
+            store,
+            depth,
+            checkpoint,
+        })
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// The store, for closing it: [`open`](Self::open) it again to continue
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    /// Levels in the tree, and sibling hashes in a proof
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The root, leaf count and commit count the store last committed
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.checkpoint
+    }
+
+    pub fn len(&self) -> usize {
+        self.checkpoint.leaf_count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checkpoint.leaf_count == 0
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.checkpoint.root
+    }
+
+    pub fn contains(&self, pubkey: &[u8; 32]) -> Result<bool, S::Error> {
+        let pubkey_hash = pubkey_to_index(pubkey);
+        let index = leaf_index(&pubkey_hash, self.depth);
+        Ok(self.store.leaf(&index)? == Some(pubkey_hash))
+    }
+
+    /// Blacklist `pubkey`; false if it already was
+    pub fn insert(&mut self, pubkey: &[u8; 32]) -> Result<bool, TreeError<S::Error>> {
+        Ok(self.apply_batch(&[Op::Insert(*pubkey)])? == 1)
+    }
+
+    /// Remove `pubkey` from the blacklist; false if it wasn't on it
+    pub fn delete(&mut self, pubkey: &[u8; 32]) -> Result<bool, TreeError<S::Error>> {
+        Ok(self.apply_batch(&[Op::Delete(*pubkey)])? == 1)
+    }
+
+    /// Apply `ops` in order, then rehash each node above the changed leaves
+    /// once, instead of every path once per change, and commit it all to the
+    /// store with the new root. Returns how many leaves changed: ops that
+    /// cancel out, or insert what is already there, don't count.
+    ///
+    /// If the commit fails, the tree stays at its last checkpoint. If an
+    /// insert's leaf holds another pubkey, nothing is applied.
+    pub fn apply_batch(&mut self, ops: &[Op]) -> Result<usize, TreeError<S::Error>> {
+        let batch = self.stage_ops(ops)?;
+        self.commit(batch).map_err(TreeError::Store)
+    }
+
+    /// The batch [`apply_batch`](Self::apply_batch) commits for `ops`
+    pub(crate) fn stage_ops(&self, ops: &[Op]) -> Result<Batch, TreeError<S::Error>> {
+        let hashed = ops
+            .par_iter()
+            .map(|op| match op {
+                Op::Insert(pubkey) => (pubkey_to_index(pubkey), true),
+                Op::Delete(pubkey) => (pubkey_to_index(pubkey), false),
+            })
+            .collect();
+        self.stage_hashed(hashed)
+    }
+
+    /// The batch for ops by pubkey hash, each with whether it blacklists it
+    pub(crate) fn stage_hashed(
+        &self,
+        hashed: Vec<([u8; 32], bool)>,
+    ) -> Result<Batch, TreeError<S::Error>> {
+        // The pubkey hash each touched leaf held before the batch, and after
+        let mut before_after: BTreeMap<_, (Option<[u8; 32]>, _)> = BTreeMap::new();
+        for (pubkey_hash, blacklisted) in hashed {
+            let index = leaf_index(&pubkey_hash, self.depth);
+            let after = match before_after.entry(index) {
+                Entry::Occupied(entry) => &mut entry.into_mut().1,
+                Entry::Vacant(entry) => {
+                    let before = self.store.leaf(&index).map_err(TreeError::Store)?;
+                    &mut entry.insert((before, before)).1
+                }
+            };
+            match (*after, blacklisted) {
+                (None, true) => *after = Some(pubkey_hash),
+                (Some(holder), true) if holder != pubkey_hash => {
+                    return Err(TreeError::Collision {
+                        pubkey_hash,
+                        holder,
+                    })
+                }
+                (Some(holder), false) if holder == pubkey_hash => *after = None,
+                // Already blacklisted, or not blacklisted to begin with
+                _ => {}
+            }
+        }
+        let leaves = before_after
+            .into_iter()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(index, (_, after))| (index, after))
+            .collect();
+        self.stage(leaves).map_err(TreeError::Store)
+    }
+
+    /// A batch setting (`Some`) or emptying (`None`) `leaves`, each of which
+    /// must change, with the nodes and checkpoint they lead to
+    pub(crate) fn stage(
+        &self,
+        leaves: BTreeMap<[u8; 32], Option<[u8; 32]>>,
+    ) -> Result<Batch, S::Error> {
+        let mut batch = Batch {
+            leaves,
+            ..Batch::default()
+        };
+        if batch.leaves.is_empty() {
+            batch.checkpoint = self.checkpoint;
+            return Ok(batch);
+        }
+        let mut leaf_count = self.checkpoint.leaf_count;
+        for (index, pubkey_hash) in &batch.leaves {
+            match (self.store.contains(index)?, pubkey_hash) {
+                (false, Some(_)) => leaf_count += 1,
+                (true, None) => leaf_count -= 1,
+                // A leaf passing from one pubkey to another
+                _ => {}
+            }
+        }
+        let root = Pending::new(&self.store, &mut batch, self.depth).rehash()?;
+        batch.checkpoint = Checkpoint {
+            root,
+            leaf_count,
+            sequence: self.checkpoint.sequence + 1,
+        };
+        Ok(batch)
+    }
+
+    /// Commit a staged batch, unless it changes nothing. Returns how many
+    /// leaves it changed.
+    pub(crate) fn commit(&mut self, batch: Batch) -> Result<usize, S::Error> {
+        if batch.leaves.is_empty() {
+            return Ok(0);
+        }
+        self.store.commit(&batch)?;
+        self.checkpoint = batch.checkpoint;
+        Ok(batch.leaves.len())
+    }
+
+    /// Siblings proving `pubkey` is not blacklisted, or `None` if it is
+    pub fn exclusion_proof(&self, pubkey: &[u8; 32]) -> Result<Option<ExclusionProof>, S::Error> {
+        let pubkey_hash = pubkey_to_index(pubkey);
+        let index = leaf_index(&pubkey_hash, self.depth);
+        let leaf_value = match self.store.leaf(&index)? {
+            None => EMPTY_LEAF,
+            Some(holder) if holder == pubkey_hash => return Ok(None),
+            // Another pubkey's, which the circuit tells from this one
+            Some(holder) => leaf_value(&holder, self.depth),
+        };
+        let mut batch = Batch::default();
+        let pending = Pending::new(&self.store, &mut batch, self.depth);
+        let siblings = (0..self.depth)
+            .into_par_iter()
+            .map(|level| pending.subtree(&sibling_of(&index, level), level))
+            .collect::<Result<_, _>>()?;
+        Ok(Some(ExclusionProof {
+            pubkey_hash,
+            leaf_value,
+            siblings,
+        }))
+    }
+
+    /// Everything the circuit takes to prove `pubkey` is not blacklisted, or
+    /// `None` if it is
+    pub fn generate_exclusion_proof(
+        &self,
+        pubkey: &[u8; 32],
+    ) -> Result<Option<ExclusionProofInputs>, S::Error> {
+        let Some(proof) = self.exclusion_proof(pubkey)? else {
+            return Ok(None);
+        };
+        Ok(Some(ExclusionProofInputs {
+            smt_root: self.root(),
+            pubkey_hash: proof.pubkey_hash,
+            pubkey: *pubkey,
+            leaf_value: proof.leaf_value,
+            path_bits: (0..self.depth)
+                .map(|level| path_bit(&proof.pubkey_hash, level))
+                .collect(),
+            siblings: proof.siblings,
+        }))
+    }
+}
+
+/// The store as a batch being built leaves it
+struct Pending<'a, S> {
+    store: &'a S,
+    batch: &'a mut Batch,
+    depth: usize,
+}
+
+/// A leaf's index and the pubkey hash it holds
+type Leaf = ([u8; 32], [u8; 32]);
+
+/// A changed node's parent: its first index, its hash, and the change to its
+/// cached hash, if any
+type Parent = ([u8; 32], [u8; 32], Option<Option<[u8; 32]>>);
+
+impl<'a, S: Store> Pending<'a, S> {
+    fn new(store: &'a S, batch: &'a mut Batch, depth: usize) -> Self {
+        Self {
+            store,
+            batch,
+            depth,
+        }
+    }
+
+    /// Leaves under the node of `height` levels above `index`'s leaf, up to
+    /// two: the first, with the pubkey hash it holds, and whether there are more
+    fn leaves_under(
+        &self,
+        index: &[u8; 32],
+        height: usize,
+    ) -> Result<(Option<Leaf>, bool), S::Error> {
+        let (first, last) = (first_under(index, height), last_under(index, height));
+        // The batch's leaves replace the store's
+        let kept = self.store.leaves(&first, &last).filter(
+            |leaf| !matches!(leaf, Ok((index, _)) if self.batch.leaves.contains_key(index)),
+        );
+        let set = self
+            .batch
+            .leaves
+            .range(first..=last)
+            .filter_map(|(index, pubkey_hash)| Some(Ok((*index, (*pubkey_hash)?))));
+        let mut leaves = kept.chain(set);
+        let first = leaves.next().transpose()?;
+        Ok((first, leaves.next().transpose()?.is_some()))
+    }
+
+    fn node(&self, height: usize, first: &[u8; 32]) -> Result<Option<[u8; 32]>, S::Error> {
+        match self.batch.nodes.get(&(height, *first)) {
+            Some(hash) => Ok(*hash),
+            None => self.store.node(height, first),
+        }
+    }
+
+    /// Hash of the node of `height` levels above `index`'s leaf
+    fn subtree(&self, index: &[u8; 32], height: usize) -> Result<[u8; 32], S::Error> {
+        Ok(match self.leaves_under(index, height)? {
+            (None, _) => default_hash(height),
+            (Some((leaf, pubkey_hash)), false) => (0..height)
+                .fold(leaf_value(&pubkey_hash, self.depth), |current, level| {
+                    parent(&leaf, level, &current, &default_hash(level))
+                }),
+            (Some(_), true) => self
+                .node(height, &first_under(index, height))?
+                .expect("nodes above two or more leaves are cached"),
+        })
+    }
+
+    /// The parent at `level + 1` of the changed node `first`, whose hash is now
+    /// `hash`, given its sibling's new hash if that changed too
+    fn parent(
+        &self,
+        level: usize,
+        first: &[u8; 32],
+        hash: [u8; 32],
+        right: Option<[u8; 32]>,
+    ) -> Result<Parent, S::Error> {
+        let parent_first = first_under(first, level + 1);
+        let (left, right) = match (path_bit(first, level), right) {
+            (true, _) => (self.subtree(&parent_first, level)?, hash),
+            (false, Some(right)) => (hash, right),
+            (false, None) => (hash, self.subtree(&sibling_of(first, level), level)?),
+        };
+        let hash = poseidon_hash_2(&left, &right);
+        // Cache the parent if it is above two or more leaves; drop it from the
+        // cache if it no longer is
+        let cached = match self.leaves_under(&parent_first, level + 1)? {
+            (Some(_), true) => Some(Some(hash)),
+            _ => self.node(level + 1, &parent_first)?.map(|_| None),
+        };
+        Ok((parent_first, hash, cached))
+    }
+
+    /// Rehash every node above the batch's changed leaves, bottom up, into the
+    /// batch. A node above several of them is hashed once. Returns the root.
+    fn rehash(&mut self) -> Result<[u8; 32], S::Error> {
+        // The changed nodes at this level, by first index, in path order
+        let mut level_nodes: Vec<([u8; 32], [u8; 32])> = self
+            .batch
+            .leaves
+            .iter()
+            .map(|(index, pubkey_hash)| match pubkey_hash {
+                Some(pubkey_hash) => (*index, leaf_value(pubkey_hash, self.depth)),
+                None => (*index, EMPTY_LEAF),
+            })
+            .collect();
+        for level in 0..self.depth {
+            // Each changed node, with its right sibling if that changed too
+            let mut pairs = Vec::with_capacity(level_nodes.len());
+            let mut nodes = level_nodes.into_iter().peekable();
+            while let Some((first, hash)) = nodes.next() {
+                let parent_first = first_under(&first, level + 1);
+                let right = match path_bit(&first, level) {
+                    true => None,
+                    // A changed right sibling is next in path order
+                    false => nodes
+                        .next_if(|(next, _)| first_under(next, level + 1) == parent_first)
+                        .map(|(_, right)| right),
+                };
+                pairs.push((first, hash, right));
+            }
+            // The parents only read this level, so they hash in parallel
+            let this = &*self;
+            let parents = pairs
+                .into_par_iter()
+                .map(|(first, hash, right)| this.parent(level, &first, hash, right))
+                .collect::<Result<Vec<Parent>, _>>()?;
+            level_nodes = Vec::with_capacity(parents.len());
+            for (parent_first, hash, cached) in parents {
+                if let Some(cached) = cached {
+                    self.batch.nodes.insert((level + 1, parent_first), cached);
+                }
+                level_nodes.push((parent_first, hash));
+            }
+        }
+        Ok(level_nodes[0].1)
+    }
+}
@@ -0,0 +1,508 @@
+//! A store in memory-mapped files, for trees larger than memory
+//!
+//! Leaves and cached nodes sit in two files of fixed-size records sorted by
+//! key, mapped read-only, so the OS pages in only what lookups touch. Memory
+//! holds every 128th record's key: a lookup binary-searches those, then one
+//! block of records, a page or two of the file.
+//!
+//! Commits go to an overlay in memory. [`MmapStore::compact`] merges it into
+//! a new generation of files and switches to them by renaming `CURRENT`, so
+//! the directory always holds one whole generation. Commits since the last
+//! compaction are lost in a crash; the store then reopens at the checkpoint
+//! compacted last. A commit compacts by itself once the overlay passes
+//! [`MmapStore::set_compact_threshold`] entries.
+//!
+//! Files, integers little-endian:
+//!
+//! - `CURRENT`: magic `SMTMMAP\0` (8), version (1), generation (8), root (32),
+//!   leaf count (8), commit sequence (8)
+//! - `leaves-<generation>`: leaf index (32) and the pubkey hash it holds (32)
+//!   of each leaf; version 1 stores, from before shallow trees, hold the
+//!   index alone
+//! - `nodes-<generation>`: height (2, big-endian so records sort by it),
+//!   first index (32) and hash (32) of each cached node
+
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    iter::Peekable,
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    ptr, slice,
+};
+
+use crate::store::{Batch, Checkpoint, Store};
+
+pub const MAGIC: [u8; 8] = *b"SMTMMAP\0";
+pub const VERSION: u8 = 2;
+
+const CURRENT_SIZE: usize = 8 + 1 + 8 + 32 + 8 + 8;
+const LEAF_SIZE: usize = 32 + 32;
+/// A version 1 store's leaf: its index alone
+const V1_LEAF_SIZE: usize = 32;
+const NODE_KEY_SIZE: usize = 2 + 32;
+const NODE_SIZE: usize = NODE_KEY_SIZE + 32;
+/// Records per block of the in-memory index
+const FENCE: usize = 128;
+/// Overlay entries a commit compacts at, unless set otherwise
+pub const DEFAULT_COMPACT_THRESHOLD: usize = 1 << 20;
+
+fn invalid(what: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what.into())
+}
+
+/// A file mapped read-only
+#[derive(Debug)]
+struct Mapped {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+// The mapping is read-only and its file is never written once mapped: each
+// generation is written in full before it is opened
+unsafe impl Send for Mapped {}
+unsafe impl Sync for Mapped {}
+
+impl Mapped {
+    fn empty() -> Self {
+        Self {
+            ptr: ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        if len == 0 {
+            return Ok(Self::empty());
+        }
+        // SAFETY: a fresh read-only mapping of the whole file
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { ptr, len })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match self.len {
+            0 => &[],
+            // SAFETY: `ptr` maps `len` readable bytes until dropped
+            len => unsafe { slice::from_raw_parts(self.ptr.cast::<u8>(), len) },
+        }
+    }
+}
+
+impl Drop for Mapped {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            // SAFETY: unmapping what `open` mapped, once
+            unsafe { libc::munmap(self.ptr, self.len) };
+        }
+    }
+}
+
+/// Sorted fixed-size records in a mapped file, with every `FENCE`th key in
+/// memory
+#[derive(Debug)]
+struct Table {
+    map: Mapped,
+    record: usize,
+    key: usize,
+    fences: Vec<u8>,
+}
+
+impl Table {
+    fn empty(record: usize, key: usize) -> Self {
+        Self {
+            map: Mapped::empty(),
+            record,
+            key,
+            fences: Vec::new(),
+        }
+    }
+
+    fn open(path: &Path, record: usize, key: usize) -> io::Result<Self> {
+        let map = Mapped::open(path)?;
+        if map.len % record != 0 {
+            return Err(invalid(format!("{} is truncated", path.display())));
+        }
+        let fences = map
+            .bytes()
+            .chunks_exact(record)
+            .step_by(FENCE)
+            .flat_map(|record| &record[..key])
+            .copied()
+            .collect();
+        Ok(Self {
+            map,
+            record,
+            key,
+            fences,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.map.len / self.record
+    }
+
+    fn record(&self, i: usize) -> &[u8] {
+        &self.map.bytes()[i * self.record..(i + 1) * self.record]
+    }
+
+    fn key(&self, i: usize) -> &[u8] {
+        &self.record(i)[..self.key]
+    }
+
+    /// Position of the first record whose key is at least `key`
+    fn lower_bound(&self, key: &[u8]) -> usize {
+        let fence = |i: usize| &self.fences[i * self.key..(i + 1) * self.key];
+        // The block after the last fence below `key` starts at or after it
+        let block = partition_point(self.fences.len() / self.key, |i| fence(i) < key);
+        let start = block.saturating_sub(1) * FENCE;
+        let end = (block * FENCE).min(self.len());
+        start + partition_point(end - start, |i| self.key(start + i) < key)
+    }
+
+    fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        let i = self.lower_bound(key);
+        (i < self.len() && self.key(i) == key).then(|| self.record(i))
+    }
+
+    /// Records from the first at or after `key`
+    fn from(&self, key: &[u8]) -> impl Iterator<Item = &[u8]> + '_ {
+        (self.lower_bound(key)..self.len()).map(|i| self.record(i))
+    }
+}
+
+/// The first of `0..len` that `below` is false for, `below` being true for
+/// a prefix
+fn partition_point(len: usize, below: impl Fn(usize) -> bool) -> usize {
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if below(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+/// A leaf record's index and pubkey hash
+fn leaf(record: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let index = record[..32].try_into().expect("32 bytes");
+    match record.len() {
+        V1_LEAF_SIZE => (index, index),
+        _ => (index, record[32..].try_into().expect("32 bytes")),
+    }
+}
+
+fn node_key(height: usize, first: &[u8; 32]) -> [u8; NODE_KEY_SIZE] {
+    let mut key = [0u8; NODE_KEY_SIZE];
+    key[..2].copy_from_slice(&(height as u16).to_be_bytes());
+    key[2..].copy_from_slice(first);
+    key
+}
+
+/// The records of `base` and `overlay` merged by key, the overlay's winning;
+/// `None` in the overlay drops the key
+fn merge<'a, K: Ord + 'a, V: 'a>(
+    base: impl Iterator<Item = (K, V)> + 'a,
+    overlay: impl Iterator<Item = (K, Option<V>)> + 'a,
+) -> impl Iterator<Item = (K, V)> + 'a {
+    struct Merge<B: Iterator, O: Iterator> {
+        base: Peekable<B>,
+        overlay: Peekable<O>,
+    }
+    impl<K: Ord, V, B, O> Iterator for Merge<B, O>
+    where
+        B: Iterator<Item = (K, V)>,
+        O: Iterator<Item = (K, Option<V>)>,
+    {
+        type Item = (K, V);
+
+        fn next(&mut self) -> Option<(K, V)> {
+            loop {
+                let order = match (self.base.peek(), self.overlay.peek()) {
+                    (None, None) => return None,
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (Some((base, _)), Some((overlay, _))) => base.cmp(overlay),
+                };
+                match order {
+                    Ordering::Less => return self.base.next(),
+                    // The overlay's entry replaces the base's
+                    Ordering::Equal => drop(self.base.next()),
+                    Ordering::Greater => {}
+                }
+                let (key, value) = self.overlay.next().expect("peeked");
+                if let Some(value) = value {
+                    return Some((key, value));
+                }
+            }
+        }
+    }
+    Merge {
+        base: base.peekable(),
+        overlay: overlay.peekable(),
+    }
+}
+
+/// A store in memory-mapped files in a directory
+#[derive(Debug)]
+pub struct MmapStore {
+    dir: PathBuf,
+    generation: u64,
+    leaves: Table,
+    nodes: Table,
+    /// Leaves changed (`None`: emptied) since the last compaction
+    leaf_overlay: BTreeMap<[u8; 32], Option<[u8; 32]>>,
+    /// Nodes changed (`None`: dropped) since the last compaction
+    node_overlay: BTreeMap<(usize, [u8; 32]), Option<[u8; 32]>>,
+    checkpoint: Option<Checkpoint>,
+    compact_threshold: usize,
+}
+
+impl MmapStore {
+    /// Open, or create, the store in `dir`
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let mut store = Self {
+            dir,
+            generation: 0,
+            leaves: Table::empty(LEAF_SIZE, 32),
+            nodes: Table::empty(NODE_SIZE, NODE_KEY_SIZE),
+            leaf_overlay: BTreeMap::new(),
+            node_overlay: BTreeMap::new(),
+            checkpoint: None,
+            compact_threshold: DEFAULT_COMPACT_THRESHOLD,
+        };
+        let current = match fs::read(store.dir.join("CURRENT")) {
+            Ok(current) => current,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(store),
+            Err(error) => return Err(error),
+        };
+        if current.len() != CURRENT_SIZE || current[..8] != MAGIC {
+            return Err(invalid("not a tree store"));
+        }
+        let leaf_size = match current[8] {
+            1 => V1_LEAF_SIZE,
+            VERSION => LEAF_SIZE,
+            version => return Err(invalid(format!("tree store version {version}"))),
+        };
+        let u64_at = |at: usize| u64::from_le_bytes(current[at..at + 8].try_into().expect("8"));
+        store.generation = u64_at(9);
+        store.checkpoint = Some(Checkpoint {
+            root: current[17..49].try_into().expect("32 bytes"),
+            leaf_count: u64_at(49),
+            sequence: u64_at(57),
+        });
+        store.leaves = Table::open(&store.path("leaves"), leaf_size, 32)?;
+        store.nodes = Table::open(&store.path("nodes"), NODE_SIZE, NODE_KEY_SIZE)?;
+        store.remove_stale()?;
+        Ok(store)
+    }
+
+    /// Write a store of exactly `leaves` and cached `nodes`, each in
+    /// ascending order, at `checkpoint`, into `dir`, which must hold no
+    /// store. Nothing is hashed or checked: this is for nodes computed
+    /// elsewhere, such as a benchmark's synthetic ones. Import a snapshot
+    /// into an empty store to check a list.
+    pub fn bulk_load(
+        dir: impl AsRef<Path>,
+        leaves: impl IntoIterator<Item = ([u8; 32], [u8; 32])>,
+        nodes: impl IntoIterator<Item = ((usize, [u8; 32]), [u8; 32])>,
+        checkpoint: Checkpoint,
+    ) -> io::Result<Self> {
+        let store = Self::open(dir)?;
+        if store.checkpoint.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "the directory already holds a tree store",
+            ));
+        }
+        store.write_generation(1, leaves, nodes, checkpoint)?;
+        Self::open(&store.dir)
+    }
+
+    /// Overlay entries past which a commit compacts
+    pub fn set_compact_threshold(&mut self, entries: usize) {
+        self.compact_threshold = entries;
+    }
+
+    /// Merge the commits since the last compaction into a new generation of
+    /// files, making them durable
+    pub fn compact(&mut self) -> io::Result<()> {
+        let Some(checkpoint) = self.checkpoint else {
+            return Ok(());
+        };
+        if self.leaf_overlay.is_empty() && self.node_overlay.is_empty() {
+            return Ok(());
+        }
+        let leaves = merge(
+            self.leaves.from(&[0; 32]).map(leaf),
+            self.leaf_overlay
+                .iter()
+                .map(|(index, leaf)| (*index, *leaf)),
+        );
+        let nodes = merge(
+            self.nodes.from(&[0; NODE_KEY_SIZE]).map(|record| {
+                let height = u16::from_be_bytes([record[0], record[1]]) as usize;
+                let first = record[2..34].try_into().expect("32 bytes");
+                ((height, first), record[34..].try_into().expect("32 bytes"))
+            }),
+            self.node_overlay.iter().map(|(key, hash)| (*key, *hash)),
+        );
+        let generation = self.generation + 1;
+        // Collected first: the writer can't borrow the tables it replaces
+        let leaves: Vec<_> = leaves.collect();
+        let nodes: Vec<_> = nodes.collect();
+        self.write_generation(generation, leaves, nodes, checkpoint)?;
+        self.generation = generation;
+        self.leaves = Table::open(&self.path("leaves"), LEAF_SIZE, 32)?;
+        self.nodes = Table::open(&self.path("nodes"), NODE_SIZE, NODE_KEY_SIZE)?;
+        self.leaf_overlay.clear();
+        self.node_overlay.clear();
+        self.remove_stale()
+    }
+
+    fn path(&self, table: &str) -> PathBuf {
+        self.dir.join(format!("{table}-{}", self.generation))
+    }
+
+    fn write_generation(
+        &self,
+        generation: u64,
+        leaves: impl IntoIterator<Item = ([u8; 32], [u8; 32])>,
+        nodes: impl IntoIterator<Item = ((usize, [u8; 32]), [u8; 32])>,
+        checkpoint: Checkpoint,
+    ) -> io::Result<()> {
+        let write = |table: &str, records: &mut dyn Iterator<Item = Vec<u8>>| {
+            let file = File::create(self.dir.join(format!("{table}-{generation}")))?;
+            let mut writer = BufWriter::with_capacity(1 << 20, file);
+            for record in records {
+                writer.write_all(&record)?;
+            }
+            writer
+                .into_inner()
+                .map_err(|error| error.into_error())?
+                .sync_all()
+        };
+        write(
+            "leaves",
+            &mut leaves
+                .into_iter()
+                .map(|(index, pubkey_hash)| [index, pubkey_hash].concat()),
+        )?;
+        write(
+            "nodes",
+            &mut nodes.into_iter().map(|((height, first), hash)| {
+                let mut record = node_key(height, &first).to_vec();
+                record.extend_from_slice(&hash);
+                record
+            }),
+        )?;
+
+        let mut current = Vec::with_capacity(CURRENT_SIZE);
+        current.extend_from_slice(&MAGIC);
+        current.push(VERSION);
+        current.extend_from_slice(&generation.to_le_bytes());
+        current.extend_from_slice(&checkpoint.root);
+        current.extend_from_slice(&checkpoint.leaf_count.to_le_bytes());
+        current.extend_from_slice(&checkpoint.sequence.to_le_bytes());
+        let staged = self.dir.join("CURRENT.tmp");
+        let mut file = File::create(&staged)?;
+        file.write_all(&current)?;
+        file.sync_all()?;
+        fs::rename(&staged, self.dir.join("CURRENT"))?;
+        File::open(&self.dir)?.sync_all()
+    }
+
+    /// Remove other generations' files, such as a compaction's that was cut
+    /// short
+    fn remove_stale(&self) -> io::Result<()> {
+        let current = self.generation.to_string();
+        for entry in fs::read_dir(&self.dir)? {
+            let name = entry?.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            let generation = name
+                .strip_prefix("leaves-")
+                .or_else(|| name.strip_prefix("nodes-"));
+            if generation.is_some_and(|generation| generation != current) {
+                fs::remove_file(self.dir.join(name))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Store for MmapStore {
+    type Error = io::Error;
+
+    fn leaf(&self, index: &[u8; 32]) -> io::Result<Option<[u8; 32]>> {
+        Ok(match self.leaf_overlay.get(index) {
+            Some(pubkey_hash) => *pubkey_hash,
+            None => self.leaves.get(index).map(|record| leaf(record).1),
+        })
+    }
+
+    fn leaves(
+        &self,
+        first: &[u8; 32],
+        last: &[u8; 32],
+    ) -> impl Iterator<Item = io::Result<([u8; 32], [u8; 32])>> + '_ {
+        let last = *last;
+        let base = self
+            .leaves
+            .from(first)
+            .map(leaf)
+            .take_while(move |(index, _)| *index <= last);
+        let overlay = self
+            .leaf_overlay
+            .range(*first..=last)
+            .map(|(index, leaf)| (*index, *leaf));
+        merge(base, overlay).map(Ok)
+    }
+
+    fn node(&self, height: usize, first: &[u8; 32]) -> io::Result<Option<[u8; 32]>> {
+        if let Some(hash) = self.node_overlay.get(&(height, *first)) {
+            return Ok(*hash);
+        }
+        Ok(self
+            .nodes
+            .get(&node_key(height, first))
+            .map(|record| record[NODE_KEY_SIZE..].try_into().expect("32 bytes")))
+    }
+
+    fn checkpoint(&self) -> io::Result<Option<Checkpoint>> {
+        Ok(self.checkpoint)
+    }
+
+    /// Commits to the overlay, compacting once it passes the threshold
+    fn commit(&mut self, batch: &Batch) -> io::Result<()> {
+        self.leaf_overlay.extend(&batch.leaves);
+        self.node_overlay.extend(&batch.nodes);
+        self.checkpoint = Some(batch.checkpoint);
+        if self.leaf_overlay.len() + self.node_overlay.len() > self.compact_threshold {
+            self.compact()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+//! Exclusion proofs for several pubkeys at once, for the batch circuit
+//!
+//! Paths to different leaves join below the root and share every sibling
+//! above that point. Where two paths meet, each one's node is the other's
+//! sibling, so neither needs to be sent. A multiproof carries only the
+//! siblings that none of its paths computes, each of them once. Verifying it
+//! rebuilds the paths level by level, taking siblings in the order they were
+//! written: leaf level first, then by index within a level.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    path::Path,
+};
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    first_under, leaf_index, parent, path_bit,
+    prover_toml::{self, Decimal, Field},
+    pubkey_to_index, sibling_of, Batch, Pending, SparseMerkleTree, Store, EMPTY_LEAF,
+};
+
+/// Pubkeys a batch proof covers (must match the batch circuit's BATCH_SIZE)
+pub const BATCH_SIZE: usize = 4;
+
+/// Size of a batch proof's public witness: gnark's 12-byte header, then
+/// `smt_root`, each of the `BATCH_SIZE` pubkey hashes and `recent_slot`
+pub const BATCH_PUBLIC_WITNESS_SIZE: usize = 12 + (BATCH_SIZE + 2) * 32;
+
+/// The batch circuit's `Prover.toml`, field for parameter
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct BatchProverToml {
+    pub smt_root: Field,
+    pub pubkey_hashes: Vec<Field>,
+    pub recent_slot: Decimal,
+    pub pubkeys: Vec<[u8; 32]>,
+    /// Each pubkey's path, leaf level first
+    pub siblings: Vec<Vec<Field>>,
+}
+
+/// Sibling hashes proving several pubkeys' leaves are empty, each sent once
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExclusionMultiproof {
+    /// The pubkeys' hashes, in the order they were given
+    pub pubkey_hashes: Vec<[u8; 32]>,
+    /// Levels in the tree
+    pub depth: usize,
+    /// Hashes of the nodes beside the paths that no path computes, leaf level
+    /// first and by index within a level
+    pub siblings: Vec<[u8; 32]>,
+}
+
+/// Every node a multiproof's paths compute, by level and first index, and
+/// the siblings it carries, by the same
+struct Nodes {
+    levels: Vec<BTreeMap<[u8; 32], [u8; 32]>>,
+    siblings: BTreeMap<(usize, [u8; 32]), [u8; 32]>,
+}
+
+impl ExclusionMultiproof {
+    /// Rebuild every path, or `None` if there are too few or too many
+    /// siblings for them
+    fn nodes(&self) -> Option<Nodes> {
+        let mut level: BTreeMap<_, _> = self
+            .pubkey_hashes
+            .iter()
+            .map(|pubkey_hash| (leaf_index(pubkey_hash, self.depth), EMPTY_LEAF))
+            .collect();
+        let mut levels = Vec::with_capacity(self.depth + 1);
+        let mut siblings = BTreeMap::new();
+        let mut carried = self.siblings.iter();
+        for height in 0..self.depth {
+            let mut above = BTreeMap::new();
+            for (first, hash) in &level {
+                let sibling_first = sibling_of(first, height);
+                let sibling = match level.get(&sibling_first) {
+                    // The left one of the pair hashed them both
+                    Some(_) if path_bit(first, height) => continue,
+                    Some(sibling) => *sibling,
+                    None => {
+                        let sibling = *carried.next()?;
+                        siblings.insert((height, sibling_first), sibling);
+                        sibling
+                    }
+                };
+                let hash = parent(first, height, hash, &sibling);
+                above.insert(first_under(first, height + 1), hash);
+            }
+            levels.push(std::mem::replace(&mut level, above));
+        }
+        levels.push(level);
+        carried
+            .next()
+            .is_none()
+            .then_some(Nodes { levels, siblings })
+    }
+
+    /// The root these siblings prove every leaf empty under, or `None` if
+    /// there are too few or too many of them
+    pub fn root(&self) -> Option<[u8; 32]> {
+        let nodes = self.nodes()?;
+        nodes.levels[self.depth].values().next().copied()
+    }
+
+    /// Each pubkey's full sibling path, leaf level first, as an
+    /// [`ExclusionProof`](crate::ExclusionProof) holds it; `None` as for
+    /// [`root`](Self::root)
+    pub fn paths(&self) -> Option<Vec<Vec<[u8; 32]>>> {
+        let nodes = self.nodes()?;
+        let paths = self.pubkey_hashes.iter().map(|pubkey_hash| {
+            let index = leaf_index(pubkey_hash, self.depth);
+            (0..self.depth)
+                .map(|level| {
+                    let sibling = sibling_of(&index, level);
+                    match nodes.levels[level].get(&sibling) {
+                        Some(hash) => *hash,
+                        None => nodes.siblings[&(level, sibling)],
+                    }
+                })
+                .collect()
+        });
+        Some(paths.collect())
+    }
+}
+
+/// The batch circuit's inputs, except the public `recent_slot`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExclusionMultiproofInputs {
+    /// Public: the tree's root
+    pub smt_root: [u8; 32],
+    /// Exactly `BATCH_SIZE` pubkeys, the last one repeated to fill the batch
+    pub pubkeys: Vec<[u8; 32]>,
+    /// Their shared siblings; `pubkey_hashes` are the circuit's public ones
+    pub multiproof: ExclusionMultiproof,
+}
+
+/// The public witness for proving each of `pubkey_hashes` is excluded from
+/// `smt_root` at `recent_slot`, laid out as [`public_witness`] lays out one
+///
+/// [`public_witness`]: crate::inputs::public_witness
+pub fn batch_public_witness(
+    smt_root: &[u8; 32],
+    pubkey_hashes: &[[u8; 32]],
+    recent_slot: u64,
+) -> Vec<u8> {
+    let inputs = pubkey_hashes.len() as u32 + 2;
+    let mut witness = Vec::with_capacity(12 + inputs as usize * 32);
+    witness.extend_from_slice(&inputs.to_be_bytes());
+    witness.extend_from_slice(&0u32.to_be_bytes());
+    witness.extend_from_slice(&inputs.to_be_bytes());
+    witness.extend_from_slice(smt_root);
+    for pubkey_hash in pubkey_hashes {
+        witness.extend_from_slice(pubkey_hash);
+    }
+    witness.extend_from_slice(&[0; 24]);
+    witness.extend_from_slice(&recent_slot.to_be_bytes());
+    witness
+}
+
+impl ExclusionMultiproofInputs {
+    /// The inputs to prove at `recent_slot`, each pubkey's path expanded
+    /// from the shared siblings, as `Prover.toml` holds them
+    pub fn prover_toml(&self, recent_slot: u64) -> BatchProverToml {
+        let paths = self
+            .multiproof
+            .paths()
+            .expect("a tree's multiproof has the siblings its paths need");
+        BatchProverToml {
+            smt_root: Field(self.smt_root),
+            pubkey_hashes: prover_toml::fields(&self.multiproof.pubkey_hashes),
+            recent_slot: Decimal(recent_slot),
+            pubkeys: self.pubkeys.clone(),
+            siblings: paths.iter().map(|path| prover_toml::fields(path)).collect(),
+        }
+    }
+
+    /// `Prover.toml` contents for the batch circuit
+    pub fn to_prover_toml(&self, recent_slot: u64) -> String {
+        prover_toml::to_string("SMT Batch Exclusion Proof", &self.prover_toml(recent_slot))
+            .expect("a struct of values and arrays")
+    }
+
+    /// The public witness a proof of these inputs at `recent_slot` comes with
+    pub fn public_witness(&self, recent_slot: u64) -> Vec<u8> {
+        batch_public_witness(&self.smt_root, &self.multiproof.pubkey_hashes, recent_slot)
+    }
+
+    /// Write `Prover.toml` for proving at `recent_slot`
+    pub fn write_prover_toml(&self, path: impl AsRef<Path>, recent_slot: u64) -> io::Result<()> {
+        std::fs::write(path, self.to_prover_toml(recent_slot))
+    }
+}
+
+impl<S: Store> SparseMerkleTree<S> {
+    /// Shared siblings proving none of `pubkeys` is blacklisted, or `None` if
+    /// any of them is, or in a shallow tree shares a leaf with a pubkey that is
+    pub fn exclusion_multiproof(
+        &self,
+        pubkeys: &[[u8; 32]],
+    ) -> Result<Option<ExclusionMultiproof>, S::Error> {
+        let pubkey_hashes: Vec<[u8; 32]> = pubkeys.par_iter().map(pubkey_to_index).collect();
+        let mut nodes: BTreeSet<[u8; 32]> = pubkey_hashes
+            .iter()
+            .map(|pubkey_hash| leaf_index(pubkey_hash, self.depth))
+            .collect();
+        for index in &nodes {
+            if self.store.contains(index)? {
+                return Ok(None);
+            }
+        }
+        let mut beside = Vec::new();
+        for level in 0..self.depth {
+            beside.extend(
+                nodes
+                    .iter()
+                    .map(|first| sibling_of(first, level))
+                    .filter(|sibling| !nodes.contains(sibling))
+                    .map(|sibling| (level, sibling)),
+            );
+            nodes = nodes
+                .iter()
+                .map(|first| first_under(first, level + 1))
+                .collect();
+        }
+        let mut batch = Batch::default();
+        let pending = Pending::new(&self.store, &mut batch, self.depth);
+        let siblings = beside
+            .into_par_iter()
+            .map(|(level, sibling)| pending.subtree(&sibling, level))
+            .collect::<Result<_, _>>()?;
+        Ok(Some(ExclusionMultiproof {
+            pubkey_hashes,
+            depth: self.depth,
+            siblings,
+        }))
+    }
+
+    /// Everything the batch circuit takes to prove none of `pubkeys` is
+    /// blacklisted, or `None` if any of them is
+    ///
+    /// # Panics
+    ///
+    /// If there are no pubkeys or more than `BATCH_SIZE`
+    pub fn generate_exclusion_multiproof(
+        &self,
+        pubkeys: &[[u8; 32]],
+    ) -> Result<Option<ExclusionMultiproofInputs>, S::Error> {
+        assert!(
+            (1..=BATCH_SIZE).contains(&pubkeys.len()),
+            "a batch proof covers 1 to {BATCH_SIZE} pubkeys"
+        );
+        let mut pubkeys = pubkeys.to_vec();
+        pubkeys.resize(BATCH_SIZE, *pubkeys.last().expect("at least one"));
+        let Some(multiproof) = self.exclusion_multiproof(&pubkeys)? else {
+            return Ok(None);
+        };
+        Ok(Some(ExclusionMultiproofInputs {
+            smt_root: self.root(),
+            pubkeys,
+            multiproof,
+        }))
+    }
+}
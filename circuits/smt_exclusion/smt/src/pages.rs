@@ -0,0 +1,88 @@
+//! A sorted map kept in shared pages, copied on write
+//!
+//! Cloning the map shares every page. Changing an entry copies only its page
+//! and the page list, so a clone taken before a batch still reads the tree as
+//! it was, at the cost of the pages the batch touches.
+
+use std::sync::Arc;
+
+/// Entries a page splits at twice of
+const PAGE_SIZE: usize = 256;
+
+/// Entries in key order, shared between clones
+type Page<K, V> = Arc<Vec<(K, V)>>;
+
+#[derive(Clone, Debug)]
+pub(crate) struct PagedMap<K, V> {
+    /// Pages in key order, none empty
+    pages: Arc<Vec<Page<K, V>>>,
+}
+
+impl<K, V> Default for PagedMap<K, V> {
+    fn default() -> Self {
+        Self {
+            pages: Arc::default(),
+        }
+    }
+}
+
+/// The page `key` is in or belongs in: the last starting at or before it
+fn page_of<K: Ord, V>(pages: &[Page<K, V>], key: &K) -> usize {
+    pages
+        .partition_point(|page| &page[0].0 <= key)
+        .saturating_sub(1)
+}
+
+impl<K: Ord + Clone, V: Clone> PagedMap<K, V> {
+    pub(crate) fn get(&self, key: &K) -> Option<&V> {
+        let page = self.pages.get(page_of(&self.pages, key))?;
+        let i = page.binary_search_by(|(other, _)| other.cmp(key)).ok()?;
+        Some(&page[i].1)
+    }
+
+    /// Entries from `first` to `last`, both included, in order
+    pub(crate) fn range(&self, first: K, last: K) -> impl Iterator<Item = &(K, V)> {
+        let start = page_of(&self.pages, &first);
+        self.pages[start.min(self.pages.len())..]
+            .iter()
+            .flat_map(|page| page.iter())
+            .skip_while(move |(key, _)| *key < first)
+            .take_while(move |(key, _)| *key <= last)
+    }
+
+    pub(crate) fn insert(&mut self, key: K, value: V) {
+        let pages = Arc::make_mut(&mut self.pages);
+        if pages.is_empty() {
+            pages.push(Arc::new(vec![(key, value)]));
+            return;
+        }
+        let p = page_of(pages, &key);
+        let page = Arc::make_mut(&mut pages[p]);
+        match page.binary_search_by(|(other, _)| other.cmp(&key)) {
+            Ok(i) => page[i].1 = value,
+            Err(i) => {
+                page.insert(i, (key, value));
+                if page.len() > 2 * PAGE_SIZE {
+                    let right = page.split_off(PAGE_SIZE);
+                    pages.insert(p + 1, Arc::new(right));
+                }
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, key: &K) {
+        let p = page_of(&self.pages, key);
+        let Some(page) = self.pages.get(p) else {
+            return;
+        };
+        let Ok(i) = page.binary_search_by(|(other, _)| other.cmp(key)) else {
+            return;
+        };
+        let pages = Arc::make_mut(&mut self.pages);
+        let page = Arc::make_mut(&mut pages[p]);
+        page.remove(i);
+        if page.is_empty() {
+            pages.remove(p);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! `Prover.toml` from serializable structs
+//!
+//! A circuit's inputs are a struct whose fields are named after `main`'s
+//! parameters and derive `Serialize`; `to_string` writes it as the TOML
+//! `nargo execute` reads. Field elements are [`Field`]s, written as `0x` and
+//! 64 hex digits, and slots and indices [`Decimal`]s. Integers (`pubkey`
+//! bytes) are plain TOML integers, and arrays, including arrays of arrays, are
+//! TOML arrays in the order the circuit takes them.
+
+use serde::{Serialize, Serializer};
+
+use crate::inputs::field_hex;
+
+/// A field element, big-endian
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Field(pub [u8; 32]);
+
+impl Serialize for Field {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&field_hex(&self.0))
+    }
+}
+
+/// A field element written as a decimal string, for slots and indices
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Decimal(pub u64);
+
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+/// Each of `fields` as a [`Field`]
+pub fn fields(fields: &[[u8; 32]]) -> Vec<Field> {
+    fields.iter().copied().map(Field).collect()
+}
+
+/// `inputs` as `Prover.toml` contents, after a `# {title}` comment
+///
+/// Fails only if `inputs` isn't a struct of values and arrays, which a TOML
+/// document can't hold at the top level.
+pub fn to_string<T: Serialize>(title: &str, inputs: &T) -> Result<String, toml::ser::Error> {
+    Ok(format!(
+        "# {title} - Generated by smt\n\n{}",
+        toml::to_string_pretty(inputs)?
+    ))
+}
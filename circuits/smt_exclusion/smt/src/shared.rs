@@ -0,0 +1,62 @@
+//! A tree that many threads read while one updates it
+//!
+//! Readers take the latest published version and keep it as long as they
+//! like: it never changes, so every proof from it agrees with its root. An
+//! update applies its batch to a clone, which shares the unchanged pages of
+//! the [`MemoryStore`](crate::MemoryStore), then publishes it. Readers never
+//! wait for a batch; the only lock they take guards swapping the version.
+
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{Op, SparseMerkleTree, TreeError};
+
+#[derive(Debug)]
+pub struct SharedTree {
+    /// The version readers get
+    published: Mutex<Arc<SparseMerkleTree>>,
+    /// The version being updated; one batch at a time
+    writer: Mutex<SparseMerkleTree>,
+}
+
+impl SharedTree {
+    pub fn new(tree: SparseMerkleTree) -> Self {
+        Self {
+            published: Mutex::new(Arc::new(tree.clone())),
+            writer: Mutex::new(tree),
+        }
+    }
+
+    /// The latest published version
+    pub fn snapshot(&self) -> Arc<SparseMerkleTree> {
+        let published = self
+            .published
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        Arc::clone(&published)
+    }
+
+    /// Apply `ops` as [`SparseMerkleTree::apply_batch`] does and publish the
+    /// result; returns how many leaves changed. Snapshots taken before keep
+    /// the version they had.
+    pub fn apply_batch(&self, ops: &[Op]) -> Result<usize, TreeError<Infallible>> {
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let changed = writer.apply_batch(ops)?;
+        if changed > 0 {
+            let next = Arc::new(writer.clone());
+            *self
+                .published
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) = next;
+        }
+        Ok(changed)
+    }
+}
+
+impl From<SparseMerkleTree> for SharedTree {
+    fn from(tree: SparseMerkleTree) -> Self {
+        Self::new(tree)
+    }
+}
@@ -0,0 +1,168 @@
+//! Snapshots signed by the list's admin
+//!
+//! The admin's ed25519 key signs a statement binding the snapshot's root, leaf
+//! count, the time it was published and the SHA-256 of the snapshot file.
+//! Whoever downloads the list checks the signature and that the statement
+//! describes the file; the client crate then checks the signer is the state
+//! account's admin and the root is the one on chain.
+//!
+//! Layout: magic `SMTSIGN\0` (8), version (1), signer (32), signature (64),
+//! unix time (8, little-endian), then the snapshot as `export_snapshot` writes
+//! it. Root, leaf count and list hash are read from the snapshot.
+
+use std::{convert::Infallible, fmt};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+use crate::snapshot::{read_header, SnapshotError};
+
+pub const MAGIC: [u8; 8] = *b"SMTSIGN\0";
+pub const VERSION: u8 = 1;
+/// Prefix of every signed statement, so the signature can't be replayed as a
+/// transaction or another message
+pub const DOMAIN: &[u8] = b"smt-exclusion snapshot v1\0";
+
+const HEADER_SIZE: usize = 8 + 1 + 32 + 64 + 8;
+
+/// Why a signed snapshot couldn't be made, read or verified
+#[derive(Debug)]
+pub enum SignedSnapshotError {
+    /// The wrapped snapshot is damaged or unreadable
+    Snapshot(SnapshotError<Infallible>),
+    /// Not a signed snapshot this version reads
+    Format(String),
+    /// The signature isn't the signer's over the statement
+    Signature,
+}
+
+impl fmt::Display for SignedSnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Snapshot(error) => error.fmt(f),
+            Self::Format(reason) => write!(f, "invalid signed snapshot: {reason}"),
+            Self::Signature => write!(f, "snapshot signature does not verify"),
+        }
+    }
+}
+
+impl std::error::Error for SignedSnapshotError {}
+
+impl From<SnapshotError<Infallible>> for SignedSnapshotError {
+    fn from(error: SnapshotError<Infallible>) -> Self {
+        Self::Snapshot(error)
+    }
+}
+
+/// What the admin signs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SnapshotStatement {
+    pub root: [u8; 32],
+    pub leaf_count: u64,
+    /// When the snapshot was published, in seconds since the Unix epoch
+    pub unix_time: i64,
+    /// SHA-256 of the snapshot file
+    pub list_hash: [u8; 32],
+}
+
+impl SnapshotStatement {
+    /// The statement for `snapshot`, published at `unix_time`
+    pub fn for_snapshot(snapshot: &[u8], unix_time: i64) -> Result<Self, SignedSnapshotError> {
+        let header = read_header(snapshot)?;
+        Ok(Self {
+            root: header.root,
+            leaf_count: header.leaf_count,
+            unix_time,
+            list_hash: Sha256::digest(snapshot).into(),
+        })
+    }
+
+    /// The signed bytes: `DOMAIN`, root, leaf count, unix time, list hash
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = DOMAIN.to_vec();
+        message.extend_from_slice(&self.root);
+        message.extend_from_slice(&self.leaf_count.to_le_bytes());
+        message.extend_from_slice(&self.unix_time.to_le_bytes());
+        message.extend_from_slice(&self.list_hash);
+        message
+    }
+}
+
+/// A snapshot with its admin's signature
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedSnapshot {
+    pub statement: SnapshotStatement,
+    /// The signer's ed25519 public key (a Solana pubkey)
+    pub signer: [u8; 32],
+    pub signature: [u8; 64],
+    pub snapshot: Vec<u8>,
+}
+
+impl SignedSnapshot {
+    /// Sign `snapshot` as published at `unix_time`
+    pub fn sign(
+        snapshot: Vec<u8>,
+        unix_time: i64,
+        key: &SigningKey,
+    ) -> Result<Self, SignedSnapshotError> {
+        let statement = SnapshotStatement::for_snapshot(&snapshot, unix_time)?;
+        Ok(Self {
+            signature: key.sign(&statement.message()).to_bytes(),
+            signer: key.verifying_key().to_bytes(),
+            statement,
+            snapshot,
+        })
+    }
+
+    /// Check the signature, and that the statement describes the snapshot.
+    /// This doesn't rebuild the tree; `import_snapshot` checks the leaves hash
+    /// to the root.
+    pub fn verify(&self) -> Result<(), SignedSnapshotError> {
+        let statement = SnapshotStatement::for_snapshot(&self.snapshot, self.statement.unix_time)?;
+        if statement != self.statement {
+            return Err(SignedSnapshotError::Format(
+                "statement does not describe the snapshot".into(),
+            ));
+        }
+        let signer =
+            VerifyingKey::from_bytes(&self.signer).map_err(|_| SignedSnapshotError::Signature)?;
+        signer
+            .verify_strict(
+                &statement.message(),
+                &Signature::from_bytes(&self.signature),
+            )
+            .map_err(|_| SignedSnapshotError::Signature)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + self.snapshot.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.signer);
+        bytes.extend_from_slice(&self.signature);
+        bytes.extend_from_slice(&self.statement.unix_time.to_le_bytes());
+        bytes.extend_from_slice(&self.snapshot);
+        bytes
+    }
+
+    /// Read a signed snapshot; `verify` checks it
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SignedSnapshotError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(SignedSnapshotError::Format("truncated".into()));
+        }
+        if bytes[..8] != MAGIC {
+            return Err(SignedSnapshotError::Format("not a signed snapshot".into()));
+        }
+        if bytes[8] != VERSION {
+            return Err(SignedSnapshotError::Format(format!("version {}", bytes[8])));
+        }
+        let unix_time = i64::from_le_bytes(bytes[105..113].try_into().expect("8 bytes"));
+        let snapshot = bytes[HEADER_SIZE..].to_vec();
+        Ok(Self {
+            statement: SnapshotStatement::for_snapshot(&snapshot, unix_time)?,
+            signer: bytes[9..41].try_into().expect("32 bytes"),
+            signature: bytes[41..105].try_into().expect("64 bytes"),
+            snapshot,
+        })
+    }
+}
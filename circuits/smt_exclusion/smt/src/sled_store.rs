@@ -0,0 +1,139 @@
+//! A store on disk, in a sled database
+
+use std::{io, path::Path};
+
+use crate::store::{Batch, Checkpoint, Store};
+
+/// Leaf keys: this, then the index. The value is the pubkey hash the leaf
+/// holds, or empty if that is the index, as stores from before shallow trees
+/// held their leaves.
+const LEAF: u8 = b'l';
+/// Node keys: this, the height, then the first index
+const NODE: u8 = b'n';
+const CHECKPOINT: &[u8] = b"checkpoint";
+
+fn leaf_key(index: &[u8; 32]) -> [u8; 33] {
+    let mut key = [LEAF; 33];
+    key[1..].copy_from_slice(index);
+    key
+}
+
+fn node_key(height: usize, first: &[u8; 32]) -> [u8; 34] {
+    let mut key = [NODE; 34];
+    key[1] = height as u8;
+    key[2..].copy_from_slice(first);
+    key
+}
+
+fn corrupt(what: &str) -> sled::Error {
+    sled::Error::Io(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("malformed {what} in tree store"),
+    ))
+}
+
+fn field(bytes: &[u8], what: &str) -> sled::Result<[u8; 32]> {
+    bytes.try_into().map_err(|_| corrupt(what))
+}
+
+/// The pubkey hash a leaf's value names
+fn pubkey_hash(index: &[u8; 32], value: &[u8]) -> sled::Result<[u8; 32]> {
+    match value {
+        [] => Ok(*index),
+        value => field(value, "leaf"),
+    }
+}
+
+/// A store in a sled database. Each commit is one atomic sled batch, flushed
+/// before it returns.
+#[derive(Clone, Debug)]
+pub struct SledStore {
+    db: sled::Db,
+}
+
+impl SledStore {
+    /// Open, or create, the database at `path`
+    pub fn open(path: impl AsRef<Path>) -> sled::Result<Self> {
+        Ok(Self {
+            db: sled::open(path)?,
+        })
+    }
+
+    /// Store in an already open database, which it takes the default tree of
+    pub fn new(db: sled::Db) -> Self {
+        Self { db }
+    }
+}
+
+impl Store for SledStore {
+    type Error = sled::Error;
+
+    fn leaf(&self, index: &[u8; 32]) -> sled::Result<Option<[u8; 32]>> {
+        self.db
+            .get(leaf_key(index))?
+            .map(|value| pubkey_hash(index, &value))
+            .transpose()
+    }
+
+    fn leaves(
+        &self,
+        first: &[u8; 32],
+        last: &[u8; 32],
+    ) -> impl Iterator<Item = sled::Result<([u8; 32], [u8; 32])>> + '_ {
+        self.db
+            .range(leaf_key(first)..=leaf_key(last))
+            .map(|entry| {
+                let (key, value) = entry?;
+                let index = field(&key[1..], "leaf key")?;
+                Ok((index, pubkey_hash(&index, &value)?))
+            })
+    }
+
+    fn node(&self, height: usize, first: &[u8; 32]) -> sled::Result<Option<[u8; 32]>> {
+        self.db
+            .get(node_key(height, first))?
+            .map(|hash| field(&hash, "node"))
+            .transpose()
+    }
+
+    fn checkpoint(&self) -> sled::Result<Option<Checkpoint>> {
+        let Some(bytes) = self.db.get(CHECKPOINT)? else {
+            return Ok(None);
+        };
+        if bytes.len() != 48 {
+            return Err(corrupt("checkpoint"));
+        }
+        let u64_at = |offset: usize| {
+            u64::from_le_bytes(bytes[offset..offset + 8].try_into().expect("8 bytes"))
+        };
+        Ok(Some(Checkpoint {
+            root: field(&bytes[..32], "checkpoint")?,
+            leaf_count: u64_at(32),
+            sequence: u64_at(40),
+        }))
+    }
+
+    fn commit(&mut self, batch: &Batch) -> sled::Result<()> {
+        let mut writes = sled::Batch::default();
+        for (index, pubkey_hash) in &batch.leaves {
+            match pubkey_hash {
+                Some(pubkey_hash) => writes.insert(&leaf_key(index)[..], &pubkey_hash[..]),
+                None => writes.remove(&leaf_key(index)[..]),
+            }
+        }
+        for ((height, first), hash) in &batch.nodes {
+            match hash {
+                Some(hash) => writes.insert(&node_key(*height, first)[..], &hash[..]),
+                None => writes.remove(&node_key(*height, first)[..]),
+            }
+        }
+        let checkpoint = &batch.checkpoint;
+        let mut bytes = checkpoint.root.to_vec();
+        bytes.extend_from_slice(&checkpoint.leaf_count.to_le_bytes());
+        bytes.extend_from_slice(&checkpoint.sequence.to_le_bytes());
+        writes.insert(CHECKPOINT, bytes);
+        self.db.apply_batch(writes)?;
+        self.db.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+//! Tree snapshots: the exact leaves behind a root, in a stable binary format
+//!
+//! A list maintainer publishes the snapshot of the tree whose root is on
+//! chain; anyone can import it, which rebuilds the tree and checks it hashes
+//! to that root. The same tree always exports the same bytes.
+//!
+//! Layout, integers little-endian:
+//!
+//! | Field | Size |
+//! |-------|------|
+//! | Magic `SMTSNAP\0` | 8 |
+//! | Version (2) | 1 |
+//! | Flags (bit 0: body is zstd-compressed) | 1 |
+//! | Tree depth | 2 |
+//! | Root | 32 |
+//! | Leaf count | 8 |
+//! | Body length | 8 |
+//! | Body: each leaf's pubkey hash, by ascending leaf index, 32 bytes each | body length |
+//! | SHA-256 of everything above | 32 |
+//!
+//! Version 1 bodies hold the leaf indices, which are the pubkey hashes in
+//! trees of 254 or more levels; those still import.
+
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt,
+    io::{self, Read, Write},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{leaf_index, SparseMerkleTree, Store, SUPPORTED_DEPTHS, TREE_DEPTH};
+
+pub const MAGIC: [u8; 8] = *b"SMTSNAP\0";
+pub const VERSION: u8 = 2;
+/// Flag: the body is zstd-compressed
+pub const ZSTD: u8 = 1;
+/// zstd level for compressed snapshots; fixed, so they are reproducible too
+pub const ZSTD_LEVEL: i32 = 19;
+
+const HEADER_SIZE: usize = 8 + 1 + 1 + 2 + 32 + 8 + 8;
+
+/// Why a snapshot couldn't be exported or imported
+#[derive(Debug)]
+pub enum SnapshotError<E> {
+    Io(io::Error),
+    /// Not a snapshot this version reads
+    Format(String),
+    /// The checksum doesn't match: the file is damaged
+    Checksum,
+    /// The leaves hash to a different root than the snapshot names
+    RootMismatch {
+        expected: [u8; 32],
+        actual: [u8; 32],
+    },
+    /// The store to import into already holds leaves
+    NotEmpty,
+    Store(E),
+}
+
+impl<E: fmt::Display> fmt::Display for SnapshotError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "snapshot I/O: {error}"),
+            Self::Format(reason) => write!(f, "invalid snapshot: {reason}"),
+            Self::Checksum => write!(f, "snapshot checksum mismatch"),
+            Self::RootMismatch { expected, actual } => write!(
+                f,
+                "snapshot leaves hash to {} instead of its root {}",
+                crate::inputs::field_hex(actual),
+                crate::inputs::field_hex(expected)
+            ),
+            Self::NotEmpty => write!(f, "can only import a snapshot into an empty store"),
+            Self::Store(error) => write!(f, "tree store: {error}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for SnapshotError<E> {}
+
+impl<E> From<io::Error> for SnapshotError<E> {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+fn format_error<E>(reason: impl Into<String>) -> SnapshotError<E> {
+    SnapshotError::Format(reason.into())
+}
+
+/// What a snapshot's header says
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SnapshotHeader {
+    pub compressed: bool,
+    /// Levels in the tree; its leaf indices are below 2^depth
+    pub depth: usize,
+    pub root: [u8; 32],
+    pub leaf_count: u64,
+}
+
+/// Check a snapshot's format and checksum, and read its header, without
+/// rebuilding the tree
+pub fn read_header(snapshot: &[u8]) -> Result<SnapshotHeader, SnapshotError<Infallible>> {
+    Ok(split(snapshot)?.0)
+}
+
+/// A checked snapshot's header and body
+fn split<E>(snapshot: &[u8]) -> Result<(SnapshotHeader, &[u8]), SnapshotError<E>> {
+    if snapshot.len() < HEADER_SIZE + 32 {
+        return Err(format_error("truncated"));
+    }
+    let (contents, checksum) = snapshot.split_at(snapshot.len() - 32);
+    if contents[..8] != MAGIC {
+        return Err(format_error("not a tree snapshot"));
+    }
+    if Sha256::digest(contents).as_slice() != checksum {
+        return Err(SnapshotError::Checksum);
+    }
+    let (header, body) = contents.split_at(HEADER_SIZE);
+    let flags = header[9];
+    if flags & !ZSTD != 0 {
+        return Err(format_error(format!("unknown flags {flags:#04x}")));
+    }
+    let depth = u16::from_le_bytes([header[10], header[11]]) as usize;
+    if !SUPPORTED_DEPTHS.contains(&depth) {
+        return Err(format_error(format!("unsupported depth {depth}")));
+    }
+    match header[8] {
+        VERSION => {}
+        1 if depth >= TREE_DEPTH => {}
+        1 => return Err(format_error("version 1 of a shallow tree: export it again")),
+        version => return Err(format_error(format!("version {version}"))),
+    }
+    let body_len = u64::from_le_bytes(header[52..60].try_into().expect("8 bytes"));
+    if body_len != body.len() as u64 {
+        return Err(format_error("body length"));
+    }
+    let header = SnapshotHeader {
+        compressed: flags & ZSTD != 0,
+        depth,
+        root: header[12..44].try_into().expect("32 bytes"),
+        leaf_count: u64::from_le_bytes(header[44..52].try_into().expect("8 bytes")),
+    };
+    Ok((header, body))
+}
+
+#[cfg(feature = "zstd")]
+fn compress(body: &[u8]) -> io::Result<Vec<u8>> {
+    zstd::encode_all(body, ZSTD_LEVEL)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn compress(_body: &[u8]) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "compressing a snapshot needs the zstd feature",
+    ))
+}
+
+#[cfg(feature = "zstd")]
+fn decompress(body: &[u8]) -> io::Result<Vec<u8>> {
+    zstd::decode_all(body)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress(_body: &[u8]) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reading a compressed snapshot needs the zstd feature",
+    ))
+}
+
+impl<S: Store> SparseMerkleTree<S> {
+    /// Write the tree's snapshot to `writer`, zstd-compressed if `compressed`
+    pub fn export_snapshot(
+        &self,
+        mut writer: impl Write,
+        compressed: bool,
+    ) -> Result<(), SnapshotError<S::Error>> {
+        let mut body = Vec::with_capacity(self.len() * 32);
+        for leaf in self.store().leaves(&[0; 32], &[0xff; 32]) {
+            let (_, pubkey_hash) = leaf.map_err(SnapshotError::Store)?;
+            body.extend_from_slice(&pubkey_hash);
+        }
+        if compressed {
+            body = compress(&body)?;
+        }
+        let mut snapshot = Vec::with_capacity(HEADER_SIZE + body.len() + 32);
+        snapshot.extend_from_slice(&MAGIC);
+        snapshot.push(VERSION);
+        snapshot.push(if compressed { ZSTD } else { 0 });
+        snapshot.extend_from_slice(&(self.depth() as u16).to_le_bytes());
+        snapshot.extend_from_slice(&self.root());
+        snapshot.extend_from_slice(&(self.len() as u64).to_le_bytes());
+        snapshot.extend_from_slice(&(body.len() as u64).to_le_bytes());
+        snapshot.extend_from_slice(&body);
+        let checksum = Sha256::digest(&snapshot);
+        snapshot.extend_from_slice(&checksum);
+        writer.write_all(&snapshot)?;
+        Ok(())
+    }
+
+    /// Rebuild the tree in a snapshot into `store`, which must be empty, at the
+    /// snapshot's depth. Nothing is committed unless the leaves hash to the
+    /// snapshot's root.
+    pub fn import_snapshot(
+        store: S,
+        mut reader: impl Read,
+    ) -> Result<Self, SnapshotError<S::Error>> {
+        let mut snapshot = Vec::new();
+        reader.read_to_end(&mut snapshot)?;
+        let (header, body) = split(&snapshot)?;
+        let SnapshotHeader {
+            compressed,
+            depth,
+            root,
+            leaf_count,
+        } = header;
+        let body = match compressed {
+            false => body.to_vec(),
+            true => decompress(body)?,
+        };
+        if leaf_count.checked_mul(32) != Some(body.len() as u64) {
+            return Err(format_error("leaf count"));
+        }
+
+        let mut leaves = BTreeMap::new();
+        let mut previous = None;
+        for pubkey_hash in body.chunks_exact(32) {
+            let pubkey_hash: [u8; 32] = pubkey_hash.try_into().expect("32 bytes");
+            if leaf_index(&pubkey_hash, TREE_DEPTH) != pubkey_hash {
+                return Err(format_error("not a pubkey hash"));
+            }
+            // Ascending and without repeats, so each tree has one snapshot
+            let index = leaf_index(&pubkey_hash, depth);
+            if previous.is_some_and(|previous| previous >= index) {
+                return Err(format_error("leaves out of order"));
+            }
+            previous = Some(index);
+            leaves.insert(index, Some(pubkey_hash));
+        }
+
+        let mut tree = Self::open_with_depth(store, depth).map_err(SnapshotError::Store)?;
+        if !tree.is_empty() {
+            return Err(SnapshotError::NotEmpty);
+        }
+        let batch = tree.stage(leaves).map_err(SnapshotError::Store)?;
+        if batch.checkpoint.root != root {
+            return Err(SnapshotError::RootMismatch {
+                expected: root,
+                actual: batch.checkpoint.root,
+            });
+        }
+        tree.commit(batch).map_err(SnapshotError::Store)?;
+        Ok(tree)
+    }
+}
@@ -0,0 +1,140 @@
+//! Where a tree keeps its leaves, cached nodes and root checkpoint
+//!
+//! A tree reads through a [`Store`] and hands it each batch of changes, with
+//! the root they lead to, to commit at once. A store that commits atomically
+//! always holds a checkpointed root and the nodes under it, so a tree reopened
+//! after a crash picks up at its last batch rather than rehashing its list.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+};
+
+use crate::{default_hash, pages::PagedMap, TREE_DEPTH};
+
+/// The root a store's contents hash to, as of its last commit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub root: [u8; 32],
+    pub leaf_count: u64,
+    /// Commits so far; 0 for an empty store
+    pub sequence: u64,
+}
+
+impl Checkpoint {
+    /// The checkpoint of an empty tree of `depth` levels
+    pub fn empty(depth: usize) -> Self {
+        Self {
+            root: default_hash(depth),
+            leaf_count: 0,
+            sequence: 0,
+        }
+    }
+}
+
+impl Default for Checkpoint {
+    /// The empty tree's, at the default depth
+    fn default() -> Self {
+        Self::empty(TREE_DEPTH)
+    }
+}
+
+/// One batch's changes, to commit together
+#[derive(Clone, Debug, Default)]
+pub struct Batch {
+    /// Leaves set to the pubkey hash they hold (`Some`) or emptied (`None`),
+    /// by index
+    pub leaves: BTreeMap<[u8; 32], Option<[u8; 32]>>,
+    /// Cached node hashes set (`Some`) or dropped (`None`), by (height, first
+    /// index)
+    pub nodes: HashMap<(usize, [u8; 32]), Option<[u8; 32]>>,
+    pub checkpoint: Checkpoint,
+}
+
+/// Storage behind a [`SparseMerkleTree`](crate::SparseMerkleTree)
+///
+/// Shared between threads: a batch's nodes are hashed in parallel, each
+/// thread reading the store.
+pub trait Store: Sync {
+    type Error: Send;
+
+    /// The pubkey hash the leaf at `index` holds; `None` if it is empty
+    fn leaf(&self, index: &[u8; 32]) -> Result<Option<[u8; 32]>, Self::Error>;
+
+    fn contains(&self, index: &[u8; 32]) -> Result<bool, Self::Error> {
+        Ok(self.leaf(index)?.is_some())
+    }
+
+    /// The leaves in `first..=last`, each index with the pubkey hash it holds,
+    /// in path order
+    fn leaves(
+        &self,
+        first: &[u8; 32],
+        last: &[u8; 32],
+    ) -> impl Iterator<Item = Result<([u8; 32], [u8; 32]), Self::Error>> + '_;
+
+    /// The cached hash of the node of `height` levels whose first index is
+    /// `first`
+    fn node(&self, height: usize, first: &[u8; 32]) -> Result<Option<[u8; 32]>, Self::Error>;
+
+    /// The last commit's checkpoint; `None` for a new store
+    fn checkpoint(&self) -> Result<Option<Checkpoint>, Self::Error>;
+
+    /// Write `batch` and its checkpoint. Implementations should write all of it
+    /// or, failing, none of it.
+    fn commit(&mut self, batch: &Batch) -> Result<(), Self::Error>;
+}
+
+/// A store in memory, gone with the process
+///
+/// Its leaves and nodes live in shared pages, copied on write: a clone costs
+/// next to nothing and keeps reading the tree as it was when cloned, while
+/// the original commits on.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    /// Pubkey hashes by leaf index; big-endian order is path order
+    leaves: PagedMap<[u8; 32], [u8; 32]>,
+    nodes: PagedMap<(usize, [u8; 32]), [u8; 32]>,
+    checkpoint: Option<Checkpoint>,
+}
+
+impl Store for MemoryStore {
+    type Error = Infallible;
+
+    fn leaf(&self, index: &[u8; 32]) -> Result<Option<[u8; 32]>, Infallible> {
+        Ok(self.leaves.get(index).copied())
+    }
+
+    fn leaves(
+        &self,
+        first: &[u8; 32],
+        last: &[u8; 32],
+    ) -> impl Iterator<Item = Result<([u8; 32], [u8; 32]), Infallible>> + '_ {
+        self.leaves.range(*first, *last).copied().map(Ok)
+    }
+
+    fn node(&self, height: usize, first: &[u8; 32]) -> Result<Option<[u8; 32]>, Infallible> {
+        Ok(self.nodes.get(&(height, *first)).copied())
+    }
+
+    fn checkpoint(&self) -> Result<Option<Checkpoint>, Infallible> {
+        Ok(self.checkpoint)
+    }
+
+    fn commit(&mut self, batch: &Batch) -> Result<(), Infallible> {
+        for (index, pubkey_hash) in &batch.leaves {
+            match pubkey_hash {
+                Some(pubkey_hash) => self.leaves.insert(*index, *pubkey_hash),
+                None => self.leaves.remove(index),
+            };
+        }
+        for (key, hash) in &batch.nodes {
+            match hash {
+                Some(hash) => self.nodes.insert(*key, *hash),
+                None => self.nodes.remove(key),
+            };
+        }
+        self.checkpoint = Some(batch.checkpoint);
+        Ok(())
+    }
+}
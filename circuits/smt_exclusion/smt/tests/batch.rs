@@ -0,0 +1,77 @@
+//! Checks batched updates leave the same tree as one update at a time.
+
+use smt::{Op, SparseMerkleTree};
+
+fn keys(range: std::ops::Range<u8>) -> Vec<[u8; 32]> {
+    range
+        .map(|i| {
+            let mut key = [i; 32];
+            key[0] = i.wrapping_mul(37);
+            key
+        })
+        .collect()
+}
+
+fn sequential(keys: &[[u8; 32]]) -> SparseMerkleTree {
+    let mut tree = SparseMerkleTree::new();
+    for key in keys {
+        tree.insert(key).unwrap();
+    }
+    tree
+}
+
+#[test]
+fn batch_matches_sequential() {
+    let inserted = keys(0..40);
+    let mut tree = SparseMerkleTree::new();
+    let ops: Vec<Op> = inserted.iter().copied().map(Op::Insert).collect();
+    assert_eq!(tree.apply_batch(&ops).unwrap(), 40);
+    assert_eq!(tree.root(), sequential(&inserted).root());
+
+    // Deletes, new inserts, no-ops and changes that cancel out
+    let mut ops: Vec<Op> = inserted[..10].iter().copied().map(Op::Delete).collect();
+    ops.extend(keys(40..50).into_iter().map(Op::Insert));
+    ops.push(Op::Insert(inserted[20]));
+    ops.push(Op::Delete(keys(60..61)[0]));
+    ops.push(Op::Insert(keys(61..62)[0]));
+    ops.push(Op::Delete(keys(61..62)[0]));
+    assert_eq!(tree.apply_batch(&ops).unwrap(), 20);
+
+    let remaining: Vec<[u8; 32]> = keys(10..50);
+    let expected = sequential(&remaining);
+    assert_eq!(tree.root(), expected.root());
+    assert_eq!(tree.len(), expected.len());
+
+    // Cached nodes are right too
+    for key in keys(0..10).iter().chain(&keys(60..64)) {
+        let proof = tree.exclusion_proof(key).unwrap().unwrap();
+        assert_eq!(proof, expected.exclusion_proof(key).unwrap().unwrap());
+        assert_eq!(proof.root(), tree.root());
+    }
+
+    let ops: Vec<Op> = remaining.iter().copied().map(Op::Delete).collect();
+    assert_eq!(tree.apply_batch(&ops).unwrap(), 40);
+    assert_eq!(tree.root(), SparseMerkleTree::new().root());
+    assert_eq!(tree.apply_batch(&[]).unwrap(), 0);
+}
+
+#[test]
+fn thread_count_does_not_change_root() {
+    let ops: Vec<Op> = keys(0..64).into_iter().map(Op::Insert).collect();
+    let roots: Vec<[u8; 32]> = [1, 4]
+        .into_iter()
+        .map(|threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                let mut tree = SparseMerkleTree::new();
+                tree.apply_batch(&ops).unwrap();
+                tree.root()
+            })
+        })
+        .collect();
+    assert_eq!(roots[0], roots[1]);
+    assert_eq!(roots[0], sequential(&keys(0..64)).root());
+}
@@ -0,0 +1,154 @@
+//! Checks the tree against the program's root computation and the circuit's
+//! sample inputs.
+
+use exclusion_program_example::smt::{compute_root, compute_update_roots, DEFAULT_HASHES};
+use smt::{default_hash, pubkey_to_index, SparseMerkleTree, EMPTY_LEAF, INSERTED_LEAF, TREE_DEPTH};
+use solana_pubkey::Pubkey;
+
+/// The key `smt.ts`'s demo blacklists when writing `Prover.toml`
+const PROVER_TOML_BLACKLISTED: &str = "FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH";
+
+/// Bitmap and siblings for the program, with every sibling passed explicitly
+fn dense(siblings: &[[u8; 32]]) -> ([u8; 32], Vec<u8>) {
+    let mut bitmap = [0u8; 32];
+    for level in 0..siblings.len() {
+        bitmap[level / 8] |= 1 << (level % 8);
+    }
+    (bitmap, siblings.concat())
+}
+
+fn field(hex: &str) -> [u8; 32] {
+    let hex = hex.trim_start_matches("0x");
+    let mut field = [0u8; 32];
+    for (i, byte) in field.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+    }
+    field
+}
+
+fn keys(count: u8) -> Vec<[u8; 32]> {
+    (1..=count).map(|i| [i; 32]).collect()
+}
+
+#[test]
+fn default_hashes_match_program() {
+    for (level, hash) in DEFAULT_HASHES.iter().enumerate() {
+        assert_eq!(default_hash(level), *hash);
+    }
+    let tree = SparseMerkleTree::new();
+    let proof = tree.exclusion_proof(&[9; 32]).unwrap().unwrap();
+    assert_eq!(proof.siblings[..], DEFAULT_HASHES[..TREE_DEPTH]);
+    let (bitmap, siblings) = dense(&proof.siblings);
+    let root = compute_root(
+        &proof.pubkey_hash,
+        &EMPTY_LEAF,
+        TREE_DEPTH,
+        &bitmap,
+        &siblings,
+    )
+    .unwrap();
+    assert_eq!(root, tree.root());
+    assert_eq!(root, default_hash(TREE_DEPTH));
+}
+
+#[test]
+fn matches_prover_toml() {
+    let toml = include_str!("../../Prover.toml");
+    let value = |key: &str| {
+        toml.lines()
+            .find(|line| line.starts_with(&format!("{key} =")))
+            .unwrap()
+            .split('"')
+            .skip(1)
+            .step_by(2)
+            .collect::<Vec<_>>()
+    };
+    let pubkey: Vec<u8> = value("pubkey")
+        .iter()
+        .map(|byte| byte.parse().unwrap())
+        .collect();
+    let pubkey: [u8; 32] = pubkey.try_into().unwrap();
+    let siblings: Vec<[u8; 32]> = value("siblings").into_iter().map(field).collect();
+
+    let mut tree = SparseMerkleTree::new();
+    let blacklisted: Pubkey = PROVER_TOML_BLACKLISTED.parse().unwrap();
+    tree.insert(&blacklisted.to_bytes()).unwrap();
+    assert_eq!(tree.root(), field(value("smt_root")[0]));
+
+    let proof = tree.exclusion_proof(&pubkey).unwrap().unwrap();
+    assert_eq!(proof.pubkey_hash, field(value("pubkey_hash")[0]));
+    assert_eq!(proof.siblings, siblings);
+    assert_eq!(proof.root(), tree.root());
+}
+
+#[test]
+fn updates_match_program() {
+    let mut tree = SparseMerkleTree::new();
+    for key in keys(6) {
+        let before = tree.root();
+        let (bitmap, siblings) = dense(&tree.exclusion_proof(&key).unwrap().unwrap().siblings);
+        let (old_root, new_root) = compute_update_roots(
+            &pubkey_to_index(&key),
+            &EMPTY_LEAF,
+            &INSERTED_LEAF,
+            TREE_DEPTH,
+            &bitmap,
+            &siblings,
+        )
+        .unwrap();
+        assert!(tree.insert(&key).unwrap());
+        assert_eq!(old_root, before);
+        assert_eq!(new_root, tree.root());
+    }
+    assert!(!tree.insert(&[1; 32]).unwrap());
+    assert!(tree.exclusion_proof(&[1; 32]).unwrap().is_none());
+
+    for key in keys(20).into_iter().skip(6) {
+        let proof = tree.exclusion_proof(&key).unwrap().unwrap();
+        let (bitmap, siblings) = dense(&proof.siblings);
+        let root = compute_root(
+            &proof.pubkey_hash,
+            &EMPTY_LEAF,
+            TREE_DEPTH,
+            &bitmap,
+            &siblings,
+        )
+        .unwrap();
+        assert_eq!(root, tree.root());
+        assert_eq!(proof.root(), tree.root());
+    }
+}
+
+#[test]
+fn delete_restores_roots() {
+    let mut tree = SparseMerkleTree::new();
+    let mut roots = vec![tree.root()];
+    for key in keys(5) {
+        tree.insert(&key).unwrap();
+        roots.push(tree.root());
+    }
+    for key in keys(5).iter().rev() {
+        roots.pop();
+        assert!(tree.delete(key).unwrap());
+        assert_eq!(tree.root(), *roots.last().unwrap());
+    }
+    assert!(tree.is_empty());
+    assert!(!tree.delete(&[1; 32]).unwrap());
+
+    // Insertion order doesn't matter
+    let mut forward = SparseMerkleTree::new();
+    let mut backward = SparseMerkleTree::new();
+    for key in keys(8) {
+        forward.insert(&key).unwrap();
+    }
+    for key in keys(8).iter().rev() {
+        backward.insert(key).unwrap();
+    }
+    assert_eq!(forward.root(), backward.root());
+    forward.delete(&[3; 32]).unwrap();
+    let mut rebuilt = SparseMerkleTree::new();
+    for key in keys(8).iter().filter(|key| key[0] != 3) {
+        rebuilt.insert(key).unwrap();
+    }
+    assert_eq!(forward.root(), rebuilt.root());
+}
@@ -0,0 +1,175 @@
+//! Checks trees of every supported depth against the program's root
+//! computation, and how shallow trees tell pubkeys sharing a leaf apart.
+
+use std::collections::HashMap;
+
+use exclusion_program_example::smt::{self as program, compute_root, compute_update_roots};
+use smt::{
+    default_hash, leaf_index, leaf_value, pubkey_to_index, snapshot::read_header, MemoryStore, Op,
+    SparseMerkleTree, TreeError, EMPTY_LEAF, INSERTED_LEAF, SUPPORTED_DEPTHS,
+};
+
+/// Bitmap and siblings for the program, with every sibling passed explicitly
+fn dense(siblings: &[[u8; 32]]) -> ([u8; 32], Vec<u8>) {
+    let mut bitmap = [0u8; 32];
+    for level in 0..siblings.len() {
+        bitmap[level / 8] |= 1 << (level % 8);
+    }
+    (bitmap, siblings.concat())
+}
+
+#[test]
+fn every_depth_matches_program() {
+    for depth in SUPPORTED_DEPTHS {
+        let mut tree = SparseMerkleTree::with_depth(depth);
+        assert_eq!(tree.root(), default_hash(depth));
+        for i in 1..=4 {
+            let key = [i; 32];
+            let proof = tree.exclusion_proof(&key).unwrap().unwrap();
+            assert_eq!(proof.siblings.len(), depth);
+            let (bitmap, siblings) = dense(&proof.siblings);
+            let (old_root, new_root) = compute_update_roots(
+                &proof.pubkey_hash,
+                &EMPTY_LEAF,
+                &leaf_value(&proof.pubkey_hash, depth),
+                depth,
+                &bitmap,
+                &siblings,
+            )
+            .unwrap();
+            assert_eq!(old_root, tree.root());
+            assert_eq!(
+                program::leaf_value(&proof.pubkey_hash, depth),
+                leaf_value(&proof.pubkey_hash, depth)
+            );
+            assert!(tree.insert(&key).unwrap());
+            assert_eq!(new_root, tree.root());
+        }
+
+        let proof = tree.exclusion_proof(&[9; 32]).unwrap().unwrap();
+        assert_eq!(proof.root(), tree.root());
+        let (bitmap, siblings) = dense(&proof.siblings);
+        let root = compute_root(&proof.pubkey_hash, &EMPTY_LEAF, depth, &bitmap, &siblings);
+        assert_eq!(root.unwrap(), tree.root());
+
+        let inputs = tree.generate_exclusion_proof(&[9; 32]).unwrap().unwrap();
+        assert_eq!(inputs.path_bits.len(), depth);
+        assert_eq!(inputs.pubkey_hash, pubkey_to_index(&[9; 32]));
+    }
+}
+
+#[test]
+fn program_rejects_levels_beyond_depth() {
+    let tree = SparseMerkleTree::with_depth(20);
+    let proof = tree.exclusion_proof(&[9; 32]).unwrap().unwrap();
+    let (mut bitmap, mut siblings) = dense(&proof.siblings);
+    bitmap[2] |= 1 << 4; // level 20
+    siblings.extend_from_slice(&[0; 32]);
+    assert!(compute_root(&proof.pubkey_hash, &EMPTY_LEAF, 20, &bitmap, &siblings).is_err());
+}
+
+/// Two pubkeys whose hashes agree in their low 20 bits
+fn sharing_a_leaf() -> ([u8; 32], [u8; 32]) {
+    let mut seen = HashMap::new();
+    (0u32..)
+        .map(|i| {
+            let mut pubkey = [0u8; 32];
+            pubkey[..4].copy_from_slice(&i.to_le_bytes());
+            pubkey
+        })
+        .find_map(|pubkey| {
+            let index = leaf_index(&pubkey_to_index(&pubkey), 20);
+            seen.insert(index, pubkey).map(|first| (first, pubkey))
+        })
+        .unwrap()
+}
+
+#[test]
+fn keys_sharing_a_shallow_leaf_prove_exclusion() {
+    let (first, second) = sharing_a_leaf();
+    let mut shallow = SparseMerkleTree::with_depth(20);
+    shallow.insert(&first).unwrap();
+    assert!(shallow.contains(&first).unwrap());
+    assert!(!shallow.contains(&second).unwrap());
+    assert!(shallow.exclusion_proof(&first).unwrap().is_none());
+
+    // The leaf holds the first pubkey's hash, which the circuit tells from
+    // the second's
+    let inputs = shallow.generate_exclusion_proof(&second).unwrap().unwrap();
+    assert_eq!(inputs.leaf_value, pubkey_to_index(&first));
+    assert_ne!(inputs.leaf_value, inputs.pubkey_hash);
+    let proof = shallow.exclusion_proof(&second).unwrap().unwrap();
+    assert_eq!(proof.root(), shallow.root());
+    let (bitmap, siblings) = dense(&proof.siblings);
+    let root = compute_root(
+        &proof.pubkey_hash,
+        &proof.leaf_value,
+        20,
+        &bitmap,
+        &siblings,
+    );
+    assert_eq!(root.unwrap(), shallow.root());
+
+    // Deleting the second pubkey leaves the first blacklisted
+    assert!(!shallow.delete(&second).unwrap());
+    assert!(shallow.contains(&first).unwrap());
+
+    // A full-depth tree's leaves hold 1
+    let mut full = SparseMerkleTree::new();
+    full.insert(&first).unwrap();
+    let inputs = full.generate_exclusion_proof(&second).unwrap().unwrap();
+    assert_eq!(inputs.leaf_value, EMPTY_LEAF);
+    assert_eq!(leaf_value(&inputs.pubkey_hash, 254), INSERTED_LEAF);
+}
+
+#[test]
+fn rejects_a_second_pubkey_in_a_shallow_leaf() {
+    let (first, second) = sharing_a_leaf();
+    let mut shallow = SparseMerkleTree::with_depth(20);
+    shallow.insert(&first).unwrap();
+    let root = shallow.root();
+
+    let error = shallow
+        .apply_batch(&[Op::Insert([7; 32]), Op::Insert(second)])
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        TreeError::Collision { pubkey_hash, holder }
+            if pubkey_hash == pubkey_to_index(&second) && holder == pubkey_to_index(&first)
+    ));
+    assert!(error.to_string().contains("deeper tree"), "{error}");
+    // Nothing in the batch was applied
+    assert_eq!(shallow.root(), root);
+    assert!(!shallow.contains(&[7; 32]).unwrap());
+
+    // The leaf can pass from one to the other within a batch
+    let changed = shallow
+        .apply_batch(&[Op::Delete(first), Op::Insert(second)])
+        .unwrap();
+    assert_eq!(changed, 1);
+    assert_eq!(shallow.len(), 1);
+    assert!(shallow.contains(&second).unwrap());
+    let mut direct = SparseMerkleTree::with_depth(20);
+    direct.insert(&second).unwrap();
+    assert_eq!(shallow.root(), direct.root());
+}
+
+#[test]
+fn snapshots_keep_their_depth() {
+    let mut tree = SparseMerkleTree::with_depth(32);
+    for i in 1..10 {
+        tree.insert(&[i; 32]).unwrap();
+    }
+    let mut snapshot = Vec::new();
+    tree.export_snapshot(&mut snapshot, false).unwrap();
+    assert_eq!(read_header(&snapshot).unwrap().depth, 32);
+
+    let imported = SparseMerkleTree::import_snapshot(MemoryStore::default(), &snapshot[..]);
+    let imported = imported.unwrap();
+    assert_eq!(imported.depth(), 32);
+    assert_eq!(imported.root(), tree.root());
+    assert_eq!(
+        imported.exclusion_proof(&[20; 32]).unwrap(),
+        tree.exclusion_proof(&[20; 32]).unwrap()
+    );
+}
@@ -0,0 +1,111 @@
+//! Checks diffs between trees against the lists they were built from.
+
+use std::collections::{BTreeSet, HashMap};
+
+use smt::{leaf_index, pubkey_to_index, LeafOp, SparseMerkleTree, TREE_DEPTH};
+
+fn tree(depth: usize, pubkeys: impl IntoIterator<Item = u32>) -> SparseMerkleTree {
+    let mut tree = SparseMerkleTree::with_depth(depth);
+    let ops: Vec<_> = pubkeys
+        .into_iter()
+        .map(|i| smt::Op::Insert(pubkey(i)))
+        .collect();
+    tree.apply_batch(&ops).unwrap();
+    tree
+}
+
+fn pubkey(i: u32) -> [u8; 32] {
+    let mut pubkey = [0u8; 32];
+    pubkey[..4].copy_from_slice(&i.to_le_bytes());
+    pubkey
+}
+
+fn pubkey_hash(i: u32) -> [u8; 32] {
+    pubkey_to_index(&pubkey(i))
+}
+
+#[test]
+fn diff_is_the_change_in_the_list() {
+    let yesterday = tree(TREE_DEPTH, 0..200);
+    let today = tree(TREE_DEPTH, (5..200).chain(300..310));
+    let ops = yesterday.diff(&today).unwrap();
+
+    // Every hash is its own leaf index at this depth
+    let inserted: BTreeSet<_> = (300..310).map(pubkey_hash).collect();
+    let deleted: BTreeSet<_> = (0..5).map(pubkey_hash).collect();
+    let mut expected: Vec<LeafOp> = inserted.iter().map(|i| LeafOp::Insert(*i)).collect();
+    expected.extend(deleted.iter().map(|i| LeafOp::Delete(*i)));
+    expected.sort_by_key(|op| *op.pubkey_hash());
+    assert_eq!(ops, expected);
+
+    let mut updated = yesterday.clone();
+    assert_eq!(updated.apply_leaf_ops(&ops).unwrap(), 15);
+    assert_eq!(updated.root(), today.root());
+    assert_eq!(updated.len(), today.len());
+}
+
+#[test]
+fn identical_trees_have_no_diff() {
+    let tree = tree(TREE_DEPTH, 0..50);
+    let copy = self::tree(TREE_DEPTH, (0..50).rev());
+    assert!(tree.diff(&copy).unwrap().is_empty());
+    assert!(SparseMerkleTree::new()
+        .diff(&SparseMerkleTree::new())
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn diff_reverses() {
+    for depth in [20, 256] {
+        let empty = SparseMerkleTree::with_depth(depth);
+        let full = tree(depth, 0..30);
+        let ops = empty.diff(&full).unwrap();
+        assert_eq!(ops.len(), 30);
+        assert!(ops.iter().all(|op| matches!(op, LeafOp::Insert(_))));
+
+        let mut emptied = full.clone();
+        emptied.apply_leaf_ops(&full.diff(&empty).unwrap()).unwrap();
+        assert_eq!(emptied.root(), empty.root());
+        assert!(emptied.is_empty());
+    }
+}
+
+#[test]
+fn shallow_leaves_pass_between_pubkeys() {
+    // Two pubkeys sharing a leaf of a 20-level tree
+    let (first, second) = collision();
+    let ours = tree(20, [first]);
+    let theirs = tree(20, [second]);
+    let ops = ours.diff(&theirs).unwrap();
+    assert_eq!(
+        ops,
+        [
+            LeafOp::Delete(pubkey_hash(first)),
+            LeafOp::Insert(pubkey_hash(second))
+        ]
+    );
+
+    let mut updated = ours.clone();
+    assert_eq!(updated.apply_leaf_ops(&ops).unwrap(), 1);
+    assert_eq!(updated.root(), theirs.root());
+    assert_eq!(updated.len(), 1);
+}
+
+/// Two of `pubkey`'s arguments whose hashes share their low 20 bits
+fn collision() -> (u32, u32) {
+    let mut seen = HashMap::new();
+    (0u32..)
+        .find_map(|i| {
+            let index = leaf_index(&pubkey_hash(i), 20);
+            seen.insert(index, i).map(|first| (first, i))
+        })
+        .unwrap()
+}
+
+#[test]
+#[should_panic(expected = "not a pubkey hash")]
+fn leaf_ops_take_pubkey_hashes() {
+    let mut tree = SparseMerkleTree::with_depth(20);
+    let _ = tree.apply_leaf_ops(&[LeafOp::Insert([0xff; 32])]);
+}
@@ -0,0 +1,130 @@
+//! Checks the indexed tree's list stays sorted and its exclusion proofs verify
+//! the way `circuits/imt_exclusion` checks them.
+
+use smt::{
+    indexed::{poseidon_hash_3, IndexedMerkleTree, INDEXED_TREE_DEPTH},
+    pubkey_to_index,
+};
+use toml::Table;
+
+fn keys(range: std::ops::Range<u8>) -> Vec<[u8; 32]> {
+    range
+        .map(|i| {
+            let mut key = [i; 32];
+            key[0] = i.wrapping_mul(37);
+            key
+        })
+        .collect()
+}
+
+/// Keys in list order, following next_index from the head
+fn walk(tree: &IndexedMerkleTree) -> Vec<[u8; 32]> {
+    let mut keys = Vec::new();
+    let mut leaf = tree.leaf(0).unwrap();
+    while leaf.next_value != [0; 32] {
+        let next = tree.leaf(leaf.next_index).unwrap();
+        assert_eq!(next.value, leaf.next_value);
+        keys.push(next.value);
+        leaf = next;
+    }
+    keys
+}
+
+#[test]
+fn poseidon_3_matches_circomlib() {
+    let field = |n: u8| {
+        let mut field = [0u8; 32];
+        field[31] = n;
+        field
+    };
+    // circomlibjs poseidon([1, 2, 3]), as the circuit's test checks
+    assert_eq!(
+        smt::inputs::field_hex(&poseidon_hash_3(&field(1), &field(2), &field(3))),
+        "0x0e7732d89e6939c0ff03d5e58dab6302f3230e269dc5b968f725df34ab36d732"
+    );
+}
+
+#[test]
+fn proofs_verify_and_list_stays_sorted() {
+    let inserted = keys(0..24);
+    let mut tree = IndexedMerkleTree::new();
+    let empty_root = tree.root();
+    let outsider = [200u8; 32];
+    assert!(tree.exclusion_proof(&outsider).unwrap().verify(&empty_root));
+
+    for key in &inserted {
+        assert!(tree.insert(key));
+    }
+    assert!(!tree.insert(&inserted[3]));
+    assert_eq!(tree.len(), 24);
+    let mut sorted: Vec<[u8; 32]> = inserted.iter().map(pubkey_to_index).collect();
+    sorted.sort();
+    assert_eq!(walk(&tree), sorted);
+
+    for key in &inserted {
+        assert!(tree.contains(key));
+        assert!(tree.exclusion_proof(key).is_none());
+    }
+    for key in keys(100..120) {
+        let proof = tree.exclusion_proof(&key).unwrap();
+        assert_eq!(proof.siblings.len(), INDEXED_TREE_DEPTH);
+        assert!(proof.verify(&tree.root()));
+        assert!(!proof.verify(&empty_root));
+    }
+
+    // A low leaf doesn't cover the keys it links to
+    let proof = tree.exclusion_proof(&outsider).unwrap();
+    let mut forged = proof.clone();
+    forged.pubkey_hash = proof.low_leaf.next_value;
+    assert!(!forged.verify(&tree.root()));
+}
+
+#[test]
+fn delete_unlinks_and_restores_root() {
+    let mut tree = IndexedMerkleTree::new();
+    for key in keys(0..8) {
+        tree.insert(&key);
+    }
+    let root = tree.root();
+    let late = keys(50..51)[0];
+    tree.insert(&late);
+    assert_ne!(tree.root(), root);
+    assert!(tree.delete(&late));
+    assert!(!tree.delete(&late));
+    assert_eq!(tree.root(), root);
+
+    let removed = keys(3..4)[0];
+    assert!(tree.delete(&removed));
+    assert_eq!(tree.len(), 7);
+    assert!(!walk(&tree).contains(&pubkey_to_index(&removed)));
+    assert!(tree.exclusion_proof(&removed).unwrap().verify(&tree.root()));
+}
+
+#[test]
+fn prover_toml_has_circuit_inputs() {
+    let mut tree = IndexedMerkleTree::new();
+    for key in keys(0..4) {
+        tree.insert(&key);
+    }
+    let customer = [9u8; 32];
+    let inputs = tree.generate_exclusion_proof(&customer).unwrap();
+    let toml: Table = inputs.to_prover_toml(42).parse().unwrap();
+    assert_eq!(
+        toml["smt_root"].as_str().unwrap(),
+        smt::inputs::field_hex(&tree.root())
+    );
+    assert_eq!(
+        toml["low_leaf_index"].as_str().unwrap(),
+        inputs.proof.low_leaf_index.to_string()
+    );
+    assert_eq!(toml["recent_slot"].as_str().unwrap(), "42");
+    assert_eq!(toml["pubkey"].as_array().unwrap().len(), 32);
+    assert_eq!(
+        toml["siblings"].as_array().unwrap().len(),
+        INDEXED_TREE_DEPTH
+    );
+
+    let witness = inputs.public_witness(42);
+    assert_eq!(witness[12..44], tree.root());
+    assert_eq!(witness[44..76], pubkey_to_index(&customer));
+}
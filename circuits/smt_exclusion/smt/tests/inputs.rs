@@ -0,0 +1,84 @@
+//! Checks generated circuit inputs and `Prover.toml` against the circuit's
+//! sample inputs.
+
+use exclusion_program_example::instruction::{WitnessHeader, NUM_PUBLIC_INPUTS, WITNESS_SIZE};
+use smt::{path_bit, SparseMerkleTree, EMPTY_LEAF, TREE_DEPTH};
+use solana_pubkey::Pubkey;
+use toml::{Table, Value};
+
+/// The key `smt.ts`'s demo blacklists when writing `Prover.toml`
+const PROVER_TOML_BLACKLISTED: &str = "FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH";
+
+/// A `Prover.toml` value as minimal hex: the sample writes decimal strings,
+/// the generator hex fields and integer bytes
+fn number(value: &Value) -> String {
+    let hex = match value {
+        Value::String(string) => match string.strip_prefix("0x") {
+            Some(hex) => hex.to_string(),
+            None => format!("{:x}", string.parse::<u64>().unwrap()),
+        },
+        Value::Integer(integer) => format!("{integer:x}"),
+        _ => panic!("unexpected value {value}"),
+    };
+    match hex.trim_start_matches('0') {
+        "" => "0".into(),
+        hex => hex.into(),
+    }
+}
+
+fn numbers(table: &Table, key: &str) -> Vec<String> {
+    match &table[key] {
+        Value::Array(array) => array.iter().map(number).collect(),
+        value => vec![number(value)],
+    }
+}
+
+#[test]
+fn prover_toml_matches_sample() {
+    let sample: Table = include_str!("../../Prover.toml").parse().unwrap();
+    let pubkey: Vec<u8> = numbers(&sample, "pubkey")
+        .iter()
+        .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+        .collect();
+    let pubkey: [u8; 32] = pubkey.try_into().unwrap();
+
+    let mut tree = SparseMerkleTree::new();
+    let blacklisted: Pubkey = PROVER_TOML_BLACKLISTED.parse().unwrap();
+    tree.insert(&blacklisted.to_bytes()).unwrap();
+    assert!(tree
+        .generate_exclusion_proof(&blacklisted.to_bytes())
+        .unwrap()
+        .is_none());
+
+    let inputs = tree.generate_exclusion_proof(&pubkey).unwrap().unwrap();
+    assert_eq!(inputs.smt_root, tree.root());
+    assert_eq!(inputs.leaf_value, EMPTY_LEAF);
+    assert_eq!(inputs.path_bits.len(), TREE_DEPTH);
+    for (level, bit) in inputs.path_bits.iter().enumerate() {
+        assert_eq!(*bit, path_bit(&inputs.pubkey_hash, level));
+    }
+
+    let generated: Table = inputs.to_prover_toml(1).parse().unwrap();
+    assert_eq!(generated.len(), sample.len());
+    for key in sample.keys() {
+        assert_eq!(numbers(&generated, key), numbers(&sample, key), "{key}");
+    }
+}
+
+#[test]
+fn public_witness_matches_program_layout() {
+    let mut tree = SparseMerkleTree::new();
+    tree.insert(&[1; 32]).unwrap();
+    let inputs = tree.generate_exclusion_proof(&[2; 32]).unwrap().unwrap();
+    let witness = inputs.public_witness(0x0102_0304);
+    assert_eq!(witness.len(), WITNESS_SIZE);
+
+    let header = WitnessHeader::unpack(&witness).unwrap();
+    assert_eq!(header.nb_public, NUM_PUBLIC_INPUTS);
+    assert_eq!(header.nb_secret, 0);
+    assert_eq!(header.witness_size(), WITNESS_SIZE);
+    assert_eq!(witness[12..44], tree.root());
+    assert_eq!(witness[44..76], inputs.pubkey_hash);
+    assert_eq!(witness[76..104], [0; 28]);
+    assert_eq!(witness[104..], [1, 2, 3, 4]);
+}
@@ -0,0 +1,126 @@
+//! Checks journals replay to the tree they recorded, and that edits to them
+//! are caught.
+
+use smt::{
+    default_hash,
+    journal::{replay, Journal, JournalError},
+    Op, SparseMerkleTree, TREE_DEPTH,
+};
+
+/// A journal of three batches, at times 100, 200 and 300, and the roots after
+/// each
+fn journaled() -> (Vec<u8>, SparseMerkleTree, Vec<[u8; 32]>) {
+    let batches = [
+        (100, vec![Op::Insert([1; 32]), Op::Insert([2; 32])]),
+        (200, vec![Op::Delete([1; 32])]),
+        (300, vec![Op::Insert([3; 32]), Op::Insert([1; 32])]),
+    ];
+    let mut tree = SparseMerkleTree::new();
+    let mut journal = Journal::create(Vec::new(), TREE_DEPTH).unwrap();
+    let mut roots = Vec::new();
+    for (timestamp, ops) in batches {
+        tree.apply_journaled(&mut journal, timestamp, &ops).unwrap();
+        roots.push(tree.root());
+    }
+    assert_eq!(journal.root(), tree.root());
+    (journal.into_inner(), tree, roots)
+}
+
+#[test]
+fn replay_rebuilds_the_tree() {
+    let (bytes, tree, roots) = journaled();
+    let replay = replay(&bytes[..]).unwrap();
+    assert_eq!(replay.tree.root(), tree.root());
+    assert_eq!(replay.entries.len(), 3);
+    assert_eq!(
+        replay
+            .entries
+            .iter()
+            .map(|entry| entry.root)
+            .collect::<Vec<_>>(),
+        roots
+    );
+    assert_eq!(replay.entries[1].ops, [Op::Delete([1; 32])]);
+}
+
+#[test]
+fn replay_answers_when() {
+    let (bytes, _, roots) = journaled();
+    let replay = replay(&bytes[..]).unwrap();
+
+    assert!(!replay.blacklisted_at(&[1; 32], 99));
+    assert!(replay.blacklisted_at(&[1; 32], 100));
+    assert!(!replay.blacklisted_at(&[1; 32], 250));
+    assert!(replay.blacklisted_at(&[1; 32], 300));
+    assert!(!replay.blacklisted_at(&[3; 32], 299));
+
+    let history: Vec<u64> = replay
+        .history(&[1; 32])
+        .map(|entry| entry.timestamp)
+        .collect();
+    assert_eq!(history, [100, 200, 300]);
+
+    assert_eq!(replay.root_at(50), default_hash(TREE_DEPTH));
+    assert_eq!(replay.root_at(250), roots[1]);
+    let current: Vec<u64> = replay
+        .entries_with_root(&roots[1])
+        .map(|entry| entry.sequence)
+        .collect();
+    assert_eq!(current, [1]);
+}
+
+#[test]
+fn resumed_journal_continues_the_chain() {
+    let (mut bytes, mut tree, _) = journaled();
+    let resumed = replay(&bytes[..]).unwrap();
+    let mut journal = Journal::resume(Vec::new(), &resumed);
+    tree.apply_journaled(&mut journal, 400, &[Op::Delete([2; 32])])
+        .unwrap();
+    bytes.extend(journal.into_inner());
+
+    let replay = replay(&bytes[..]).unwrap();
+    assert_eq!(replay.entries.len(), 4);
+    assert_eq!(replay.tree.root(), tree.root());
+}
+
+#[test]
+fn journal_must_follow_the_tree() {
+    let mut tree = SparseMerkleTree::new();
+    tree.insert(&[9; 32]).unwrap();
+    let mut journal = Journal::create(Vec::new(), TREE_DEPTH).unwrap();
+    let applied = tree.apply_journaled(&mut journal, 1, &[Op::Insert([1; 32])]);
+    assert!(matches!(applied, Err(JournalError::Diverged)));
+    assert!(!tree.contains(&[1; 32]).unwrap());
+}
+
+#[test]
+fn edits_are_caught() {
+    let (bytes, _, _) = journaled();
+    // The first record starts after the 11-byte header: sequence, then time
+    let mut backdated = bytes.clone();
+    backdated[11 + 8] ^= 1;
+    assert!(matches!(
+        replay(&backdated[..]),
+        Err(JournalError::Chain { sequence: 0 })
+    ));
+
+    // A record dropped from the middle breaks the sequence
+    let first = 11 + 20 + 2 * 33 + 64;
+    let second = 20 + 33 + 64;
+    let mut dropped = bytes[..first].to_vec();
+    dropped.extend_from_slice(&bytes[first + second..]);
+    assert!(matches!(replay(&dropped[..]), Err(JournalError::Format(_))));
+
+    let truncated = &bytes[..bytes.len() - 1];
+    assert!(matches!(replay(truncated), Err(JournalError::Format(_))));
+}
+
+#[test]
+fn forged_roots_are_caught() {
+    let mut journal = Journal::create(Vec::new(), TREE_DEPTH).unwrap();
+    journal.append(1, &[Op::Insert([1; 32])], [7; 32]).unwrap();
+    assert!(matches!(
+        replay(&journal.into_inner()[..]),
+        Err(JournalError::RootMismatch { sequence: 0, .. })
+    ));
+}
@@ -0,0 +1,155 @@
+//! Checks the program's INSERT_LEAF / REMOVE_LEAF root computation against
+//! the tree, with sparse sibling bitmaps as clients send them, at the full
+//! depth and a shallow one.
+
+use exclusion_program_example::smt::{compute_root, compute_update_roots};
+use smt::{default_hash, leaf_value, pubkey_to_index, SparseMerkleTree, EMPTY_LEAF, TREE_DEPTH};
+
+const DEPTHS: [usize; 2] = [TREE_DEPTH, 20];
+
+/// Bitmap and siblings for the program, leaving out default siblings
+fn sparse(siblings: &[[u8; 32]]) -> ([u8; 32], Vec<u8>) {
+    let mut bitmap = [0u8; 32];
+    let mut provided = Vec::new();
+    for (level, sibling) in siblings.iter().enumerate() {
+        if *sibling != default_hash(level) {
+            bitmap[level / 8] |= 1 << (level % 8);
+            provided.extend_from_slice(sibling);
+        }
+    }
+    (bitmap, provided)
+}
+
+fn keys(count: u8) -> Vec<[u8; 32]> {
+    (1..=count).map(|i| [i; 32]).collect()
+}
+
+/// `key`'s leaf value once blacklisted in a tree of `depth` levels
+fn blacklisted(key: &[u8; 32], depth: usize) -> [u8; 32] {
+    leaf_value(&pubkey_to_index(key), depth)
+}
+
+/// The program's roots before and after setting `key`'s leaf from `old_leaf`
+/// to `new_leaf`, along `siblings`
+fn program_roots(
+    key: &[u8; 32],
+    old_leaf: &[u8; 32],
+    new_leaf: &[u8; 32],
+    depth: usize,
+    siblings: &[[u8; 32]],
+) -> ([u8; 32], [u8; 32]) {
+    let (bitmap, provided) = sparse(siblings);
+    compute_update_roots(
+        &pubkey_to_index(key),
+        old_leaf,
+        new_leaf,
+        depth,
+        &bitmap,
+        &provided,
+    )
+    .unwrap()
+}
+
+#[test]
+fn inserts_match_tree() {
+    for depth in DEPTHS {
+        let mut tree = SparseMerkleTree::with_depth(depth);
+        for key in keys(8) {
+            let before = tree.root();
+            let siblings = tree.exclusion_proof(&key).unwrap().unwrap().siblings;
+            let roots = program_roots(
+                &key,
+                &EMPTY_LEAF,
+                &blacklisted(&key, depth),
+                depth,
+                &siblings,
+            );
+            assert!(tree.insert(&key).unwrap());
+            assert_eq!(roots, (before, tree.root()), "depth {depth}");
+        }
+    }
+}
+
+#[test]
+fn removals_match_tree() {
+    for depth in DEPTHS {
+        let mut tree = SparseMerkleTree::with_depth(depth);
+        for key in keys(8) {
+            tree.insert(&key).unwrap();
+        }
+        // Out of insertion order, down to the empty tree
+        for key in [3, 8, 1, 5, 2, 7, 4, 6].map(|i| [i; 32]) {
+            let before = tree.root();
+            assert!(tree.delete(&key).unwrap());
+            // Changing a leaf leaves its path's siblings as they were
+            let siblings = tree.exclusion_proof(&key).unwrap().unwrap().siblings;
+            let roots = program_roots(
+                &key,
+                &blacklisted(&key, depth),
+                &EMPTY_LEAF,
+                depth,
+                &siblings,
+            );
+            assert_eq!(roots, (before, tree.root()), "depth {depth}");
+        }
+        assert_eq!(tree.root(), default_hash(depth));
+    }
+}
+
+#[test]
+fn updates_between_trees_match_tree() {
+    // Each leaf of a full tree, set and cleared: the roots of the trees with
+    // and without it
+    for depth in DEPTHS {
+        let mut tree = SparseMerkleTree::with_depth(depth);
+        for key in keys(6) {
+            tree.insert(&key).unwrap();
+        }
+        let full = tree.root();
+        for key in keys(6) {
+            tree.delete(&key).unwrap();
+            let proof = tree.exclusion_proof(&key).unwrap().unwrap();
+            let (bitmap, provided) = sparse(&proof.siblings);
+            let member = compute_root(
+                &proof.pubkey_hash,
+                &blacklisted(&key, depth),
+                depth,
+                &bitmap,
+                &provided,
+            );
+            assert_eq!(member.unwrap(), full, "depth {depth}");
+            let old_leaf = blacklisted(&key, depth);
+            let roots = program_roots(&key, &old_leaf, &EMPTY_LEAF, depth, &proof.siblings);
+            assert_eq!(roots, (full, tree.root()), "depth {depth}");
+            tree.insert(&key).unwrap();
+        }
+    }
+}
+
+#[test]
+fn misordered_siblings_give_another_root() {
+    for depth in DEPTHS {
+        let mut tree = SparseMerkleTree::with_depth(depth);
+        for key in keys(12) {
+            tree.insert(&key).unwrap();
+        }
+        let proof = tree.exclusion_proof(&[40; 32]).unwrap().unwrap();
+        let (bitmap, mut provided) = sparse(&proof.siblings);
+        assert!(provided.len() >= 64, "depth {depth}");
+        let root = compute_root(&proof.pubkey_hash, &EMPTY_LEAF, depth, &bitmap, &provided);
+        assert_eq!(root.unwrap(), tree.root());
+
+        // The two lowest provided siblings swapped
+        let (first, rest) = provided.split_at_mut(32);
+        first.swap_with_slice(&mut rest[..32]);
+        let root = compute_root(&proof.pubkey_hash, &EMPTY_LEAF, depth, &bitmap, &provided);
+        assert_ne!(root.unwrap(), tree.root(), "depth {depth}");
+
+        // A tree of another depth
+        let other = if depth == TREE_DEPTH { 20 } else { TREE_DEPTH };
+        let (bitmap, provided) = sparse(&proof.siblings);
+        if let Ok(root) = compute_root(&proof.pubkey_hash, &EMPTY_LEAF, other, &bitmap, &provided) {
+            assert_ne!(root, tree.root(), "depth {depth} as {other}");
+        }
+    }
+}
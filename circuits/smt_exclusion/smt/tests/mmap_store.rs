@@ -0,0 +1,142 @@
+//! Checks a tree in mapped files matches one in memory, before and after
+//! compacting, and reopens at the checkpoint compacted last.
+#![cfg(all(feature = "mmap", unix))]
+
+use smt::{mmap_store::MmapStore, Checkpoint, Op, SparseMerkleTree, Store};
+
+fn keys(range: std::ops::Range<u16>) -> Vec<[u8; 32]> {
+    range
+        .map(|i| {
+            let mut key = [0u8; 32];
+            key[..2].copy_from_slice(&i.to_le_bytes());
+            key
+        })
+        .collect()
+}
+
+fn open(dir: &std::path::Path) -> SparseMerkleTree<MmapStore> {
+    SparseMerkleTree::open(MmapStore::open(dir).unwrap()).unwrap()
+}
+
+fn generations(dir: &std::path::Path) -> usize {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .filter(|entry| {
+            let name = entry.as_ref().unwrap().file_name();
+            name.to_str().unwrap().starts_with("nodes-")
+        })
+        .count()
+}
+
+/// Whether `mapped` holds the leaves and proofs `memory` does
+fn check(mapped: &SparseMerkleTree<MmapStore>, memory: &SparseMerkleTree) {
+    let leaves: Vec<_> = mapped
+        .store()
+        .leaves(&[0; 32], &[0xff; 32])
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let expected: Vec<_> = memory
+        .store()
+        .leaves(&[0; 32], &[0xff; 32])
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(leaves, expected);
+    for key in keys(30..50).iter().chain(&keys(315..330)) {
+        assert_eq!(mapped.contains(key).unwrap(), memory.contains(key).unwrap());
+        assert_eq!(
+            mapped.exclusion_proof(key).unwrap(),
+            memory.exclusion_proof(key).unwrap()
+        );
+    }
+}
+
+#[test]
+fn matches_memory_across_compactions() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut memory = SparseMerkleTree::new();
+    let mut mapped = open(dir.path());
+    assert_eq!(mapped.checkpoint(), Checkpoint::default());
+
+    // Enough leaves and nodes to span several blocks of the index
+    let ops: Vec<Op> = keys(0..300).into_iter().map(Op::Insert).collect();
+    memory.apply_batch(&ops).unwrap();
+    mapped.apply_batch(&ops).unwrap();
+    assert_eq!(mapped.root(), memory.root());
+
+    // Overlay over the mapped files: some deleted, some added
+    let mut ops: Vec<Op> = keys(0..40).into_iter().map(Op::Delete).collect();
+    ops.extend(keys(300..320).into_iter().map(Op::Insert));
+    memory.apply_batch(&ops).unwrap();
+    mapped.apply_batch(&ops).unwrap();
+    assert_eq!(mapped.root(), memory.root());
+
+    check(&mapped, &memory);
+
+    // Compacting makes the overlay durable
+    let mut store = mapped.into_store();
+    store.compact().unwrap();
+    assert_eq!(generations(dir.path()), 1);
+    let mut store = MmapStore::open(dir.path()).unwrap();
+    store.set_compact_threshold(0);
+    let mut mapped = SparseMerkleTree::open(store).unwrap();
+    check(&mapped, &memory);
+
+    // Compacting on every commit, over the last generation
+    let ops: Vec<Op> = keys(40..60).into_iter().map(Op::Delete).collect();
+    memory.apply_batch(&ops).unwrap();
+    mapped.apply_batch(&ops).unwrap();
+    assert_eq!(generations(dir.path()), 1);
+    let checkpoint = mapped.checkpoint();
+    assert_eq!(checkpoint.root, memory.root());
+    drop(mapped);
+
+    let mapped = open(dir.path());
+    assert_eq!(mapped.checkpoint(), checkpoint);
+    assert_eq!(checkpoint.leaf_count, 260);
+    assert_eq!(checkpoint.sequence, 3);
+    check(&mapped, &memory);
+}
+
+#[test]
+fn reopens_at_last_compaction() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut mapped = open(dir.path());
+    let ops: Vec<Op> = keys(0..10).into_iter().map(Op::Insert).collect();
+    mapped.apply_batch(&ops).unwrap();
+    let compacted = mapped.checkpoint();
+    let mut store = mapped.into_store();
+    store.compact().unwrap();
+    let mut mapped = SparseMerkleTree::open(store).unwrap();
+
+    // Not compacted: lost on reopening
+    let ops: Vec<Op> = keys(10..20).into_iter().map(Op::Insert).collect();
+    mapped.apply_batch(&ops).unwrap();
+    drop(mapped);
+    let mapped = open(dir.path());
+    assert_eq!(mapped.checkpoint(), compacted);
+    assert!(mapped.contains(&keys(5..6)[0]).unwrap());
+    assert!(!mapped.contains(&keys(15..16)[0]).unwrap());
+}
+
+#[test]
+fn bulk_load_needs_an_empty_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut memory = SparseMerkleTree::new();
+    let ops: Vec<Op> = keys(0..20).into_iter().map(Op::Insert).collect();
+    memory.apply_batch(&ops).unwrap();
+    let leaves: Vec<_> = memory
+        .store()
+        .leaves(&[0; 32], &[0xff; 32])
+        .map(Result::unwrap)
+        .collect();
+
+    // Leaves without their nodes: the tree rebuilds none of them
+    let store = MmapStore::bulk_load(dir.path(), leaves.clone(), [], memory.checkpoint()).unwrap();
+    let mapped = SparseMerkleTree::open(store).unwrap();
+    assert_eq!(mapped.checkpoint(), memory.checkpoint());
+    assert!(mapped.contains(&keys(3..4)[0]).unwrap());
+    drop(mapped);
+
+    let error = MmapStore::bulk_load(dir.path(), leaves, [], memory.checkpoint()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+}
@@ -0,0 +1,145 @@
+//! Checks multiproofs against single exclusion proofs, and the batch
+//! circuit's inputs against its sample.
+
+use smt::{
+    multiproof::{batch_public_witness, BATCH_PUBLIC_WITNESS_SIZE, BATCH_SIZE},
+    pubkey_to_index, SparseMerkleTree,
+};
+use solana_pubkey::Pubkey;
+use toml::{Table, Value};
+
+/// The key the batch circuit's sample blacklists, as the single circuit's does
+const SAMPLE_BLACKLISTED: &str = "FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH";
+
+fn tree(depth: usize) -> SparseMerkleTree {
+    let mut tree = SparseMerkleTree::with_depth(depth);
+    for i in 1..=20 {
+        tree.insert(&[i; 32]).unwrap();
+    }
+    tree
+}
+
+#[test]
+fn multiproof_matches_single_proofs() {
+    for depth in [20, 254, 256] {
+        let tree = tree(depth);
+        let pubkeys: Vec<[u8; 32]> = (100..108).map(|i| [i; 32]).collect();
+        let multiproof = tree.exclusion_multiproof(&pubkeys).unwrap().unwrap();
+        assert_eq!(multiproof.root(), Some(tree.root()));
+        assert!(multiproof.siblings.len() < pubkeys.len() * depth);
+
+        let paths = multiproof.paths().unwrap();
+        for (pubkey, path) in pubkeys.iter().zip(paths) {
+            let proof = tree.exclusion_proof(pubkey).unwrap().unwrap();
+            assert_eq!(path, proof.siblings);
+        }
+        assert_eq!(
+            multiproof.pubkey_hashes,
+            pubkeys.iter().map(pubkey_to_index).collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn one_key_multiproof_is_its_proof() {
+    let tree = tree(254);
+    let multiproof = tree.exclusion_multiproof(&[[100; 32]]).unwrap().unwrap();
+    let proof = tree.exclusion_proof(&[100; 32]).unwrap().unwrap();
+    assert_eq!(multiproof.siblings, proof.siblings);
+}
+
+#[test]
+fn repeated_keys_share_their_path() {
+    let tree = tree(254);
+    let once = tree.exclusion_multiproof(&[[100; 32]]).unwrap().unwrap();
+    let twice = tree.exclusion_multiproof(&[[100; 32]; 3]).unwrap().unwrap();
+    assert_eq!(once.siblings, twice.siblings);
+    assert_eq!(twice.paths().unwrap().len(), 3);
+}
+
+#[test]
+fn blacklisted_key_has_no_multiproof() {
+    let tree = tree(254);
+    let pubkeys = [[100; 32], [5; 32], [101; 32]];
+    assert!(tree.exclusion_multiproof(&pubkeys).unwrap().is_none());
+    assert!(tree
+        .generate_exclusion_multiproof(&pubkeys)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn tampered_multiproof_fails() {
+    let tree = tree(254);
+    let pubkeys = [[100; 32], [101; 32]];
+    let mut multiproof = tree.exclusion_multiproof(&pubkeys).unwrap().unwrap();
+    multiproof.siblings[3][31] ^= 1;
+    assert_ne!(multiproof.root(), Some(tree.root()));
+
+    multiproof.siblings.pop();
+    assert_eq!(multiproof.root(), None);
+    multiproof.siblings.extend([[0; 32]; 2]);
+    assert_eq!(multiproof.paths(), None);
+}
+
+#[test]
+fn batch_inputs_fill_the_batch() {
+    let tree = tree(254);
+    let inputs = tree
+        .generate_exclusion_multiproof(&[[100; 32], [101; 32]])
+        .unwrap()
+        .unwrap();
+    assert_eq!(inputs.smt_root, tree.root());
+    assert_eq!(inputs.pubkeys, [[100; 32], [101; 32], [101; 32], [101; 32]]);
+    assert_eq!(inputs.multiproof.pubkey_hashes.len(), BATCH_SIZE);
+
+    let witness = inputs.public_witness(42);
+    assert_eq!(witness.len(), BATCH_PUBLIC_WITNESS_SIZE);
+    assert_eq!(witness[..4], (BATCH_SIZE as u32 + 2).to_be_bytes());
+    assert_eq!(witness[4..8], [0; 4]);
+    assert_eq!(witness[12..44], tree.root());
+    assert_eq!(witness[44..76], pubkey_to_index(&[100; 32]));
+    assert_eq!(
+        witness[BATCH_PUBLIC_WITNESS_SIZE - 8..],
+        42u64.to_be_bytes()
+    );
+
+    let single = smt::inputs::public_witness(&tree.root(), &pubkey_to_index(&[100; 32]), 42);
+    let witness = batch_public_witness(&tree.root(), &[pubkey_to_index(&[100; 32])], 42);
+    assert_eq!(witness, single);
+}
+
+#[test]
+#[should_panic(expected = "1 to 4 pubkeys")]
+fn batch_inputs_are_bounded() {
+    let _ = tree(254).generate_exclusion_multiproof(&[[100; 32]; BATCH_SIZE + 1]);
+}
+
+#[test]
+fn prover_toml_matches_sample() {
+    let sample = include_str!("../../../smt_batch_exclusion/Prover.toml");
+    let sample: Table = sample.parse().unwrap();
+    let pubkeys: Vec<[u8; 32]> = sample["pubkeys"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|pubkey| {
+            let bytes = pubkey.as_array().unwrap().iter();
+            let bytes: Vec<u8> = bytes.map(|byte| byte.as_integer().unwrap() as u8).collect();
+            bytes.try_into().unwrap()
+        })
+        .collect();
+
+    let mut tree = SparseMerkleTree::new();
+    let blacklisted: Pubkey = SAMPLE_BLACKLISTED.parse().unwrap();
+    tree.insert(&blacklisted.to_bytes()).unwrap();
+    let inputs = tree
+        .generate_exclusion_multiproof(&pubkeys)
+        .unwrap()
+        .unwrap();
+    let generated: Table = inputs.to_prover_toml(1).parse().unwrap();
+    for key in ["smt_root", "pubkey_hashes", "pubkeys", "siblings"] {
+        assert_eq!(generated[key], sample[key], "{key}");
+    }
+    assert!(matches!(&sample["siblings"], Value::Array(paths) if paths.len() == BATCH_SIZE));
+}
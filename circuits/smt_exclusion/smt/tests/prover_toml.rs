@@ -0,0 +1,80 @@
+//! Checks `Prover.toml` is written with the encodings nargo reads: hex field
+//! elements, decimal slots, integer bytes and nested arrays in order.
+
+use serde::Serialize;
+use smt::{
+    prover_toml::{self, Decimal, Field},
+    SparseMerkleTree, TREE_DEPTH,
+};
+use toml::{Table, Value};
+
+#[derive(Serialize)]
+struct Inputs {
+    root: Field,
+    slot: Decimal,
+    bytes: [u8; 3],
+    paths: Vec<Vec<Field>>,
+}
+
+#[test]
+fn writes_circuit_encodings() {
+    let mut root = [0u8; 32];
+    root[0] = 0x2c;
+    root[31] = 0x01;
+    let inputs = Inputs {
+        root: Field(root),
+        slot: Decimal(u64::MAX),
+        bytes: [0, 7, 255],
+        paths: vec![
+            prover_toml::fields(&[[1; 32], [2; 32]]),
+            prover_toml::fields(&[[3; 32]]),
+        ],
+    };
+    let toml = prover_toml::to_string("Test", &inputs).unwrap();
+    assert!(toml.starts_with("# Test - Generated by smt\n"));
+
+    let table: Table = toml.parse().unwrap();
+    assert_eq!(
+        table["root"].as_str().unwrap(),
+        format!("0x2c{}01", "00".repeat(30))
+    );
+    assert_eq!(table["slot"].as_str().unwrap(), u64::MAX.to_string());
+    assert_eq!(
+        table["bytes"],
+        Value::Array(vec![0.into(), 7.into(), 255.into()])
+    );
+    let paths = table["paths"].as_array().unwrap();
+    assert_eq!(paths.len(), 2);
+    assert_eq!(
+        paths[0].as_array().unwrap()[1].as_str().unwrap(),
+        format!("0x{}", "02".repeat(32))
+    );
+    assert_eq!(paths[1].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn lays_out_exclusion_inputs_by_parameter() {
+    let mut tree = SparseMerkleTree::new();
+    tree.insert(&[1; 32]).unwrap();
+    let inputs = tree.generate_exclusion_proof(&[2; 32]).unwrap().unwrap();
+    let table: Table = inputs.to_prover_toml(300).parse().unwrap();
+    let keys: Vec<&str> = table.keys().map(String::as_str).collect();
+    assert_eq!(
+        keys,
+        [
+            "leaf_value",
+            "pubkey",
+            "pubkey_hash",
+            "recent_slot",
+            "siblings",
+            "smt_root"
+        ]
+    );
+    assert_eq!(table["recent_slot"].as_str().unwrap(), "300");
+    assert_eq!(table["pubkey"].as_array().unwrap()[0].as_integer(), Some(2));
+    assert_eq!(table["siblings"].as_array().unwrap().len(), TREE_DEPTH);
+    assert_eq!(
+        table["smt_root"].as_str().unwrap(),
+        smt::inputs::field_hex(&tree.root())
+    );
+}
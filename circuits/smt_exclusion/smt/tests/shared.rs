@@ -0,0 +1,91 @@
+//! Checks that clones of a tree in memory keep their version, and that
+//! readers of a shared tree prove against consistent versions while it is
+//! updated.
+
+use std::{sync::Arc, thread};
+
+use smt::{shared::SharedTree, Op, SparseMerkleTree};
+
+fn pubkey(i: u32) -> [u8; 32] {
+    let mut pubkey = [0u8; 32];
+    pubkey[..4].copy_from_slice(&i.to_le_bytes());
+    pubkey
+}
+
+fn inserts(range: std::ops::Range<u32>) -> Vec<Op> {
+    range.map(|i| Op::Insert(pubkey(i))).collect()
+}
+
+#[test]
+fn clones_keep_their_version() {
+    // Enough leaves and nodes to fill several pages
+    let mut tree = SparseMerkleTree::new();
+    tree.apply_batch(&inserts(0..600)).unwrap();
+    let before = tree.clone();
+
+    let deletes: Vec<Op> = (0..600).step_by(2).map(|i| Op::Delete(pubkey(i))).collect();
+    tree.apply_batch(&deletes).unwrap();
+    tree.apply_batch(&inserts(2000..2050)).unwrap();
+
+    let mut rebuilt = SparseMerkleTree::new();
+    rebuilt.apply_batch(&inserts(0..600)).unwrap();
+    assert_eq!(before.root(), rebuilt.root());
+    assert_eq!(before.len(), 600);
+    assert!(before.contains(&pubkey(0)).unwrap());
+    assert!(!before.contains(&pubkey(2000)).unwrap());
+    assert_eq!(
+        before.exclusion_proof(&pubkey(2000)).unwrap(),
+        rebuilt.exclusion_proof(&pubkey(2000)).unwrap()
+    );
+
+    let mut expected = SparseMerkleTree::new();
+    let odd: Vec<Op> = (1..600).step_by(2).map(|i| Op::Insert(pubkey(i))).collect();
+    expected.apply_batch(&odd).unwrap();
+    expected.apply_batch(&inserts(2000..2050)).unwrap();
+    assert_eq!(tree.root(), expected.root());
+}
+
+#[test]
+fn readers_prove_against_their_snapshot() {
+    let shared = Arc::new(SharedTree::new(SparseMerkleTree::new()));
+    let readers: Vec<_> = (0..3)
+        .map(|reader| {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                for i in 0..20 {
+                    let snapshot = shared.snapshot();
+                    let customer = pubkey(1_000_000 + reader * 100 + i);
+                    let proof = snapshot.exclusion_proof(&customer).unwrap().unwrap();
+                    assert_eq!(proof.root(), snapshot.root());
+                }
+            })
+        })
+        .collect();
+    for batch in 0..5 {
+        let changed = shared.apply_batch(&inserts(batch * 20..(batch + 1) * 20));
+        assert_eq!(changed.unwrap(), 20);
+    }
+    for reader in readers {
+        reader.join().unwrap();
+    }
+
+    let mut expected = SparseMerkleTree::new();
+    expected.apply_batch(&inserts(0..100)).unwrap();
+    assert_eq!(shared.snapshot().root(), expected.root());
+}
+
+#[test]
+fn snapshots_outlive_updates() {
+    let shared = SharedTree::from(SparseMerkleTree::new());
+    let empty = shared.snapshot();
+    assert_eq!(shared.apply_batch(&inserts(0..10)).unwrap(), 10);
+    assert_eq!(shared.apply_batch(&inserts(0..10)).unwrap(), 0);
+    assert!(empty.is_empty());
+    assert_eq!(shared.snapshot().len(), 10);
+    assert!(empty.exclusion_proof(&pubkey(3)).unwrap().is_some());
+    assert!(shared
+        .snapshot()
+        .exclusion_proof(&pubkey(3))
+        .unwrap()
+        .is_none());
+}
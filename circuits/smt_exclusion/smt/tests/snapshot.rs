@@ -0,0 +1,137 @@
+//! Checks snapshots round-trip, are reproducible and reject damaged files.
+
+use ed25519_dalek::SigningKey;
+use smt::{
+    signed::{SignedSnapshot, SignedSnapshotError},
+    snapshot::SnapshotError,
+    MemoryStore, Op, SparseMerkleTree, Store,
+};
+
+fn tree(keys: impl Iterator<Item = u8>) -> SparseMerkleTree {
+    let mut tree = SparseMerkleTree::new();
+    let ops: Vec<Op> = keys.map(|i| Op::Insert([i; 32])).collect();
+    tree.apply_batch(&ops).unwrap();
+    tree
+}
+
+fn export(tree: &SparseMerkleTree, compressed: bool) -> Vec<u8> {
+    let mut snapshot = Vec::new();
+    tree.export_snapshot(&mut snapshot, compressed).unwrap();
+    snapshot
+}
+
+fn import(
+    snapshot: &[u8],
+) -> Result<SparseMerkleTree, SnapshotError<<MemoryStore as Store>::Error>> {
+    SparseMerkleTree::import_snapshot(MemoryStore::default(), snapshot)
+}
+
+#[test]
+fn round_trips_byte_for_byte() {
+    let original = tree(1..20);
+    let snapshot = export(&original, false);
+    assert_eq!(snapshot.len(), 60 + 19 * 32 + 32);
+
+    let imported = import(&snapshot).unwrap();
+    assert_eq!(imported.root(), original.root());
+    assert_eq!(imported.len(), 19);
+    assert_eq!(
+        imported.exclusion_proof(&[30; 32]).unwrap(),
+        original.exclusion_proof(&[30; 32]).unwrap()
+    );
+    assert_eq!(export(&imported, false), snapshot);
+
+    // The same leaves, however they were added, export the same bytes
+    let mut shuffled = tree((1..20).rev());
+    shuffled.insert(&[40; 32]).unwrap();
+    shuffled.delete(&[40; 32]).unwrap();
+    assert_eq!(export(&shuffled, false), snapshot);
+
+    let empty = export(&SparseMerkleTree::new(), false);
+    assert_eq!(
+        import(&empty).unwrap().root(),
+        SparseMerkleTree::new().root()
+    );
+}
+
+#[test]
+fn rejects_damaged_snapshots() {
+    let original = tree(1..5);
+    let snapshot = export(&original, false);
+
+    let mut flipped = snapshot.clone();
+    flipped[70] ^= 1;
+    assert!(matches!(import(&flipped), Err(SnapshotError::Checksum)));
+    assert!(matches!(
+        import(&snapshot[..snapshot.len() - 1]),
+        Err(SnapshotError::Checksum)
+    ));
+    assert!(matches!(
+        import(&snapshot[..50]),
+        Err(SnapshotError::Format(_))
+    ));
+    assert!(matches!(
+        import(b"not a snapshot at all, but long enough to have a header and checksum"),
+        Err(SnapshotError::Format(_))
+    ));
+
+    // A consistent file whose leaves don't hash to its root
+    let mut wrong_root = snapshot[..snapshot.len() - 32].to_vec();
+    wrong_root[12..44].copy_from_slice(&tree(1..4).root());
+    let checksum = <sha2::Sha256 as sha2::Digest>::digest(&wrong_root);
+    wrong_root.extend_from_slice(&checksum);
+    assert!(matches!(
+        import(&wrong_root),
+        Err(SnapshotError::RootMismatch { .. })
+    ));
+
+    // Only into an empty store
+    let full = tree(1..2);
+    let store = full.store().clone();
+    assert!(matches!(
+        SparseMerkleTree::import_snapshot(store, snapshot.as_slice()),
+        Err(SnapshotError::NotEmpty)
+    ));
+}
+
+#[test]
+fn signed_snapshot_verifies() {
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let snapshot = export(&tree(1..5), false);
+    let signed = SignedSnapshot::sign(snapshot.clone(), 1_700_000_000, &key).unwrap();
+    assert_eq!(signed.statement.leaf_count, 4);
+    let bytes = signed.to_bytes();
+    let read = SignedSnapshot::from_bytes(&bytes).unwrap();
+    assert_eq!(read, signed);
+    read.verify().unwrap();
+
+    // Another list under the same signature
+    let mut swapped = read.clone();
+    swapped.snapshot = export(&tree(1..6), false);
+    assert!(matches!(
+        swapped.verify(),
+        Err(SignedSnapshotError::Format(_))
+    ));
+    let mut forged = read.clone();
+    forged.signature[0] ^= 1;
+    assert!(matches!(
+        forged.verify(),
+        Err(SignedSnapshotError::Signature)
+    ));
+    let mut damaged = bytes;
+    *damaged.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        SignedSnapshot::from_bytes(&damaged),
+        Err(SignedSnapshotError::Snapshot(SnapshotError::Checksum))
+    ));
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn compressed_round_trip() {
+    let original = tree(1..50);
+    let snapshot = export(&original, true);
+    assert_eq!(snapshot[9], smt::snapshot::ZSTD);
+    assert_eq!(import(&snapshot).unwrap().root(), original.root());
+    assert_eq!(export(&original, true), snapshot);
+}
@@ -0,0 +1,60 @@
+//! Checks a tree on disk matches one in memory and reopens at its checkpoint.
+#![cfg(feature = "sled")]
+
+use smt::{sled_store::SledStore, Checkpoint, Op, SparseMerkleTree};
+
+/// Open the store at `path`. sled's background threads let go of a closed
+/// database's lock shortly after it is dropped.
+fn open(path: &std::path::Path) -> SparseMerkleTree<SledStore> {
+    for _ in 0..50 {
+        if let Ok(store) = SledStore::open(path) {
+            return SparseMerkleTree::open(store).unwrap();
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    panic!("{} stays locked", path.display());
+}
+
+fn keys(range: std::ops::Range<u8>) -> Vec<[u8; 32]> {
+    range.map(|i| [i; 32]).collect()
+}
+
+#[test]
+fn reopens_at_checkpoint() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut memory = SparseMerkleTree::new();
+    let mut disk = open(dir.path());
+    assert_eq!(disk.checkpoint(), Checkpoint::default());
+
+    let ops: Vec<Op> = keys(1..30).into_iter().map(Op::Insert).collect();
+    assert_eq!(memory.apply_batch(&ops).unwrap(), 29);
+    assert_eq!(disk.apply_batch(&ops).unwrap(), 29);
+    let mut ops: Vec<Op> = keys(1..10).into_iter().map(Op::Delete).collect();
+    ops.extend(keys(30..35).into_iter().map(Op::Insert));
+    memory.apply_batch(&ops).unwrap();
+    disk.apply_batch(&ops).unwrap();
+    // A batch that changes nothing commits nothing
+    assert_eq!(disk.apply_batch(&[Op::Delete([1; 32])]).unwrap(), 0);
+
+    let checkpoint = disk.checkpoint();
+    assert_eq!(checkpoint.root, memory.root());
+    assert_eq!(checkpoint.leaf_count, 25);
+    assert_eq!(checkpoint.sequence, 2);
+    drop(disk);
+
+    let mut disk = open(dir.path());
+    assert_eq!(disk.checkpoint(), checkpoint);
+    assert!(disk.contains(&[12; 32]).unwrap());
+    assert!(!disk.contains(&[3; 32]).unwrap());
+    for key in keys(1..10).iter().chain(&keys(40..45)) {
+        let proof = disk.exclusion_proof(key).unwrap().unwrap();
+        assert_eq!(proof, memory.exclusion_proof(key).unwrap().unwrap());
+        assert_eq!(proof.root(), checkpoint.root);
+    }
+
+    // Updates after reopening use the stored nodes
+    let ops: Vec<Op> = keys(10..35).into_iter().map(Op::Delete).collect();
+    disk.apply_batch(&ops).unwrap();
+    assert!(disk.is_empty());
+    assert_eq!(disk.root(), SparseMerkleTree::new().root());
+}
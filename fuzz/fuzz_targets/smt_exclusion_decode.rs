@@ -0,0 +1,53 @@
+//! Exercises the instruction envelope and payload decoders without accounts,
+//! so malformed lengths are explored much faster than through the handlers.
+
+#![no_main]
+
+use exclusion_program_example::instruction::{self, InstructionHeader, WitnessHeader};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok((header, payload)) = InstructionHeader::unpack(data) else {
+        return;
+    };
+    assert!(header.version >= 1 && header.version <= instruction::CURRENT_VERSION);
+
+    let decoded = match header.discriminator {
+        instruction::WRITE_PROOF_CHUNK => {
+            instruction::known_fields_prefixed(payload, instruction::WRITE_PROOF_CHUNK_FIXED_LEN)
+        }
+        instruction::TRANSFER_SOL_WITH_SEED => instruction::known_fields_prefixed(
+            payload,
+            instruction::TRANSFER_SOL_WITH_SEED_FIXED_LEN,
+        ),
+        instruction::INSERT_LEAF | instruction::REMOVE_LEAF => {
+            instruction::leaf_update_fields(payload)
+        }
+        instruction::TRANSFER_SOL => {
+            instruction::known_fields(payload, instruction::TRANSFER_SOL_LEN)
+        }
+        instruction::TRANSFER_SOL_COMPRESSED => {
+            instruction::known_fields(payload, instruction::TRANSFER_SOL_COMPRESSED_LEN)
+        }
+        instruction::TRANSFER_SOL_BATCH => instruction::batch_transfer_fields(payload),
+        instruction::CHALLENGE => instruction::challenge_fields(payload),
+        _ => instruction::known_fields(payload, instruction::SET_SMT_ROOT_LEN),
+    };
+
+    if let Ok(fields) = decoded {
+        // Decoded fields are always a prefix of the payload
+        assert!(payload.starts_with(fields));
+        if header.discriminator == instruction::TRANSFER_SOL_BATCH {
+            // One amount per recipient, within the batch limit
+            let amounts = fields.len() - instruction::TRANSFER_SOL_BATCH_FIXED_LEN;
+            assert_eq!(amounts, 8 * fields[0] as usize);
+            assert!((1..=instruction::MAX_BATCH_RECIPIENTS).contains(&(fields[0] as usize)));
+        }
+        if header.discriminator == instruction::TRANSFER_SOL {
+            let witness = &fields[8 + instruction::PROOF_SIZE..];
+            if let Some(witness_header) = WitnessHeader::unpack(witness) {
+                let _ = witness_header.witness_size();
+            }
+        }
+    }
+});
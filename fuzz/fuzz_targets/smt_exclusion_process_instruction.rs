@@ -0,0 +1,18 @@
+//! Runs arbitrary instruction data through the full smt_exclusion handler with
+//! the accounts a real client would pass for its discriminator.
+
+#![no_main]
+
+use exclusion_program_example::process_instruction;
+use libfuzzer_sys::fuzz_target;
+use noir_examples_fuzz::{fixtures::SmtExclusion, serialize_input, with_accounts};
+
+fuzz_target!(|data: &[u8]| {
+    let fixtures = SmtExclusion::new();
+    let discriminator = data.first().copied().unwrap_or_default();
+    let accounts = fixtures.accounts(discriminator);
+    let mut input = serialize_input(&fixtures.program_id, &accounts, data);
+
+    // Errors are expected; only panics and memory errors are findings
+    let _ = with_accounts(&mut input, process_instruction);
+});
@@ -0,0 +1,18 @@
+//! Writes the seed corpus for every fuzz target from the circuits' fixtures.
+//!
+//! Usage: cargo run --bin seed_corpus
+
+use std::{fs, path::Path};
+
+use noir_examples_fuzz::fixtures::SmtExclusion;
+
+fn main() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus/smt_exclusion");
+    fs::create_dir_all(&corpus).expect("failed to create corpus directory");
+
+    for (name, data) in SmtExclusion::new().seeds() {
+        let path = corpus.join(format!("seed-{name}"));
+        fs::write(&path, data).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+        println!("{}", path.display());
+    }
+}
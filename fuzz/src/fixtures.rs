@@ -0,0 +1,1084 @@
+//! Program fixtures derived from the circuits' `Prover.toml` inputs.
+
+use std::{fs, path::Path};
+
+use exclusion_common::{instruction::METADATA_SIZE, seeds, State};
+use exclusion_program_example::{
+    bubblegum,
+    events::NOOP_PROGRAM_ID,
+    hash_scheme,
+    instruction::{self, InstructionHeader},
+    light, smt, token, tree_kind, verifier_flags, wormhole, BOND_ACCOUNT_DISCRIMINATOR,
+    CONFIG_DISCRIMINATOR, CONFIG_SIZE, ESCROW_ACCOUNT_DISCRIMINATOR, ESCROW_ACCOUNT_SIZE,
+    EXCLUSION_QUEUE_DISCRIMINATOR, EXCLUSION_QUEUE_SIZE, MAX_APPROVED_CALLERS, MAX_FEE_BPS,
+    MAX_PROOF_AGE_SLOTS, PROOF_BUFFER_DISCRIMINATOR, RELAY_ACCOUNT_DISCRIMINATOR,
+    RELAY_ACCOUNT_SIZE, STATE_DISCRIMINATOR, STATE_SIZE, TRANSITION_VERIFIER_PROGRAM_ID,
+    TREASURY_ACCOUNT_DISCRIMINATOR, UNVERSIONED_STATE_MAX_SIZE, VERIFIER_REGISTRY_DISCRIMINATOR,
+    VERIFIER_REGISTRY_SIZE, ZK_VERIFIER_PROGRAM_ID,
+};
+use solana_instruction::BorrowedInstruction;
+use solana_program::{
+    ed25519_program, pubkey,
+    pubkey::Pubkey,
+    sysvar::{self, instructions::construct_instructions_data},
+};
+
+use crate::{Account, BPF_LOADER_UPGRADEABLE};
+
+const EXCLUSION_PROVER: &str = include_str!("../../circuits/smt_exclusion/Prover.toml");
+const INSERTION_PROVER: &str = include_str!("../../circuits/smt_insertion/Prover.toml");
+
+/// Pubkey inserted by `circuits/smt_insertion/Prover.toml` (blacklisted in the exclusion root)
+pub const BLACKLISTED: Pubkey = pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+
+/// Lamports moved by the seed transfers (and authorized by the seed relay message)
+const SEED_AMOUNT: u64 = 1_000_000;
+
+/// Circuit hash the seed registry records for the exclusion verifier
+const SEED_CIRCUIT_HASH: [u8; 32] = [0xc1; 32];
+
+/// Seed of the sender-derived account the seed-derived transfer spends from
+const SEED_SOURCE_SEED: &str = "custody";
+
+/// cNFT proof nodes the gated cNFT transfer passes (a tree deeper than its canopy)
+const CNFT_PROOF_NODES: u8 = 3;
+
+/// Escrow used by the escrow seeds
+const SEED_ESCROW_ID: u64 = 0;
+
+/// Placeholder admin; the state PDA is derived from it
+pub const ADMIN: Pubkey = Pubkey::new_from_array([0xad; 32]);
+
+/// Raw value of `key = ...` in a Prover.toml
+fn value<'a>(toml: &'a str, key: &str) -> &'a str {
+    let start = toml
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .find(|(k, _)| k.trim() == key)
+        .map(|(_, v)| v)
+        .unwrap_or_else(|| panic!("Prover.toml: missing `{key}`"));
+    // Arrays may span several lines
+    let offset = start.as_ptr() as usize - toml.as_ptr() as usize;
+    let rest = &toml[offset..];
+    match rest.trim_start().starts_with('[') {
+        true => &rest[..=rest.find(']').expect("unterminated array")],
+        false => rest.lines().next().unwrap_or_default(),
+    }
+}
+
+/// Parse a quoted `0x`-prefixed field element as 32 big-endian bytes
+fn field(raw: &str) -> [u8; 32] {
+    let hex = raw.trim().trim_matches('"').trim_start_matches("0x");
+    let mut out = [0u8; 32];
+    let padded = format!("{hex:0>64}");
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&padded[2 * i..2 * i + 2], 16).expect("invalid hex");
+    }
+    out
+}
+
+/// Parse a quoted decimal integer (e.g. `recent_slot`) as a big-endian field element
+fn integer(raw: &str) -> [u8; 32] {
+    let value: u64 = raw
+        .trim()
+        .trim_matches('"')
+        .parse()
+        .expect("invalid integer");
+    let mut out = [0u8; 32];
+    out[24..].copy_from_slice(&value.to_be_bytes());
+    out
+}
+
+/// Split a TOML array into its quoted elements
+fn elements(raw: &str) -> impl Iterator<Item = &str> {
+    raw.trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(',')
+        .map(|item| item.trim().trim_matches('"'))
+        .filter(|item| !item.is_empty())
+}
+
+/// Gnark public witness: 12-byte header followed by big-endian field elements
+fn public_witness(inputs: &[[u8; 32]]) -> Vec<u8> {
+    let count = inputs.len() as u32;
+    let mut witness = Vec::with_capacity(instruction::WITNESS_HEADER_SIZE + 32 * inputs.len());
+    witness.extend_from_slice(&count.to_be_bytes());
+    witness.extend_from_slice(&0u32.to_be_bytes());
+    witness.extend_from_slice(&count.to_be_bytes());
+    for input in inputs {
+        witness.extend_from_slice(input);
+    }
+    witness
+}
+
+/// Real proof from `just prove-smt` when present; placeholder bytes otherwise
+fn exclusion_proof() -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../circuits/smt_exclusion/target/smt_exclusion.proof");
+    fs::read(path)
+        .ok()
+        .filter(|proof| proof.len() == instruction::PROOF_SIZE)
+        .unwrap_or_else(|| {
+            // Zeroed points behind the single commitment count real proofs carry
+            let mut proof = vec![0u8; instruction::PROOF_SIZE];
+            proof[256..260].copy_from_slice(&1u32.to_be_bytes());
+            proof
+        })
+}
+
+/// `proof` with each point cut to its x coordinate, as in a compressed proof.
+/// The y-sign flag bits are left clear, which off-chain decompression ignores.
+fn compressed_proof(proof: &[u8]) -> Vec<u8> {
+    [
+        &proof[0..32],
+        &proof[64..128],
+        &proof[192..224],
+        &proof[256..260],
+        &proof[260..292],
+        &proof[324..356],
+    ]
+    .concat()
+}
+
+fn instruction_data(discriminator: u8, payload: &[&[u8]]) -> Vec<u8> {
+    let mut data = InstructionHeader::pack(discriminator).to_vec();
+    for field in payload {
+        data.extend_from_slice(field);
+    }
+    data
+}
+
+/// Fixtures for `circuits/smt_exclusion/on_chain_program`
+pub struct SmtExclusion {
+    pub program_id: Pubkey,
+    pub state: Pubkey,
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub sender_buffer: Pubkey,
+    pub recipient_buffer: Pubkey,
+    pub relay_account: Pubkey,
+    pub escrow_account: Pubkey,
+    pub vault: Pubkey,
+    pub treasury: Pubkey,
+    pub bond: Pubkey,
+    pub exclusion_queue: Pubkey,
+    pub config: Pubkey,
+    pub program_data: Pubkey,
+    pub verifier_registry: Pubkey,
+    pub smt_root: [u8; 32],
+    pub witness: Vec<u8>,
+    pub proof: Vec<u8>,
+}
+
+impl Default for SmtExclusion {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SmtExclusion {
+    pub fn new() -> Self {
+        let program_id = Pubkey::new_from_array([0x5e; 32]);
+        let pubkey_bytes: Vec<u8> = elements(value(EXCLUSION_PROVER, "pubkey"))
+            .map(|byte| byte.parse().expect("invalid pubkey byte"))
+            .collect();
+        let sender = Pubkey::try_from(pubkey_bytes.as_slice()).expect("pubkey must be 32 bytes");
+        let recipient = BLACKLISTED;
+        let smt_root = field(value(EXCLUSION_PROVER, "smt_root"));
+        let pubkey_hash = field(value(EXCLUSION_PROVER, "pubkey_hash"));
+        let recent_slot = integer(value(EXCLUSION_PROVER, "recent_slot"));
+        let buffer = |owner: &Pubkey| {
+            Pubkey::find_program_address(&[seeds::PROOF_BUFFER, owner.as_ref()], &program_id).0
+        };
+
+        let state = Pubkey::find_program_address(&[seeds::STATE, ADMIN.as_ref()], &program_id).0;
+
+        Self {
+            program_id,
+            state,
+            sender,
+            recipient,
+            sender_buffer: buffer(&sender),
+            recipient_buffer: buffer(&recipient),
+            relay_account: Pubkey::find_program_address(
+                &[seeds::RELAY, sender.as_ref()],
+                &program_id,
+            )
+            .0,
+            escrow_account: Pubkey::find_program_address(
+                &[
+                    seeds::ESCROW,
+                    sender.as_ref(),
+                    recipient.as_ref(),
+                    &SEED_ESCROW_ID.to_le_bytes(),
+                ],
+                &program_id,
+            )
+            .0,
+            vault: Pubkey::find_program_address(&[seeds::VAULT, ADMIN.as_ref()], &program_id).0,
+            treasury: Pubkey::find_program_address(&[seeds::TREASURY, state.as_ref()], &program_id)
+                .0,
+            bond: Pubkey::find_program_address(&[seeds::BOND, state.as_ref()], &program_id).0,
+            exclusion_queue: Pubkey::find_program_address(
+                &[seeds::EXCLUSION_QUEUE, state.as_ref()],
+                &program_id,
+            )
+            .0,
+            config: Pubkey::find_program_address(&[seeds::CONFIG], &program_id).0,
+            program_data: Pubkey::find_program_address(
+                &[program_id.as_ref()],
+                &BPF_LOADER_UPGRADEABLE,
+            )
+            .0,
+            verifier_registry: Pubkey::find_program_address(
+                &[seeds::VERIFIER_REGISTRY],
+                &program_id,
+            )
+            .0,
+            smt_root,
+            witness: public_witness(&[smt_root, pubkey_hash, recent_slot]),
+            proof: exclusion_proof(),
+        }
+    }
+
+    fn state_account(&self) -> Account {
+        // A bond with its full withdrawal already unlocked
+        let state = State {
+            admin: ADMIN.to_bytes(),
+            smt_root: self.smt_root,
+            root_valid_until: 0,
+            transfer_flags: 0,
+            daily_limit: 0,
+            fee_bps: 0,
+            root_seq: 0,
+            approved_callers: [[0; 32]; MAX_APPROVED_CALLERS],
+            approved_caller_count: 0,
+            bump: Pubkey::find_program_address(&[seeds::STATE, ADMIN.as_ref()], &self.program_id).1,
+            treasury_bump: Pubkey::find_program_address(
+                &[seeds::TREASURY, self.state.as_ref()],
+                &self.program_id,
+            )
+            .1,
+            hash_scheme: 0,
+            verifier_flags: 0,
+            metadata: [0; METADATA_SIZE],
+            challenged: false,
+            bond_bump: Pubkey::find_program_address(
+                &[seeds::BOND, self.state.as_ref()],
+                &self.program_id,
+            )
+            .1,
+            bond_amount: SEED_AMOUNT,
+            bond_withdrawal_amount: SEED_AMOUNT,
+            bond_withdrawal_at: 0,
+            config: [0; 32],
+            tree_kind: 0,
+            tree_depth: smt::TREE_DEPTH as u16,
+        };
+        let mut data = vec![0u8; STATE_SIZE];
+        state.pack(&mut data);
+        Account::data(self.state, self.program_id, data)
+    }
+
+    /// The largest state account from before layout versioning, for MIGRATE_STATE
+    fn unversioned_state_account(&self) -> Account {
+        let mut data = vec![0u8; UNVERSIONED_STATE_MAX_SIZE];
+        data[0..8].copy_from_slice(&STATE_DISCRIMINATOR);
+        data[8..40].copy_from_slice(ADMIN.as_ref());
+        data[40..72].copy_from_slice(&self.smt_root);
+        Account::data(self.state, self.program_id, data)
+    }
+
+    /// Exclusion queue holding the key INSERT_LEAF inserts
+    fn exclusion_queue(&self) -> Account {
+        let mut data = vec![0u8; EXCLUSION_QUEUE_SIZE];
+        data[0..8].copy_from_slice(&EXCLUSION_QUEUE_DISCRIMINATOR);
+        data[8] = 1;
+        data[9..41].copy_from_slice(BLACKLISTED.as_ref());
+        Account::data(self.exclusion_queue, self.program_id, data)
+    }
+
+    /// SET_CONFIG payload holding the compiled-in defaults, unpaused
+    fn config_fields(&self) -> Vec<u8> {
+        [
+            &[0][..],
+            ZK_VERIFIER_PROGRAM_ID.as_ref(),
+            TRANSITION_VERIFIER_PROGRAM_ID.as_ref(),
+            &MAX_PROOF_AGE_SLOTS.to_le_bytes(),
+            &MAX_FEE_BPS.to_le_bytes(),
+        ]
+        .concat()
+    }
+
+    fn config_account(&self) -> Account {
+        let mut data = CONFIG_DISCRIMINATOR.to_vec();
+        data.push(Pubkey::find_program_address(&[seeds::CONFIG], &self.program_id).1);
+        data.extend_from_slice(&self.config_fields());
+        assert_eq!(data.len(), CONFIG_SIZE);
+        Account::data(self.config, self.program_id, data)
+    }
+
+    /// Verifier registry governed by ADMIN, listing the exclusion verifier
+    fn verifier_registry_account(&self) -> Account {
+        let mut data = vec![0u8; VERIFIER_REGISTRY_SIZE];
+        data[0..8].copy_from_slice(&VERIFIER_REGISTRY_DISCRIMINATOR);
+        data[8] = Pubkey::find_program_address(&[seeds::VERIFIER_REGISTRY], &self.program_id).1;
+        data[9..41].copy_from_slice(ADMIN.as_ref());
+        data[41] = 1;
+        data[42..74].copy_from_slice(ZK_VERIFIER_PROGRAM_ID.as_ref());
+        data[74..106].copy_from_slice(&SEED_CIRCUIT_HASH);
+        data[106] = tree_kind::SPARSE;
+        data[107..109].copy_from_slice(&(smt::TREE_DEPTH as u16).to_le_bytes());
+        Account::data(self.verifier_registry, self.program_id, data)
+    }
+
+    /// Upgradeable loader ProgramData naming ADMIN as the upgrade authority
+    fn program_data_account(&self) -> Account {
+        let mut data = 3u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(ADMIN.as_ref());
+        Account::data(self.program_data, BPF_LOADER_UPGRADEABLE, data)
+    }
+
+    fn buffer_account(&self, key: Pubkey, owner: &Pubkey) -> Account {
+        let mut data = PROOF_BUFFER_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(owner.as_ref());
+        data.extend_from_slice(&self.proof);
+        data.extend_from_slice(&self.witness);
+        Account::data(key, self.program_id, data)
+    }
+
+    fn relay(&self) -> Account {
+        let mut data = vec![0u8; RELAY_ACCOUNT_SIZE];
+        data[0..8].copy_from_slice(&RELAY_ACCOUNT_DISCRIMINATOR);
+        data[8..40].copy_from_slice(self.sender.as_ref());
+        Account::data(self.relay_account, self.program_id, data)
+    }
+
+    /// Escrow of `SEED_AMOUNT` from sender to recipient, already released
+    fn escrow(&self) -> Account {
+        let mut data = vec![0u8; ESCROW_ACCOUNT_SIZE];
+        data[0..8].copy_from_slice(&ESCROW_ACCOUNT_DISCRIMINATOR);
+        data[8..40].copy_from_slice(self.sender.as_ref());
+        data[40..72].copy_from_slice(self.recipient.as_ref());
+        data[72..104].copy_from_slice(self.state.as_ref());
+        data[104..112].copy_from_slice(&SEED_ESCROW_ID.to_le_bytes());
+        data[112..120].copy_from_slice(&SEED_AMOUNT.to_le_bytes());
+        Account::data(self.escrow_account, self.program_id, data)
+    }
+
+    /// Instructions sysvar for `[ed25519 check of the seed relay message, this program]`,
+    /// executing the program instruction. The signature bytes are placeholders:
+    /// the ed25519 program has already run by the time the program inspects it.
+    fn instructions_sysvar(&self) -> Account {
+        let message = instruction::relay_message(&self.program_id, &self.recipient, SEED_AMOUNT, 0);
+        // Header (2) + offsets (14), then public key, signature, message
+        let (public_key_offset, signature_offset, message_offset) = (16u16, 48u16, 112u16);
+        let mut ed25519_data = vec![1u8, 0];
+        for offset in [
+            signature_offset,
+            u16::MAX,
+            public_key_offset,
+            u16::MAX,
+            message_offset,
+            message.len() as u16,
+            u16::MAX,
+        ] {
+            ed25519_data.extend_from_slice(&offset.to_le_bytes());
+        }
+        ed25519_data.extend_from_slice(self.sender.as_ref());
+        ed25519_data.extend_from_slice(&[0u8; 64]);
+        ed25519_data.extend_from_slice(&message);
+
+        let mut data = construct_instructions_data(&[
+            BorrowedInstruction {
+                program_id: &ed25519_program::ID,
+                accounts: Vec::new(),
+                data: &ed25519_data,
+            },
+            BorrowedInstruction {
+                program_id: &self.program_id,
+                accounts: Vec::new(),
+                data: &[],
+            },
+        ]);
+        let len = data.len();
+        data[len - 2..].copy_from_slice(&1u16.to_le_bytes());
+        Account::data(sysvar::instructions::ID, sysvar::ID, data)
+    }
+
+    /// Accounts a well-behaved client passes for `discriminator`
+    pub fn accounts(&self, discriminator: u8) -> Vec<Account> {
+        let admin = Account::wallet(ADMIN, true);
+        let state = self.state_account();
+        let system = Account::program(solana_system_interface::program::ID);
+        let sender = Account::wallet(self.sender, true);
+        let recipient = Account::wallet(self.recipient, false);
+        let verifier = Account::program(ZK_VERIFIER_PROGRAM_ID);
+        let sender_buffer = self.buffer_account(self.sender_buffer, &self.sender);
+        let noop = Account::program(NOOP_PROGRAM_ID);
+        let bond = Account::data(
+            self.bond,
+            self.program_id,
+            BOND_ACCOUNT_DISCRIMINATOR.to_vec(),
+        );
+
+        match discriminator {
+            instruction::INITIALIZE => vec![
+                admin,
+                state,
+                system,
+                Account::data(self.bond, solana_system_interface::program::ID, Vec::new()),
+            ],
+            instruction::MIGRATE_STATE => vec![admin, self.unversioned_state_account(), system],
+            instruction::SET_SMT_ROOT | instruction::REMOVE_LEAF => vec![admin, state, noop],
+            instruction::INSERT_LEAF => vec![admin, state, self.exclusion_queue(), noop],
+            instruction::SELF_EXCLUDE => vec![
+                sender,
+                state,
+                Account::data(
+                    self.exclusion_queue,
+                    solana_system_interface::program::ID,
+                    Vec::new(),
+                ),
+                system,
+            ],
+            instruction::SET_TRANSFER_FLAGS
+            | instruction::SET_RATE_LIMIT
+            | instruction::SET_APPROVED_CALLERS
+            | instruction::SET_HASH_SCHEME
+            | instruction::SET_TREE_KIND
+            | instruction::SET_TREE_DEPTH
+            | instruction::SET_VERIFIER_FLAGS
+            | instruction::SET_METADATA
+            | instruction::SET_ADMIN
+            | instruction::REQUEST_BOND_WITHDRAWAL => vec![admin, state],
+            instruction::DEPOSIT_BOND => vec![admin, state, bond, system],
+            instruction::SET_CONFIG => vec![
+                admin,
+                Account::data(
+                    self.config,
+                    solana_system_interface::program::ID,
+                    Vec::new(),
+                ),
+                self.program_data_account(),
+                system,
+            ],
+            instruction::LINK_CONFIG => vec![admin, state, self.config_account()],
+            instruction::SET_REGISTRY_AUTHORITY => vec![
+                admin,
+                Account::data(
+                    self.verifier_registry,
+                    solana_system_interface::program::ID,
+                    Vec::new(),
+                ),
+                self.program_data_account(),
+                system,
+            ],
+            instruction::REGISTER_VERIFIER | instruction::UNREGISTER_VERIFIER => {
+                vec![admin, self.verifier_registry_account()]
+            }
+            instruction::WITHDRAW_BOND => vec![admin, state, bond, Account::wallet(ADMIN, false)],
+            instruction::TRANSFER_SOL | instruction::TRANSFER_SOL_COMPRESSED => {
+                vec![sender, recipient, state, verifier, system, noop]
+            }
+            instruction::ATTEST => vec![
+                sender,
+                state,
+                verifier,
+                Account::data(
+                    Pubkey::find_program_address(
+                        &[
+                            seeds::ATTESTATION,
+                            self.state.as_ref(),
+                            self.sender.as_ref(),
+                        ],
+                        &self.program_id,
+                    )
+                    .0,
+                    solana_system_interface::program::ID,
+                    Vec::new(),
+                ),
+                system,
+                noop,
+            ],
+            instruction::ATTEST_COMPRESSED => vec![
+                sender,
+                state,
+                verifier,
+                Account::program(light::LIGHT_SYSTEM_PROGRAM_ID),
+                Account::wallet(
+                    Pubkey::find_program_address(&[light::CPI_AUTHORITY_SEED], &self.program_id).0,
+                    false,
+                ),
+                Account::data(
+                    Pubkey::find_program_address(
+                        &[light::LIGHT_SYSTEM_PROGRAM_ID.as_ref()],
+                        &light::ACCOUNT_COMPRESSION_PROGRAM_ID,
+                    )
+                    .0,
+                    light::ACCOUNT_COMPRESSION_PROGRAM_ID,
+                    Vec::new(),
+                ),
+                noop,
+                Account::wallet(
+                    Pubkey::find_program_address(
+                        &[light::CPI_AUTHORITY_SEED],
+                        &light::LIGHT_SYSTEM_PROGRAM_ID,
+                    )
+                    .0,
+                    false,
+                ),
+                Account::program(light::ACCOUNT_COMPRESSION_PROGRAM_ID),
+                Account::program(self.program_id),
+                system,
+                Account::data(
+                    Pubkey::new_from_array([0xa1; 32]),
+                    light::ACCOUNT_COMPRESSION_PROGRAM_ID,
+                    Vec::new(),
+                ),
+                Account::data(
+                    Pubkey::new_from_array([0xa2; 32]),
+                    light::ACCOUNT_COMPRESSION_PROGRAM_ID,
+                    Vec::new(),
+                ),
+                Account::data(
+                    Pubkey::new_from_array([0xa3; 32]),
+                    light::ACCOUNT_COMPRESSION_PROGRAM_ID,
+                    Vec::new(),
+                ),
+            ],
+            instruction::TRANSFER_CNFT => {
+                let tree = Pubkey::new_from_array([0xb1; 32]);
+                let mut accounts = vec![
+                    sender.clone(),
+                    sender,
+                    recipient,
+                    state,
+                    verifier,
+                    Account::program(bubblegum::BUBBLEGUM_PROGRAM_ID),
+                    Account::data(
+                        Pubkey::find_program_address(
+                            &[tree.as_ref()],
+                            &bubblegum::BUBBLEGUM_PROGRAM_ID,
+                        )
+                        .0,
+                        bubblegum::BUBBLEGUM_PROGRAM_ID,
+                        Vec::new(),
+                    ),
+                    Account::data(tree, bubblegum::ACCOUNT_COMPRESSION_PROGRAM_ID, Vec::new()),
+                    noop,
+                    Account::program(bubblegum::ACCOUNT_COMPRESSION_PROGRAM_ID),
+                    system,
+                ];
+                accounts.extend(
+                    (0..CNFT_PROOF_NODES)
+                        .map(|i| Account::wallet(Pubkey::new_from_array([0xb2 + i; 32]), false)),
+                );
+                accounts
+            }
+            instruction::PUBLISH_ROOT => {
+                let bridge = |seeds: &[&[u8]]| {
+                    Pubkey::find_program_address(seeds, &wormhole::CORE_BRIDGE_PROGRAM_ID).0
+                };
+                let emitter =
+                    Pubkey::find_program_address(&[wormhole::EMITTER_SEED], &self.program_id).0;
+                // Bridge config: the message fee follows the guardian set fields
+                let mut config = vec![0u8; wormhole::BRIDGE_FEE_OFFSET + 8];
+                config[wormhole::BRIDGE_FEE_OFFSET..].copy_from_slice(&100u64.to_le_bytes());
+                vec![
+                    sender,
+                    state,
+                    Account::program(wormhole::CORE_BRIDGE_PROGRAM_ID),
+                    Account::data(
+                        bridge(&[b"Bridge"]),
+                        wormhole::CORE_BRIDGE_PROGRAM_ID,
+                        config,
+                    ),
+                    Account::wallet(
+                        Pubkey::find_program_address(
+                            &[
+                                seeds::WORMHOLE_MESSAGE,
+                                self.state.as_ref(),
+                                &0u64.to_le_bytes(),
+                            ],
+                            &self.program_id,
+                        )
+                        .0,
+                        false,
+                    ),
+                    Account::wallet(emitter, false),
+                    Account::wallet(bridge(&[b"Sequence", emitter.as_ref()]), false),
+                    Account::wallet(bridge(&[b"fee_collector"]), false),
+                    Account::data(sysvar::clock::ID, sysvar::ID, Vec::new()),
+                    Account::data(sysvar::rent::ID, sysvar::ID, Vec::new()),
+                    system,
+                ]
+            }
+            instruction::TRANSFER_SOL_WITH_SEED => vec![
+                sender,
+                Account::wallet(
+                    Pubkey::create_with_seed(
+                        &self.sender,
+                        SEED_SOURCE_SEED,
+                        &solana_system_interface::program::ID,
+                    )
+                    .unwrap(),
+                    false,
+                ),
+                recipient,
+                state,
+                verifier,
+                system,
+                noop,
+            ],
+            instruction::TRANSFER_WSOL => vec![
+                sender,
+                recipient,
+                Account::data(
+                    token::associated_token_address(&self.recipient, &token::NATIVE_MINT),
+                    token::TOKEN_PROGRAM_ID,
+                    vec![0; token::TOKEN_ACCOUNT_SIZE],
+                ),
+                state,
+                verifier,
+                system,
+                Account::data(
+                    Pubkey::find_program_address(
+                        &[seeds::WSOL, self.sender.as_ref()],
+                        &self.program_id,
+                    )
+                    .0,
+                    solana_system_interface::program::ID,
+                    Vec::new(),
+                ),
+                Account::data(token::NATIVE_MINT, token::TOKEN_PROGRAM_ID, vec![0; 82]),
+                Account::program(token::TOKEN_PROGRAM_ID),
+                noop,
+            ],
+            instruction::TRANSFER_SOL_BATCH => vec![
+                sender,
+                state,
+                verifier,
+                system,
+                recipient,
+                Account::wallet(ADMIN, false),
+                noop,
+            ],
+            instruction::CHALLENGE => vec![sender, state, verifier, bond, noop],
+            instruction::SUBMIT_ROOT_TRANSITION => vec![
+                state,
+                Account::program(TRANSITION_VERIFIER_PROGRAM_ID),
+                admin,
+                noop,
+            ],
+            instruction::CREATE_PROOF_BUFFER => vec![sender, sender_buffer, system],
+            instruction::WRITE_PROOF_CHUNK => vec![sender, sender_buffer],
+            instruction::TRANSFER_FROM_BUFFER => {
+                vec![
+                    sender,
+                    recipient,
+                    state,
+                    verifier,
+                    system,
+                    sender_buffer,
+                    noop,
+                ]
+            }
+            instruction::TRANSFER_SOL_DUAL => vec![
+                sender,
+                recipient,
+                state,
+                verifier,
+                system,
+                sender_buffer,
+                self.buffer_account(self.recipient_buffer, &self.recipient),
+                noop,
+            ],
+            instruction::CREATE_RELAY_ACCOUNT | instruction::CLOSE_RELAY_ACCOUNT => {
+                vec![sender, self.relay(), system]
+            }
+            instruction::TRANSFER_SOL_RELAYED => vec![
+                Account::wallet(self.sender, false),
+                recipient,
+                state,
+                verifier,
+                self.relay(),
+                self.instructions_sysvar(),
+                noop,
+            ],
+            instruction::TRANSFER_TO_ESCROW => vec![
+                sender,
+                recipient,
+                Account::data(
+                    self.escrow_account,
+                    solana_system_interface::program::ID,
+                    Vec::new(),
+                ),
+                state,
+                verifier,
+                system,
+            ],
+            instruction::CLAIM_FROM_ESCROW => vec![
+                Account::wallet(self.recipient, true),
+                Account::wallet(self.sender, false),
+                self.escrow(),
+                state,
+                verifier,
+                system,
+            ],
+            // Pays the innocent prover, whose proof the fixture carries
+            instruction::TRANSFER_FROM_VAULT => vec![
+                admin,
+                sender,
+                state,
+                verifier,
+                system,
+                Account::wallet(self.vault, false),
+            ],
+            instruction::SET_FEE => vec![
+                admin,
+                state,
+                Account::data(
+                    self.treasury,
+                    solana_system_interface::program::ID,
+                    Vec::new(),
+                ),
+                system,
+            ],
+            instruction::WITHDRAW_FEES => vec![
+                admin,
+                state,
+                Account::data(
+                    self.treasury,
+                    self.program_id,
+                    TREASURY_ACCOUNT_DISCRIMINATOR.to_vec(),
+                ),
+                Account::wallet(ADMIN, false),
+            ],
+            _ => vec![admin, state],
+        }
+    }
+
+    /// One well-formed instruction per handler, named for the corpus file
+    pub fn seeds(&self) -> Vec<(&'static str, Vec<u8>)> {
+        let amount = SEED_AMOUNT.to_le_bytes();
+        let old_root = field(value(INSERTION_PROVER, "old_root"));
+        let new_root = field(value(INSERTION_PROVER, "new_root"));
+        let key = field(value(INSERTION_PROVER, "key"));
+        let transition_witness = public_witness(&[old_root, new_root, key]);
+
+        // Sparse sibling encoding: bitmap of non-default levels + those siblings
+        let mut bitmap = [0u8; 32];
+        let mut siblings = Vec::new();
+        for (level, raw) in elements(value(INSERTION_PROVER, "siblings")).enumerate() {
+            let sibling = field(raw);
+            if sibling != smt::DEFAULT_HASHES[level] {
+                bitmap[level / 8] |= 1 << (level % 8);
+                siblings.extend_from_slice(&sibling);
+            }
+        }
+
+        let chunk_len = (self.witness.len() as u16).to_le_bytes();
+        let chunk_offset = (instruction::PROOF_SIZE as u16).to_le_bytes();
+        let leaf_update = [BLACKLISTED.as_ref(), &bitmap, &siblings].concat();
+
+        let mut approved_callers = instruction_data(instruction::SET_APPROVED_CALLERS, &[&[1]]);
+        approved_callers.extend_from_slice(self.program_id.as_ref());
+        approved_callers.resize(
+            instruction::HEADER_LEN + instruction::SET_APPROVED_CALLERS_LEN,
+            0,
+        );
+
+        let mut set_root_v2 = instruction_data(instruction::SET_SMT_ROOT, &[&self.smt_root]);
+        set_root_v2[1] = 2;
+        set_root_v2.extend_from_slice(&i64::MAX.to_le_bytes());
+
+        // A claim without the recipient's proof is version 1
+        let mut claim_v1 = instruction_data(instruction::CLAIM_FROM_ESCROW, &[]);
+        claim_v1[1] = 1;
+
+        // Off-chain list pointer: a CIDv1 string, zero-padded
+        let mut metadata = [0u8; instruction::METADATA_SIZE];
+        let cid = b"bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+        metadata[..cid.len()].copy_from_slice(cid);
+
+        vec![
+            (
+                "initialize",
+                instruction_data(instruction::INITIALIZE, &[&amount]),
+            ),
+            (
+                "set_smt_root",
+                instruction_data(instruction::SET_SMT_ROOT, &[&self.smt_root]),
+            ),
+            ("set_smt_root_v2", set_root_v2),
+            (
+                "set_smt_root_v3",
+                instruction_data(
+                    instruction::SET_SMT_ROOT,
+                    &[&self.smt_root, &i64::MAX.to_le_bytes(), &metadata],
+                ),
+            ),
+            (
+                "set_metadata",
+                instruction_data(instruction::SET_METADATA, &[&metadata]),
+            ),
+            (
+                "set_admin",
+                instruction_data(instruction::SET_ADMIN, &[&[0xae; 32]]),
+            ),
+            (
+                "transfer_sol",
+                instruction_data(
+                    instruction::TRANSFER_SOL,
+                    &[&amount, &self.proof, &self.witness],
+                ),
+            ),
+            (
+                "transfer_wsol",
+                instruction_data(
+                    instruction::TRANSFER_WSOL,
+                    &[&amount, &self.proof, &self.witness],
+                ),
+            ),
+            (
+                "attest",
+                instruction_data(instruction::ATTEST, &[&self.proof, &self.witness]),
+            ),
+            (
+                "transfer_cnft",
+                instruction_data(
+                    instruction::TRANSFER_CNFT,
+                    &[
+                        &self.proof,
+                        &self.witness,
+                        &[0xb0; bubblegum::Leaf::LEN],
+                        &[CNFT_PROOF_NODES],
+                    ],
+                ),
+            ),
+            (
+                "publish_root",
+                instruction_data(instruction::PUBLISH_ROOT, &[]),
+            ),
+            (
+                "attest_compressed",
+                instruction_data(
+                    instruction::ATTEST_COMPRESSED,
+                    &[
+                        &self.proof,
+                        &self.witness,
+                        &[0; light::VALIDITY_PROOF_SIZE],
+                        &0u16.to_le_bytes(),
+                    ],
+                ),
+            ),
+            (
+                "transfer_sol_with_seed",
+                instruction_data(
+                    instruction::TRANSFER_SOL_WITH_SEED,
+                    &[
+                        &amount,
+                        &self.proof,
+                        &self.witness,
+                        &(SEED_SOURCE_SEED.len() as u16).to_le_bytes(),
+                        SEED_SOURCE_SEED.as_bytes(),
+                    ],
+                ),
+            ),
+            (
+                "transfer_sol_compressed",
+                instruction_data(
+                    instruction::TRANSFER_SOL_COMPRESSED,
+                    &[&amount, &compressed_proof(&self.proof), &self.witness],
+                ),
+            ),
+            (
+                "transfer_sol_batch",
+                instruction_data(
+                    instruction::TRANSFER_SOL_BATCH,
+                    &[&[2], &self.proof, &self.witness, &amount, &amount],
+                ),
+            ),
+            (
+                "create_proof_buffer",
+                instruction_data(instruction::CREATE_PROOF_BUFFER, &[]),
+            ),
+            (
+                "write_proof_chunk",
+                instruction_data(
+                    instruction::WRITE_PROOF_CHUNK,
+                    &[&chunk_offset, &chunk_len, &self.witness],
+                ),
+            ),
+            (
+                "transfer_from_buffer",
+                instruction_data(instruction::TRANSFER_FROM_BUFFER, &[&amount]),
+            ),
+            (
+                "submit_root_transition",
+                instruction_data(
+                    instruction::SUBMIT_ROOT_TRANSITION,
+                    &[BLACKLISTED.as_ref(), &self.proof, &transition_witness],
+                ),
+            ),
+            (
+                "challenge",
+                instruction_data(
+                    instruction::CHALLENGE,
+                    &[&self.proof, &self.witness, &bitmap, &siblings],
+                ),
+            ),
+            (
+                "self_exclude",
+                instruction_data(instruction::SELF_EXCLUDE, &[]),
+            ),
+            (
+                "deposit_bond",
+                instruction_data(instruction::DEPOSIT_BOND, &[&amount]),
+            ),
+            (
+                "request_bond_withdrawal",
+                instruction_data(instruction::REQUEST_BOND_WITHDRAWAL, &[&amount]),
+            ),
+            (
+                "withdraw_bond",
+                instruction_data(instruction::WITHDRAW_BOND, &[]),
+            ),
+            (
+                "set_config",
+                instruction_data(instruction::SET_CONFIG, &[&self.config_fields()]),
+            ),
+            (
+                "link_config",
+                instruction_data(instruction::LINK_CONFIG, &[]),
+            ),
+            (
+                "set_registry_authority",
+                instruction_data(instruction::SET_REGISTRY_AUTHORITY, &[ADMIN.as_ref()]),
+            ),
+            (
+                "register_verifier",
+                instruction_data(
+                    instruction::REGISTER_VERIFIER,
+                    // A redeployed exclusion verifier
+                    &[
+                        &[0x7e; 32],
+                        &SEED_CIRCUIT_HASH,
+                        &[tree_kind::SPARSE],
+                        &(smt::TREE_DEPTH as u16).to_le_bytes(),
+                    ],
+                ),
+            ),
+            (
+                "unregister_verifier",
+                instruction_data(
+                    instruction::UNREGISTER_VERIFIER,
+                    &[ZK_VERIFIER_PROGRAM_ID.as_ref()],
+                ),
+            ),
+            (
+                "set_transfer_flags",
+                instruction_data(instruction::SET_TRANSFER_FLAGS, &[&[1]]),
+            ),
+            (
+                "insert_leaf",
+                instruction_data(instruction::INSERT_LEAF, &[&leaf_update]),
+            ),
+            (
+                "remove_leaf",
+                instruction_data(instruction::REMOVE_LEAF, &[&leaf_update]),
+            ),
+            (
+                "transfer_sol_dual",
+                instruction_data(instruction::TRANSFER_SOL_DUAL, &[&amount]),
+            ),
+            (
+                "create_relay_account",
+                instruction_data(instruction::CREATE_RELAY_ACCOUNT, &[]),
+            ),
+            (
+                "transfer_sol_relayed",
+                instruction_data(
+                    instruction::TRANSFER_SOL_RELAYED,
+                    &[&amount, &0u64.to_le_bytes(), &self.proof, &self.witness],
+                ),
+            ),
+            (
+                "close_relay_account",
+                instruction_data(instruction::CLOSE_RELAY_ACCOUNT, &[]),
+            ),
+            (
+                "transfer_to_escrow",
+                instruction_data(
+                    instruction::TRANSFER_TO_ESCROW,
+                    &[
+                        &amount,
+                        &SEED_ESCROW_ID.to_le_bytes(),
+                        &0i64.to_le_bytes(),
+                        &[0],
+                        &self.proof,
+                        &self.witness,
+                    ],
+                ),
+            ),
+            ("claim_from_escrow", claim_v1),
+            (
+                "claim_from_escrow_with_proof",
+                instruction_data(
+                    instruction::CLAIM_FROM_ESCROW,
+                    &[&self.proof, &self.witness],
+                ),
+            ),
+            (
+                "set_rate_limit",
+                instruction_data(instruction::SET_RATE_LIMIT, &[&amount]),
+            ),
+            (
+                "set_fee",
+                instruction_data(instruction::SET_FEE, &[&25u16.to_le_bytes()]),
+            ),
+            (
+                "withdraw_fees",
+                instruction_data(instruction::WITHDRAW_FEES, &[]),
+            ),
+            ("set_approved_callers", approved_callers),
+            (
+                "migrate_state",
+                instruction_data(instruction::MIGRATE_STATE, &[]),
+            ),
+            (
+                "set_hash_scheme",
+                instruction_data(instruction::SET_HASH_SCHEME, &[&[hash_scheme::POSEIDON]]),
+            ),
+            (
+                "set_tree_kind",
+                instruction_data(instruction::SET_TREE_KIND, &[&[tree_kind::SPARSE]]),
+            ),
+            (
+                "set_tree_depth",
+                instruction_data(
+                    instruction::SET_TREE_DEPTH,
+                    &[&(smt::TREE_DEPTH as u16).to_le_bytes()],
+                ),
+            ),
+            (
+                "set_verifier_flags",
+                instruction_data(
+                    instruction::SET_VERIFIER_FLAGS,
+                    &[&[verifier_flags::WITNESS_LITTLE_ENDIAN]],
+                ),
+            ),
+            (
+                "transfer_from_vault",
+                instruction_data(
+                    instruction::TRANSFER_FROM_VAULT,
+                    &[&amount, &self.proof, &self.witness],
+                ),
+            ),
+        ]
+    }
+}
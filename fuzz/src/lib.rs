@@ -0,0 +1,117 @@
+//! Shared harness for the libFuzzer targets in `fuzz_targets/`.
+//!
+//! Every target consumes raw instruction data (`[discriminator, version,
+//! payload ...]`), so all of them share one corpus per program under
+//! `corpus/<program>/`. Account fixtures are built from the circuits'
+//! `Prover.toml` files, so proofs reach the checks that compare them against
+//! real roots and pubkey hashes instead of failing on the first lookup.
+
+use solana_program::{account_info::AccountInfo, pubkey, pubkey::Pubkey};
+
+pub mod fixtures;
+
+/// Bytes the runtime reserves after each account's data for reallocation
+const MAX_PERMITTED_DATA_INCREASE: usize = 10 * 1024;
+
+/// Owner of deployed program accounts
+pub const BPF_LOADER_UPGRADEABLE: Pubkey = pubkey!("BPFLoaderUpgradeab1e11111111111111111111111");
+
+/// Marker for a non-duplicate account in the serialized input
+const NON_DUP_MARKER: u8 = u8::MAX;
+
+/// One account as it would be passed to the program by the runtime
+#[derive(Clone, Debug)]
+pub struct Account {
+    pub key: Pubkey,
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub is_signer: bool,
+    pub is_writable: bool,
+    pub executable: bool,
+}
+
+impl Account {
+    pub fn wallet(key: Pubkey, is_signer: bool) -> Self {
+        Self {
+            key,
+            owner: solana_system_interface::program::ID,
+            lamports: 10_000_000_000,
+            data: Vec::new(),
+            is_signer,
+            is_writable: true,
+            executable: false,
+        }
+    }
+
+    pub fn program(key: Pubkey) -> Self {
+        Self {
+            key,
+            owner: BPF_LOADER_UPGRADEABLE,
+            lamports: 1,
+            data: Vec::new(),
+            is_signer: false,
+            is_writable: false,
+            executable: true,
+        }
+    }
+
+    pub fn data(key: Pubkey, owner: Pubkey, data: Vec<u8>) -> Self {
+        Self {
+            key,
+            owner,
+            lamports: 1_000_000_000,
+            data,
+            is_signer: false,
+            is_writable: true,
+            executable: false,
+        }
+    }
+}
+
+/// Serialize accounts and instruction data in the loader's aligned input
+/// format, so the program sees the same memory layout (including realloc
+/// headroom) it gets on-chain and `AccountInfo::resize` stays in bounds.
+pub fn serialize_input(program_id: &Pubkey, accounts: &[Account], data: &[u8]) -> Vec<u64> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(accounts.len() as u64).to_le_bytes());
+    for account in accounts {
+        bytes.push(NON_DUP_MARKER);
+        bytes.push(account.is_signer as u8);
+        bytes.push(account.is_writable as u8);
+        bytes.push(account.executable as u8);
+        bytes.extend_from_slice(&[0u8; 4]); // original data length, filled in on deserialize
+        bytes.extend_from_slice(account.key.as_ref());
+        bytes.extend_from_slice(account.owner.as_ref());
+        bytes.extend_from_slice(&account.lamports.to_le_bytes());
+        bytes.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&account.data);
+        bytes.resize(bytes.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+        bytes.resize(bytes.len().next_multiple_of(8), 0);
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes()); // rent epoch
+    }
+    bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(data);
+    bytes.extend_from_slice(program_id.as_ref());
+
+    // Back the buffer with u64s so every field the loader reads is aligned
+    let mut input = vec![0u64; bytes.len().div_ceil(8)];
+    for (word, chunk) in input.iter_mut().zip(bytes.chunks(8)) {
+        let mut buf = [0u8; 8];
+        buf[..chunk.len()].copy_from_slice(chunk);
+        *word = u64::from_le_bytes(buf);
+    }
+    input
+}
+
+/// Deserialize an input built by [`serialize_input`] and run `process`
+pub fn with_accounts<R>(
+    input: &mut [u64],
+    process: impl FnOnce(&Pubkey, &[AccountInfo], &[u8]) -> R,
+) -> R {
+    // SAFETY: `input` was laid out by `serialize_input` in the format the
+    // entrypoint deserializer expects, and outlives the returned references.
+    let (program_id, accounts, data) =
+        unsafe { solana_program::entrypoint::deserialize(input.as_mut_ptr() as *mut u8) };
+    process(program_id, &accounts, data)
+}